@@ -0,0 +1,174 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Benchmarks of the data availability hot paths: the commitment of a blob, the FK20 proofs of all
+//! its segments, the verification of a segment, the recovery of a row from half of its segments,
+//! the plotting of a piece by a farmer, and the extension and the plotting of the rows of a block,
+//! serially and in parallel.
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use melo_core_primitives::config::{
+	FIELD_ELEMENTS_PER_BLOB, FIELD_ELEMENTS_PER_SEGMENT, SEGMENTS_PER_BLOB,
+};
+use melo_das_db::{mock_db::MockDb, sqlite::SqliteDasDb};
+use melo_das_primitives::{blob::Blob, crypto::SCALAR_SAFE_BYTES, Segment, KZG};
+use melo_erasure_coding::{
+	bytes_to_segments, extend_row::extend_rows_parallel, recovery::recovery_order_row_from_segments,
+};
+use melo_proof_of_space::{FarmerId, Piece, PiecePosition, PlotHashing, PlotUpdate};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::ThreadPoolBuilder;
+
+const BYTES_PER_BLOB: usize = SCALAR_SAFE_BYTES * FIELD_ELEMENTS_PER_BLOB;
+
+/// Numbers of rows plotted at once by `plot_update`, from one row to a full block of blobs.
+const PLOTTED_ROWS: [usize; 3] = [1, 4, 16];
+
+/// Numbers of rows extended at once by `extend_rows`.
+const EXTENDED_ROWS: [usize; 3] = [1, 4, 16];
+
+fn blob_bytes() -> Vec<u8> {
+	seeded_blob_bytes(0)
+}
+
+fn seeded_blob_bytes(seed: u64) -> Vec<u8> {
+	let mut rng = StdRng::seed_from_u64(seed);
+	(0..BYTES_PER_BLOB).map(|_| rng.gen()).collect()
+}
+
+fn row(kzg: &KZG) -> Vec<Segment> {
+	seeded_row(kzg, 0)
+}
+
+fn seeded_row(kzg: &KZG, seed: u64) -> Vec<Segment> {
+	bytes_to_segments(
+		&seeded_blob_bytes(seed),
+		FIELD_ELEMENTS_PER_BLOB,
+		FIELD_ELEMENTS_PER_SEGMENT,
+		kzg,
+	)
+	.expect("The data fits in a blob; qed")
+}
+
+fn bench_blob(c: &mut Criterion) {
+	let kzg = KZG::default_embedded();
+	let blob = Blob::try_from_bytes_pad(&blob_bytes(), BYTES_PER_BLOB).unwrap();
+	let poly = blob.to_poly();
+
+	c.bench_function("blob_commit", |b| b.iter(|| blob.commit(&kzg).unwrap()));
+	c.bench_function("fk20_all_proofs", |b| {
+		b.iter(|| kzg.all_proofs(&poly, FIELD_ELEMENTS_PER_SEGMENT).unwrap())
+	});
+}
+
+fn bench_segment(c: &mut Criterion) {
+	let kzg = KZG::default_embedded();
+	let blob = Blob::try_from_bytes_pad(&blob_bytes(), BYTES_PER_BLOB).unwrap();
+	let commitment = blob.commit(&kzg).unwrap();
+	let segments = row(&kzg);
+
+	c.bench_function("segment_verify", |b| {
+		b.iter(|| segments[1].verify(&kzg, &commitment, SEGMENTS_PER_BLOB).unwrap())
+	});
+
+	// Every other segment is dropped, the most the recovery allows.
+	let half = segments
+		.iter()
+		.enumerate()
+		.map(|(x, segment)| (x % 2 == 0).then(|| segment.clone()))
+		.collect::<Vec<_>>();
+	c.bench_function("row_recovery", |b| {
+		b.iter(|| recovery_order_row_from_segments(&half, &kzg).unwrap())
+	});
+}
+
+fn bench_piece(c: &mut Criterion) {
+	let kzg = KZG::default_embedded();
+	let piece = Piece::new(1u32, PiecePosition::Row(0), &row(&kzg));
+	let farmer_id = FarmerId::default();
+
+	c.bench_function("piece_save", |b| {
+		b.iter_batched(
+			MockDb::new,
+			|mut db| piece.save(&mut db, PlotHashing::Blake2, &farmer_id).unwrap(),
+			BatchSize::SmallInput,
+		)
+	});
+}
+
+/// Plots the rows of a block into a SQLite plot, on one thread and on all of them.
+fn bench_extend(c: &mut Criterion) {
+	let kzg = KZG::default_embedded();
+	let rows = (0..*EXTENDED_ROWS.iter().max().unwrap())
+		.map(|index| {
+			Blob::try_from_bytes_pad(&seeded_blob_bytes(index as u64), BYTES_PER_BLOB)
+				.unwrap()
+				.to_poly()
+		})
+		.collect::<Vec<_>>();
+
+	let mut group = c.benchmark_group("extend_rows");
+	for count in EXTENDED_ROWS {
+		group.throughput(Throughput::Elements(count as u64));
+		for (name, threads) in [("serial", 1), ("parallel", 0)] {
+			group.bench_with_input(BenchmarkId::new(name, count), &rows[..count], |b, rows| {
+				b.iter(|| {
+					extend_rows_parallel(rows, &kzg, FIELD_ELEMENTS_PER_SEGMENT, threads).unwrap()
+				})
+			});
+		}
+	}
+	group.finish();
+}
+
+fn bench_plot(c: &mut Criterion) {
+	let kzg = KZG::default_embedded();
+	let farmer_id = FarmerId::default();
+	let rows = (0..*PLOTTED_ROWS.iter().max().unwrap())
+		.map(|index| seeded_row(&kzg, index as u64))
+		.collect::<Vec<_>>();
+	let single_thread = ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+
+	let mut group = c.benchmark_group("plot_update");
+	for count in PLOTTED_ROWS {
+		let mut update = PlotUpdate::new(farmer_id.clone());
+		for (index, row) in rows[..count].iter().enumerate() {
+			update.push(Piece::new(1u32, PiecePosition::Row(index as u32), row));
+		}
+		let segments = rows[..count].iter().map(Vec::len).sum::<usize>();
+		group.throughput(Throughput::Elements(segments as u64));
+
+		group.bench_with_input(BenchmarkId::new("serial", count), &update, |b, update| {
+			b.iter_batched(
+				|| SqliteDasDb::new(":memory:").unwrap(),
+				|mut db| single_thread.install(|| update.apply(&mut db, |_| ()).unwrap()),
+				BatchSize::SmallInput,
+			)
+		});
+		group.bench_with_input(BenchmarkId::new("parallel", count), &update, |b, update| {
+			b.iter_batched(
+				|| SqliteDasDb::new(":memory:").unwrap(),
+				|mut db| update.apply(&mut db, |_| ()).unwrap(),
+				BatchSize::SmallInput,
+			)
+		});
+	}
+	group.finish();
+}
+
+criterion_group! {
+	name = benches;
+	config = Criterion::default().sample_size(10);
+	targets = bench_blob, bench_segment, bench_piece, bench_extend, bench_plot
+}
+criterion_main!(benches);
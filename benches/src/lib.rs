@@ -0,0 +1,196 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reports of the benchmarks.
+//!
+//! Criterion keeps the estimates of each benchmark under `target/criterion/<name>/<baseline>`,
+//! and those of the benchmarks of a group under `target/criterion/<group>/<function>/<parameter>`.
+//! [`collect`] gathers them into a single [`Report`], which is written as JSON so that the
+//! reports of two runs, such as a release and the previous one, can be compared by [`compare`].
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path};
+
+/// The estimates of the benchmarks of a run.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Report {
+	/// The estimates of each benchmark, by name.
+	pub benchmarks: BTreeMap<String, Estimate>,
+}
+
+/// The estimated time of an iteration of a benchmark.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+	/// The mean time, in nanoseconds.
+	pub mean_ns: f64,
+	/// The median time, in nanoseconds.
+	pub median_ns: f64,
+}
+
+/// A benchmark slower than in the base report.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regression {
+	/// The name of the benchmark.
+	pub name: String,
+	/// The median time of the base report, in nanoseconds.
+	pub base_ns: f64,
+	/// The median time of the current report, in nanoseconds.
+	pub current_ns: f64,
+}
+
+impl Regression {
+	/// Returns how much slower the benchmark is, in percent.
+	pub fn percent(&self) -> f64 {
+		(self.current_ns / self.base_ns - 1.0) * 100.0
+	}
+}
+
+#[derive(Deserialize)]
+struct CriterionEstimates {
+	mean: CriterionEstimate,
+	median: CriterionEstimate,
+}
+
+#[derive(Deserialize)]
+struct CriterionEstimate {
+	point_estimate: f64,
+}
+
+/// Gathers the estimates of the baseline `baseline` of the benchmarks under `criterion_dir`,
+/// `new` being the last run.
+pub fn collect(criterion_dir: &Path, baseline: &str) -> Result<Report> {
+	let mut report = Report::default();
+	collect_dir(criterion_dir, "", baseline, &mut report)
+		.with_context(|| format!("Failed to read {:?}, run the benchmarks first", criterion_dir))?;
+	Ok(report)
+}
+
+/// Adds the estimates of the benchmarks under `dir` to `report`. The benchmarks of a group are
+/// nested in its directory and named `<group>/<function>/<parameter>`.
+fn collect_dir(dir: &Path, prefix: &str, baseline: &str, report: &mut Report) -> Result<()> {
+	for entry in fs::read_dir(dir)? {
+		let path = entry?.path();
+		if !path.is_dir() {
+			continue
+		}
+		let name = format!(
+			"{}{}",
+			prefix,
+			path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default()
+		);
+		let estimates_path = path.join(baseline).join("estimates.json");
+		if !estimates_path.is_file() {
+			collect_dir(&path, &format!("{}/", name), baseline, report)?;
+			continue
+		}
+		let estimates: CriterionEstimates = serde_json::from_slice(&fs::read(&estimates_path)?)
+			.with_context(|| format!("Invalid estimates in {:?}", estimates_path))?;
+		report.benchmarks.insert(
+			name,
+			Estimate {
+				mean_ns: estimates.mean.point_estimate,
+				median_ns: estimates.median.point_estimate,
+			},
+		);
+	}
+	Ok(())
+}
+
+/// Returns the benchmarks of `current` whose median time is more than `max_regression` percent
+/// above the one of `base`. Benchmarks missing from either report are skipped.
+pub fn compare(base: &Report, current: &Report, max_regression: f64) -> Vec<Regression> {
+	current
+		.benchmarks
+		.iter()
+		.filter_map(|(name, estimate)| {
+			let base = base.benchmarks.get(name)?;
+			let regression = Regression {
+				name: name.clone(),
+				base_ns: base.median_ns,
+				current_ns: estimate.median_ns,
+			};
+			(regression.percent() > max_regression).then_some(regression)
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn report(benchmarks: &[(&str, f64)]) -> Report {
+		Report {
+			benchmarks: benchmarks
+				.iter()
+				.map(|(name, ns)| (name.to_string(), Estimate { mean_ns: *ns, median_ns: *ns }))
+				.collect(),
+		}
+	}
+
+	#[test]
+	fn test_compare_reports_regressions_only() {
+		let base = report(&[("blob_commit", 100.0), ("row_recovery", 100.0), ("old", 1.0)]);
+		let current = report(&[("blob_commit", 105.0), ("row_recovery", 120.0), ("new", 1.0)]);
+
+		let regressions = compare(&base, &current, 10.0);
+		assert_eq!(regressions.len(), 1);
+		assert_eq!(regressions[0].name, "row_recovery");
+		assert!((regressions[0].percent() - 20.0).abs() < 1e-9);
+	}
+
+	#[test]
+	fn test_collect_names_group_benchmarks_by_path() {
+		let dir = std::env::temp_dir().join(format!("melo-benches-group-{}", std::process::id()));
+		let bench = dir.join("plot_update").join("parallel").join("16").join("new");
+		fs::create_dir_all(&bench).unwrap();
+		fs::create_dir_all(dir.join("plot_update").join("report")).unwrap();
+		fs::write(
+			bench.join("estimates.json"),
+			r#"{"mean":{"point_estimate":8.0},"median":{"point_estimate":7.5}}"#,
+		)
+		.unwrap();
+
+		let report = collect(&dir, "new").unwrap();
+		fs::remove_dir_all(&dir).unwrap();
+
+		assert_eq!(report.benchmarks.len(), 1);
+		assert_eq!(
+			report.benchmarks.get("plot_update/parallel/16"),
+			Some(&Estimate { mean_ns: 8.0, median_ns: 7.5 })
+		);
+	}
+
+	#[test]
+	fn test_collect_reads_criterion_estimates() {
+		let dir = std::env::temp_dir().join(format!("melo-benches-{}", std::process::id()));
+		let bench = dir.join("segment_verify").join("new");
+		fs::create_dir_all(&bench).unwrap();
+		fs::write(
+			bench.join("estimates.json"),
+			r#"{"mean":{"point_estimate":2.5},"median":{"point_estimate":2.0}}"#,
+		)
+		.unwrap();
+
+		let report = collect(&dir, "new").unwrap();
+		fs::remove_dir_all(&dir).unwrap();
+
+		assert_eq!(report, {
+			let mut expected = Report::default();
+			expected
+				.benchmarks
+				.insert("segment_verify".into(), Estimate { mean_ns: 2.5, median_ns: 2.0 });
+			expected
+		});
+	}
+}
@@ -0,0 +1,84 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Command line collecting and comparing the reports of the benchmarks.
+use anyhow::{bail, Result};
+use melo_benches::{collect, compare, Report};
+use std::{fs, path::PathBuf};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "melo-bench-report", about = "Reports of the DAS benchmarks.")]
+enum Command {
+	/// Writes the estimates of the last run of the benchmarks as JSON.
+	Collect {
+		/// The directory of the criterion results.
+		#[structopt(long, parse(from_os_str), default_value = "target/criterion")]
+		criterion_dir: PathBuf,
+		/// The criterion baseline to collect, `new` being the last run.
+		#[structopt(long, default_value = "new")]
+		baseline: String,
+		/// The file to write the report to, standard output if not set.
+		#[structopt(long, parse(from_os_str))]
+		output: Option<PathBuf>,
+	},
+	/// Compares two reports, failing if a benchmark regressed.
+	Compare {
+		/// The report of the reference run.
+		#[structopt(parse(from_os_str))]
+		base: PathBuf,
+		/// The report of the run to check.
+		#[structopt(parse(from_os_str))]
+		current: PathBuf,
+		/// The slowdown of the median time above which a benchmark regressed, in percent.
+		#[structopt(long, default_value = "10")]
+		max_regression: f64,
+	},
+}
+
+fn main() -> Result<()> {
+	match Command::from_args() {
+		Command::Collect { criterion_dir, baseline, output } => {
+			let json = serde_json::to_string_pretty(&collect(&criterion_dir, &baseline)?)?;
+			match output {
+				Some(path) => fs::write(path, json)?,
+				None => println!("{}", json),
+			}
+		},
+		Command::Compare { base, current, max_regression } => {
+			let base: Report = serde_json::from_slice(&fs::read(&base)?)?;
+			let current: Report = serde_json::from_slice(&fs::read(&current)?)?;
+			let regressions = compare(&base, &current, max_regression);
+			for regression in &regressions {
+				eprintln!(
+					"{}: {:.0} ns -> {:.0} ns (+{:.1}%)",
+					regression.name,
+					regression.base_ns,
+					regression.current_ns,
+					regression.percent()
+				);
+			}
+			if !regressions.is_empty() {
+				bail!(
+					"{} benchmarks regressed by more than {}%",
+					regressions.len(),
+					max_regression
+				);
+			}
+			println!("{} benchmarks within {}%", current.benchmarks.len(), max_regression);
+		},
+	}
+
+	Ok(())
+}
@@ -0,0 +1,158 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compression of sidecar payloads and segment values.
+//!
+//! Commitments and proofs are always computed over the uncompressed data. Compression only
+//! applies to the bytes that are stored and transported: the payload submitted along with a
+//! [`crate::SidecarMetadata`] and the values of its segments in the DHT.
+use crate::TypeInfo;
+#[cfg(feature = "std")]
+use crate::{String, ToString, Vec};
+use codec::{Decode, Encode};
+#[cfg(feature = "std")]
+use melo_das_primitives::{config::BYTES_PER_BLOB, SegmentData};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use sp_core::RuntimeDebug;
+#[cfg(feature = "std")]
+use std::io::Read;
+
+/// The magic number starting every zstd frame.
+#[cfg(feature = "std")]
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// The zstd compression level.
+#[cfg(feature = "std")]
+const ZSTD_LEVEL: i32 = 3;
+
+/// The maximum length of a decompressed segment value.
+#[cfg(feature = "std")]
+const MAX_SEGMENT_VALUE_LEN: usize = 2 * BYTES_PER_BLOB;
+
+/// The compression of the data of a sidecar when it is stored and transported.
+#[derive(Encode, Decode, Clone, Copy, Default, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum Compression {
+	/// The data is not compressed.
+	#[default]
+	None,
+	/// The data is compressed with zstd.
+	Zstd,
+}
+
+#[cfg(feature = "std")]
+impl Compression {
+	/// Compresses `bytes`.
+	pub fn compress(&self, bytes: &[u8]) -> Result<Vec<u8>, String> {
+		match self {
+			Compression::None => Ok(bytes.to_vec()),
+			Compression::Zstd => zstd::bulk::compress(bytes, ZSTD_LEVEL).map_err(|e| e.to_string()),
+		}
+	}
+
+	/// Decompresses `bytes`, refusing to produce more than `max_len` bytes.
+	///
+	/// The zstd frame is decoded as a stream rather than into a buffer of `max_len` bytes, so that
+	/// a few untrusted bytes can not make it allocate a large `max_len` upfront.
+	pub fn decompress(&self, bytes: &[u8], max_len: usize) -> Result<Vec<u8>, String> {
+		let decompressed = match self {
+			Compression::None => bytes.to_vec(),
+			Compression::Zstd => {
+				let decoder = zstd::stream::read::Decoder::new(bytes).map_err(|e| e.to_string())?;
+				let mut decompressed = Vec::new();
+				decoder
+					.take((max_len as u64).saturating_add(1))
+					.read_to_end(&mut decompressed)
+					.map_err(|e| e.to_string())?;
+				decompressed
+			},
+		};
+		if decompressed.len() > max_len {
+			return Err("Data is longer than expected".to_string())
+		}
+		Ok(decompressed)
+	}
+}
+
+/// Encodes the DHT value of a segment, compressed with `compression`.
+///
+/// The value is left uncompressed if compressing it does not make it shorter.
+#[cfg(feature = "std")]
+pub fn encode_segment_value(data: &SegmentData, compression: Compression) -> Vec<u8> {
+	let encoded = data.encode();
+	match compression.compress(&encoded) {
+		Ok(compressed) if compressed.len() < encoded.len() => compressed,
+		_ => encoded,
+	}
+}
+
+/// Decodes the DHT value of a segment, compressed or not.
+///
+/// Compressed values are recognized by the zstd magic number, which can not start the encoding of
+/// a segment, as the number of field elements of a segment is a power of two.
+#[cfg(feature = "std")]
+pub fn decode_segment_value(value: &[u8]) -> Option<SegmentData> {
+	if value.starts_with(&ZSTD_MAGIC) {
+		let decompressed = Compression::Zstd.decompress(value, MAX_SEGMENT_VALUE_LEN).ok()?;
+		SegmentData::decode(&mut &decompressed[..]).ok()
+	} else {
+		SegmentData::decode(&mut &value[..]).ok()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::config::FIELD_ELEMENTS_PER_SEGMENT;
+	use melo_das_primitives::{config::FIELD_ELEMENTS_PER_BLOB, KZG};
+	use melo_erasure_coding::bytes_to_segments;
+
+	#[test]
+	fn test_compression() {
+		let bytes = vec![5u8; 10_000];
+		for compression in [Compression::None, Compression::Zstd] {
+			let compressed = compression.compress(&bytes).unwrap();
+			assert_eq!(compression.decompress(&compressed, bytes.len()).unwrap(), bytes);
+			assert!(compression.decompress(&compressed, bytes.len() - 1).is_err());
+			assert_eq!(compression.decompress(&compressed, usize::MAX).unwrap(), bytes);
+		}
+		assert!(Compression::Zstd.compress(&bytes).unwrap().len() < bytes.len());
+	}
+
+	#[test]
+	fn test_segment_value() {
+		let kzg = KZG::default_embedded();
+		let segments = bytes_to_segments(
+			&[0u8; 100],
+			FIELD_ELEMENTS_PER_BLOB,
+			FIELD_ELEMENTS_PER_SEGMENT,
+			&kzg,
+		)
+		.unwrap();
+		let data = &segments[1].content;
+
+		let plain = encode_segment_value(data, Compression::None);
+		assert_eq!(plain, data.encode());
+		assert_eq!(decode_segment_value(&plain).as_ref(), Some(data));
+
+		// The segments of zeros compress well.
+		let compressed = encode_segment_value(data, Compression::Zstd);
+		assert!(compressed.starts_with(&ZSTD_MAGIC));
+		assert_eq!(decode_segment_value(&compressed).as_ref(), Some(data));
+
+		assert_eq!(decode_segment_value(&compressed[..compressed.len() - 1]), None);
+	}
+}
@@ -12,7 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-pub use melo_das_primitives::config::FIELD_ELEMENTS_PER_BLOB;
+pub use melo_das_primitives::config::{
+	Cols, MatrixDimensions, Rows, SampleCount, FIELD_ELEMENTS_PER_BLOB,
+};
 
 /// The current version of the network.
 pub const DAS_NETWORK_VERSION: &str = "0.0.1";
@@ -22,14 +24,55 @@ pub const BLOCK_SAMPLE_LIMIT: u32 = 3;
 pub const MAX_UNAVAILABLE_BLOCK_INTERVAL: u32 = 3;
 /// The number of elements per segment, must be a power of 2.
 pub const FIELD_ELEMENTS_PER_SEGMENT: usize = 2usize.pow(4);
+/// The dimensions of the data matrix, from which the layout constants below are derived.
+pub const MATRIX: MatrixDimensions =
+	MatrixDimensions::new(FIELD_ELEMENTS_PER_BLOB, FIELD_ELEMENTS_PER_SEGMENT);
 /// The number of samples/segments per blob.
-pub const SEGMENTS_PER_BLOB: usize = FIELD_ELEMENTS_PER_BLOB / FIELD_ELEMENTS_PER_SEGMENT;
+pub const SEGMENTS_PER_BLOB: usize = MATRIX.cols().0 as usize;
 /// The number of segments per row after extension.
-pub const EXTENDED_SEGMENTS_PER_BLOB: usize = SEGMENTS_PER_BLOB * 2;
+pub const EXTENDED_SEGMENTS_PER_BLOB: usize = MATRIX.extended_cols().0 as usize;
 /// Blocks with data available greater than this value.
 pub const BLOCK_AVAILABILITY_THRESHOLD: u32 = 5;
 /// The number of samples per block.
 pub const SAMPLES_PER_BLOCK: usize = 8;
+/// The default maximum number of rows of the data matrix of a block.
+pub const MAX_BLOCK_ROWS: u32 = 100;
+/// The default maximum total length in bytes of the data submitted at a block.
+pub const MAX_BLOCK_DATA_BYTES: u32 = 8 * 1024 * 1024;
+/// The default number of blocks the data of a block is kept available for.
+///
+/// It is also the compaction limit of the commitments: past the retention period of the DAS
+/// parameters, `pallet-melo-store` replaces the commitments and proofs of the blobs of a block
+/// with their root, a few blocks at a time from its `NextCompaction`. The segments of a compacted
+/// block can then no longer be verified against the state, so farmers neither plot them nor claim
+/// rewards over them.
+pub const DATA_RETENTION_PERIOD: u32 = 14_400;
 
 /// The number of leading zeros required for the pre-cell.
-pub const PRE_CELL_LEADING_ZEROS: u8 = 0;
\ No newline at end of file
+pub const PRE_CELL_LEADING_ZEROS: u8 = 0;
+
+const _: () = assert!(MATRIX.is_valid(), "segments must split a blob evenly");
+const _: () = assert!(
+	SEGMENTS_PER_BLOB == 128 && EXTENDED_SEGMENTS_PER_BLOB == 256,
+	"the layout of the data matrix changed"
+);
+const _: () = assert!(
+	BLOCK_AVAILABILITY_THRESHOLD as usize <= SAMPLES_PER_BLOCK,
+	"the block threshold must be reachable with the samples of a block"
+);
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_matrix_dimensions() {
+		assert_eq!(MATRIX.cols(), Cols(FIELD_ELEMENTS_PER_BLOB as u32 / 16));
+		assert_eq!(MATRIX.extended_cols(), Cols(2 * MATRIX.cols().0));
+		assert_eq!(MATRIX.cells(Rows(3)), SampleCount(3 * EXTENDED_SEGMENTS_PER_BLOB as u32));
+
+		assert!(!MatrixDimensions::new(FIELD_ELEMENTS_PER_BLOB, 12).is_valid());
+		assert!(!MatrixDimensions::new(FIELD_ELEMENTS_PER_BLOB, 0).is_valid());
+		assert_eq!(MatrixDimensions::new(16, 32).cols(), Cols(0));
+	}
+}
@@ -0,0 +1,83 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+	config::{DATA_RETENTION_PERIOD, MAX_BLOCK_DATA_BYTES, MAX_BLOCK_ROWS},
+	TypeInfo,
+};
+use codec::{Decode, Encode, MaxEncodedLen};
+use melo_das_primitives::config::DATA_BYTES_PER_BLOB;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use sp_core::RuntimeDebug;
+
+/// The tunable parameters of data availability, set by governance.
+///
+/// The layout of the data matrix is not tunable: the segment length is fixed by the proofs the
+/// runtime verifies, see [`crate::config::MATRIX`]. Neither is the number of samples, which each
+/// node sets for itself.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct DasParams {
+	/// The maximum length in bytes of the data of a submission.
+	pub max_blob_bytes: u32,
+	/// The maximum number of rows, one per blob, of the data matrix of a block.
+	pub max_block_rows: u32,
+	/// The maximum total length in bytes of the data submitted at a block.
+	pub max_block_bytes: u32,
+	/// The number of blocks the data of a block is kept available for.
+	pub retention_period: u32,
+}
+
+impl Default for DasParams {
+	fn default() -> Self {
+		Self {
+			max_blob_bytes: MAX_BLOCK_ROWS * DATA_BYTES_PER_BLOB as u32,
+			max_block_rows: MAX_BLOCK_ROWS,
+			max_block_bytes: MAX_BLOCK_DATA_BYTES,
+			retention_period: DATA_RETENTION_PERIOD,
+		}
+	}
+}
+
+impl DasParams {
+	/// Checks that the parameters are consistent.
+	///
+	/// A submission has to fit in a block, both in bytes and in rows.
+	pub fn is_valid(&self) -> bool {
+		self.max_block_rows > 0 &&
+			self.retention_period > 0 &&
+			self.max_blob_bytes > 0 &&
+			self.max_blob_bytes <= self.max_block_bytes &&
+			(self.max_blob_bytes as u64) <=
+				self.max_block_rows as u64 * DATA_BYTES_PER_BLOB as u64
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_das_params_validity() {
+		let params = DasParams::default();
+		assert!(params.is_valid());
+
+		assert!(!DasParams { max_block_rows: 0, ..params }.is_valid());
+		assert!(!DasParams { retention_period: 0, ..params }.is_valid());
+		assert!(!DasParams { max_blob_bytes: params.max_block_bytes + 1, ..params }.is_valid());
+		assert!(!DasParams { max_block_rows: 1, ..params }.is_valid());
+	}
+}
@@ -0,0 +1,182 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Encryption of private application data.
+//!
+//! Applications which want their data available without exposing it, such as validiums, encrypt
+//! it with a key they manage before it is split into blobs. Commitments, proofs, sampling and the
+//! DHT then only ever see the ciphertext. The [`Encryption`] of a [`crate::SidecarMetadata`] tells
+//! the readers of the data how to decrypt it, the node does not use it.
+//!
+//! Data encrypted with [`Encryption::ChaCha20Poly1305`] is the random 12-byte nonce of the
+//! encryption followed by the ciphertext and its 16-byte tag. The app ID and the nonce of the
+//! submission are authenticated with it, so that the data of a submission can not be passed off
+//! as the data of another one.
+use crate::{String, ToString, TypeInfo, Vec};
+use chacha20poly1305::{
+	aead::{Aead, KeyInit, Payload},
+	ChaCha20Poly1305, Key, Nonce,
+};
+use codec::{Decode, Encode};
+#[cfg(feature = "std")]
+use rand::RngCore;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use sp_core::RuntimeDebug;
+
+/// The length of the nonce of the ChaCha20-Poly1305 encryption.
+pub const CHACHA20_POLY1305_NONCE_BYTES: usize = 12;
+
+/// The length of the authentication tag of the ChaCha20-Poly1305 encryption.
+pub const CHACHA20_POLY1305_TAG_BYTES: usize = 16;
+
+/// The encryption of the data of a sidecar.
+#[derive(Encode, Decode, Clone, Copy, Default, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum Encryption {
+	/// The data is not encrypted.
+	#[default]
+	None,
+	/// The data is encrypted with ChaCha20-Poly1305.
+	ChaCha20Poly1305,
+}
+
+/// A 256-bit symmetric key of an application.
+#[derive(Clone, PartialEq, Eq)]
+pub struct EncryptionKey(pub [u8; 32]);
+
+impl Encryption {
+	/// Returns the number of bytes the encryption adds to the data.
+	pub const fn overhead(&self) -> usize {
+		match self {
+			Encryption::None => 0,
+			Encryption::ChaCha20Poly1305 =>
+				CHACHA20_POLY1305_NONCE_BYTES + CHACHA20_POLY1305_TAG_BYTES,
+		}
+	}
+
+	/// Encrypts `data` submitted by `app_id` with `nonce`, using `key` and a random nonce of
+	/// encryption.
+	#[cfg(feature = "std")]
+	pub fn encrypt(
+		&self,
+		key: &EncryptionKey,
+		app_id: u32,
+		nonce: u32,
+		data: &[u8],
+	) -> Result<Vec<u8>, String> {
+		let mut nonce_bytes = [0u8; CHACHA20_POLY1305_NONCE_BYTES];
+		rand::thread_rng().fill_bytes(&mut nonce_bytes);
+		self.encrypt_with_nonce(key, app_id, nonce, data, nonce_bytes)
+	}
+
+	/// Encrypts `data` as [`Encryption::encrypt`] does, with the nonce of encryption
+	/// `nonce_bytes`, which must never be used twice with the same key.
+	pub fn encrypt_with_nonce(
+		&self,
+		key: &EncryptionKey,
+		app_id: u32,
+		nonce: u32,
+		data: &[u8],
+		nonce_bytes: [u8; CHACHA20_POLY1305_NONCE_BYTES],
+	) -> Result<Vec<u8>, String> {
+		match self {
+			Encryption::None => Ok(data.to_vec()),
+			Encryption::ChaCha20Poly1305 => {
+				let aad = associated_data(app_id, nonce);
+				let ciphertext = ChaCha20Poly1305::new(Key::from_slice(&key.0))
+					.encrypt(Nonce::from_slice(&nonce_bytes), Payload { msg: data, aad: &aad })
+					.map_err(|e| e.to_string())?;
+
+				let mut encrypted = Vec::with_capacity(data.len() + self.overhead());
+				encrypted.extend_from_slice(&nonce_bytes);
+				encrypted.extend_from_slice(&ciphertext);
+				Ok(encrypted)
+			},
+		}
+	}
+
+	/// Decrypts the `data` submitted by `app_id` with `nonce`, using `key`.
+	///
+	/// Fails if the data was not encrypted with `key` for this submission, or was tampered with.
+	pub fn decrypt(
+		&self,
+		key: &EncryptionKey,
+		app_id: u32,
+		nonce: u32,
+		data: &[u8],
+	) -> Result<Vec<u8>, String> {
+		match self {
+			Encryption::None => Ok(data.to_vec()),
+			Encryption::ChaCha20Poly1305 => {
+				if data.len() < self.overhead() {
+					return Err("Encrypted data is too short".to_string())
+				}
+				let (nonce_bytes, ciphertext) = data.split_at(CHACHA20_POLY1305_NONCE_BYTES);
+
+				let aad = associated_data(app_id, nonce);
+				ChaCha20Poly1305::new(Key::from_slice(&key.0))
+					.decrypt(Nonce::from_slice(nonce_bytes), Payload { msg: ciphertext, aad: &aad })
+					.map_err(|_| "Failed to decrypt the data".to_string())
+			},
+		}
+	}
+}
+
+fn associated_data(app_id: u32, nonce: u32) -> [u8; 8] {
+	let mut aad = [0u8; 8];
+	aad[..4].copy_from_slice(&app_id.to_le_bytes());
+	aad[4..].copy_from_slice(&nonce.to_le_bytes());
+	aad
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_encryption_round_trip() {
+		let key = EncryptionKey([7u8; 32]);
+		let data = b"private rollup batch".to_vec();
+
+		for encryption in [Encryption::None, Encryption::ChaCha20Poly1305] {
+			let encrypted = encryption.encrypt(&key, 1, 2, &data).unwrap();
+			assert_eq!(encrypted.len(), data.len() + encryption.overhead());
+			assert_eq!(encryption.decrypt(&key, 1, 2, &encrypted).unwrap(), data);
+		}
+
+		let encryption = Encryption::ChaCha20Poly1305;
+		let encrypted = encryption.encrypt(&key, 1, 2, &data).unwrap();
+		assert_ne!(&encrypted[CHACHA20_POLY1305_NONCE_BYTES..][..data.len()], &data[..]);
+		// The nonce is drawn anew for each encryption.
+		assert_ne!(encryption.encrypt(&key, 1, 2, &data).unwrap(), encrypted);
+	}
+
+	#[test]
+	fn test_decryption_fails_out_of_context() {
+		let key = EncryptionKey([7u8; 32]);
+		let encryption = Encryption::ChaCha20Poly1305;
+		let encrypted = encryption.encrypt(&key, 1, 2, b"data").unwrap();
+
+		assert!(encryption.decrypt(&EncryptionKey([8u8; 32]), 1, 2, &encrypted).is_err());
+		assert!(encryption.decrypt(&key, 3, 2, &encrypted).is_err());
+		assert!(encryption.decrypt(&key, 1, 3, &encrypted).is_err());
+		assert!(encryption.decrypt(&key, 1, 2, &encrypted[..10]).is_err());
+
+		let mut tampered = encrypted;
+		tampered[CHACHA20_POLY1305_NONCE_BYTES] ^= 1;
+		assert!(encryption.decrypt(&key, 1, 2, &tampered).is_err());
+	}
+}
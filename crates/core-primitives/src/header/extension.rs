@@ -12,14 +12,21 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{Decode, Encode, TypeInfo, Vec};
-#[cfg(feature = "std")]
+use crate::{
+	config::MATRIX,
+	inclusion::{commitments_root, MELO_ENGINE_ID},
+	namespace::{namespace_root, NamespaceId, NamespaceProof, NamespacedNode, NamespacedRow},
+	Decode, Digest, Encode, KZGCommitment, TypeInfo, Vec,
+};
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use sp_core::RuntimeDebug;
+use sp_core::{RuntimeDebug, H256};
+use core::ops::Range;
+use sp_runtime::DigestItem;
 
 /// The AppLookup struct represents information related to data in a block.
 #[derive(PartialEq, Eq, Clone, RuntimeDebug, TypeInfo, Encode, Decode, Default)]
-#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct AppLookup {
 	/// The id of the app.
 	#[codec(compact)]
@@ -59,10 +66,37 @@ impl AppLookup {
 
 		None
 	}
+
+	/// Returns the row ranges that belong to the given app.
+	///
+	/// Rows are laid out in the order of the lookups, so an app that submitted several times in
+	/// the same block may own more than one range.
+	///
+	/// # Arguments
+	///
+	/// * `lookups` - An array of lookups.
+	/// * `app_id` - The id of the app.
+	pub fn row_ranges(lookups: &[Self], app_id: u32) -> Vec<Range<u32>> {
+		let mut start = 0u32;
+		let mut ranges: Vec<Range<u32>> = Vec::new();
+
+		for lookup in lookups {
+			let end = start + lookup.count as u32;
+			if lookup.app_id == app_id && end > start {
+				match ranges.last_mut() {
+					Some(last) if last.end == start => last.end = end,
+					_ => ranges.push(start..end),
+				}
+			}
+			start = end;
+		}
+
+		ranges
+	}
 }
 
 #[derive(PartialEq, Eq, Clone, RuntimeDebug, TypeInfo, Encode, Decode, Default)]
-#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct HeaderExtension {
 	/// The commitment of the data root.
 	pub commitments_bytes: Vec<u8>,
@@ -123,6 +157,89 @@ impl HeaderExtension {
     pub fn get_lookup(&self, at: u32) -> Option<(&AppLookup, u32)> {
         AppLookup::get_lookup(&self.app_lookup, at)
     }
+
+    /// Returns the row ranges that belong to the given app.
+    pub fn app_rows(&self, app_id: u32) -> Vec<Range<u32>> {
+        AppLookup::row_ranges(&self.app_lookup, app_id)
+    }
+
+    /// Returns the namespace root of the rows, or `None` if the commitments can not be decoded.
+    pub fn namespace_root(&self) -> Option<NamespacedNode> {
+        decode_commitments(&self.commitments_bytes)
+            .map(|commitments| namespace_root(&commitments, &self.app_lookup))
+    }
+
+    /// Returns the rows of the given app along with the proof that they are all its rows, or
+    /// `None` if the commitments can not be decoded.
+    pub fn namespace_proof(&self, app_id: u32) -> Option<(Vec<NamespacedRow>, NamespaceProof)> {
+        decode_commitments(&self.commitments_bytes).map(|commitments| {
+            NamespaceProof::generate(&commitments, &self.app_lookup, NamespaceId::from_app_id(app_id))
+        })
+    }
+}
+
+fn decode_commitments(bytes: &[u8]) -> Option<Vec<KZGCommitment>> {
+	bytes
+		.chunks(KZGCommitment::size())
+		.map(|c| Decode::decode(&mut &c[..]).ok())
+		.collect()
+}
+
+/// The DAS metadata of a block, carried in the header digest.
+///
+/// Unlike the full commitment list in `HeaderExtension`, this is small enough to be fetched and
+/// checked cheaply by light clients, which can then request the rows they need.
+#[derive(PartialEq, Eq, Clone, RuntimeDebug, TypeInfo, Encode, Decode, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ExtensionDigest {
+	/// The number of rows, which is the number of commitments.
+	#[codec(compact)]
+	pub rows: u32,
+	/// The number of columns, which is the number of segments per extended row.
+	#[codec(compact)]
+	pub cols: u32,
+	/// The Merkle root of the commitments.
+	pub commitments_root: H256,
+	/// The lookup table for the data.
+	pub app_lookup: Vec<AppLookup>,
+}
+
+impl ExtensionDigest {
+	/// Creates the digest of the given commitments and app lookups.
+	pub fn new(commitments: &[KZGCommitment], app_lookup: Vec<AppLookup>) -> Self {
+		Self {
+			rows: commitments.len() as u32,
+			cols: MATRIX.extended_cols().0,
+			commitments_root: commitments_root(commitments),
+			app_lookup,
+		}
+	}
+
+	/// Converts the digest into a digest item.
+	pub fn to_digest_item(&self) -> DigestItem {
+		DigestItem::Consensus(MELO_ENGINE_ID, self.encode())
+	}
+
+	/// Decodes the digest from the first matching item in `digest`, if any.
+	pub fn from_digest(digest: &Digest) -> Option<Self> {
+		digest.convert_first(|item| item.consensus_try_to::<Self>(&MELO_ENGINE_ID))
+	}
+
+	/// Returns the row ranges that belong to the given app.
+	pub fn app_rows(&self, app_id: u32) -> Vec<Range<u32>> {
+		AppLookup::row_ranges(&self.app_lookup, app_id)
+	}
+
+	/// Checks that the digest matches the given header extension.
+	pub fn matches(&self, extension: &HeaderExtension) -> bool {
+		match decode_commitments(&extension.commitments_bytes) {
+			Some(commitments) =>
+				self.rows as usize == commitments.len() &&
+					self.commitments_root == commitments_root(&commitments) &&
+					self.app_lookup == extension.app_lookup,
+			None => false,
+		}
+	}
 }
 
 #[cfg(test)]
@@ -162,6 +279,25 @@ mod tests {
 		assert!(AppLookup::get_lookup(&lookups, 61).is_none());
 	}
 
+	#[test]
+	fn test_app_lookup_row_ranges() {
+		let lookups = vec![
+			AppLookup { app_id: 1, nonce: 1, count: 10 },
+			AppLookup { app_id: 1, nonce: 2, count: 5 },
+			AppLookup { app_id: 2, nonce: 1, count: 20 },
+			AppLookup { app_id: 3, nonce: 1, count: 0 },
+			AppLookup { app_id: 1, nonce: 3, count: 30 },
+		];
+
+		// Adjacent ranges of the same app are merged
+		assert_eq!(AppLookup::row_ranges(&lookups, 1), vec![0..15, 35..65]);
+		assert_eq!(AppLookup::row_ranges(&lookups, 2), vec![15..35]);
+
+		// Apps without rows have no ranges
+		assert!(AppLookup::row_ranges(&lookups, 3).is_empty());
+		assert!(AppLookup::row_ranges(&lookups, 4).is_empty());
+	}
+
 	#[test]
 	fn test_header_extension_start_at() {
 		let header_extension = HeaderExtension {
@@ -181,4 +317,19 @@ mod tests {
 		// Testing for non-existing app_id and nonce
 		assert_eq!(header_extension.start_at(3, 1), None);
 	}
+
+	#[test]
+	fn test_extension_digest_round_trip() {
+		let app_lookup = vec![AppLookup { app_id: 1, nonce: 1, count: 0 }];
+		let extension_digest = ExtensionDigest::new(&[], app_lookup.clone());
+
+		let mut digest = Digest::default();
+		assert_eq!(ExtensionDigest::from_digest(&digest), None);
+
+		digest.push(extension_digest.to_digest_item());
+		assert_eq!(ExtensionDigest::from_digest(&digest), Some(extension_digest.clone()));
+
+		assert!(extension_digest.matches(&HeaderExtension::new(vec![], app_lookup)));
+		assert!(!extension_digest.matches(&HeaderExtension::new(vec![], vec![])));
+	}
 }
@@ -24,17 +24,19 @@ pub use codec::{Codec, Decode, Encode};
 pub use scale_info::TypeInfo;
 
 use crate::{
+	inclusion::InclusionProof,
+	namespace::NamespacedNode,
 	traits::{ExtendedHeader, HeaderWithCommitment},
 	Digest,
 };
 
 pub mod extension;
-pub use extension::{AppLookup, HeaderExtension};
+pub use extension::{AppLookup, ExtensionDigest, HeaderExtension};
 
 use melo_das_primitives::KZGCommitment;
 #[cfg(feature = "std")]
 use serde::{Deserialize, Serialize};
-use sp_core::U256;
+use sp_core::{H256, U256};
 use sp_runtime::traits::{
 	self, AtLeast32BitUnsigned, Hash as HashT, MaybeDisplay, MaybeSerialize,
 	MaybeSerializeDeserialize, Member, SimpleBitOps,
@@ -191,6 +193,26 @@ where
 	pub fn hash(&self) -> Hash::Output {
 		Hash::hash_of(self)
 	}
+
+	/// Returns the DAS metadata deposited into the digest, if any.
+	pub fn extension_digest(&self) -> Option<ExtensionDigest> {
+		ExtensionDigest::from_digest(&self.digest)
+	}
+
+	/// Returns the commitments root deposited into the digest, if any.
+	pub fn commitments_root(&self) -> Option<H256> {
+		self.extension_digest().map(|d| d.commitments_root)
+	}
+
+	/// Returns the namespace root deposited into the digest, if any.
+	pub fn namespace_root(&self) -> Option<NamespacedNode> {
+		NamespacedNode::from_digest(&self.digest)
+	}
+
+	/// Generates a proof that the commitment at `index` is included in this header.
+	pub fn inclusion_proof(&self, index: u32) -> Option<InclusionProof> {
+		InclusionProof::generate(&ExtendedHeader::commitments(self)?, index)
+	}
 }
 
 impl<Number: Copy + Into<U256> + TryFrom<U256>, Hash: HashT> ExtendedHeader
@@ -0,0 +1,263 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Blob inclusion proofs.
+//!
+//! All row commitments of a block are folded into a binary Merkle tree whose root is deposited
+//! into the block header digest as part of the `ExtensionDigest`. A compact [`InclusionProof`] then
+//! shows that a given `KZGCommitment` is part of block B, without shipping the full commitment list
+//! or a storage proof.
+//!
+//! Past the retention period, the runtime only keeps this root of the commitments of a block, as
+//! [`BlockCommitments::Compacted`]. The commitments themselves stay in the state of archive nodes.
+
+use crate::{Decode, Encode, KZGCommitment, TypeInfo, Vec};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use sp_core::{RuntimeDebug, H256};
+use sp_io::hashing::blake2_256;
+use sp_runtime::ConsensusEngineId;
+
+/// The engine id used for Melodot digest items.
+pub const MELO_ENGINE_ID: ConsensusEngineId = *b"MELO";
+
+// Domain separation prefixes, so that an inner node can never be presented as a leaf.
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+/// Hashes a commitment into a leaf of the commitments tree.
+pub fn leaf_hash(commitment: &KZGCommitment) -> H256 {
+	let mut buffer = Vec::with_capacity(1 + KZGCommitment::size());
+	buffer.push(LEAF_PREFIX);
+	buffer.extend_from_slice(&commitment.to_bytes());
+	blake2_256(&buffer).into()
+}
+
+fn node_hash(left: &H256, right: &H256) -> H256 {
+	let mut buffer = [0u8; 65];
+	buffer[0] = NODE_PREFIX;
+	buffer[1..33].copy_from_slice(left.as_bytes());
+	buffer[33..].copy_from_slice(right.as_bytes());
+	blake2_256(&buffer).into()
+}
+
+// Builds the next level of the tree. An unpaired node is promoted unchanged.
+fn next_level(level: &[H256]) -> Vec<H256> {
+	level
+		.chunks(2)
+		.map(|pair| match pair {
+			[left, right] => node_hash(left, right),
+			[single] => *single,
+			_ => unreachable!("chunks(2) yields one or two items; qed"),
+		})
+		.collect()
+}
+
+/// Computes the Merkle root over the given leaves.
+///
+/// Returns `H256::zero()` if there are no leaves.
+pub fn merkle_root(leaves: &[H256]) -> H256 {
+	if leaves.is_empty() {
+		return H256::zero()
+	}
+
+	let mut level = leaves.to_vec();
+	while level.len() > 1 {
+		level = next_level(&level);
+	}
+	level[0]
+}
+
+/// Computes the commitments root of the given list of commitments.
+pub fn commitments_root(commitments: &[KZGCommitment]) -> H256 {
+	merkle_root(&commitments.iter().map(leaf_hash).collect::<Vec<_>>())
+}
+
+/// The commitments of the rows of a block, as kept in the state of the runtime.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum BlockCommitments {
+	/// The commitments of the rows, within the retention period.
+	Full(Vec<KZGCommitment>),
+	/// The root of the commitments of the rows, past the retention period.
+	Compacted(H256),
+}
+
+impl BlockCommitments {
+	/// Returns the commitments root of the block.
+	pub fn root(&self) -> H256 {
+		match self {
+			BlockCommitments::Full(commitments) => commitments_root(commitments),
+			BlockCommitments::Compacted(root) => *root,
+		}
+	}
+
+	/// Verifies that `commitment` is the row of the block `proof` is for, whether or not the
+	/// commitments of the block were compacted.
+	pub fn verify(&self, proof: &InclusionProof, commitment: &KZGCommitment) -> bool {
+		proof.verify(&self.root(), commitment)
+	}
+}
+
+/// A compact proof that a commitment is included in a commitments root.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct InclusionProof {
+	/// The index of the commitment in the block.
+	#[codec(compact)]
+	pub index: u32,
+	/// The total number of commitments in the block.
+	#[codec(compact)]
+	pub leaf_count: u32,
+	/// The sibling hashes from the leaf up to the root.
+	pub siblings: Vec<H256>,
+}
+
+impl InclusionProof {
+	/// Generates a proof for the leaf at `index`.
+	///
+	/// Returns `None` if `index` is out of bounds.
+	pub fn from_leaves(leaves: &[H256], index: u32) -> Option<Self> {
+		if index as usize >= leaves.len() {
+			return None
+		}
+
+		let mut siblings = Vec::new();
+		let mut level = leaves.to_vec();
+		let mut pos = index as usize;
+
+		while level.len() > 1 {
+			let sibling = pos ^ 1;
+			if sibling < level.len() {
+				siblings.push(level[sibling]);
+			}
+			level = next_level(&level);
+			pos /= 2;
+		}
+
+		Some(Self { index, leaf_count: leaves.len() as u32, siblings })
+	}
+
+	/// Generates a proof that the commitment at `index` is part of `commitments`.
+	pub fn generate(commitments: &[KZGCommitment], index: u32) -> Option<Self> {
+		Self::from_leaves(&commitments.iter().map(leaf_hash).collect::<Vec<_>>(), index)
+	}
+
+	/// Computes the root implied by this proof for the given leaf.
+	///
+	/// Returns `None` if the proof is malformed.
+	pub fn compute_root(&self, leaf: H256) -> Option<H256> {
+		if self.index >= self.leaf_count {
+			return None
+		}
+
+		let mut siblings = self.siblings.iter();
+		let mut hash = leaf;
+		let mut pos = self.index as usize;
+		let mut width = self.leaf_count as usize;
+
+		while width > 1 {
+			let sibling = pos ^ 1;
+			if sibling < width {
+				let node = siblings.next()?;
+				hash = if pos % 2 == 0 { node_hash(&hash, node) } else { node_hash(node, &hash) };
+			}
+			pos /= 2;
+			width = (width + 1) / 2;
+		}
+
+		// All siblings must be consumed.
+		siblings.next().is_none().then_some(hash)
+	}
+
+	/// Verifies that `leaf` is included under `root`.
+	pub fn verify_leaf(&self, root: &H256, leaf: H256) -> bool {
+		self.compute_root(leaf).as_ref() == Some(root)
+	}
+
+	/// Verifies that `commitment` is included under `root`.
+	pub fn verify(&self, root: &H256, commitment: &KZGCommitment) -> bool {
+		self.verify_leaf(root, leaf_hash(commitment))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn leaves(n: u8) -> Vec<H256> {
+		(0..n).map(|i| H256::repeat_byte(i)).collect()
+	}
+
+	#[test]
+	fn test_merkle_root_edge_cases() {
+		assert_eq!(merkle_root(&[]), H256::zero());
+		assert_eq!(merkle_root(&leaves(1)), H256::repeat_byte(0));
+		assert_eq!(
+			merkle_root(&leaves(2)),
+			node_hash(&H256::repeat_byte(0), &H256::repeat_byte(1))
+		);
+	}
+
+	#[test]
+	fn test_compacted_commitments_verify_like_full() {
+		let commitments: Vec<_> = (1..=3u8)
+			.map(|i| {
+				crate::SidecarMetadata::try_from_app_data(&[i; 10], 0, 0).unwrap().commitments[0]
+			})
+			.collect();
+		let full = BlockCommitments::Full(commitments.clone());
+		let compacted = BlockCommitments::Compacted(commitments_root(&commitments));
+		assert_eq!(full.root(), compacted.root());
+
+		let proof = InclusionProof::generate(&commitments, 1).unwrap();
+		assert!(full.verify(&proof, &commitments[1]));
+		assert!(compacted.verify(&proof, &commitments[1]));
+		assert!(!compacted.verify(&proof, &commitments[0]));
+	}
+
+	#[test]
+	fn test_inclusion_proof_round_trip() {
+		for n in 1..=9u8 {
+			let leaves = leaves(n);
+			let root = merkle_root(&leaves);
+			for (i, leaf) in leaves.iter().enumerate() {
+				let proof = InclusionProof::from_leaves(&leaves, i as u32).unwrap();
+				assert!(proof.verify_leaf(&root, *leaf), "n = {}, i = {}", n, i);
+				assert!(!proof.verify_leaf(&root, H256::repeat_byte(0xff)));
+			}
+			assert!(InclusionProof::from_leaves(&leaves, n as u32).is_none());
+		}
+	}
+
+	#[test]
+	fn test_inclusion_proof_rejects_tampering() {
+		let leaves = leaves(5);
+		let root = merkle_root(&leaves);
+		let proof = InclusionProof::from_leaves(&leaves, 2).unwrap();
+
+		let mut wrong_index = proof.clone();
+		wrong_index.index = 3;
+		assert!(!wrong_index.verify_leaf(&root, leaves[2]));
+
+		let mut extra_sibling = proof.clone();
+		extra_sibling.siblings.push(H256::zero());
+		assert!(!extra_sibling.verify_leaf(&root, leaves[2]));
+
+		let mut out_of_bounds = proof;
+		out_of_bounds.index = 5;
+		assert!(out_of_bounds.compute_root(leaves[2]).is_none());
+	}
+}
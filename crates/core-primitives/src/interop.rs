@@ -0,0 +1,431 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! External representation of sidecars and segments.
+//!
+//! The messages of this module mirror `proto/sidecar.proto`, so that consumers outside of
+//! Substrate, such as bridges and indexers, can parse the payloads with any protobuf library.
+//! They also serialize to the canonical proto3 JSON form: lowerCamelCase field names, bytes in
+//! standard base64 and enums by name.
+//!
+//! Conversions from the native types are infallible, conversions back check the lengths and the
+//! validity of the points and scalars.
+use crate::{
+	sidecar::{Sidecar as NativeSidecar, SidecarMetadata as NativeSidecarMetadata, SidecarStatus},
+	Compression as NativeCompression, Encryption as NativeEncryption, KZGCommitment, KZGProof,
+	Position,
+};
+use melo_das_primitives::{BlsScalar, Segment as NativeSegment, SegmentData};
+use prost::Message;
+use serde::{Deserialize, Serialize};
+
+/// Compression of the data of a sidecar.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum Compression {
+	None = 0,
+	Zstd = 1,
+}
+
+/// Encryption of the data of a sidecar.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum Encryption {
+	None = 0,
+	ChaCha20Poly1305 = 1,
+}
+
+/// Status of a sidecar.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum Status {
+	Unspecified = 0,
+	NotFound = 1,
+	ProofError = 2,
+	Success = 3,
+}
+
+/// `melodot.da.v1.SidecarMetadata`.
+#[derive(Clone, PartialEq, Message, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SidecarMetadata {
+	#[prost(uint32, tag = "1")]
+	pub app_id: u32,
+	#[prost(uint32, tag = "2")]
+	pub bytes_len: u32,
+	#[prost(uint32, tag = "3")]
+	pub nonce: u32,
+	#[prost(bytes = "vec", repeated, tag = "4")]
+	#[serde(with = "base64_list")]
+	pub commitments: Vec<Vec<u8>>,
+	#[prost(bytes = "vec", repeated, tag = "5")]
+	#[serde(with = "base64_list")]
+	pub proofs: Vec<Vec<u8>>,
+	#[prost(enumeration = "Compression", tag = "6")]
+	#[serde(with = "compression_name")]
+	pub compression: i32,
+	#[prost(enumeration = "Encryption", tag = "7")]
+	#[serde(with = "encryption_name")]
+	pub encryption: i32,
+}
+
+/// `melodot.da.v1.Sidecar`.
+#[derive(Clone, PartialEq, Message, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Sidecar {
+	#[prost(message, optional, tag = "1")]
+	pub metadata: Option<SidecarMetadata>,
+	#[prost(enumeration = "Status", tag = "2")]
+	#[serde(with = "status_name")]
+	pub status: i32,
+}
+
+/// `melodot.da.v1.Segment`.
+#[derive(Clone, PartialEq, Message, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Segment {
+	#[prost(uint32, tag = "1")]
+	pub x: u32,
+	#[prost(uint32, tag = "2")]
+	pub y: u32,
+	#[prost(bytes = "vec", repeated, tag = "3")]
+	#[serde(with = "base64_list")]
+	pub data: Vec<Vec<u8>>,
+	#[prost(bytes = "vec", tag = "4")]
+	#[serde(with = "base64_bytes")]
+	pub proof: Vec<u8>,
+}
+
+/// Encodes `message` to protobuf.
+pub fn to_proto<M: Message>(message: &M) -> Vec<u8> {
+	message.encode_to_vec()
+}
+
+/// Decodes a protobuf message.
+pub fn from_proto<M: Message + Default>(bytes: &[u8]) -> Result<M, String> {
+	M::decode(bytes).map_err(|e| e.to_string())
+}
+
+/// Encodes `message` to canonical JSON.
+pub fn to_json<M: Serialize>(message: &M) -> String {
+	serde_json::to_string(message).expect("Messages are always serializable; qed")
+}
+
+/// Decodes a message from JSON.
+pub fn from_json<'a, M: Deserialize<'a>>(json: &'a str) -> Result<M, String> {
+	serde_json::from_str(json).map_err(|e| e.to_string())
+}
+
+impl From<NativeCompression> for Compression {
+	fn from(compression: NativeCompression) -> Self {
+		match compression {
+			NativeCompression::None => Compression::None,
+			NativeCompression::Zstd => Compression::Zstd,
+		}
+	}
+}
+
+impl From<Compression> for NativeCompression {
+	fn from(compression: Compression) -> Self {
+		match compression {
+			Compression::None => NativeCompression::None,
+			Compression::Zstd => NativeCompression::Zstd,
+		}
+	}
+}
+
+impl From<NativeEncryption> for Encryption {
+	fn from(encryption: NativeEncryption) -> Self {
+		match encryption {
+			NativeEncryption::None => Encryption::None,
+			NativeEncryption::ChaCha20Poly1305 => Encryption::ChaCha20Poly1305,
+		}
+	}
+}
+
+impl From<Encryption> for NativeEncryption {
+	fn from(encryption: Encryption) -> Self {
+		match encryption {
+			Encryption::None => NativeEncryption::None,
+			Encryption::ChaCha20Poly1305 => NativeEncryption::ChaCha20Poly1305,
+		}
+	}
+}
+
+impl From<&NativeSidecarMetadata> for SidecarMetadata {
+	fn from(metadata: &NativeSidecarMetadata) -> Self {
+		Self {
+			app_id: metadata.app_id,
+			bytes_len: metadata.bytes_len,
+			nonce: metadata.nonce,
+			commitments: metadata.commitments.iter().map(|c| c.to_bytes().to_vec()).collect(),
+			proofs: metadata.proofs.iter().map(|p| p.to_bytes().to_vec()).collect(),
+			compression: Compression::from(metadata.compression) as i32,
+			encryption: Encryption::from(metadata.encryption) as i32,
+		}
+	}
+}
+
+impl TryFrom<&SidecarMetadata> for NativeSidecarMetadata {
+	type Error = String;
+
+	fn try_from(metadata: &SidecarMetadata) -> Result<Self, String> {
+		let compression = Compression::from_i32(metadata.compression)
+			.ok_or_else(|| format!("Unknown compression {}", metadata.compression))?;
+		let encryption = Encryption::from_i32(metadata.encryption)
+			.ok_or_else(|| format!("Unknown encryption {}", metadata.encryption))?;
+		let commitments = metadata
+			.commitments
+			.iter()
+			.map(|bytes| fixed_bytes(bytes).and_then(|b| KZGCommitment::try_from_bytes(&b)))
+			.collect::<Result<Vec<_>, _>>()?;
+		let proofs = metadata
+			.proofs
+			.iter()
+			.map(|bytes| fixed_bytes(bytes).and_then(|b| KZGProof::try_from_bytes(&b)))
+			.collect::<Result<Vec<_>, _>>()?;
+
+		Ok(NativeSidecarMetadata::new(
+			metadata.app_id,
+			metadata.bytes_len,
+			metadata.nonce,
+			commitments,
+			proofs,
+		)
+		.with_compression(compression.into())
+		.with_encryption(encryption.into()))
+	}
+}
+
+impl From<&NativeSidecar> for Sidecar {
+	fn from(sidecar: &NativeSidecar) -> Self {
+		let status = match sidecar.status {
+			None => Status::Unspecified,
+			Some(SidecarStatus::NotFound) => Status::NotFound,
+			Some(SidecarStatus::ProofError) => Status::ProofError,
+			Some(SidecarStatus::Success) => Status::Success,
+		};
+		Self { metadata: Some((&sidecar.metadata).into()), status: status as i32 }
+	}
+}
+
+impl TryFrom<&Sidecar> for NativeSidecar {
+	type Error = String;
+
+	fn try_from(sidecar: &Sidecar) -> Result<Self, String> {
+		let metadata = sidecar.metadata.as_ref().ok_or("Sidecar without metadata")?;
+		let status = match Status::from_i32(sidecar.status) {
+			Some(Status::Unspecified) => None,
+			Some(Status::NotFound) => Some(SidecarStatus::NotFound),
+			Some(Status::ProofError) => Some(SidecarStatus::ProofError),
+			Some(Status::Success) => Some(SidecarStatus::Success),
+			None => return Err(format!("Unknown sidecar status {}", sidecar.status)),
+		};
+		Ok(NativeSidecar { metadata: metadata.try_into()?, status })
+	}
+}
+
+impl From<&NativeSegment> for Segment {
+	fn from(segment: &NativeSegment) -> Self {
+		Self {
+			x: segment.position.x,
+			y: segment.position.y,
+			data: segment.content.data.iter().map(|s| s.to_bytes().to_vec()).collect(),
+			proof: segment.content.proof.to_bytes().to_vec(),
+		}
+	}
+}
+
+impl TryFrom<&Segment> for NativeSegment {
+	type Error = String;
+
+	fn try_from(segment: &Segment) -> Result<Self, String> {
+		let data = segment
+			.data
+			.iter()
+			.map(|bytes| fixed_bytes(bytes).and_then(|b| BlsScalar::try_from_bytes(&b)))
+			.collect::<Result<Vec<_>, _>>()?;
+		let proof = fixed_bytes(&segment.proof).and_then(|b| KZGProof::try_from_bytes(&b))?;
+
+		Ok(NativeSegment {
+			position: Position { x: segment.x, y: segment.y },
+			content: SegmentData { data, proof },
+		})
+	}
+}
+
+fn fixed_bytes<const N: usize>(bytes: &[u8]) -> Result<[u8; N], String> {
+	bytes
+		.try_into()
+		.map_err(|_| format!("Expected {} bytes, got {}", N, bytes.len()))
+}
+
+mod base64_bytes {
+	use base64::{engine::general_purpose::STANDARD, Engine};
+	use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+	pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_str(&STANDARD.encode(bytes))
+	}
+
+	pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+		STANDARD.decode(String::deserialize(deserializer)?).map_err(D::Error::custom)
+	}
+}
+
+mod base64_list {
+	use base64::{engine::general_purpose::STANDARD, Engine};
+	use serde::{de::Error, ser::SerializeSeq, Deserialize, Deserializer, Serializer};
+
+	pub fn serialize<S: Serializer>(list: &[Vec<u8>], serializer: S) -> Result<S::Ok, S::Error> {
+		let mut seq = serializer.serialize_seq(Some(list.len()))?;
+		for bytes in list {
+			seq.serialize_element(&STANDARD.encode(bytes))?;
+		}
+		seq.end()
+	}
+
+	pub fn deserialize<'de, D: Deserializer<'de>>(
+		deserializer: D,
+	) -> Result<Vec<Vec<u8>>, D::Error> {
+		Vec::<String>::deserialize(deserializer)?
+			.iter()
+			.map(|s| STANDARD.decode(s).map_err(D::Error::custom))
+			.collect()
+	}
+}
+
+mod compression_name {
+	use super::Compression;
+	use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+	pub fn serialize<S: Serializer>(value: &i32, serializer: S) -> Result<S::Ok, S::Error> {
+		match Compression::from_i32(*value) {
+			Some(Compression::None) => serializer.serialize_str("COMPRESSION_NONE"),
+			Some(Compression::Zstd) => serializer.serialize_str("COMPRESSION_ZSTD"),
+			None => serializer.serialize_i32(*value),
+		}
+	}
+
+	pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i32, D::Error> {
+		match String::deserialize(deserializer)?.as_str() {
+			"COMPRESSION_NONE" => Ok(Compression::None as i32),
+			"COMPRESSION_ZSTD" => Ok(Compression::Zstd as i32),
+			name => Err(D::Error::custom(format!("Unknown compression {}", name))),
+		}
+	}
+}
+
+mod encryption_name {
+	use super::Encryption;
+	use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+	pub fn serialize<S: Serializer>(value: &i32, serializer: S) -> Result<S::Ok, S::Error> {
+		match Encryption::from_i32(*value) {
+			Some(Encryption::None) => serializer.serialize_str("ENCRYPTION_NONE"),
+			Some(Encryption::ChaCha20Poly1305) =>
+				serializer.serialize_str("ENCRYPTION_CHACHA20_POLY1305"),
+			None => serializer.serialize_i32(*value),
+		}
+	}
+
+	pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i32, D::Error> {
+		match String::deserialize(deserializer)?.as_str() {
+			"ENCRYPTION_NONE" => Ok(Encryption::None as i32),
+			"ENCRYPTION_CHACHA20_POLY1305" => Ok(Encryption::ChaCha20Poly1305 as i32),
+			name => Err(D::Error::custom(format!("Unknown encryption {}", name))),
+		}
+	}
+}
+
+mod status_name {
+	use super::Status;
+	use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+	pub fn serialize<S: Serializer>(value: &i32, serializer: S) -> Result<S::Ok, S::Error> {
+		match Status::from_i32(*value) {
+			Some(Status::Unspecified) => serializer.serialize_str("SIDECAR_STATUS_UNSPECIFIED"),
+			Some(Status::NotFound) => serializer.serialize_str("SIDECAR_STATUS_NOT_FOUND"),
+			Some(Status::ProofError) => serializer.serialize_str("SIDECAR_STATUS_PROOF_ERROR"),
+			Some(Status::Success) => serializer.serialize_str("SIDECAR_STATUS_SUCCESS"),
+			None => serializer.serialize_i32(*value),
+		}
+	}
+
+	pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i32, D::Error> {
+		match String::deserialize(deserializer)?.as_str() {
+			"SIDECAR_STATUS_UNSPECIFIED" => Ok(Status::Unspecified as i32),
+			"SIDECAR_STATUS_NOT_FOUND" => Ok(Status::NotFound as i32),
+			"SIDECAR_STATUS_PROOF_ERROR" => Ok(Status::ProofError as i32),
+			"SIDECAR_STATUS_SUCCESS" => Ok(Status::Success as i32),
+			name => Err(D::Error::custom(format!("Unknown sidecar status {}", name))),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::config::FIELD_ELEMENTS_PER_SEGMENT;
+	use melo_das_primitives::{config::FIELD_ELEMENTS_PER_BLOB, KZG};
+	use melo_erasure_coding::bytes_to_segments;
+
+	#[test]
+	fn test_sidecar_round_trip() {
+		let metadata = NativeSidecarMetadata::try_from_app_data(&[3u8; 100], 2, 5)
+			.unwrap()
+			.with_compression(NativeCompression::Zstd)
+			.with_encryption(NativeEncryption::ChaCha20Poly1305);
+		let mut sidecar = NativeSidecar::new(metadata);
+		sidecar.status = Some(SidecarStatus::ProofError);
+
+		let message = Sidecar::from(&sidecar);
+		let decoded: Sidecar = from_proto(&to_proto(&message)).unwrap();
+		assert_eq!(decoded, message);
+		assert!(NativeSidecar::try_from(&decoded).unwrap() == sidecar);
+
+		let json = to_json(&message);
+		assert!(json.contains("\"appId\":2"));
+		assert!(json.contains("\"compression\":\"COMPRESSION_ZSTD\""));
+		assert!(json.contains("\"encryption\":\"ENCRYPTION_CHACHA20_POLY1305\""));
+		assert!(json.contains("\"status\":\"SIDECAR_STATUS_PROOF_ERROR\""));
+		assert_eq!(from_json::<Sidecar>(&json).unwrap(), message);
+
+		// Points of the wrong length are rejected.
+		let mut invalid = message;
+		invalid.metadata.as_mut().unwrap().commitments[0].pop();
+		assert!(NativeSidecar::try_from(&invalid).is_err());
+	}
+
+	#[test]
+	fn test_segment_round_trip() {
+		let kzg = KZG::default_embedded();
+		let segments = bytes_to_segments(
+			&[1u8; 100],
+			FIELD_ELEMENTS_PER_BLOB,
+			FIELD_ELEMENTS_PER_SEGMENT,
+			&kzg,
+		)
+		.unwrap();
+
+		let message = Segment::from(&segments[3]);
+		assert_eq!(message.data.len(), FIELD_ELEMENTS_PER_SEGMENT);
+
+		let decoded: Segment = from_proto(&to_proto(&message)).unwrap();
+		assert_eq!(NativeSegment::try_from(&decoded).unwrap(), segments[3]);
+
+		let decoded: Segment = from_json(&to_json(&message)).unwrap();
+		assert_eq!(NativeSegment::try_from(&decoded).unwrap(), segments[3]);
+	}
+}
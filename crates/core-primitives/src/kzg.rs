@@ -0,0 +1,303 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Native KZG verification.
+//!
+//! Pairings are an order of magnitude slower in WASM than natively, so the runtime checks KZG
+//! proofs through the [`kzg_verifier`] host functions. The node has to register
+//! [`kzg_verifier::HostFunctions`] with its executor.
+//!
+//! It also provides the aggregation of openings of several commitments at the same point into a
+//! single [`AggregateCommitment`] and [`AggregateProof`], combined with the powers of a random
+//! challenge, so that they can be checked with one pairing.
+//!
+//! The hash of the trusted setup, given by [`trusted_setup_hash`], is registered on chain so that
+//! nodes can check at startup that they use the same KZG settings as the network.
+use crate::{KZGCommitment, KZGProof, String, ToString, TypeInfo, Vec};
+use codec::{Decode, Encode, MaxEncodedLen};
+use melo_das_primitives::{config::EMBEDDED_KZG_SETTINGS_BYTES, BlsScalar, Segment, KZG};
+use sp_core::{RuntimeDebug, H256};
+use sp_io::hashing::blake2_256;
+use sp_runtime_interface::{pass_by::PassByCodec, runtime_interface};
+
+#[cfg(feature = "std")]
+lazy_static::lazy_static! {
+	/// The KZG settings of the host, loaded once.
+	static ref EMBEDDED_KZG: KZG = KZG::default_embedded();
+}
+
+/// A segment to verify against the commitment of its row.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, PassByCodec)]
+pub struct SegmentCheck {
+	/// The commitment of the row of the segment.
+	pub commitment: KZGCommitment,
+	/// The segment, with its multi-proof.
+	pub segment: Segment,
+	/// The number of segments of the row.
+	pub count: u32,
+}
+
+impl SegmentCheck {
+	/// Creates a new check of `segment` in a row of `count` segments committed to by
+	/// `commitment`.
+	pub fn new(commitment: KZGCommitment, segment: Segment, count: u32) -> Self {
+		Self { commitment, segment, count }
+	}
+
+	/// Verifies the multi-proof of the segment with `kzg`.
+	pub fn verify(&self, kzg: &KZG) -> bool {
+		self.segment.verify(kzg, &self.commitment, self.count as usize).unwrap_or(false)
+	}
+}
+
+/// The domain separator of the challenge of aggregated openings.
+const AGGREGATION_DOMAIN: &[u8] = b"MELO_AGGREGATION";
+
+/// A random linear combination of commitments.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct AggregateCommitment {
+	/// The combined commitment.
+	pub commitment: KZGCommitment,
+	/// The number of commitments combined.
+	pub count: u32,
+}
+
+/// The opening of an [`AggregateCommitment`] at the point of an index.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct AggregateProof {
+	/// The index of the point, in the expanded roots of unity.
+	pub index: u32,
+	/// The combined value at the point.
+	pub value: BlsScalar,
+	/// The combined proof.
+	pub proof: KZGProof,
+}
+
+impl AggregateCommitment {
+	/// Combines `commitments` with the powers of `challenge`.
+	pub fn new(commitments: &[KZGCommitment], challenge: &BlsScalar) -> Self {
+		let powers = challenge.powers(commitments.len());
+		Self {
+			commitment: KZGCommitment::linear_combination(commitments, &powers),
+			count: commitments.len() as u32,
+		}
+	}
+
+	/// Verifies `proof` against the combined commitment.
+	pub fn verify(&self, kzg: &KZG, proof: &AggregateProof) -> Result<bool, String> {
+		kzg.verify(&self.commitment, proof.index, &proof.value, &proof.proof)
+	}
+}
+
+impl AggregateProof {
+	/// Combines the openings of several commitments at the point of `index` with the powers of
+	/// `challenge`.
+	pub fn new(
+		index: u32,
+		values: &[BlsScalar],
+		proofs: &[KZGProof],
+		challenge: &BlsScalar,
+	) -> Self {
+		let powers = challenge.powers(values.len());
+		Self {
+			index,
+			value: BlsScalar::linear_combination(values, &powers),
+			proof: KZGProof::linear_combination(proofs, &powers),
+		}
+	}
+}
+
+/// Derives the challenge combining the openings of `commitments` at the point of `index` to
+/// `values`.
+pub fn aggregation_challenge(
+	commitments: &[KZGCommitment],
+	index: u32,
+	values: &[BlsScalar],
+) -> BlsScalar {
+	let mut bytes = AGGREGATION_DOMAIN.to_vec();
+	bytes.extend(index.encode());
+	commitments.iter().for_each(|commitment| bytes.extend(commitment.to_bytes()));
+	values.iter().for_each(|value| bytes.extend(value.to_bytes()));
+	BlsScalar::hash_to_scalar(&bytes)
+}
+
+/// Aggregates the openings of `commitments` at the point of `index` to `values`, proven by
+/// `proofs`.
+///
+/// # Errors
+///
+/// Returns an error if there is no commitment, or if the numbers of commitments, values and proofs
+/// differ.
+pub fn aggregate_openings(
+	commitments: &[KZGCommitment],
+	index: u32,
+	values: &[BlsScalar],
+	proofs: &[KZGProof],
+) -> Result<(AggregateCommitment, AggregateProof), String> {
+	if commitments.is_empty() ||
+		commitments.len() != values.len() ||
+		commitments.len() != proofs.len()
+	{
+		return Err("Mismatched number of commitments, values and proofs".to_string())
+	}
+
+	let challenge = aggregation_challenge(commitments, index, values);
+	Ok((
+		AggregateCommitment::new(commitments, &challenge),
+		AggregateProof::new(index, values, proofs, &challenge),
+	))
+}
+
+/// Verifies that `commitments` open at the point of `index` to `values`, with the `proof` of their
+/// aggregation by [`aggregate_openings`].
+pub fn verify_aggregated_openings(
+	kzg: &KZG,
+	commitments: &[KZGCommitment],
+	index: u32,
+	values: &[BlsScalar],
+	proof: &KZGProof,
+) -> Result<bool, String> {
+	if commitments.is_empty() || commitments.len() != values.len() {
+		return Err("Mismatched number of commitments and values".to_string())
+	}
+
+	let challenge = aggregation_challenge(commitments, index, values);
+	let powers = challenge.powers(values.len());
+	let aggregate = AggregateCommitment::new(commitments, &challenge);
+	let proof = AggregateProof {
+		index,
+		value: BlsScalar::linear_combination(values, &powers),
+		proof: *proof,
+	};
+	aggregate.verify(kzg, &proof)
+}
+
+/// Returns the hash of the serialized KZG settings `settings_bytes`, in the format of the
+/// embedded trusted setup.
+pub fn trusted_setup_hash(settings_bytes: &[u8]) -> H256 {
+	blake2_256(settings_bytes).into()
+}
+
+/// Returns the hash of the embedded trusted setup, used by the node and by the host functions.
+pub fn embedded_trusted_setup_hash() -> H256 {
+	trusted_setup_hash(EMBEDDED_KZG_SETTINGS_BYTES)
+}
+
+/// Host functions verifying KZG proofs natively.
+#[runtime_interface]
+pub trait KzgVerifier {
+	/// Verifies the multi-proof of a segment.
+	fn verify_segment(check: SegmentCheck) -> bool {
+		check.verify(&EMBEDDED_KZG)
+	}
+
+	/// Verifies the multi-proofs of several segments.
+	///
+	/// Returns `true` only if every proof is valid.
+	fn verify_segments(checks: Vec<SegmentCheck>) -> bool {
+		checks.iter().all(|check| check.verify(&EMBEDDED_KZG))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		config::{FIELD_ELEMENTS_PER_SEGMENT, SEGMENTS_PER_BLOB},
+		SidecarMetadata,
+	};
+	use melo_das_primitives::{
+		config::{BYTES_PER_BLOB, FIELD_ELEMENTS_PER_BLOB},
+		Blob,
+	};
+	use melo_erasure_coding::bytes_to_segments;
+
+	#[test]
+	fn test_verify_segments() {
+		let kzg = KZG::default_embedded();
+		let bytes = vec![7u8; 1000];
+		let metadata = SidecarMetadata::try_from_app_data(&bytes, 1, 1).unwrap();
+		let segments =
+			bytes_to_segments(&bytes, FIELD_ELEMENTS_PER_BLOB, FIELD_ELEMENTS_PER_SEGMENT, &kzg)
+				.unwrap();
+
+		let checks = segments
+			.iter()
+			.take(2)
+			.map(|segment| {
+				SegmentCheck::new(
+					metadata.commitments[0],
+					segment.clone(),
+					SEGMENTS_PER_BLOB as u32,
+				)
+			})
+			.collect::<Vec<_>>();
+		assert!(kzg_verifier::verify_segment(checks[0].clone()));
+		assert!(kzg_verifier::verify_segments(checks.clone()));
+
+		// A segment checked at the position of another one is invalid.
+		let mut wrong = checks[1].clone();
+		wrong.segment.position.x = 0;
+		assert!(!kzg_verifier::verify_segment(wrong.clone()));
+		assert!(!kzg_verifier::verify_segments(vec![checks[0].clone(), wrong]));
+	}
+
+	#[test]
+	fn test_aggregate_openings() {
+		let kzg = KZG::default_embedded();
+		let index = 5u32;
+		let polys = (0..3u8)
+			.map(|i| {
+				Blob::try_from_bytes_pad(&vec![i + 1; 1000], BYTES_PER_BLOB).unwrap().to_poly()
+			})
+			.collect::<Vec<_>>();
+		let x = kzg.get_expanded_roots_of_unity_at(index as usize);
+		let commitments = polys.iter().map(|poly| kzg.commit(poly).unwrap()).collect::<Vec<_>>();
+		let values = polys.iter().map(|poly| poly.eval(&BlsScalar(x))).collect::<Vec<_>>();
+		let proofs = polys
+			.iter()
+			.map(|poly| kzg.compute_proof(poly, &x).unwrap())
+			.collect::<Vec<_>>();
+
+		let (aggregate, proof) = aggregate_openings(&commitments, index, &values, &proofs).unwrap();
+		assert_eq!(aggregate.count, 3);
+		assert!(aggregate.verify(&kzg, &proof).unwrap());
+		assert!(
+			verify_aggregated_openings(&kzg, &commitments, index, &values, &proof.proof).unwrap()
+		);
+
+		// A wrong value is detected.
+		let mut wrong = values.clone();
+		wrong[1] = values[0];
+		assert!(
+			!verify_aggregated_openings(&kzg, &commitments, index, &wrong, &proof.proof).unwrap()
+		);
+
+		// So is an opening at another point.
+		assert!(!verify_aggregated_openings(&kzg, &commitments, index + 1, &values, &proof.proof)
+			.unwrap());
+
+		assert!(aggregate_openings(&commitments, index, &values[1..], &proofs).is_err());
+	}
+
+	#[test]
+	fn test_trusted_setup_hash() {
+		let hash = embedded_trusted_setup_hash();
+		assert_eq!(hash, trusted_setup_hash(EMBEDDED_KZG_SETTINGS_BYTES));
+
+		let mut bytes = EMBEDDED_KZG_SETTINGS_BYTES.to_vec();
+		bytes[0] ^= 1;
+		assert_ne!(trusted_setup_hash(&bytes), hash);
+	}
+}
@@ -0,0 +1,362 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A pool of long-running threads for the KZG computations of the node.
+//!
+//! Loading the trusted setup and building the FFT and FK20 settings takes longer than most of the
+//! computations using them. [`KzgWorkerPool`] loads them once for threads that live as long as the
+//! node, and the services of the node hand their commitments, proofs and verifications over to it
+//! instead of running them on the async runtime. Jobs wait in a bounded queue, submitting a job
+//! waits while the queue is full.
+use crate::{
+	config::{FIELD_ELEMENTS_PER_BLOB, FIELD_ELEMENTS_PER_SEGMENT},
+	SegmentCheck, SidecarMetadata,
+};
+use futures::{
+	channel::{mpsc, oneshot},
+	future::try_join_all,
+	SinkExt, StreamExt,
+};
+use kzg_traits::FK20MultiSettings;
+use melo_das_primitives::{crypto::ReprConvert, Blob, KZGCommitment, KZGProof, Polynomial, KZG};
+use melo_erasure_coding::bytes_to_blobs;
+use rust_kzg_blst::types::fk20_multi_settings::FsFK20MultiSettings;
+use std::{
+	collections::{hash_map::Entry, HashMap},
+	panic::{self, AssertUnwindSafe},
+	sync::{Arc, Mutex},
+	thread,
+};
+
+/// The default number of jobs waiting for a worker.
+pub const DEFAULT_KZG_QUEUE_SIZE: usize = 64;
+
+/// A job run by a worker of the pool.
+type Job = Box<dyn FnOnce(&mut KzgWorker) + Send>;
+
+/// The state of a worker thread: the KZG settings shared by the pool and the FK20 settings the
+/// worker built.
+pub struct KzgWorker {
+	kzg: Arc<KZG>,
+	fk20: HashMap<(usize, usize), FsFK20MultiSettings>,
+}
+
+impl KzgWorker {
+	fn new(kzg: Arc<KZG>) -> Self {
+		Self { kzg, fk20: HashMap::new() }
+	}
+
+	/// Returns the KZG settings of the pool.
+	pub fn kzg(&self) -> &KZG {
+		&self.kzg
+	}
+
+	/// Computes the proofs of all the segments of `chunk_size` field elements of the extended
+	/// `poly`, building the FK20 settings of its size the first time.
+	pub fn segment_proofs(
+		&mut self,
+		poly: &Polynomial,
+		chunk_size: usize,
+	) -> Result<Vec<KZGProof>, String> {
+		let n2 = poly.checked()?.0.coeffs.len() * 2;
+		let fk = match self.fk20.entry((n2, chunk_size)) {
+			Entry::Occupied(entry) => entry.into_mut(),
+			Entry::Vacant(entry) =>
+				entry.insert(FsFK20MultiSettings::new(&self.kzg.ks, n2, chunk_size)?),
+		};
+		fk.data_availability(&poly.0).map(KZGProof::vec_from_repr)
+	}
+}
+
+/// A pool of threads doing KZG computations, fed through a bounded queue.
+///
+/// Cloning the pool shares its threads and queue. The threads stop once every clone is dropped.
+#[derive(Clone)]
+pub struct KzgWorkerPool {
+	jobs: mpsc::Sender<Job>,
+	workers: usize,
+}
+
+impl KzgWorkerPool {
+	/// Starts `workers` threads with the embedded KZG settings, and a queue of `queue_size` jobs.
+	pub fn new(workers: usize, queue_size: usize) -> Result<Self, String> {
+		Self::with_kzg(Arc::new(KZG::default_embedded()), workers, queue_size)
+	}
+
+	/// Starts `workers` threads sharing `kzg`, and a queue of `queue_size` jobs.
+	///
+	/// Each worker builds the FK20 settings of the segments of a blob before taking its first job.
+	pub fn with_kzg(kzg: Arc<KZG>, workers: usize, queue_size: usize) -> Result<Self, String> {
+		let workers = workers.max(1);
+		let (jobs, receiver) = mpsc::channel::<Job>(queue_size);
+		let receiver = Arc::new(Mutex::new(receiver));
+
+		for index in 0..workers {
+			let receiver = receiver.clone();
+			let mut worker = KzgWorker::new(kzg.clone());
+			thread::Builder::new()
+				.name(format!("kzg-worker-{}", index))
+				.spawn(move || {
+					let blob_poly = Polynomial::new(FIELD_ELEMENTS_PER_BLOB);
+					if let Err(e) = blob_poly.and_then(|poly| {
+						worker.segment_proofs(&poly, FIELD_ELEMENTS_PER_SEGMENT).map(|_| ())
+					}) {
+						log::warn!("⚠️ Failed to build the FK20 settings of a blob: {}", e);
+					}
+
+					loop {
+						let job = {
+							let mut receiver = receiver.lock().expect("Lock is not poisoned; qed");
+							futures::executor::block_on(receiver.next())
+						};
+						let job = match job {
+							Some(job) => job,
+							None => break,
+						};
+						// A failed job drops its reply, the worker goes on with the next one.
+						if panic::catch_unwind(AssertUnwindSafe(|| job(&mut worker))).is_err() {
+							log::warn!("⚠️ A KZG job panicked");
+						}
+					}
+				})
+				.map_err(|e| format!("Failed to start a KZG worker: {}", e))?;
+		}
+
+		Ok(Self { jobs, workers })
+	}
+
+	/// Returns the number of threads of the pool.
+	pub fn workers(&self) -> usize {
+		self.workers
+	}
+
+	/// Runs `job` on a worker of the pool, waiting for a place in the queue.
+	pub async fn run<R, F>(&self, job: F) -> Result<R, String>
+	where
+		F: FnOnce(&mut KzgWorker) -> R + Send + 'static,
+		R: Send + 'static,
+	{
+		let (reply, result) = oneshot::channel();
+		let job: Job = Box::new(move |worker| {
+			let _ = reply.send(job(worker));
+		});
+		self.jobs
+			.clone()
+			.send(job)
+			.await
+			.map_err(|_| "The KZG worker pool is stopped".to_string())?;
+		result.await.map_err(|_| "The KZG job failed".to_string())
+	}
+
+	/// Splits `items` in one share per worker and runs `job` on each share, returning the results
+	/// in the order of the shares.
+	async fn run_split<T, R, F>(&self, items: Vec<T>, job: F) -> Result<Vec<R>, String>
+	where
+		T: Send + 'static,
+		F: Fn(&KzgWorker, Vec<T>) -> Result<R, String> + Clone + Send + 'static,
+		R: Send + 'static,
+	{
+		let share = ((items.len() + self.workers - 1) / self.workers).max(1);
+		let mut items = items.into_iter().peekable();
+		let mut shares = Vec::new();
+		while items.peek().is_some() {
+			let items = items.by_ref().take(share).collect::<Vec<_>>();
+			let job = job.clone();
+			shares.push(async move { self.run(move |worker| job(worker, items)).await? });
+		}
+		try_join_all(shares).await
+	}
+
+	/// Computes the commitments and proofs of `blobs`.
+	pub async fn commit_and_prove(
+		&self,
+		blobs: Vec<Blob>,
+	) -> Result<(Vec<KZGCommitment>, Vec<KZGProof>), String> {
+		let shares = self
+			.run_split(blobs, |worker, blobs| {
+				blobs
+					.iter()
+					.map(|blob| blob.commit_and_proof(worker.kzg(), FIELD_ELEMENTS_PER_BLOB))
+					.collect::<Result<Vec<_>, _>>()
+			})
+			.await?;
+		Ok(shares.into_iter().flatten().unzip())
+	}
+
+	/// Computes the metadata of the sidecar of `bytes`, with a proof per blob or, if
+	/// `batch_proof`, a single proof of all the blobs.
+	pub async fn sidecar_metadata(
+		&self,
+		bytes: Vec<u8>,
+		app_id: u32,
+		nonce: u32,
+		batch_proof: bool,
+	) -> Result<SidecarMetadata, String> {
+		let bytes_len = bytes.len() as u32;
+		let blobs = bytes_to_blobs(&bytes, FIELD_ELEMENTS_PER_BLOB)?;
+		let (commitments, proofs) = if batch_proof {
+			let shares = self
+				.run_split(blobs.clone(), |worker, blobs| {
+					blobs
+						.iter()
+						.map(|blob| blob.commit(worker.kzg()))
+						.collect::<Result<Vec<_>, _>>()
+				})
+				.await?;
+			let commitments = shares.into_iter().flatten().collect::<Vec<_>>();
+			let batch_commitments = commitments.clone();
+			let proof = self
+				.run(move |worker| {
+					Blob::compute_blobs_proof_batch(
+						&blobs,
+						&batch_commitments,
+						worker.kzg(),
+						FIELD_ELEMENTS_PER_BLOB,
+					)
+				})
+				.await??;
+			(commitments, vec![proof])
+		} else {
+			self.commit_and_prove(blobs).await?
+		};
+		Ok(SidecarMetadata::new(app_id, bytes_len, nonce, commitments, proofs))
+	}
+
+	/// Verifies `bytes` against the commitments and proofs of `metadata`, as
+	/// [`SidecarMetadata::verify_bytes`] does.
+	pub async fn verify_sidecar(
+		&self,
+		metadata: SidecarMetadata,
+		bytes: Vec<u8>,
+	) -> Result<bool, String> {
+		let blobs = bytes_to_blobs(&bytes, FIELD_ELEMENTS_PER_BLOB)?;
+		if metadata.has_batch_proof() {
+			return self
+				.run(move |worker| {
+					Blob::verify_blobs_proof_batch(
+						&blobs,
+						&metadata.commitments,
+						&metadata.proofs[0],
+						worker.kzg(),
+						FIELD_ELEMENTS_PER_BLOB,
+					)
+				})
+				.await?
+		}
+
+		if blobs.is_empty() ||
+			blobs.len() != metadata.commitments.len() ||
+			blobs.len() != metadata.proofs.len()
+		{
+			return Err(format!(
+				"Invalid input length. Expected {} got commitments: {} and proofs: {}",
+				blobs.len(),
+				metadata.commitments.len(),
+				metadata.proofs.len()
+			))
+		}
+		let items = blobs
+			.into_iter()
+			.zip(metadata.commitments)
+			.zip(metadata.proofs)
+			.map(|((blob, commitment), proof)| (blob, commitment, proof))
+			.collect::<Vec<_>>();
+		let shares = self
+			.run_split(items, |worker, items| {
+				let (blobs, (commitments, proofs)): (Vec<_>, (Vec<_>, Vec<_>)) = items
+					.into_iter()
+					.map(|(blob, commitment, proof)| (blob, (commitment, proof)))
+					.unzip();
+				Blob::verify_batch(
+					&blobs,
+					&commitments,
+					&proofs,
+					worker.kzg(),
+					FIELD_ELEMENTS_PER_BLOB,
+				)
+			})
+			.await?;
+		Ok(shares.into_iter().all(|verified| verified))
+	}
+
+	/// Verifies the multi-proofs of `checks`.
+	///
+	/// Returns `true` only if every proof is valid.
+	pub async fn verify_segments(&self, checks: Vec<SegmentCheck>) -> Result<bool, String> {
+		let shares = self
+			.run_split(checks, |worker, checks| {
+				Ok(checks.iter().all(|check| check.verify(worker.kzg())))
+			})
+			.await?;
+		Ok(shares.into_iter().all(|verified| verified))
+	}
+
+	/// Computes the proofs of all the segments of `chunk_size` field elements of the extended
+	/// `poly`.
+	pub async fn segment_proofs(
+		&self,
+		poly: Polynomial,
+		chunk_size: usize,
+	) -> Result<Vec<KZGProof>, String> {
+		self.run(move |worker| worker.segment_proofs(&poly, chunk_size)).await?
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::executor::block_on;
+	use melo_das_primitives::config::BYTES_PER_BLOB;
+
+	#[test]
+	fn test_sidecar_metadata() {
+		let pool = KzgWorkerPool::new(2, 4).unwrap();
+		let data = (0..BYTES_PER_BLOB * 3).map(|i| i as u8).collect::<Vec<_>>();
+
+		let metadata = block_on(pool.sidecar_metadata(data.clone(), 1, 1, false)).unwrap();
+		assert_eq!(metadata, SidecarMetadata::try_from_app_data(&data, 1, 1).unwrap());
+		assert_eq!(block_on(pool.verify_sidecar(metadata.clone(), data.clone())), Ok(true));
+
+		let batch = block_on(pool.sidecar_metadata(data.clone(), 1, 1, true)).unwrap();
+		assert_eq!(
+			batch,
+			SidecarMetadata::try_from_app_data_with_batch_proof(&data, 1, 1).unwrap()
+		);
+		assert_eq!(block_on(pool.verify_sidecar(batch.clone(), data.clone())), Ok(true));
+
+		let mut tampered = data;
+		tampered[0] ^= 1;
+		assert_eq!(block_on(pool.verify_sidecar(metadata, tampered.clone())), Ok(false));
+		assert_eq!(block_on(pool.verify_sidecar(batch, tampered)), Ok(false));
+	}
+
+	#[test]
+	fn test_segment_proofs() {
+		let kzg = Arc::new(KZG::default_embedded());
+		let pool = KzgWorkerPool::with_kzg(kzg.clone(), 1, 1).unwrap();
+		let poly = Blob::try_from_bytes_pad(&[7u8; 1000], BYTES_PER_BLOB).unwrap().to_poly();
+
+		let proofs = block_on(pool.segment_proofs(poly.clone(), FIELD_ELEMENTS_PER_SEGMENT));
+		assert_eq!(proofs, kzg.all_proofs(&poly, FIELD_ELEMENTS_PER_SEGMENT));
+	}
+
+	#[test]
+	fn test_failed_job() {
+		let pool = KzgWorkerPool::new(1, 1).unwrap();
+		let failed: Result<(), String> = block_on(pool.run(|_| panic!("Failed job")));
+		assert!(failed.is_err());
+		// The worker takes the next jobs
+		assert_eq!(block_on(pool.run(|worker| worker.kzg().max_width() > 0)), Ok(true));
+	}
+}
@@ -31,8 +31,36 @@ pub use header::*;
 pub mod sidecar;
 pub use sidecar::*;
 
+pub mod das_params;
+pub use das_params::*;
+
+pub mod kzg;
+pub use kzg::*;
+
+#[cfg(feature = "std")]
+pub mod kzg_pool;
+#[cfg(feature = "std")]
+pub use kzg_pool::{KzgWorker, KzgWorkerPool, DEFAULT_KZG_QUEUE_SIZE};
+
+pub mod compression;
+pub use compression::*;
+
+pub mod encryption;
+pub use encryption::{Encryption, EncryptionKey};
+
+pub mod plot_hashing;
+pub use plot_hashing::PlotHashing;
+
 pub mod config;
+pub mod inclusion;
+#[cfg(feature = "interop")]
+pub mod interop;
+pub mod namespace;
+pub mod padding;
+pub mod receipt;
 pub mod reliability;
+pub mod serving;
+pub mod storage_proof;
 pub mod traits;
 
 #[cfg(feature = "std")]
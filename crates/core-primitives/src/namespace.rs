@@ -0,0 +1,419 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Namespaced rows.
+//!
+//! Each row of a block is tagged with the [`NamespaceId`] of the app that submitted it. The rows
+//! are sorted by namespace, keeping their order within a namespace, and folded into a namespaced
+//! Merkle tree whose nodes carry the smallest and largest namespaces below them. Its root is
+//! deposited into the block header digest, under [`MELO_NAMESPACE_ENGINE_ID`].
+//!
+//! A [`NamespaceProof`] then shows that some rows are all the rows of an app in the block: the
+//! nodes left of them only hold smaller namespaces, and the nodes right of them larger ones. An
+//! empty proof shows that the app has no row in the block.
+
+use crate::{AppLookup, Decode, Digest, Encode, KZGCommitment, TypeInfo, Vec};
+use codec::MaxEncodedLen;
+use core::slice;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use sp_core::{RuntimeDebug, H256};
+use sp_io::hashing::blake2_256;
+use sp_runtime::{ConsensusEngineId, DigestItem};
+
+/// The engine id of the digest item holding the namespace root.
+pub const MELO_NAMESPACE_ENGINE_ID: ConsensusEngineId = *b"MELN";
+
+// Domain separation prefixes, so that an inner node can never be presented as a leaf.
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+/// The namespace of the rows of an app.
+#[derive(
+	Encode,
+	Decode,
+	Clone,
+	Copy,
+	Default,
+	PartialEq,
+	Eq,
+	PartialOrd,
+	Ord,
+	RuntimeDebug,
+	TypeInfo,
+	MaxEncodedLen,
+)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NamespaceId(pub u32);
+
+impl NamespaceId {
+	/// Returns the namespace of the rows of `app_id`.
+	pub fn from_app_id(app_id: u32) -> Self {
+		Self(app_id)
+	}
+
+	/// Returns the bytes of the namespace, ordered as the namespaces are.
+	pub fn to_bytes(&self) -> [u8; 4] {
+		self.0.to_be_bytes()
+	}
+}
+
+/// A node of the namespaced Merkle tree.
+#[derive(
+	Encode, Decode, Clone, Copy, Default, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen,
+)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NamespacedNode {
+	/// The smallest namespace below the node.
+	pub min: NamespaceId,
+	/// The largest namespace below the node.
+	pub max: NamespaceId,
+	/// The hash of the node.
+	pub hash: H256,
+}
+
+impl NamespacedNode {
+	/// Returns the leaf of the row at `row`, committed to by `commitment`.
+	pub fn leaf(namespace: NamespaceId, row: u32, commitment: &KZGCommitment) -> Self {
+		let mut buffer = Vec::with_capacity(1 + 4 + 4 + KZGCommitment::size());
+		buffer.push(LEAF_PREFIX);
+		buffer.extend_from_slice(&namespace.to_bytes());
+		buffer.extend_from_slice(&row.to_be_bytes());
+		buffer.extend_from_slice(&commitment.to_bytes());
+		Self { min: namespace, max: namespace, hash: blake2_256(&buffer).into() }
+	}
+
+	/// Combines two nodes, returning `None` if the namespaces of `left` are not all smaller than or
+	/// equal to the ones of `right`.
+	pub fn combine(left: &Self, right: &Self) -> Option<Self> {
+		if left.max > right.min {
+			return None
+		}
+
+		let mut buffer = Vec::with_capacity(1 + 2 * (4 + 4 + 32));
+		buffer.push(NODE_PREFIX);
+		for node in [left, right] {
+			buffer.extend_from_slice(&node.min.to_bytes());
+			buffer.extend_from_slice(&node.max.to_bytes());
+			buffer.extend_from_slice(node.hash.as_bytes());
+		}
+		Some(Self { min: left.min, max: right.max, hash: blake2_256(&buffer).into() })
+	}
+
+	/// Converts the node into a digest item.
+	pub fn to_digest_item(&self) -> DigestItem {
+		DigestItem::Consensus(MELO_NAMESPACE_ENGINE_ID, self.encode())
+	}
+
+	/// Decodes the namespace root from the first matching item in `digest`, if any.
+	pub fn from_digest(digest: &Digest) -> Option<Self> {
+		digest.convert_first(|item| item.consensus_try_to::<Self>(&MELO_NAMESPACE_ENGINE_ID))
+	}
+}
+
+/// A row tagged with its namespace.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NamespacedRow {
+	/// The namespace of the row.
+	pub namespace: NamespaceId,
+	/// The index of the row in the block.
+	#[codec(compact)]
+	pub row: u32,
+	/// The commitment of the row.
+	pub commitment: KZGCommitment,
+}
+
+impl NamespacedRow {
+	/// Returns the leaf of the row.
+	pub fn leaf(&self) -> NamespacedNode {
+		NamespacedNode::leaf(self.namespace, self.row, &self.commitment)
+	}
+}
+
+/// Tags the rows of a block with the namespaces of their apps, and sorts them by namespace.
+///
+/// Rows beyond the lookups are left out.
+pub fn namespaced_rows(
+	commitments: &[KZGCommitment],
+	app_lookup: &[AppLookup],
+) -> Vec<NamespacedRow> {
+	let mut rows = commitments
+		.iter()
+		.enumerate()
+		.filter_map(|(row, commitment)| {
+			AppLookup::get_lookup(app_lookup, row as u32).map(|(lookup, _)| NamespacedRow {
+				namespace: NamespaceId::from_app_id(lookup.app_id),
+				row: row as u32,
+				commitment: *commitment,
+			})
+		})
+		.collect::<Vec<_>>();
+	// The sort is stable, so the rows of a namespace stay in the order of the block.
+	rows.sort_by_key(|row| row.namespace);
+	rows
+}
+
+// The tree splits its leaves at the largest power of two smaller than their number, which yields
+// the same tree as pairing the nodes level by level and promoting an unpaired node.
+fn split_point(len: usize) -> usize {
+	let mut k = 1;
+	while k * 2 < len {
+		k *= 2;
+	}
+	k
+}
+
+fn root_of(leaves: &[NamespacedNode]) -> NamespacedNode {
+	match leaves {
+		[] => NamespacedNode::default(),
+		[leaf] => *leaf,
+		_ => {
+			let (left, right) = leaves.split_at(split_point(leaves.len()));
+			NamespacedNode::combine(&root_of(left), &root_of(right))
+				.expect("Leaves are sorted by namespace; qed")
+		},
+	}
+}
+
+/// Computes the namespace root of the rows of a block.
+///
+/// Returns the default node if there are no rows.
+pub fn namespace_root(commitments: &[KZGCommitment], app_lookup: &[AppLookup]) -> NamespacedNode {
+	let leaves = namespaced_rows(commitments, app_lookup)
+		.iter()
+		.map(NamespacedRow::leaf)
+		.collect::<Vec<_>>();
+	root_of(&leaves)
+}
+
+/// A proof that some rows are all the rows of a namespace in a block.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NamespaceProof {
+	/// The position of the first row of the namespace among the sorted rows, or where it would be
+	/// if the namespace has no row.
+	#[codec(compact)]
+	pub start: u32,
+	/// The total number of rows in the block.
+	#[codec(compact)]
+	pub leaf_count: u32,
+	/// The roots of the subtrees left and right of the rows, from left to right.
+	pub siblings: Vec<NamespacedNode>,
+}
+
+impl NamespaceProof {
+	/// Generates a proof for the rows of `namespace` in a block.
+	///
+	/// Returns the rows of the namespace along with their proof.
+	pub fn generate(
+		commitments: &[KZGCommitment],
+		app_lookup: &[AppLookup],
+		namespace: NamespaceId,
+	) -> (Vec<NamespacedRow>, Self) {
+		let rows = namespaced_rows(commitments, app_lookup);
+		let leaves = rows.iter().map(NamespacedRow::leaf).collect::<Vec<_>>();
+		let start = rows.partition_point(|row| row.namespace < namespace);
+		let end = rows.partition_point(|row| row.namespace <= namespace);
+
+		let mut siblings = Vec::new();
+		if !leaves.is_empty() {
+			collect_siblings(&leaves, 0, start, end, &mut siblings);
+		}
+
+		let proof = Self { start: start as u32, leaf_count: leaves.len() as u32, siblings };
+		(rows[start..end].to_vec(), proof)
+	}
+
+	/// Verifies that `rows` are all the rows of `namespace` under `root`.
+	pub fn verify(
+		&self,
+		root: &NamespacedNode,
+		namespace: NamespaceId,
+		rows: &[NamespacedRow],
+	) -> bool {
+		if rows.iter().any(|row| row.namespace != namespace) ||
+			rows.windows(2).any(|pair| pair[0].row >= pair[1].row)
+		{
+			return false
+		}
+
+		let start = self.start as usize;
+		let end = start + rows.len();
+		let leaf_count = self.leaf_count as usize;
+		if end > leaf_count {
+			return false
+		}
+		if leaf_count == 0 {
+			return self.siblings.is_empty() && *root == NamespacedNode::default()
+		}
+
+		let leaves = rows.iter().map(NamespacedRow::leaf).collect::<Vec<_>>();
+		let mut siblings = self.siblings.iter();
+		let computed =
+			RangeVerifier { namespace, start, end, leaves: &leaves, siblings: &mut siblings }
+				.root(0, leaf_count);
+
+		// All siblings must be consumed.
+		computed.as_ref() == Some(root) && siblings.next().is_none()
+	}
+}
+
+fn collect_siblings(
+	leaves: &[NamespacedNode],
+	offset: usize,
+	start: usize,
+	end: usize,
+	siblings: &mut Vec<NamespacedNode>,
+) {
+	let (lo, hi) = (offset, offset + leaves.len());
+	if hi <= start || lo >= end {
+		siblings.push(root_of(leaves));
+	} else if lo < start || hi > end {
+		let (left, right) = leaves.split_at(split_point(leaves.len()));
+		collect_siblings(left, lo, start, end, siblings);
+		collect_siblings(right, lo + left.len(), start, end, siblings);
+	}
+}
+
+// Recomputes the root from the rows of the namespace and the siblings of a proof, checking that
+// the siblings hold no row of the namespace.
+struct RangeVerifier<'a, 'b> {
+	namespace: NamespaceId,
+	start: usize,
+	end: usize,
+	leaves: &'a [NamespacedNode],
+	siblings: &'a mut slice::Iter<'b, NamespacedNode>,
+}
+
+impl RangeVerifier<'_, '_> {
+	fn root(&mut self, lo: usize, hi: usize) -> Option<NamespacedNode> {
+		if hi <= self.start {
+			let node = self.siblings.next()?;
+			return (node.max < self.namespace).then_some(*node)
+		}
+		if lo >= self.end {
+			let node = self.siblings.next()?;
+			return (node.min > self.namespace).then_some(*node)
+		}
+		if lo >= self.start && hi <= self.end {
+			return Some(root_of(&self.leaves[lo - self.start..hi - self.start]))
+		}
+
+		let mid = lo + split_point(hi - lo);
+		let left = self.root(lo, mid)?;
+		let right = self.root(mid, hi)?;
+		NamespacedNode::combine(&left, &right)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::SidecarMetadata;
+
+	fn commitment(i: u8) -> KZGCommitment {
+		SidecarMetadata::try_from_app_data(&[i + 1; 10], 0, 0).unwrap().commitments[0]
+	}
+
+	fn block() -> (Vec<KZGCommitment>, Vec<AppLookup>) {
+		let app_lookup = vec![
+			AppLookup { app_id: 3, nonce: 1, count: 2 },
+			AppLookup { app_id: 1, nonce: 1, count: 1 },
+			AppLookup { app_id: 3, nonce: 2, count: 1 },
+			AppLookup { app_id: 2, nonce: 1, count: 3 },
+			AppLookup { app_id: 7, nonce: 1, count: 1 },
+		];
+		((0..8).map(commitment).collect(), app_lookup)
+	}
+
+	#[test]
+	fn test_namespaced_rows_are_sorted() {
+		let (commitments, app_lookup) = block();
+		let rows = namespaced_rows(&commitments, &app_lookup);
+		assert_eq!(
+			rows.iter().map(|row| (row.namespace.0, row.row)).collect::<Vec<_>>(),
+			vec![(1, 2), (2, 4), (2, 5), (2, 6), (3, 0), (3, 1), (3, 3), (7, 7)]
+		);
+
+		let root = namespace_root(&commitments, &app_lookup);
+		assert_eq!((root.min.0, root.max.0), (1, 7));
+		assert_eq!(namespace_root(&[], &[]), NamespacedNode::default());
+	}
+
+	#[test]
+	fn test_namespace_proof_round_trip() {
+		let (commitments, app_lookup) = block();
+		for len in 1..=commitments.len() {
+			let commitments = &commitments[..len];
+			let root = namespace_root(commitments, &app_lookup);
+			for app_id in 0..9 {
+				let namespace = NamespaceId::from_app_id(app_id);
+				let (rows, proof) = NamespaceProof::generate(commitments, &app_lookup, namespace);
+				assert!(
+					rows.iter().map(|row| row.row).eq(AppLookup::row_ranges(&app_lookup, app_id)
+						.into_iter()
+						.flatten()
+						.filter(|row| (*row as usize) < len)),
+					"len = {}, app_id = {}",
+					len,
+					app_id
+				);
+				assert!(
+					proof.verify(&root, namespace, &rows),
+					"len = {}, app_id = {}",
+					len,
+					app_id
+				);
+			}
+		}
+
+		let (rows, proof) = NamespaceProof::generate(&[], &[], NamespaceId(1));
+		assert!(rows.is_empty());
+		assert!(proof.verify(&NamespacedNode::default(), NamespaceId(1), &rows));
+	}
+
+	#[test]
+	fn test_namespace_proof_rejects_incomplete_rows() {
+		let (commitments, app_lookup) = block();
+		let root = namespace_root(&commitments, &app_lookup);
+		let namespace = NamespaceId(3);
+		let (rows, proof) = NamespaceProof::generate(&commitments, &app_lookup, namespace);
+		assert_eq!(rows.len(), 3);
+
+		// A row left out
+		assert!(!proof.verify(&root, namespace, &rows[..2]));
+		let mut shifted = proof.clone();
+		shifted.start += 1;
+		assert!(!shifted.verify(&root, namespace, &rows[1..]));
+
+		// A row of another namespace
+		let mut other = rows.clone();
+		other[0].namespace = NamespaceId(2);
+		assert!(!proof.verify(&root, namespace, &other));
+
+		// A wrong commitment
+		let mut wrong = rows.clone();
+		wrong[1].commitment = commitment(9);
+		assert!(!proof.verify(&root, namespace, &wrong));
+
+		// An absence proof of a namespace with rows
+		let (_, absent) = NamespaceProof::generate(&commitments, &app_lookup, NamespaceId(4));
+		assert!(!absent.verify(&root, namespace, &[]));
+
+		let mut extra_sibling = proof;
+		extra_sibling.siblings.push(NamespacedNode::default());
+		assert!(!extra_sibling.verify(&root, namespace, &rows));
+	}
+}
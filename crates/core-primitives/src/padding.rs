@@ -0,0 +1,119 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Padding of application data.
+//!
+//! Data is converted to blobs by filling their field elements and padding the last one with
+//! zeros, so that trailing zeros of the data can not be told apart from the padding. Data framed
+//! with [`pad`] carries its length and a terminator, and [`unpad`] returns exactly the original
+//! bytes from the bytes of its blobs, checking that all the bytes after the terminator are zero.
+use crate::Vec;
+use sp_core::RuntimeDebug;
+
+/// The length of the little-endian `u32` prefix holding the length of the data.
+pub const LENGTH_PREFIX_BYTES: usize = 4;
+
+/// The byte following the data.
+pub const PADDING_TERMINATOR: u8 = 0x80;
+
+/// The reasons padded data is rejected by [`unpad`].
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum PaddingError {
+	/// The data is shorter than its length prefix and terminator.
+	TooShort,
+	/// The byte following the data is not [`PADDING_TERMINATOR`].
+	MissingTerminator,
+	/// A byte after the terminator is not zero.
+	NonZeroPadding,
+}
+
+/// Returns the length of `len` bytes of data framed by [`pad`].
+pub const fn padded_len(len: usize) -> usize {
+	LENGTH_PREFIX_BYTES + len + 1
+}
+
+/// Frames `data` with its length and a terminator.
+///
+/// # Panics
+/// If `data` is longer than `u32::MAX` bytes.
+pub fn pad(data: &[u8]) -> Vec<u8> {
+	let len = u32::try_from(data.len()).expect("Data is longer than u32::MAX bytes");
+	let mut padded = Vec::with_capacity(padded_len(data.len()));
+	padded.extend_from_slice(&len.to_le_bytes());
+	padded.extend_from_slice(data);
+	padded.push(PADDING_TERMINATOR);
+	padded
+}
+
+/// Returns the data framed by [`pad`] in `padded`, which may be followed by zeros, e.g. when it
+/// is the bytes of whole blobs.
+pub fn unpad(padded: &[u8]) -> Result<&[u8], PaddingError> {
+	if padded.len() < padded_len(0) {
+		return Err(PaddingError::TooShort)
+	}
+	let mut prefix = [0u8; LENGTH_PREFIX_BYTES];
+	prefix.copy_from_slice(&padded[..LENGTH_PREFIX_BYTES]);
+	let len = u32::from_le_bytes(prefix) as usize;
+
+	let end = match LENGTH_PREFIX_BYTES.checked_add(len) {
+		Some(end) if end < padded.len() => end,
+		_ => return Err(PaddingError::TooShort),
+	};
+	if padded[end] != PADDING_TERMINATOR {
+		return Err(PaddingError::MissingTerminator)
+	}
+	if padded[end + 1..].iter().any(|byte| *byte != 0) {
+		return Err(PaddingError::NonZeroPadding)
+	}
+	Ok(&padded[LENGTH_PREFIX_BYTES..end])
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::vec;
+	use melo_das_primitives::config::FIELD_ELEMENTS_PER_BLOB;
+	use melo_erasure_coding::bytes_to_blobs;
+
+	#[test]
+	fn test_pad_round_trip() {
+		for data in
+			[vec![], vec![0u8; 31], vec![1u8, 0, 0], (0..=255u8).cycle().take(100).collect()]
+		{
+			let mut padded = pad(&data);
+			assert_eq!(padded.len(), padded_len(data.len()));
+			assert_eq!(unpad(&padded), Ok(&data[..]));
+
+			padded.resize(padded.len() + 40, 0);
+			assert_eq!(unpad(&padded), Ok(&data[..]));
+		}
+	}
+
+	#[test]
+	fn test_pad_round_trip_through_blobs() {
+		let data = vec![7u8, 0, 0, 0];
+		let blobs = bytes_to_blobs(&pad(&data), FIELD_ELEMENTS_PER_BLOB).unwrap();
+		let bytes: Vec<u8> = blobs.iter().flat_map(|blob| blob.to_bytes()).collect();
+		assert_eq!(unpad(&bytes), Ok(&data[..]));
+	}
+
+	#[test]
+	fn test_unpad_rejects_invalid_frames() {
+		assert_eq!(unpad(&[1, 0, 0]), Err(PaddingError::TooShort));
+		assert_eq!(unpad(&[2, 0, 0, 0, 1, 0x80]), Err(PaddingError::TooShort));
+		assert_eq!(unpad(&[u8::MAX; 8]), Err(PaddingError::TooShort));
+		assert_eq!(unpad(&[1, 0, 0, 0, 1, 0]), Err(PaddingError::MissingTerminator));
+		assert_eq!(unpad(&[1, 0, 0, 0, 1, 0x80, 0, 1]), Err(PaddingError::NonZeroPadding));
+	}
+}
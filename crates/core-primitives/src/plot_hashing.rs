@@ -0,0 +1,80 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The hashing schemes of the plots of farmers.
+//!
+//! The scheme hashes the account of a farmer into its farmer ID, and the cells of a plot into
+//! their Y and Z values. It is chosen by governance, and changing it invalidates every plot made
+//! with the previous scheme.
+
+use crate::TypeInfo;
+use codec::{Decode, Encode, MaxEncodedLen};
+use core::str::FromStr;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use sp_core::RuntimeDebug;
+
+/// The hash function of the plots of farmers.
+#[derive(
+	Encode, Decode, Clone, Copy, Default, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen,
+)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum PlotHashing {
+	/// BLAKE2b-256, with the ChaCha8 keystream for Y values and no domain separation. The
+	/// original scheme, kept so that existing plots stay valid.
+	#[default]
+	Blake2,
+	/// BLAKE3, with domain separation tags.
+	Blake3,
+	/// Keccak-256, with domain separation tags.
+	Keccak,
+}
+
+impl PlotHashing {
+	/// Returns the name of the scheme.
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			PlotHashing::Blake2 => "blake2",
+			PlotHashing::Blake3 => "blake3",
+			PlotHashing::Keccak => "keccak",
+		}
+	}
+}
+
+impl FromStr for PlotHashing {
+	type Err = &'static str;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"blake2" => Ok(PlotHashing::Blake2),
+			"blake3" => Ok(PlotHashing::Blake3),
+			"keccak" => Ok(PlotHashing::Keccak),
+			_ => Err("Unknown plot hashing scheme, expected blake2, blake3 or keccak"),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_names_round_trip() {
+		for scheme in [PlotHashing::Blake2, PlotHashing::Blake3, PlotHashing::Keccak] {
+			assert_eq!(scheme.as_str().parse::<PlotHashing>(), Ok(scheme));
+		}
+		assert!("sha256".parse::<PlotHashing>().is_err());
+	}
+}
@@ -0,0 +1,366 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Availability receipts.
+//!
+//! An [`AvailabilityReceipt`] is the availability verdict of a sampling node on a block, signed
+//! with the sr25519 key of the node, of type [`RECEIPT_KEY_TYPE`] in its keystore. It lists the
+//! positions the node fetched, from which anyone can recompute the confidence it claims, so that
+//! another chain, such as the other side of a bridge, can accept the verdicts of the nodes it
+//! trusts without sampling the block itself.
+//!
+//! A [`StorageReceipt`] is the acknowledgement of a member of a data availability committee that
+//! it stores the data of a sidecar, signed with its key of type [`STORAGE_RECEIPT_KEY_TYPE`].
+//! Nodes mirroring the sidecars they receive to a committee report the receipts of its members,
+//! a fallback while the DHT holds few copies of the data. The members only store the sidecars
+//! sent with a [`MirrorAuthorization`], signed by one of the nodes they allow with its key of type
+//! [`MIRROR_KEY_TYPE`].
+
+use crate::{
+	reliability::{calculate_confidence, Reliability, BLOCK_FAILURE_PROBABILITY},
+	Decode, Encode, TypeInfo, Vec,
+};
+use melo_das_primitives::Position;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use sp_core::{crypto::KeyTypeId, sr25519, RuntimeDebug, H256, H512};
+use sp_io::{crypto::sr25519_verify, hashing::blake2_256};
+
+/// The key type of the keys signing receipts in the keystore of a node.
+pub const RECEIPT_KEY_TYPE: KeyTypeId = KeyTypeId(*b"dasr");
+
+/// The domain separation prefix of the signed payload of a receipt.
+pub const RECEIPT_CONTEXT: &[u8] = b"melodot-das-receipt-v1";
+
+/// The key type of the keys signing storage receipts in the keystore of a committee member.
+pub const STORAGE_RECEIPT_KEY_TYPE: KeyTypeId = KeyTypeId(*b"dacr");
+
+/// The domain separation prefix of the signed payload of a storage receipt.
+pub const STORAGE_RECEIPT_CONTEXT: &[u8] = b"melodot-dac-receipt-v1";
+
+/// The key type of the keys signing the sidecars mirrored to a committee in the keystore of a node.
+pub const MIRROR_KEY_TYPE: KeyTypeId = KeyTypeId(*b"dacm");
+
+/// The domain separation prefix of the signed payload of a mirror authorization.
+pub const MIRROR_CONTEXT: &[u8] = b"melodot-dac-mirror-v1";
+
+/// The signed availability verdict of a sampling node on a block.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct AvailabilityReceipt {
+	/// The hash of the block.
+	pub block_hash: H256,
+	/// The confidence of the node in the availability of the block, in parts per million.
+	pub confidence: u32,
+	/// Whether the node considers the data of the block available.
+	pub available: bool,
+	/// The positions of the samples the node fetched.
+	pub positions: Vec<Position>,
+	/// The key of the node.
+	pub signer: sr25519::Public,
+	/// The signature of the node over [`AvailabilityReceipt::payload`].
+	pub signature: sr25519::Signature,
+}
+
+/// The reasons a receipt is rejected.
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum ReceiptError {
+	/// The signature does not match the receipt.
+	BadSignature,
+	/// The confidence does not match the number of positions.
+	ConfidenceMismatch,
+	/// A position is listed more than once.
+	DuplicatePosition,
+	/// The signer is not trusted.
+	UntrustedSigner,
+	/// The confidence is below the required one.
+	ConfidenceTooLow,
+	/// The data is not available.
+	Unavailable,
+}
+
+impl AvailabilityReceipt {
+	/// Returns the hash signed by the node.
+	pub fn payload(&self) -> [u8; 32] {
+		let mut buffer = RECEIPT_CONTEXT.to_vec();
+		(&self.block_hash, self.confidence, self.available, &self.positions, &self.signer)
+			.encode_to(&mut buffer);
+		blake2_256(&buffer)
+	}
+
+	/// Creates the receipt of the block `block_hash` from the `confidence` sampled by the node of
+	/// `signer`, without its signature.
+	pub fn unsigned(block_hash: H256, confidence: &Reliability, signer: sr25519::Public) -> Self {
+		let positions = confidence
+			.samples
+			.iter()
+			.filter(|sample| sample.is_availability)
+			.map(|sample| sample.position.clone())
+			.collect();
+		AvailabilityReceipt {
+			block_hash,
+			confidence: confidence.value().unwrap_or_default(),
+			available: confidence.is_availability(),
+			positions,
+			signer,
+			signature: sr25519::Signature::from_raw([0u8; 64]),
+		}
+	}
+
+	/// Creates the receipt of the block `block_hash` from the `confidence` sampled by the node,
+	/// signed with `pair`.
+	#[cfg(feature = "std")]
+	pub fn sign(block_hash: H256, confidence: &Reliability, pair: &sr25519::Pair) -> Self {
+		use sp_core::Pair;
+
+		let mut receipt = Self::unsigned(block_hash, confidence, pair.public());
+		receipt.signature = pair.sign(&receipt.payload());
+		receipt
+	}
+
+	/// Checks that the receipt is signed by its signer and that its confidence matches the
+	/// distinct positions it lists.
+	pub fn verify(&self) -> Result<(), ReceiptError> {
+		if !sr25519_verify(&self.signature, &self.payload(), &self.signer) {
+			return Err(ReceiptError::BadSignature)
+		}
+		let mut positions: Vec<_> = self.positions.iter().map(|p| (p.x, p.y)).collect();
+		positions.sort_unstable();
+		positions.dedup();
+		if positions.len() != self.positions.len() {
+			return Err(ReceiptError::DuplicatePosition)
+		}
+		let expected = calculate_confidence(self.positions.len() as u32, BLOCK_FAILURE_PROBABILITY);
+		if self.confidence != expected {
+			return Err(ReceiptError::ConfidenceMismatch)
+		}
+		Ok(())
+	}
+
+	/// Checks the receipt with [`AvailabilityReceipt::verify`], and that it is signed by one of
+	/// `trusted` and shows the data available with a confidence of at least `min_confidence`.
+	pub fn verify_verdict(
+		&self,
+		trusted: &[sr25519::Public],
+		min_confidence: u32,
+	) -> Result<(), ReceiptError> {
+		if !trusted.contains(&self.signer) {
+			return Err(ReceiptError::UntrustedSigner)
+		}
+		self.verify()?;
+		if self.confidence < min_confidence {
+			return Err(ReceiptError::ConfidenceTooLow)
+		}
+		if !self.available {
+			return Err(ReceiptError::Unavailable)
+		}
+		Ok(())
+	}
+}
+
+/// The signed acknowledgement of a committee member that it stores the data of a sidecar.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct StorageReceipt {
+	/// The ID of the sidecar metadata.
+	pub data_hash: H256,
+	/// The sr25519 public key of the member.
+	pub signer: H256,
+	/// The sr25519 signature of the member over [`StorageReceipt::payload`].
+	pub signature: H512,
+}
+
+impl StorageReceipt {
+	/// Returns the hash signed by the member.
+	pub fn payload(&self) -> [u8; 32] {
+		let mut buffer = STORAGE_RECEIPT_CONTEXT.to_vec();
+		(&self.data_hash, &self.signer).encode_to(&mut buffer);
+		blake2_256(&buffer)
+	}
+
+	/// Creates the receipt of the sidecar `data_hash` stored by the member of `signer`, without
+	/// its signature.
+	pub fn unsigned(data_hash: H256, signer: sr25519::Public) -> Self {
+		StorageReceipt { data_hash, signer: H256(signer.0), signature: H512::zero() }
+	}
+
+	/// Creates the receipt of the sidecar `data_hash` stored by the member, signed with `pair`.
+	#[cfg(feature = "std")]
+	pub fn sign(data_hash: H256, pair: &sr25519::Pair) -> Self {
+		use sp_core::Pair;
+
+		let mut receipt = Self::unsigned(data_hash, pair.public());
+		receipt.signature = H512(pair.sign(&receipt.payload()).0);
+		receipt
+	}
+
+	/// Checks that the receipt is signed by its signer.
+	pub fn verify(&self) -> Result<(), ReceiptError> {
+		let signature = sr25519::Signature::from_raw(self.signature.0);
+		let signer = sr25519::Public::from_raw(self.signer.0);
+		if !sr25519_verify(&signature, &self.payload(), &signer) {
+			return Err(ReceiptError::BadSignature)
+		}
+		Ok(())
+	}
+}
+
+/// The request of a node to a member of a data availability committee to store a sidecar.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct MirrorAuthorization {
+	/// The ID of the sidecar metadata.
+	pub data_hash: H256,
+	/// The sr25519 public key of the node.
+	pub signer: H256,
+	/// The sr25519 signature of the node over [`MirrorAuthorization::payload`].
+	pub signature: H512,
+}
+
+impl MirrorAuthorization {
+	/// Returns the hash signed by the node.
+	pub fn payload(&self) -> [u8; 32] {
+		let mut buffer = MIRROR_CONTEXT.to_vec();
+		(&self.data_hash, &self.signer).encode_to(&mut buffer);
+		blake2_256(&buffer)
+	}
+
+	/// Creates the request of the node of `signer` to store the sidecar `data_hash`, without its
+	/// signature.
+	pub fn unsigned(data_hash: H256, signer: sr25519::Public) -> Self {
+		MirrorAuthorization { data_hash, signer: H256(signer.0), signature: H512::zero() }
+	}
+
+	/// Creates the request of the node to store the sidecar `data_hash`, signed with `pair`.
+	#[cfg(feature = "std")]
+	pub fn sign(data_hash: H256, pair: &sr25519::Pair) -> Self {
+		use sp_core::Pair;
+
+		let mut authorization = Self::unsigned(data_hash, pair.public());
+		authorization.signature = H512(pair.sign(&authorization.payload()).0);
+		authorization
+	}
+
+	/// Checks that the request is signed by one of `trusted`.
+	pub fn verify(&self, trusted: &[sr25519::Public]) -> Result<(), ReceiptError> {
+		let signer = sr25519::Public::from_raw(self.signer.0);
+		if !trusted.contains(&signer) {
+			return Err(ReceiptError::UntrustedSigner)
+		}
+		let signature = sr25519::Signature::from_raw(self.signature.0);
+		if !sr25519_verify(&signature, &self.payload(), &signer) {
+			return Err(ReceiptError::BadSignature)
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::reliability::{ReliabilityType, Sample};
+	use sp_core::Pair;
+
+	fn sampled(success: usize) -> Reliability {
+		let mut confidence = Reliability::new(ReliabilityType::Block, &[]);
+		confidence.samples = (0..8)
+			.map(|x| Sample {
+				position: Position { x, y: 0 },
+				is_availability: (x as usize) < success,
+				..Default::default()
+			})
+			.collect();
+		confidence
+	}
+
+	#[test]
+	fn test_receipt_verify() {
+		let pair = sr25519::Pair::from_seed(&[1u8; 32]);
+		let receipt = AvailabilityReceipt::sign(H256::repeat_byte(1), &sampled(8), &pair);
+
+		assert_eq!(receipt.positions.len(), 8);
+		assert!(receipt.available);
+		assert_eq!(receipt.verify(), Ok(()));
+		assert_eq!(receipt.verify_verdict(&[pair.public()], receipt.confidence), Ok(()));
+
+		let receipt = AvailabilityReceipt::decode(&mut &receipt.encode()[..]).unwrap();
+		assert_eq!(receipt.verify(), Ok(()));
+
+		let other = sr25519::Pair::from_seed(&[2u8; 32]);
+		assert_eq!(
+			receipt.verify_verdict(&[other.public()], 0),
+			Err(ReceiptError::UntrustedSigner)
+		);
+		assert_eq!(
+			receipt.verify_verdict(&[pair.public()], receipt.confidence + 1),
+			Err(ReceiptError::ConfidenceTooLow)
+		);
+	}
+
+	#[test]
+	fn test_tampered_receipt() {
+		let pair = sr25519::Pair::from_seed(&[1u8; 32]);
+		let receipt = AvailabilityReceipt::sign(H256::repeat_byte(1), &sampled(3), &pair);
+		assert!(!receipt.available);
+		assert_eq!(receipt.verify(), Ok(()));
+		assert_eq!(receipt.verify_verdict(&[pair.public()], 0), Err(ReceiptError::Unavailable));
+
+		let mut tampered = receipt.clone();
+		tampered.block_hash = H256::repeat_byte(2);
+		assert_eq!(tampered.verify(), Err(ReceiptError::BadSignature));
+
+		// A confidence not backed by the positions is rejected, even if signed.
+		let mut inflated = receipt.clone();
+		inflated.confidence += 1;
+		inflated.signature = pair.sign(&inflated.payload());
+		assert_eq!(inflated.verify(), Err(ReceiptError::ConfidenceMismatch));
+
+		// Repeating a position does not count as another sample.
+		let mut repeated = receipt;
+		repeated.positions = vec![repeated.positions[0].clone(); 8];
+		repeated.confidence =
+			calculate_confidence(repeated.positions.len() as u32, BLOCK_FAILURE_PROBABILITY);
+		repeated.signature = pair.sign(&repeated.payload());
+		assert_eq!(repeated.verify(), Err(ReceiptError::DuplicatePosition));
+	}
+
+	#[test]
+	fn test_storage_receipt_verify() {
+		let pair = sr25519::Pair::from_seed(&[1u8; 32]);
+		let receipt = StorageReceipt::sign(H256::repeat_byte(1), &pair);
+		assert_eq!(receipt.signer.0, pair.public().0);
+		assert_eq!(receipt.verify(), Ok(()));
+
+		let receipt = StorageReceipt::decode(&mut &receipt.encode()[..]).unwrap();
+		assert_eq!(receipt.verify(), Ok(()));
+
+		let mut tampered = receipt;
+		tampered.data_hash = H256::repeat_byte(2);
+		assert_eq!(tampered.verify(), Err(ReceiptError::BadSignature));
+	}
+
+	#[test]
+	fn test_mirror_authorization_verify() {
+		let pair = sr25519::Pair::from_seed(&[1u8; 32]);
+		let other = sr25519::Pair::from_seed(&[2u8; 32]);
+		let authorization = MirrorAuthorization::sign(H256::repeat_byte(1), &pair);
+		assert_eq!(authorization.verify(&[other.public(), pair.public()]), Ok(()));
+		assert_eq!(authorization.verify(&[other.public()]), Err(ReceiptError::UntrustedSigner));
+
+		let mut tampered = authorization;
+		tampered.data_hash = H256::repeat_byte(2);
+		assert_eq!(tampered.verify(&[pair.public()]), Err(ReceiptError::BadSignature));
+	}
+}
@@ -19,14 +19,14 @@ pub use sp_arithmetic::Permill;
 use sp_arithmetic::traits::Saturating;
 
 #[cfg(feature = "std")]
-use crate::config::EXTENDED_SEGMENTS_PER_BLOB;
+use crate::config::{Rows, MATRIX};
 #[cfg(feature = "std")]
 use crate::AppLookup;
 use crate::{KZGCommitment, String};
 use alloc::vec::Vec;
 use codec::{Decode, Encode};
 use melo_das_db::traits::DasKv;
-use melo_das_primitives::{Position, Segment, KZG};
+use melo_das_primitives::{Position, Segment, SegmentData, KZG};
 #[cfg(feature = "std")]
 use rand::Rng;
 
@@ -43,6 +43,24 @@ pub const APP_FAILURE_PROBABILITY: Permill = Permill::from_parts(500_000);
 /// The failure probability of the block, this is a permillage
 pub const BLOCK_FAILURE_PROBABILITY: Permill = Permill::from_parts(250_000);
 
+/// The thresholds above which sampled data is considered available.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AvailabilityThresholds {
+	/// The share of the samples of an application that have to be fetched.
+	pub app: Permill,
+	/// The number of consecutive samples of a block that have to be fetched.
+	pub block: u32,
+}
+
+impl Default for AvailabilityThresholds {
+	fn default() -> Self {
+		AvailabilityThresholds {
+			app: APP_AVAILABILITY_THRESHOLD_PERMILL,
+			block: BLOCK_AVAILABILITY_THRESHOLD,
+		}
+	}
+}
+
 /// A trait for setting reliability samples.
 #[cfg(feature = "std")]
 pub trait ReliabilitySample {
@@ -206,10 +224,20 @@ impl ReliabilityType {
 
 	/// Returns whether the reliability type is available given the total count and success count.
 	pub fn is_availability(&self, total_count: u32, success_count: u32) -> bool {
+		self.is_available_with(total_count, success_count, &AvailabilityThresholds::default())
+	}
+
+	/// Returns whether the reliability type is available given the total count and success
+	/// count, against `thresholds`.
+	pub fn is_available_with(
+		&self,
+		total_count: u32,
+		success_count: u32,
+		thresholds: &AvailabilityThresholds,
+	) -> bool {
 		match self {
-			ReliabilityType::App =>
-				success_count > APP_AVAILABILITY_THRESHOLD_PERMILL.mul_floor(total_count),
-			ReliabilityType::Block => success_count >= BLOCK_AVAILABILITY_THRESHOLD,
+			ReliabilityType::App => success_count > thresholds.app.mul_floor(total_count),
+			ReliabilityType::Block => success_count >= thresholds.block,
 		}
 	}
 }
@@ -263,12 +291,17 @@ impl Reliability {
 	/// If the reliability type is App, then the value is always `None`.
 	/// If the reliability type is Block, then the value is calculated using the formula above.
 	pub fn value(&self) -> Option<u32> {
+		self.value_with(self.confidence_type.failure_probability())
+	}
+
+	/// Calculates the value of the reliability as [`Reliability::value`] does, with the failure
+	/// probability of a sample `failure_probability`.
+	pub fn value_with(&self, failure_probability: Permill) -> Option<u32> {
 		match self.confidence_type {
 			ReliabilityType::App => None,
 			ReliabilityType::Block => match self.samples.len() {
 				0 => None,
 				_ => {
-					let failure_probability = self.confidence_type.failure_probability();
 					let success_count =
 						self.samples.iter().filter(|&sample| sample.is_availability).count();
 					Some(calculate_confidence(success_count as u32, failure_probability))
@@ -279,8 +312,16 @@ impl Reliability {
 
 	/// Returns whether the reliability is available or not.
 	pub fn is_availability(&self) -> bool {
-		self.confidence_type
-			.is_availability(self.samples.len() as u32, self.success_count() as u32)
+		self.is_available_with(&AvailabilityThresholds::default())
+	}
+
+	/// Returns whether the reliability is available against `thresholds`.
+	pub fn is_available_with(&self, thresholds: &AvailabilityThresholds) -> bool {
+		self.confidence_type.is_available_with(
+			self.samples.len() as u32,
+			self.success_count() as u32,
+			thresholds,
+		)
 	}
 
 	/// Saves the reliability to the database.
@@ -320,6 +361,33 @@ impl Reliability {
 		let commitment = self.commitments[position.y as usize];
 		segment.checked()?.verify(&kzg, &commitment, FIELD_ELEMENTS_PER_SEGMENT)
 	}
+
+	/// Verifies the samples again with the segments cached in `db` under their IDs, and returns
+	/// whether the reliability is available.
+	///
+	/// A sample succeeds if its segment is cached and matches the commitment at its position in
+	/// `commitments`, which are usually those of the chain rather than the local ones.
+	pub fn verify_cached(&mut self, commitments: &[KZGCommitment], db: &mut impl DasKv) -> bool {
+		let kzg = KZG::default_embedded();
+		for sample in self.samples.iter_mut() {
+			let content = db
+				.get(sample.get_id())
+				.and_then(|bytes| SegmentData::decode(&mut &bytes[..]).ok());
+			sample.is_availability = match (commitments.get(sample.position.y as usize), content) {
+				(Some(commitment), Some(content)) => {
+					let segment = Segment { position: sample.position.clone(), content };
+					segment
+						.checked()
+						.and_then(|segment| {
+							segment.verify(&kzg, commitment, FIELD_ELEMENTS_PER_SEGMENT)
+						})
+						.unwrap_or(false)
+				},
+				_ => false,
+			};
+		}
+		self.is_availability()
+	}
 }
 
 #[cfg(feature = "std")]
@@ -333,26 +401,54 @@ impl ReliabilitySample for Reliability {
 		let mut rng = rand::thread_rng();
 		let mut positions = Vec::with_capacity(n);
 
-		let column_count = self.commitments.len() as u32;
+		let rows = Rows(self.commitments.len() as u32);
 
-		if column_count == 0 {
+		if rows.0 == 0 {
 			return Ok(vec![])
 		}
 
-		let mut commitments = Vec::with_capacity(n);
+		// Samples are distinct cells, so there can not be more than the cells of the matrix.
+		let n = n.min(MATRIX.cells(rows).0 as usize);
+		let cols = MATRIX.extended_cols();
 
 		while positions.len() < n {
-			let x = rng.gen_range(0..EXTENDED_SEGMENTS_PER_BLOB) as u32;
-			let y = rng.gen_range(0..column_count);
+			let x = rng.gen_range(0..cols.0);
+			let y = rng.gen_range(0..rows.0);
 
 			let pos = Position { x, y };
 
 			if !positions.contains(&pos) {
-				commitments.push(self.commitments[pos.y as usize]);
 				positions.push(pos);
 			}
 		}
 
+		self.set_sample_positions(positions, app_lookups, block_hash)
+	}
+}
+
+#[cfg(feature = "std")]
+impl Reliability {
+	/// Sets the samples to the cells at `positions`, as [`ReliabilitySample::set_sample`] does
+	/// with the positions it draws, and returns the commitments of their rows.
+	///
+	/// The positions must be distinct cells of the extended matrix of the commitments.
+	pub fn set_sample_positions(
+		&mut self,
+		positions: Vec<Position>,
+		app_lookups: &[AppLookup],
+		block_hash: Option<&[u8]>,
+	) -> Result<Vec<KZGCommitment>, String> {
+		let rows = Rows(self.commitments.len() as u32);
+		let commitments = positions
+			.iter()
+			.map(|pos| {
+				self.commitments
+					.get(pos.y as usize)
+					.copied()
+					.ok_or_else(|| "Position out of the commitments".to_string())
+			})
+			.collect::<Result<Vec<_>, String>>()?;
+
 		self.samples = match self.confidence_type {
 			ReliabilityType::App => app_lookups
 				.first()
@@ -371,7 +467,7 @@ impl ReliabilitySample for Reliability {
 				positions
 					.into_iter()
 					.map(|pos| {
-						if pos.y < column_count / 2 {
+						if pos.y < rows.0 / 2 {
 							AppLookup::get_lookup(app_lookups, pos.y)
 								.ok_or_else(|| "AppLookup not found for position".to_string())
 								.map(|(lookup, relative_y)| {
@@ -397,7 +493,9 @@ impl ReliabilitySample for Reliability {
 	}
 }
 
-fn calculate_confidence(samples: u32, failure_probability: Permill) -> u32 {
+/// Returns the confidence, in parts per million, given by `samples` successful samples which
+/// each succeed with the probability `failure_probability` when the data is unavailable.
+pub fn calculate_confidence(samples: u32, failure_probability: Permill) -> u32 {
 	let one = Permill::one();
 	let base_power_sample = failure_probability.saturating_pow(samples as usize);
 	one.saturating_sub(base_power_sample).deconstruct()
@@ -536,6 +634,28 @@ mod tests {
 		assert_eq!(reliability.success_count(), 1);
 	}
 
+	#[test]
+	fn test_is_available_with_thresholds() {
+		let mut reliability = Reliability::new(ReliabilityType::Block, &[]);
+		for x in 0..4 {
+			reliability.samples.push(Sample {
+				id: SampleId(vec![x as u8]),
+				position: Position { x, y: 0 },
+				is_availability: true,
+			});
+		}
+
+		assert!(!reliability.is_availability());
+		let thresholds = AvailabilityThresholds { block: 4, ..Default::default() };
+		assert!(reliability.is_available_with(&thresholds));
+
+		assert_eq!(reliability.value(), reliability.value_with(BLOCK_FAILURE_PROBABILITY));
+		assert!(
+			reliability.value_with(Permill::from_percent(10)).unwrap() >
+				reliability.value().unwrap()
+		);
+	}
+
 	#[test]
 	fn test_set_sample_with_empty_commitments() {
 		let mut reliability = Reliability::default();
@@ -635,6 +755,64 @@ mod tests {
 		assert_eq!(positions.len(), n);
 	}
 
+	#[test]
+	fn test_set_sample_positions() {
+		let mut reliability =
+			Reliability::new(ReliabilityType::Block, &[KZGCommitment::default(); 4]);
+		let app_lookups = vec![AppLookup { app_id: 1, nonce: 3, count: 2 }];
+		let block_hash = vec![0u8; 32];
+		let positions = vec![Position { x: 3, y: 1 }, Position { x: 0, y: 3 }];
+
+		let commitments = reliability
+			.set_sample_positions(positions.clone(), &app_lookups, Some(&block_hash))
+			.unwrap();
+		assert_eq!(commitments.len(), 2);
+		assert_eq!(reliability.samples[0].id.0, sample_key(1, 3, &Position { x: 3, y: 1 }));
+		assert_eq!(reliability.samples[1].id.0, sample_key_from_block(&block_hash, &positions[1]));
+
+		assert!(reliability
+			.set_sample_positions(vec![Position { x: 0, y: 4 }], &app_lookups, Some(&block_hash))
+			.is_err());
+	}
+
+	#[test]
+	fn test_verify_cached() {
+		use crate::{config::FIELD_ELEMENTS_PER_BLOB, SidecarMetadata};
+		use melo_erasure_coding::bytes_to_segments;
+
+		let mut db = MockDb::new();
+		let kzg = KZG::default_embedded();
+		let bytes = vec![7u8; 1000];
+		let metadata = SidecarMetadata::try_from_app_data(&bytes, 1, 1).unwrap();
+		let segments =
+			bytes_to_segments(&bytes, FIELD_ELEMENTS_PER_BLOB, FIELD_ELEMENTS_PER_SEGMENT, &kzg)
+				.unwrap();
+
+		let mut confidence = Reliability::new(ReliabilityType::App, &metadata.commitments);
+		confidence.samples = segments
+			.iter()
+			.take(4)
+			.map(|segment| Sample {
+				id: SampleId(sample_key(1, 1, &segment.position)),
+				position: segment.position.clone(),
+				is_availability: true,
+			})
+			.collect();
+
+		// Nothing is cached yet.
+		assert!(!confidence.verify_cached(&metadata.commitments, &mut db));
+		assert!(confidence.samples.iter().all(|sample| !sample.is_availability));
+
+		for segment in segments.iter().take(4) {
+			db.set(&sample_key(1, 1, &segment.position), &segment.content.encode());
+		}
+		assert!(confidence.verify_cached(&metadata.commitments, &mut db));
+
+		// The cached segments do not match other commitments.
+		let other = SidecarMetadata::try_from_app_data(&vec![8u8; 1000], 1, 1).unwrap();
+		assert!(!confidence.verify_cached(&other.commitments, &mut db));
+	}
+
 	#[test]
 	fn test_max_consecutive_success_count() {
 		let mut samples = Vec::new();
@@ -0,0 +1,132 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Segment serving receipts.
+//!
+//! A [`ServingReceipt`] acknowledges the segments a node served to a peer during a serving period
+//! of [`SERVING_PERIOD_SECS`] seconds. The serving node proposes the receipt, and the peer signs
+//! it with the ed25519 key of its DAS network identity if it received at least as many segments
+//! and bytes from the node during the period. The serving node then claims a reward for its
+//! receipts on-chain, where only the receipts of the requesters registered with the signature of
+//! their key over a [`registration_payload`] are rewarded.
+
+use crate::{Decode, Encode, TypeInfo};
+use sp_core::{ed25519, RuntimeDebug};
+use sp_io::{crypto::ed25519_verify, hashing::blake2_256};
+
+/// The domain separation prefix of the signed payload of a serving receipt.
+pub const SERVING_RECEIPT_CONTEXT: &[u8] = b"melodot-das-serving-v1";
+
+/// The domain separation prefix of the signed payload of the registration of a requester.
+pub const REQUESTER_REGISTRATION_CONTEXT: &[u8] = b"melodot-das-requester-v1";
+
+/// The length of a serving period, in seconds.
+pub const SERVING_PERIOD_SECS: u64 = 60 * 60;
+
+/// Returns the serving period of the unix time `secs`.
+pub fn serving_period(secs: u64) -> u32 {
+	(secs / SERVING_PERIOD_SECS) as u32
+}
+
+/// Returns the hash a requester signs to be registered by `account`.
+pub fn registration_payload<AccountId: Encode>(account: &AccountId) -> [u8; 32] {
+	let mut buffer = REQUESTER_REGISTRATION_CONTEXT.to_vec();
+	account.encode_to(&mut buffer);
+	blake2_256(&buffer)
+}
+
+/// The segments served by `server` to `requester` during a serving period, signed by the
+/// requester.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct ServingReceipt<AccountId> {
+	/// The account the serving node claims its rewards with.
+	pub server: AccountId,
+	/// The ed25519 key of the DAS network identity of the requester.
+	pub requester: ed25519::Public,
+	/// The serving period.
+	pub period: u32,
+	/// The number of segments served.
+	pub segments: u32,
+	/// The number of bytes of the responses holding the segments.
+	pub bytes: u64,
+	/// The signature of the requester over [`ServingReceipt::payload`].
+	pub signature: ed25519::Signature,
+}
+
+impl<AccountId: Encode> ServingReceipt<AccountId> {
+	/// Returns the hash signed by the requester.
+	pub fn payload(&self) -> [u8; 32] {
+		let mut buffer = SERVING_RECEIPT_CONTEXT.to_vec();
+		(&self.server, &self.requester, self.period, self.segments, self.bytes)
+			.encode_to(&mut buffer);
+		blake2_256(&buffer)
+	}
+
+	/// Creates the receipt of the `segments` and `bytes` served by `server` to `requester` during
+	/// `period`, without its signature.
+	pub fn unsigned(
+		server: AccountId,
+		requester: ed25519::Public,
+		period: u32,
+		segments: u32,
+		bytes: u64,
+	) -> Self {
+		ServingReceipt {
+			server,
+			requester,
+			period,
+			segments,
+			bytes,
+			signature: ed25519::Signature::from_raw([0u8; 64]),
+		}
+	}
+
+	/// Checks that the receipt is signed by its requester.
+	pub fn verify(&self) -> bool {
+		ed25519_verify(&self.signature, &self.payload(), &self.requester)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::Pair;
+
+	#[test]
+	fn test_serving_receipt_verify() {
+		let pair = ed25519::Pair::from_seed(&[1u8; 32]);
+		let mut receipt = ServingReceipt::unsigned([7u8; 32], pair.public(), 3, 16, 4096);
+		assert!(!receipt.verify());
+
+		receipt.signature = pair.sign(&receipt.payload());
+		assert!(receipt.verify());
+		let receipt = ServingReceipt::<[u8; 32]>::decode(&mut &receipt.encode()[..]).unwrap();
+		assert!(receipt.verify());
+
+		let mut inflated = receipt.clone();
+		inflated.segments += 1;
+		assert!(!inflated.verify());
+
+		let mut redirected = receipt;
+		redirected.server = [8u8; 32];
+		assert!(!redirected.verify());
+	}
+
+	#[test]
+	fn test_serving_period() {
+		assert_eq!(serving_period(0), 0);
+		assert_eq!(serving_period(SERVING_PERIOD_SECS - 1), 0);
+		assert_eq!(serving_period(SERVING_PERIOD_SECS * 5 + 1), 5);
+	}
+}
@@ -12,25 +12,34 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{reliability::ReliabilityId, String, TypeInfo, Vec};
-use alloc::format;
+use crate::{
+	receipt::StorageReceipt, reliability::ReliabilityId, Compression, DasParams, Encryption,
+	String, TypeInfo, Vec,
+};
+use alloc::{format, vec};
 use codec::{Decode, Encode};
 use melo_das_primitives::{Blob, KZGCommitment, KZGProof, KZG};
 use melo_erasure_coding::bytes_to_blobs;
 use sp_core::RuntimeDebug;
 
-use core::result::Result;
-#[cfg(feature = "std")]
+use core::{ops::Range, result::Result};
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use sp_io::hashing;
 
-use melo_das_primitives::config::FIELD_ELEMENTS_PER_BLOB;
+use melo_das_primitives::config::{DATA_BYTES_PER_BLOB, FIELD_ELEMENTS_PER_BLOB};
 
-// const SIDERCAR_PREFIX: &[u8] = b"sidecar";
+/// The prefix of the keys of the sidecars a node holds locally.
+pub const SIDECAR_PREFIX: &[u8] = b"sidecar";
+
+/// Returns the key under which a node stores the sidecar of `data_hash` it holds locally.
+pub fn sidecar_key(data_hash: &[u8]) -> Vec<u8> {
+	[SIDECAR_PREFIX, data_hash].concat()
+}
 
 /// Represents the possible statuses of the sidecar, including failures and success cases.
 #[derive(Encode, Decode, Debug, Clone, PartialEq, Eq)]
-#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum SidecarStatus {
 	// Failed to retrieve data
 	NotFound,
@@ -40,9 +49,142 @@ pub enum SidecarStatus {
 	Success,
 }
 
+/// JSON-RPC error codes of the DAS RPC.
+///
+/// The codes are shared by the errors returned by the methods and the [`BlobTxError`]s reported
+/// in a [`BlobTxSatus`].
+pub mod error_code {
+	/// Base of the DAS error codes.
+	pub const BASE: i32 = 10000;
+	/// The extrinsic could not be decoded.
+	pub const DECODING_EXTRINSIC_FAILED: i32 = BASE + 1;
+	/// The call of the extrinsic could not be decoded.
+	pub const DECODING_TRANSACTION_METADATA_FAILED: i32 = BASE + 2;
+	/// The runtime could not be queried.
+	pub const FETCH_TRANSACTION_METADATA_FAILED: i32 = BASE + 3;
+	/// The extrinsic does not submit exactly one blob.
+	pub const INVALID_TRANSACTION_FORMAT: i32 = BASE + 4;
+	/// The length of the data does not match its metadata.
+	pub const DATA_LENGTH: i32 = BASE + 5;
+	/// The transaction pool rejected the transaction.
+	pub const TRANSACTION_PUSH_FAILED: i32 = BASE + 6;
+	/// The data could not be fetched.
+	pub const FETCH_BLOB_FAILED: i32 = BASE + 7;
+	/// The data exceeds the maximum blob size of the runtime.
+	pub const BLOB_TOO_LARGE: i32 = BASE + 8;
+	/// The blobs exceed the quota of the app in the next block.
+	pub const QUOTA_EXCEEDED: i32 = BASE + 9;
+	/// The number of commitments or proofs does not match the data length.
+	pub const COMMITMENT_COUNT_MISMATCH: i32 = BASE + 10;
+	/// The data does not match its commitments and proofs.
+	pub const DATA_VERIFICATION_FAILED: i32 = BASE + 11;
+	/// The data could not be put to the DHT network.
+	pub const PUBLISH_BLOB_FAILED: i32 = BASE + 12;
+	/// The data exceeds the bytes left to the app in the next block.
+	pub const BYTES_QUOTA_EXCEEDED: i32 = BASE + 13;
+	/// The node does not sign availability receipts.
+	pub const NO_RECEIPT_SIGNER: i32 = BASE + 14;
+	/// The sampling parameters are inconsistent.
+	pub const INVALID_SAMPLING_PARAMS: i32 = BASE + 15;
+	/// The data is not framed by the padding scheme.
+	pub const INVALID_PADDING: i32 = BASE + 16;
+	/// The local data could not be pruned.
+	pub const PRUNE_FAILED: i32 = BASE + 17;
+	/// The DHT network is degraded, its operations are short-circuited for a while.
+	pub const DHT_DEGRADED: i32 = BASE + 18;
+	/// More positions are requested at once than allowed.
+	pub const TOO_MANY_POSITIONS: i32 = BASE + 19;
+	/// The filter of a blob listing is invalid.
+	pub const INVALID_BLOB_FILTER: i32 = BASE + 20;
+	/// The request ID of a submission was already used for another extrinsic.
+	pub const REQUEST_ID_CONFLICT: i32 = BASE + 21;
+	/// A signed extrinsic was expected.
+	pub const UNSIGNED_EXTRINSIC: i32 = BASE + 22;
+	/// More commitments are given than the rows of a block.
+	pub const TOO_MANY_COMMITMENTS: i32 = BASE + 23;
+	/// The node mirroring a sidecar to a committee member is not allowed by the member.
+	pub const UNAUTHORIZED_NODE: i32 = BASE + 24;
+	/// The sidecar exceeds the bytes a committee member stores for the node.
+	pub const COMMITTEE_QUOTA_EXCEEDED: i32 = BASE + 25;
+}
+
+/// Reasons for which the transaction pool rejects a blob transaction.
+#[derive(Eq, PartialEq, Clone, Copy, Encode, Decode, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum PoolRejection {
+	/// The nonce was already used by an included transaction.
+	StaleNonce,
+	/// The nonce is ahead of the next nonce of the account.
+	FutureNonce,
+	/// A transaction with the same nonce and a higher priority is already in the pool.
+	PriorityTooLow,
+	/// The transaction is already in the pool.
+	AlreadyImported,
+	/// The transaction is invalid for any other reason.
+	Invalid,
+}
+
+/// An error reported in a [`BlobTxSatus`].
+///
+/// Errors preventing the submission altogether, such as data not matching its commitments, are
+/// returned as JSON-RPC errors instead.
+#[derive(Eq, PartialEq, Clone, Encode, Decode, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", rename_all = "camelCase"))]
+pub enum BlobTxError {
+	/// The data could not be put to the DHT network. The transaction was still submitted, and the
+	/// data can be published again with `das_republishBlob`.
+	DhtPutFailed { message: String },
+	/// The data was not put to the DHT network because its operations kept timing out and are
+	/// short-circuited for a while. The transaction was still submitted, and the data can be
+	/// published again with `das_republishBlob` once the network recovers.
+	DhtDegraded { message: String },
+	/// The transaction pool rejected the transaction.
+	PoolRejected { reason: PoolRejection, message: String },
+}
+
+impl BlobTxError {
+	/// Returns the [`error_code`] of the error.
+	pub fn code(&self) -> i32 {
+		match self {
+			BlobTxError::DhtPutFailed { .. } => error_code::PUBLISH_BLOB_FAILED,
+			BlobTxError::DhtDegraded { .. } => error_code::DHT_DEGRADED,
+			BlobTxError::PoolRejected { .. } => error_code::TRANSACTION_PUSH_FAILED,
+		}
+	}
+
+	/// Returns `true` if the operation can succeed when tried again.
+	pub fn is_retriable(&self) -> bool {
+		match self {
+			BlobTxError::DhtPutFailed { .. } | BlobTxError::DhtDegraded { .. } => true,
+			BlobTxError::PoolRejected { reason, .. } => matches!(
+				reason,
+				PoolRejection::StaleNonce |
+					PoolRejection::FutureNonce |
+					PoolRejection::PriorityTooLow
+			),
+		}
+	}
+}
+
+/// Represents the status of a Blob transaction.
+/// Includes the transaction hash and potential error details.
+#[derive(Eq, PartialEq, Default, Clone, Encode, Decode, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct BlobTxSatus<Hash> {
+	pub tx_hash: Hash,
+	pub err: Option<BlobTxError>,
+	/// The receipts of the committee members which acknowledged storing the data, when the node
+	/// mirrors the data to a committee.
+	#[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Vec::is_empty"))]
+	pub committee_receipts: Vec<StorageReceipt>,
+}
+
 /// Contains essential metadata for the sidecar, such as data length, hash, commitments, and proofs.
 #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
-// #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+// #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SidecarMetadata {
 	/// Application ID.
 	pub app_id: u32,
@@ -52,8 +194,14 @@ pub struct SidecarMetadata {
 	pub nonce: u32,
 	/// Commitments of the data.
 	pub commitments: Vec<KZGCommitment>,
-	/// Proofs of the data.
+	/// Proofs of the data, either one per blob or a single batch proof of all the blobs.
 	pub proofs: Vec<KZGProof>,
+	/// Compression of the data when it is stored and transported. Commitments and proofs are
+	/// computed over the uncompressed data.
+	pub compression: Compression,
+	/// Encryption of the data by its application. Commitments and proofs are computed over the
+	/// encrypted data.
+	pub encryption: Encryption,
 }
 
 impl SidecarMetadata {
@@ -65,16 +213,62 @@ impl SidecarMetadata {
 		commitments: Vec<KZGCommitment>,
 		proofs: Vec<KZGProof>,
 	) -> Self {
-		Self { app_id, bytes_len, nonce, commitments, proofs }
+		Self {
+			app_id,
+			bytes_len,
+			nonce,
+			commitments,
+			proofs,
+			compression: Compression::None,
+			encryption: Encryption::None,
+		}
+	}
+
+	/// Returns the metadata with the data compressed with `compression`.
+	pub fn with_compression(mut self, compression: Compression) -> Self {
+		self.compression = compression;
+		self
+	}
+
+	/// Returns the metadata with the data encrypted with `encryption`.
+	pub fn with_encryption(mut self, encryption: Encryption) -> Self {
+		self.encryption = encryption;
+		self
+	}
+
+	/// Decodes the payload submitted along with the metadata into the original data.
+	///
+	/// The data is expected to be `bytes_len` bytes long once decompressed.
+	#[cfg(feature = "std")]
+	pub fn decode_payload(&self, payload: &[u8]) -> Result<Vec<u8>, String> {
+		let bytes = self.compression.decompress(payload, self.bytes_len as usize)?;
+		if bytes.len() != self.bytes_len as usize {
+			return Err(format!("Expected {} bytes of data, got {}", self.bytes_len, bytes.len()))
+		}
+		Ok(bytes)
 	}
 
 	/// Checks if the metadata is valid.
 	pub fn check(&self) -> bool {
-		self.commitments.len() == self.proofs.len() &&
+		(self.commitments.len() == self.proofs.len() || self.has_batch_proof()) &&
 			!self.commitments.is_empty() &&
 			self.bytes_len > 0
 	}
 
+	/// Returns whether the blobs are proven by a single batch proof rather than one proof each.
+	///
+	/// For a single blob, its batch proof is its own proof.
+	pub fn has_batch_proof(&self) -> bool {
+		self.proofs.len() == 1
+	}
+
+	/// Returns the number of blobs `bytes_len` bytes of data are split into, which is the number
+	/// of commitments the metadata is expected to carry, and of proofs unless it carries a batch
+	/// proof.
+	pub fn blob_count(&self) -> usize {
+		Blob::blob_count(self.bytes_len as usize, DATA_BYTES_PER_BLOB)
+	}
+
 	/// Returns the confidence ID of the metadata.
 	pub fn confidence_id(&self) -> ReliabilityId {
 		ReliabilityId::app_confidence(self.app_id, self.nonce)
@@ -89,16 +283,48 @@ impl SidecarMetadata {
 	pub fn verify_bytes(&self, bytes: &[u8]) -> Result<bool, String> {
 		let kzg = KZG::default_embedded();
 		bytes_to_blobs(bytes, FIELD_ELEMENTS_PER_BLOB).and_then(|blobs| {
-			Blob::verify_batch(
-				&blobs,
-				&self.commitments,
-				&self.proofs,
-				&kzg,
-				FIELD_ELEMENTS_PER_BLOB,
-			)
+			if self.has_batch_proof() {
+				Blob::verify_blobs_proof_batch(
+					&blobs,
+					&self.commitments,
+					&self.proofs[0],
+					&kzg,
+					FIELD_ELEMENTS_PER_BLOB,
+				)
+			} else {
+				Blob::verify_batch(
+					&blobs,
+					&self.commitments,
+					&self.proofs,
+					&kzg,
+					FIELD_ELEMENTS_PER_BLOB,
+				)
+			}
 		})
 	}
 
+	/// Attempts to generate a `SidecarMetadata` instance from given application data bytes, with
+	/// a single batch proof of all its blobs.
+	pub fn try_from_app_data_with_batch_proof(
+		bytes: &[u8],
+		app_id: u32,
+		nonce: u32,
+	) -> Result<Self, String> {
+		let kzg = KZG::default_embedded();
+
+		let blobs = bytes_to_blobs(bytes, FIELD_ELEMENTS_PER_BLOB)?;
+		let commitments = blobs
+			.iter()
+			.map(|blob| blob.commit(&kzg))
+			.collect::<Result<Vec<_>, _>>()
+			.map_err(|e| format!("Failed to commit: {}", e))?;
+		let proof =
+			Blob::compute_blobs_proof_batch(&blobs, &commitments, &kzg, FIELD_ELEMENTS_PER_BLOB)
+				.map_err(|e| format!("Failed to prove: {}", e))?;
+
+		Ok(Self::new(app_id, bytes.len() as u32, nonce, commitments, vec![proof]))
+	}
+
 	/// Attempts to generate a `SidecarMetadata` instance from given application data bytes.
 	pub fn try_from_app_data(bytes: &[u8], app_id: u32, nonce: u32) -> Result<Self, String> {
 		let kzg = KZG::default_embedded();
@@ -120,7 +346,15 @@ impl SidecarMetadata {
 				.into_iter()
 				.unzip();
 
-			Ok(Self { app_id, bytes_len: data_len, nonce, commitments, proofs })
+			Ok(Self {
+				app_id,
+				bytes_len: data_len,
+				nonce,
+				commitments,
+				proofs,
+				compression: Compression::None,
+				encryption: Encryption::None,
+			})
 		}
 
 		#[cfg(not(feature = "std"))]
@@ -140,14 +374,88 @@ impl SidecarMetadata {
 				}
 			}
 
-			Ok(Self { app_id, bytes_len: data_len, nonce, commitments, proofs })
+			Ok(Self {
+				app_id,
+				bytes_len: data_len,
+				nonce,
+				commitments,
+				proofs,
+				compression: Compression::None,
+				encryption: Encryption::None,
+			})
+		}
+	}
+}
+
+/// A blob carried by an extrinsic, together with the rows of the block matrix it is expected to
+/// take.
+///
+/// Rows are laid out in the order the blobs are submitted, so the position assumes that every blob
+/// submitted before it in the same block becomes available.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct BlobPlacement {
+	/// Metadata of the blob, including its app id.
+	pub metadata: SidecarMetadata,
+	/// The first row of the blob in the block matrix.
+	pub start_row: u32,
+}
+
+impl BlobPlacement {
+	/// Places the blobs of `metadata` one after the other, starting at `start_row`.
+	pub fn place(metadata: Vec<SidecarMetadata>, start_row: u32) -> Vec<Self> {
+		let mut next_row = start_row;
+		metadata
+			.into_iter()
+			.map(|metadata| {
+				let start_row = next_row;
+				next_row = next_row.saturating_add(metadata.commitments.len() as u32);
+				Self { metadata, start_row }
+			})
+			.collect()
+	}
+
+	/// Returns the rows of the blob in the block matrix.
+	pub fn rows(&self) -> Range<u32> {
+		self.start_row..self.start_row.saturating_add(self.metadata.commitments.len() as u32)
+	}
+
+	/// Returns the ID (hash) of the blob metadata.
+	pub fn id(&self) -> [u8; 32] {
+		self.metadata.id()
+	}
+}
+
+/// Limits the runtime puts on a blob submission to the next block.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct BlobLimits {
+	/// The maximum length in bytes of the data of a submission.
+	pub max_bytes_len: u32,
+	/// The number of blobs the app can still submit to the next block.
+	pub app_quota: u32,
+	/// The number of bytes the app can still submit to the next block.
+	pub bytes_quota: u32,
+}
+
+impl BlobLimits {
+	/// The limits of a registered app in an empty block under `params`, for the runtimes that can
+	/// not report theirs.
+	pub fn of_empty_block(params: &DasParams) -> Self {
+		let max_bytes_len = params
+			.max_block_rows
+			.saturating_mul(DATA_BYTES_PER_BLOB as u32)
+			.min(params.max_blob_bytes)
+			.min(params.max_block_bytes);
+		Self {
+			max_bytes_len,
+			app_quota: params.max_block_rows,
+			bytes_quota: params.max_block_bytes,
 		}
 	}
 }
 
 /// Represents a sidecar, encapsulating its metadata, potential data, and its current status.
 #[derive(Encode, Decode, Clone, PartialEq, Eq)]
-// #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+// #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Sidecar {
 	/// Metadata associated with the sidecar.
 	pub metadata: SidecarMetadata,
@@ -183,3 +491,92 @@ impl Sidecar {
 		self.status = Some(SidecarStatus::NotFound);
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn metadata(app_id: u32, rows: usize) -> SidecarMetadata {
+		SidecarMetadata::new(
+			app_id,
+			1,
+			1,
+			vec![KZGCommitment::default(); rows],
+			vec![KZGProof::default(); rows],
+		)
+	}
+
+	#[test]
+	fn test_blob_limits_of_empty_block() {
+		let params = DasParams::default();
+		let limits = BlobLimits::of_empty_block(&params);
+		assert_eq!(limits.app_quota, params.max_block_rows);
+		assert_eq!(limits.bytes_quota, params.max_block_bytes);
+		assert_eq!(limits.max_bytes_len, params.max_blob_bytes.min(params.max_block_bytes));
+
+		let params = DasParams { max_block_rows: 1, ..Default::default() };
+		assert_eq!(BlobLimits::of_empty_block(&params).max_bytes_len, DATA_BYTES_PER_BLOB as u32);
+	}
+
+	#[test]
+	fn test_blob_placement() {
+		let placements =
+			BlobPlacement::place(vec![metadata(1, 2), metadata(2, 1), metadata(1, 3)], 4);
+
+		assert_eq!(
+			placements.iter().map(|p| (p.metadata.app_id, p.rows())).collect::<Vec<_>>(),
+			vec![(1, 4..6), (2, 6..7), (1, 7..10)]
+		);
+		assert_eq!(placements[1].id(), placements[1].metadata.id());
+
+		assert!(BlobPlacement::place(vec![], 0).is_empty());
+	}
+
+	#[test]
+	fn test_blob_count_matches_packing() {
+		for len in [1, DATA_BYTES_PER_BLOB, DATA_BYTES_PER_BLOB + 1, DATA_BYTES_PER_BLOB + 100] {
+			let metadata = SidecarMetadata::try_from_app_data(&vec![1u8; len], 1, 1).unwrap();
+			assert_eq!(metadata.blob_count(), metadata.commitments.len());
+		}
+	}
+
+	#[test]
+	fn test_batch_proof() {
+		let bytes = vec![9u8; DATA_BYTES_PER_BLOB + 100];
+		let metadata = SidecarMetadata::try_from_app_data_with_batch_proof(&bytes, 1, 1).unwrap();
+		assert_eq!(metadata.commitments.len(), 2);
+		assert!(metadata.has_batch_proof());
+		assert!(metadata.check());
+		assert!(metadata.verify_bytes(&bytes).unwrap());
+
+		let per_blob = SidecarMetadata::try_from_app_data(&bytes, 1, 1).unwrap();
+		assert_eq!(per_blob.commitments, metadata.commitments);
+		assert!(!per_blob.has_batch_proof());
+
+		let mut other = bytes.clone();
+		other[0] = 8;
+		assert!(!metadata.verify_bytes(&other).unwrap());
+
+		// Neither one proof per blob nor a batch proof.
+		let mut invalid = metadata.clone();
+		invalid.commitments.push(KZGCommitment::default());
+		invalid.proofs.push(KZGProof::default());
+		assert!(!invalid.check());
+	}
+
+	#[test]
+	fn test_blob_tx_error() {
+		let rejected = |reason| BlobTxError::PoolRejected { reason, message: String::new() };
+
+		let dht = BlobTxError::DhtPutFailed { message: String::new() };
+		assert_eq!(dht.code(), error_code::PUBLISH_BLOB_FAILED);
+		assert!(dht.is_retriable());
+
+		assert_eq!(rejected(PoolRejection::Invalid).code(), error_code::TRANSACTION_PUSH_FAILED);
+		assert!(rejected(PoolRejection::StaleNonce).is_retriable());
+		assert!(rejected(PoolRejection::FutureNonce).is_retriable());
+		assert!(rejected(PoolRejection::PriorityTooLow).is_retriable());
+		assert!(!rejected(PoolRejection::AlreadyImported).is_retriable());
+		assert!(!rejected(PoolRejection::Invalid).is_retriable());
+	}
+}
@@ -0,0 +1,174 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Storage proofs of the commitments posted to `pallet-melo-store`.
+//!
+//! The commitments of the blobs submitted at a block are stored in the `MeloStore::Metadata` entry
+//! of the block. A [`CommitmentsProof`] holds the nodes of the state trie leading to that entry,
+//! as returned by the `state_getReadProof` RPC, so that anyone holding the header of the block can
+//! check which commitments were posted without trusting the node serving them.
+
+use crate::{Decode, Encode, KZGCommitment, KZGProof, TypeInfo, Vec};
+use sp_core::{RuntimeDebug, H256};
+use sp_io::hashing::{twox_128, twox_64};
+use sp_runtime::traits::BlakeTwo256;
+use sp_trie::{read_trie_value, LayoutV1, StorageProof};
+
+/// The name of `pallet-melo-store` in the runtime.
+pub const MELO_STORE_PALLET: &[u8] = b"MeloStore";
+/// The name of the storage entry holding the metadata of the blobs of a block.
+pub const METADATA_ENTRY: &[u8] = b"Metadata";
+
+/// A blob of `MeloStore::Metadata`, decoded without the types of the runtime.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct StoredBlob {
+	/// The application of the blob.
+	pub app_id: u32,
+	/// The account that submitted the blob.
+	pub from: [u8; 32],
+	/// The KZG commitments of the blob.
+	pub commitments: Vec<KZGCommitment>,
+	/// The KZG proofs of the blob.
+	pub proofs: Vec<KZGProof>,
+	/// The length of the data of the blob.
+	pub bytes_len: u32,
+	/// Whether the data of the blob is available.
+	pub is_available: bool,
+	/// The nonce of the blob in its application.
+	pub nonce: u32,
+}
+
+/// The reasons a [`CommitmentsProof`] is rejected.
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum StorageProofError {
+	/// The proof does not lead from the state root to the entry.
+	InvalidProof,
+	/// The entry can not be decoded.
+	InvalidValue,
+}
+
+/// Returns the storage key of the `MeloStore::Metadata` entry of the block `block_number`.
+pub fn metadata_storage_key(block_number: u32) -> Vec<u8> {
+	let encoded = block_number.encode();
+	let mut key = Vec::with_capacity(32 + 8 + encoded.len());
+	key.extend_from_slice(&twox_128(MELO_STORE_PALLET));
+	key.extend_from_slice(&twox_128(METADATA_ENTRY));
+	key.extend_from_slice(&twox_64(&encoded));
+	key.extend_from_slice(&encoded);
+	key
+}
+
+/// A storage proof of the `MeloStore::Metadata` entry of a block.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct CommitmentsProof {
+	/// The hash of the block whose state the proof was read from.
+	pub block_hash: H256,
+	/// The nodes of the state trie.
+	pub proof: Vec<Vec<u8>>,
+}
+
+impl CommitmentsProof {
+	/// Returns the blobs of the block `block_number` whose state root is `state_root`.
+	///
+	/// Both should come from a header the caller trusts, such as a finalized one, rather than from
+	/// the node that produced the proof. A block without blobs is proven by the absence of its
+	/// entry, and yields no blobs.
+	pub fn verify_blobs(
+		&self,
+		state_root: &H256,
+		block_number: u32,
+	) -> Result<Vec<StoredBlob>, StorageProofError> {
+		let db = StorageProof::new(self.proof.iter().cloned()).into_memory_db::<BlakeTwo256>();
+		let key = metadata_storage_key(block_number);
+		let value = read_trie_value::<LayoutV1<BlakeTwo256>, _>(&db, state_root, &key, None, None)
+			.map_err(|_| StorageProofError::InvalidProof)?;
+
+		match value {
+			Some(value) => Vec::<StoredBlob>::decode(&mut &value[..])
+				.map_err(|_| StorageProofError::InvalidValue),
+			None => Ok(Vec::new()),
+		}
+	}
+
+	/// Returns the blobs of `app_id` posted at the block `block_number` whose state root is
+	/// `state_root`, as [`CommitmentsProof::verify_blobs`] does.
+	pub fn verify_app(
+		&self,
+		state_root: &H256,
+		block_number: u32,
+		app_id: u32,
+	) -> Result<Vec<StoredBlob>, StorageProofError> {
+		let blobs = self.verify_blobs(state_root, block_number)?;
+		Ok(blobs.into_iter().filter(|blob| blob.app_id == app_id).collect())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::storage::StateVersion;
+	use sp_state_machine::{prove_read, InMemoryBackend};
+
+	fn blob(app_id: u32, nonce: u32) -> StoredBlob {
+		StoredBlob {
+			app_id,
+			from: [app_id as u8; 32],
+			commitments: vec![KZGCommitment::default(); 2],
+			proofs: vec![KZGProof::default(); 2],
+			bytes_len: 100,
+			is_available: true,
+			nonce,
+		}
+	}
+
+	fn prove(entries: Vec<(Vec<u8>, Vec<u8>)>, key: &[u8]) -> (H256, CommitmentsProof) {
+		let entries = entries.into_iter().map(|(key, value)| (key, Some(value))).collect();
+		let backend =
+			InMemoryBackend::<BlakeTwo256>::from((vec![(None, entries)], StateVersion::V1));
+		let root = *backend.root();
+		let proof = prove_read(backend, &[key]).unwrap();
+		(
+			root,
+			CommitmentsProof { block_hash: H256::zero(), proof: proof.into_iter_nodes().collect() },
+		)
+	}
+
+	#[test]
+	fn test_verify_commitments_proof() {
+		let blobs = vec![blob(1, 0), blob(2, 0), blob(1, 1)];
+		let entries = vec![
+			(metadata_storage_key(7), blobs.encode()),
+			(metadata_storage_key(6), vec![blob(3, 0)].encode()),
+		];
+		let (root, proof) = prove(entries, &metadata_storage_key(7));
+
+		assert_eq!(proof.verify_blobs(&root, 7), Ok(blobs.clone()));
+		assert_eq!(proof.verify_app(&root, 7, 1), Ok(vec![blobs[0].clone(), blobs[2].clone()]));
+		assert_eq!(proof.verify_app(&root, 7, 4), Ok(vec![]));
+
+		// The proof does not cover the entry of another block.
+		assert_eq!(proof.verify_blobs(&root, 6), Err(StorageProofError::InvalidProof));
+		assert_eq!(
+			proof.verify_blobs(&H256::repeat_byte(1), 7),
+			Err(StorageProofError::InvalidProof)
+		);
+	}
+
+	#[test]
+	fn test_verify_absent_entry() {
+		let entries = vec![(metadata_storage_key(6), vec![blob(3, 0)].encode())];
+		let (root, proof) = prove(entries, &metadata_storage_key(7));
+		assert_eq!(proof.verify_blobs(&root, 7), Ok(vec![]));
+	}
+}
@@ -14,10 +14,17 @@
 
 use core::fmt::Display;
 
-use crate::{AppLookup, Digest, HeaderExtension, KZGCommitment, SidecarMetadata, Vec};
+use crate::{
+	inclusion::BlockCommitments, AppLookup, BlobLimits, BlobPlacement, DasParams, Digest,
+	HeaderExtension, KZGCommitment, SidecarMetadata, Vec,
+};
 use codec::{Decode, Encode};
 use melo_das_primitives::Position;
-use sp_runtime::traits::{Hash, MaybeSerialize};
+use sp_core::{sr25519, H256};
+use sp_runtime::{
+	traits::{Hash, MaybeSerialize},
+	Permill,
+};
 
 pub trait ExtendedHeader {
 	/// Header number.
@@ -93,20 +100,92 @@ pub trait HeaderWithCommitment: MaybeSerialize + Encode + Sized {
 sp_api::decl_runtime_apis! {
 	/// Extracts the `data` field from some types of extrinsics.
 	#[allow(clippy::ptr_arg, clippy::type_complexity)]
+	#[api_version(3)]
 	pub trait Extractor {
 		fn extract(
 			extrinsic: &Vec<u8>,
 			// (data_hash, bytes_len, commitments, proofs)
 		) -> Option<Vec<SidecarMetadata>>;
+
+		/// Extracts the blobs of an extrinsic along with the rows they are expected to take in
+		/// the block matrix if the extrinsic is the first to submit blobs to the next block.
+		///
+		/// Returns `None` if the extrinsic can not be decoded.
+		fn extract_placements(
+			extrinsic: &Vec<u8>,
+		) -> Option<Vec<BlobPlacement>>;
+
+		/// Extracts the blobs of each of `extrinsics` as [`Extractor::extract_placements`] does,
+		/// in a single call to the runtime. The blobs of each extrinsic are placed after those of
+		/// the extrinsics before it, as if they were included in the next block in that order.
+		#[api_version(3)]
+		fn extract_placements_batch(
+			extrinsics: Vec<Vec<u8>>,
+		) -> Vec<Option<Vec<BlobPlacement>>>;
 	}
 }
 
 sp_api::decl_runtime_apis! {
+	#[api_version(5)]
 	pub trait AppDataApi<RuntimeCall>
 	where RuntimeCall: Encode {
 		fn get_blob_tx_param(
 			function: &RuntimeCall,
 		) -> Option<SidecarMetadata>;
+
+		/// Returns the result of [`AppDataApi::get_blob_tx_param`] for each of `calls`, in a
+		/// single call to the runtime.
+		#[api_version(3)]
+		fn get_blob_tx_params(
+			calls: Vec<RuntimeCall>,
+		) -> Vec<Option<SidecarMetadata>>;
+
+		/// Returns the blobs submitted by `function` along with the rows they are expected to
+		/// take in the block matrix if the call is the first to submit blobs to the next block.
+		fn get_blob_placements(
+			function: &RuntimeCall,
+		) -> Vec<BlobPlacement>;
+
+		/// Returns the limits a submission of `app_id` to the next block has to respect.
+		#[api_version(5)]
+		fn blob_limits(
+			app_id: u32,
+		) -> BlobLimits;
+
+		/// Returns the commitments of the rows of the block `at_block`, or only their root once
+		/// they are compacted past the retention period.
+		#[api_version(4)]
+		fn block_commitments(
+			at_block: u32,
+		) -> BlockCommitments;
+	}
+}
+
+sp_api::decl_runtime_apis! {
+	/// Exposes the parameters of data availability set by governance.
+	#[api_version(2)]
+	pub trait DasParamsApi {
+		/// Returns the parameters in use.
+		fn das_params() -> DasParams;
+
+		/// Returns the parameters scheduled to replace the ones in use, with the block they
+		/// activate at.
+		fn pending_das_params() -> Option<(u32, DasParams)>;
+
+		/// Returns the hash of the KZG trusted setup of the network, if registered.
+		#[api_version(2)]
+		fn trusted_setup_hash() -> Option<H256>;
+	}
+}
+
+sp_api::decl_runtime_apis! {
+	/// Exposes the attesters of the withheld blocks set in `pallet-withholding`.
+	pub trait WithholdingApi {
+		/// Returns the keys of the sampling nodes whose receipts are accepted as attestations.
+		fn attesters() -> Vec<sr25519::Public>;
+
+		/// Returns the maximum number of attesters, and so of the attestations of a block.
+		fn max_attesters() -> u32;
 	}
 }
 
@@ -115,4 +194,50 @@ pub trait CommitmentFromPosition {
 
 	fn commitments(block_number: Self::BlockNumber, postion: &Position)
 		-> Option<KZGCommitment>;
+
+	/// Returns the number of extended rows of a block, zero if its commitments are unknown.
+	fn extended_rows(block_number: Self::BlockNumber) -> u32;
+}
+
+/// Splits the reward of a claim between accounts.
+pub trait RewardPayout<AccountId, Balance> {
+	/// Returns the accounts paid for the claim of `winner`, with their part of `reward`.
+	fn payout(winner: &AccountId, reward: Balance) -> Vec<(AccountId, Balance)>;
+}
+
+impl<AccountId: Clone, Balance> RewardPayout<AccountId, Balance> for () {
+	fn payout(winner: &AccountId, reward: Balance) -> Vec<(AccountId, Balance)> {
+		Vec::from([(winner.clone(), reward)])
+	}
+}
+
+/// Provides the randomness the proof-of-space challenge of a block is derived from.
+pub trait ChallengeSource {
+	/// Returns the randomness of the current epoch.
+	fn epoch_randomness() -> [u8; 32];
+
+	/// Returns the slot of the current block.
+	fn current_slot() -> u64;
+}
+
+/// Notified by the availability pallet when the authorities confirm the availability of the data
+/// of a block.
+///
+/// Pallets consuming data availability, such as the settlement of rollups, register by being
+/// listed in the `OnDataAvailable` type of the availability pallet, in a tuple when there are
+/// several of them. The hook runs within the vote confirming the data, so it should stay light.
+#[impl_trait_for_tuples::impl_for_tuples(8)]
+pub trait OnDataAvailable<BlockNumber> {
+	/// The data submitted at `at_block` was confirmed available by a share `confidence` of the
+	/// authorities. `blobs` are the app IDs and nonces of its available blobs.
+	fn on_data_available(at_block: BlockNumber, confidence: Permill, blobs: &[(u32, u32)]);
+}
+
+/// Tells the pallets consuming data availability whether a blob was confirmed available.
+pub trait DataAvailability<BlockNumber> {
+	/// Returns `true` if the blob `nonce` of `app_id` was submitted at `at_block` and the
+	/// authorities confirmed the availability of the data of the block. The confirmation is only
+	/// known during the voting window of the block, consumers that need it later record it on
+	/// [`OnDataAvailable::on_data_available`].
+	fn is_blob_available(at_block: BlockNumber, app_id: u32, nonce: u32) -> bool;
 }
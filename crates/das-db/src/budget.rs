@@ -0,0 +1,298 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A store bounding the size of the values it keeps.
+//!
+//! A local SQLite index records the size of each value and when it was last used. Once the values
+//! take more than [`BudgetConfig::max_bytes`], the least recently used ones are removed, except
+//! the pinned ones: those whose key starts with one of [`BudgetConfig::pinned_prefixes`], such as
+//! the sidecars submitted through the node, and those pinned with [`BudgetedDasDb::pin`].
+
+use crate::traits::DasKv;
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use std::sync::{Mutex, MutexGuard};
+
+/// The maximum number of values removed from the index in one query when evicting.
+const EVICTION_BATCH: i64 = 64;
+
+/// The settings of a [`BudgetedDasDb`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BudgetConfig {
+	/// The maximum size in bytes of the values kept, unbounded if `None`.
+	pub max_bytes: Option<u64>,
+	/// The prefixes of the keys whose values are never evicted.
+	pub pinned_prefixes: Vec<Vec<u8>>,
+}
+
+/// A [`DasKv`] evicting the least recently used values of `inner` beyond
+/// [`BudgetConfig::max_bytes`].
+pub struct BudgetedDasDb<DB> {
+	inner: DB,
+	index: Mutex<Connection>,
+	config: BudgetConfig,
+	total: u64,
+	clock: i64,
+}
+
+impl<DB: DasKv> BudgetedDasDb<DB> {
+	/// Creates a store over `inner`, with its index in the SQLite database at `index_path`.
+	///
+	/// Values are only evicted once indexed, the values written before the index was created are
+	/// kept.
+	pub fn new(inner: DB, index_path: &str, config: BudgetConfig) -> SqliteResult<Self> {
+		let index = Connection::open(index_path)?;
+		index.execute(
+			"CREATE TABLE IF NOT EXISTS melodot_das_budget (key BLOB PRIMARY KEY, size INTEGER NOT NULL, used_at INTEGER NOT NULL, pinned INTEGER NOT NULL)",
+			[],
+		)?;
+		index.execute(
+			"CREATE INDEX IF NOT EXISTS melodot_das_budget_lru ON melodot_das_budget (pinned, used_at)",
+			[],
+		)?;
+		let (total, clock) = index.query_row(
+			"SELECT COALESCE(SUM(size), 0), COALESCE(MAX(used_at), 0) FROM melodot_das_budget",
+			[],
+			|row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)),
+		)?;
+
+		let mut db = Self { inner, index: Mutex::new(index), config, total: total as u64, clock };
+		// The budget may have been lowered since the last run.
+		db.evict();
+		Ok(db)
+	}
+
+	/// Returns the size in bytes of the values indexed.
+	pub fn total_bytes(&self) -> u64 {
+		self.total
+	}
+
+	/// Pins the value of `key`, so that it is never evicted.
+	///
+	/// Returns `false` if the key is not indexed.
+	pub fn pin(&mut self, key: &[u8]) -> bool {
+		self.set_pinned(key, true)
+	}
+
+	/// Unpins the value of `key`, so that it can be evicted again, unless its key starts with one
+	/// of [`BudgetConfig::pinned_prefixes`].
+	///
+	/// Returns `false` if the key is not indexed.
+	pub fn unpin(&mut self, key: &[u8]) -> bool {
+		let pinned = self.is_pinned_key(key);
+		self.set_pinned(key, pinned)
+	}
+
+	/// Removes the least recently used values that are not pinned until the values fit in
+	/// [`BudgetConfig::max_bytes`].
+	///
+	/// Returns the number of values removed.
+	pub fn evict(&mut self) -> usize {
+		let max_bytes = match self.config.max_bytes {
+			Some(max_bytes) => max_bytes,
+			None => return 0,
+		};
+
+		let mut evicted = 0;
+		while self.total > max_bytes {
+			let candidates = {
+				let index = self.index();
+				let mut stmt = index
+					.prepare(
+						"SELECT key, size FROM melodot_das_budget WHERE pinned = 0 ORDER BY used_at LIMIT ?",
+					)
+					.expect("Should be able to prepare the eviction query");
+				let rows = stmt
+					.query_map(params![EVICTION_BATCH], |row| {
+						Ok((row.get::<_, Vec<u8>>(0)?, row.get::<_, i64>(1)?))
+					})
+					.expect("Should be able to query the index");
+				rows.collect::<SqliteResult<Vec<_>>>()
+					.expect("Should be able to read the index")
+			};
+			// Everything left is pinned.
+			if candidates.is_empty() {
+				break
+			}
+
+			for (key, size) in candidates {
+				if self.total <= max_bytes {
+					break
+				}
+				self.inner.remove(&key);
+				self.unindex(&key);
+				self.total = self.total.saturating_sub(size as u64);
+				evicted += 1;
+			}
+		}
+		evicted
+	}
+
+	fn index(&self) -> MutexGuard<'_, Connection> {
+		self.index.lock().expect("Lock is not poisoned; qed")
+	}
+
+	fn is_pinned_key(&self, key: &[u8]) -> bool {
+		self.config.pinned_prefixes.iter().any(|prefix| key.starts_with(prefix))
+	}
+
+	fn set_pinned(&self, key: &[u8], pinned: bool) -> bool {
+		self.index()
+			.execute("UPDATE melodot_das_budget SET pinned = ? WHERE key = ?", params![pinned, key])
+			.expect("Should be able to update the index") >
+			0
+	}
+
+	/// Returns the size of the value of `key` in the index.
+	fn size(&self, key: &[u8]) -> Option<u64> {
+		self.index()
+			.query_row("SELECT size FROM melodot_das_budget WHERE key = ?", params![key], |row| {
+				row.get::<_, i64>(0)
+			})
+			.optional()
+			.expect("Should be able to query the index")
+			.map(|size| size as u64)
+	}
+
+	/// Records that the value of `key` was just used.
+	fn touch(&mut self, key: &[u8]) {
+		self.clock += 1;
+		self.index()
+			.execute(
+				"UPDATE melodot_das_budget SET used_at = ? WHERE key = ?",
+				params![self.clock, key],
+			)
+			.expect("Should be able to update the index");
+	}
+
+	/// Indexes a value of `size` bytes just written under `key`, and evicts values if needed.
+	fn index_value(&mut self, key: &[u8], size: usize) {
+		let old = self.size(key).unwrap_or_default();
+		self.clock += 1;
+		// A value pinned with `pin` stays pinned when it is written again.
+		self.index()
+			.execute(
+				"INSERT INTO melodot_das_budget (key, size, used_at, pinned) VALUES (?,?,?,?) ON CONFLICT(key) DO UPDATE SET size = excluded.size, used_at = excluded.used_at",
+				params![key, size as i64, self.clock, self.is_pinned_key(key)],
+			)
+			.expect("Should be able to insert or update a key in the index");
+		self.total = self.total.saturating_sub(old).saturating_add(size as u64);
+		self.evict();
+	}
+
+	fn unindex(&self, key: &[u8]) {
+		self.index()
+			.execute("DELETE FROM melodot_das_budget WHERE key = ?", params![key])
+			.expect("Should be able to delete from the index");
+	}
+}
+
+impl<DB: DasKv> DasKv for BudgetedDasDb<DB> {
+	fn get(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+		let value = self.inner.get(key)?;
+		self.touch(key);
+		Some(value)
+	}
+
+	fn set(&mut self, key: &[u8], value: &[u8]) {
+		self.inner.set(key, value);
+		self.index_value(key, value.len());
+	}
+
+	fn remove(&mut self, key: &[u8]) {
+		self.inner.remove(key);
+		if let Some(size) = self.size(key) {
+			self.unindex(key);
+			self.total = self.total.saturating_sub(size);
+		}
+	}
+
+	fn contains(&mut self, key: &[u8]) -> bool {
+		self.inner.contains(key)
+	}
+
+	fn compare_and_set(&mut self, key: &[u8], old_value: Option<&[u8]>, new_value: &[u8]) -> bool {
+		if !self.inner.compare_and_set(key, old_value, new_value) {
+			return false
+		}
+		self.index_value(key, new_value.len());
+		true
+	}
+
+	#[cfg(feature = "std")]
+	fn snapshot(&mut self, path: &std::path::Path) -> Result<(), String> {
+		self.inner.snapshot(path)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock_db::MockDb;
+
+	fn new_db(max_bytes: u64) -> BudgetedDasDb<MockDb> {
+		let config =
+			BudgetConfig { max_bytes: Some(max_bytes), pinned_prefixes: vec![b"sidecar".to_vec()] };
+		BudgetedDasDb::new(MockDb::new(), ":memory:", config).unwrap()
+	}
+
+	#[test]
+	fn test_evicts_least_recently_used() {
+		let mut db = new_db(30);
+		db.set(b"key1", &[1; 10]);
+		db.set(b"key2", &[2; 10]);
+		db.set(b"key3", &[3; 10]);
+		assert_eq!(db.total_bytes(), 30);
+
+		// Reading `key1` makes `key2` the least recently used value.
+		assert!(db.get(b"key1").is_some());
+		db.set(b"key4", &[4; 10]);
+		assert!(!db.contains(b"key2"));
+		assert!(db.contains(b"key1"));
+		assert_eq!(db.total_bytes(), 30);
+
+		// Replacing a value only counts its new size.
+		db.set(b"key1", &[1; 5]);
+		assert_eq!(db.total_bytes(), 25);
+
+		db.remove(b"key3");
+		assert_eq!(db.total_bytes(), 15);
+		assert!(db.compare_and_set(b"key5", None, &[5; 20]));
+		assert!(!db.contains(b"key4"));
+		assert!(db.contains(b"key1"));
+		assert_eq!(db.total_bytes(), 25);
+	}
+
+	#[test]
+	fn test_pinned_values_are_kept() {
+		let mut db = new_db(20);
+		db.set(b"sidecar1", &[1; 10]);
+		db.set(b"key1", &[2; 10]);
+		assert!(db.pin(b"key1"));
+		assert!(!db.pin(b"key2"));
+
+		// Pinned values stay even beyond the budget.
+		db.set(b"key2", &[3; 10]);
+		assert!(db.contains(b"sidecar1"));
+		assert!(db.contains(b"key1"));
+		assert!(!db.contains(b"key2"));
+
+		assert!(db.unpin(b"key1"));
+		assert!(db.unpin(b"sidecar1"));
+		db.set(b"key3", &[4; 10]);
+		assert!(!db.contains(b"key1"));
+		assert!(db.contains(b"sidecar1"));
+		assert!(db.contains(b"key3"));
+		assert_eq!(db.total_bytes(), 20);
+	}
+}
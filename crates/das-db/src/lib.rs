@@ -28,4 +28,10 @@ pub mod offchain;
 #[cfg(feature = "outside")]
 pub mod offchain_outside;
 #[cfg(feature = "std")]
-pub mod mock_db;
\ No newline at end of file
+pub mod mock_db;
+#[cfg(feature = "tiered")]
+pub mod tiered;
+#[cfg(feature = "budget")]
+pub mod budget;
+#[cfg(feature = "s3")]
+pub mod s3_store;
\ No newline at end of file
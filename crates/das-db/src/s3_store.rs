@@ -0,0 +1,81 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::tiered::ColdStore;
+use s3::{creds::Credentials, Bucket, Region};
+
+/// A [`ColdStore`] in a bucket of an S3-compatible object storage.
+///
+/// Each value is an object named after the hex encoding of its key, under `prefix`.
+pub struct S3ColdStore {
+	bucket: Bucket,
+	prefix: String,
+}
+
+impl S3ColdStore {
+	/// Creates a store in `bucket` of the region `region`, or of the S3-compatible service at
+	/// `endpoint` if set. The credentials are read from the environment, such as
+	/// `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY`, or from the AWS profile.
+	pub fn new(
+		bucket: &str,
+		region: &str,
+		endpoint: Option<&str>,
+		prefix: &str,
+	) -> Result<Self, String> {
+		let region = match endpoint {
+			Some(endpoint) =>
+				Region::Custom { region: region.to_string(), endpoint: endpoint.to_string() },
+			None => region.parse().map_err(|e| format!("Invalid region {}: {:?}", region, e))?,
+		};
+		let credentials = Credentials::default().map_err(|e| e.to_string())?;
+		let mut bucket = Bucket::new(bucket, region, credentials).map_err(|e| e.to_string())?;
+		if endpoint.is_some() {
+			// S3-compatible services, such as MinIO, usually do not serve virtual-hosted buckets.
+			bucket = bucket.with_path_style();
+		}
+		Ok(Self { bucket, prefix: prefix.trim_end_matches('/').to_string() })
+	}
+
+	fn path(&self, key: &[u8]) -> String {
+		let name = key.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+		format!("{}/{}", self.prefix, name)
+	}
+}
+
+impl ColdStore for S3ColdStore {
+	fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), String> {
+		let response = self.bucket.put_object(self.path(key), value).map_err(|e| e.to_string())?;
+		match response.status_code() {
+			200..=299 => Ok(()),
+			code => Err(format!("Failed to put object, status {}", code)),
+		}
+	}
+
+	fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, String> {
+		let response = self.bucket.get_object(self.path(key)).map_err(|e| e.to_string())?;
+		match response.status_code() {
+			200..=299 => Ok(Some(response.bytes().to_vec())),
+			404 => Ok(None),
+			code => Err(format!("Failed to get object, status {}", code)),
+		}
+	}
+
+	fn delete(&mut self, key: &[u8]) -> Result<(), String> {
+		let response = self.bucket.delete_object(self.path(key)).map_err(|e| e.to_string())?;
+		match response.status_code() {
+			200..=299 | 404 => Ok(()),
+			code => Err(format!("Failed to delete object, status {}", code)),
+		}
+	}
+}
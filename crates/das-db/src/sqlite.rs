@@ -14,7 +14,10 @@
 
 use crate::traits::DasKv;
 use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
-use std::{path::PathBuf, sync::Mutex};
+use std::{
+	path::{Path, PathBuf},
+	sync::Mutex,
+};
 
 #[derive(Debug)]
 pub struct SqliteDasDb {
@@ -30,6 +33,61 @@ impl SqliteDasDb {
 		)?;
 		Ok(SqliteDasDb { conn: Mutex::new(conn) })
 	}
+
+	/// Calls `f` with every key-value pair of the database, in key order.
+	pub fn for_each<F: FnMut(&[u8], &[u8])>(&self, mut f: F) -> SqliteResult<()> {
+		let conn = self.conn.lock().unwrap();
+		let mut stmt = conn.prepare("SELECT key, value FROM melodot_das_kvs ORDER BY key")?;
+		let mut rows = stmt.query([])?;
+		while let Some(row) = rows.next()? {
+			let key: Vec<u8> = row.get(0)?;
+			let value: Vec<u8> = row.get(1)?;
+			f(&key, &value);
+		}
+		Ok(())
+	}
+
+	/// Returns the number of entries of the database and their size in bytes.
+	pub fn size(&self) -> SqliteResult<(u64, u64)> {
+		let conn = self.conn.lock().unwrap();
+		conn.query_row(
+			"SELECT COUNT(*), COALESCE(SUM(LENGTH(key) + LENGTH(value)), 0) FROM melodot_das_kvs",
+			[],
+			|row| Ok((row.get::<_, i64>(0)? as u64, row.get::<_, i64>(1)? as u64)),
+		)
+	}
+
+	/// Rebuilds the database file, reclaiming the space of the removed values.
+	pub fn compact(&self) -> SqliteResult<()> {
+		self.conn.lock().unwrap().execute_batch("VACUUM")
+	}
+
+	/// Checks the structure of the database file, returning the problems found.
+	pub fn integrity_check(&self) -> SqliteResult<Vec<String>> {
+		let conn = self.conn.lock().unwrap();
+		let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+		let problems = stmt
+			.query_map([], |row| row.get::<_, String>(0))?
+			.collect::<SqliteResult<Vec<_>>>()?;
+		Ok(problems.into_iter().filter(|problem| problem != "ok").collect())
+	}
+
+	/// Copies all the entries of the snapshot at `path` into the database, in a single transaction,
+	/// replacing the values of the keys present in both. Returns the number of entries copied.
+	///
+	/// The snapshot is not verified, see [`Self::integrity_check`].
+	pub fn import(&mut self, path: &Path) -> SqliteResult<usize> {
+		let path = path.to_string_lossy();
+		let conn = self.conn.lock().unwrap();
+		conn.execute("ATTACH DATABASE ? AS snapshot", params![path])?;
+		let copied = conn.execute(
+			"INSERT OR REPLACE INTO melodot_das_kvs (key, value)
+				SELECT key, value FROM snapshot.melodot_das_kvs",
+			[],
+		);
+		conn.execute("DETACH DATABASE snapshot", [])?;
+		copied
+	}
 }
 
 impl Default for SqliteDasDb {
@@ -58,6 +116,29 @@ impl DasKv for SqliteDasDb {
 		.expect("Should be able to insert or replace a value in the database");
 	}
 
+	fn set_batch(&mut self, entries: &[(Vec<u8>, Vec<u8>)]) {
+		let mut conn = self.conn.lock().unwrap();
+		let tx = conn.transaction().expect("Should be able to start a transaction");
+		{
+			let mut stmt = tx
+				.prepare_cached("INSERT OR REPLACE INTO melodot_das_kvs (key, value) VALUES (?,?)")
+				.expect("Should be able to prepare the insert statement");
+			for (key, value) in entries {
+				stmt.execute(params![key, value])
+					.expect("Should be able to insert or replace a value in the database");
+			}
+		}
+		tx.commit().expect("Should be able to commit the transaction");
+	}
+
+	#[cfg(feature = "std")]
+	fn snapshot(&mut self, path: &Path) -> Result<(), String> {
+		let conn = self.conn.lock().unwrap();
+		conn.execute("VACUUM INTO ?", params![path.to_string_lossy()])
+			.map(|_| ())
+			.map_err(|e| e.to_string())
+	}
+
 	fn remove(&mut self, key: &[u8]) {
 		let conn = self.conn.lock().unwrap();
 		conn.execute("DELETE FROM melodot_das_kvs WHERE key = ?", params![key])
@@ -197,4 +278,63 @@ mod tests {
 		assert!(db.compare_and_set(key, None, old_value));
 		assert_eq!(db.get(key), Some(old_value.to_vec()));
 	}
-}
\ No newline at end of file
+
+	#[test]
+	fn test_set_batch() {
+		let mut db = SqliteDasDb::new(":memory:").unwrap();
+		db.set(b"key1", b"old");
+
+		db.set_batch(&[
+			(b"key1".to_vec(), b"value1".to_vec()),
+			(b"key2".to_vec(), b"value2".to_vec()),
+			(b"key2".to_vec(), b"value3".to_vec()),
+		]);
+		assert_eq!(db.get(b"key1"), Some(b"value1".to_vec()));
+		// Later entries of the batch replace earlier ones.
+		assert_eq!(db.get(b"key2"), Some(b"value3".to_vec()));
+	}
+
+	#[test]
+	fn test_snapshot_and_import() {
+		let dir =
+			std::env::temp_dir().join(format!("melodot-das-db-snapshot-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+		let path = dir.join("snapshot.sqlite3");
+		let _ = std::fs::remove_file(&path);
+
+		let mut db = SqliteDasDb::new(":memory:").unwrap();
+		db.set(b"key1", b"value1");
+		db.set(b"key2", b"value2");
+		db.snapshot(&path).unwrap();
+		// A snapshot never overwrites a file.
+		assert!(db.snapshot(&path).is_err());
+
+		let snapshot = SqliteDasDb::new(path.to_str().unwrap()).unwrap();
+		assert!(snapshot.integrity_check().unwrap().is_empty());
+		assert_eq!(snapshot.size().unwrap(), (2, 20));
+
+		let mut restored = SqliteDasDb::new(":memory:").unwrap();
+		restored.set(b"key1", b"old");
+		restored.set(b"key3", b"value3");
+		assert_eq!(restored.import(&path).unwrap(), 2);
+		assert_eq!(restored.get(b"key1"), Some(b"value1".to_vec()));
+		assert_eq!(restored.get(b"key2"), Some(b"value2".to_vec()));
+		assert_eq!(restored.get(b"key3"), Some(b"value3".to_vec()));
+
+		std::fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn test_for_each() {
+		let mut db = SqliteDasDb::new(":memory:").unwrap();
+		db.set(b"key2", b"value2");
+		db.set(b"key1", b"value1");
+
+		let mut entries = Vec::new();
+		db.for_each(|key, value| entries.push((key.to_vec(), value.to_vec()))).unwrap();
+		assert_eq!(
+			entries,
+			vec![(b"key1".to_vec(), b"value1".to_vec()), (b"key2".to_vec(), b"value2".to_vec())]
+		);
+	}
+}
@@ -0,0 +1,306 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A store keeping recent values locally and older ones in a cold store, such as an S3 bucket.
+//!
+//! Every value is first written to the hot store. A local SQLite index records when each key was
+//! written and in which tier its value is. Values older than [`TieredConfig::max_age`] are moved
+//! to the cold store in batches, at most once per [`TieredConfig::offload_interval`], and are
+//! fetched back from it when read.
+
+use crate::traits::DasKv;
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// An object store holding the values moved out of the hot store.
+pub trait ColdStore {
+	/// Stores `value` under `key`, replacing any previous object.
+	fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), String>;
+
+	/// Returns the object stored under `key`, or `None` if there is none.
+	fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, String>;
+
+	/// Deletes the object stored under `key`, if any.
+	fn delete(&mut self, key: &[u8]) -> Result<(), String>;
+}
+
+/// The settings of a [`TieredDasDb`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TieredConfig {
+	/// The age after which a value is moved to the cold store.
+	pub max_age: Duration,
+	/// The minimum time between two passes moving values to the cold store.
+	pub offload_interval: Duration,
+	/// The maximum number of values moved to the cold store in one pass.
+	pub batch_size: usize,
+}
+
+impl Default for TieredConfig {
+	fn default() -> Self {
+		Self {
+			max_age: Duration::from_secs(7 * 24 * 60 * 60),
+			offload_interval: Duration::from_secs(60),
+			batch_size: 256,
+		}
+	}
+}
+
+/// A [`DasKv`] moving the values older than [`TieredConfig::max_age`] from `hot` to `cold`.
+pub struct TieredDasDb<H, C> {
+	hot: H,
+	cold: C,
+	index: Connection,
+	config: TieredConfig,
+	last_offload: u64,
+}
+
+impl<H: DasKv, C: ColdStore> TieredDasDb<H, C> {
+	/// Creates a store over `hot` and `cold`, with its index in the SQLite database at
+	/// `index_path`.
+	pub fn new(hot: H, cold: C, index_path: &str, config: TieredConfig) -> SqliteResult<Self> {
+		let index = Connection::open(index_path)?;
+		index.execute(
+			"CREATE TABLE IF NOT EXISTS melodot_das_tiers (key BLOB PRIMARY KEY, stored_at INTEGER NOT NULL, cold INTEGER NOT NULL)",
+			[],
+		)?;
+		index.execute(
+			"CREATE INDEX IF NOT EXISTS melodot_das_tiers_age ON melodot_das_tiers (cold, stored_at)",
+			[],
+		)?;
+		Ok(Self { hot, cold, index, config, last_offload: 0 })
+	}
+
+	/// Moves up to [`TieredConfig::batch_size`] values written before `now - max_age` to the
+	/// cold store, `now` being in seconds since the Unix epoch.
+	///
+	/// Returns the number of values moved. A value whose upload fails is kept in the hot store
+	/// and moved on a later pass.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the index cannot be read, or if no value could be moved.
+	pub fn offload(&mut self, now: u64) -> Result<usize, String> {
+		let cutoff = now.saturating_sub(self.config.max_age.as_secs());
+		let keys = {
+			let mut stmt = self
+				.index
+				.prepare(
+					"SELECT key FROM melodot_das_tiers WHERE cold = 0 AND stored_at < ? ORDER BY stored_at LIMIT ?",
+				)
+				.map_err(|e| e.to_string())?;
+			let rows = stmt
+				.query_map(params![cutoff as i64, self.config.batch_size as i64], |row| {
+					row.get::<_, Vec<u8>>(0)
+				})
+				.map_err(|e| e.to_string())?;
+			rows.collect::<SqliteResult<Vec<_>>>().map_err(|e| e.to_string())?
+		};
+
+		let mut moved = 0;
+		let mut last_err = None;
+		for key in keys.iter() {
+			let value = match self.hot.get(key) {
+				Some(value) => value,
+				None => {
+					self.unindex(key);
+					continue
+				},
+			};
+			if let Err(e) = self.cold.put(key, &value) {
+				last_err = Some(e);
+				continue
+			}
+			self.index
+				.execute("UPDATE melodot_das_tiers SET cold = 1 WHERE key = ?", params![key])
+				.map_err(|e| e.to_string())?;
+			self.hot.remove(key);
+			moved += 1;
+		}
+
+		match last_err {
+			Some(e) if moved == 0 => Err(e),
+			_ => Ok(moved),
+		}
+	}
+
+	/// Returns whether the value of `key` is in the cold store.
+	pub fn is_cold(&self, key: &[u8]) -> bool {
+		self.tier(key) == Some(true)
+	}
+
+	/// Returns the tier of `key` in the index, `true` for the cold store.
+	fn tier(&self, key: &[u8]) -> Option<bool> {
+		self.index
+			.query_row("SELECT cold FROM melodot_das_tiers WHERE key = ?", params![key], |row| {
+				row.get::<_, bool>(0)
+			})
+			.optional()
+			.expect("Should be able to query the index")
+	}
+
+	fn unindex(&self, key: &[u8]) {
+		self.index
+			.execute("DELETE FROM melodot_das_tiers WHERE key = ?", params![key])
+			.expect("Should be able to delete from the index");
+	}
+
+	/// Runs an offload pass if the last one is older than [`TieredConfig::offload_interval`].
+	fn maybe_offload(&mut self) {
+		let now = now();
+		if now.saturating_sub(self.last_offload) < self.config.offload_interval.as_secs() {
+			return
+		}
+		self.last_offload = now;
+		// Values that could not be moved stay in the hot store and are tried again.
+		let _ = self.offload(now);
+	}
+}
+
+impl<H: DasKv, C: ColdStore> DasKv for TieredDasDb<H, C> {
+	fn get(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+		match self.hot.get(key) {
+			Some(value) => Some(value),
+			None if self.is_cold(key) => self.cold.get(key).ok().flatten(),
+			None => None,
+		}
+	}
+
+	fn set(&mut self, key: &[u8], value: &[u8]) {
+		if self.is_cold(key) {
+			let _ = self.cold.delete(key);
+		}
+		self.hot.set(key, value);
+		self.index
+			.execute(
+				"INSERT OR REPLACE INTO melodot_das_tiers (key, stored_at, cold) VALUES (?,?,0)",
+				params![key, now() as i64],
+			)
+			.expect("Should be able to insert or replace a key in the index");
+		self.maybe_offload();
+	}
+
+	fn remove(&mut self, key: &[u8]) {
+		if self.is_cold(key) {
+			let _ = self.cold.delete(key);
+		}
+		self.hot.remove(key);
+		self.unindex(key);
+	}
+
+	fn contains(&mut self, key: &[u8]) -> bool {
+		self.hot.contains(key) || self.is_cold(key)
+	}
+
+	fn compare_and_set(&mut self, key: &[u8], old_value: Option<&[u8]>, new_value: &[u8]) -> bool {
+		if self.get(key).as_deref() != old_value {
+			return false
+		}
+		self.set(key, new_value);
+		true
+	}
+}
+
+fn now() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock_db::MockDb;
+	use std::collections::HashMap;
+
+	#[derive(Default)]
+	struct MemoryColdStore {
+		objects: HashMap<Vec<u8>, Vec<u8>>,
+		fail: bool,
+	}
+
+	impl ColdStore for MemoryColdStore {
+		fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), String> {
+			if self.fail {
+				return Err("unavailable".to_string())
+			}
+			self.objects.insert(key.to_vec(), value.to_vec());
+			Ok(())
+		}
+
+		fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, String> {
+			Ok(self.objects.get(key).cloned())
+		}
+
+		fn delete(&mut self, key: &[u8]) -> Result<(), String> {
+			self.objects.remove(key);
+			Ok(())
+		}
+	}
+
+	fn new_db(fail: bool) -> TieredDasDb<MockDb, MemoryColdStore> {
+		let config = TieredConfig {
+			max_age: Duration::from_secs(100),
+			offload_interval: Duration::from_secs(u64::MAX),
+			batch_size: 1,
+		};
+		let cold = MemoryColdStore { fail, ..Default::default() };
+		let mut db = TieredDasDb::new(MockDb::new(), cold, ":memory:", config).unwrap();
+		db.last_offload = now();
+		db
+	}
+
+	#[test]
+	fn test_offload_and_fetch_back() {
+		let mut db = new_db(false);
+		db.set(b"key1", b"value1");
+		db.set(b"key2", b"value2");
+
+		// Nothing is old enough yet.
+		assert_eq!(db.offload(now()), Ok(0));
+
+		// One value per pass.
+		let later = now() + 1000;
+		assert_eq!(db.offload(later), Ok(1));
+		assert_eq!(db.offload(later), Ok(1));
+		assert_eq!(db.offload(later), Ok(0));
+
+		assert!(db.is_cold(b"key1"));
+		assert!(!db.hot.contains(b"key1"));
+		assert_eq!(db.cold.objects.len(), 2);
+
+		// Values are read back from the cold store.
+		assert!(db.contains(b"key1"));
+		assert_eq!(db.get(b"key1"), Some(b"value1".to_vec()));
+		assert!(db.compare_and_set(b"key2", Some(b"value2"), b"value3"));
+		assert!(!db.is_cold(b"key2"));
+		assert_eq!(db.get(b"key2"), Some(b"value3".to_vec()));
+		assert!(!db.cold.objects.contains_key(&b"key2".to_vec()));
+
+		db.remove(b"key1");
+		assert!(!db.contains(b"key1"));
+		assert!(db.cold.objects.is_empty());
+	}
+
+	#[test]
+	fn test_failed_upload_stays_hot() {
+		let mut db = new_db(true);
+		db.set(b"key", b"value");
+
+		assert!(db.offload(now() + 1000).is_err());
+		assert!(!db.is_cold(b"key"));
+		assert_eq!(db.get(b"key"), Some(b"value".to_vec()));
+
+		db.cold.fail = false;
+		assert_eq!(db.offload(now() + 1000), Ok(1));
+		assert_eq!(db.get(b"key"), Some(b"value".to_vec()));
+	}
+}
@@ -34,6 +34,19 @@ pub trait DasKv {
     /// * `value` - A byte slice representing the value to associate with the key.
     fn set(&mut self, key: &[u8], value: &[u8]);
 
+    /// Sets the values of several keys in the store.
+    ///
+    /// Stores backed by a database override it to write all the entries at once.
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - The key-value pairs to set, in the order they are written.
+    fn set_batch(&mut self, entries: &[(Vec<u8>, Vec<u8>)]) {
+        for (key, value) in entries {
+            self.set(key, value);
+        }
+    }
+
     /// Removes the value associated with the given key from the store.
     ///
     /// # Arguments
@@ -65,4 +78,16 @@ pub trait DasKv {
     ///
     /// `true` if the comparison was successful and the value was set, `false` otherwise.
     fn compare_and_set(&mut self, key: &[u8], old_value: Option<&[u8]>, new_value: &[u8]) -> bool;
+
+    /// Writes a consistent copy of the store to `path`, to back it up or move it to another machine.
+    ///
+    /// Stores which can not be copied return an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the copy, which must not exist.
+    #[cfg(feature = "std")]
+    fn snapshot(&mut self, _path: &std::path::Path) -> Result<(), String> {
+        Err("Snapshots are not supported by this store".to_string())
+    }
 }
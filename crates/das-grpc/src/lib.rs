@@ -0,0 +1,286 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! gRPC gateway of the data availability services of a Melodot node.
+//!
+//! Many rollup sequencer stacks speak gRPC rather than JSON-RPC. [`DasGateway`] serves the
+//! `melodot.das.v1.Das` service defined in `proto/das.proto`, whose methods call the same
+//! implementations as the `das_*` JSON-RPC methods of `melo-das-rpc`, so both interfaces validate,
+//! store and publish blobs the same way.
+
+use codec::Encode;
+use futures::{
+	future,
+	lock::Mutex,
+	stream::{self, Stream, StreamExt},
+};
+use jsonrpsee::{core::Error as JsonRpseeError, types::error::CallError};
+use log::{error, info};
+use melo_core_primitives::{error_code, traits::AppDataApi, KZGCommitment};
+use melo_das_db::traits::DasKv;
+use melo_das_rpc::{
+	BlobTxError, Confidence, ConfidenceApiServer, GetBlob, GetBlobApiServer, PoolRejection,
+	SubmitBlob, SubmitBlobApiServer,
+};
+use melo_daser::{BackfillProgress, DasNetworkOperations};
+use melodot_runtime::RuntimeCall;
+use sc_transaction_pool_api::{TransactionPool, TransactionStatus};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::H256;
+use std::{net::SocketAddr, pin::Pin, sync::Arc};
+use tonic::{transport::Server, Code, Request, Response, Status};
+
+/// The types and services generated from `proto/das.proto`.
+pub mod proto {
+	tonic::include_proto!("melodot.das.v1");
+}
+
+use proto::{
+	blob_tx_error::Kind,
+	das_server::{Das, DasServer},
+	GetBlobRequest, GetBlobResponse, GetConfidenceRequest, GetConfidenceResponse, StatusUpdate,
+	SubmitBlobRequest, SubmitBlobResponse, TxStatus,
+};
+
+/// The metadata key of the JSON-RPC error code of a failed call.
+pub const ERROR_CODE_METADATA: &str = "das-error-code";
+
+/// The gRPC gateway, proxying into the JSON-RPC implementations of `melo-das-rpc`.
+pub struct DasGateway<P: TransactionPool, C, D, DB> {
+	submit: SubmitBlob<P, C, P::Block, D, DB>,
+	get_blob: GetBlob<D, DB>,
+	confidence: Confidence<DB, H256, D>,
+}
+
+impl<P: TransactionPool, C, D, DB> DasGateway<P, C, D, DB>
+where
+	DB: DasKv + 'static,
+{
+	/// Creates a new [`DasGateway`] instance.
+	pub fn new(
+		client: Arc<C>,
+		pool: Arc<P>,
+		das_network: Arc<D>,
+		database: Arc<Mutex<DB>>,
+		backfill: Arc<BackfillProgress>,
+	) -> Self {
+		Self {
+			submit: SubmitBlob::new(client, pool, das_network.clone(), database.clone()),
+			get_blob: GetBlob::new(das_network.clone(), database.clone()),
+			confidence: Confidence::new(&database, &das_network, &backfill),
+		}
+	}
+}
+
+#[tonic::async_trait]
+impl<P, C, D, DB> Das for DasGateway<P, C, D, DB>
+where
+	P: TransactionPool + 'static,
+	C: ProvideRuntimeApi<P::Block> + HeaderBackend<P::Block> + 'static + Sync + Send,
+	C::Api: AppDataApi<P::Block, RuntimeCall>,
+	D: DasNetworkOperations + Sync + Send + 'static + Clone,
+	DB: DasKv + Send + Sync + 'static,
+{
+	type SubscribeStatusStream = Pin<Box<dyn Stream<Item = Result<StatusUpdate, Status>> + Send>>;
+
+	async fn submit_blob(
+		&self,
+		request: Request<SubmitBlobRequest>,
+	) -> Result<Response<SubmitBlobResponse>, Status> {
+		let SubmitBlobRequest { data, extrinsic } = request.into_inner();
+		let status = self
+			.submit
+			.submit_blob_tx(data.into(), extrinsic.into(), None)
+			.await
+			.map_err(into_status)?;
+
+		Ok(Response::new(SubmitBlobResponse {
+			tx_hash: status.tx_hash.encode(),
+			error: status.err.as_ref().map(Into::into),
+		}))
+	}
+
+	async fn get_blob(
+		&self,
+		request: Request<GetBlobRequest>,
+	) -> Result<Response<GetBlobResponse>, Status> {
+		let GetBlobRequest { app_id, nonce, commitments } = request.into_inner();
+		let commitments = commitments
+			.iter()
+			.map(|bytes| {
+				<[u8; 48]>::try_from(&bytes[..])
+					.map_err(|_| format!("Invalid commitment length {}", bytes.len()))
+					.and_then(|bytes| KZGCommitment::try_from_bytes(&bytes))
+			})
+			.collect::<Result<Vec<_>, _>>()
+			.map_err(Status::invalid_argument)?;
+
+		let data = self
+			.get_blob
+			.get_blob(app_id, nonce, commitments, None)
+			.await
+			.map_err(into_status)?;
+
+		Ok(Response::new(GetBlobResponse {
+			found: data.is_some(),
+			data: data.map(|data| data.0).unwrap_or_default(),
+		}))
+	}
+
+	async fn get_confidence(
+		&self,
+		request: Request<GetConfidenceRequest>,
+	) -> Result<Response<GetConfidenceResponse>, Status> {
+		let block_hash = request.into_inner().block_hash;
+		if block_hash.len() != H256::len_bytes() {
+			return Err(Status::invalid_argument(format!(
+				"Invalid block hash length {}",
+				block_hash.len()
+			)))
+		}
+		let block_hash = H256::from_slice(&block_hash);
+
+		let confidence = self.confidence.block_confidence(block_hash).await.map_err(into_status)?;
+		let available = self.confidence.is_available(block_hash).await.map_err(into_status)?;
+
+		Ok(Response::new(GetConfidenceResponse {
+			sampled: confidence.is_some(),
+			confidence: confidence.unwrap_or_default(),
+			available: available.unwrap_or_default(),
+		}))
+	}
+
+	async fn subscribe_status(
+		&self,
+		request: Request<SubmitBlobRequest>,
+	) -> Result<Response<Self::SubscribeStatusStream>, Status> {
+		let SubmitBlobRequest { data, extrinsic } = request.into_inner();
+		let (tx_hash, err, watcher) = self
+			.submit
+			.submit_and_watch(data.into(), extrinsic.into())
+			.await
+			.map_err(into_status)?;
+
+		let tx_hash = tx_hash.encode();
+		let submitted = StatusUpdate {
+			status: TxStatus::Submitted as i32,
+			tx_hash: tx_hash.clone(),
+			hash: Vec::new(),
+			error: err.as_ref().map(Into::into),
+		};
+
+		// A rejected transaction is not watched, its stream ends with the submission.
+		let updates = stream::iter(watcher)
+			.flatten()
+			.map(move |status| Ok(status_update(status, &tx_hash)));
+
+		Ok(Response::new(Box::pin(stream::once(future::ready(Ok(submitted))).chain(updates))))
+	}
+}
+
+/// Serves `gateway` on `addr`, until the future is dropped or the server fails.
+pub async fn serve<P, C, D, DB>(addr: SocketAddr, gateway: DasGateway<P, C, D, DB>)
+where
+	P: TransactionPool + 'static,
+	C: ProvideRuntimeApi<P::Block> + HeaderBackend<P::Block> + 'static + Sync + Send,
+	C::Api: AppDataApi<P::Block, RuntimeCall>,
+	D: DasNetworkOperations + Sync + Send + 'static + Clone,
+	DB: DasKv + Send + Sync + 'static,
+{
+	info!("📡 DAS gRPC gateway listening on {}", addr);
+	if let Err(e) = Server::builder().add_service(DasServer::new(gateway)).serve(addr).await {
+		error!("❌ DAS gRPC gateway stopped: {:?}", e);
+	}
+}
+
+/// Converts the error of a JSON-RPC call to a gRPC status, keeping its code in the
+/// [`ERROR_CODE_METADATA`] metadata.
+fn into_status(e: JsonRpseeError) -> Status {
+	let err = match e {
+		JsonRpseeError::Call(CallError::Custom(err)) => err,
+		e => return Status::internal(e.to_string()),
+	};
+
+	let code = match err.code() {
+		error_code::FETCH_TRANSACTION_METADATA_FAILED |
+		error_code::TRANSACTION_PUSH_FAILED |
+		error_code::FETCH_BLOB_FAILED |
+		error_code::PUBLISH_BLOB_FAILED => Code::Unavailable,
+		error_code::QUOTA_EXCEEDED | error_code::BYTES_QUOTA_EXCEEDED => Code::ResourceExhausted,
+		_ => Code::InvalidArgument,
+	};
+	let message = match err.data() {
+		Some(data) => format!("{}: {}", err.message(), data.get()),
+		None => err.message().to_string(),
+	};
+
+	let mut status = Status::new(code, message);
+	status.metadata_mut().insert(ERROR_CODE_METADATA, err.code().into());
+	status
+}
+
+/// Converts a status of the transaction pool to a [`StatusUpdate`] of the transaction `tx_hash`.
+fn status_update<Hash: Encode, BlockHash: Encode>(
+	status: TransactionStatus<Hash, BlockHash>,
+	tx_hash: &[u8],
+) -> StatusUpdate {
+	let (status, hash) = match status {
+		TransactionStatus::Future => (TxStatus::Future, None),
+		TransactionStatus::Ready => (TxStatus::Ready, None),
+		TransactionStatus::Broadcast(_) => (TxStatus::Broadcast, None),
+		TransactionStatus::InBlock((block, _)) => (TxStatus::InBlock, Some(block.encode())),
+		TransactionStatus::Retracted(block) => (TxStatus::Retracted, Some(block.encode())),
+		TransactionStatus::FinalityTimeout(block) =>
+			(TxStatus::FinalityTimeout, Some(block.encode())),
+		TransactionStatus::Finalized((block, _)) => (TxStatus::Finalized, Some(block.encode())),
+		TransactionStatus::Usurped(hash) => (TxStatus::Usurped, Some(hash.encode())),
+		TransactionStatus::Dropped => (TxStatus::Dropped, None),
+		TransactionStatus::Invalid => (TxStatus::Invalid, None),
+	};
+
+	StatusUpdate {
+		status: status as i32,
+		tx_hash: tx_hash.to_vec(),
+		hash: hash.unwrap_or_default(),
+		error: None,
+	}
+}
+
+impl From<&BlobTxError> for proto::BlobTxError {
+	fn from(err: &BlobTxError) -> Self {
+		let (kind, reason, message) = match err {
+			BlobTxError::DhtPutFailed { message } =>
+				(Kind::DhtPutFailed, PoolRejection::Invalid, message),
+			BlobTxError::DhtDegraded { message } =>
+				(Kind::DhtDegraded, PoolRejection::Invalid, message),
+			BlobTxError::PoolRejected { reason, message } => (Kind::PoolRejected, *reason, message),
+		};
+		let reason = match reason {
+			PoolRejection::StaleNonce => proto::PoolRejection::StaleNonce,
+			PoolRejection::FutureNonce => proto::PoolRejection::FutureNonce,
+			PoolRejection::PriorityTooLow => proto::PoolRejection::PriorityTooLow,
+			PoolRejection::AlreadyImported => proto::PoolRejection::AlreadyImported,
+			PoolRejection::Invalid => proto::PoolRejection::Invalid,
+		};
+
+		Self {
+			kind: kind as i32,
+			reason: reason as i32,
+			message: message.clone(),
+			code: err.code(),
+			retriable: err.is_retriable(),
+		}
+	}
+}
@@ -12,32 +12,82 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+//! The transport of the DHT operations of the DAS services.
+//!
+//! [`DasDht`] is implemented by the DAS network service, and by [`InMemoryDht`], a simulated
+//! network with controllable latency and faults for the tests of the services running on it.
+
 use async_trait::async_trait;
 pub use sc_network::{DhtEvent, KademliaKey, NetworkDHTProvider, NetworkSigner, NetworkStateInfo};
 use std::fmt::Debug;
 
-pub use melo_das_network::Service as DasDhtService;
+pub use melo_das_network::{PeerId, Service as DasDhtService};
+
+mod memory;
+
+pub use memory::{Fault, InMemoryDht, DEFAULT_REPLICATION};
 
-/// `DasDht` trait provides an asynchronous interface for interacting with the DHT (Distributed Hash Table).
+/// `DasDht` trait provides an asynchronous interface for interacting with the DHT (Distributed Hash
+/// Table).
 #[async_trait]
-pub trait DasDht: Send + Debug + 'static {
-    /// Asynchronously puts a key-value pair into the DHT.
-    ///
-    /// # Arguments
-    /// 
-    /// * `key` - The key to be inserted into the DHT.
-    /// * `value` - The value associated with the provided key.
-    ///
-    /// # Returns
-    /// 
-    /// An `Option<()>` which is `Some(())` if the operation is successful and `None` otherwise.
-	async fn put_value_to_dht(&mut self, key: KademliaKey, value: Vec<u8>) -> Option<()>;
+pub trait DasDht: Send + Sync + Debug + 'static {
+	/// Gets the values stored under `key`, which need to be verified manually.
+	async fn get_value(&self, key: KademliaKey) -> anyhow::Result<Vec<Vec<u8>>>;
+
+	/// Gets the values stored under each of `keys`, in order, `None` for the keys not found.
+	async fn get_values(&self, keys: &[KademliaKey]) -> anyhow::Result<Vec<Option<Vec<Vec<u8>>>>>;
+
+	/// Puts the key-value pairs of `keys_and_values` into the DHT.
+	async fn put_values(&self, keys_and_values: Vec<(KademliaKey, Vec<u8>)>) -> anyhow::Result<()>;
+
+	/// Removes the records of `keys` from the local storage.
+	async fn remove_records(&self, keys: &[KademliaKey]) -> anyhow::Result<()>;
+
+	/// Finds the peers closest to `key`, those expected to store its record.
+	async fn get_closest_peers(&self, key: &KademliaKey) -> anyhow::Result<Vec<PeerId>>;
+
+	/// Requests the values of `keys` from the peer `peer_id`, in order.
+	async fn request_segments(
+		&self,
+		peer_id: PeerId,
+		keys: Vec<Vec<u8>>,
+	) -> anyhow::Result<Vec<Option<Vec<u8>>>>;
+
+	/// Gets the value of `key` from the archive nodes, for the records expired from the DHT.
+	async fn get_archived_value(&self, key: &KademliaKey) -> anyhow::Result<Vec<u8>>;
 }
 
 #[async_trait]
 impl DasDht for DasDhtService {
-    /// Implementation of the `put_value_to_dht` method for `DasDhtService`.
-	async fn put_value_to_dht(&mut self, key: KademliaKey, value: Vec<u8>) -> Option<()> {
-		DasDhtService::put_value_to_dht(self, key, value).await
+	async fn get_value(&self, key: KademliaKey) -> anyhow::Result<Vec<Vec<u8>>> {
+		DasDhtService::get_value(self, key).await
+	}
+
+	async fn get_values(&self, keys: &[KademliaKey]) -> anyhow::Result<Vec<Option<Vec<Vec<u8>>>>> {
+		DasDhtService::get_values(self, keys).await
+	}
+
+	async fn put_values(&self, keys_and_values: Vec<(KademliaKey, Vec<u8>)>) -> anyhow::Result<()> {
+		DasDhtService::put_values(self, keys_and_values).await
+	}
+
+	async fn remove_records(&self, keys: &[KademliaKey]) -> anyhow::Result<()> {
+		DasDhtService::remove_records(self, keys).await
+	}
+
+	async fn get_closest_peers(&self, key: &KademliaKey) -> anyhow::Result<Vec<PeerId>> {
+		DasDhtService::get_closest_peers(self, key).await
+	}
+
+	async fn request_segments(
+		&self,
+		peer_id: PeerId,
+		keys: Vec<Vec<u8>>,
+	) -> anyhow::Result<Vec<Option<Vec<u8>>>> {
+		DasDhtService::request_segments(self, peer_id, keys).await
+	}
+
+	async fn get_archived_value(&self, key: &KademliaKey) -> anyhow::Result<Vec<u8>> {
+		DasDhtService::get_archived_value(self, key).await
 	}
 }
@@ -0,0 +1,300 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! In-memory DHT.
+//!
+//! [`InMemoryDht`] keeps the records of a simulated network in memory. Its routing table places
+//! each record on the online peers closest to its key, by the XOR distance of their hashes, so
+//! that taking peers offline loses the records they hold as it would in Kademlia. Each operation
+//! waits for the configured latency, and fails or stalls as the injected [`Fault`]s say, so that
+//! the handling of a failing DHT can be tested deterministically.
+
+use crate::{DasDht, KademliaKey, PeerId};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::{
+	collections::{hash_map::DefaultHasher, HashMap, VecDeque},
+	hash::{Hash, Hasher},
+	sync::Mutex,
+	time::Duration,
+};
+
+/// The default number of peers a record is put on.
+pub const DEFAULT_REPLICATION: usize = 3;
+
+/// A fault injected into the operations of an [`InMemoryDht`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+	/// The operation fails at once.
+	Fail,
+	/// The operation never completes, as a query timing out.
+	Stall,
+}
+
+/// A simulated peer.
+#[derive(Debug, Default)]
+struct Peer {
+	online: bool,
+	archive: bool,
+	records: HashMap<Vec<u8>, Vec<u8>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+	peers: HashMap<PeerId, Peer>,
+	/// The faults of the next operations, in order.
+	faults: VecDeque<Fault>,
+	/// The fault of every operation once `faults` is exhausted.
+	fault: Option<Fault>,
+	operations: u64,
+}
+
+impl State {
+	/// Returns the `count` online peers closest to `key`, archive nodes aside.
+	fn closest(&self, key: &[u8], count: usize) -> Vec<PeerId> {
+		let key = hash(key);
+		let mut peers = self
+			.peers
+			.iter()
+			.filter(|(_, peer)| peer.online && !peer.archive)
+			.map(|(peer_id, _)| (hash(&peer_id.to_bytes()) ^ key, *peer_id))
+			.collect::<Vec<_>>();
+		peers.sort_by_key(|(distance, _)| *distance);
+		peers.into_iter().take(count).map(|(_, peer_id)| peer_id).collect()
+	}
+
+	/// Returns the distinct values of `key` held by the online peers closest to it.
+	fn values(&self, key: &[u8], replication: usize) -> Vec<Vec<u8>> {
+		let mut values: Vec<Vec<u8>> = Vec::new();
+		for peer_id in self.closest(key, replication) {
+			if let Some(value) = self.peers[&peer_id].records.get(key) {
+				if !values.contains(value) {
+					values.push(value.clone());
+				}
+			}
+		}
+		values
+	}
+}
+
+fn hash(bytes: &[u8]) -> u64 {
+	let mut hasher = DefaultHasher::new();
+	bytes.hash(&mut hasher);
+	hasher.finish()
+}
+
+/// A DHT simulated in memory.
+#[derive(Debug)]
+pub struct InMemoryDht {
+	state: Mutex<State>,
+	latency: Duration,
+	replication: usize,
+}
+
+impl InMemoryDht {
+	/// Creates a network of `peers` online peers.
+	pub fn new(peers: usize) -> Self {
+		let dht = Self {
+			state: Default::default(),
+			latency: Duration::ZERO,
+			replication: DEFAULT_REPLICATION,
+		};
+		for _ in 0..peers {
+			dht.add_peer();
+		}
+		dht
+	}
+
+	/// Delays every operation by `latency`.
+	pub fn with_latency(mut self, latency: Duration) -> Self {
+		self.latency = latency;
+		self
+	}
+
+	/// Puts each record on the `replication` online peers closest to its key, instead of
+	/// [`DEFAULT_REPLICATION`].
+	pub fn with_replication(mut self, replication: usize) -> Self {
+		self.replication = replication;
+		self
+	}
+
+	/// Adds an online peer to the network.
+	pub fn add_peer(&self) -> PeerId {
+		let peer_id = PeerId::random();
+		self.state().peers.insert(peer_id, Peer { online: true, ..Default::default() });
+		peer_id
+	}
+
+	/// Adds an online archive node to the network, serving the records given to
+	/// [`InMemoryDht::archive`]. Archive nodes are not part of the routing table.
+	pub fn add_archive_peer(&self) -> PeerId {
+		let peer_id = PeerId::random();
+		self.state()
+			.peers
+			.insert(peer_id, Peer { online: true, archive: true, ..Default::default() });
+		peer_id
+	}
+
+	/// Returns the peers of the network, online or not.
+	pub fn peers(&self) -> Vec<PeerId> {
+		self.state().peers.keys().copied().collect()
+	}
+
+	/// Takes `peer_id` offline, or back online. An offline peer keeps its records, but is neither
+	/// queried nor given new records.
+	pub fn set_online(&self, peer_id: &PeerId, online: bool) {
+		if let Some(peer) = self.state().peers.get_mut(peer_id) {
+			peer.online = online;
+		}
+	}
+
+	/// Stores `value` under `key` on every archive node.
+	pub fn archive(&self, key: &[u8], value: Vec<u8>) {
+		for peer in self.state().peers.values_mut().filter(|peer| peer.archive) {
+			peer.records.insert(key.to_vec(), value.clone());
+		}
+	}
+
+	/// Replaces the record of `key` held by `peer_id` with `value`, as a faulty or malicious peer
+	/// would serve it.
+	pub fn corrupt(&self, peer_id: &PeerId, key: &[u8], value: Vec<u8>) {
+		if let Some(peer) = self.state().peers.get_mut(peer_id) {
+			peer.records.insert(key.to_vec(), value);
+		}
+	}
+
+	/// Returns the peers, online or not, holding a record of `key`, archive nodes aside.
+	pub fn holders(&self, key: &[u8]) -> Vec<PeerId> {
+		self.state()
+			.peers
+			.iter()
+			.filter(|(_, peer)| !peer.archive && peer.records.contains_key(key))
+			.map(|(peer_id, _)| *peer_id)
+			.collect()
+	}
+
+	/// Injects `fault` into the next `count` operations.
+	pub fn fail_next(&self, count: usize, fault: Fault) {
+		self.state().faults.extend(std::iter::repeat(fault).take(count));
+	}
+
+	/// Injects `fault` into every operation once those of [`InMemoryDht::fail_next`] are
+	/// exhausted, or stops injecting faults if `None`.
+	pub fn set_fault(&self, fault: Option<Fault>) {
+		self.state().fault = fault;
+	}
+
+	/// Returns the number of operations started so far.
+	pub fn operations(&self) -> u64 {
+		self.state().operations
+	}
+
+	fn state(&self) -> std::sync::MutexGuard<'_, State> {
+		self.state.lock().expect("In-memory DHT lock poisoned")
+	}
+
+	/// Starts an operation, waiting for the latency and applying the next fault.
+	async fn enter(&self) -> Result<()> {
+		let fault = {
+			let mut state = self.state();
+			state.operations += 1;
+			let next = state.faults.pop_front();
+			next.or(state.fault)
+		};
+		if !self.latency.is_zero() {
+			tokio::time::sleep(self.latency).await;
+		}
+		match fault {
+			Some(Fault::Fail) => Err(anyhow!("Injected DHT failure")),
+			Some(Fault::Stall) => futures::future::pending::<Result<()>>().await,
+			None => Ok(()),
+		}
+	}
+}
+
+#[async_trait]
+impl DasDht for InMemoryDht {
+	async fn get_value(&self, key: KademliaKey) -> Result<Vec<Vec<u8>>> {
+		self.enter().await?;
+		let values = self.state().values(key.as_ref(), self.replication);
+		if values.is_empty() {
+			return Err(anyhow!("Record not found"))
+		}
+		Ok(values)
+	}
+
+	async fn get_values(&self, keys: &[KademliaKey]) -> Result<Vec<Option<Vec<Vec<u8>>>>> {
+		self.enter().await?;
+		let state = self.state();
+		Ok(keys
+			.iter()
+			.map(|key| Some(state.values(key.as_ref(), self.replication)).filter(|v| !v.is_empty()))
+			.collect())
+	}
+
+	async fn put_values(&self, keys_and_values: Vec<(KademliaKey, Vec<u8>)>) -> Result<()> {
+		self.enter().await?;
+		let mut state = self.state();
+		for (key, value) in keys_and_values {
+			let closest = state.closest(key.as_ref(), self.replication);
+			if closest.is_empty() {
+				return Err(anyhow!("No peer to put the record to"))
+			}
+			for peer_id in closest {
+				if let Some(peer) = state.peers.get_mut(&peer_id) {
+					peer.records.insert(key.to_vec(), value.clone());
+				}
+			}
+		}
+		Ok(())
+	}
+
+	async fn remove_records(&self, keys: &[KademliaKey]) -> Result<()> {
+		self.enter().await?;
+		for peer in self.state().peers.values_mut().filter(|peer| !peer.archive) {
+			for key in keys {
+				peer.records.remove(key.as_ref());
+			}
+		}
+		Ok(())
+	}
+
+	async fn get_closest_peers(&self, key: &KademliaKey) -> Result<Vec<PeerId>> {
+		self.enter().await?;
+		Ok(self.state().closest(key.as_ref(), self.replication))
+	}
+
+	async fn request_segments(
+		&self,
+		peer_id: PeerId,
+		keys: Vec<Vec<u8>>,
+	) -> Result<Vec<Option<Vec<u8>>>> {
+		self.enter().await?;
+		match self.state().peers.get(&peer_id) {
+			Some(peer) if peer.online =>
+				Ok(keys.iter().map(|key| peer.records.get(key).cloned()).collect()),
+			_ => Err(anyhow!("Peer {} unreachable", peer_id)),
+		}
+	}
+
+	async fn get_archived_value(&self, key: &KademliaKey) -> Result<Vec<u8>> {
+		self.enter().await?;
+		self.state()
+			.peers
+			.values()
+			.filter(|peer| peer.online && peer.archive)
+			.find_map(|peer| peer.records.get(key.as_ref()).cloned())
+			.ok_or_else(|| anyhow!("No archive node has the record."))
+	}
+}
@@ -0,0 +1,178 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Segment archive protocol.
+//!
+//! The DHT only keeps the segments for a limited time. Archive nodes keep every segment they
+//! receive in their database instead, and serve them to their peers over the request-response
+//! protocol [`ARCHIVE_PROTOCOL`]. They announce themselves as providers of
+//! [`ARCHIVE_PROVIDER_KEY`] so that peers needing deep history can find them. The other nodes
+//! answer the requests from their DHT record store, so that a response holding a segment also
+//! acknowledges that the peer stores it.
+//!
+//! Version 2 of the protocol, [`ARCHIVE_PROTOCOL_V2`], compresses the messages with zstd. Both
+//! versions are supported, and the newest one supported by both peers is negotiated.
+use async_trait::async_trait;
+use codec::{Decode, Encode};
+use futures::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use libp2p::{
+	core::upgrade::{read_length_prefixed, write_length_prefixed, ProtocolName},
+	request_response::RequestResponseCodec,
+};
+use melo_core_primitives::Compression;
+use std::io;
+
+/// The name of the segment archive protocol.
+pub const ARCHIVE_PROTOCOL: &[u8] = b"/melodot-das/archive/1";
+
+/// The name of the segment archive protocol with compressed messages.
+pub const ARCHIVE_PROTOCOL_V2: &[u8] = b"/melodot-das/archive/2";
+
+/// The DHT key under which archive nodes are announced as providers.
+pub const ARCHIVE_PROVIDER_KEY: &[u8] = b"melodot/das/archive";
+
+/// The maximum number of segments requested at once.
+pub const MAX_ARCHIVE_REQUEST_KEYS: usize = 256;
+
+/// The maximum size of an encoded request or response.
+const MAX_MESSAGE_SIZE: usize = 4 * 1024 * 1024;
+
+/// A request for the segments stored under `keys`.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct SegmentsRequest {
+	pub keys: Vec<Vec<u8>>,
+}
+
+/// The values stored under the requested keys, in order, `None` for the unknown keys.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct SegmentsResponse {
+	pub values: Vec<Option<Vec<u8>>>,
+}
+
+/// The versions of the segment archive protocol.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArchiveProtocol {
+	/// [`ARCHIVE_PROTOCOL`], with uncompressed messages.
+	#[default]
+	V1,
+	/// [`ARCHIVE_PROTOCOL_V2`], with zstd compressed messages.
+	V2,
+}
+
+impl ArchiveProtocol {
+	/// Returns the compression of the messages of the protocol.
+	pub fn compression(&self) -> Compression {
+		match self {
+			ArchiveProtocol::V1 => Compression::None,
+			ArchiveProtocol::V2 => Compression::Zstd,
+		}
+	}
+}
+
+impl ProtocolName for ArchiveProtocol {
+	fn protocol_name(&self) -> &[u8] {
+		match self {
+			ArchiveProtocol::V1 => ARCHIVE_PROTOCOL,
+			ArchiveProtocol::V2 => ARCHIVE_PROTOCOL_V2,
+		}
+	}
+}
+
+/// SCALE codec of the [`ArchiveProtocol`] messages, each message is length prefixed and
+/// compressed as the negotiated version requires.
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveCodec;
+
+async fn read_message<T, M>(protocol: &ArchiveProtocol, io: &mut T) -> io::Result<M>
+where
+	T: AsyncRead + Unpin + Send,
+	M: Decode,
+{
+	let bytes = read_length_prefixed(io, MAX_MESSAGE_SIZE).await?;
+	let bytes = protocol
+		.compression()
+		.decompress(&bytes, MAX_MESSAGE_SIZE)
+		.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+	M::decode(&mut &bytes[..]).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+async fn write_message<T, M>(protocol: &ArchiveProtocol, io: &mut T, message: M) -> io::Result<()>
+where
+	T: AsyncWrite + Unpin + Send,
+	M: Encode,
+{
+	let bytes = protocol
+		.compression()
+		.compress(&message.encode())
+		.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+	write_length_prefixed(io, bytes).await?;
+	io.close().await
+}
+
+#[async_trait]
+impl RequestResponseCodec for ArchiveCodec {
+	type Protocol = ArchiveProtocol;
+	type Request = SegmentsRequest;
+	type Response = SegmentsResponse;
+
+	async fn read_request<T>(
+		&mut self,
+		protocol: &ArchiveProtocol,
+		io: &mut T,
+	) -> io::Result<Self::Request>
+	where
+		T: AsyncRead + Unpin + Send,
+	{
+		let request: SegmentsRequest = read_message(protocol, io).await?;
+		if request.keys.len() > MAX_ARCHIVE_REQUEST_KEYS {
+			return Err(io::Error::new(io::ErrorKind::InvalidData, "Too many keys requested"))
+		}
+		Ok(request)
+	}
+
+	async fn read_response<T>(
+		&mut self,
+		protocol: &ArchiveProtocol,
+		io: &mut T,
+	) -> io::Result<Self::Response>
+	where
+		T: AsyncRead + Unpin + Send,
+	{
+		read_message(protocol, io).await
+	}
+
+	async fn write_request<T>(
+		&mut self,
+		protocol: &ArchiveProtocol,
+		io: &mut T,
+		request: Self::Request,
+	) -> io::Result<()>
+	where
+		T: AsyncWrite + Unpin + Send,
+	{
+		write_message(protocol, io, request).await
+	}
+
+	async fn write_response<T>(
+		&mut self,
+		protocol: &ArchiveProtocol,
+		io: &mut T,
+		response: Self::Response,
+	) -> io::Result<()>
+	where
+		T: AsyncWrite + Unpin + Send,
+	{
+		write_message(protocol, io, response).await
+	}
+}
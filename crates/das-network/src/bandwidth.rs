@@ -0,0 +1,188 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Upload bandwidth budgets of the segment archive protocol.
+//!
+//! Each response is charged to a global token bucket and to the bucket of the requesting peer,
+//! both refilled at a constant rate up to their burst size, so that a single peer cannot exhaust
+//! the uplink of the node. Requests of at most [`BandwidthConfig::sampling_max_keys`] keys, as
+//! sent when sampling, may use the whole global budget, while bulk requests, such as those of a
+//! backfill, leave [`BandwidthConfig::sampling_reserve`] bytes of it to sampling.
+use libp2p::PeerId;
+use std::{collections::HashMap, time::Instant};
+
+/// The number of tracked peers above which the buckets of idle peers are dropped.
+const MAX_IDLE_PEERS: usize = 1024;
+
+/// Upload bandwidth budgets of the segment archive protocol. Rates are in bytes per second and
+/// bursts in bytes.
+#[derive(Clone, Debug)]
+pub struct BandwidthConfig {
+	/// The rate of the budget shared by all the peers.
+	pub global_rate: u64,
+	/// The burst of the budget shared by all the peers.
+	pub global_burst: u64,
+	/// The rate of the budget of each peer.
+	pub peer_rate: u64,
+	/// The burst of the budget of each peer.
+	pub peer_burst: u64,
+	/// The maximum number of keys of a request served as a sampling request.
+	pub sampling_max_keys: usize,
+	/// The part of the global budget that bulk requests leave to sampling requests.
+	pub sampling_reserve: u64,
+}
+
+impl Default for BandwidthConfig {
+	fn default() -> Self {
+		BandwidthConfig {
+			global_rate: 8 * 1024 * 1024,
+			global_burst: 16 * 1024 * 1024,
+			peer_rate: 1024 * 1024,
+			peer_burst: 4 * 1024 * 1024,
+			sampling_max_keys: 16,
+			sampling_reserve: 2 * 1024 * 1024,
+		}
+	}
+}
+
+/// The class of a segment request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestClass {
+	/// A request of a few segments, as sent when sampling.
+	Sampling,
+	/// A request of many segments, such as those of a backfill.
+	Bulk,
+}
+
+impl RequestClass {
+	/// Returns the metrics label of the class.
+	pub fn label(&self) -> &'static str {
+		match self {
+			RequestClass::Sampling => "sampling",
+			RequestClass::Bulk => "bulk",
+		}
+	}
+}
+
+/// The budget a response exceeds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Throttle {
+	/// The budget of the requesting peer.
+	Peer,
+	/// The budget shared by all the peers.
+	Global,
+}
+
+impl Throttle {
+	/// Returns the metrics label of the budget.
+	pub fn label(&self) -> &'static str {
+		match self {
+			Throttle::Peer => "peer",
+			Throttle::Global => "global",
+		}
+	}
+}
+
+/// A token bucket of bytes.
+#[derive(Clone, Debug)]
+struct TokenBucket {
+	rate: u64,
+	burst: u64,
+	tokens: u64,
+	last: Instant,
+}
+
+impl TokenBucket {
+	fn new(rate: u64, burst: u64, now: Instant) -> Self {
+		TokenBucket { rate, burst, tokens: burst, last: now }
+	}
+
+	/// Returns the tokens available at `now`.
+	fn available(&mut self, now: Instant) -> u64 {
+		let elapsed = now.saturating_duration_since(self.last);
+		let refill = (elapsed.as_secs_f64() * self.rate as f64) as u64;
+		// The time of a partial token is kept until it adds up to a whole one.
+		if refill > 0 {
+			self.tokens = self.tokens.saturating_add(refill).min(self.burst);
+			self.last = now;
+		}
+		self.tokens
+	}
+
+	fn take(&mut self, amount: u64) {
+		self.tokens = self.tokens.saturating_sub(amount);
+	}
+
+	/// Returns whether the bucket is full at `now`, that is it would be created the same.
+	fn is_full(&mut self, now: Instant) -> bool {
+		self.available(now) >= self.burst
+	}
+}
+
+/// Charges the responses of the segment archive protocol to the budgets of [`BandwidthConfig`].
+pub(crate) struct BandwidthLimiter {
+	config: BandwidthConfig,
+	global: TokenBucket,
+	peers: HashMap<PeerId, TokenBucket>,
+}
+
+impl BandwidthLimiter {
+	pub(crate) fn new(config: BandwidthConfig) -> Self {
+		let global = TokenBucket::new(config.global_rate, config.global_burst, Instant::now());
+		BandwidthLimiter { config, global, peers: HashMap::new() }
+	}
+
+	/// Returns the class of a request of `keys` keys.
+	pub(crate) fn classify(&self, keys: usize) -> RequestClass {
+		if keys <= self.config.sampling_max_keys {
+			RequestClass::Sampling
+		} else {
+			RequestClass::Bulk
+		}
+	}
+
+	/// Charges a response of `bytes` bytes to `peer`, unless it exceeds a budget.
+	pub(crate) fn admit(
+		&mut self,
+		peer: PeerId,
+		class: RequestClass,
+		bytes: u64,
+	) -> Result<(), Throttle> {
+		let now = Instant::now();
+		if self.peers.len() > MAX_IDLE_PEERS {
+			self.peers.retain(|_, bucket| !bucket.is_full(now));
+		}
+
+		let (peer_rate, peer_burst) = (self.config.peer_rate, self.config.peer_burst);
+		let peer_bucket = self
+			.peers
+			.entry(peer)
+			.or_insert_with(|| TokenBucket::new(peer_rate, peer_burst, now));
+		if peer_bucket.available(now) < bytes {
+			return Err(Throttle::Peer)
+		}
+
+		let reserve = match class {
+			RequestClass::Sampling => 0,
+			RequestClass::Bulk => self.config.sampling_reserve,
+		};
+		if self.global.available(now) < bytes.saturating_add(reserve) {
+			return Err(Throttle::Global)
+		}
+
+		peer_bucket.take(bytes);
+		self.global.take(bytes);
+		Ok(())
+	}
+}
@@ -12,16 +12,29 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::{
+	archive::{ArchiveCodec, ArchiveProtocol, SegmentsRequest, SegmentsResponse},
+	handshake::{Handshake, HandshakeCodec, HandshakeProtocol},
+	serving::{ServingCodec, ServingProtocol},
+	solution::gossipsub_config,
+	AccountId,
+};
 use anyhow::Result;
 use derive_more::From;
 use libp2p::{
 	core::PeerId,
+	gossipsub::{Gossipsub, GossipsubEvent, MessageAuthenticity},
 	identify::{Behaviour as Identify, Config as IdentifyConfig, Event as IdentifyEvent},
 	kad::{store::MemoryStore, Kademlia, KademliaConfig, KademliaEvent},
 	mdns::{tokio::Behaviour as TokioMdns, Config as MdnsConfig, Event as MdnsEvent},
 	ping::{Behaviour as Ping, Event as PingEvent},
+	request_response::{
+		ProtocolSupport, RequestResponse, RequestResponseConfig, RequestResponseEvent,
+	},
 	swarm::NetworkBehaviour,
 };
+use melo_core_primitives::serving::ServingReceipt;
+use sp_core::ed25519;
 
 pub struct BehaviorConfig {
 	/// Identity keypair of a node used for authenticated connections.
@@ -32,6 +45,14 @@ pub struct BehaviorConfig {
 	pub kademlia: KademliaConfig,
 	/// The configuration for the [`kad_store`] behaviour.
 	pub kad_store: MemoryStore,
+	/// The configuration for the segment archive [`RequestResponse`] behaviour.
+	pub archive: RequestResponseConfig,
+	/// The configuration for the handshake [`RequestResponse`] behaviour.
+	pub handshake: RequestResponseConfig,
+	/// The configuration for the serving receipts [`RequestResponse`] behaviour.
+	pub serving: RequestResponseConfig,
+	/// How the solutions published by the node are signed.
+	pub solutions: MessageAuthenticity,
 }
 
 /// The [`NetworkBehaviour`] of the DAS network.
@@ -47,6 +68,14 @@ pub struct Behavior {
 	pub ping: Ping,
 	/// The [`Mdns`] behaviour.
 	pub mdns: TokioMdns,
+	/// The segment archive [`RequestResponse`] behaviour.
+	pub archive: RequestResponse<ArchiveCodec>,
+	/// The handshake [`RequestResponse`] behaviour.
+	pub handshake: RequestResponse<HandshakeCodec>,
+	/// The serving receipts [`RequestResponse`] behaviour.
+	pub serving: RequestResponse<ServingCodec>,
+	/// The solution relay [`Gossipsub`] behaviour.
+	pub solutions: Gossipsub,
 }
 
 impl Behavior {
@@ -54,8 +83,37 @@ impl Behavior {
 	pub fn new(config: BehaviorConfig) -> Result<Self> {
 		let mdns = TokioMdns::new(MdnsConfig::default())?;
 		let kademlia = Kademlia::with_config(config.peer_id, config.kad_store, config.kademlia);
+		let archive = RequestResponse::new(
+			ArchiveCodec,
+			[
+				(ArchiveProtocol::V2, ProtocolSupport::Full),
+				(ArchiveProtocol::V1, ProtocolSupport::Full),
+			],
+			config.archive,
+		);
+		let handshake = RequestResponse::new(
+			HandshakeCodec,
+			[(HandshakeProtocol, ProtocolSupport::Full)],
+			config.handshake,
+		);
+		let serving = RequestResponse::new(
+			ServingCodec,
+			[(ServingProtocol, ProtocolSupport::Full)],
+			config.serving,
+		);
+		let solutions =
+			Gossipsub::new(config.solutions, gossipsub_config()).map_err(|e| anyhow::anyhow!(e))?;
 
-		Ok(Self { identify: Identify::new(config.identify), mdns, kademlia, ping: Ping::default() })
+		Ok(Self {
+			identify: Identify::new(config.identify),
+			mdns,
+			kademlia,
+			ping: Ping::default(),
+			archive,
+			handshake,
+			serving,
+			solutions,
+		})
 	}
 }
 
@@ -65,4 +123,8 @@ pub enum BehaviourEvent {
 	Kademlia(KademliaEvent),
 	Ping(PingEvent),
 	Mdns(MdnsEvent),
+	Archive(RequestResponseEvent<SegmentsRequest, SegmentsResponse>),
+	Handshake(RequestResponseEvent<Handshake, Handshake>),
+	Serving(RequestResponseEvent<ServingReceipt<AccountId>, Option<ed25519::Signature>>),
+	Solutions(GossipsubEvent),
 }
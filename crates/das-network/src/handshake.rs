@@ -0,0 +1,203 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! DAS handshake protocol.
+//!
+//! When a connection is established, each peer sends its [`Handshake`] over the request-response
+//! protocol [`HANDSHAKE_PROTOCOL`] and receives the handshake of the remote in response. The
+//! handshake lists the versions of the DAS protocols supported by the node, the dimensions of the
+//! data matrix it samples and its [`NodeRole`]. Peers sharing no protocol version, or sampling a
+//! different matrix, are disconnected before they exchange any segment.
+//!
+//! Peers which do not support the handshake protocol are kept, so that nodes predating it still
+//! take part in the network.
+use async_trait::async_trait;
+use codec::{Decode, Encode};
+use futures::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use libp2p::{
+	core::upgrade::{read_length_prefixed, write_length_prefixed, ProtocolName},
+	request_response::RequestResponseCodec,
+};
+use melo_core_primitives::config::{FIELD_ELEMENTS_PER_BLOB, FIELD_ELEMENTS_PER_SEGMENT};
+use std::io;
+
+/// The name of the handshake protocol.
+pub const HANDSHAKE_PROTOCOL: &[u8] = b"/melodot-das/handshake/1";
+
+/// The versions of the DAS protocols supported by the node, the newest last.
+pub const DAS_PROTOCOL_VERSIONS: &[u32] = &[1];
+
+/// The maximum size of an encoded handshake.
+const MAX_HANDSHAKE_SIZE: usize = 1024;
+
+/// The role of a node in the DAS network.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Encode, Decode)]
+pub enum NodeRole {
+	/// A full node, sampling the blocks and storing the segments of the DHT.
+	#[default]
+	Full,
+	/// A farmer, publishing solutions.
+	Farmer,
+	/// An archive node, keeping every segment.
+	Archive,
+	/// A light client, only sampling the blocks.
+	Light,
+}
+
+impl NodeRole {
+	/// Returns the label of the role in the metrics.
+	pub fn label(&self) -> &'static str {
+		match self {
+			NodeRole::Full => "full",
+			NodeRole::Farmer => "farmer",
+			NodeRole::Archive => "archive",
+			NodeRole::Light => "light",
+		}
+	}
+}
+
+/// The handshake exchanged by the peers when they connect.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct Handshake {
+	/// The versions of the DAS protocols supported by the node.
+	pub versions: Vec<u32>,
+	/// The number of field elements per blob of the data matrix.
+	pub field_elements_per_blob: u32,
+	/// The number of field elements per segment of the data matrix.
+	pub field_elements_per_segment: u32,
+	/// The role of the node.
+	pub role: NodeRole,
+}
+
+/// Why the handshake of a peer is rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Incompatibility {
+	/// The peers share no DAS protocol version.
+	Versions,
+	/// The peers sample data matrices of different dimensions.
+	Matrix,
+}
+
+impl Incompatibility {
+	/// Returns the label of the incompatibility in the metrics.
+	pub fn label(&self) -> &'static str {
+		match self {
+			Incompatibility::Versions => "incompatible_versions",
+			Incompatibility::Matrix => "incompatible_matrix",
+		}
+	}
+}
+
+impl Handshake {
+	/// Returns the handshake of the local node, with the given `role`.
+	pub fn local(role: NodeRole) -> Self {
+		Self {
+			versions: DAS_PROTOCOL_VERSIONS.to_vec(),
+			field_elements_per_blob: FIELD_ELEMENTS_PER_BLOB as u32,
+			field_elements_per_segment: FIELD_ELEMENTS_PER_SEGMENT as u32,
+			role,
+		}
+	}
+
+	/// Returns the newest DAS protocol version supported by both nodes, or why the handshake of
+	/// the `remote` node is rejected.
+	pub fn negotiate(&self, remote: &Handshake) -> Result<u32, Incompatibility> {
+		if self.field_elements_per_blob != remote.field_elements_per_blob ||
+			self.field_elements_per_segment != remote.field_elements_per_segment
+		{
+			return Err(Incompatibility::Matrix)
+		}
+		self.versions
+			.iter()
+			.filter(|version| remote.versions.contains(version))
+			.max()
+			.copied()
+			.ok_or(Incompatibility::Versions)
+	}
+}
+
+/// The [`HANDSHAKE_PROTOCOL`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HandshakeProtocol;
+
+impl ProtocolName for HandshakeProtocol {
+	fn protocol_name(&self) -> &[u8] {
+		HANDSHAKE_PROTOCOL
+	}
+}
+
+/// SCALE codec of the [`HandshakeProtocol`] messages, each message is length prefixed.
+#[derive(Debug, Clone, Default)]
+pub struct HandshakeCodec;
+
+async fn read_handshake<T>(io: &mut T) -> io::Result<Handshake>
+where
+	T: AsyncRead + Unpin + Send,
+{
+	let bytes = read_length_prefixed(io, MAX_HANDSHAKE_SIZE).await?;
+	Handshake::decode(&mut &bytes[..]).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+async fn write_handshake<T>(io: &mut T, handshake: Handshake) -> io::Result<()>
+where
+	T: AsyncWrite + Unpin + Send,
+{
+	write_length_prefixed(io, handshake.encode()).await?;
+	io.close().await
+}
+
+#[async_trait]
+impl RequestResponseCodec for HandshakeCodec {
+	type Protocol = HandshakeProtocol;
+	type Request = Handshake;
+	type Response = Handshake;
+
+	async fn read_request<T>(&mut self, _: &HandshakeProtocol, io: &mut T) -> io::Result<Handshake>
+	where
+		T: AsyncRead + Unpin + Send,
+	{
+		read_handshake(io).await
+	}
+
+	async fn read_response<T>(&mut self, _: &HandshakeProtocol, io: &mut T) -> io::Result<Handshake>
+	where
+		T: AsyncRead + Unpin + Send,
+	{
+		read_handshake(io).await
+	}
+
+	async fn write_request<T>(
+		&mut self,
+		_: &HandshakeProtocol,
+		io: &mut T,
+		request: Handshake,
+	) -> io::Result<()>
+	where
+		T: AsyncWrite + Unpin + Send,
+	{
+		write_handshake(io, request).await
+	}
+
+	async fn write_response<T>(
+		&mut self,
+		_: &HandshakeProtocol,
+		io: &mut T,
+		response: Handshake,
+	) -> io::Result<()>
+	where
+		T: AsyncWrite + Unpin + Send,
+	{
+		write_handshake(io, response).await
+	}
+}
@@ -0,0 +1,139 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! DHT keys of the segments.
+//!
+//! The DHT key of a segment is the hash of a path naming the version of the key layout and the
+//! place of the segment in the data, `/melo/das/v1/app/{app_id}/{nonce}/{row}/{col}` for the
+//! segments of the data of an app and `/melo/das/v1/block/{block_hash}/{row}/{col}` for the
+//! segments of the parity rows of a block. A change of the layout bumps [`DHT_KEY_VERSION`], so
+//! that the records put under the previous layout can still be told apart and fetched.
+//!
+//! The first releases put the segments under their local database key, `sample_key` or
+//! `sample_key_from_block`. While [`LegacyKeys::Accept`] is set, segments are also put under these
+//! legacy keys, and fetched under them when missing under the current ones, so that the records of
+//! the nodes not upgraded yet are not orphaned.
+use crate::KademliaKey;
+use codec::{Decode, Encode};
+use melo_das_primitives::Position;
+use sp_core::{hashing::blake2_256, hexdisplay::HexDisplay};
+
+/// The version of the layout of the DHT keys.
+pub const DHT_KEY_VERSION: u32 = 1;
+
+/// The namespace of the paths of the DHT keys.
+const DHT_KEY_NAMESPACE: &str = "/melo/das";
+
+/// The length of an encoded position.
+const POSITION_LEN: usize = 8;
+
+/// The length of the local key of a segment of app data: the app ID, the nonce and the position.
+const APP_KEY_LEN: usize = 8 + POSITION_LEN;
+
+/// The length of the local key of a segment of a block: the block hash and the position.
+const BLOCK_KEY_LEN: usize = 32 + POSITION_LEN;
+
+/// The place of a segment in the data, from which its keys are derived.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SegmentKey {
+	/// A segment of the data of an app, positioned in the data of the app.
+	App { app_id: u32, nonce: u32, position: Position },
+	/// A segment of the parity rows of a block, positioned in the extended data of the block.
+	Block { block_hash: Vec<u8>, position: Position },
+}
+
+impl SegmentKey {
+	/// Parses the local database key of a segment.
+	///
+	/// Returns `None` if `key` is not the key of a segment.
+	pub fn from_local_key(key: &[u8]) -> Option<Self> {
+		match key.len() {
+			APP_KEY_LEN => {
+				let app_id = u32::from_be_bytes(key[..4].try_into().ok()?);
+				let nonce = u32::from_be_bytes(key[4..8].try_into().ok()?);
+				let position = Position::decode(&mut &key[8..]).ok()?;
+				Some(SegmentKey::App { app_id, nonce, position })
+			},
+			BLOCK_KEY_LEN => {
+				let (block_hash, position) = key.split_at(BLOCK_KEY_LEN - POSITION_LEN);
+				let position = Position::decode(&mut &*position).ok()?;
+				Some(SegmentKey::Block { block_hash: block_hash.to_vec(), position })
+			},
+			_ => None,
+		}
+	}
+
+	/// Returns the local database key of the segment.
+	pub fn local_key(&self) -> Vec<u8> {
+		match self {
+			SegmentKey::App { app_id, nonce, position } =>
+				[&app_id.to_be_bytes()[..], &nonce.to_be_bytes()[..], &position.encode()[..]]
+					.concat(),
+			SegmentKey::Block { block_hash, position } =>
+				[&block_hash[..], &position.encode()[..]].concat(),
+		}
+	}
+
+	/// Returns the path of the segment in the version `version` of the key layout.
+	pub fn path(&self, version: u32) -> String {
+		match self {
+			SegmentKey::App { app_id, nonce, position } => format!(
+				"{}/v{}/app/{}/{}/{}/{}",
+				DHT_KEY_NAMESPACE, version, app_id, nonce, position.y, position.x
+			),
+			SegmentKey::Block { block_hash, position } => format!(
+				"{}/v{}/block/0x{}/{}/{}",
+				DHT_KEY_NAMESPACE,
+				version,
+				HexDisplay::from(block_hash),
+				position.y,
+				position.x
+			),
+		}
+	}
+
+	/// Returns the DHT key of the segment in the current layout.
+	pub fn dht_key(&self) -> KademliaKey {
+		KademliaKey::new(&blake2_256(self.path(DHT_KEY_VERSION).as_bytes()))
+	}
+
+	/// Returns the DHT key of the segment in the legacy layout, its local key.
+	pub fn legacy_dht_key(&self) -> KademliaKey {
+		KademliaKey::new(&self.local_key())
+	}
+}
+
+/// How the DHT keys of the legacy layout are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LegacyKeys {
+	/// Segments are also put under their legacy key, and fetched under it when missing under the
+	/// current one.
+	#[default]
+	Accept,
+	/// Only the current layout is used.
+	Reject,
+}
+
+impl LegacyKeys {
+	/// Returns the DHT keys of the segment of local key `local_key`, the current one first.
+	///
+	/// Keys which are not the key of a segment are used as they are.
+	pub fn dht_keys(&self, local_key: &[u8]) -> Vec<KademliaKey> {
+		match (SegmentKey::from_local_key(local_key), self) {
+			(Some(key), LegacyKeys::Accept) => vec![key.dht_key(), key.legacy_dht_key()],
+			(Some(key), LegacyKeys::Reject) => vec![key.dht_key()],
+			(None, _) => vec![KademliaKey::new(&local_key)],
+		}
+	}
+}
@@ -22,11 +22,13 @@ use libp2p::{
 		PeerId,
 	},
 	dns::TokioDnsConfig,
+	gossipsub::MessageAuthenticity,
 	identify::Config as IdentifyConfig,
 	identity,
 	identity::Keypair,
-	kad::{store::MemoryStore, KademliaConfig},
+	kad::{store::MemoryStore, KademliaConfig, KademliaStoreInserts},
 	noise::NoiseAuthenticated,
+	request_response::RequestResponseConfig,
 	swarm::SwarmBuilder,
 	tcp::{tokio::Transport as TokioTcpTransport, Config as GenTcpConfig},
 	yamux::YamuxConfig,
@@ -36,6 +38,7 @@ use melo_core_primitives::config;
 
 pub use log::warn;
 
+pub use libp2p::{gossipsub::MessageId, PeerId};
 pub use node_primitives::AccountId;
 pub use sc_client_api::Backend;
 pub use sc_network::{DhtEvent, KademliaKey, NetworkDHTProvider, NetworkSigner, NetworkStateInfo};
@@ -43,14 +46,32 @@ pub use sp_runtime::traits::{Block, Header};
 pub use std::sync::Arc;
 use std::time::Duration;
 
+pub use archive::{
+	ARCHIVE_PROTOCOL, ARCHIVE_PROTOCOL_V2, ARCHIVE_PROVIDER_KEY, MAX_ARCHIVE_REQUEST_KEYS,
+};
+pub use bandwidth::{BandwidthConfig, RequestClass, Throttle};
 pub use behaviour::{Behavior, BehaviorConfig, BehaviourEvent};
+pub use handshake::{
+	Handshake, Incompatibility, NodeRole, DAS_PROTOCOL_VERSIONS, HANDSHAKE_PROTOCOL,
+};
+pub use keys::{LegacyKeys, SegmentKey, DHT_KEY_VERSION};
 pub use service::{DasNetworkConfig, Service};
+pub use serving::SERVING_PROTOCOL;
 pub use shared::Command;
+pub use solution::{
+	InboundSolution, SolutionAnnouncement, SolutionVerdict, MAX_SOLUTION_SIZE, SOLUTION_TOPIC,
+};
 pub use worker::DasNetwork;
 
+mod archive;
+mod bandwidth;
 mod behaviour;
+mod handshake;
+mod keys;
 mod service;
+mod serving;
 mod shared;
+mod solution;
 mod worker;
 
 const SWARM_MAX_NEGOTIATING_INBOUND_STREAMS: usize = 5000;
@@ -70,11 +91,19 @@ pub fn create(
 
 	let transport = build_transport(&keypair, true)?;
 
+	// Inbound records are stored by the worker, which also writes them to the archive.
+	let mut kademlia = KademliaConfig::default();
+	kademlia.set_record_filtering(KademliaStoreInserts::FilterBoth);
+
 	let behaviour = Behavior::new(BehaviorConfig {
 		peer_id: local_peer_id,
 		identify,
-		kademlia: KademliaConfig::default(),
+		kademlia,
 		kad_store: MemoryStore::new(local_peer_id),
+		archive: RequestResponseConfig::default(),
+		handshake: RequestResponseConfig::default(),
+		serving: RequestResponseConfig::default(),
+		solutions: MessageAuthenticity::Signed(keypair.clone()),
 	})?;
 
 	let swarm = SwarmBuilder::with_tokio_executor(transport, behaviour, local_peer_id)
@@ -85,7 +114,7 @@ pub fn create(
 
 	Ok((
 		service::Service::new(to_worker, config.parallel_limit),
-		worker::DasNetwork::new(swarm, from_service, prometheus_registry, &config),
+		worker::DasNetwork::new(swarm, keypair, from_service, prometheus_registry, &config),
 	))
 }
 
@@ -93,6 +122,16 @@ pub fn create(
 pub fn default(
 	config: Option<DasNetworkConfig>,
 	keypair: Option<identity::Keypair>,
+) -> Result<(service::Service, worker::DasNetwork)> {
+	default_with_registry(config, keypair, prometheus_endpoint::Registry::default())
+}
+
+/// Creates a new [`DasNetwork`] instance with default configuration, registering its metrics in
+/// the given registry.
+pub fn default_with_registry(
+	config: Option<DasNetworkConfig>,
+	keypair: Option<identity::Keypair>,
+	metric_registry: prometheus_endpoint::Registry,
 ) -> Result<(service::Service, worker::DasNetwork)> {
 	let keypair = match keypair {
 		Some(keypair) => keypair,
@@ -106,8 +145,6 @@ pub fn default(
 		None => DasNetworkConfig::default(),
 	};
 
-	let metric_registry = prometheus_endpoint::Registry::default();
-
 	create(
 		keypair,
 		config::DAS_NETWORK_VERSION.to_string(),
@@ -12,8 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{Command, KademliaKey};
+use crate::{
+	shared::Values, BandwidthConfig, Command, KademliaKey, NodeRole, SolutionAnnouncement,
+	SolutionVerdict, MAX_ARCHIVE_REQUEST_KEYS,
+};
 use anyhow::Context;
+use codec::Encode;
 use futures::{
 	channel::{mpsc, oneshot},
 	future::join_all,
@@ -21,6 +25,7 @@ use futures::{
 };
 use libp2p::{
 	futures,
+	gossipsub::MessageId,
 	kad::{record, Quorum, Record},
 	Multiaddr, PeerId,
 };
@@ -139,6 +144,84 @@ impl Service {
 			.await?;
 		receiver.await.context("Failed receiving remove records response")?
 	}
+
+	/// Finds the archive nodes announced in the DHT.
+	pub async fn get_archive_providers(&self) -> anyhow::Result<Vec<PeerId>> {
+		let (sender, receiver) = oneshot::channel();
+		self.to_worker.clone().send(Command::GetArchiveProviders { sender }).await?;
+		receiver.await.context("Failed receiving get archive providers response")?
+	}
+
+	/// Finds the peers closest to `key`, those expected to store its record.
+	pub async fn get_closest_peers(&self, key: &KademliaKey) -> anyhow::Result<Vec<PeerId>> {
+		let (sender, receiver) = oneshot::channel();
+		self.to_worker
+			.clone()
+			.send(Command::GetClosestPeers { key: key.clone(), sender })
+			.await?;
+		receiver.await.context("Failed receiving get closest peers response")?
+	}
+
+	/// Requests the values of `keys` from the peer `peer_id`, which answers from its archive or
+	/// else from its DHT record store. The values are returned in the order of `keys`, and need
+	/// to be verified manually.
+	pub async fn request_segments(
+		&self,
+		peer_id: PeerId,
+		keys: Vec<Vec<u8>>,
+	) -> anyhow::Result<Values> {
+		anyhow::ensure!(keys.len() <= MAX_ARCHIVE_REQUEST_KEYS, "Too many keys requested");
+		let (sender, receiver) = oneshot::channel();
+		self.to_worker
+			.clone()
+			.send(Command::RequestSegments { peer_id, keys, sender })
+			.await?;
+		receiver.await.context("Failed receiving request segments response")?
+	}
+
+	/// Asynchronously gets the value corresponding to `key` from the archive nodes, asking them in
+	/// turn until one of them has it. Used for the segments which expired from the DHT.
+	pub async fn get_archived_value(&self, key: &KademliaKey) -> anyhow::Result<Vec<u8>> {
+		for peer_id in self.get_archive_providers().await? {
+			match self.request_segments(peer_id, vec![key.to_vec()]).await {
+				Ok(mut values) =>
+					if let Some(Some(value)) = values.pop() {
+						return Ok(value)
+					},
+				Err(e) => log::debug!("Archive node {:?} did not answer: {:?}", peer_id, e),
+			}
+		}
+		Err(anyhow::anyhow!("No archive node has the record."))
+	}
+
+	/// Publishes `announcement` to the nodes relaying solutions. Fails if no relaying node is
+	/// connected.
+	pub async fn publish_solution(
+		&self,
+		announcement: &SolutionAnnouncement,
+	) -> anyhow::Result<()> {
+		let (sender, receiver) = oneshot::channel();
+		self.to_worker
+			.clone()
+			.send(Command::PublishSolution { data: announcement.encode(), sender })
+			.await?;
+		receiver.await.context("Failed receiving publish solution response")?
+	}
+
+	/// Reports the verdict of the relay on the solution `message_id` received from `source`, so
+	/// that it is gossiped further only if accepted.
+	pub async fn report_solution(
+		&self,
+		message_id: MessageId,
+		source: PeerId,
+		verdict: SolutionVerdict,
+	) -> anyhow::Result<()> {
+		self.to_worker
+			.clone()
+			.send(Command::ReportSolution { message_id, source, verdict })
+			.await?;
+		Ok(())
+	}
 }
 
 /// Configuration for the DAS network service.
@@ -158,6 +241,11 @@ pub struct DasNetworkConfig {
 	pub bootstrap_timeout: Duration,
 	/// Maximum number of parallel connections to maintain.
 	pub parallel_limit: usize,
+	/// Upload bandwidth budgets of the segments served over the archive protocol.
+	pub bandwidth: BandwidthConfig,
+	/// The role announced to the peers in the handshake, archive nodes announce themselves as
+	/// [`NodeRole::Archive`] whatever the configured role.
+	pub role: NodeRole,
 }
 
 impl Default for DasNetworkConfig {
@@ -170,6 +258,8 @@ impl Default for DasNetworkConfig {
 			retry_delay: Duration::from_secs(5),
 			bootstrap_timeout: Duration::from_secs(60),
 			parallel_limit: 10,
+			bandwidth: BandwidthConfig::default(),
+			role: NodeRole::default(),
 		}
 	}
 }
@@ -0,0 +1,223 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Segment serving receipts protocol.
+//!
+//! Each node counts the segments it receives from its peers over the archive protocol, by
+//! serving period. A node serving segments for rewards also counts the segments it serves, and
+//! once a period is over, proposes to each peer it served a [`ServingReceipt`] over the
+//! request-response protocol [`SERVING_PROTOCOL`]. The peer answers with its signature if it
+//! received at least the proposed segments and bytes from the node during the period, and the
+//! signed receipts are handed to the node to be claimed on-chain.
+use crate::AccountId;
+use async_trait::async_trait;
+use codec::{Decode, Encode};
+use futures::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use libp2p::{
+	core::upgrade::{read_length_prefixed, write_length_prefixed, ProtocolName},
+	identity::{Keypair, PublicKey},
+	request_response::RequestResponseCodec,
+	PeerId,
+};
+use melo_core_primitives::serving::{serving_period, ServingReceipt};
+use sp_core::ed25519;
+use std::{
+	collections::HashMap,
+	io,
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The name of the serving receipts protocol.
+pub const SERVING_PROTOCOL: &[u8] = b"/melodot-das/serving/1";
+
+/// The number of past periods whose received segments are kept to countersign receipts.
+const RECEIVED_PERIODS: u32 = 2;
+
+/// The maximum size of an encoded receipt.
+const MAX_RECEIPT_SIZE: usize = 1024;
+
+/// The segments and bytes exchanged with a peer during a period.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Tally {
+	pub segments: u32,
+	pub bytes: u64,
+}
+
+impl Tally {
+	fn add(&mut self, segments: u32, bytes: u64) {
+		self.segments = self.segments.saturating_add(segments);
+		self.bytes = self.bytes.saturating_add(bytes);
+	}
+
+	fn covers(&self, segments: u32, bytes: u64) -> bool {
+		segments <= self.segments && bytes <= self.bytes
+	}
+}
+
+/// Returns the current serving period.
+pub fn current_period() -> u32 {
+	let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+	serving_period(secs)
+}
+
+/// Returns the ed25519 key of `peer`, if its identity is an ed25519 key.
+pub fn ed25519_key(peer: &PeerId) -> Option<ed25519::Public> {
+	let multihash = peer.as_ref();
+	// Ed25519 keys are small enough to be inlined in the peer id.
+	if multihash.code() != 0 {
+		return None
+	}
+	match PublicKey::from_protobuf_encoding(multihash.digest()) {
+		Ok(PublicKey::Ed25519(key)) => Some(ed25519::Public::from_raw(key.encode())),
+		_ => None,
+	}
+}
+
+/// Signs `receipt` with `keypair`, if it is an ed25519 keypair.
+pub fn sign_receipt(
+	keypair: &Keypair,
+	receipt: &ServingReceipt<AccountId>,
+) -> Option<ed25519::Signature> {
+	if !matches!(keypair, Keypair::Ed25519(_)) {
+		return None
+	}
+	let signature = keypair.sign(&receipt.payload()).ok()?;
+	let signature: [u8; 64] = signature.try_into().ok()?;
+	Some(ed25519::Signature::from_raw(signature))
+}
+
+/// The segments served to and received from the peers, by period.
+#[derive(Debug, Default)]
+pub struct ServingLedger {
+	served: HashMap<(PeerId, u32), Tally>,
+	received: HashMap<(PeerId, u32), Tally>,
+}
+
+impl ServingLedger {
+	/// Records `segments` in `bytes` served to `peer` during `period`.
+	pub fn record_served(&mut self, peer: PeerId, period: u32, segments: u32, bytes: u64) {
+		self.served.entry((peer, period)).or_default().add(segments, bytes);
+	}
+
+	/// Records `segments` in `bytes` received from `peer` during `period`, and forgets the
+	/// segments received before the last [`RECEIVED_PERIODS`] periods.
+	pub fn record_received(&mut self, peer: PeerId, period: u32, segments: u32, bytes: u64) {
+		self.received.entry((peer, period)).or_default().add(segments, bytes);
+		let oldest = period.saturating_sub(RECEIVED_PERIODS);
+		self.received.retain(|(_, period), _| *period >= oldest);
+	}
+
+	/// Removes and returns the segments served during the periods before `period`.
+	pub fn take_closed(&mut self, period: u32) -> Vec<(PeerId, u32, Tally)> {
+		let closed: Vec<_> =
+			self.served.keys().filter(|(_, served)| *served < period).copied().collect();
+		closed
+			.into_iter()
+			.filter_map(|key| self.served.remove(&key).map(|tally| (key.0, key.1, tally)))
+			.collect()
+	}
+
+	/// Whether the node received at least `segments` and `bytes` from `peer` during `period`.
+	pub fn covers(&self, peer: &PeerId, period: u32, segments: u32, bytes: u64) -> bool {
+		self.received
+			.get(&(*peer, period))
+			.map_or(false, |tally| tally.covers(segments, bytes))
+	}
+}
+
+/// The [`SERVING_PROTOCOL`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ServingProtocol;
+
+impl ProtocolName for ServingProtocol {
+	fn protocol_name(&self) -> &[u8] {
+		SERVING_PROTOCOL
+	}
+}
+
+/// SCALE codec of the [`ServingProtocol`] messages, each message is length prefixed. The request
+/// is the proposed receipt, unsigned, and the response the signature of the peer, if it agrees.
+#[derive(Debug, Clone, Default)]
+pub struct ServingCodec;
+
+async fn read_message<T, M>(io: &mut T) -> io::Result<M>
+where
+	T: AsyncRead + Unpin + Send,
+	M: Decode,
+{
+	let bytes = read_length_prefixed(io, MAX_RECEIPT_SIZE).await?;
+	M::decode(&mut &bytes[..]).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+async fn write_message<T, M>(io: &mut T, message: M) -> io::Result<()>
+where
+	T: AsyncWrite + Unpin + Send,
+	M: Encode,
+{
+	write_length_prefixed(io, message.encode()).await?;
+	io.close().await
+}
+
+#[async_trait]
+impl RequestResponseCodec for ServingCodec {
+	type Protocol = ServingProtocol;
+	type Request = ServingReceipt<AccountId>;
+	type Response = Option<ed25519::Signature>;
+
+	async fn read_request<T>(
+		&mut self,
+		_: &ServingProtocol,
+		io: &mut T,
+	) -> io::Result<Self::Request>
+	where
+		T: AsyncRead + Unpin + Send,
+	{
+		read_message(io).await
+	}
+
+	async fn read_response<T>(
+		&mut self,
+		_: &ServingProtocol,
+		io: &mut T,
+	) -> io::Result<Self::Response>
+	where
+		T: AsyncRead + Unpin + Send,
+	{
+		read_message(io).await
+	}
+
+	async fn write_request<T>(
+		&mut self,
+		_: &ServingProtocol,
+		io: &mut T,
+		request: Self::Request,
+	) -> io::Result<()>
+	where
+		T: AsyncWrite + Unpin + Send,
+	{
+		write_message(io, request).await
+	}
+
+	async fn write_response<T>(
+		&mut self,
+		_: &ServingProtocol,
+		io: &mut T,
+		response: Self::Response,
+	) -> io::Result<()>
+	where
+		T: AsyncWrite + Unpin + Send,
+	{
+		write_message(io, response).await
+	}
+}
@@ -12,10 +12,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::behaviour::BehaviourEvent;
+use crate::{behaviour::BehaviourEvent, solution::SolutionVerdict};
 use anyhow::Result;
 use futures::channel::{mpsc, oneshot};
 use libp2p::{
+	gossipsub::MessageId,
 	kad::{Quorum, Record},
 	Multiaddr, PeerId,
 };
@@ -30,4 +31,12 @@ pub enum Command {
 	GetKadRecord { key: KademliaKey, sender: oneshot::Sender<Result<Vec<Record>>> },
 	PutKadRecord { record: Record, quorum: Quorum, sender: oneshot::Sender<Result<()>> },
 	RemoveRecords { keys: Vec<KademliaKey>, sender: oneshot::Sender<Result<()>> },
+	GetArchiveProviders { sender: oneshot::Sender<Result<Vec<PeerId>>> },
+	GetClosestPeers { key: KademliaKey, sender: oneshot::Sender<Result<Vec<PeerId>>> },
+	RequestSegments { peer_id: PeerId, keys: Vec<Vec<u8>>, sender: oneshot::Sender<Result<Values>> },
+	PublishSolution { data: Vec<u8>, sender: oneshot::Sender<Result<()>> },
+	ReportSolution { message_id: MessageId, source: PeerId, verdict: SolutionVerdict },
 }
+
+/// The values of a batch of keys, `None` for the missing ones.
+pub type Values = Vec<Option<Vec<u8>>>;
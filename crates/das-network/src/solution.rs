@@ -0,0 +1,102 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Solution relay protocol.
+//!
+//! Farmers publish the solutions they find on the gossipsub topic [`SOLUTION_TOPIC`] instead of
+//! each submitting a claim to a node. Only the nodes relaying solutions subscribe to the topic.
+//! A message received by such a node is handed to the relay as an [`InboundSolution`], which
+//! validates it and reports a [`SolutionVerdict`]: only accepted messages are gossiped further,
+//! and the peers sending rejected ones are penalized.
+//!
+//! Messages are identified by the hash of their content, so that the same solution received from
+//! several peers is only validated once.
+use codec::{Decode, Encode};
+use libp2p::{
+	gossipsub::{
+		GossipsubConfig, GossipsubConfigBuilder, GossipsubMessage, IdentTopic, MessageAcceptance,
+		MessageId, ValidationMode,
+	},
+	PeerId,
+};
+use sp_core::hashing::blake2_256;
+
+/// The gossipsub topic of the solutions.
+pub const SOLUTION_TOPIC: &str = "/melodot-das/solutions/1";
+
+/// The maximum size of an encoded [`SolutionAnnouncement`].
+pub const MAX_SOLUTION_SIZE: usize = 64 * 1024;
+
+/// A solution published by a farmer.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct SolutionAnnouncement {
+	/// The reward account claiming the solution.
+	pub farmer: [u8; 32],
+	/// The farmer ID the solution was found with, bound to `farmer`.
+	pub farmer_id: [u8; 32],
+	/// The SCALE encoding of the `CompactSolution`.
+	pub solution: Vec<u8>,
+}
+
+/// A solution received from the network, waiting for its verdict.
+#[derive(Debug, Clone)]
+pub struct InboundSolution {
+	/// The identifier of the message, passed back with the verdict.
+	pub message_id: MessageId,
+	/// The peer the message was received from.
+	pub source: PeerId,
+	/// The solution.
+	pub announcement: SolutionAnnouncement,
+}
+
+/// The verdict of the relay on an [`InboundSolution`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolutionVerdict {
+	/// The solution is valid and gossiped further.
+	Accept,
+	/// The solution is dropped without penalizing the peer, e.g. a duplicate or a solution
+	/// over the quota of the peer.
+	Ignore,
+	/// The solution is invalid, it is dropped and the peer is penalized.
+	Reject,
+}
+
+impl From<SolutionVerdict> for MessageAcceptance {
+	fn from(verdict: SolutionVerdict) -> Self {
+		match verdict {
+			SolutionVerdict::Accept => MessageAcceptance::Accept,
+			SolutionVerdict::Ignore => MessageAcceptance::Ignore,
+			SolutionVerdict::Reject => MessageAcceptance::Reject,
+		}
+	}
+}
+
+/// Returns the topic of the solutions.
+pub(crate) fn solution_topic() -> IdentTopic {
+	IdentTopic::new(SOLUTION_TOPIC)
+}
+
+/// Returns the configuration of the gossipsub behaviour, which waits for the verdict of the relay
+/// before forwarding a message.
+pub(crate) fn gossipsub_config() -> GossipsubConfig {
+	GossipsubConfigBuilder::default()
+		.validation_mode(ValidationMode::Strict)
+		.validate_messages()
+		.max_transmit_size(MAX_SOLUTION_SIZE)
+		.message_id_fn(|message: &GossipsubMessage| {
+			MessageId::from(blake2_256(&message.data).to_vec())
+		})
+		.build()
+		.expect("The gossipsub configuration is valid; qed")
+}
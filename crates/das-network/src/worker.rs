@@ -11,37 +11,59 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
-use crate::{Behavior, BehaviourEvent, Command, DasNetworkConfig};
+use crate::{
+	archive::{SegmentsRequest, SegmentsResponse, ARCHIVE_PROVIDER_KEY},
+	bandwidth::BandwidthLimiter,
+	handshake::{Handshake, NodeRole},
+	serving::{current_period, ed25519_key, sign_receipt, ServingLedger},
+	shared::Values,
+	solution::{solution_topic, InboundSolution, SolutionAnnouncement, SolutionVerdict},
+	AccountId, Behavior, BehaviourEvent, Command, DasNetworkConfig, KademliaKey,
+};
+use codec::{Decode, Encode};
 use futures::{
 	channel::{mpsc, oneshot},
 	stream::StreamExt,
 };
 use libp2p::{
+	gossipsub::{GossipsubEvent, MessageId},
 	identify::Event as IdentifyEvent,
+	identity::Keypair,
 	kad::{
-		store::RecordStore, BootstrapOk, GetRecordOk, InboundRequest, KademliaEvent, PutRecordOk,
-		QueryId, QueryResult, Record,
+		store::RecordStore, BootstrapOk, GetClosestPeersError, GetClosestPeersOk, GetProvidersOk,
+		GetRecordOk, InboundRequest, KademliaEvent, PutRecordOk, QueryId, QueryResult, Record,
 	},
 	mdns::Event as MdnsEvent,
 	multiaddr::Protocol,
+	request_response::{OutboundFailure, RequestId, RequestResponseEvent, RequestResponseMessage},
 	swarm::{ConnectionError, Swarm, SwarmEvent},
 	Multiaddr, PeerId,
 };
 use log::{debug, error, info, trace, warn};
+use melo_core_primitives::serving::ServingReceipt;
+use melo_das_db::traits::DasKv;
 use prometheus_endpoint::{register, Counter, CounterVec, Gauge, Opts, U64};
-use std::{collections::HashMap, fmt::Debug};
+use sp_core::ed25519;
+use std::{collections::HashMap, fmt::Debug, time::Duration};
 
 /// The maximum number of connection retries.
 const MAX_RETRIES: u8 = 3;
 
+/// How often the receipts of the segments served during the closed periods are proposed.
+const RECEIPT_INTERVAL: Duration = Duration::from_secs(60);
+
 const LOG_TARGET: &str = "melo-das-network-worker";
 
 enum QueryResultSender {
 	PutRecord(oneshot::Sender<Result<(), anyhow::Error>>),
 	GetRecord(oneshot::Sender<Result<Vec<Record>, anyhow::Error>>),
 	Bootstrap(oneshot::Sender<Result<(), anyhow::Error>>),
+	GetProviders(oneshot::Sender<Result<Vec<PeerId>, anyhow::Error>>),
+	GetClosestPeers(oneshot::Sender<Result<Vec<PeerId>, anyhow::Error>>),
 }
 
+type SegmentsResultSender = oneshot::Sender<Result<Values, anyhow::Error>>;
+
 macro_rules! handle_send {
 	($sender_variant:ident, $msg:expr, $result:expr) => {
 		if let Some(QueryResultSender::$sender_variant(ch)) = $msg {
@@ -62,16 +84,27 @@ pub struct DasNetwork {
 	retry_counts: HashMap<PeerId, u8>,
 	metrics: Option<Metrics>,
 	known_addresses: HashMap<PeerId, Vec<String>>,
+	archive: Option<Box<dyn DasKv + Send>>,
+	pending_segment_requests: HashMap<RequestId, SegmentsResultSender>,
+	bandwidth: BandwidthLimiter,
+	solution_relay: Option<mpsc::Sender<InboundSolution>>,
+	handshake: Handshake,
+	keypair: Keypair,
+	serving: ServingLedger,
+	serving_rewards: Option<(AccountId, mpsc::Sender<ServingReceipt<AccountId>>)>,
+	pending_receipts: HashMap<RequestId, ServingReceipt<AccountId>>,
 }
 
 impl DasNetwork {
-	/// Creates a new worker with the given `swarm`, `command_receiver`, `prometheus_registry`, and
-	/// `config`. The `swarm` is a `Swarm` instance of the `Behavior` type.
+	/// Creates a new worker with the given `swarm`, `keypair`, `command_receiver`,
+	/// `prometheus_registry`, and `config`. The `swarm` is a `Swarm` instance of the `Behavior`
+	/// type, and `keypair` the identity of the node, signing the serving receipts of its peers.
 	/// The `command_receiver` is an `mpsc::Receiver` instance of the `Command` type.
 	/// The `prometheus_registry` is an optional `prometheus_endpoint::Registry` instance.
 	/// The `config` is a reference to a `DasNetworkConfig` instance.
 	pub fn new(
 		swarm: Swarm<Behavior>,
+		keypair: Keypair,
 		command_receiver: mpsc::Receiver<Command>,
 		prometheus_registry: Option<prometheus_endpoint::Registry>,
 		config: &DasNetworkConfig,
@@ -124,9 +157,56 @@ impl DasNetwork {
 			retry_counts: HashMap::default(),
 			metrics,
 			known_addresses,
+			archive: None,
+			pending_segment_requests: HashMap::default(),
+			bandwidth: BandwidthLimiter::new(config.bandwidth.clone()),
+			solution_relay: None,
+			handshake: Handshake::local(config.role),
+			keypair,
+			serving: ServingLedger::default(),
+			serving_rewards: None,
+			pending_receipts: HashMap::default(),
 		}
 	}
 
+	/// Turns the node into an archive node.
+	///
+	/// Every record put into or found in the DHT is also written to `store`, where it is never
+	/// pruned, and the stored segments are served to the peers over the archive protocol. The node
+	/// announces itself as [`NodeRole::Archive`] in its handshake.
+	pub fn with_archive(mut self, store: Box<dyn DasKv + Send>) -> Self {
+		self.archive = Some(store);
+		self.handshake.role = NodeRole::Archive;
+		self
+	}
+
+	/// Makes the node relay the solutions published by the farmers.
+	///
+	/// The node subscribes to the solution topic and hands each solution received to `relay`,
+	/// which reports its verdict through [`crate::Service::report_solution`]. Solutions received
+	/// while `relay` is full are ignored.
+	pub fn with_solution_relay(mut self, relay: mpsc::Sender<InboundSolution>) -> Self {
+		if let Err(e) = self.swarm.behaviour_mut().solutions.subscribe(&solution_topic()) {
+			warn!("Failed to subscribe to the solution topic: {:?}", e);
+		}
+		self.solution_relay = Some(relay);
+		self
+	}
+
+	/// Makes the node claim rewards for the segments it serves, with `account`.
+	///
+	/// The node counts the segments it serves to each peer, and once a serving period is over,
+	/// proposes a receipt of them to the peer. The receipts signed by the peers are handed to
+	/// `sink`, to be claimed on-chain. Receipts signed while `sink` is full are dropped.
+	pub fn with_serving_rewards(
+		mut self,
+		account: AccountId,
+		sink: mpsc::Sender<ServingReceipt<AccountId>>,
+	) -> Self {
+		self.serving_rewards = Some((account, sink));
+		self
+	}
+
 	/// Runs the worker asynchronously.
 	/// If there are known addresses, it adds them to the Kademlia routing table and initiates a
 	/// bootstrap process. The worker then enters an event loop, handling incoming swarm events and
@@ -147,6 +227,14 @@ impl DasNetwork {
 			}
 		}
 
+		if self.archive.is_some() {
+			let key = KademliaKey::new(&ARCHIVE_PROVIDER_KEY);
+			if let Err(e) = self.swarm.behaviour_mut().kademlia.start_providing(key) {
+				warn!("Failed to announce the archive node: {:?}", e);
+			}
+		}
+
+		let mut receipts = tokio::time::interval(RECEIPT_INTERVAL);
 		loop {
 			tokio::select! {
 				swarm_event = self.swarm.select_next_some() => {
@@ -154,6 +242,9 @@ impl DasNetwork {
 				},
 				command = self.command_receiver.select_next_some() => {
 					self.handle_command(command).await;
+				},
+				_ = receipts.tick() => {
+					self.propose_receipts();
 				}
 			}
 		}
@@ -188,10 +279,23 @@ impl DasNetwork {
 				self.handle_kademlia_event(event).await,
 			SwarmEvent::Behaviour(BehaviourEvent::Identify(event)) =>
 				self.handle_identify_event(event).await,
+			SwarmEvent::Behaviour(BehaviourEvent::Archive(event)) =>
+				self.handle_archive_event(event).await,
+			SwarmEvent::Behaviour(BehaviourEvent::Handshake(event)) =>
+				self.handle_handshake_event(event),
+			SwarmEvent::Behaviour(BehaviourEvent::Serving(event)) =>
+				self.handle_serving_event(event),
+			SwarmEvent::Behaviour(BehaviourEvent::Solutions(event)) =>
+				self.handle_solution_event(event),
+			SwarmEvent::ConnectionEstablished { peer_id, num_established, .. } =>
+				if num_established.get() == 1 {
+					let handshake = self.handshake.clone();
+					self.swarm.behaviour_mut().handshake.send_request(&peer_id, handshake);
+				},
 			SwarmEvent::NewListenAddr { address, .. } => {
 				let peer_id = self.swarm.local_peer_id();
 				let address_with_peer = address.with(Protocol::P2p((*peer_id).into()));
-				debug!("Local node is listening on {:?}", address_with_peer);
+				info!("🌐 DAS network listening on {}", address_with_peer);
 			},
 			SwarmEvent::Behaviour(BehaviourEvent::Mdns(event)) => {
 				// Obtain a mutable reference to the behaviour to avoid multiple mutable borrowings
@@ -273,20 +377,56 @@ impl DasNetwork {
 			},
 			KademliaEvent::InboundRequest { request } => {
 				trace!("Received an inbound request: {:?}", request);
-				if let InboundRequest::PutRecord { source, record: Some(block_ref), .. } = request {
-					trace!(
-						"Received an inbound PUT request. Record Key: {:?}. Request Source: {:?}",
-						block_ref.key,
-						source
-					);
+				if let Some(metrics) = &self.metrics {
+					let label = match &request {
+						InboundRequest::GetRecord { present_locally: true, .. } => "get_record_served",
+						InboundRequest::GetRecord { .. } => "get_record",
+						InboundRequest::PutRecord { .. } => "put_record",
+						InboundRequest::AddProvider { .. } => "add_provider",
+						InboundRequest::FindNode { .. } => "find_node",
+						_ => "other",
+					};
+					metrics.inbound_requests.with_label_values(&[label]).inc();
+				}
+				match request {
+					InboundRequest::PutRecord { source, record: Some(block_ref), .. } => {
+						trace!(
+							"Received an inbound PUT request. Record Key: {:?}. Request Source: {:?}",
+							block_ref.key,
+							source
+						);
+						self.archive_record(&block_ref);
+						// Records are filtered so that archive nodes see them, store them as the
+						// unfiltered Kademlia would.
+						if let Err(e) =
+							self.swarm.behaviour_mut().kademlia.store_mut().put(block_ref)
+						{
+							debug!("Failed to store the inbound record: {:?}", e);
+						}
+					},
+					// Kademlia only hands over the provider records announced by the provider
+					// itself. The DHT is only used to find the archive nodes, other keys are
+					// ignored.
+					InboundRequest::AddProvider { record: Some(provider) } => {
+						if provider.key != KademliaKey::new(&ARCHIVE_PROVIDER_KEY) {
+							debug!("Ignoring a provider record of key {:?}", provider.key);
+						} else if let Err(e) =
+							self.swarm.behaviour_mut().kademlia.store_mut().add_provider(provider)
+						{
+							debug!("Failed to store the inbound provider record: {:?}", e);
+						}
+					},
+					_ => {},
 				}
 			},
 			KademliaEvent::OutboundQueryProgressed { id, result, .. } => match result {
 				QueryResult::GetRecord(result) => {
 					let msg = self.query_id_receivers.remove(&id);
 					match result {
-						Ok(GetRecordOk::FoundRecord(rec)) =>
-							handle_send!(GetRecord, msg, Ok(vec![rec.record])),
+						Ok(GetRecordOk::FoundRecord(rec)) => {
+							self.archive_record(&rec.record);
+							handle_send!(GetRecord, msg, Ok(vec![rec.record]))
+						},
 						Ok(GetRecordOk::FinishedWithNoAdditionalRecord { .. }) =>
 							handle_send!(GetRecord, msg, Err(anyhow::anyhow!("No record found."))),
 						Err(err) => handle_send!(GetRecord, msg, Err(err.into())),
@@ -299,6 +439,34 @@ impl DasNetwork {
 						Err(err) => handle_send!(PutRecord, msg, Err(err.into())),
 					}
 				},
+				QueryResult::GetProviders(result) => match result {
+					Ok(GetProvidersOk::FoundProviders { providers, .. }) => {
+						let msg = self.query_id_receivers.remove(&id);
+						handle_send!(GetProviders, msg, Ok(providers.into_iter().collect()));
+					},
+					Ok(GetProvidersOk::FinishedWithNoAdditionalRecord { .. }) => {
+						let msg = self.query_id_receivers.remove(&id);
+						handle_send!(GetProviders, msg, Ok(vec![]));
+					},
+					Err(err) => {
+						let msg = self.query_id_receivers.remove(&id);
+						handle_send!(GetProviders, msg, Err(err.into()));
+					},
+				},
+				QueryResult::GetClosestPeers(result) => {
+					let msg = self.query_id_receivers.remove(&id);
+					match result {
+						Ok(GetClosestPeersOk { peers, .. }) =>
+							handle_send!(GetClosestPeers, msg, Ok(peers)),
+						// The peers found before the timeout are still the closest known.
+						Err(GetClosestPeersError::Timeout { peers, .. }) =>
+							handle_send!(GetClosestPeers, msg, Ok(peers)),
+					}
+				},
+				QueryResult::StartProviding(result) => match result {
+					Ok(_) => info!("📦 Announced as a DAS archive node"),
+					Err(err) => warn!("Failed to announce the archive node: {:?}", err),
+				},
 				QueryResult::Bootstrap(result) => match result {
 					Ok(BootstrapOk { peer, num_remaining }) => {
 						trace!("BootstrapOK event. PeerID: {peer:?}. Num remaining: {num_remaining:?}.");
@@ -331,6 +499,333 @@ impl DasNetwork {
 		}
 	}
 
+	async fn handle_archive_event(
+		&mut self,
+		event: RequestResponseEvent<SegmentsRequest, SegmentsResponse>,
+	) {
+		match event {
+			RequestResponseEvent::Message { peer, message } => match message {
+				RequestResponseMessage::Request { request, channel, .. } => {
+					trace!(
+						"Received an archive request of {} keys from {:?}",
+						request.keys.len(),
+						peer
+					);
+					if let Some(metrics) = &self.metrics {
+						metrics.inbound_requests.with_label_values(&["archive_segments"]).inc();
+					}
+					let values = request.keys.iter().map(|key| self.stored_value(key)).collect();
+					let response = SegmentsResponse { values };
+
+					// A throttled request is dropped, the peer can ask another archive node.
+					let class = self.bandwidth.classify(request.keys.len());
+					let bytes = response.encoded_size() as u64;
+					if let Err(throttle) = self.bandwidth.admit(peer, class, bytes) {
+						debug!(
+							"Throttled the archive request of {:?}, {} bytes over the {} budget",
+							peer,
+							bytes,
+							throttle.label()
+						);
+						if let Some(metrics) = &self.metrics {
+							metrics
+								.archive_throttled
+								.with_label_values(&[class.label(), throttle.label()])
+								.inc();
+						}
+						return
+					}
+					if let Some(metrics) = &self.metrics {
+						metrics
+							.archive_served_bytes
+							.with_label_values(&[class.label()])
+							.inc_by(bytes);
+					}
+					if self.serving_rewards.is_some() {
+						let served = response.values.iter().filter(|v| v.is_some()).count() as u32;
+						if served > 0 {
+							let period = current_period();
+							self.serving.record_served(peer, period, served, bytes);
+						}
+					}
+
+					if self.swarm.behaviour_mut().archive.send_response(channel, response).is_err()
+					{
+						debug!("Failed to send the archive response to {:?}", peer);
+					}
+				},
+				RequestResponseMessage::Response { request_id, response } => {
+					let received = response.values.iter().filter(|v| v.is_some()).count() as u32;
+					if received > 0 {
+						let (period, bytes) = (current_period(), response.encoded_size() as u64);
+						self.serving.record_received(peer, period, received, bytes);
+					}
+					if let Some(sender) = self.pending_segment_requests.remove(&request_id) {
+						if sender.send(Ok(response.values)).is_err() {
+							debug!("Failed to send result");
+						}
+					}
+				},
+			},
+			RequestResponseEvent::OutboundFailure { peer, request_id, error } => {
+				debug!("Archive request to {:?} failed: {:?}", peer, error);
+				if let Some(sender) = self.pending_segment_requests.remove(&request_id) {
+					if sender
+						.send(Err(anyhow::anyhow!("Archive request failed: {:?}", error)))
+						.is_err()
+					{
+						debug!("Failed to send result");
+					}
+				}
+			},
+			RequestResponseEvent::InboundFailure { peer, error, .. } => {
+				debug!("Failed to answer the archive request of {:?}: {:?}", peer, error);
+			},
+			RequestResponseEvent::ResponseSent { .. } => {},
+		}
+	}
+
+	fn handle_handshake_event(&mut self, event: RequestResponseEvent<Handshake, Handshake>) {
+		match event {
+			RequestResponseEvent::Message { peer, message } => match message {
+				RequestResponseMessage::Request { request, channel, .. } => {
+					let handshake = self.handshake.clone();
+					let behaviour = self.swarm.behaviour_mut();
+					if behaviour.handshake.send_response(channel, handshake).is_err() {
+						debug!("Failed to send the handshake response to {:?}", peer);
+					}
+					self.check_handshake(peer, &request);
+				},
+				RequestResponseMessage::Response { response, .. } =>
+					self.check_handshake(peer, &response),
+			},
+			// Nodes predating the handshake are kept.
+			RequestResponseEvent::OutboundFailure {
+				peer,
+				error: OutboundFailure::UnsupportedProtocols,
+				..
+			} => {
+				debug!("Peer {:?} does not support the DAS handshake", peer);
+				self.record_handshake("unsupported");
+			},
+			RequestResponseEvent::OutboundFailure { peer, error, .. } => {
+				debug!("Handshake with {:?} failed: {:?}", peer, error);
+			},
+			RequestResponseEvent::InboundFailure { peer, error, .. } => {
+				debug!("Failed to answer the handshake of {:?}: {:?}", peer, error);
+			},
+			RequestResponseEvent::ResponseSent { .. } => {},
+		}
+	}
+
+	fn handle_serving_event(
+		&mut self,
+		event: RequestResponseEvent<ServingReceipt<AccountId>, Option<ed25519::Signature>>,
+	) {
+		match event {
+			RequestResponseEvent::Message { peer, message } => match message {
+				RequestResponseMessage::Request { request, channel, .. } => {
+					let signature = self.countersign(&peer, &request);
+					self.record_receipt(if signature.is_some() { "signed" } else { "refused" });
+					let behaviour = self.swarm.behaviour_mut();
+					if behaviour.serving.send_response(channel, signature).is_err() {
+						debug!("Failed to send the serving receipt to {:?}", peer);
+					}
+				},
+				RequestResponseMessage::Response { request_id, response } => {
+					let receipt = self.pending_receipts.remove(&request_id);
+					match (receipt, response) {
+						(Some(mut receipt), Some(signature)) => {
+							receipt.signature = signature;
+							if !receipt.verify() {
+								debug!("Invalid serving receipt signature from {:?}", peer);
+								return
+							}
+							self.record_receipt("received");
+							if let Some((_, sink)) = self.serving_rewards.as_mut() {
+								if sink.try_send(receipt).is_err() {
+									debug!("Dropped the serving receipt of {:?}, sink busy", peer);
+								}
+							}
+						},
+						(Some(_), None) => {
+							debug!("Peer {:?} refused to sign a serving receipt", peer);
+							self.record_receipt("rejected");
+						},
+						(None, _) => {},
+					}
+				},
+			},
+			RequestResponseEvent::OutboundFailure { peer, request_id, error } => {
+				debug!("Serving receipt request to {:?} failed: {:?}", peer, error);
+				self.pending_receipts.remove(&request_id);
+			},
+			RequestResponseEvent::InboundFailure { peer, error, .. } => {
+				debug!("Failed to answer the serving receipt request of {:?}: {:?}", peer, error);
+			},
+			RequestResponseEvent::ResponseSent { .. } => {},
+		}
+	}
+
+	/// Returns the signature of the node over the `receipt` proposed by `peer`, if the node
+	/// received at least the segments and bytes it lists.
+	fn countersign(
+		&self,
+		peer: &PeerId,
+		receipt: &ServingReceipt<AccountId>,
+	) -> Option<ed25519::Signature> {
+		if ed25519_key(self.swarm.local_peer_id()) != Some(receipt.requester) {
+			return None
+		}
+		if !self.serving.covers(peer, receipt.period, receipt.segments, receipt.bytes) {
+			return None
+		}
+		sign_receipt(&self.keypair, receipt)
+	}
+
+	/// Proposes to the peers the receipts of the segments served to them during the closed
+	/// periods, if the node claims serving rewards.
+	fn propose_receipts(&mut self) {
+		let account = match &self.serving_rewards {
+			Some((account, _)) => account.clone(),
+			None => return,
+		};
+		for (peer, period, tally) in self.serving.take_closed(current_period()) {
+			let requester = match ed25519_key(&peer) {
+				Some(requester) => requester,
+				None => continue,
+			};
+			let receipt = ServingReceipt::unsigned(
+				account.clone(),
+				requester,
+				period,
+				tally.segments,
+				tally.bytes,
+			);
+			let behaviour = self.swarm.behaviour_mut();
+			let request_id = behaviour.serving.send_request(&peer, receipt.clone());
+			self.pending_receipts.insert(request_id, receipt);
+			self.record_receipt("proposed");
+		}
+	}
+
+	fn record_receipt(&self, outcome: &str) {
+		if let Some(metrics) = &self.metrics {
+			metrics.serving_receipts.with_label_values(&[outcome]).inc();
+		}
+	}
+
+	/// Disconnects `peer` if its `handshake` is incompatible with the local one.
+	fn check_handshake(&mut self, peer: PeerId, handshake: &Handshake) {
+		match self.handshake.negotiate(handshake) {
+			Ok(version) => {
+				debug!(
+					"Handshake with {:?} as {} node, DAS protocol version {}",
+					peer,
+					handshake.role.label(),
+					version
+				);
+				self.record_handshake("accepted");
+			},
+			Err(incompatibility) => {
+				warn!(
+					"Disconnecting peer {:?}, its DAS handshake is incompatible: {:?}",
+					peer, incompatibility
+				);
+				self.record_handshake(incompatibility.label());
+				self.swarm.behaviour_mut().kademlia.remove_peer(&peer);
+				if self.swarm.disconnect_peer_id(peer).is_err() {
+					debug!("Peer {:?} is already disconnected", peer);
+				}
+			},
+		}
+	}
+
+	fn record_handshake(&self, outcome: &str) {
+		if let Some(metrics) = &self.metrics {
+			metrics.handshakes.with_label_values(&[outcome]).inc();
+		}
+	}
+
+	fn handle_solution_event(&mut self, event: GossipsubEvent) {
+		let (propagation_source, message_id, message) = match event {
+			GossipsubEvent::Message { propagation_source, message_id, message } =>
+				(propagation_source, message_id, message),
+			_ => {
+				trace!("Solution relay event: {:?}", event);
+				return
+			},
+		};
+		if let Some(metrics) = &self.metrics {
+			metrics.inbound_requests.with_label_values(&["solution"]).inc();
+		}
+
+		let announcement = match SolutionAnnouncement::decode(&mut &message.data[..]) {
+			Ok(announcement) => announcement,
+			Err(_) => {
+				debug!("Undecodable solution from {:?}", propagation_source);
+				self.report_solution(&message_id, &propagation_source, SolutionVerdict::Reject);
+				return
+			},
+		};
+
+		let inbound = InboundSolution {
+			message_id: message_id.clone(),
+			source: propagation_source,
+			announcement,
+		};
+		let queued = match self.solution_relay.as_mut() {
+			Some(relay) => relay.try_send(inbound).is_ok(),
+			None => false,
+		};
+		if !queued {
+			debug!("Dropped a solution from {:?}, the relay is busy", propagation_source);
+			self.report_solution(&message_id, &propagation_source, SolutionVerdict::Ignore);
+		}
+	}
+
+	fn report_solution(
+		&mut self,
+		message_id: &MessageId,
+		source: &PeerId,
+		verdict: SolutionVerdict,
+	) {
+		if let Err(e) = self.swarm.behaviour_mut().solutions.report_message_validation_result(
+			message_id,
+			source,
+			verdict.into(),
+		) {
+			debug!("Failed to report the verdict on a solution: {:?}", e);
+		}
+	}
+
+	/// Returns the value stored under `key` in the archive, or else in the DHT record store.
+	fn stored_value(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+		if let Some(value) = self.archive.as_mut().and_then(|store| store.get(key)) {
+			return Some(value)
+		}
+		let key = KademliaKey::new(&key);
+		self.swarm
+			.behaviour_mut()
+			.kademlia
+			.store_mut()
+			.get(&key)
+			.map(|record| record.value.clone())
+	}
+
+	/// Writes `record` to the archive, if the node is an archive node.
+	fn archive_record(&mut self, record: &Record) {
+		if let Some(store) = self.archive.as_mut() {
+			if !store.contains(record.key.as_ref()) {
+				if let Some(metrics) = &self.metrics {
+					metrics.archived_segments.inc();
+					metrics.archived_bytes.inc_by(record.value.len() as u64);
+				}
+			}
+			store.set(record.key.as_ref(), &record.value);
+		}
+	}
+
 	async fn handle_command(&mut self, command: Command) {
 		if let Some(metrics) = &self.metrics {
 			metrics.requests.inc();
@@ -376,6 +871,13 @@ impl DasNetwork {
 				}
 			},
 			Command::GetKadRecord { key, sender } => {
+				if let Some(value) = self.archive.as_mut().and_then(|store| store.get(key.as_ref()))
+				{
+					sender.send(Ok(vec![Record::new(key, value)])).unwrap_or_else(|_| {
+						debug!("Failed to send result");
+					});
+					return
+				}
 				let query_id = self.swarm.behaviour_mut().kademlia.get_record(key);
 				self.query_id_receivers.insert(query_id, QueryResultSender::GetRecord(sender));
 			},
@@ -387,6 +889,7 @@ impl DasNetwork {
 				if let Some(metrics) = &self.metrics {
 					metrics.publish.inc();
 				}
+				self.archive_record(&record);
 				if let Ok(query_id) = self.swarm.behaviour_mut().kademlia.put_record(record, quorum)
 				{
 					self.query_id_receivers.insert(query_id, QueryResultSender::PutRecord(sender));
@@ -404,6 +907,40 @@ impl DasNetwork {
 					debug!("Failed to send result");
 				});
 			},
+			Command::GetArchiveProviders { sender } => {
+				let key = KademliaKey::new(&ARCHIVE_PROVIDER_KEY);
+				let query_id = self.swarm.behaviour_mut().kademlia.get_providers(key);
+				self.query_id_receivers
+					.insert(query_id, QueryResultSender::GetProviders(sender));
+			},
+			Command::GetClosestPeers { key, sender } => {
+				let query_id = self.swarm.behaviour_mut().kademlia.get_closest_peers(key.to_vec());
+				self.query_id_receivers
+					.insert(query_id, QueryResultSender::GetClosestPeers(sender));
+			},
+			Command::RequestSegments { peer_id, keys, sender } => {
+				let request_id = self
+					.swarm
+					.behaviour_mut()
+					.archive
+					.send_request(&peer_id, SegmentsRequest { keys });
+				self.pending_segment_requests.insert(request_id, sender);
+			},
+			Command::PublishSolution { data, sender } => {
+				let result = self
+					.swarm
+					.behaviour_mut()
+					.solutions
+					.publish(solution_topic(), data)
+					.map(|_| ())
+					.map_err(|e| anyhow::anyhow!("Failed to publish the solution: {:?}", e));
+				sender.send(result).unwrap_or_else(|_| {
+					debug!("Failed to send result");
+				});
+			},
+			Command::ReportSolution { message_id, source, verdict } => {
+				self.report_solution(&message_id, &source, verdict);
+			},
 		}
 	}
 }
@@ -415,6 +952,13 @@ pub(crate) struct Metrics {
 	requests_total: CounterVec<U64>,
 	requests_pending: Gauge<U64>,
 	dht_event_received: CounterVec<U64>,
+	inbound_requests: CounterVec<U64>,
+	archived_segments: Counter<U64>,
+	archived_bytes: Counter<U64>,
+	archive_served_bytes: CounterVec<U64>,
+	archive_throttled: CounterVec<U64>,
+	handshakes: CounterVec<U64>,
+	serving_receipts: CounterVec<U64>,
 }
 
 impl Metrics {
@@ -431,7 +975,7 @@ impl Metrics {
 			)?,
 			requests: register(
 				Counter::new(
-					"das_network_requests_total",
+					"das_network_requests",
 					"Total number of requests in the DAS network",
 				)?,
 				registry,
@@ -463,6 +1007,115 @@ impl Metrics {
 				)?,
 				registry,
 			)?,
+			inbound_requests: register(
+				CounterVec::new(
+					Opts::new(
+						"das_network_inbound_requests_total",
+						"Total number of inbound DHT requests served by the DAS network",
+					),
+					&["type"],
+				)?,
+				registry,
+			)?,
+			archived_segments: register(
+				Counter::new(
+					"das_network_archived_segments_total",
+					"Total number of segments written to the archive since the node started",
+				)?,
+				registry,
+			)?,
+			archived_bytes: register(
+				Counter::new(
+					"das_network_archived_bytes_total",
+					"Total size in bytes of the segments written to the archive since the node started",
+				)?,
+				registry,
+			)?,
+			archive_served_bytes: register(
+				CounterVec::new(
+					Opts::new(
+						"das_network_archive_served_bytes_total",
+						"Total size in bytes of the archive responses sent, by request class",
+					),
+					&["class"],
+				)?,
+				registry,
+			)?,
+			archive_throttled: register(
+				CounterVec::new(
+					Opts::new(
+						"das_network_archive_throttled_total",
+						"Total number of archive requests dropped over a bandwidth budget",
+					),
+					&["class", "budget"],
+				)?,
+				registry,
+			)?,
+			handshakes: register(
+				CounterVec::new(
+					Opts::new(
+						"das_network_handshakes_total",
+						"Total number of DAS handshakes with the peers, by outcome",
+					),
+					&["outcome"],
+				)?,
+				registry,
+			)?,
+			serving_receipts: register(
+				CounterVec::new(
+					Opts::new(
+						"das_network_serving_receipts_total",
+						"Total number of segment serving receipts exchanged with the peers, by outcome",
+					),
+					&["outcome"],
+				)?,
+				registry,
+			)?,
 		})
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use melo_das_db::mock_db::MockDb;
+	use std::net::TcpListener;
+
+	fn free_port() -> u16 {
+		TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+	}
+
+	fn config(listen_port: u16, bootstrap_nodes: Vec<String>) -> DasNetworkConfig {
+		DasNetworkConfig {
+			listen_addr: "127.0.0.1".to_string(),
+			listen_port,
+			bootstrap_nodes,
+			..Default::default()
+		}
+	}
+
+	#[tokio::test]
+	async fn archive_provider_is_stored_by_its_peers() {
+		let port = free_port();
+		let keypair = Keypair::generate_ed25519();
+		let peer_id = PeerId::from(keypair.public());
+		let (service, worker) = crate::default(Some(config(port, vec![])), Some(keypair)).unwrap();
+		tokio::spawn(worker.run());
+
+		let archive_keypair = Keypair::generate_ed25519();
+		let archive_id = PeerId::from(archive_keypair.public());
+		let bootstrap = format!("/ip4/127.0.0.1/tcp/{}/p2p/{}", port, peer_id);
+		let (_archive_service, archive) =
+			crate::default(Some(config(free_port(), vec![bootstrap])), Some(archive_keypair))
+				.unwrap();
+		let archive = tokio::spawn(archive.with_archive(Box::new(MockDb::new())).run());
+
+		// The archive node announces itself to its peer when it starts. Once it is gone, the peer
+		// can only answer from the provider record it stored.
+		tokio::time::sleep(Duration::from_secs(3)).await;
+		archive.abort();
+		let _ = archive.await;
+
+		assert_eq!(service.get_archive_providers().await.unwrap(), vec![archive_id]);
+	}
+}
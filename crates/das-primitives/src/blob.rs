@@ -32,6 +32,9 @@ use rust_kzg_blst::{
 
 use crate::config::BYTES_PER_FIELD_ELEMENT;
 
+/// The domain separator of the challenge combining the blobs of a batch proof.
+const BATCH_PROTOCOL_DOMAIN: [u8; 16] = *b"MELO_BLOB_BATCH_";
+
 /// A blob is a vector of field elements. It is the basic unit of data that is
 /// stored in the data availability layer.
 #[derive(Debug, Default, Clone, PartialEq, Eq, From, AsRef, AsMut, Deref, DerefMut)]
@@ -138,6 +141,28 @@ impl Blob {
 		kzg.commit(&poly)
 	}
 
+	/// Updates `commitment`, the commitment to `self`, to the commitment to `new`, committing only
+	/// to the suffix from the first field element where they differ.
+	///
+	/// # Arguments
+	///
+	/// * `kzg` - A reference to a `KZG` scheme.
+	/// * `commitment` - The commitment to `self`.
+	/// * `new` - The blob to commit to, usually `self` with more data appended.
+	///
+	/// # Returns
+	///
+	/// Returns a `Result` containing the `KZGCommitment` of `new` or an error message.
+	pub fn update_commitment(
+		&self,
+		kzg: &KZG,
+		commitment: &KZGCommitment,
+		new: &Blob,
+	) -> Result<KZGCommitment, String> {
+		let offset = self.0.iter().zip(new.0.iter()).take_while(|(old, new)| old == new).count();
+		kzg.update_commitment(commitment, offset, &self.0[offset..], &new.0[offset..])
+	}
+
 	/// Computes a KZG proof for the `Self` instance using the provided `KZG` scheme.
 	///
 	/// # Arguments
@@ -285,6 +310,83 @@ impl Blob {
 		))
 	}
 
+	/// Computes a single KZG proof for a batch of blobs, using the provided `KZG` scheme.
+	///
+	/// The blobs and their commitments are combined with the powers of a challenge derived from
+	/// the commitments, and the proof opens the combined polynomial at the challenge of the
+	/// combined blob. For a single blob, the proof is the one of [`Blob::kzg_proof`].
+	///
+	/// # Arguments
+	/// * `blobs` - A slice of `Blob`s.
+	/// * `commitments` - A slice of the `KZGCommitment`s of the blobs.
+	/// * `kzg` - A reference to a `KZG` scheme.
+	/// * `field_elements_per_blob` - The number of field elements per blob.
+	///
+	/// # Returns
+	/// Returns a `Result` containing the `KZGProof` or an error message.
+	pub fn compute_blobs_proof_batch(
+		blobs: &[Blob],
+		commitments: &[KZGCommitment],
+		kzg: &KZG,
+		field_elements_per_blob: usize,
+	) -> Result<KZGProof, String> {
+		check_field_elements_per_blob(field_elements_per_blob)?;
+		let bytes_per_blob: usize = BYTES_PER_FIELD_ELEMENT * field_elements_per_blob;
+
+		let (blob, commitment) = aggregate_blobs(blobs, commitments, field_elements_per_blob)?;
+		let x = compute_challenge(
+			&blob.to_fs_fr_vec(),
+			&commitment,
+			bytes_per_blob,
+			field_elements_per_blob,
+		);
+		kzg.compute_proof(&blob.to_poly(), &x)
+	}
+
+	/// Verifies a single KZG proof for a batch of blobs, computed by
+	/// [`Blob::compute_blobs_proof_batch`], using the provided `KZG` scheme.
+	///
+	/// Unlike [`Blob::verify_batch`], only one pairing check is needed whatever the number of
+	/// blobs.
+	///
+	/// # Arguments
+	/// * `blobs` - A slice of `Blob`s.
+	/// * `commitments` - A slice of the `KZGCommitment`s of the blobs.
+	/// * `proof` - A reference to the `KZGProof` of the batch.
+	/// * `kzg` - A reference to a `KZG` scheme.
+	/// * `field_elements_per_blob` - The number of field elements per blob.
+	///
+	/// # Returns
+	/// Returns a `Result` containing a boolean indicating whether the proof is valid or an error message.
+	pub fn verify_blobs_proof_batch(
+		blobs: &[Blob],
+		commitments: &[KZGCommitment],
+		proof: &KZGProof,
+		kzg: &KZG,
+		field_elements_per_blob: usize,
+	) -> Result<bool, String> {
+		if commitments.iter().any(|commitment| !commitment.0.is_valid()) {
+			return Err("Invalid commitment".to_string());
+		}
+
+		if !proof.0.is_valid() {
+			return Err("Invalid proof".to_string());
+		}
+
+		check_field_elements_per_blob(field_elements_per_blob)?;
+		let bytes_per_blob: usize = BYTES_PER_FIELD_ELEMENT * field_elements_per_blob;
+
+		let (blob, commitment) = aggregate_blobs(blobs, commitments, field_elements_per_blob)?;
+		let x = compute_challenge(
+			&blob.to_fs_fr_vec(),
+			&commitment,
+			bytes_per_blob,
+			field_elements_per_blob,
+		);
+		let y = blob.to_poly().eval(&BlsScalar(x));
+		kzg.check_proof_single(&commitment, proof, &x, &y)
+	}
+
 	/// Converts the `Self` instance to a `Polynomial`.
 	///
 	/// # Returns
@@ -315,6 +417,47 @@ impl Blob {
 	}
 }
 
+// Combine the blobs and their commitments with the powers of a challenge derived from the
+// commitments
+fn aggregate_blobs(
+	blobs: &[Blob],
+	commitments: &[KZGCommitment],
+	field_elements_per_blob: usize,
+) -> Result<(Blob, KZGCommitment), String> {
+	if blobs.is_empty() || blobs.len() != commitments.len() {
+		return Err(alloc::format!(
+			"Invalid input length. Expected {} commitments, got {}",
+			blobs.len(),
+			commitments.len()
+		));
+	}
+
+	if blobs.iter().any(|blob| blob.len() != field_elements_per_blob) {
+		return Err("Invalid blob length".to_string());
+	}
+
+	if blobs.len() == 1 {
+		return Ok((blobs[0].clone(), commitments[0]));
+	}
+
+	let mut bytes = Vec::with_capacity(16 + commitments.len() * 48);
+	bytes.extend_from_slice(&BATCH_PROTOCOL_DOMAIN);
+	for commitment in commitments {
+		bytes.extend_from_slice(&commitment.to_bytes());
+	}
+	let powers = BlsScalar::hash_to_scalar(&bytes).powers(blobs.len());
+
+	let mut elements = vec![FsFr::zero(); field_elements_per_blob];
+	for (blob, power) in blobs.iter().zip(powers.iter()) {
+		for (element, scalar) in elements.iter_mut().zip(blob.to_fs_fr_slice()) {
+			*element = element.add(&scalar.mul(&power.0));
+		}
+	}
+
+	let commitment = KZGCommitment::linear_combination(commitments, &powers);
+	Ok((Blob(BlsScalar::vec_from_repr(elements)), commitment))
+}
+
 // field_elements_per_blob should be a power of 2
 fn check_field_elements_per_blob(field_elements_per_blob: usize) -> Result<(), String> {
 	if !field_elements_per_blob.is_power_of_two() {
@@ -350,7 +493,7 @@ fn compute_challenges_and_evaluate_polynomial(
 // This is a copy from kzg-rust https://github.com/sifraitech/rust-kzg/blob/main/blst/src/eip_4844.rs#L337
 // Used to calculate the challenge value for the Blob, where we pass in the constant field_elements_per_blob
 // as a parameter for ease of use by the application layer
-fn compute_challenge(
+pub(crate) fn compute_challenge(
 	blob: &[FsFr],
 	commitment: &FsG1,
 	bytes_per_blob: usize,
@@ -0,0 +1,88 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Packing of application data into blobs.
+//!
+//! Each field element of a blob holds [`SCALAR_SAFE_BYTES`] bytes of data, so that any bytes are
+//! a valid scalar. Data longer than a blob is split over several blobs, and the last one is padded
+//! with zeros: the length of the data is needed to restore it exactly with [`blobs_to_bytes`].
+//! Clients and nodes both pack data with these functions, so that they commit to the same blobs.
+use crate::{blob::Blob, crypto::SCALAR_SAFE_BYTES};
+use alloc::{
+	format,
+	string::{String, ToString},
+	vec::Vec,
+};
+
+/// Returns the number of data bytes in a blob of `field_elements_per_blob` field elements.
+///
+/// # Errors
+///
+/// Returns an error if `field_elements_per_blob` is zero or not a power of two.
+pub fn bytes_per_blob(field_elements_per_blob: usize) -> Result<usize, String> {
+	if !field_elements_per_blob.is_power_of_two() {
+		return Err("field_elements_per_blob should be a power of 2; qed".to_string())
+	}
+	Ok(SCALAR_SAFE_BYTES * field_elements_per_blob)
+}
+
+/// Returns the number of blobs of `field_elements_per_blob` field elements holding `len` bytes.
+///
+/// # Errors
+///
+/// Returns an error if `field_elements_per_blob` is zero or not a power of two.
+pub fn blob_count(len: usize, field_elements_per_blob: usize) -> Result<usize, String> {
+	bytes_per_blob(field_elements_per_blob)
+		.map(|bytes_per_blob| Blob::blob_count(len, bytes_per_blob))
+}
+
+/// Packs `bytes` into blobs of `field_elements_per_blob` field elements, padding the last one
+/// with zeros.
+///
+/// # Errors
+///
+/// Returns an error if `bytes` is empty, or `field_elements_per_blob` is zero or not a power of
+/// two.
+pub fn bytes_to_blobs(bytes: &[u8], field_elements_per_blob: usize) -> Result<Vec<Blob>, String> {
+	if bytes.is_empty() {
+		return Err("bytes should not contain empty bytes; qed".to_string())
+	}
+	let bytes_per_blob = bytes_per_blob(field_elements_per_blob)?;
+	bytes
+		.chunks(bytes_per_blob)
+		.map(|chunk| Blob::try_from_bytes_pad(chunk, bytes_per_blob))
+		.collect()
+}
+
+/// Restores the `len` bytes packed into `blobs` by [`bytes_to_blobs`].
+///
+/// # Errors
+///
+/// Returns an error if the blobs hold fewer than `len` bytes, or more blobs than `len` bytes
+/// take.
+pub fn blobs_to_bytes(blobs: &[Blob], len: usize) -> Result<Vec<u8>, String> {
+	let mut bytes: Vec<u8> = blobs.iter().flat_map(|blob| blob.to_bytes()).collect();
+
+	// Only the last blob may be padded, and not entirely.
+	let last_blob_len = blobs.last().map(|blob| blob.len() * SCALAR_SAFE_BYTES).unwrap_or(1);
+	if bytes.len() < len || bytes.len() - len >= last_blob_len {
+		return Err(format!(
+			"Expected the blobs of {} bytes, got {} bytes of blobs",
+			len,
+			bytes.len()
+		))
+	}
+	bytes.truncate(len);
+	Ok(bytes)
+}
@@ -16,4 +16,68 @@ pub const BYTES_PER_FIELD_ELEMENT: usize = 32;
 pub const EMBEDDED_KZG_SETTINGS_BYTES: &[u8] = include_bytes!("../../../scripts/eth-public-parameters-4096.bin");
 
 pub const FIELD_ELEMENTS_PER_BLOB: usize = 2048;
-pub const BYTES_PER_BLOB: usize = FIELD_ELEMENTS_PER_BLOB * BYTES_PER_FIELD_ELEMENT;
\ No newline at end of file
+pub const BYTES_PER_BLOB: usize = FIELD_ELEMENTS_PER_BLOB * BYTES_PER_FIELD_ELEMENT;
+/// The number of bytes of application data packed into a blob, one field element per
+/// [`SCALAR_SAFE_BYTES`](crate::crypto::SCALAR_SAFE_BYTES) bytes.
+pub const DATA_BYTES_PER_BLOB: usize = FIELD_ELEMENTS_PER_BLOB * crate::crypto::SCALAR_SAFE_BYTES;
+
+/// A number of rows of the data matrix, one per blob.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Rows(pub u32);
+
+/// A number of columns of the data matrix, one per segment of a row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Cols(pub u32);
+
+/// A number of samples, that is of cells of the data matrix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct SampleCount(pub u32);
+
+/// The dimensions of the data matrix: each row is a blob, split into segments of
+/// `field_elements_per_segment` field elements, and extended to twice as many columns by erasure
+/// coding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatrixDimensions {
+	field_elements_per_blob: usize,
+	field_elements_per_segment: usize,
+}
+
+impl MatrixDimensions {
+	/// Creates the dimensions of blobs of `field_elements_per_blob` field elements split into
+	/// segments of `field_elements_per_segment` field elements.
+	pub const fn new(field_elements_per_blob: usize, field_elements_per_segment: usize) -> Self {
+		Self { field_elements_per_blob, field_elements_per_segment }
+	}
+
+	/// Returns the number of field elements per segment.
+	pub const fn field_elements_per_segment(&self) -> usize {
+		self.field_elements_per_segment
+	}
+
+	/// Checks that segments are a power of two of field elements splitting a blob evenly.
+	pub const fn is_valid(&self) -> bool {
+		self.field_elements_per_segment.is_power_of_two() &&
+			self.field_elements_per_segment <= self.field_elements_per_blob &&
+			self.field_elements_per_blob % self.field_elements_per_segment == 0
+	}
+
+	/// Returns the number of columns of a row before extension, `0` if the dimensions are
+	/// invalid.
+	pub const fn cols(&self) -> Cols {
+		if !self.is_valid() {
+			return Cols(0)
+		}
+		Cols((self.field_elements_per_blob / self.field_elements_per_segment) as u32)
+	}
+
+	/// Returns the number of columns of a row after extension.
+	pub const fn extended_cols(&self) -> Cols {
+		Cols(self.cols().0 * 2)
+	}
+
+	/// Returns the number of cells of the extended matrix of `rows` rows, that is the number of
+	/// distinct samples of a block.
+	pub const fn cells(&self, rows: Rows) -> SampleCount {
+		SampleCount(rows.0.saturating_mul(self.extended_cols().0))
+	}
+}
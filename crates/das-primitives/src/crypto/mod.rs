@@ -25,11 +25,11 @@ use core::{
 };
 use derive_more::{AsMut, AsRef, Deref, DerefMut, From, Into};
 use kzg::{
-	eip_4844::{BYTES_PER_G1, BYTES_PER_G2},
+	eip_4844::{hash, BYTES_PER_G1, BYTES_PER_G2},
 	FFTSettings, FK20MultiSettings, Fr, KZGSettings, G1, G2,
 };
 
-use rust_kzg_blst::{types::{
+use rust_kzg_blst::{eip_4844::hash_to_bls_field, kzg_proofs::g1_linear_combination, types::{
 	fft_settings::FsFFTSettings, fk20_multi_settings::FsFK20MultiSettings, fr::FsFr, g1::FsG1,
 	g2::FsG2, kzg_settings::FsKZGSettings,
 }, utils::reverse_bit_order};
@@ -363,6 +363,58 @@ impl From<[u8; SCALAR_SAFE_BYTES]> for BlsScalar {
 	}
 }
 
+impl BlsScalar {
+	/// Hashes `bytes` to a scalar, to derive Fiat-Shamir challenges.
+	pub fn hash_to_scalar(bytes: &[u8]) -> Self {
+		Self(hash_to_bls_field(&hash(bytes)))
+	}
+
+	/// Returns the `n` first powers of the scalar, starting from one.
+	pub fn powers(&self, n: usize) -> Vec<Self> {
+		let mut powers = Vec::with_capacity(n);
+		let mut power = FsFr::one();
+		for _ in 0..n {
+			powers.push(Self(power));
+			power = power.mul(&self.0);
+		}
+		powers
+	}
+
+	/// Returns the sum of `values` weighted by `scalars`. Values without a scalar are ignored.
+	pub fn linear_combination(values: &[Self], scalars: &[Self]) -> Self {
+		let sum = values
+			.iter()
+			.zip(scalars)
+			.fold(FsFr::zero(), |sum, (value, scalar)| sum.add(&value.0.mul(&scalar.0)));
+		Self(sum)
+	}
+}
+
+macro_rules! g1_linear_combination {
+	($name:ident) => {
+		impl $name {
+			/// Returns the sum of `points` weighted by `scalars`. Points without a scalar are
+			/// ignored.
+			pub fn linear_combination(points: &[Self], scalars: &[BlsScalar]) -> Self {
+				let len = points.len().min(scalars.len());
+				let mut out = FsG1::identity();
+				if len > 0 {
+					g1_linear_combination(
+						&mut out,
+						Self::slice_to_repr(&points[..len]),
+						BlsScalar::slice_to_repr(&scalars[..len]),
+						len,
+					);
+				}
+				Self(out)
+			}
+		}
+	};
+}
+
+g1_linear_combination!(KZGCommitment);
+g1_linear_combination!(KZGProof);
+
 /// Number of G1 powers stored in [`EMBEDDED_KZG_SETTINGS_BYTES`]
 pub const NUM_G1_POWERS: usize = 4_096;
 /// Number of G2 powers stored in [`EMBEDDED_KZG_SETTINGS_BYTES`]
@@ -541,6 +593,14 @@ impl KZG {
 		proof: &KZGProof,
 		chunk_size: usize,
 	) -> Result<bool, String> {
+		self.check_chunks(chunk_count, chunk_size)?;
+		if values.len() != chunk_size {
+			return Err(alloc::format!(
+				"Invalid chunk length. Expected {} got {}",
+				chunk_size,
+				values.len()
+			))
+		}
 		let mut ys = values.to_vec();
 		reverse_bit_order(&mut ys);
 		let pos = self.get_kzg_index(chunk_count, i, chunk_size);
@@ -594,6 +654,54 @@ impl KZG {
 		self.ks.commit_to_poly(&poly.0).map(KZGCommitment)
 	}
 
+	/// Updates the commitment of a polynomial whose coefficients from `offset` changed from `old`
+	/// to `new`, without committing to the whole polynomial again.
+	///
+	/// A blob holds the coefficients of its polynomial, so the commitments of its basis
+	/// polynomials are the powers of the secret in G1 of the settings, and the commitment moves by
+	/// the changes of the coefficients weighted by them. A sequencer appending data to a blob
+	/// during the block time pays for the appended elements only. The shorter of `old` and `new`
+	/// is padded with zeros, so appending to the zero padding of a blob passes an empty `old`.
+	///
+	/// # Arguments
+	///
+	/// * `commitment` - The commitment to the polynomial with the `old` coefficients.
+	/// * `offset` - The index of the first changed coefficient.
+	/// * `old` - The coefficients from `offset` before the change.
+	/// * `new` - The coefficients from `offset` after the change.
+	///
+	/// # Returns
+	///
+	/// The commitment to the polynomial with the `new` coefficients, or an error message if the
+	/// changed coefficients exceed the width of the settings.
+	pub fn update_commitment(
+		&self,
+		commitment: &KZGCommitment,
+		offset: usize,
+		old: &[BlsScalar],
+		new: &[BlsScalar],
+	) -> Result<KZGCommitment, String> {
+		let len = old.len().max(new.len());
+		let basis = offset
+			.checked_add(len)
+			.and_then(|end| self.ks.secret_g1.get(offset..end))
+			.ok_or_else(|| "Changed coefficients exceed the width of the settings".to_string())?;
+
+		let mut points = Vec::with_capacity(len + 1);
+		points.push(*commitment);
+		points.extend_from_slice(KZGCommitment::slice_from_repr(basis));
+
+		let mut scalars = Vec::with_capacity(len + 1);
+		scalars.push(BlsScalar(FsFr::one()));
+		scalars.extend((0..len).map(|i| {
+			let old = old.get(i).map_or(FsFr::zero(), |c| c.0);
+			let new = new.get(i).map_or(FsFr::zero(), |c| c.0);
+			BlsScalar(new.sub(&old))
+		}));
+
+		Ok(KZGCommitment::linear_combination(&points, &scalars))
+	}
+
 	/// Verify the given commitment, index, value, and proof.
 	///
 	/// # Arguments
@@ -644,6 +752,21 @@ impl KZG {
 		&self.ks.fs
 	}
 
+	/// Checks that `chunk_count` chunks of `chunk_size` field elements, extended twice, fit in the
+	/// domain of the settings, so that the index of any chunk is a root of unity of the settings.
+	///
+	/// Sizes read from the network are checked against it before indexing the roots of unity.
+	fn check_chunks(&self, chunk_count: usize, chunk_size: usize) -> Result<(), String> {
+		if !chunk_count.is_power_of_two() || !chunk_size.is_power_of_two() {
+			return Err("Chunk count and size must be powers of two".to_string())
+		}
+		let width = chunk_count.checked_mul(chunk_size).and_then(|n| n.checked_mul(2));
+		if width.map_or(true, |width| width > self.max_width()) {
+			return Err("Chunks exceed the width of the settings".to_string())
+		}
+		Ok(())
+	}
+
 	/// Reverse the bits of the given value up to the given length.
 	fn reverse_bits_limited(length: usize, value: usize) -> usize {
 		let unused_bits = length.leading_zeros();
@@ -0,0 +1,199 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compatibility with the blobs of EIP-4844.
+//!
+//! An EIP-4844 blob holds 4096 field elements of 32 bytes in big endian, which are the
+//! evaluations of its polynomial at the roots of unity in bit-reversed order. A Melodot blob
+//! instead holds the coefficients of its polynomial, packed from 31-byte chunks of the data.
+//!
+//! A rollup dual-posts its data by submitting the 131072 bytes of its EIP-4844 blob as the
+//! application data, which Melodot splits into its own blobs. [`Eip4844Blob`] converts between the
+//! two forms, computes the commitment and proof Ethereum expects, and checks that the commitments
+//! of both sides are of the same data.
+extern crate alloc;
+
+use alloc::{
+	format,
+	string::{String, ToString},
+	vec::Vec,
+};
+use kzg::{eip_4844::hash, FFTFr};
+use rust_kzg_blst::{types::fr::FsFr, utils::reverse_bit_order};
+
+use crate::{
+	blob::compute_challenge,
+	config::BYTES_PER_FIELD_ELEMENT,
+	crypto::{BlsScalar, KZGCommitment, KZGProof, ReprConvert, KZG, SCALAR_SAFE_BYTES},
+	Blob, Polynomial,
+};
+
+/// The number of field elements of an EIP-4844 blob.
+pub const EIP4844_FIELD_ELEMENTS_PER_BLOB: usize = 4096;
+/// The number of bytes of an EIP-4844 blob.
+pub const EIP4844_BYTES_PER_BLOB: usize = EIP4844_FIELD_ELEMENTS_PER_BLOB * BYTES_PER_FIELD_ELEMENT;
+/// The version byte of the versioned hash of a KZG commitment.
+pub const VERSIONED_HASH_VERSION_KZG: u8 = 0x01;
+
+/// A blob in the format of EIP-4844.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Eip4844Blob(Vec<BlsScalar>);
+
+impl Eip4844Blob {
+	/// Parses an EIP-4844 blob.
+	///
+	/// # Errors
+	///
+	/// Returns an error if `bytes` is not [`EIP4844_BYTES_PER_BLOB`] long, or if a field element is
+	/// not canonical.
+	pub fn try_from_bytes(bytes: &[u8]) -> Result<Self, String> {
+		if bytes.len() != EIP4844_BYTES_PER_BLOB {
+			return Err(format!(
+				"Invalid byte length. Expected {} got {}",
+				EIP4844_BYTES_PER_BLOB,
+				bytes.len()
+			))
+		}
+
+		bytes
+			.chunks_exact(BYTES_PER_FIELD_ELEMENT)
+			.map(|chunk| {
+				chunk
+					.try_into()
+					.map_err(|_| "Chunked into incorrect number of bytes".to_string())
+					.and_then(BlsScalar::try_from_bytes)
+			})
+			.collect::<Result<Vec<_>, _>>()
+			.map(Self)
+	}
+
+	/// Returns the bytes of the blob, as posted to Ethereum and submitted to Melodot.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		self.0.iter().flat_map(|scalar| scalar.to_bytes()).collect()
+	}
+
+	/// Returns the field elements of the blob.
+	pub fn field_elements(&self) -> &[BlsScalar] {
+		&self.0
+	}
+
+	/// Splits the bytes of the blob into Melodot blobs of `field_elements_per_blob` field elements,
+	/// the last one padded with zeros.
+	pub fn to_melodot_blobs(&self, field_elements_per_blob: usize) -> Result<Vec<Blob>, String> {
+		if !field_elements_per_blob.is_power_of_two() {
+			return Err("field_elements_per_blob must be powers of two".to_string())
+		}
+		let bytes_per_blob = SCALAR_SAFE_BYTES * field_elements_per_blob;
+		self.to_bytes()
+			.chunks(bytes_per_blob)
+			.map(|chunk| Blob::try_from_bytes_pad(chunk, bytes_per_blob))
+			.collect()
+	}
+
+	/// Rebuilds an EIP-4844 blob from the Melodot blobs of its bytes.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the blobs hold less than [`EIP4844_BYTES_PER_BLOB`] bytes, or if they do
+	/// not encode an EIP-4844 blob.
+	pub fn from_melodot_blobs(blobs: &[Blob]) -> Result<Self, String> {
+		let bytes = blobs.iter().flat_map(|blob| blob.to_bytes()).collect::<Vec<_>>();
+		if bytes.len() < EIP4844_BYTES_PER_BLOB {
+			return Err(format!(
+				"Blobs hold {} bytes, expected at least {}",
+				bytes.len(),
+				EIP4844_BYTES_PER_BLOB
+			))
+		}
+		Self::try_from_bytes(&bytes[..EIP4844_BYTES_PER_BLOB])
+	}
+
+	/// Interpolates the polynomial of the blob, in coefficient form.
+	pub fn to_poly(&self, kzg: &KZG) -> Result<Polynomial, String> {
+		let mut evaluations = BlsScalar::vec_to_repr(self.0.clone());
+		reverse_bit_order(&mut evaluations);
+		kzg.get_fs()
+			.fft_fr(&evaluations, true)
+			.map(|coeffs| Polynomial::from_coeffs(&coeffs))
+	}
+
+	/// Computes the KZG commitment of the blob, as defined by EIP-4844.
+	pub fn commitment(&self, kzg: &KZG) -> Result<KZGCommitment, String> {
+		kzg.commit(&self.to_poly(kzg)?)
+	}
+
+	/// Computes the KZG proof of the blob for `commitment`, as defined by EIP-4844.
+	pub fn compute_proof(&self, kzg: &KZG, commitment: &KZGCommitment) -> Result<KZGProof, String> {
+		let x = self.challenge(commitment);
+		kzg.compute_proof(&self.to_poly(kzg)?, &x)
+	}
+
+	/// Verifies the KZG proof of the blob for `commitment`, as defined by EIP-4844.
+	pub fn verify_proof(
+		&self,
+		kzg: &KZG,
+		commitment: &KZGCommitment,
+		proof: &KZGProof,
+	) -> Result<bool, String> {
+		let x = self.challenge(commitment);
+		let y = self.to_poly(kzg)?.eval(&BlsScalar(x));
+		kzg.check_proof_single(commitment, proof, &x, &y)
+	}
+
+	/// Checks that `blobs`, committed to by `commitments` on Melodot, hold the same data as the
+	/// EIP-4844 blob committed to by `eth_commitment` on Ethereum.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the numbers of blobs and commitments differ, or if the blobs do not
+	/// encode an EIP-4844 blob.
+	pub fn check_equivalence(
+		kzg: &KZG,
+		eth_commitment: &KZGCommitment,
+		blobs: &[Blob],
+		commitments: &[KZGCommitment],
+	) -> Result<bool, String> {
+		if blobs.len() != commitments.len() {
+			return Err(format!(
+				"Invalid input length. Expected {} got commitments: {}",
+				blobs.len(),
+				commitments.len()
+			))
+		}
+
+		for (blob, commitment) in blobs.iter().zip(commitments) {
+			if blob.commit(kzg)? != *commitment {
+				return Ok(false)
+			}
+		}
+
+		Ok(Self::from_melodot_blobs(blobs)?.commitment(kzg)? == *eth_commitment)
+	}
+
+	fn challenge(&self, commitment: &KZGCommitment) -> FsFr {
+		compute_challenge(
+			BlsScalar::slice_to_repr(&self.0),
+			&commitment.0,
+			EIP4844_BYTES_PER_BLOB,
+			EIP4844_FIELD_ELEMENTS_PER_BLOB,
+		)
+	}
+}
+
+/// Returns the versioned hash of `commitment`, by which the blob is referenced on Ethereum.
+pub fn versioned_hash(commitment: &KZGCommitment) -> [u8; 32] {
+	let mut versioned_hash = hash(&commitment.to_bytes());
+	versioned_hash[0] = VERSIONED_HASH_VERSION_KZG;
+	versioned_hash
+}
@@ -19,9 +19,13 @@ pub mod crypto;
 pub use crypto::*;
 
 pub mod blob;
+pub mod chunking;
 pub mod config;
+pub mod eip4844;
 pub mod polynomial;
 pub mod segment;
+#[cfg(feature = "testutils")]
+pub mod testutils;
 
 pub use polynomial::*;
 pub use segment::*;
@@ -0,0 +1,72 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Generators for property-based tests.
+//!
+//! The strategies below produce random blobs and random subsets of the segments of a row, so that
+//! the crates building on the primitives, such as the erasure coding or the recovery of the
+//! nodes, can check their code paths on arbitrary data. Enabled by the `testutils` feature.
+use crate::{blob::Blob, crypto::SCALAR_SAFE_BYTES, BlsScalar};
+use core::ops::RangeInclusive;
+use proptest::{collection, prelude::*, sample};
+
+/// Returns a strategy of non-empty data fitting in a blob of `field_elements_per_blob` field
+/// elements.
+pub fn blob_bytes(field_elements_per_blob: usize) -> impl Strategy<Value = Vec<u8>> {
+	collection::vec(any::<u8>(), 1..=SCALAR_SAFE_BYTES * field_elements_per_blob)
+}
+
+/// Returns a strategy of blobs of `field_elements_per_blob` field elements, padded from
+/// [`blob_bytes`].
+pub fn blob(field_elements_per_blob: usize) -> impl Strategy<Value = Blob> {
+	blob_bytes(field_elements_per_blob).prop_map(move |bytes| {
+		Blob::try_from_bytes_pad(&bytes, SCALAR_SAFE_BYTES * field_elements_per_blob)
+			.expect("The data fits in the blob; qed")
+	})
+}
+
+/// Returns a strategy of field elements.
+pub fn scalar() -> impl Strategy<Value = BlsScalar> {
+	any::<[u8; SCALAR_SAFE_BYTES]>().prop_map(BlsScalar::from)
+}
+
+/// Returns a strategy of the positions kept out of `len`, as a mask, keeping at least half of
+/// them, which is enough to recover an extended row.
+pub fn recoverable_positions(len: usize) -> impl Strategy<Value = Vec<bool>> {
+	kept_positions(len, (len + 1) / 2..=len)
+}
+
+/// Returns a strategy of the positions kept out of `len`, as a mask, keeping fewer than half of
+/// them.
+pub fn unrecoverable_positions(len: usize) -> impl Strategy<Value = Vec<bool>> {
+	kept_positions(len, 0..=(len + 1) / 2 - 1)
+}
+
+/// Returns a strategy of the positions kept out of `len`, as a mask, keeping a number of
+/// positions in `count`.
+pub fn kept_positions(
+	len: usize,
+	count: RangeInclusive<usize>,
+) -> impl Strategy<Value = Vec<bool>> {
+	sample::subsequence((0..len).collect::<Vec<_>>(), count).prop_map(move |kept| {
+		let mut mask = vec![false; len];
+		kept.into_iter().for_each(|i| mask[i] = true);
+		mask
+	})
+}
+
+/// Returns `items` with those not kept by the mask `kept` dropped.
+pub fn drop_unkept<T: Clone>(items: &[T], kept: &[bool]) -> Vec<Option<T>> {
+	items.iter().zip(kept).map(|(item, kept)| kept.then(|| item.clone())).collect()
+}
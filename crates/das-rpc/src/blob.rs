@@ -0,0 +1,121 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::Error;
+
+use futures::lock::Mutex;
+use jsonrpsee::{
+	core::{async_trait, RpcResult},
+	proc_macros::rpc,
+};
+use log::error;
+use melo_core_primitives::{config::MAX_BLOCK_ROWS, padding, KZGCommitment};
+use melo_das_db::traits::DasKv;
+use melo_daser::{read_app_data, DasNetworkOperations};
+use sp_core::Bytes;
+use std::sync::Arc;
+
+/// Defines the API for retrieving blobs from the DAS network.
+#[rpc(client, server, namespace = "das")]
+pub trait GetBlobApi {
+	/// Returns the data of an application submission.
+	///
+	/// The segments are read from the local database, or else fetched from the DAS network, and
+	/// verified against the given commitments. Missing segments are recovered where possible.
+	///
+	/// # Arguments
+	///
+	/// * `app_id` - The ID of the application.
+	/// * `nonce` - The nonce of the submission.
+	/// * `commitments` - The commitments of the submission, in order, at most the rows of a block.
+	/// * `padded` - Whether the data was framed by the padding scheme of `melo_core_primitives`.
+	///
+	/// # Returns
+	///
+	/// Returns the data padded to whole blobs, or exactly the framed data if `padded` is set, or
+	/// `None` if it is not available.
+	#[method(name = "getBlob")]
+	async fn get_blob(
+		&self,
+		app_id: u32,
+		nonce: u32,
+		commitments: Vec<KZGCommitment>,
+		padded: Option<bool>,
+	) -> RpcResult<Option<Bytes>>;
+}
+
+/// The blob retrieval API's implementation.
+pub struct GetBlob<D, DB> {
+	/// DAS DHT network service.
+	das_network: Arc<D>,
+	/// DAS database, holding the segments stored by the node.
+	database: Arc<Mutex<DB>>,
+}
+
+impl<D, DB> GetBlob<D, DB> {
+	/// Creates a new [`GetBlob`] instance.
+	pub fn new(das_network: Arc<D>, database: Arc<Mutex<DB>>) -> Self {
+		Self { das_network, database }
+	}
+}
+
+#[async_trait]
+impl<D, DB> GetBlobApiServer for GetBlob<D, DB>
+where
+	D: DasNetworkOperations + Sync + Send + 'static + Clone,
+	DB: DasKv + Send + Sync + 'static,
+{
+	async fn get_blob(
+		&self,
+		app_id: u32,
+		nonce: u32,
+		commitments: Vec<KZGCommitment>,
+		padded: Option<bool>,
+	) -> RpcResult<Option<Bytes>> {
+		if commitments.is_empty() {
+			return Err(Error::DataLength.into())
+		}
+		// Each commitment is looked up segment by segment in the DHT, and a submission cannot
+		// span more rows than the `MaxBlobNum` of the runtime.
+		if commitments.len() > MAX_BLOCK_ROWS as usize {
+			return Err(Error::TooManyCommitments {
+				requested: commitments.len(),
+				max: MAX_BLOCK_ROWS as usize,
+			}
+			.into())
+		}
+
+		let kzg = self.das_network.kzg();
+		let local =
+			read_app_data(&mut *self.database.lock().await, &kzg, app_id, nonce, &commitments);
+		let data = match local {
+			Some(data) => Some(data),
+			None =>
+				self.das_network
+					.fetch_app_data(app_id, nonce, &commitments)
+					.await
+					.map_err(|e| {
+						error!("❌ Failed to fetch data from DHT network: {:?}", e);
+						Error::FetchBlobFailed(e.into())
+					})?,
+		};
+
+		match data {
+			Some(data) if padded.unwrap_or_default() => padding::unpad(&data)
+				.map(|data| Some(Bytes::from(data.to_vec())))
+				.map_err(|e| Error::InvalidPadding(format!("{:?}", e)).into()),
+			data => Ok(data.map(Bytes::from)),
+		}
+	}
+}
@@ -0,0 +1,195 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Listing of the blobs of past blocks.
+//!
+//! `das_listBlobs` pages through the index of the blobs of the finalized blocks kept by the
+//! node, see `melo_daser::blob_index`, so that explorers do not have to walk the blocks through
+//! the chain API.
+use codec::Decode;
+use futures::lock::Mutex;
+use jsonrpsee::{
+	core::{async_trait, RpcResult},
+	proc_macros::rpc,
+};
+use melo_core_primitives::{inclusion::commitments_root, reliability::ReliabilityId};
+use melo_das_db::traits::DasKv;
+use melo_daser::{list_blobs, BlobCursor, IndexedBlob};
+use serde::{Deserialize, Serialize};
+use sp_core::H256;
+use std::sync::Arc;
+
+use crate::Error;
+
+/// The maximum number of blobs listed by a single call to `das_listBlobs`.
+pub const MAX_LISTED_BLOBS: u32 = 100;
+
+/// The blobs to list.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlobFilter {
+	/// The app whose blobs to list, all apps if not given.
+	#[serde(default)]
+	pub app_id: Option<u32>,
+	/// The first block to list the blobs of.
+	pub from_block: u32,
+	/// The last block to list the blobs of.
+	pub to_block: u32,
+	/// The maximum number of blobs to list, at most [`MAX_LISTED_BLOBS`].
+	pub limit: u32,
+	/// The cursor returned by the previous call, to list the next page.
+	#[serde(default)]
+	pub cursor: Option<String>,
+}
+
+/// The availability of a listed blob, as sampled by the node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BlobAvailability {
+	/// The samples show the data available.
+	Available,
+	/// The samples show the data unavailable.
+	Unavailable,
+	/// The data was not sampled, or its confidence was pruned.
+	Unknown,
+}
+
+/// A blob listed by `das_listBlobs`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListedBlob {
+	/// The number of the block including the blob.
+	pub block_number: u32,
+	/// The hash of the block including the blob.
+	pub block_hash: H256,
+	/// The hash of the sidecar metadata of the blob.
+	pub hash: H256,
+	/// The ID of the app.
+	pub app_id: u32,
+	/// The nonce of the submission.
+	pub nonce: u32,
+	/// The length of the data in bytes.
+	pub size: u32,
+	/// The first row of the blob in the block matrix.
+	pub start_row: u32,
+	/// The number of rows of the blob.
+	pub rows: u32,
+	/// The merkle root of the commitments of the rows of the blob.
+	pub commitments_root: H256,
+	/// The availability of the blob.
+	pub availability: BlobAvailability,
+}
+
+/// A page of blobs listed by `das_listBlobs`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlobPage {
+	/// The blobs, in the order of the blocks and of the rows.
+	pub blobs: Vec<ListedBlob>,
+	/// The cursor to pass to list the next page, or `None` if the range is exhausted.
+	pub next_cursor: Option<String>,
+}
+
+/// Defines the API listing the blobs of past blocks.
+#[rpc(client, server, namespace = "das")]
+pub trait BlobListApi {
+	/// Lists the blobs of the finalized blocks of a range, optionally of a single app.
+	///
+	/// Only the blocks finalized while the node runs are indexed. A page may hold fewer blobs
+	/// than `limit` while `nextCursor` is set, when many blocks without blobs are skipped.
+	#[method(name = "listBlobs")]
+	async fn list_blobs(&self, filter: BlobFilter) -> RpcResult<BlobPage>;
+}
+
+/// The blob listing API's implementation.
+pub struct BlobList<DB> {
+	/// DAS database, holding the index of the blobs.
+	database: Arc<Mutex<DB>>,
+}
+
+impl<DB> BlobList<DB> {
+	/// Creates a new [`BlobList`] instance.
+	pub fn new(database: Arc<Mutex<DB>>) -> Self {
+		Self { database }
+	}
+}
+
+/// Returns the availability of `blob` as sampled in `db`, from the confidence of its submission
+/// or else of its block.
+fn availability(db: &mut impl DasKv, blob: &IndexedBlob) -> BlobAvailability {
+	let confidence = ReliabilityId::app_confidence(blob.app_id, blob.nonce)
+		.get_confidence(db)
+		.or_else(|| ReliabilityId::block_confidence(&blob.block_hash).get_confidence(db));
+	match confidence {
+		Some(confidence) if confidence.is_availability() => BlobAvailability::Available,
+		Some(_) => BlobAvailability::Unavailable,
+		None => BlobAvailability::Unknown,
+	}
+}
+
+#[async_trait]
+impl<DB> BlobListApiServer for BlobList<DB>
+where
+	DB: DasKv + Send + Sync + 'static,
+{
+	async fn list_blobs(&self, filter: BlobFilter) -> RpcResult<BlobPage> {
+		if filter.from_block > filter.to_block {
+			return Err(Error::InvalidBlobFilter(format!(
+				"fromBlock {} is after toBlock {}",
+				filter.from_block, filter.to_block
+			))
+			.into())
+		}
+		if filter.limit == 0 || filter.limit > MAX_LISTED_BLOBS {
+			return Err(Error::InvalidBlobFilter(format!(
+				"limit must be between 1 and {}, got {}",
+				MAX_LISTED_BLOBS, filter.limit
+			))
+			.into())
+		}
+		let cursor = filter
+			.cursor
+			.as_deref()
+			.map(str::parse::<BlobCursor>)
+			.transpose()
+			.map_err(Error::InvalidBlobFilter)?;
+
+		let mut db = self.database.lock().await;
+		let (blobs, next_cursor) = list_blobs(
+			&mut *db,
+			filter.app_id,
+			filter.from_block..=filter.to_block,
+			filter.limit as usize,
+			cursor,
+		);
+
+		let blobs = blobs
+			.into_iter()
+			.map(|(block_number, blob)| ListedBlob {
+				block_number,
+				block_hash: H256::decode(&mut &blob.block_hash[..]).unwrap_or_default(),
+				hash: H256(blob.id),
+				app_id: blob.app_id,
+				nonce: blob.nonce,
+				size: blob.bytes_len,
+				start_row: blob.start_row,
+				rows: blob.commitments.len() as u32,
+				commitments_root: commitments_root(&blob.commitments),
+				availability: availability(&mut *db, &blob),
+			})
+			.collect();
+
+		Ok(BlobPage { blobs, next_cursor: next_cursor.map(|cursor| cursor.to_string()) })
+	}
+}
@@ -0,0 +1,308 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Mirroring of the sidecars submitted through the node to a data availability committee.
+//!
+//! While a chain has few farmers, the DHT may hold too few copies of the data for it to stay
+//! retrievable. A node configured with the endpoints of a committee sends each sidecar it
+//! publishes to the members as well, and reports the [`StorageReceipt`] each member signs once
+//! it verified and stored the data.
+//!
+//! The node signs a [`MirrorAuthorization`] of each sidecar with its key of type
+//! [`MIRROR_KEY_TYPE`], and a member only stores the sidecars authorized by the nodes it allows,
+//! up to a quota of bytes for each of them. The stored sidecars are pruned once the retention
+//! period is over, which releases their bytes from the quota of their node.
+
+use crate::Error;
+use codec::{Decode, Encode};
+use futures::{future::join_all, lock::Mutex};
+use jsonrpsee::{
+	core::{async_trait, RpcResult},
+	http_client::{HttpClient, HttpClientBuilder},
+	proc_macros::rpc,
+};
+use log::{debug, warn};
+use melo_core_primitives::{
+	receipt::{MirrorAuthorization, StorageReceipt, MIRROR_KEY_TYPE, STORAGE_RECEIPT_KEY_TYPE},
+	sidecar_key, SidecarMetadata,
+};
+use melo_das_db::traits::DasKv;
+use melo_daser::{
+	committee_usage, record_stored, SidecarVerifier, StoredSidecar, DEFAULT_COMMITTEE_QUOTA,
+};
+use sc_client_api::HeaderBackend;
+use sp_core::{crypto::KeyTypeId, sr25519, Bytes, H256, H512};
+use sp_keystore::KeystorePtr;
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+use std::{marker::PhantomData, sync::Arc, time::Duration};
+
+/// The default time a committee member has to acknowledge a sidecar.
+pub const DEFAULT_COMMITTEE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Defines the API of the members of a data availability committee.
+#[rpc(client, server, namespace = "dac")]
+pub trait CommitteeApi {
+	/// Stores a sidecar mirrored by a node.
+	///
+	/// # Arguments
+	/// * `metadata` - The SCALE encoded metadata of the sidecar.
+	/// * `data` - The data of the sidecar, compressed as set by the `compression` of the metadata.
+	/// * `authorization` - The request of the node to store the sidecar, signed with its key of
+	///   type `dacm`.
+	///
+	/// # Returns
+	/// The receipt of the member, signed with its first key of type `dacr`, once the data is
+	/// verified against the commitments of the metadata and stored, or if it was already stored.
+	#[method(name = "store")]
+	async fn store(
+		&self,
+		metadata: Bytes,
+		data: Bytes,
+		authorization: MirrorAuthorization,
+	) -> RpcResult<StorageReceipt>;
+}
+
+/// The member side of a data availability committee.
+pub struct CommitteeMember<Client, Block, DB> {
+	/// The client, whose last finalized block the stored sidecars are recorded at.
+	client: Arc<Client>,
+	/// DAS database, holding the sidecars stored for the committee.
+	database: Arc<Mutex<DB>>,
+	/// Verifier of the data of the sidecars.
+	verifier: Arc<SidecarVerifier>,
+	/// The keystore holding the key signing the receipts.
+	keystore: KeystorePtr,
+	/// The keys of the nodes allowed to mirror sidecars to the member.
+	nodes: Vec<sr25519::Public>,
+	/// The maximum number of bytes stored for each node.
+	quota: u64,
+	_marker: PhantomData<Block>,
+}
+
+impl<Client, Block, DB> CommitteeMember<Client, Block, DB> {
+	/// Creates a new [`CommitteeMember`] storing the sidecars mirrored by the nodes of keys
+	/// `nodes`, up to [`DEFAULT_COMMITTEE_QUOTA`] bytes for each, and signing its receipts with
+	/// the first key of type [`STORAGE_RECEIPT_KEY_TYPE`] in `keystore`.
+	pub fn new(
+		client: Arc<Client>,
+		database: Arc<Mutex<DB>>,
+		verifier: Arc<SidecarVerifier>,
+		keystore: KeystorePtr,
+		nodes: Vec<sr25519::Public>,
+	) -> Self {
+		Self {
+			client,
+			database,
+			verifier,
+			keystore,
+			nodes,
+			quota: DEFAULT_COMMITTEE_QUOTA,
+			_marker: PhantomData,
+		}
+	}
+
+	/// Sets the maximum number of bytes stored for each node.
+	pub fn with_quota(mut self, quota: u64) -> Self {
+		self.quota = quota;
+		self
+	}
+}
+
+#[async_trait]
+impl<Client, Block, DB> CommitteeApiServer for CommitteeMember<Client, Block, DB>
+where
+	Block: BlockT,
+	Client: HeaderBackend<Block> + Send + Sync + 'static,
+	DB: DasKv + Send + Sync + 'static,
+	NumberFor<Block>: Into<u32>,
+{
+	async fn store(
+		&self,
+		metadata: Bytes,
+		data: Bytes,
+		authorization: MirrorAuthorization,
+	) -> RpcResult<StorageReceipt> {
+		let metadata = SidecarMetadata::decode(&mut &metadata[..])
+			.map_err(|e| Error::DecodingTransactionMetadataFailed(Box::new(e)))?;
+		let id = metadata.id();
+		if authorization.data_hash != H256(id) {
+			return Err(
+				Error::UnauthorizedNode("Authorization of another sidecar".to_string()).into()
+			)
+		}
+		authorization
+			.verify(&self.nodes)
+			.map_err(|e| Error::UnauthorizedNode(format!("{:?}", e)))?;
+
+		if self.database.lock().await.contains(&sidecar_key(&id)) {
+			return sign_storage_receipt(&self.keystore, H256(id)).map_err(Into::into)
+		}
+
+		let bytes = metadata.decode_payload(&data).map_err(|_| Error::DataLength)?;
+		if !metadata.check() {
+			return Err(Error::DataLength.into())
+		}
+
+		match self.verifier.verify(&metadata, bytes).await {
+			Ok(true) => (),
+			Ok(false) =>
+				return Err(Error::DataVerificationFailed(
+					"Data does not match the commitments and proofs".to_string(),
+				)
+				.into()),
+			Err(e) => return Err(Error::DataVerificationFailed(e).into()),
+		}
+
+		let value = (&metadata, &data.0).encode();
+		let node = authorization.signer.0;
+		let mut db = self.database.lock().await;
+		if !db.contains(&sidecar_key(&id)) {
+			let used = committee_usage(&mut *db, &node);
+			if used.saturating_add(value.len() as u64) > self.quota {
+				return Err(Error::CommitteeQuotaExceeded {
+					used,
+					len: value.len() as u64,
+					quota: self.quota,
+				}
+				.into())
+			}
+			db.set(&sidecar_key(&id), &value);
+			let number = self.client.info().finalized_number.into();
+			record_stored(&mut *db, number, StoredSidecar { id, node, bytes: value.len() as u64 });
+			debug!("🗄️ Stored the sidecar {} of node {} for the committee", H256(id), H256(node));
+		}
+		drop(db);
+
+		sign_storage_receipt(&self.keystore, H256(id)).map_err(Into::into)
+	}
+}
+
+/// Returns the first key of type `key_type` in `keystore`, with its signature over the payload
+/// `payload` returns for it, `None` if there is no such key.
+fn sign_with_first_key(
+	keystore: &KeystorePtr,
+	key_type: KeyTypeId,
+	payload: impl FnOnce(sr25519::Public) -> [u8; 32],
+) -> Option<(sr25519::Public, H512)> {
+	let signer = *keystore.sr25519_public_keys(key_type).first()?;
+	let signature = keystore.sr25519_sign(key_type, &signer, &payload(signer)).ok().flatten()?;
+	Some((signer, signature.0.into()))
+}
+
+/// Signs the storage receipt of the sidecar `data_hash` with the first key of type
+/// [`STORAGE_RECEIPT_KEY_TYPE`] in `keystore`.
+fn sign_storage_receipt(keystore: &KeystorePtr, data_hash: H256) -> Result<StorageReceipt, Error> {
+	let (signer, signature) = sign_with_first_key(keystore, STORAGE_RECEIPT_KEY_TYPE, |signer| {
+		StorageReceipt::unsigned(data_hash, signer).payload()
+	})
+	.ok_or(Error::NoReceiptSigner)?;
+	Ok(StorageReceipt { signature, ..StorageReceipt::unsigned(data_hash, signer) })
+}
+
+/// Signs the request to store the sidecar `data_hash` with the first key of type
+/// [`MIRROR_KEY_TYPE`] in `keystore`, `None` if there is no such key.
+fn sign_mirror_authorization(
+	keystore: &KeystorePtr,
+	data_hash: H256,
+) -> Option<MirrorAuthorization> {
+	let (signer, signature) = sign_with_first_key(keystore, MIRROR_KEY_TYPE, |signer| {
+		MirrorAuthorization::unsigned(data_hash, signer).payload()
+	})?;
+	Some(MirrorAuthorization { signature, ..MirrorAuthorization::unsigned(data_hash, signer) })
+}
+
+/// The node side of a data availability committee, mirroring sidecars to its members.
+pub struct CommitteeMirror {
+	/// The endpoints of the members, with their clients.
+	members: Vec<(String, HttpClient)>,
+	/// The keystore holding the key signing the requests to the members.
+	keystore: KeystorePtr,
+}
+
+impl CommitteeMirror {
+	/// Creates a new [`CommitteeMirror`] to the HTTP JSON-RPC `endpoints` of the members, each
+	/// having `timeout` to acknowledge a sidecar, signing its requests with the first key of type
+	/// [`MIRROR_KEY_TYPE`] in `keystore`.
+	pub fn new(
+		endpoints: &[String],
+		timeout: Duration,
+		keystore: KeystorePtr,
+	) -> Result<Self, String> {
+		let members = endpoints
+			.iter()
+			.map(|endpoint| {
+				HttpClientBuilder::default()
+					.request_timeout(timeout)
+					.build(endpoint)
+					.map(|client| (endpoint.clone(), client))
+					.map_err(|e| format!("Invalid committee endpoint {}: {}", endpoint, e))
+			})
+			.collect::<Result<_, _>>()?;
+		Ok(Self { members, keystore })
+	}
+
+	/// Returns the number of members of the committee.
+	pub fn len(&self) -> usize {
+		self.members.len()
+	}
+
+	/// Returns whether the committee has no member.
+	pub fn is_empty(&self) -> bool {
+		self.members.is_empty()
+	}
+
+	/// Sends the sidecar of `metadata` and its `data` to all the members at once.
+	///
+	/// Returns the valid receipts of the members which stored it. The members which failed, or
+	/// returned a receipt not signed for this sidecar, are logged and left out. Nothing is sent
+	/// if the node has no key to sign its request.
+	pub async fn mirror(&self, metadata: &SidecarMetadata, data: &Bytes) -> Vec<StorageReceipt> {
+		let data_hash = H256(metadata.id());
+		let authorization = match sign_mirror_authorization(&self.keystore, data_hash) {
+			Some(authorization) => authorization,
+			None => {
+				warn!("⚠️ No `dacm` key to mirror the sidecar {} to the committee", data_hash);
+				return vec![]
+			},
+		};
+		let encoded: Bytes = metadata.encode().into();
+		let results =
+			join_all(self.members.iter().map(|(_, client)| {
+				client.store(encoded.clone(), data.clone(), authorization.clone())
+			}))
+			.await;
+
+		self.members
+			.iter()
+			.zip(results)
+			.filter_map(|((endpoint, _), result)| match result {
+				Ok(receipt) if receipt.data_hash != data_hash => {
+					warn!("⚠️ Committee member {} acknowledged another sidecar", endpoint);
+					None
+				},
+				Ok(receipt) => match receipt.verify() {
+					Ok(()) => Some(receipt),
+					Err(e) => {
+						warn!("⚠️ Invalid receipt of committee member {}: {:?}", endpoint, e);
+						None
+					},
+				},
+				Err(e) => {
+					warn!("⚠️ Committee member {} did not store {}: {}", endpoint, data_hash, e);
+					None
+				},
+			})
+			.collect()
+	}
+}
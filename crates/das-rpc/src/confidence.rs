@@ -16,17 +16,149 @@ use jsonrpsee::{
 	core::{async_trait, RpcResult},
 	proc_macros::rpc,
 };
-use melo_core_primitives::reliability::{Reliability, ReliabilityId};
+use melo_core_primitives::{
+	receipt::{AvailabilityReceipt, RECEIPT_KEY_TYPE},
+	reliability::{Reliability, ReliabilityId},
+};
 
+use codec::{Decode, Encode};
 use futures::lock::Mutex;
-use melo_daser::DasNetworkOperations;
-use sp_core::Bytes;
-use std::{marker::PhantomData, sync::Arc};
+use melo_core_primitives::reliability::Permill;
+use melo_daser::{
+	confidence_history, BackfillProgress, BackfillStatus, ConfidencePoint, DasNetworkOperations,
+	SamplingParams, SharedSamplingParams, WithholdingCollector,
+};
+use serde::{Deserialize, Serialize};
+use sp_core::{Bytes, H256};
+use sp_keystore::KeystorePtr;
+use std::{marker::PhantomData, sync::Arc, time::Duration};
 
 use melo_das_db::traits::DasKv;
 
+use crate::error::Error;
+
 pub use sc_rpc_api::DenyUnsafe;
 
+/// The progress of the backfill of the recent data by the node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackfillInfo {
+	/// The first block to backfill.
+	pub from: u32,
+	/// The last block to backfill.
+	pub to: u32,
+	/// The number of blocks processed.
+	pub processed: u32,
+	/// The number of blocks whose data could not be fetched.
+	pub failed: u32,
+	/// The number of segments stored.
+	pub segments: u64,
+	/// Whether the backfill started.
+	pub started: bool,
+	/// Whether the backfill is done.
+	pub done: bool,
+}
+
+impl From<BackfillStatus> for BackfillInfo {
+	fn from(status: BackfillStatus) -> Self {
+		Self {
+			from: status.from,
+			to: status.to,
+			processed: status.processed,
+			failed: status.failed,
+			segments: status.segments,
+			started: status.started,
+			done: status.done,
+		}
+	}
+}
+
+/// The confidence of a block at one of its samplings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfidencePointInfo {
+	/// The time of the sampling, in seconds since the Unix epoch.
+	pub at: u64,
+	/// The confidence of the block, in parts per million.
+	pub confidence: Option<u32>,
+	/// The number of samples fetched.
+	pub success: u32,
+	/// The number of samples.
+	pub samples: u32,
+}
+
+impl From<ConfidencePoint> for ConfidencePointInfo {
+	fn from(point: ConfidencePoint) -> Self {
+		Self {
+			at: point.at,
+			confidence: point.confidence,
+			success: point.success,
+			samples: point.samples,
+		}
+	}
+}
+
+/// The parameters the node samples blocks with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SamplingParamsInfo {
+	/// The number of segments sampled in each block.
+	pub samples_per_block: u32,
+	/// The number of consecutive samples of a block that have to be fetched for the block to be
+	/// available.
+	pub block_threshold: u32,
+	/// The share of the samples of an application that have to be fetched, in parts per million.
+	pub app_threshold: u32,
+	/// The base of the confidence of a block, in parts per million.
+	pub base_factor: u32,
+	/// The time in milliseconds after which the samples not fetched yet count as failed.
+	pub timeout_ms: u64,
+}
+
+impl From<SamplingParams> for SamplingParamsInfo {
+	fn from(params: SamplingParams) -> Self {
+		Self {
+			samples_per_block: params.samples_per_block,
+			block_threshold: params.block_threshold,
+			app_threshold: params.app_threshold.deconstruct(),
+			base_factor: params.base_factor.deconstruct(),
+			timeout_ms: params.timeout.as_millis() as u64,
+		}
+	}
+}
+
+impl From<SamplingParamsInfo> for SamplingParams {
+	fn from(info: SamplingParamsInfo) -> Self {
+		Self {
+			samples_per_block: info.samples_per_block,
+			block_threshold: info.block_threshold,
+			app_threshold: Permill::from_parts(info.app_threshold),
+			base_factor: Permill::from_parts(info.base_factor),
+			timeout: Duration::from_millis(info.timeout_ms),
+		}
+	}
+}
+
+/// Signs the availability receipt of the block `hash` from its `confidence`, with the first key
+/// of type [`RECEIPT_KEY_TYPE`] in `keystore`.
+pub(crate) fn sign_receipt(
+	keystore: &KeystorePtr,
+	hash: H256,
+	confidence: &Reliability,
+) -> Result<AvailabilityReceipt, Error> {
+	let signer = *keystore
+		.sr25519_public_keys(RECEIPT_KEY_TYPE)
+		.first()
+		.ok_or(Error::NoReceiptSigner)?;
+	let mut receipt = AvailabilityReceipt::unsigned(hash, confidence, signer);
+	receipt.signature = keystore
+		.sr25519_sign(RECEIPT_KEY_TYPE, &signer, &receipt.payload())
+		.ok()
+		.flatten()
+		.ok_or(Error::NoReceiptSigner)?;
+	Ok(receipt)
+}
+
 /// Defines the Das API's functionalities.
 #[rpc(client, server, namespace = "das")]
 pub trait ConfidenceApi<DB, Hash, DN> {
@@ -57,6 +189,45 @@ pub trait ConfidenceApi<DB, Hash, DN> {
 	#[method(name = "isAvailable")]
 	async fn is_available(&self, block_hash: Hash) -> RpcResult<Option<bool>>;
 
+	/// Returns the confidence of a block at each of its samplings, oldest first.
+	///
+	/// Finalized blocks of the retention window are sampled again from time to time, so the
+	/// confidence of a block decreases when its data stops being served. Returns an empty list if
+	/// the block was never sampled.
+	#[method(name = "confidenceHistory")]
+	async fn confidence_history(&self, block_hash: Hash) -> RpcResult<Vec<ConfidencePointInfo>>;
+
+	/// Returns the availability receipt of a block, SCALE encoded and signed by the node.
+	///
+	/// Returns `None` if the block was not sampled, and fails if the node has no key to sign
+	/// receipts with.
+	#[method(name = "availabilityReceipt")]
+	async fn availability_receipt(&self, block_hash: Hash) -> RpcResult<Option<Bytes>>;
+
+	/// Returns whether `receipt`, a SCALE encoded `AvailabilityReceipt`, is signed by its signer
+	/// and claims the confidence its positions show.
+	///
+	/// The signer is not checked against any trusted set, which is up to the consumer.
+	#[method(name = "verifyReceipt")]
+	async fn verify_receipt(&self, receipt: Bytes) -> RpcResult<bool>;
+
+	/// Returns the attestations collected that the block is withheld, each a SCALE encoded
+	/// `AvailabilityReceipt` showing the data unavailable.
+	///
+	/// Once they are enough, the attestations can be submitted with
+	/// `withholding.report_withholding` to report the author of the block.
+	#[method(name = "withholdingAttestations")]
+	async fn withholding_attestations(&self, block_hash: Hash) -> RpcResult<Vec<Bytes>>;
+
+	/// Adds `receipt`, a SCALE encoded `AvailabilityReceipt` of another node, to the attestations
+	/// that its block is withheld.
+	///
+	/// This is an unsafe method. Returns `false` if the receipt is not valid, its signer is not
+	/// one of the `Attesters` of `pallet-withholding`, it shows the data available, its signer
+	/// already attested the block or the block has enough attestations already.
+	#[method(name = "addWithholdingAttestation")]
+	async fn add_withholding_attestation(&self, receipt: Bytes) -> RpcResult<bool>;
+
 	/// Removes records from the local node.
 	///
 	/// # Arguments
@@ -71,12 +242,36 @@ pub trait ConfidenceApi<DB, Hash, DN> {
 
 	#[method(name = "last")]
 	async fn last(&self) -> RpcResult<Option<(u32, Bytes)>>;
+
+	/// Returns the progress of the backfill of the recent data.
+	///
+	/// The backfill only runs on the nodes started with `--das-backfill`, `started` stays `false`
+	/// on the other nodes.
+	#[method(name = "backfillProgress")]
+	async fn backfill_progress(&self) -> RpcResult<BackfillInfo>;
+
+	/// Returns the parameters the node samples blocks with.
+	#[method(name = "samplingParams")]
+	async fn sampling_params(&self) -> RpcResult<SamplingParamsInfo>;
+
+	/// Replaces the parameters the node samples blocks with, from the next sampling on.
+	///
+	/// This is an unsafe method. The parameters are rejected if the block threshold can not be
+	/// reached with the samples of a block, the base factor is one million or the timeout is
+	/// zero.
+	#[method(name = "setSamplingParams")]
+	async fn set_sampling_params(&self, params: SamplingParamsInfo) -> RpcResult<()>;
 }
 
 /// The Das API's implementation.
 pub struct Confidence<DB, Hash, DN> {
 	database: Arc<Mutex<DB>>,
 	das_network: Arc<DN>,
+	backfill: Arc<BackfillProgress>,
+	keystore: Option<KeystorePtr>,
+	sampling: SharedSamplingParams,
+	deny_unsafe: DenyUnsafe,
+	withholding: Arc<WithholdingCollector>,
 	_marker: PhantomData<Hash>,
 }
 
@@ -86,8 +281,48 @@ where
 	DB: DasKv + 'static,
 {
 	/// Creates a new [`Confidence`] instance.
-	pub fn new(database: &Arc<Mutex<DB>>, das_network: &Arc<DN>) -> Self {
-		Self { database: database.clone(), das_network: das_network.clone(), _marker: PhantomData }
+	pub fn new(
+		database: &Arc<Mutex<DB>>,
+		das_network: &Arc<DN>,
+		backfill: &Arc<BackfillProgress>,
+	) -> Self {
+		Self {
+			database: database.clone(),
+			das_network: das_network.clone(),
+			backfill: backfill.clone(),
+			keystore: None,
+			sampling: Default::default(),
+			deny_unsafe: DenyUnsafe::Yes,
+			withholding: Default::default(),
+			_marker: PhantomData,
+		}
+	}
+
+	/// Signs the availability receipts returned by `das_availabilityReceipt` with the first key
+	/// of type [`RECEIPT_KEY_TYPE`] in `keystore`.
+	pub fn with_keystore(mut self, keystore: KeystorePtr) -> Self {
+		self.keystore = Some(keystore);
+		self
+	}
+
+	/// Reports the confidence of the blocks with the parameters of `sampling`, which
+	/// `das_setSamplingParams` replaces unless `deny_unsafe` denies it, as it denies
+	/// `das_addWithholdingAttestation`.
+	pub fn with_sampling_params(
+		mut self,
+		sampling: SharedSamplingParams,
+		deny_unsafe: DenyUnsafe,
+	) -> Self {
+		self.sampling = sampling;
+		self.deny_unsafe = deny_unsafe;
+		self
+	}
+
+	/// Collects the withholding attestations in `withholding`, shared with the sampling of the
+	/// finalized blocks.
+	pub fn with_withholding(mut self, withholding: Arc<WithholdingCollector>) -> Self {
+		self.withholding = withholding;
+		self
 	}
 
 	/// Returns the confidence of a block.
@@ -112,12 +347,53 @@ where
 {
 	async fn block_confidence(&self, block_hash: Hash) -> RpcResult<Option<u32>> {
 		let confidence = self.confidence(block_hash).await;
-		Ok(confidence.and_then(|c| c.value()))
+		let base_factor = self.sampling.get().base_factor;
+		Ok(confidence.and_then(|c| c.value_with(base_factor)))
 	}
 
 	async fn is_available(&self, block_hash: Hash) -> RpcResult<Option<bool>> {
 		let confidence = self.confidence(block_hash).await;
-		Ok(Some(confidence.map_or(false, |c| c.is_availability())))
+		let thresholds = self.sampling.get().thresholds();
+		Ok(Some(confidence.map_or(false, |c| c.is_available_with(&thresholds))))
+	}
+
+	async fn confidence_history(&self, block_hash: Hash) -> RpcResult<Vec<ConfidencePointInfo>> {
+		let history = confidence_history(&mut *self.database.lock().await, block_hash.as_ref());
+		Ok(history.into_iter().map(Into::into).collect())
+	}
+
+	async fn availability_receipt(&self, block_hash: Hash) -> RpcResult<Option<Bytes>> {
+		let keystore = self.keystore.as_ref().ok_or(Error::NoReceiptSigner)?;
+		if block_hash.as_ref().len() != H256::len_bytes() {
+			return Ok(None)
+		}
+		let hash = H256::from_slice(block_hash.as_ref());
+
+		let confidence = match self.confidence(block_hash).await {
+			Some(confidence) => confidence,
+			None => return Ok(None),
+		};
+		let receipt = sign_receipt(keystore, hash, &confidence)?;
+		Ok(Some(receipt.encode().into()))
+	}
+
+	async fn verify_receipt(&self, receipt: Bytes) -> RpcResult<bool> {
+		let receipt = AvailabilityReceipt::decode(&mut &receipt[..]);
+		Ok(receipt.map_or(false, |receipt| receipt.verify().is_ok()))
+	}
+
+	async fn withholding_attestations(&self, block_hash: Hash) -> RpcResult<Vec<Bytes>> {
+		if block_hash.as_ref().len() != H256::len_bytes() {
+			return Ok(Vec::new())
+		}
+		let attestations = self.withholding.attestations(&H256::from_slice(block_hash.as_ref()));
+		Ok(attestations.iter().map(|receipt| receipt.encode().into()).collect())
+	}
+
+	async fn add_withholding_attestation(&self, receipt: Bytes) -> RpcResult<bool> {
+		self.deny_unsafe.check_if_safe()?;
+		let receipt = AvailabilityReceipt::decode(&mut &receipt[..]);
+		Ok(receipt.map_or(false, |receipt| self.withholding.add(receipt)))
 	}
 
 	async fn remove_records(&self, keys: Vec<Bytes>) -> RpcResult<()> {
@@ -129,4 +405,18 @@ where
 	async fn last(&self) -> RpcResult<Option<(u32, Bytes)>> {
 		self.get_last().await.map_or(Ok(None), |(hash, number)| Ok(Some((number, hash))))
 	}
+
+	async fn backfill_progress(&self) -> RpcResult<BackfillInfo> {
+		Ok(self.backfill.status().into())
+	}
+
+	async fn sampling_params(&self) -> RpcResult<SamplingParamsInfo> {
+		Ok(self.sampling.get().into())
+	}
+
+	async fn set_sampling_params(&self, params: SamplingParamsInfo) -> RpcResult<()> {
+		self.deny_unsafe.check_if_safe()?;
+		self.sampling.set(params.into()).map_err(Error::InvalidSamplingParams)?;
+		Ok(())
+	}
 }
@@ -0,0 +1,95 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deduplication of retried submissions.
+//!
+//! A client retrying `das_submitBlobTx` after a dropped connection can not tell whether its first
+//! attempt went through. Submissions made with a request ID are remembered for a window, and a
+//! retry with the same ID returns the status of the original submission instead of submitting
+//! again. Concurrent retries wait for the original submission to complete.
+use sp_core::hashing::blake2_256;
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+/// How long the submissions made with a request ID are remembered by default.
+pub const DEFAULT_DEDUP_WINDOW: Duration = Duration::from_secs(600);
+
+/// The maximum number of request IDs remembered at once, the oldest are forgotten first.
+pub const MAX_REQUEST_IDS: usize = 10_000;
+
+/// A submission made with a request ID.
+#[derive(Clone, Debug)]
+pub struct Submission<T> {
+	/// The hash of the extrinsic submitted.
+	pub extrinsic_hash: [u8; 32],
+	/// The outcome of the submission.
+	pub status: T,
+}
+
+/// The slot of a request ID, holding its submission once it completed.
+pub type Slot<T> = Arc<tokio::sync::Mutex<Option<Submission<T>>>>;
+
+/// The submissions made with a request ID within the window.
+pub struct SubmissionDedup<T> {
+	window: Duration,
+	slots: Mutex<HashMap<String, (Instant, Slot<T>)>>,
+}
+
+impl<T> Default for SubmissionDedup<T> {
+	fn default() -> Self {
+		Self::new(DEFAULT_DEDUP_WINDOW)
+	}
+}
+
+impl<T> SubmissionDedup<T> {
+	/// Creates a new [`SubmissionDedup`] remembering the submissions for `window`.
+	pub fn new(window: Duration) -> Self {
+		Self { window, slots: Default::default() }
+	}
+
+	/// Returns the slot of `request_id`, creating it if the ID is new or its window elapsed.
+	///
+	/// The caller locks the slot for the duration of the submission, so that retries wait for it.
+	pub fn slot(&self, request_id: &str) -> Slot<T> {
+		let mut slots = self.slots.lock().expect("Dedup lock is not poisoned; qed");
+		let now = Instant::now();
+		slots.retain(|_, (created, _)| now.duration_since(*created) < self.window);
+
+		if let Some((_, slot)) = slots.get(request_id) {
+			return slot.clone()
+		}
+
+		if slots.len() >= MAX_REQUEST_IDS {
+			let oldest = slots
+				.iter()
+				.min_by_key(|(_, (created, _))| *created)
+				.map(|(request_id, _)| request_id.clone());
+			if let Some(oldest) = oldest {
+				slots.remove(&oldest);
+			}
+		}
+
+		let slot = Slot::default();
+		slots.insert(request_id.to_string(), (now, slot.clone()));
+		slot
+	}
+}
+
+/// Returns the hash identifying `extrinsic` in a [`Submission`].
+pub fn extrinsic_hash(extrinsic: &[u8]) -> [u8; 32] {
+	blake2_256(extrinsic)
+}
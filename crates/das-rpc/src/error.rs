@@ -22,6 +22,7 @@ use jsonrpsee::{
     core::Error as JsonRpseeError,
     types::error::{CallError, ErrorObject},
 };
+use melo_core_primitives::error_code;
 
 /// DAS RPC errors.
 #[derive(Debug, thiserror::Error)]
@@ -44,45 +45,261 @@ pub enum Error {
     /// Failed to push transaction
     #[error("Failed to push transaction: {}", .0)]
     TransactionPushFailed(Box<dyn std::error::Error + Send + Sync>),
+    /// Failed to fetch blob
+    #[error("Failed to fetch blob: {}", .0)]
+    FetchBlobFailed(Box<dyn std::error::Error + Send + Sync>),
+    /// Data exceeds the maximum blob size of the runtime
+    #[error("Data of {} bytes exceeds the maximum of {} bytes", .len, .max)]
+    BlobTooLarge { len: u32, max: u32 },
+    /// Blobs exceed the quota of the app in the next block
+    #[error("App {} can submit {} more blobs to the next block, got {}", .app_id, .quota, .blobs)]
+    QuotaExceeded { app_id: u32, blobs: u32, quota: u32 },
+    /// Number of commitments or proofs does not match the data length
+    #[error("Expected {} commitments and as many proofs or a batch proof, got {} commitments and {} proofs", .expected, .commitments, .proofs)]
+    CommitmentCountMismatch { expected: usize, commitments: usize, proofs: usize },
+    /// Data does not match the commitments
+    #[error("Data verification failed: {}", .0)]
+    DataVerificationFailed(String),
+    /// Failed to publish blob
+    #[error("Failed to publish blob: {}", .0)]
+    PublishBlobFailed(Box<dyn std::error::Error + Send + Sync>),
+    /// Data exceeds the bytes left to the app in the next block
+    #[error("App {} can submit {} more bytes to the next block, got {}", .app_id, .quota, .len)]
+    BytesQuotaExceeded { app_id: u32, len: u32, quota: u32 },
+    /// The node has no key to sign availability receipts
+    #[error("The node does not sign availability receipts")]
+    NoReceiptSigner,
+    /// The sampling parameters are inconsistent
+    #[error("Invalid sampling parameters: {}", .0)]
+    InvalidSamplingParams(String),
+    /// The data is not framed by the padding scheme
+    #[error("Invalid padding: {}", .0)]
+    InvalidPadding(String),
+    /// The local data could not be pruned
+    #[error("Failed to prune the local data: {}", .0)]
+    PruneFailed(String),
+    /// The DHT network is degraded
+    #[error("DHT network degraded: {}", .0)]
+    DhtDegraded(String),
+    /// More positions are requested than allowed
+    #[error("{} positions requested, at most {} allowed", .requested, .max)]
+    TooManyPositions { requested: usize, max: usize },
+    /// The filter of a blob listing is invalid
+    #[error("Invalid blob filter: {}", .0)]
+    InvalidBlobFilter(String),
+    /// The request ID was already used for another extrinsic
+    #[error("Request ID {} was already used for another extrinsic", .0)]
+    RequestIdConflict(String),
+    /// The extrinsic is not signed
+    #[error("The extrinsic is not signed")]
+    UnsignedExtrinsic,
+    /// More commitments are given than the rows of a block
+    #[error("{} commitments given, at most {} allowed", .requested, .max)]
+    TooManyCommitments { requested: usize, max: usize },
+    /// The node mirroring a sidecar is not allowed by the committee member
+    #[error("Node not allowed to mirror sidecars: {}", .0)]
+    UnauthorizedNode(String),
+    /// The sidecar exceeds the bytes the committee member stores for the node
+    #[error("The node stores {} bytes out of {}, got {}", .used, .quota, .len)]
+    CommitteeQuotaExceeded { used: u64, len: u64, quota: u64 },
 }
 
-/// DAS error codes
-const BASE_ERROR: i32 = 10000;
+impl Error {
+    /// Returns the JSON-RPC error code of the error.
+    ///
+    /// | Code  | Error                                    |
+    /// |-------|------------------------------------------|
+    /// | 10001 | The extrinsic could not be decoded       |
+    /// | 10002 | The call could not be decoded            |
+    /// | 10003 | The runtime could not be queried         |
+    /// | 10004 | The extrinsic does not submit one blob   |
+    /// | 10005 | Data length does not match the metadata  |
+    /// | 10006 | The transaction pool failed              |
+    /// | 10007 | The data could not be fetched            |
+    /// | 10008 | The data exceeds the maximum blob size   |
+    /// | 10009 | The blobs exceed the quota of the app    |
+    /// | 10010 | Commitment or proof count mismatch       |
+    /// | 10011 | Data does not match its commitments      |
+    /// | 10012 | The data could not be put to the DHT     |
+    /// | 10013 | The data exceeds the bytes of the block  |
+    /// | 10014 | The node does not sign receipts          |
+    /// | 10015 | The sampling parameters are inconsistent |
+    /// | 10016 | The data is not framed by the padding    |
+    /// | 10017 | The local data could not be pruned       |
+    /// | 10018 | The DHT network is degraded              |
+    /// | 10019 | Too many positions requested at once     |
+    /// | 10020 | The filter of a blob listing is invalid  |
+    /// | 10021 | The request ID is used by another tx     |
+    /// | 10022 | The extrinsic is not signed              |
+    /// | 10023 | Too many commitments given at once       |
+    /// | 10024 | The node may not mirror sidecars         |
+    /// | 10025 | The node exceeds its committee quota     |
+    ///
+    /// The errors `10003`, `10006`, `10007`, `10012`, `10017` and `10018` may be transient, the others are
+    /// fatal for the given request. `10009` and `10013` only hold until the next block, `10025` until
+    /// sidecars of the node are pruned.
+    pub fn code(&self) -> i32 {
+        match self {
+            Error::DecodingExtrinsicFailed(_) => error_code::DECODING_EXTRINSIC_FAILED,
+            Error::DecodingTransactionMetadataFailed(_) =>
+                error_code::DECODING_TRANSACTION_METADATA_FAILED,
+            Error::FetchTransactionMetadataFailed(_) =>
+                error_code::FETCH_TRANSACTION_METADATA_FAILED,
+            Error::InvalidTransactionFormat => error_code::INVALID_TRANSACTION_FORMAT,
+            Error::DataLength => error_code::DATA_LENGTH,
+            Error::TransactionPushFailed(_) => error_code::TRANSACTION_PUSH_FAILED,
+            Error::FetchBlobFailed(_) => error_code::FETCH_BLOB_FAILED,
+            Error::BlobTooLarge { .. } => error_code::BLOB_TOO_LARGE,
+            Error::QuotaExceeded { .. } => error_code::QUOTA_EXCEEDED,
+            Error::CommitmentCountMismatch { .. } => error_code::COMMITMENT_COUNT_MISMATCH,
+            Error::DataVerificationFailed(_) => error_code::DATA_VERIFICATION_FAILED,
+            Error::PublishBlobFailed(_) => error_code::PUBLISH_BLOB_FAILED,
+            Error::BytesQuotaExceeded { .. } => error_code::BYTES_QUOTA_EXCEEDED,
+            Error::NoReceiptSigner => error_code::NO_RECEIPT_SIGNER,
+            Error::InvalidSamplingParams(_) => error_code::INVALID_SAMPLING_PARAMS,
+            Error::InvalidPadding(_) => error_code::INVALID_PADDING,
+            Error::PruneFailed(_) => error_code::PRUNE_FAILED,
+            Error::DhtDegraded(_) => error_code::DHT_DEGRADED,
+            Error::TooManyPositions { .. } => error_code::TOO_MANY_POSITIONS,
+            Error::InvalidBlobFilter(_) => error_code::INVALID_BLOB_FILTER,
+            Error::RequestIdConflict(_) => error_code::REQUEST_ID_CONFLICT,
+            Error::UnsignedExtrinsic => error_code::UNSIGNED_EXTRINSIC,
+            Error::TooManyCommitments { .. } => error_code::TOO_MANY_COMMITMENTS,
+            Error::UnauthorizedNode(_) => error_code::UNAUTHORIZED_NODE,
+            Error::CommitteeQuotaExceeded { .. } => error_code::COMMITTEE_QUOTA_EXCEEDED,
+        }
+    }
+}
 
 impl From<Error> for JsonRpseeError {
     fn from(e: Error) -> Self {
+        let code = e.code();
         match e {
             Error::DecodingExtrinsicFailed(e) => CallError::Custom(ErrorObject::owned(
-                BASE_ERROR + 1,
+                code,
                 "Decoding extrinsic failed",
                 Some(format!("{:?}", e)),
             )),
             Error::DecodingTransactionMetadataFailed(e) => CallError::Custom(ErrorObject::owned(
-                BASE_ERROR + 2,
+                code,
                 "Decoding transaction metadata failed",
                 Some(format!("{:?}", e)),
             )),
             Error::FetchTransactionMetadataFailed(e) => CallError::Custom(ErrorObject::owned(
-                BASE_ERROR + 3,
+                code,
                 "Failed to fetch transaction metadata details",
                 Some(format!("{:?}", e)),
             )),
             Error::InvalidTransactionFormat => CallError::Custom(ErrorObject::owned(
-                BASE_ERROR + 4,
+                code,
                 "Invalid transaction format",
                 None::<()>,
             )),
             Error::DataLength => CallError::Custom(ErrorObject::owned(
-                BASE_ERROR + 5,
+                code,
                 "Data/Commitments/Proofs length error",
                 None::<()>,
             )),
             Error::TransactionPushFailed(e) => CallError::Custom(ErrorObject::owned(
-                BASE_ERROR + 6,
+                code,
                 "Failed to push transaction",
                 Some(format!("{:?}", e)),
             )),
+            Error::FetchBlobFailed(e) => CallError::Custom(ErrorObject::owned(
+                code,
+                "Failed to fetch blob",
+                Some(format!("{:?}", e)),
+            )),
+            e @ Error::BlobTooLarge { .. } => CallError::Custom(ErrorObject::owned(
+                code,
+                "Blob too large",
+                Some(e.to_string()),
+            )),
+            e @ Error::QuotaExceeded { .. } => CallError::Custom(ErrorObject::owned(
+                code,
+                "App quota exceeded",
+                Some(e.to_string()),
+            )),
+            e @ Error::CommitmentCountMismatch { .. } => CallError::Custom(ErrorObject::owned(
+                code,
+                "Commitment count mismatch",
+                Some(e.to_string()),
+            )),
+            Error::DataVerificationFailed(e) => CallError::Custom(ErrorObject::owned(
+                code,
+                "Data verification failed",
+                Some(e),
+            )),
+            Error::PublishBlobFailed(e) => CallError::Custom(ErrorObject::owned(
+                code,
+                "Failed to publish blob",
+                Some(format!("{:?}", e)),
+            )),
+            e @ Error::BytesQuotaExceeded { .. } => CallError::Custom(ErrorObject::owned(
+                code,
+                "App bytes quota exceeded",
+                Some(e.to_string()),
+            )),
+            Error::NoReceiptSigner => CallError::Custom(ErrorObject::owned(
+                code,
+                "No receipt signer",
+                None::<()>,
+            )),
+            Error::InvalidSamplingParams(e) => CallError::Custom(ErrorObject::owned(
+                code,
+                "Invalid sampling parameters",
+                Some(e),
+            )),
+            Error::InvalidPadding(e) => CallError::Custom(ErrorObject::owned(
+                code,
+                "Invalid padding",
+                Some(e),
+            )),
+            Error::PruneFailed(e) => CallError::Custom(ErrorObject::owned(
+                code,
+                "Failed to prune the local data",
+                Some(e),
+            )),
+            Error::DhtDegraded(e) => CallError::Custom(ErrorObject::owned(
+                code,
+                "DHT network degraded",
+                Some(e),
+            )),
+            e @ Error::TooManyPositions { .. } => CallError::Custom(ErrorObject::owned(
+                code,
+                "Too many positions",
+                Some(e.to_string()),
+            )),
+            Error::InvalidBlobFilter(e) => CallError::Custom(ErrorObject::owned(
+                code,
+                "Invalid blob filter",
+                Some(e),
+            )),
+            e @ Error::RequestIdConflict(_) => CallError::Custom(ErrorObject::owned(
+                code,
+                "Request ID conflict",
+                Some(e.to_string()),
+            )),
+            Error::UnsignedExtrinsic => CallError::Custom(ErrorObject::owned(
+                code,
+                "Unsigned extrinsic",
+                None::<()>,
+            )),
+            e @ Error::TooManyCommitments { .. } => CallError::Custom(ErrorObject::owned(
+                code,
+                "Too many commitments",
+                Some(e.to_string()),
+            )),
+            Error::UnauthorizedNode(e) => CallError::Custom(ErrorObject::owned(
+                code,
+                "Unauthorized node",
+                Some(e),
+            )),
+            e @ Error::CommitteeQuotaExceeded { .. } => CallError::Custom(ErrorObject::owned(
+                code,
+                "Committee quota exceeded",
+                Some(e.to_string()),
+            )),
         }.into()
     }
 }
-
@@ -0,0 +1,154 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Push of the header extensions of the imported blocks.
+//!
+//! `das_subscribeHeaderExtensions` streams what a sampler needs to start sampling a block as soon
+//! as the node imports it, instead of polling the chain: the hash of the block, the root of its
+//! commitments, the dimensions of its matrix and the lookup of its apps.
+use futures::{future, FutureExt, StreamExt};
+use jsonrpsee::{proc_macros::rpc, types::SubscriptionResult, SubscriptionSink};
+use melo_core_primitives::{
+	config::{EXTENDED_SEGMENTS_PER_BLOB, SEGMENTS_PER_BLOB},
+	inclusion::commitments_root,
+	traits::HeaderWithCommitment,
+};
+use sc_client_api::BlockchainEvents;
+use sc_rpc::SubscriptionTaskExecutor;
+use serde::{Deserialize, Serialize};
+use sp_core::H256;
+use sp_runtime::traits::Block as BlockT;
+use std::{marker::PhantomData, sync::Arc};
+
+/// The dimensions of the matrix of a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DimensionsInfo {
+	/// The number of rows, one per blob, before the extension.
+	pub rows: u32,
+	/// The number of columns, one per segment, before the extension.
+	pub cols: u32,
+	/// The number of rows of the extended matrix.
+	pub extended_rows: u32,
+	/// The number of columns of the extended matrix.
+	pub extended_cols: u32,
+}
+
+/// The blobs of an app submission in a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppLookupInfo {
+	/// The ID of the app.
+	pub app_id: u32,
+	/// The nonce of the submission.
+	pub nonce: u32,
+	/// The number of blobs, that is of rows, of the submission.
+	pub count: u16,
+}
+
+/// The header extension of an imported block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeaderExtensionInfo<Hash> {
+	/// The hash of the block.
+	pub block_hash: Hash,
+	/// The number of the block.
+	pub number: u32,
+	/// The merkle root of the commitments of the rows of the block, as
+	/// `inclusion::commitments_root` computes it.
+	pub commitments_root: H256,
+	/// The dimensions of the matrix of the block.
+	pub dimensions: DimensionsInfo,
+	/// The lookup of the apps of the block, in the order of their rows.
+	pub app_lookup: Vec<AppLookupInfo>,
+}
+
+impl<Hash> HeaderExtensionInfo<Hash> {
+	/// Returns the header extension of the block `block_hash` of `header`, or `None` if its
+	/// commitments can not be decoded.
+	pub fn new<Header: HeaderWithCommitment>(block_hash: Hash, header: &Header) -> Option<Self>
+	where
+		Header::Number: Into<u32>,
+	{
+		let commitments = header.commitments()?;
+		let rows = commitments.len() as u32;
+		Some(Self {
+			block_hash,
+			number: (*header.number()).into(),
+			commitments_root: commitments_root(&commitments),
+			dimensions: DimensionsInfo {
+				rows,
+				cols: SEGMENTS_PER_BLOB as u32,
+				extended_rows: rows * 2,
+				extended_cols: EXTENDED_SEGMENTS_PER_BLOB as u32,
+			},
+			app_lookup: header
+				.extension()
+				.app_lookup
+				.iter()
+				.map(|lookup| AppLookupInfo {
+					app_id: lookup.app_id,
+					nonce: lookup.nonce,
+					count: lookup.count,
+				})
+				.collect(),
+		})
+	}
+}
+
+/// Defines the API pushing the header extensions of the imported blocks.
+#[rpc(client, server, namespace = "das")]
+pub trait HeaderExtensionsApi<Hash> {
+	/// Streams the header extension of each block as soon as the node imports it.
+	#[subscription(
+		name = "subscribeHeaderExtensions" => "headerExtension",
+		unsubscribe = "unsubscribeHeaderExtensions",
+		item = HeaderExtensionInfo<Hash>
+	)]
+	fn subscribe_header_extensions(&self);
+}
+
+/// The header extensions API's implementation.
+pub struct HeaderExtensions<C, Block> {
+	client: Arc<C>,
+	executor: SubscriptionTaskExecutor,
+	_marker: PhantomData<Block>,
+}
+
+impl<C, Block> HeaderExtensions<C, Block> {
+	/// Creates a new [`HeaderExtensions`] instance, driving the subscriptions on `executor`.
+	pub fn new(client: Arc<C>, executor: SubscriptionTaskExecutor) -> Self {
+		Self { client, executor, _marker: PhantomData }
+	}
+}
+
+impl<C, Block> HeaderExtensionsApiServer<Block::Hash> for HeaderExtensions<C, Block>
+where
+	Block: BlockT,
+	Block::Header: HeaderWithCommitment,
+	<Block::Header as HeaderWithCommitment>::Number: Into<u32>,
+	C: BlockchainEvents<Block> + Send + Sync + 'static,
+{
+	fn subscribe_header_extensions(&self, mut sink: SubscriptionSink) -> SubscriptionResult {
+		let stream = self.client.import_notification_stream().filter_map(|notification| {
+			future::ready(HeaderExtensionInfo::new(notification.hash, &notification.header))
+		});
+
+		let fut = async move {
+			sink.pipe_from_stream(stream).await;
+		};
+		self.executor.spawn("das-rpc-header-extensions", Some("rpc"), fut.boxed());
+		Ok(())
+	}
+}
@@ -12,12 +12,42 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod blob;
+mod blob_list;
+mod committee;
 mod confidence;
+mod dedup;
 mod error;
+mod header_extensions;
+mod light;
+mod metrics;
+mod node_info;
+mod prune;
 mod submit_blob;
 
-pub use confidence::{Confidence, ConfidenceApiServer};
-pub use submit_blob::{BlobTxSatus, SubmitBlob, SubmitBlobApiServer};
+pub use blob::{GetBlob, GetBlobApiServer};
+pub use blob_list::{
+	BlobAvailability, BlobFilter, BlobList, BlobListApiServer, BlobPage, ListedBlob,
+	MAX_LISTED_BLOBS,
+};
+pub use committee::{
+	CommitteeApiServer, CommitteeMember, CommitteeMirror, DEFAULT_COMMITTEE_TIMEOUT,
+};
+pub use confidence::{
+	BackfillInfo, Confidence, ConfidenceApiServer, ConfidencePointInfo, SamplingParamsInfo,
+};
+pub use dedup::{SubmissionDedup, DEFAULT_DEDUP_WINDOW, MAX_REQUEST_IDS};
+pub use header_extensions::{
+	AppLookupInfo, DimensionsInfo, HeaderExtensionInfo, HeaderExtensions, HeaderExtensionsApiServer,
+};
+pub use light::{Light, LightApiServer, LightCell, LightHeader, MAX_LIGHT_CELLS};
+pub use metrics::{SubmitMetrics, SubmitStage};
+pub use node_info::{DasNode, MatrixInfo, NodeCapabilities, NodeInfo, NodeInfoApiServer};
+pub use prune::{PruneApiServer, PruneInfo, PruneLocalData};
+pub use submit_blob::{
+	BlobTxError, BlobTxSatus, PoolRejection, PrepareBlobTxParams, PreparedBlobCall, PreparedBlobTx,
+	RawDataParams, SubmitBlob, SubmitBlobApiServer,
+};
 
 pub(crate) use error::Error;
 
@@ -0,0 +1,239 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Light client API.
+//!
+//! The `das_light` namespace serves what a light client needs to sample the blocks by itself,
+//! without the rest of the chain RPC: the header of a block with its extension, the commitments
+//! of its rows, the cells at the positions the client picked with their proofs, and the
+//! availability receipt of the node. Each response can be checked by the client: the header
+//! hashes to the block hash, the row commitments extend those of the header, the cells verify
+//! against the commitments of their rows, and the receipts are signed.
+use codec::Encode;
+use futures::lock::Mutex;
+use jsonrpsee::{
+	core::{async_trait, RpcResult},
+	proc_macros::rpc,
+};
+use melo_core_primitives::traits::HeaderWithCommitment;
+use melo_das_db::traits::DasKv;
+use melo_daser::{
+	sample_key_from_block, DasNetworkOperations, KZGCommitment, Position, ReliabilityId, Sample,
+	SampleId, EXTENDED_SEGMENTS_PER_BLOB,
+};
+use melo_erasure_coding::erasure_coding::extend_fs_g1;
+use serde::{Deserialize, Serialize};
+use sp_blockchain::HeaderBackend;
+use sp_core::{Bytes, H256};
+use sp_keystore::KeystorePtr;
+use sp_runtime::traits::Block as BlockT;
+use std::{marker::PhantomData, sync::Arc};
+
+use crate::{confidence::sign_receipt, Error};
+
+/// The maximum number of cells requested at once.
+pub const MAX_LIGHT_CELLS: usize = 64;
+
+/// The header of a block along with its extension.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LightHeader {
+	/// The SCALE encoded header, whose blake2-256 hash is the hash of the block.
+	pub header: Bytes,
+	/// The SCALE encoded `HeaderExtension` of the header, with the commitments of the rows and
+	/// the lookup of the apps.
+	pub extension: Bytes,
+}
+
+/// A cell of the extended matrix of a block, with its proof.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LightCell {
+	/// The column of the cell.
+	pub x: u32,
+	/// The row of the cell.
+	pub y: u32,
+	/// The SCALE encoded `SegmentData` of the cell, its field elements and their KZG proof.
+	pub data: Bytes,
+	/// The commitment of the row of the cell, which the proof verifies against.
+	pub commitment: KZGCommitment,
+}
+
+/// Defines the light client API.
+#[rpc(client, server, namespace = "das_light")]
+pub trait LightApi<Hash> {
+	/// Returns the header of a block and its extension, or `None` if the block is unknown.
+	#[method(name = "headerExtension")]
+	async fn header_extension(&self, block_hash: Hash) -> RpcResult<Option<LightHeader>>;
+
+	/// Returns the commitments of the rows of the extended matrix of a block, in order, or
+	/// `None` if the block is unknown.
+	///
+	/// The commitments are those of the header extended with `extend_fs_g1`, which the client can
+	/// compute again.
+	#[method(name = "rowCommitments")]
+	async fn row_commitments(&self, block_hash: Hash) -> RpcResult<Option<Vec<KZGCommitment>>>;
+
+	/// Returns the cells of a block at `positions`, given as `(x, y)` pairs of the extended
+	/// matrix, each `None` if it is out of the matrix or not available.
+	///
+	/// The cells are read from the local database, or else fetched from the DAS network, and are
+	/// only returned once they verify. At most [`MAX_LIGHT_CELLS`] positions are accepted.
+	#[method(name = "cells")]
+	async fn cells(
+		&self,
+		block_hash: Hash,
+		positions: Vec<(u32, u32)>,
+	) -> RpcResult<Vec<Option<LightCell>>>;
+
+	/// Returns the availability receipt of a block, SCALE encoded and signed by the node, as
+	/// `das_availabilityReceipt` does.
+	#[method(name = "availabilityReceipt")]
+	async fn availability_receipt(&self, block_hash: Hash) -> RpcResult<Option<Bytes>>;
+}
+
+/// The light client API's implementation.
+pub struct Light<C, Block, DB, DN> {
+	client: Arc<C>,
+	database: Arc<Mutex<DB>>,
+	das_network: Arc<DN>,
+	keystore: Option<KeystorePtr>,
+	_marker: PhantomData<Block>,
+}
+
+impl<C, Block, DB, DN> Light<C, Block, DB, DN>
+where
+	Block: BlockT,
+	Block::Header: HeaderWithCommitment,
+	C: HeaderBackend<Block>,
+	DN: DasNetworkOperations + Sync,
+{
+	/// Creates a new [`Light`] instance, serving the cells stored in `database` or found on
+	/// `das_network`.
+	pub fn new(client: Arc<C>, database: Arc<Mutex<DB>>, das_network: Arc<DN>) -> Self {
+		Self { client, database, das_network, keystore: None, _marker: PhantomData }
+	}
+
+	/// Signs the availability receipts with the first key of type `RECEIPT_KEY_TYPE` in
+	/// `keystore`.
+	pub fn with_keystore(mut self, keystore: KeystorePtr) -> Self {
+		self.keystore = Some(keystore);
+		self
+	}
+
+	fn header(&self, block_hash: Block::Hash) -> RpcResult<Option<Block::Header>> {
+		self.client
+			.header(block_hash)
+			.map_err(|e| Error::FetchTransactionMetadataFailed(Box::new(e)).into())
+	}
+
+	/// Returns the commitments of the rows of the extended matrix of the block of `header`.
+	fn extended_commitments(&self, header: &Block::Header) -> RpcResult<Vec<KZGCommitment>> {
+		let commitments = header.commitments().ok_or(Error::DataLength)?;
+		if commitments.is_empty() {
+			return Ok(vec![])
+		}
+		extend_fs_g1(self.das_network.kzg().get_fs(), &commitments)
+			.map_err(|e| Error::DataVerificationFailed(e).into())
+	}
+}
+
+#[async_trait]
+impl<C, Block, DB, DN> LightApiServer<Block::Hash> for Light<C, Block, DB, DN>
+where
+	Block: BlockT,
+	Block::Header: HeaderWithCommitment,
+	C: HeaderBackend<Block> + Send + Sync + 'static,
+	DB: DasKv + Send + Sync + 'static,
+	DN: DasNetworkOperations + Sync + Send + 'static,
+{
+	async fn header_extension(&self, block_hash: Block::Hash) -> RpcResult<Option<LightHeader>> {
+		Ok(self.header(block_hash)?.map(|header| LightHeader {
+			header: header.encode().into(),
+			extension: header.extension().encode().into(),
+		}))
+	}
+
+	async fn row_commitments(
+		&self,
+		block_hash: Block::Hash,
+	) -> RpcResult<Option<Vec<KZGCommitment>>> {
+		match self.header(block_hash)? {
+			Some(header) => self.extended_commitments(&header).map(Some),
+			None => Ok(None),
+		}
+	}
+
+	async fn cells(
+		&self,
+		block_hash: Block::Hash,
+		positions: Vec<(u32, u32)>,
+	) -> RpcResult<Vec<Option<LightCell>>> {
+		if positions.len() > MAX_LIGHT_CELLS {
+			return Err(
+				Error::TooManyPositions { requested: positions.len(), max: MAX_LIGHT_CELLS }.into()
+			)
+		}
+		let commitments = match self.header(block_hash)? {
+			Some(header) => self.extended_commitments(&header)?,
+			None => return Ok(vec![None; positions.len()]),
+		};
+
+		let mut cells = Vec::with_capacity(positions.len());
+		for (x, y) in positions {
+			let commitment = match commitments.get(y as usize) {
+				Some(commitment) if (x as usize) < EXTENDED_SEGMENTS_PER_BLOB => *commitment,
+				_ => {
+					cells.push(None);
+					continue
+				},
+			};
+			let position = Position { x, y };
+			let key = sample_key_from_block(block_hash.as_ref(), &position);
+
+			let local = self.database.lock().await.get(&key);
+			let data = match local
+				.and_then(|value| self.das_network.verify_values(&[value], &commitment, &position))
+			{
+				Some(segment) => Some(segment.content),
+				None => {
+					let sample = Sample {
+						id: SampleId::block_sample(block_hash.as_ref(), &position),
+						position: position.clone(),
+						is_availability: false,
+					};
+					self.das_network.fetch_sample(&sample, &commitment).await
+				},
+			};
+			cells.push(data.map(|data| LightCell { x, y, data: data.encode().into(), commitment }));
+		}
+		Ok(cells)
+	}
+
+	async fn availability_receipt(&self, block_hash: Block::Hash) -> RpcResult<Option<Bytes>> {
+		let keystore = self.keystore.as_ref().ok_or(Error::NoReceiptSigner)?;
+		if block_hash.as_ref().len() != H256::len_bytes() {
+			return Ok(None)
+		}
+		let hash = H256::from_slice(block_hash.as_ref());
+
+		let id = ReliabilityId::block_confidence(block_hash.as_ref());
+		let confidence = match id.get_confidence(&mut *self.database.lock().await) {
+			Some(confidence) => confidence,
+			None => return Ok(None),
+		};
+		let receipt = sign_receipt(keystore, hash, &confidence)?;
+		Ok(Some(receipt.encode().into()))
+	}
+}
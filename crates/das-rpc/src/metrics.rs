@@ -0,0 +1,115 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Metrics of the submission of blob transactions.
+//!
+//! A submission goes through stages: the extrinsic is decoded, the blob and the limits of the
+//! runtime are fetched, the data is verified, put to the DHT network and the transaction is
+//! submitted to the pool. Each stage runs in a tracing span, and its latency is recorded by
+//! outcome, so that operators can see where submissions stall.
+use prometheus_endpoint::{
+	exponential_buckets, register, HistogramOpts, HistogramVec, PrometheusError, Registry,
+};
+use std::{future::Future, time::Instant};
+use tracing::Instrument;
+
+const LOG_TARGET: &str = "das-rpc";
+
+/// A stage of the submission of a blob transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubmitStage {
+	/// Decoding of the extrinsic.
+	Decode,
+	/// Fetching of the blob of the call and of the limits of the runtime.
+	Params,
+	/// Verification of the data against its metadata.
+	Verify,
+	/// Submission of the transaction to the pool.
+	Pool,
+	/// Putting of the data to the DHT network.
+	DhtPut,
+}
+
+impl SubmitStage {
+	/// Returns the label of the stage in the metrics and spans.
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			SubmitStage::Decode => "decode",
+			SubmitStage::Params => "params",
+			SubmitStage::Verify => "verify",
+			SubmitStage::Pool => "pool",
+			SubmitStage::DhtPut => "dht_put",
+		}
+	}
+}
+
+/// Latency of the stages of the submissions of blob transactions.
+#[derive(Clone)]
+pub struct SubmitMetrics {
+	stage_duration: HistogramVec,
+}
+
+impl SubmitMetrics {
+	/// Registers the metrics to `registry`.
+	pub fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			stage_duration: register(
+				HistogramVec::new(
+					HistogramOpts::new(
+						"das_rpc_submit_blob_stage_seconds",
+						"Time taken by each stage of the submission of blob transactions",
+					)
+					.buckets(exponential_buckets(0.001, 2.0, 16)?),
+					&["stage", "outcome"],
+				)?,
+				registry,
+			)?,
+		})
+	}
+
+	/// Records that `stage` took `seconds`, succeeding or not.
+	pub fn observe(&self, stage: SubmitStage, ok: bool, seconds: f64) {
+		self.stage_duration
+			.with_label_values(&[stage.as_str(), if ok { "ok" } else { "error" }])
+			.observe(seconds);
+	}
+}
+
+/// Runs `stage` in its span, and records its latency and outcome to `metrics`.
+pub(crate) async fn timed<T, E>(
+	metrics: Option<&SubmitMetrics>,
+	stage: SubmitStage,
+	future: impl Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+	let started = Instant::now();
+	let result = future
+		.instrument(
+			tracing::debug_span!(target: LOG_TARGET, "submit_stage", stage = stage.as_str()),
+		)
+		.await;
+	let elapsed = started.elapsed();
+
+	tracing::debug!(
+		target: LOG_TARGET,
+		stage = stage.as_str(),
+		ok = result.is_ok(),
+		elapsed_ms = elapsed.as_millis() as u64,
+		"Blob submission stage done",
+	);
+	if let Some(metrics) = metrics {
+		metrics.observe(stage, result.is_ok(), elapsed.as_secs_f64());
+	}
+
+	result
+}
@@ -0,0 +1,160 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use jsonrpsee::{
+	core::{async_trait, RpcResult},
+	proc_macros::rpc,
+};
+use melo_core_primitives::{
+	config::{DAS_NETWORK_VERSION, FIELD_ELEMENTS_PER_BLOB, FIELD_ELEMENTS_PER_SEGMENT, MATRIX},
+	traits::DasParamsApi,
+};
+use melo_das_network::{
+	ARCHIVE_PROTOCOL, ARCHIVE_PROTOCOL_V2, HANDSHAKE_PROTOCOL, SERVING_PROTOCOL, SOLUTION_TOPIC,
+};
+use melo_daser::SharedSamplingParams;
+use serde::{Deserialize, Serialize};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::Block as BlockT;
+use std::{marker::PhantomData, sync::Arc};
+
+use crate::{Error, SamplingParamsInfo};
+
+/// The services of a node beyond sampling and serving the segments it stores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeCapabilities {
+	/// Whether the node keeps every segment and serves them over the archive protocols.
+	pub archive: bool,
+	/// Whether the node validates and relays the solutions of the farmers.
+	pub relay_solutions: bool,
+	/// Whether the node backfills the data of the recent blocks.
+	pub backfill: bool,
+}
+
+/// The dimensions of the data matrix of a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatrixInfo {
+	/// The number of field elements of a blob, that is of a row.
+	pub field_elements_per_blob: u32,
+	/// The number of field elements of a segment.
+	pub field_elements_per_segment: u32,
+	/// The number of columns, one per segment, of a row before its extension.
+	pub cols: u32,
+	/// The number of columns of an extended row.
+	pub extended_cols: u32,
+	/// The maximum number of rows, one per blob, of a block.
+	pub max_rows: u32,
+}
+
+/// The protocols and settings of the data availability of a node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeInfo {
+	/// The version of the DAS network protocol.
+	pub network_version: String,
+	/// The DAS network protocols the node speaks, newest first.
+	pub protocols: Vec<String>,
+	/// The dimensions of the data matrix.
+	pub matrix: MatrixInfo,
+	/// The maximum length in bytes of the data of a submission.
+	pub max_blob_bytes: u32,
+	/// The maximum total length in bytes of the data submitted at a block.
+	pub max_block_bytes: u32,
+	/// The number of blocks the data of a block is kept available for.
+	pub retention_period: u32,
+	/// The parameters the node samples blocks with.
+	pub sampling: SamplingParamsInfo,
+	/// The services of the node.
+	pub capabilities: NodeCapabilities,
+}
+
+/// Defines the API describing the data availability of a node.
+#[rpc(client, server, namespace = "das")]
+pub trait NodeInfoApi {
+	/// Returns the protocols the node speaks and the settings of its data availability.
+	///
+	/// The matrix dimensions, blob size and retention window are read from the parameters in use
+	/// at the best block, and the sampling parameters are the ones in use by the node, so that
+	/// clients can configure themselves for the node they connect to.
+	#[method(name = "nodeInfo")]
+	async fn node_info(&self) -> RpcResult<NodeInfo>;
+}
+
+/// The node information API's implementation.
+pub struct DasNode<C, Block> {
+	client: Arc<C>,
+	sampling: SharedSamplingParams,
+	capabilities: NodeCapabilities,
+	_marker: PhantomData<Block>,
+}
+
+impl<C, Block> DasNode<C, Block> {
+	/// Creates a new [`DasNode`] instance, reporting the sampling parameters of `sampling`.
+	pub fn new(client: Arc<C>, sampling: SharedSamplingParams) -> Self {
+		Self { client, sampling, capabilities: Default::default(), _marker: PhantomData }
+	}
+
+	/// Reports the services of the node as `capabilities`.
+	pub fn with_capabilities(mut self, capabilities: NodeCapabilities) -> Self {
+		self.capabilities = capabilities;
+		self
+	}
+}
+
+#[async_trait]
+impl<C, Block> NodeInfoApiServer for DasNode<C, Block>
+where
+	Block: BlockT,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: DasParamsApi<Block>,
+{
+	async fn node_info(&self) -> RpcResult<NodeInfo> {
+		let at = self.client.info().best_hash;
+		let params = self
+			.client
+			.runtime_api()
+			.das_params(at)
+			.map_err(|e| Error::FetchTransactionMetadataFailed(Box::new(e)))?;
+
+		let mut protocols = vec![
+			String::from_utf8_lossy(HANDSHAKE_PROTOCOL).into_owned(),
+			String::from_utf8_lossy(ARCHIVE_PROTOCOL_V2).into_owned(),
+			String::from_utf8_lossy(ARCHIVE_PROTOCOL).into_owned(),
+			String::from_utf8_lossy(SERVING_PROTOCOL).into_owned(),
+		];
+		if self.capabilities.relay_solutions {
+			protocols.push(SOLUTION_TOPIC.to_string());
+		}
+
+		Ok(NodeInfo {
+			network_version: format!("/melodot-das/{}", DAS_NETWORK_VERSION),
+			protocols,
+			matrix: MatrixInfo {
+				field_elements_per_blob: FIELD_ELEMENTS_PER_BLOB as u32,
+				field_elements_per_segment: FIELD_ELEMENTS_PER_SEGMENT as u32,
+				cols: MATRIX.cols().0,
+				extended_cols: MATRIX.extended_cols().0,
+				max_rows: params.max_block_rows,
+			},
+			max_blob_bytes: params.max_blob_bytes,
+			max_block_bytes: params.max_block_bytes,
+			retention_period: params.retention_period,
+			sampling: self.sampling.get().into(),
+			capabilities: self.capabilities,
+		})
+	}
+}
@@ -0,0 +1,100 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use jsonrpsee::{
+	core::{async_trait, RpcResult},
+	proc_macros::rpc,
+};
+use melo_core_primitives::traits::{DasParamsApi, Extractor, HeaderWithCommitment};
+use melo_das_db::traits::DasKv;
+use melo_daser::{PruneReport, Pruner};
+use sc_client_api::BlockBackend;
+use sc_rpc_api::DenyUnsafe;
+use serde::{Deserialize, Serialize};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+use std::sync::Arc;
+
+use crate::Error;
+
+/// The local data removed by `das_pruneLocalData`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PruneInfo {
+	/// The number of blocks pruned.
+	pub blocks: u32,
+	/// The number of values removed.
+	pub values: u32,
+	/// The number of bytes reclaimed.
+	pub bytes: u64,
+}
+
+impl From<PruneReport> for PruneInfo {
+	fn from(report: PruneReport) -> Self {
+		Self { blocks: report.blocks, values: report.values, bytes: report.bytes }
+	}
+}
+
+/// Defines the API pruning the data stored by the node.
+#[rpc(client, server, namespace = "das")]
+pub trait PruneApi {
+	/// Removes the sidecars, segments and confidences of the finalized blocks before
+	/// `before_block` from the local storage of the node, and returns the data reclaimed.
+	///
+	/// This is an unsafe method. The blocks already pruned, by a previous call or by the pruning
+	/// task following the retention period, are skipped.
+	#[method(name = "pruneLocalData")]
+	async fn prune_local_data(&self, before_block: u32) -> RpcResult<PruneInfo>;
+}
+
+/// The prune API's implementation.
+pub struct PruneLocalData<C, Block, DB> {
+	pruner: Arc<Pruner<C, Block, DB>>,
+	deny_unsafe: DenyUnsafe,
+}
+
+impl<C, Block, DB> PruneLocalData<C, Block, DB> {
+	/// Creates a new [`PruneLocalData`] instance, pruning with `pruner` unless `deny_unsafe`
+	/// denies it.
+	pub fn new(pruner: Arc<Pruner<C, Block, DB>>, deny_unsafe: DenyUnsafe) -> Self {
+		Self { pruner, deny_unsafe }
+	}
+}
+
+#[async_trait]
+impl<C, Block, DB> PruneApiServer for PruneLocalData<C, Block, DB>
+where
+	Block: BlockT,
+	Block::Header: HeaderWithCommitment,
+	C: HeaderBackend<Block>
+		+ BlockBackend<Block>
+		+ ProvideRuntimeApi<Block>
+		+ Send
+		+ Sync
+		+ 'static,
+	C::Api: Extractor<Block> + DasParamsApi<Block>,
+	DB: DasKv + Send + Sync + 'static,
+	NumberFor<Block>: Into<u32> + From<u32>,
+{
+	async fn prune_local_data(&self, before_block: u32) -> RpcResult<PruneInfo> {
+		self.deny_unsafe.check_if_safe()?;
+		let report = self
+			.pruner
+			.prune_before(before_block, u32::MAX)
+			.await
+			.map_err(|e| Error::PruneFailed(e.to_string()))?;
+		Ok(report.into())
+	}
+}
@@ -12,35 +12,125 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::Error;
+use crate::{
+	committee::CommitteeMirror,
+	dedup::{extrinsic_hash, Submission, SubmissionDedup},
+	metrics::{timed, SubmitMetrics, SubmitStage},
+	Error,
+};
 
 use codec::{Decode, Encode};
+use futures::lock::Mutex;
 use jsonrpsee::{
 	core::{async_trait, RpcResult},
 	proc_macros::rpc,
 };
-use log::{error, info};
-use melo_core_primitives::traits::AppDataApi;
-use melo_daser::DasNetworkOperations;
-use melodot_runtime::{RuntimeCall, UncheckedExtrinsic};
+use log::{error, info, warn};
+use melo_core_primitives::{
+	padding, receipt::StorageReceipt, sidecar_key, traits::AppDataApi, BlobLimits, DasParams,
+	KzgWorkerPool, SidecarMetadata,
+};
+pub use melo_core_primitives::{BlobTxError, BlobTxSatus, PoolRejection};
+use melo_das_db::traits::DasKv;
+use melo_daser::{is_dht_degraded, DasNetworkOperations, SidecarVerifier};
+use melodot_runtime::{
+	AccountId, Balance, BlockHashCount, Index, Runtime, RuntimeCall, SignedExtra, SignedPayload,
+	UncheckedExtrinsic,
+};
+
+use frame_system_rpc_runtime_api::AccountNonceApi;
 
-use sc_transaction_pool_api::{error::IntoPoolError, TransactionPool, TransactionSource};
+use sc_transaction_pool_api::{
+	error::{Error as PoolError, IntoPoolError},
+	TransactionFor, TransactionPool, TransactionSource, TransactionStatusStreamFor,
+};
 use serde::{Deserialize, Serialize};
-use sp_api::ProvideRuntimeApi;
+use sp_api::{ApiExt, Core, ProvideRuntimeApi};
 use sp_blockchain::HeaderBackend;
-use sp_core::Bytes;
-use sp_runtime::{generic, traits::Block as BlockT};
-use std::{marker::PhantomData, sync::Arc};
+use sp_core::{Bytes, H256};
+use sp_runtime::{
+	generic::{self, Era},
+	traits::Block as BlockT,
+	transaction_validity::InvalidTransaction,
+	SaturatedConversion,
+};
+use std::{marker::PhantomData, pin::Pin, sync::Arc, time::Duration};
+use tracing::Instrument;
 
 pub use sc_rpc_api::DenyUnsafe;
 
-/// Represents the status of a Blob transaction.
-/// Includes the transaction hash and potential error details.
-#[derive(Eq, PartialEq, Default, Clone, Encode, Decode, Debug, Serialize, Deserialize)]
+/// The parameters of a blob prepared from raw data by the node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RawDataParams {
+	/// The nonce of the blob in its application, the current nonce of the application plus one.
+	pub nonce: u32,
+	/// Whether to prove all the blobs with a single batch proof rather than a proof per blob.
+	#[serde(default)]
+	pub batch_proof: bool,
+	/// Whether to frame the data with the padding scheme of `melo_core_primitives` first, so that
+	/// `das_getBlob` returns it exactly.
+	#[serde(default)]
+	pub padded: bool,
+}
+
+/// A call submitting a blob prepared from raw data by the node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct BlobTxSatus<Hash> {
-	pub tx_hash: Hash,
-	pub err: Option<String>,
+pub struct PreparedBlobCall {
+	/// The SCALE-encoded `MeloStore::submit_data` call, to be signed by the client.
+	pub call: Bytes,
+	/// The hash of the metadata of the blob.
+	pub data_hash: H256,
+	/// The number of blobs the data takes.
+	pub blobs: u32,
+	/// The framed data to submit in place of the raw data, if it was padded.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub data: Option<Bytes>,
+}
+
+/// The parameters of a blob transaction prepared for signing offline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrepareBlobTxParams {
+	/// The account signing the transaction.
+	pub signer: AccountId,
+	/// The parameters of the blob, as for `das_submitRawData`.
+	#[serde(flatten)]
+	pub blob: RawDataParams,
+	/// The nonce of the signer, the one of the account at the best block if not set. Transactions
+	/// of the signer waiting in the pool are not counted.
+	#[serde(default)]
+	pub account_nonce: Option<Index>,
+	/// The tip of the transaction.
+	#[serde(default)]
+	pub tip: Balance,
+	/// Whether the transaction never expires, rather than in about 2048 blocks, for signing flows
+	/// taking longer.
+	#[serde(default)]
+	pub immortal: bool,
+}
+
+/// A blob transaction prepared by the node, to be signed offline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreparedBlobTx {
+	/// The SCALE-encoded `MeloStore::submit_data` call.
+	pub call: Bytes,
+	/// The SCALE-encoded signed extensions of the transaction.
+	pub extra: Bytes,
+	/// The payload to sign, the call, the signed extensions and the data they sign, hashed with
+	/// Blake2-256 when longer than 256 bytes.
+	pub signing_payload: Bytes,
+	/// The nonce of the signer the transaction is prepared with.
+	pub account_nonce: Index,
+	/// The hash of the metadata of the blob.
+	pub data_hash: H256,
+	/// The number of blobs the data takes.
+	pub blobs: u32,
+	/// The framed data to submit in place of the raw data, if it was padded.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub data: Option<Bytes>,
 }
 
 /// Defines the Das API's functionalities.
@@ -49,121 +139,863 @@ pub trait SubmitBlobApi<Hash> {
 	/// Method for submitting blob transactions.
 	/// This will take care of encoding, and then submitting the data and extrinsic to the pool.
 	#[method(name = "submitBlobTx")]
-	async fn submit_blob_tx(&self, data: Bytes, extrinsic: Bytes) -> RpcResult<BlobTxSatus<Hash>>;
+	async fn submit_blob_tx(
+		&self,
+		data: Bytes,
+		extrinsic: Bytes,
+		request_id: Option<String>,
+	) -> RpcResult<BlobTxSatus<Hash>>;
+
+	/// Submits several blob transactions, each as `das_submitBlobTx` does.
+	///
+	/// The blobs of all the extrinsics are read in a single call to the runtime, and all of them
+	/// are validated before any is published or submitted.
+	///
+	/// # Arguments
+	/// * `txs` - The data and the extrinsic of each transaction.
+	///
+	/// # Returns
+	/// The status of each transaction, in the order of `txs`.
+	///
+	/// # Errors
+	/// None of the transactions is submitted if one of them is invalid, the error is the one
+	/// `das_submitBlobTx` returns for the first invalid transaction.
+	#[method(name = "submitBlobTxs")]
+	async fn submit_blob_txs(&self, txs: Vec<(Bytes, Bytes)>) -> RpcResult<Vec<BlobTxSatus<Hash>>>;
+
+	/// Puts the segments of a sidecar submitted through this node to the DHT network again.
+	///
+	/// The data is read from the local database and verified against its commitments before
+	/// being published.
+	///
+	/// # Arguments
+	/// * `data_hash` - The hash of the sidecar metadata.
+	///
+	/// # Returns
+	/// `false` if the node does not hold the sidecar, `true` once its segments are published.
+	///
+	/// # Errors
+	/// `DhtDegraded` while the DHT operations are short-circuited after repeated timeouts. The
+	/// call is unsafe, and denied to the clients not allowed to make unsafe calls.
+	#[method(name = "republishBlob")]
+	async fn republish_blob(&self, data_hash: H256) -> RpcResult<bool>;
+
+	/// Computes the commitments and proofs of raw data and returns the call submitting it.
+	///
+	/// The node builds the metadata of the blob with its own KZG settings, so that clients need
+	/// neither the trusted setup nor the proving logic. The returned call is signed by the client
+	/// and submitted with `das_submitBlobTx`, along with the same data, or the returned framed
+	/// data if it was padded.
+	///
+	/// # Arguments
+	/// * `app_id` - The application of the blob.
+	/// * `data` - The raw data of the blob.
+	/// * `signer_payload` - The nonce of the blob, whether to use a batch proof and whether to pad
+	///   the data.
+	///
+	/// # Errors
+	/// The call is not prepared if the data is empty (`DataLength`), or larger than the runtime
+	/// allows or exceeds the quotas of the app in the next block, as with `das_submitBlobTx`.
+	#[method(name = "submitRawData")]
+	async fn submit_raw_data(
+		&self,
+		app_id: u32,
+		data: Bytes,
+		signer_payload: RawDataParams,
+	) -> RpcResult<PreparedBlobCall>;
+
+	/// Prepares a blob transaction of raw data for signing on another machine.
+	///
+	/// The node computes the commitments and proofs of the data, as `das_submitRawData` does, and
+	/// the signed extensions of the transaction, so that the offline machine only signs the
+	/// returned payload. The signed extrinsic, built from the call, the signer, the signature and
+	/// the signed extensions, is submitted with `das_submitSignedBlobTx`.
+	///
+	/// # Arguments
+	/// * `app_id` - The application of the blob.
+	/// * `data` - The raw data of the blob.
+	/// * `params` - The signer and the parameters of the blob and of the transaction.
+	///
+	/// # Errors
+	/// As `das_submitRawData`, and `FetchTransactionMetadataFailed` if the nonce of the signer or
+	/// the runtime version could not be read.
+	#[method(name = "prepareBlobTx")]
+	async fn prepare_blob_tx(
+		&self,
+		app_id: u32,
+		data: Bytes,
+		params: PrepareBlobTxParams,
+	) -> RpcResult<PreparedBlobTx>;
+
+	/// Submits a blob transaction signed offline, as `das_submitBlobTx` does.
+	///
+	/// # Arguments
+	/// * `signed_extrinsic` - The signed extrinsic of a call prepared by `das_prepareBlobTx`.
+	/// * `data` - The data of the blob, the framed data returned by `das_prepareBlobTx` if it was
+	///   padded.
+	///
+	/// # Errors
+	/// As `das_submitBlobTx`, and `UnsignedExtrinsic` if the extrinsic is not signed.
+	#[method(name = "submitSignedBlobTx")]
+	async fn submit_signed_blob_tx(
+		&self,
+		signed_extrinsic: Bytes,
+		data: Bytes,
+	) -> RpcResult<BlobTxSatus<Hash>>;
 }
 
 /// Main structure representing the Das system.
 /// Holds client connection, transaction pool, and DHT network service.
-pub struct SubmitBlob<P: TransactionPool, Client, B, D> {
+pub struct SubmitBlob<P: TransactionPool, Client, B, D, DB> {
 	/// Client interface for interacting with the blockchain.
 	client: Arc<Client>,
 	/// Pool for managing and processing transactions.
 	pool: Arc<P>,
 	/// DAS DHT network service.
 	das_network: Arc<D>,
+	/// DAS database, holding the sidecars submitted through this node.
+	database: Arc<Mutex<DB>>,
+	/// Verifier of the data of the sidecars, remembering the data recently verified.
+	verifier: Arc<SidecarVerifier>,
+	/// Pool computing the commitments and proofs of raw data, the blocking task pool if `None`.
+	kzg_pool: Option<KzgWorkerPool>,
+	/// Latency of the stages of the submissions, not recorded if `None`.
+	metrics: Option<SubmitMetrics>,
+	/// The submissions recently made with a request ID.
+	dedup: SubmissionDedup<BlobTxSatus<P::Hash>>,
+	/// The data availability committee the sidecars are mirrored to, not mirrored if `None`.
+	committee: Option<Arc<CommitteeMirror>>,
+	/// Whether to deny the unsafe calls, such as `das_republishBlob`.
+	deny_unsafe: DenyUnsafe,
 	/// Marker for the block type.
 	_marker: PhantomData<B>,
 }
 
-impl<P: TransactionPool, Client, B, D> SubmitBlob<P, Client, B, D> {
+impl<P: TransactionPool, Client, B, D, DB> SubmitBlob<P, Client, B, D, DB> {
 	/// Constructor: Creates a new instance of Das.
-	pub fn new(client: Arc<Client>, pool: Arc<P>, das_network: Arc<D>) -> Self {
-		Self { client, pool, das_network, _marker: Default::default() }
+	pub fn new(
+		client: Arc<Client>,
+		pool: Arc<P>,
+		das_network: Arc<D>,
+		database: Arc<Mutex<DB>>,
+	) -> Self {
+		Self {
+			client,
+			pool,
+			das_network,
+			database,
+			verifier: Default::default(),
+			kzg_pool: None,
+			metrics: None,
+			dedup: Default::default(),
+			committee: None,
+			deny_unsafe: DenyUnsafe::Yes,
+			_marker: Default::default(),
+		}
+	}
+
+	/// Shares `verifier`, and the data it remembers as verified, with other services.
+	pub fn with_verifier(mut self, verifier: Arc<SidecarVerifier>) -> Self {
+		self.verifier = verifier;
+		self
+	}
+
+	/// Allows the unsafe calls if `deny_unsafe` is `DenyUnsafe::No`, they are denied otherwise.
+	pub fn with_deny_unsafe(mut self, deny_unsafe: DenyUnsafe) -> Self {
+		self.deny_unsafe = deny_unsafe;
+		self
+	}
+
+	/// Computes the commitments and proofs of `das_submitRawData` on `pool`.
+	pub fn with_kzg_pool(mut self, pool: KzgWorkerPool) -> Self {
+		self.kzg_pool = Some(pool);
+		self
+	}
+
+	/// Records the latency of the stages of the submissions to `metrics`.
+	pub fn with_metrics(mut self, metrics: SubmitMetrics) -> Self {
+		self.metrics = Some(metrics);
+		self
+	}
+
+	/// Remembers the submissions made with a request ID for `window`, instead of the default
+	/// `DEFAULT_DEDUP_WINDOW`.
+	pub fn with_dedup_window(mut self, window: Duration) -> Self {
+		self.dedup = SubmissionDedup::new(window);
+		self
+	}
+
+	/// Mirrors the sidecars to the members of `committee` alongside their publication to the DHT
+	/// network, reporting the receipts of the members in the status of the submissions.
+	pub fn with_committee(mut self, committee: Arc<CommitteeMirror>) -> Self {
+		self.committee = Some(committee);
+		self
 	}
 }
 
 const TX_SOURCE: TransactionSource = TransactionSource::External;
 
-#[async_trait]
-impl<P, C, Block, D> SubmitBlobApiServer<P::Hash> for SubmitBlob<P, C, Block, D>
+impl<P, C, Block, D, DB> SubmitBlob<P, C, Block, D, DB>
 where
 	Block: BlockT,
 	P: TransactionPool<Block = Block> + 'static,
 	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + 'static + Sync + Send,
 	C::Api: AppDataApi<Block, RuntimeCall>,
 	D: DasNetworkOperations + Sync + Send + 'static + Clone,
+	DB: DasKv + Send + Sync + 'static,
+{
+	/// Submits a blob transaction as [`SubmitBlobApiServer::submit_blob_tx`] does, and watches
+	/// the status of the transaction in the pool.
+	///
+	/// # Returns
+	/// The hash of the transaction, a [`BlobTxError`] if the data could not be put to the DHT
+	/// network or if the transaction pool rejected the transaction, and the stream of the status
+	/// of the transaction unless it was rejected.
+	pub async fn submit_and_watch(
+		&self,
+		data: Bytes,
+		extrinsic: Bytes,
+	) -> RpcResult<(P::Hash, Option<BlobTxError>, Option<Pin<Box<TransactionStatusStreamFor<P>>>>)>
+	{
+		async {
+			let (xt, mut err, _) = self.publish(&data, &extrinsic).await?;
+
+			let at = generic::BlockId::hash(self.client.info().best_hash);
+			let tx_hash = self.pool.hash_of(&xt);
+			let stream = match self
+				.stage(SubmitStage::Pool, self.pool.submit_and_watch(&at, TX_SOURCE, xt))
+				.await
+			{
+				Ok(stream) => Some(stream),
+				Err(e) => {
+					let e = e
+						.into_pool_error()
+						.map_err(|e| Error::TransactionPushFailed(Box::new(e)))?;
+					err = Some(BlobTxError::PoolRejected {
+						reason: pool_rejection(&e),
+						message: e.to_string(),
+					});
+					None
+				},
+			};
+
+			Ok((tx_hash, err, stream))
+		}
+		.instrument(submit_span("submit_and_watch", 1, data.len()))
+		.await
+	}
+
+	/// Submits a blob transaction as [`SubmitBlobApiServer::submit_blob_tx`] does without a
+	/// request ID.
+	async fn submit_one(&self, data: Bytes, extrinsic: Bytes) -> RpcResult<BlobTxSatus<P::Hash>> {
+		async {
+			let (xt, mut err, committee_receipts) = self.publish(&data, &extrinsic).await?;
+
+			// Submit to the transaction pool
+			let best_block_hash = self.client.info().best_hash;
+			let at = generic::BlockId::hash(best_block_hash)
+				as generic::BlockId<<P as sc_transaction_pool_api::TransactionPool>::Block>;
+
+			let tx_hash = self.pool.hash_of(&xt);
+			if let Err(e) =
+				self.stage(SubmitStage::Pool, self.pool.submit_one(&at, TX_SOURCE, xt)).await
+			{
+				let e =
+					e.into_pool_error().map_err(|e| Error::TransactionPushFailed(Box::new(e)))?;
+				err = Some(BlobTxError::PoolRejected {
+					reason: pool_rejection(&e),
+					message: e.to_string(),
+				});
+			}
+
+			Ok(BlobTxSatus { tx_hash, err, committee_receipts })
+		}
+		.instrument(submit_span("submit_blob_tx", 1, data.len()))
+		.await
+	}
+
+	/// Runs `stage` of a submission in its span, recording its latency and outcome.
+	async fn stage<T, E>(
+		&self,
+		stage: SubmitStage,
+		future: impl std::future::Future<Output = Result<T, E>>,
+	) -> Result<T, E> {
+		timed(self.metrics.as_ref(), stage, future).await
+	}
+
+	/// Validates the data of a blob transaction against its metadata and the limits of the
+	/// runtime, keeps the sidecar and puts the data to the DHT network.
+	///
+	/// Returns the decoded extrinsic, with a [`BlobTxError`] if the data could not be put to the
+	/// DHT network and the receipts of the committee members which stored it.
+	async fn publish(
+		&self,
+		data: &Bytes,
+		extrinsic: &Bytes,
+	) -> Result<(TransactionFor<P>, Option<BlobTxError>, Vec<StorageReceipt>), Error> {
+		let (xt, ext) = self
+			.stage(SubmitStage::Decode, async { decode_extrinsic::<P>(extrinsic) })
+			.await?;
+
+		// Get block hash
+		let at = self.client.info().best_hash;
+
+		// Get the blob of the call, with its rows in the block matrix, and validate
+		let placement = self
+			.stage(SubmitStage::Params, async {
+				match &self
+					.client
+					.runtime_api()
+					.get_blob_placements(at, &ext.function)
+					.map_err(|e| Error::FetchTransactionMetadataFailed(Box::new(e)))?[..]
+				{
+					[placement] => Ok(placement.clone()),
+					_ => Err(Error::InvalidTransactionFormat),
+				}
+			})
+			.await?;
+		let rows = placement.rows();
+		let metadata = placement.metadata;
+
+		let bytes = self.validate(at, data, &metadata).await?;
+
+		info!(
+			"🤩 Data verification successful. Pushing data of app {} at rows {:?} to DHT network.",
+			metadata.app_id, rows
+		);
+
+		let (err, receipts) = self.put(&metadata, data, &bytes).await;
+		Ok((xt, err, receipts))
+	}
+
+	/// Validates `data` against its `metadata` and the limits of the runtime at `at`.
+	///
+	/// Returns the uncompressed bytes of the data.
+	async fn validate(
+		&self,
+		at: Block::Hash,
+		data: &Bytes,
+		metadata: &SidecarMetadata,
+	) -> Result<Vec<u8>, Error> {
+		// Validate the data against the metadata and the limits of the runtime before anything
+		// enters the pool, a transaction whose data is rejected would be included unavailable.
+		let limits = self
+			.stage(SubmitStage::Params, async { self.blob_limits(at, metadata.app_id) })
+			.await?;
+
+		self.stage(SubmitStage::Verify, self.verify(data, metadata, &limits)).await
+	}
+
+	/// Verifies `data` against its `metadata`, within `limits`.
+	///
+	/// Returns the uncompressed bytes of the data.
+	async fn verify(
+		&self,
+		data: &Bytes,
+		metadata: &SidecarMetadata,
+		limits: &BlobLimits,
+	) -> Result<Vec<u8>, Error> {
+		if metadata.bytes_len > limits.max_bytes_len {
+			return Err(Error::BlobTooLarge { len: metadata.bytes_len, max: limits.max_bytes_len })
+		}
+
+		// The data may be compressed, the metadata commits to the uncompressed bytes.
+		let bytes = metadata.decode_payload(data).map_err(|_| Error::DataLength)?;
+
+		let expected = metadata.blob_count();
+		// Either one proof per blob or a single batch proof of all the blobs.
+		if metadata.commitments.len() != expected ||
+			(metadata.proofs.len() != expected && !metadata.has_batch_proof())
+		{
+			return Err(Error::CommitmentCountMismatch {
+				expected,
+				commitments: metadata.commitments.len(),
+				proofs: metadata.proofs.len(),
+			})
+		}
+
+		if !metadata.check() {
+			return Err(Error::DataLength)
+		}
+
+		check_quotas(metadata, limits)?;
+
+		match self.verifier.verify(metadata, bytes.clone()).await {
+			Ok(true) => Ok(bytes),
+			Ok(false) => Err(Error::DataVerificationFailed(
+				"Data does not match the commitments and proofs".to_string(),
+			)),
+			Err(e) => Err(Error::DataVerificationFailed(e)),
+		}
+	}
+
+	/// Keeps the sidecar of validated data, puts its `bytes` to the DHT network and mirrors it to
+	/// the committee if any.
+	///
+	/// Returns a [`BlobTxError`] if the data could not be put to the DHT network, and the receipts
+	/// of the committee members which stored it.
+	async fn put(
+		&self,
+		metadata: &SidecarMetadata,
+		data: &Bytes,
+		bytes: &[u8],
+	) -> (Option<BlobTxError>, Vec<StorageReceipt>) {
+		// Keep the sidecar, in its compressed form, so that its segments can be republished later.
+		self.database
+			.lock()
+			.await
+			.set(&sidecar_key(&metadata.id()), &(metadata, &data.0).encode());
+
+		// On successful data verification, push data to DHT network, and to the committee at the
+		// same time.
+		let mirror = async {
+			match &self.committee {
+				Some(committee) => committee.mirror(metadata, data).await,
+				None => Vec::new(),
+			}
+		};
+		let (result, receipts) = futures::join!(
+			self.stage(
+				SubmitStage::DhtPut,
+				self.das_network.put_bytes(
+					bytes,
+					metadata.app_id,
+					metadata.nonce,
+					metadata.compression,
+				),
+			),
+			mirror
+		);
+
+		let err = match result {
+			Ok(_) => None,
+			Err(e) if is_dht_degraded(&e) => {
+				warn!("⚡ Data not pushed to the degraded DHT network: {}", e);
+				Some(BlobTxError::DhtDegraded { message: e.to_string() })
+			},
+			Err(e) => {
+				error!("❌ Failed to put data to DHT network: {:?}", e);
+				Some(BlobTxError::DhtPutFailed { message: e.to_string() })
+			},
+		};
+
+		(err, receipts)
+	}
+
+	/// Computes the metadata of raw `data` of `app_id`, framed first if `params` ask so.
+	///
+	/// Returns the metadata and the data to submit.
+	async fn prepare(
+		&self,
+		app_id: u32,
+		data: Bytes,
+		params: RawDataParams,
+	) -> Result<(SidecarMetadata, Bytes), Error> {
+		if data.is_empty() {
+			return Err(Error::DataLength)
+		}
+
+		let RawDataParams { nonce, batch_proof, padded } = params;
+		let data = if padded { Bytes::from(padding::pad(&data)) } else { data };
+
+		let at = self.client.info().best_hash;
+		let limits = self.blob_limits(at, app_id)?;
+		let len = data.len() as u32;
+		if data.len() > limits.max_bytes_len as usize {
+			return Err(Error::BlobTooLarge { len, max: limits.max_bytes_len })
+		}
+
+		// Proving takes a while for large data, keep it off the async workers.
+		let bytes = data.0.clone();
+		let metadata = match &self.kzg_pool {
+			Some(pool) => pool.sidecar_metadata(bytes, app_id, nonce, batch_proof).await,
+			None => tokio::task::spawn_blocking(move || {
+				if batch_proof {
+					SidecarMetadata::try_from_app_data_with_batch_proof(&bytes, app_id, nonce)
+				} else {
+					SidecarMetadata::try_from_app_data(&bytes, app_id, nonce)
+				}
+			})
+			.await
+			.map_err(|e| Error::DataVerificationFailed(format!("Proving task failed: {}", e)))?,
+		}
+		.map_err(Error::DataVerificationFailed)?;
+
+		check_quotas(&metadata, &limits)?;
+
+		// The commitments were computed here, the data needs no verification when submitted.
+		self.verifier.mark_verified(&metadata, &data);
+
+		info!("🧮 Prepared {} blobs of app {} nonce {}", metadata.blob_count(), app_id, nonce);
+		Ok((metadata, data))
+	}
+
+	/// Returns the limits a submission of `app_id` to the block after `at` has to respect.
+	fn blob_limits(&self, at: Block::Hash, app_id: u32) -> Result<BlobLimits, Error> {
+		let api = self.client.runtime_api();
+		// Runtimes before version 5 of the API can not report their limits, the data is then
+		// checked against those of an empty block under the default parameters.
+		let has_blob_limits = api
+			.has_api_with::<dyn AppDataApi<Block, RuntimeCall>, _>(at, |version| version >= 5)
+			.map_err(|e| Error::FetchTransactionMetadataFailed(Box::new(e)))?;
+		if !has_blob_limits {
+			return Ok(BlobLimits::of_empty_block(&DasParams::default()))
+		}
+		api.blob_limits(at, app_id)
+			.map_err(|e| Error::FetchTransactionMetadataFailed(Box::new(e)))
+	}
+}
+
+#[async_trait]
+impl<P, C, Block, D, DB> SubmitBlobApiServer<P::Hash> for SubmitBlob<P, C, Block, D, DB>
+where
+	Block: BlockT,
+	P: TransactionPool<Block = Block> + 'static,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + 'static + Sync + Send,
+	C::Api: AppDataApi<Block, RuntimeCall> + Core<Block> + AccountNonceApi<Block, AccountId, Index>,
+	D: DasNetworkOperations + Sync + Send + 'static + Clone,
+	DB: DasKv + Send + Sync + 'static,
 {
 	/// Submits a blob transaction to the transaction pool.
 	/// The transaction undergoes validation and then gets executed by the runtime.
 	///
 	/// # Arguments
-	/// * `data` - Raw data intended for DHT network.
+	/// * `data` - Raw data intended for DHT network, compressed as set by the `compression` of the
+	///   metadata of the blob.
 	/// * `extrinsic` - An unsigned extrinsic to be included in the transaction pool.
+	/// * `request_id` - An ID chosen by the client for the submission. A retry with the same ID and
+	///   extrinsic within the deduplication window, 10 minutes by default, returns the status of
+	///   the original submission instead of submitting again.
 	///
 	/// # Returns
 	/// A struct containing:
 	/// * `tx_hash` - The hash of the transaction.
-	/// * `err` - `Some` error string if the data submission fails. `None` if successful.
+	/// * `err` - A [`BlobTxError`] if the data could not be put to the DHT network, or if the
+	///   transaction pool rejected the transaction. `None` if successful.
+	/// * `committeeReceipts` - The receipts of the committee members which stored the data, when
+	///   the node mirrors the sidecars to a data availability committee.
+	///
+	/// # Errors
+	/// The extrinsic is not submitted if the data is larger than the runtime allows
+	/// (`BlobTooLarge`), exceeds the blob or byte quota of the app in the next block
+	/// (`QuotaExceeded`, `BytesQuotaExceeded`), carries a number of commitments or proofs not
+	/// matching its length (`CommitmentCountMismatch`), or does not match its commitments
+	/// (`DataVerificationFailed`). A request ID already used for another extrinsic within the
+	/// window is rejected (`RequestIdConflict`). A submission failing with an error is not
+	/// remembered, and can be retried with the same ID.
 	///
 	/// # Note
-	/// Ensure proper encoding of the data. Improper encoding can result in a successful transaction
-	/// submission (if it's valid), but a failed data publication, rendering the data inaccessible.
+	/// A failure to publish valid data to the DHT network does not prevent the submission, and is
+	/// reported in `err`.
 	async fn submit_blob_tx(
 		&self,
 		data: Bytes,
 		extrinsic: Bytes,
+		request_id: Option<String>,
 	) -> RpcResult<BlobTxSatus<P::Hash>> {
-		// Decode the provided extrinsic.
-		let xt = Decode::decode(&mut &extrinsic[..])
-			.map_err(|e| Error::DecodingExtrinsicFailed(Box::new(e)))?;
+		let request_id = match request_id {
+			Some(request_id) => request_id,
+			None => return self.submit_one(data, extrinsic).await,
+		};
 
-		let ext = UncheckedExtrinsic::decode(&mut &extrinsic[..])
-			.map_err(|e| Error::DecodingTransactionMetadataFailed(Box::new(e)))?;
+		// Retries wait here for the original submission to complete.
+		let extrinsic_hash = extrinsic_hash(&extrinsic);
+		let slot = self.dedup.slot(&request_id);
+		let mut submission = slot.lock().await;
+		if let Some(submission) = &*submission {
+			if submission.extrinsic_hash != extrinsic_hash {
+				return Err(Error::RequestIdConflict(request_id).into())
+			}
+			info!("🔁 Request {} already submitted, returning its status", request_id);
+			return Ok(submission.status.clone())
+		}
 
-		// Get block hash
-		let at = self.client.info().best_hash;
+		let status = self.submit_one(data, extrinsic).await?;
+		*submission = Some(Submission { extrinsic_hash, status: status.clone() });
+		Ok(status)
+	}
+
+	async fn submit_blob_txs(
+		&self,
+		txs: Vec<(Bytes, Bytes)>,
+	) -> RpcResult<Vec<BlobTxSatus<P::Hash>>> {
+		if txs.is_empty() {
+			return Ok(Vec::new())
+		}
+
+		let len = txs.iter().map(|(data, _)| data.len()).sum();
+		async {
+			let (xts, calls) = self
+				.stage(SubmitStage::Decode, async {
+					let mut xts = Vec::with_capacity(txs.len());
+					let mut calls = Vec::with_capacity(txs.len());
+					for (_, extrinsic) in &txs {
+						let (xt, ext) = decode_extrinsic::<P>(extrinsic)?;
+						xts.push(xt);
+						calls.push(ext.function);
+					}
+					Ok::<_, Error>((xts, calls))
+				})
+				.await?;
+
+			let at = self.client.info().best_hash;
+
+			// Get the blobs of all the calls at once, and validate them all before publishing any.
+			let params = self
+				.stage(SubmitStage::Params, async {
+					self.client
+						.runtime_api()
+						.get_blob_tx_params(at, calls)
+						.map_err(|e| Error::FetchTransactionMetadataFailed(Box::new(e)))
+				})
+				.await?;
+
+			let mut verified = Vec::with_capacity(txs.len());
+			for ((data, _), metadata) in txs.iter().zip(params) {
+				let metadata = metadata.ok_or(Error::InvalidTransactionFormat)?;
+				let bytes = self.validate(at, data, &metadata).await?;
+				verified.push((metadata, bytes));
+			}
+
+			info!(
+				"🤩 Data verification successful. Pushing data of {} blob txs to DHT network.",
+				txs.len()
+			);
 
-		// Get blob_tx_param and validate
-		let metadata = self
-			.client
-			.runtime_api()
-			.get_blob_tx_param(at, &ext.function)
-			.map_err(|e| Error::FetchTransactionMetadataFailed(Box::new(e)))?
-			.ok_or(Error::InvalidTransactionFormat)?;
+			let mut puts = Vec::with_capacity(txs.len());
+			for ((data, _), (metadata, bytes)) in txs.iter().zip(&verified) {
+				puts.push(self.put(metadata, data, bytes).await);
+			}
 
-		// Validate the length of the data.
-		if !metadata.check() || data.len() != (metadata.bytes_len as usize) {
-			return Err(Error::DataLength.into())
+			// Submit to the transaction pool
+			let tx_hashes: Vec<_> = xts.iter().map(|xt| self.pool.hash_of(xt)).collect();
+			let results = self
+				.stage(
+					SubmitStage::Pool,
+					self.pool.submit_at(&generic::BlockId::hash(at), TX_SOURCE, xts),
+				)
+				.await
+				.map_err(|e| Error::TransactionPushFailed(Box::new(e)))?;
+
+			let mut statuses = Vec::with_capacity(results.len());
+			for ((tx_hash, (mut err, committee_receipts)), result) in
+				tx_hashes.into_iter().zip(puts).zip(results)
+			{
+				if let Err(e) = result {
+					let e = e
+						.into_pool_error()
+						.map_err(|e| Error::TransactionPushFailed(Box::new(e)))?;
+					err = Some(BlobTxError::PoolRejected {
+						reason: pool_rejection(&e),
+						message: e.to_string(),
+					});
+				}
+				statuses.push(BlobTxSatus { tx_hash, err, committee_receipts });
+			}
+
+			Ok(statuses)
 		}
+		.instrument(submit_span("submit_blob_txs", txs.len(), len))
+		.await
+	}
 
-		let mut err_msg = None;
+	async fn republish_blob(&self, data_hash: H256) -> RpcResult<bool> {
+		self.deny_unsafe.check_if_safe()?;
 
-		match metadata.verify_bytes(&data) {
-			Ok(true) => {
-				info!("🤩 Data verification successful. Pushing data to DHT network.");
-				// On successful data verification, push data to DHT network.
-				let put_res =
-					self.das_network.put_bytes(&data, metadata.app_id, metadata.nonce).await;
+		let encoded = match self.database.lock().await.get(&sidecar_key(data_hash.as_bytes())) {
+			Some(encoded) => encoded,
+			None => return Ok(false),
+		};
+
+		let (metadata, data) = <(SidecarMetadata, Vec<u8>)>::decode(&mut &encoded[..])
+			.map_err(|e| Error::DataVerificationFailed(e.to_string()))?;
+		let bytes = metadata.decode_payload(&data).map_err(Error::DataVerificationFailed)?;
+
+		match self.verifier.verify(&metadata, bytes.clone()).await {
+			Ok(true) => (),
+			Ok(false) =>
+				return Err(Error::DataVerificationFailed(
+					"Data does not match the commitments and proofs".to_string(),
+				)
+				.into()),
+			Err(e) => return Err(Error::DataVerificationFailed(e).into()),
+		}
 
-				if let Err(e) = put_res {
-					error!("❌ Failed to put data to DHT network: {:?}", e);
-					err_msg = Some(e.to_string());
+		self.das_network
+			.put_bytes(&bytes, metadata.app_id, metadata.nonce, metadata.compression)
+			.await
+			.map_err(|e| {
+				if is_dht_degraded(&e) {
+					return Error::DhtDegraded(e.to_string())
 				}
-			},
-			Ok(false) => {
-				// Handle cases where data verification failed.
-				err_msg = Some(
-					"Data verification failed. Please check your data and try again.".to_string(),
-				);
-			},
-			Err(e) => {
-				// Handle unexpected errors during verification.
-				err_msg = Some(e);
-			},
+				error!("❌ Failed to put data to DHT network: {:?}", e);
+				Error::PublishBlobFailed(e.into())
+			})?;
+
+		info!("📢 Republished data of app {} nonce {}", metadata.app_id, metadata.nonce);
+		Ok(true)
+	}
+
+	async fn submit_raw_data(
+		&self,
+		app_id: u32,
+		data: Bytes,
+		signer_payload: RawDataParams,
+	) -> RpcResult<PreparedBlobCall> {
+		let padded = signer_payload.padded;
+		let (metadata, data) = self.prepare(app_id, data, signer_payload).await?;
+
+		Ok(PreparedBlobCall {
+			data_hash: H256(metadata.id()),
+			blobs: metadata.blob_count() as u32,
+			call: RuntimeCall::MeloStore(pallet_melo_store::Call::submit_data { params: metadata })
+				.encode()
+				.into(),
+			data: padded.then_some(data),
+		})
+	}
+
+	async fn prepare_blob_tx(
+		&self,
+		app_id: u32,
+		data: Bytes,
+		params: PrepareBlobTxParams,
+	) -> RpcResult<PreparedBlobTx> {
+		let PrepareBlobTxParams { signer, blob, account_nonce, tip, immortal } = params;
+		let (metadata, data) = self.prepare(app_id, data, blob).await?;
+
+		let info = self.client.info();
+		let runtime_api = self.client.runtime_api();
+		let account_nonce = match account_nonce {
+			Some(account_nonce) => account_nonce,
+			None => runtime_api
+				.account_nonce(info.best_hash, signer)
+				.map_err(|e| Error::FetchTransactionMetadataFailed(Box::new(e)))?,
+		};
+		let version = runtime_api
+			.version(info.best_hash)
+			.map_err(|e| Error::FetchTransactionMetadataFailed(Box::new(e)))?;
+
+		// Mortal transactions are checked against the hash of the block their era starts at.
+		let genesis_hash = H256::from_slice(info.genesis_hash.as_ref());
+		let (era, era_hash) = if immortal {
+			(Era::Immortal, genesis_hash)
+		} else {
+			let period =
+				BlockHashCount::get().checked_next_power_of_two().map(|c| c / 2).unwrap_or(2)
+					as u64;
+			let era = Era::mortal(period, info.best_number.saturated_into());
+			(era, H256::from_slice(info.best_hash.as_ref()))
+		};
+
+		let data_hash = H256(metadata.id());
+		let blobs = metadata.blob_count() as u32;
+		let call =
+			RuntimeCall::MeloStore(pallet_melo_store::Call::submit_data { params: metadata });
+		let extra = signed_extra(era, account_nonce, tip);
+		let payload = SignedPayload::from_raw(
+			call,
+			extra,
+			(
+				(),
+				version.spec_version,
+				version.transaction_version,
+				genesis_hash,
+				era_hash,
+				(),
+				(),
+				(),
+			),
+		);
+		let signing_payload = payload.using_encoded(|payload| payload.to_vec());
+		let (call, extra, _) = payload.deconstruct();
+
+		Ok(PreparedBlobTx {
+			call: call.encode().into(),
+			extra: extra.encode().into(),
+			signing_payload: signing_payload.into(),
+			account_nonce,
+			data_hash,
+			blobs,
+			data: blob.padded.then_some(data),
+		})
+	}
+
+	async fn submit_signed_blob_tx(
+		&self,
+		signed_extrinsic: Bytes,
+		data: Bytes,
+	) -> RpcResult<BlobTxSatus<P::Hash>> {
+		let ext = UncheckedExtrinsic::decode(&mut &signed_extrinsic[..])
+			.map_err(|e| Error::DecodingTransactionMetadataFailed(Box::new(e)))?;
+		if ext.signature.is_none() {
+			return Err(Error::UnsignedExtrinsic.into())
 		}
 
-		// Submit to the transaction pool
-		let best_block_hash = self.client.info().best_hash;
-		let at = generic::BlockId::hash(best_block_hash)
-			as generic::BlockId<<P as sc_transaction_pool_api::TransactionPool>::Block>;
+		self.submit_one(data, signed_extrinsic).await
+	}
+}
+
+/// Returns the signed extensions of a transaction of the signer's `nonce` valid for `era`, with
+/// `tip`.
+fn signed_extra(era: Era, nonce: Index, tip: Balance) -> SignedExtra {
+	(
+		frame_system::CheckNonZeroSender::<Runtime>::new(),
+		frame_system::CheckSpecVersion::<Runtime>::new(),
+		frame_system::CheckTxVersion::<Runtime>::new(),
+		frame_system::CheckGenesis::<Runtime>::new(),
+		frame_system::CheckEra::<Runtime>::from(era),
+		frame_system::CheckNonce::<Runtime>::from(nonce),
+		frame_system::CheckWeight::<Runtime>::new(),
+		pallet_transaction_payment::ChargeTransactionPayment::<Runtime>::from(tip),
+	)
+}
+
+/// Returns the span of a submission through `method` of `txs` transactions with `len` bytes of
+/// data.
+fn submit_span(method: &'static str, txs: usize, len: usize) -> tracing::Span {
+	tracing::info_span!(target: "das-rpc", "submit_blob", method, txs, len)
+}
 
-		let tx_hash = self.pool.submit_one(&at, TX_SOURCE, xt).await.map_err(|e| {
-			e.into_pool_error()
-				.map(|e| Error::TransactionPushFailed(Box::new(e)))
-				.unwrap_or_else(|e| Error::TransactionPushFailed(Box::new(e)))
-		})?;
+/// Decodes `extrinsic` both as a transaction of the pool and as an extrinsic of the runtime.
+fn decode_extrinsic<P: TransactionPool>(
+	extrinsic: &Bytes,
+) -> Result<(TransactionFor<P>, UncheckedExtrinsic), Error> {
+	let xt = Decode::decode(&mut &extrinsic[..])
+		.map_err(|e| Error::DecodingExtrinsicFailed(Box::new(e)))?;
+
+	let ext = UncheckedExtrinsic::decode(&mut &extrinsic[..])
+		.map_err(|e| Error::DecodingTransactionMetadataFailed(Box::new(e)))?;
+
+	Ok((xt, ext))
+}
+
+/// Checks that the blobs of `metadata` fit in the quotas of its app in `limits`.
+fn check_quotas(metadata: &SidecarMetadata, limits: &BlobLimits) -> Result<(), Error> {
+	let blobs = metadata.blob_count() as u32;
+	if blobs > limits.app_quota {
+		return Err(Error::QuotaExceeded { app_id: metadata.app_id, blobs, quota: limits.app_quota })
+	}
+
+	if metadata.bytes_len > limits.bytes_quota {
+		return Err(Error::BytesQuotaExceeded {
+			app_id: metadata.app_id,
+			len: metadata.bytes_len,
+			quota: limits.bytes_quota,
+		})
+	}
+
+	Ok(())
+}
 
-		// Return the transaction hash
-		Ok(BlobTxSatus { tx_hash, err: err_msg })
+/// Classifies the error of the transaction pool rejecting a transaction.
+fn pool_rejection(e: &PoolError) -> PoolRejection {
+	match e {
+		PoolError::InvalidTransaction(InvalidTransaction::Stale) => PoolRejection::StaleNonce,
+		PoolError::InvalidTransaction(InvalidTransaction::Future) |
+		PoolError::RejectedFutureTransaction => PoolRejection::FutureNonce,
+		PoolError::TooLowPriority { .. } => PoolRejection::PriorityTooLow,
+		PoolError::AlreadyImported(_) => PoolRejection::AlreadyImported,
+		_ => PoolRejection::Invalid,
 	}
 }
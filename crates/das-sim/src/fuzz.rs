@@ -0,0 +1,158 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Fuzzing of the sampling.
+//!
+//! [`fuzz`] runs trials of random scenarios and checks invariants that hold whatever the
+//! parameters, such as an honest block being accepted by every light client or a client never
+//! being more confident than its fetched samples allow.
+use crate::{
+	scenario::{Scenario, Withholding},
+	sim::{reference_row, run_trial, TrialOutcome},
+};
+use anyhow::Result;
+use melo_core_primitives::{
+	config::MATRIX,
+	reliability::{calculate_confidence, Permill},
+};
+use melo_das_primitives::KZG;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// A trial breaking an invariant.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzFailure {
+	/// The scenario of the trial, which reproduces it with its seed.
+	pub scenario: Scenario,
+	/// The invariant broken.
+	pub invariant: &'static str,
+}
+
+/// Runs `runs` trials of random scenarios derived from `seed`, returning those breaking an
+/// invariant.
+pub fn fuzz(kzg: &KZG, runs: u32, seed: u64) -> Result<Vec<FuzzFailure>> {
+	let reference = reference_row(kzg, seed)?;
+	let mut rng = StdRng::seed_from_u64(seed);
+	let mut failures = Vec::new();
+
+	for run in 0..runs {
+		let scenario = random_scenario(&mut rng, run);
+		let mut trial_rng = StdRng::seed_from_u64(scenario.seed);
+		let outcome = run_trial(kzg, &scenario, &reference, &mut trial_rng)?;
+		if let Some(invariant) = check(&scenario, &outcome) {
+			failures.push(FuzzFailure { scenario, invariant });
+		}
+	}
+
+	Ok(failures)
+}
+
+/// Returns a random valid scenario of a single trial.
+pub fn random_scenario(rng: &mut StdRng, run: u32) -> Scenario {
+	let cols = MATRIX.extended_cols().0;
+	let samples_per_block = rng.gen_range(1..=32);
+	let withholding = match rng.gen_range(0..5) {
+		0 => Withholding::None,
+		1 => Withholding::Random { fraction: rng.gen() },
+		2 => Withholding::MinimalRow { rows: rng.gen_range(0..4) },
+		3 => Withholding::Columns { count: rng.gen_range(0..=cols) },
+		_ => Withholding::Selective { served_clients: rng.gen_range(0..8) },
+	};
+
+	Scenario {
+		name: format!("fuzz-{}", run),
+		light_clients: rng.gen_range(1..=30),
+		farmers: rng.gen_range(0..=20),
+		rows: rng.gen_range(1..=8),
+		samples_per_block,
+		block_threshold: rng.gen_range(1..=samples_per_block),
+		base_factor: rng.gen_range(0..1_000_000),
+		withholding,
+		loss: if rng.gen_bool(0.25) { 0.0 } else { rng.gen_range(0.0..0.5) },
+		trials: 1,
+		seed: rng.gen(),
+		kzg_recovery: false,
+	}
+}
+
+/// Returns the first invariant `outcome` breaks, if any.
+fn check(scenario: &Scenario, outcome: &TrialOutcome) -> Option<&'static str> {
+	let cols = MATRIX.extended_cols().0;
+	let min_confidence =
+		calculate_confidence(scenario.block_threshold, Permill::from_parts(scenario.base_factor));
+
+	if outcome.confidence.iter().any(|c| *c > 1_000_000) {
+		return Some("the confidence is at most one")
+	}
+	if outcome
+		.accepted
+		.iter()
+		.zip(&outcome.confidence)
+		.any(|(accepted, confidence)| *accepted && *confidence < min_confidence)
+	{
+		return Some("a client accepting a block fetched at least the threshold of samples")
+	}
+	if outcome.farmer_recovered && !outcome.recovered {
+		return Some("the farmers only store served segments")
+	}
+
+	let unrecoverable = match scenario.withholding {
+		Withholding::None => {
+			if !outcome.recovered {
+				return Some("an honest block is recovered")
+			}
+			if scenario.loss == 0.0 && outcome.accepted.iter().any(|accepted| !accepted) {
+				return Some("an honest block is accepted without losses")
+			}
+			false
+		},
+		Withholding::Random { fraction } => fraction == 1.0,
+		Withholding::MinimalRow { rows } => rows > 0,
+		Withholding::Columns { count } => count > cols / 2,
+		Withholding::Selective { served_clients } => served_clients == 0,
+	};
+	if unrecoverable && outcome.recovered {
+		return Some("withheld rows are not recovered")
+	}
+
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use melo_core_primitives::reliability::BLOCK_FAILURE_PROBABILITY;
+
+	#[test]
+	fn test_fuzz_holds_invariants() {
+		assert_eq!(fuzz(&KZG::default_embedded(), 50, 7).unwrap(), vec![]);
+	}
+
+	#[test]
+	fn test_check_detects_broken_invariants() {
+		let scenario = Scenario::default();
+		let honest = TrialOutcome {
+			accepted: vec![true],
+			confidence: vec![calculate_confidence(8, BLOCK_FAILURE_PROBABILITY)],
+			recovered: true,
+			farmer_recovered: true,
+		};
+		assert_eq!(check(&scenario, &honest), None);
+
+		let overconfident = TrialOutcome { confidence: vec![0], ..honest.clone() };
+		assert!(check(&scenario, &overconfident).is_some());
+
+		let unrecovered = TrialOutcome { recovered: false, farmer_recovered: false, ..honest };
+		assert_eq!(check(&scenario, &unrecovered), Some("an honest block is recovered"));
+	}
+}
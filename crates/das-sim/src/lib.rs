@@ -0,0 +1,31 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Simulation of the data availability sampling.
+//!
+//! A [`Scenario`] models light clients sampling a block and farmers storing their assigned pieces,
+//! while a block producer withholds part of the data following a [`Withholding`] strategy and the
+//! network loses some requests. Each trial runs the sampling and confidence code of the node
+//! ([`Reliability`](melo_core_primitives::reliability::Reliability)) for each client and the
+//! recovery of the erasure coding on the pieces fetched by all of them, so that the sampling
+//! parameters can be checked against the actual availability of the data. [`run`] aggregates the
+//! trials of a scenario into a [`Summary`], written as CSV by the command line, and [`fuzz`] runs
+//! random scenarios checking the invariants of the sampling.
+pub mod fuzz;
+pub mod scenario;
+pub mod sim;
+
+pub use fuzz::{fuzz, FuzzFailure};
+pub use scenario::{Scenario, ScenarioFile, Withholding};
+pub use sim::{run, run_trial, Summary, TrialOutcome, CSV_HEADER};
@@ -0,0 +1,83 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Command line of the DAS simulation.
+use anyhow::{bail, Context, Result};
+use melo_das_primitives::KZG;
+use melo_das_sim::{fuzz, run, Scenario, ScenarioFile, CSV_HEADER};
+use std::{fs, io::Write, path::PathBuf};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "melo-das-sim", about = "Simulation of the data availability sampling.")]
+enum Command {
+	/// Runs scenarios and writes their summaries as CSV.
+	Run {
+		/// The TOML file of the scenarios, the default scenario if not set.
+		#[structopt(long, parse(from_os_str))]
+		scenarios: Option<PathBuf>,
+		/// The file to write the CSV to, standard output if not set.
+		#[structopt(long, parse(from_os_str))]
+		output: Option<PathBuf>,
+	},
+	/// Runs random scenarios, failing if one breaks an invariant of the sampling.
+	Fuzz {
+		/// The number of random scenarios.
+		#[structopt(long, default_value = "1000")]
+		runs: u32,
+		/// The seed of the random scenarios.
+		#[structopt(long, default_value = "0")]
+		seed: u64,
+	},
+}
+
+fn main() -> Result<()> {
+	let kzg = KZG::default_embedded();
+
+	match Command::from_args() {
+		Command::Run { scenarios, output } => {
+			let scenarios = match scenarios {
+				Some(path) => {
+					let content = fs::read_to_string(&path)
+						.with_context(|| format!("Failed to read {:?}", path))?;
+					toml::from_str::<ScenarioFile>(&content)
+						.with_context(|| format!("Invalid scenarios in {:?}", path))?
+						.scenarios
+				},
+				None => vec![Scenario::default()],
+			};
+
+			let mut out: Box<dyn Write> = match output {
+				Some(path) => Box::new(fs::File::create(path)?),
+				None => Box::new(std::io::stdout()),
+			};
+			writeln!(out, "{}", CSV_HEADER)?;
+			for scenario in scenarios {
+				writeln!(out, "{}", run(&kzg, &scenario)?.to_csv_row())?;
+			}
+		},
+		Command::Fuzz { runs, seed } => {
+			let failures = fuzz(&kzg, runs, seed)?;
+			for failure in &failures {
+				eprintln!("{}: {:?}", failure.invariant, failure.scenario);
+			}
+			if !failures.is_empty() {
+				bail!("{} of {} scenarios break an invariant", failures.len(), runs);
+			}
+			println!("{} scenarios hold the invariants", runs);
+		},
+	}
+
+	Ok(())
+}
@@ -0,0 +1,135 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Scenarios of the simulation.
+use anyhow::{ensure, Result};
+use melo_core_primitives::{
+	config::{BLOCK_AVAILABILITY_THRESHOLD, SAMPLES_PER_BLOCK},
+	reliability::BLOCK_FAILURE_PROBABILITY,
+};
+use serde::{Deserialize, Serialize};
+
+/// A file of scenarios, each in a `[[scenario]]` table.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ScenarioFile {
+	/// The scenarios.
+	#[serde(default, rename = "scenario")]
+	pub scenarios: Vec<Scenario>,
+}
+
+/// The parameters of a simulated block and of the parties sampling it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scenario {
+	/// The name of the scenario, the first column of its CSV row.
+	pub name: String,
+	/// The number of light clients sampling the block.
+	pub light_clients: u32,
+	/// The number of farmers fetching their assigned rows and columns of the block.
+	pub farmers: u32,
+	/// The number of rows of the block before extension, one per blob.
+	pub rows: u32,
+	/// The number of segments sampled by each light client.
+	pub samples_per_block: u32,
+	/// The number of consecutive samples a client has to fetch to consider the block available.
+	pub block_threshold: u32,
+	/// The probability, in parts per million, of a sample to be fetched although the data is
+	/// unavailable, the base of the confidence.
+	pub base_factor: u32,
+	/// The data withheld by the block producer.
+	pub withholding: Withholding,
+	/// The probability of a request for a segment to be lost.
+	pub loss: f64,
+	/// The number of trials of the scenario.
+	pub trials: u32,
+	/// The seed of the withholding, the losses and the farmers of the trials.
+	pub seed: u64,
+	/// Whether the rows are recovered with the erasure coding, instead of only counting their
+	/// segments. The recovery is the costliest part of a trial.
+	pub kzg_recovery: bool,
+}
+
+impl Default for Scenario {
+	fn default() -> Self {
+		Scenario {
+			name: "default".into(),
+			light_clients: 20,
+			farmers: 10,
+			rows: 4,
+			samples_per_block: SAMPLES_PER_BLOCK as u32,
+			block_threshold: BLOCK_AVAILABILITY_THRESHOLD,
+			base_factor: BLOCK_FAILURE_PROBABILITY.deconstruct(),
+			withholding: Withholding::None,
+			loss: 0.0,
+			trials: 20,
+			seed: 0,
+			kzg_recovery: true,
+		}
+	}
+}
+
+impl Scenario {
+	/// Checks that the parameters are consistent.
+	pub fn validate(&self) -> Result<()> {
+		ensure!(self.rows > 0, "{}: a block has at least one row", self.name);
+		ensure!(self.trials > 0, "{}: at least one trial is required", self.name);
+		ensure!(
+			self.block_threshold > 0 && self.block_threshold <= self.samples_per_block,
+			"{}: the block threshold must be between 1 and the samples per block",
+			self.name
+		);
+		ensure!(self.base_factor < 1_000_000, "{}: the base factor must be below one", self.name);
+		ensure!((0.0..=1.0).contains(&self.loss), "{}: the loss is a probability", self.name);
+		if let Withholding::Random { fraction } = self.withholding {
+			ensure!(
+				(0.0..=1.0).contains(&fraction),
+				"{}: the withheld fraction is a probability",
+				self.name
+			);
+		}
+		Ok(())
+	}
+}
+
+/// The strategy of a block producer withholding data.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(tag = "strategy", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Withholding {
+	/// All the segments are served.
+	#[default]
+	None,
+	/// Each segment is withheld with the probability `fraction`.
+	Random { fraction: f64 },
+	/// One more than half of the segments of each of the first `rows` rows are withheld, the
+	/// fewest segments that make these rows unrecoverable.
+	MinimalRow { rows: u32 },
+	/// The first `count` extended columns are withheld.
+	Columns { count: u32 },
+	/// Segments are only served to the first `served_clients` light clients, and to no farmer.
+	Selective { served_clients: u32 },
+}
+
+impl Withholding {
+	/// Returns the name of the strategy, as written in scenario files.
+	pub fn name(&self) -> &'static str {
+		match self {
+			Withholding::None => "none",
+			Withholding::Random { .. } => "random",
+			Withholding::MinimalRow { .. } => "minimal_row",
+			Withholding::Columns { .. } => "columns",
+			Withholding::Selective { .. } => "selective",
+		}
+	}
+}
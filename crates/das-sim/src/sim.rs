@@ -0,0 +1,374 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Trials of the simulation.
+use crate::scenario::{Scenario, Withholding};
+use anyhow::{anyhow, Result};
+use melo_core_primitives::{
+	config::{Rows, FIELD_ELEMENTS_PER_SEGMENT, MATRIX},
+	reliability::{
+		AvailabilityThresholds, Permill, Reliability, ReliabilitySample, ReliabilityType,
+	},
+	AppLookup, KZGCommitment,
+};
+use melo_das_primitives::{config::FIELD_ELEMENTS_PER_BLOB, Position, Segment, KZG};
+use melo_erasure_coding::{bytes_to_segments, recovery::recovery_order_row_from_segments};
+use melo_proof_of_space::{
+	assignment::{assigned_columns, assigned_rows},
+	FarmerId, H256,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// The columns of the CSV rows of [`Summary::to_csv_row`].
+pub const CSV_HEADER: &str = "scenario,strategy,light_clients,farmers,rows,samples_per_block,\
+	block_threshold,base_factor,loss,trials,accept_rate,mean_confidence,recovered_rate,\
+	farmer_recovered_rate,unsafe_rate,false_reject_rate";
+
+/// The outcome of a trial.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrialOutcome {
+	/// Whether each light client considers the block available.
+	pub accepted: Vec<bool>,
+	/// The confidence of each light client, in parts per million.
+	pub confidence: Vec<u32>,
+	/// Whether every row of the block can be recovered from the segments served to the network.
+	pub recovered: bool,
+	/// Whether every row of the block can be recovered from the segments stored by the farmers,
+	/// which keep the data available once the block producer stops serving it.
+	pub farmer_recovered: bool,
+}
+
+impl TrialOutcome {
+	/// Returns whether a light client considers the block available although its data can not be
+	/// recovered, the failure the sampling parameters have to make unlikely.
+	pub fn is_unsafe(&self) -> bool {
+		!self.recovered && self.accepted.iter().any(|accepted| *accepted)
+	}
+}
+
+/// The aggregated outcomes of the trials of a scenario.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+	/// The scenario.
+	pub scenario: Scenario,
+	/// The share of the light clients considering the block available.
+	pub accept_rate: f64,
+	/// The mean confidence of the light clients, between 0 and 1.
+	pub mean_confidence: f64,
+	/// The share of the trials whose data can be recovered.
+	pub recovered_rate: f64,
+	/// The share of the trials whose data can be recovered from the farmers.
+	pub farmer_recovered_rate: f64,
+	/// The share of the trials in which a light client considers unrecoverable data available.
+	pub unsafe_rate: f64,
+	/// The share of the light clients rejecting recoverable data.
+	pub false_reject_rate: f64,
+}
+
+impl Summary {
+	/// Aggregates the `outcomes` of the trials of `scenario`.
+	pub fn new(scenario: &Scenario, outcomes: &[TrialOutcome]) -> Self {
+		let clients = outcomes.iter().map(|o| o.accepted.len()).sum::<usize>();
+		let accepted = outcomes.iter().flat_map(|o| &o.accepted).filter(|a| **a).count();
+		let confidence =
+			outcomes.iter().flat_map(|o| &o.confidence).map(|c| *c as f64).sum::<f64>();
+		let recovered = outcomes.iter().filter(|o| o.recovered).collect::<Vec<_>>();
+		let recovered_clients = recovered.iter().map(|o| o.accepted.len()).sum::<usize>();
+		let rejected = recovered.iter().flat_map(|o| &o.accepted).filter(|a| !**a).count();
+
+		Summary {
+			scenario: scenario.clone(),
+			accept_rate: ratio(accepted, clients),
+			mean_confidence: ratio(1, clients) * confidence / 1_000_000.0,
+			recovered_rate: ratio(recovered.len(), outcomes.len()),
+			farmer_recovered_rate: ratio(
+				outcomes.iter().filter(|o| o.farmer_recovered).count(),
+				outcomes.len(),
+			),
+			unsafe_rate: ratio(outcomes.iter().filter(|o| o.is_unsafe()).count(), outcomes.len()),
+			false_reject_rate: ratio(rejected, recovered_clients),
+		}
+	}
+
+	/// Returns the CSV row of the summary, with the columns of [`CSV_HEADER`].
+	pub fn to_csv_row(&self) -> String {
+		let s = &self.scenario;
+		format!(
+			"{},{},{},{},{},{},{},{},{},{},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6}",
+			s.name.replace(',', ";"),
+			s.withholding.name(),
+			s.light_clients,
+			s.farmers,
+			s.rows,
+			s.samples_per_block,
+			s.block_threshold,
+			s.base_factor,
+			s.loss,
+			s.trials,
+			self.accept_rate,
+			self.mean_confidence,
+			self.recovered_rate,
+			self.farmer_recovered_rate,
+			self.unsafe_rate,
+			self.false_reject_rate,
+		)
+	}
+}
+
+fn ratio(n: usize, total: usize) -> f64 {
+	if total == 0 {
+		0.0
+	} else {
+		n as f64 / total as f64
+	}
+}
+
+/// Runs the trials of `scenario`.
+pub fn run(kzg: &KZG, scenario: &Scenario) -> Result<Summary> {
+	scenario.validate()?;
+	let reference = reference_row(kzg, scenario.seed)?;
+	let outcomes = (0..scenario.trials)
+		.map(|trial| {
+			let mut rng = StdRng::seed_from_u64(scenario.seed.wrapping_add(trial as u64));
+			run_trial(kzg, scenario, &reference, &mut rng)
+		})
+		.collect::<Result<Vec<_>>>()?;
+	Ok(Summary::new(scenario, &outcomes))
+}
+
+/// Returns the extended segments of a row of pseudo-random data derived from `seed`.
+///
+/// The rows of a simulated block all hold the same data, the recovery of a row only depending on
+/// which of its segments are fetched.
+pub fn reference_row(kzg: &KZG, seed: u64) -> Result<Vec<Segment>> {
+	let mut rng = StdRng::seed_from_u64(seed);
+	let data = (0..1024).map(|_| rng.gen()).collect::<Vec<u8>>();
+	let mut segments =
+		bytes_to_segments(&data, FIELD_ELEMENTS_PER_BLOB, FIELD_ELEMENTS_PER_SEGMENT, kzg)
+			.map_err(|e| anyhow!(e))?;
+	segments.sort_by_key(|segment| segment.position.x);
+	Ok(segments)
+}
+
+/// Runs a trial of `scenario` on a block whose rows hold the segments `reference`.
+///
+/// The positions sampled by the light clients are drawn by [`ReliabilitySample::set_sample`]
+/// from the thread RNG, so that only the withholding, the losses and the farmers are derived from
+/// `rng`.
+pub fn run_trial(
+	kzg: &KZG,
+	scenario: &Scenario,
+	reference: &[Segment],
+	rng: &mut StdRng,
+) -> Result<TrialOutcome> {
+	let block = Block::new(scenario, rng);
+	let block_hash = H256(rng.gen());
+	// The segments served to the network, only those answered to the served light clients when
+	// the block producer is selective.
+	let mut published = match block.served_clients {
+		None => block.withheld.iter().map(|withheld| !withheld).collect::<Vec<_>>(),
+		Some(_) => vec![false; block.cells()],
+	};
+
+	let commitments = vec![KZGCommitment::default(); block.rows as usize];
+	let app_lookups = vec![AppLookup { app_id: 1, nonce: 1, count: scenario.rows as u16 }];
+	let thresholds =
+		AvailabilityThresholds { block: scenario.block_threshold, ..Default::default() };
+	let base_factor = Permill::from_parts(scenario.base_factor);
+
+	let mut accepted = Vec::with_capacity(scenario.light_clients as usize);
+	let mut confidence = Vec::with_capacity(scenario.light_clients as usize);
+	for client in 0..scenario.light_clients {
+		let mut reliability = Reliability::new(ReliabilityType::Block, &commitments);
+		reliability
+			.set_sample(
+				scenario.samples_per_block as usize,
+				&app_lookups,
+				Some(block_hash.as_bytes()),
+			)
+			.map_err(|e| anyhow!(e))?;
+
+		for sample in reliability.samples.iter_mut() {
+			if block.serves(Some(client), &sample.position) && !rng.gen_bool(scenario.loss) {
+				sample.set_success();
+				published[block.index(&sample.position)] = true;
+			}
+		}
+
+		accepted.push(reliability.is_available_with(&thresholds));
+		confidence.push(reliability.value_with(base_factor).unwrap_or_default());
+	}
+
+	let mut stored = vec![false; block.cells()];
+	for _ in 0..scenario.farmers {
+		let farmer_id = FarmerId(H256(rng.gen()));
+		let rows = assigned_rows(&farmer_id, &block_hash, block.rows);
+		let cols = assigned_columns(&farmer_id, &block_hash);
+		let positions = rows
+			.iter()
+			.flat_map(|&y| (0..block.cols).map(move |x| Position { x, y }))
+			.chain(cols.iter().flat_map(|&x| (0..block.rows).map(move |y| Position { x, y })));
+		for position in positions {
+			if block.serves(None, &position) && !rng.gen_bool(scenario.loss) {
+				stored[block.index(&position)] = true;
+			}
+		}
+	}
+
+	let kzg = if scenario.kzg_recovery { Some(kzg) } else { None };
+	let recover = |fetched: &[bool]| {
+		(0..scenario.rows).all(|y| {
+			let row = &fetched[(y * block.cols) as usize..((y + 1) * block.cols) as usize];
+			recover_row(reference, row, kzg)
+		})
+	};
+
+	Ok(TrialOutcome {
+		accepted,
+		confidence,
+		recovered: recover(&published),
+		farmer_recovered: recover(&stored),
+	})
+}
+
+/// Returns whether a row whose segments `fetched` are known can be recovered.
+///
+/// A row is recovered from its own segments, as the nodes do, which only requires any half of
+/// them. With `kzg`, the row is recovered from
+/// the fetched segments of `reference`, and checked against it.
+fn recover_row(reference: &[Segment], fetched: &[bool], kzg: Option<&KZG>) -> bool {
+	let count = fetched.iter().filter(|f| **f).count();
+	if count * 2 < fetched.len() {
+		return false
+	}
+	if count == fetched.len() {
+		return true
+	}
+
+	match kzg {
+		None => true,
+		Some(kzg) => {
+			let order = reference
+				.iter()
+				.zip(fetched)
+				.map(|(segment, fetched)| fetched.then(|| segment.clone()))
+				.collect::<Vec<_>>();
+			recovery_order_row_from_segments(&order, kzg)
+				.map(|row| row.iter().zip(reference).all(|(a, b)| a.content.data == b.content.data))
+				.unwrap_or(false)
+		},
+	}
+}
+
+/// The segments a block producer serves.
+struct Block {
+	/// The number of extended rows.
+	rows: u32,
+	/// The number of extended columns.
+	cols: u32,
+	/// Whether each segment is withheld, row by row.
+	withheld: Vec<bool>,
+	/// The number of light clients served, all of them if `None`.
+	served_clients: Option<u32>,
+}
+
+impl Block {
+	fn new(scenario: &Scenario, rng: &mut StdRng) -> Self {
+		let rows = scenario.rows * 2;
+		let cols = MATRIX.extended_cols().0;
+		let cells = MATRIX.cells(Rows(rows)).0 as usize;
+
+		let withheld = match scenario.withholding {
+			Withholding::None | Withholding::Selective { .. } => vec![false; cells],
+			Withholding::Random { fraction } =>
+				(0..cells).map(|_| rng.gen_bool(fraction)).collect(),
+			Withholding::MinimalRow { rows: withheld_rows } => (0..cells as u32)
+				.map(|i| i / cols < withheld_rows && i % cols <= cols / 2)
+				.collect(),
+			Withholding::Columns { count } => (0..cells as u32).map(|i| i % cols < count).collect(),
+		};
+		let served_clients = match scenario.withholding {
+			Withholding::Selective { served_clients } => Some(served_clients),
+			_ => None,
+		};
+
+		Block { rows, cols, withheld, served_clients }
+	}
+
+	fn cells(&self) -> usize {
+		self.withheld.len()
+	}
+
+	fn index(&self, position: &Position) -> usize {
+		(position.y * self.cols + position.x) as usize
+	}
+
+	/// Returns whether the segment at `position` is served to the light client `client`, or to
+	/// a farmer if `None`.
+	fn serves(&self, client: Option<u32>, position: &Position) -> bool {
+		let served = match (self.served_clients, client) {
+			(None, _) => true,
+			(Some(served), Some(client)) => client < served,
+			(Some(_), None) => false,
+		};
+		served && !self.withheld[self.index(position)]
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn scenario(withholding: Withholding) -> Scenario {
+		Scenario { withholding, trials: 3, kzg_recovery: false, ..Default::default() }
+	}
+
+	#[test]
+	fn test_honest_block_is_accepted_and_recovered() {
+		let summary = run(&KZG::default_embedded(), &scenario(Withholding::None)).unwrap();
+		assert_eq!(summary.accept_rate, 1.0);
+		assert_eq!(summary.recovered_rate, 1.0);
+		assert_eq!(summary.unsafe_rate, 0.0);
+		assert!(summary.mean_confidence > 0.99);
+	}
+
+	#[test]
+	fn test_withheld_rows_are_not_recovered() {
+		let summary =
+			run(&KZG::default_embedded(), &scenario(Withholding::MinimalRow { rows: 1 })).unwrap();
+		assert_eq!(summary.recovered_rate, 0.0);
+		assert!(summary.accept_rate > 0.0);
+
+		let summary =
+			run(&KZG::default_embedded(), &scenario(Withholding::Random { fraction: 1.0 }))
+				.unwrap();
+		assert_eq!(summary.accept_rate, 0.0);
+		assert_eq!(summary.unsafe_rate, 0.0);
+	}
+
+	#[test]
+	fn test_recover_row() {
+		let kzg = KZG::default_embedded();
+		let reference = reference_row(&kzg, 1).unwrap();
+		let cols = reference.len();
+
+		let half = (0..cols).map(|x| x % 2 == 0).collect::<Vec<_>>();
+		assert!(recover_row(&reference, &half, Some(&kzg)));
+
+		let mut too_few = half.clone();
+		too_few[0] = false;
+		assert!(!recover_row(&reference, &too_few, Some(&kzg)));
+		assert!(!recover_row(&reference, &too_few, None));
+	}
+}
@@ -0,0 +1,292 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deterministic test vectors of the data availability primitives.
+//!
+//! Each [`TestCase`] starts from pseudo-random bytes derived from a fixed seed and holds what the
+//! reference implementation computes from them: the commitments and proofs of the blobs, the
+//! batch proof of all the blobs, and a sample of extended segments with their multi-proofs.
+//! Alternative implementations, such as a JS or Go light client, can be checked against the
+//! JSON produced by [`generate`], and [`verify`] checks that vectors still match the reference.
+use anyhow::{anyhow, ensure, Context, Result};
+use melo_core_primitives::{
+	config::{EXTENDED_SEGMENTS_PER_BLOB, FIELD_ELEMENTS_PER_SEGMENT, SEGMENTS_PER_BLOB},
+	SidecarMetadata,
+};
+use melo_das_primitives::{
+	config::{BYTES_PER_BLOB, FIELD_ELEMENTS_PER_BLOB},
+	Position, Segment, KZG,
+};
+use melo_erasure_coding::bytes_to_segments;
+use serde::{Deserialize, Serialize};
+
+/// The version of the format of the test vectors.
+pub const VECTORS_VERSION: u32 = 1;
+
+/// The name, seed and length of the data of each test case.
+pub const CASES: &[(&str, u64, usize)] = &[
+	("single-byte", 1, 1),
+	("partial-blob", 2, 1000),
+	("full-blob", 3, BYTES_PER_BLOB),
+	("two-blobs", 4, BYTES_PER_BLOB + 31),
+];
+
+/// The columns of the segments sampled in each row: the first and last segments of the original
+/// data, and the first and last segments of the extension.
+const SAMPLED_COLUMNS: [usize; 4] =
+	[0, SEGMENTS_PER_BLOB - 1, SEGMENTS_PER_BLOB, EXTENDED_SEGMENTS_PER_BLOB - 1];
+
+/// A set of test vectors, with the parameters they were generated with.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TestVectors {
+	/// The version of the format.
+	pub version: u32,
+	/// The number of field elements of a blob.
+	pub field_elements_per_blob: u32,
+	/// The number of field elements of a segment.
+	pub field_elements_per_segment: u32,
+	/// The test cases.
+	pub cases: Vec<TestCase>,
+}
+
+/// The expected outputs of the primitives for some data. Bytes are hex encoded with a `0x` prefix.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TestCase {
+	/// The name of the case.
+	pub name: String,
+	/// The data.
+	pub data: String,
+	/// The commitment of each blob of the data.
+	pub commitments: Vec<String>,
+	/// The proof of each blob of the data.
+	pub proofs: Vec<String>,
+	/// The batch proof of all the blobs of the data.
+	pub batch_proof: String,
+	/// A sample of the extended segments of the data.
+	pub samples: Vec<SegmentVector>,
+}
+
+/// An extended segment with its multi-proof.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SegmentVector {
+	/// The column of the segment in the extended row.
+	pub x: u32,
+	/// The row of the segment, that is the index of its blob.
+	pub y: u32,
+	/// The field elements of the segment.
+	pub data: Vec<String>,
+	/// The multi-proof of the segment.
+	pub proof: String,
+}
+
+impl From<&Segment> for SegmentVector {
+	fn from(segment: &Segment) -> Self {
+		Self {
+			x: segment.position.x,
+			y: segment.position.y,
+			data: segment.content.data.iter().map(|scalar| to_hex(&scalar.to_bytes())).collect(),
+			proof: to_hex(&segment.content.proof.to_bytes()),
+		}
+	}
+}
+
+/// Encodes `bytes` to hex with a `0x` prefix.
+pub fn to_hex(bytes: &[u8]) -> String {
+	format!("0x{}", hex::encode(bytes))
+}
+
+/// Decodes hex with an optional `0x` prefix.
+pub fn from_hex(s: &str) -> Result<Vec<u8>> {
+	hex::decode(s.trim_start_matches("0x")).with_context(|| format!("Invalid hex {}", s))
+}
+
+/// Returns `len` pseudo-random bytes derived from `seed` with xorshift64*.
+pub fn case_data(seed: u64, len: usize) -> Vec<u8> {
+	let mut state = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
+	(0..len)
+		.map(|_| {
+			state ^= state >> 12;
+			state ^= state << 25;
+			state ^= state >> 27;
+			(state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+		})
+		.collect()
+}
+
+/// Computes the test case `name` of `data` with the reference implementation.
+pub fn generate_case(kzg: &KZG, name: &str, data: &[u8]) -> Result<TestCase> {
+	let metadata = SidecarMetadata::try_from_app_data(data, 0, 0).map_err(|e| anyhow!(e))?;
+	let batch =
+		SidecarMetadata::try_from_app_data_with_batch_proof(data, 0, 0).map_err(|e| anyhow!(e))?;
+	let segments =
+		bytes_to_segments(data, FIELD_ELEMENTS_PER_BLOB, FIELD_ELEMENTS_PER_SEGMENT, kzg)
+			.map_err(|e| anyhow!(e))?;
+
+	let samples = (0..metadata.commitments.len())
+		.flat_map(|y| SAMPLED_COLUMNS.iter().map(move |&x| Position { x: x as u32, y: y as u32 }))
+		.map(|position| {
+			segments
+				.iter()
+				.find(|segment| segment.position == position)
+				.map(SegmentVector::from)
+				.with_context(|| format!("Segment {:?} is missing", position))
+		})
+		.collect::<Result<Vec<_>>>()?;
+
+	Ok(TestCase {
+		name: name.to_string(),
+		data: to_hex(data),
+		commitments: metadata.commitments.iter().map(|c| to_hex(&c.to_bytes())).collect(),
+		proofs: metadata.proofs.iter().map(|p| to_hex(&p.to_bytes())).collect(),
+		batch_proof: to_hex(&batch.proofs[0].to_bytes()),
+		samples,
+	})
+}
+
+/// Generates the test vectors of all the [`CASES`].
+pub fn generate(kzg: &KZG) -> Result<TestVectors> {
+	let cases = CASES
+		.iter()
+		.map(|(name, seed, len)| generate_case(kzg, name, &case_data(*seed, *len)))
+		.collect::<Result<Vec<_>>>()?;
+
+	Ok(TestVectors {
+		version: VECTORS_VERSION,
+		field_elements_per_blob: FIELD_ELEMENTS_PER_BLOB as u32,
+		field_elements_per_segment: FIELD_ELEMENTS_PER_SEGMENT as u32,
+		cases,
+	})
+}
+
+/// Checks that `vectors` match the outputs of the reference implementation.
+///
+/// The data of each case is taken from the vectors rather than from [`CASES`], so that vectors
+/// of other data can be checked as well.
+///
+/// # Errors
+///
+/// Returns an error naming the first value that differs.
+pub fn verify(kzg: &KZG, vectors: &TestVectors) -> Result<()> {
+	ensure!(
+		vectors.version == VECTORS_VERSION,
+		"Unsupported version {}, expected {}",
+		vectors.version,
+		VECTORS_VERSION
+	);
+	ensure!(
+		vectors.field_elements_per_blob == FIELD_ELEMENTS_PER_BLOB as u32 &&
+			vectors.field_elements_per_segment == FIELD_ELEMENTS_PER_SEGMENT as u32,
+		"Vectors of {} field elements per blob and {} per segment, expected {} and {}",
+		vectors.field_elements_per_blob,
+		vectors.field_elements_per_segment,
+		FIELD_ELEMENTS_PER_BLOB,
+		FIELD_ELEMENTS_PER_SEGMENT
+	);
+
+	for case in vectors.cases.iter() {
+		let data = from_hex(&case.data)?;
+		let expected = generate_case(kzg, &case.name, &data)?;
+		check_case(case, &expected).with_context(|| format!("Case {}", case.name))?;
+	}
+
+	Ok(())
+}
+
+fn check_case(case: &TestCase, expected: &TestCase) -> Result<()> {
+	check_list("commitment", &case.commitments, &expected.commitments)?;
+	check_list("proof", &case.proofs, &expected.proofs)?;
+	ensure!(case.batch_proof == expected.batch_proof, "Batch proof differs");
+
+	ensure!(
+		case.samples.len() == expected.samples.len(),
+		"Expected {} samples, got {}",
+		expected.samples.len(),
+		case.samples.len()
+	);
+	for (sample, expected) in case.samples.iter().zip(expected.samples.iter()) {
+		ensure!(
+			sample.x == expected.x && sample.y == expected.y,
+			"Expected sample at ({}, {}), got ({}, {})",
+			expected.x,
+			expected.y,
+			sample.x,
+			sample.y
+		);
+		check_list(
+			&format!("element of sample ({}, {})", sample.x, sample.y),
+			&sample.data,
+			&expected.data,
+		)?;
+		ensure!(
+			sample.proof == expected.proof,
+			"Proof of sample ({}, {}) differs",
+			sample.x,
+			sample.y
+		);
+	}
+
+	Ok(())
+}
+
+fn check_list(what: &str, values: &[String], expected: &[String]) -> Result<()> {
+	ensure!(
+		values.len() == expected.len(),
+		"Expected {} {}s, got {}",
+		expected.len(),
+		what,
+		values.len()
+	);
+	for (i, (value, expected)) in values.iter().zip(expected).enumerate() {
+		ensure!(value.to_lowercase() == *expected, "{} {} differs", what, i);
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use melo_core_primitives::KZGCommitment;
+
+	#[test]
+	fn test_case_data_is_deterministic() {
+		assert_eq!(case_data(7, 64), case_data(7, 64));
+		assert_ne!(case_data(7, 64), case_data(8, 64));
+		assert_eq!(&case_data(7, 64)[..10], &case_data(7, 10)[..]);
+	}
+
+	#[test]
+	fn test_generate_and_verify() {
+		let kzg = KZG::default_embedded();
+		let vectors = generate(&kzg).unwrap();
+		assert_eq!(vectors.cases.len(), CASES.len());
+		assert_eq!(vectors.cases[3].commitments.len(), 2);
+		assert_eq!(vectors.cases[3].samples.len(), 2 * SAMPLED_COLUMNS.len());
+
+		// The vectors survive a JSON round trip.
+		let json = serde_json::to_string(&vectors).unwrap();
+		let vectors: TestVectors = serde_json::from_str(&json).unwrap();
+		verify(&kzg, &vectors).unwrap();
+
+		let mut tampered = vectors.clone();
+		tampered.cases[1].commitments[0] = to_hex(&KZGCommitment::default().to_bytes());
+		assert!(verify(&kzg, &tampered).is_err());
+
+		let mut tampered = vectors;
+		tampered.cases[2].samples[1].data[0] = tampered.cases[2].samples[0].data[1].clone();
+		assert!(verify(&kzg, &tampered).is_err());
+	}
+}
@@ -0,0 +1,58 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Command line generator and verifier of the DAS test vectors.
+use anyhow::Result;
+use melo_das_primitives::KZG;
+use melo_das_testvectors::{generate, verify, TestVectors};
+use std::{fs, path::PathBuf};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "melo-das-testvectors", about = "Test vectors of the DAS primitives.")]
+enum Command {
+	/// Generates the test vectors.
+	Generate {
+		/// The file to write the vectors to, standard output if not set.
+		#[structopt(long, parse(from_os_str))]
+		output: Option<PathBuf>,
+	},
+	/// Checks that test vectors match the reference implementation.
+	Verify {
+		/// The file to read the vectors from.
+		#[structopt(parse(from_os_str))]
+		input: PathBuf,
+	},
+}
+
+fn main() -> Result<()> {
+	let kzg = KZG::default_embedded();
+
+	match Command::from_args() {
+		Command::Generate { output } => {
+			let json = serde_json::to_string_pretty(&generate(&kzg)?)?;
+			match output {
+				Some(path) => fs::write(path, json)?,
+				None => println!("{}", json),
+			}
+		},
+		Command::Verify { input } => {
+			let vectors: TestVectors = serde_json::from_slice(&fs::read(&input)?)?;
+			verify(&kzg, &vectors)?;
+			println!("{} test cases match", vectors.cases.len());
+		},
+	}
+
+	Ok(())
+}
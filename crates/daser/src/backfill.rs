@@ -0,0 +1,296 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Sidecar backfill.
+//!
+//! A new full node only holds the data it observed after startup. Once the node is synced, the
+//! backfill task fetches the data of the last finalized blocks from the DAS network, verifies it
+//! against the commitments of the headers and stores it in the database, so that the node can
+//! serve it right away. The progress of the backfill is tracked by [`BackfillProgress`].
+use crate::{
+	network::verify_values, sample_key, Arc, Context, DasKv, DasNetworkOperations, KZGCommitment,
+	Position, Result, SamplingClient, EXTENDED_SEGMENTS_PER_BLOB,
+};
+use log::{info, warn};
+use melo_core_primitives::traits::HeaderWithCommitment;
+use melo_das_primitives::KZG;
+use melo_erasure_coding::recovery::recovery_blob_from_order_row;
+use sc_client_api::HeaderBackend;
+use sp_consensus::SyncOracle;
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+use std::{
+	sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+	time::Duration,
+};
+
+/// Interval between two checks of the sync state before the backfill starts.
+const SYNC_CHECK_INTERVAL: Duration = Duration::from_secs(6);
+
+/// A snapshot of the progress of the backfill.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BackfillStatus {
+	/// The first block to backfill.
+	pub from: u32,
+	/// The last block to backfill.
+	pub to: u32,
+	/// The number of blocks processed.
+	pub processed: u32,
+	/// The number of blocks whose data could not be fetched.
+	pub failed: u32,
+	/// The number of segments stored.
+	pub segments: u64,
+	/// Whether the backfill started.
+	pub started: bool,
+	/// Whether the backfill is done.
+	pub done: bool,
+}
+
+/// The progress of the backfill, shared between the backfill task and the RPC.
+#[derive(Debug, Default)]
+pub struct BackfillProgress {
+	from: AtomicU32,
+	to: AtomicU32,
+	processed: AtomicU32,
+	failed: AtomicU32,
+	segments: AtomicU64,
+	started: AtomicBool,
+	done: AtomicBool,
+}
+
+impl BackfillProgress {
+	/// Returns a snapshot of the progress.
+	pub fn status(&self) -> BackfillStatus {
+		BackfillStatus {
+			from: self.from.load(Ordering::Relaxed),
+			to: self.to.load(Ordering::Relaxed),
+			processed: self.processed.load(Ordering::Relaxed),
+			failed: self.failed.load(Ordering::Relaxed),
+			segments: self.segments.load(Ordering::Relaxed),
+			started: self.started.load(Ordering::Relaxed),
+			done: self.done.load(Ordering::Relaxed),
+		}
+	}
+
+	fn start(&self, from: u32, to: u32) {
+		self.from.store(from, Ordering::Relaxed);
+		self.to.store(to, Ordering::Relaxed);
+		self.started.store(true, Ordering::Relaxed);
+	}
+
+	fn record_block(&self, segments: Option<u64>) {
+		match segments {
+			Some(segments) => {
+				self.segments.fetch_add(segments, Ordering::Relaxed);
+			},
+			None => {
+				self.failed.fetch_add(1, Ordering::Relaxed);
+			},
+		}
+		self.processed.fetch_add(1, Ordering::Relaxed);
+	}
+
+	fn finish(&self) {
+		self.done.store(true, Ordering::Relaxed);
+	}
+}
+
+/// Backfills the data of the last `depth` finalized blocks once the node is synced.
+pub async fn start_backfill<Client, B, H, DB, D, SO>(
+	client: Arc<Client>,
+	das_client: Arc<SamplingClient<H, DB, D>>,
+	sync_oracle: SO,
+	depth: u32,
+	progress: Arc<BackfillProgress>,
+) where
+	B: BlockT,
+	B::Header: HeaderWithCommitment + Sync,
+	Client: HeaderBackend<B> + 'static,
+	DB: DasKv + Send + Sync,
+	H: HeaderWithCommitment + Send + Sync,
+	D: DasNetworkOperations + Send + Sync,
+	SO: SyncOracle + Send + Sync,
+	NumberFor<B>: Into<u32>,
+{
+	while sync_oracle.is_major_syncing() {
+		tokio::time::sleep(SYNC_CHECK_INTERVAL).await;
+	}
+
+	let to: u32 = client.info().finalized_number.into();
+	let from = to.saturating_sub(depth).saturating_add(1).max(1);
+	progress.start(from, to);
+	info!("📚 Backfilling the data of blocks {} to {}", from, to);
+
+	for number in from..=to {
+		let result = match client.hash(number.into()) {
+			Ok(Some(hash)) => client
+				.header(hash)
+				.map_err(Into::into)
+				.and_then(|header| header.context(format!("Header of block {} not found", number))),
+			Ok(None) => Err(anyhow::anyhow!("Hash of block {} not found", number)),
+			Err(e) => Err(e.into()),
+		};
+
+		let segments = match result {
+			Ok(header) => backfill_block(&das_client, &header).await,
+			Err(e) => Err(e),
+		};
+
+		match segments {
+			Ok(segments) => progress.record_block(Some(segments)),
+			Err(e) => {
+				warn!("⚠️ Failed to backfill the data of block {}: {:?}", number, e);
+				progress.record_block(None);
+			},
+		}
+	}
+
+	progress.finish();
+	let status = progress.status();
+	info!(
+		"📚 Backfilled {} segments of {} blocks, {} blocks failed",
+		status.segments, status.processed, status.failed
+	);
+}
+
+/// Fetches the data of the block of `header` and stores it in the database.
+///
+/// # Returns
+///
+/// Returns the number of segments stored.
+async fn backfill_block<Header, H, DB, D>(
+	das_client: &SamplingClient<H, DB, D>,
+	header: &Header,
+) -> Result<u64>
+where
+	Header: HeaderWithCommitment,
+	DB: DasKv + Send + Sync,
+	D: DasNetworkOperations + Send + Sync,
+{
+	let commitments = header.commitments().context("Header does not contain commitments.")?;
+
+	let mut stored = 0u64;
+	let mut start = 0usize;
+	for lookup in header.extension().app_lookup.iter() {
+		let end = start + lookup.count as usize;
+		let app_commitments = commitments.get(start..end).context("Commitments are missing.")?;
+		start = end;
+
+		let segments = das_client
+			.network
+			.fetch_app_segments(lookup.app_id, lookup.nonce, app_commitments)
+			.await?
+			.with_context(|| {
+				format!("Data of app {} nonce {} is not available", lookup.app_id, lookup.nonce)
+			})?;
+
+		das_client.store_app_segments(lookup.app_id, lookup.nonce, &segments).await;
+		stored += segments.len() as u64;
+	}
+
+	Ok(stored)
+}
+
+/// Reads the data of `app_id` and `nonce` from the segments stored in `db`.
+///
+/// Every row is verified against its commitment and recovered if some of its segments are
+/// missing. The returned bytes are padded to whole blobs.
+///
+/// # Returns
+///
+/// Returns `None` if any of the rows cannot be recovered from the stored segments.
+pub fn read_app_data(
+	db: &mut impl DasKv,
+	kzg: &KZG,
+	app_id: u32,
+	nonce: u32,
+	commitments: &[KZGCommitment],
+) -> Option<Vec<u8>> {
+	let mut bytes = Vec::new();
+
+	for (y, commitment) in commitments.iter().enumerate() {
+		let segments = (0..EXTENDED_SEGMENTS_PER_BLOB)
+			.map(|x| {
+				let position = Position { x: x as u32, y: y as u32 };
+				let value = db.get(&sample_key(app_id, nonce, &position))?;
+				verify_values(kzg, &[value], commitment, &position)
+			})
+			.collect::<Vec<_>>();
+
+		let blob = recovery_blob_from_order_row(&segments, kzg).ok()?;
+		bytes.extend(blob.to_bytes());
+	}
+
+	Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use codec::Encode;
+	use melo_core_primitives::{config::FIELD_ELEMENTS_PER_SEGMENT, SidecarMetadata};
+	use melo_das_db::mock_db::MockDb;
+	use melo_erasure_coding::bytes_to_segments;
+
+	#[test]
+	fn test_read_app_data() {
+		let kzg = KZG::default_embedded();
+		let bytes = vec![3u8; 1000];
+		let metadata = SidecarMetadata::try_from_app_data(&bytes, 1, 1).unwrap();
+		let segments = bytes_to_segments(
+			&bytes,
+			crate::FIELD_ELEMENTS_PER_BLOB,
+			FIELD_ELEMENTS_PER_SEGMENT,
+			&kzg,
+		)
+		.unwrap();
+
+		let mut db = MockDb::new();
+		assert_eq!(read_app_data(&mut db, &kzg, 1, 1, &metadata.commitments), None);
+
+		// Half of the segments are enough to recover the data.
+		for segment in segments.iter().step_by(2) {
+			db.set(&sample_key(1, 1, &segment.position), &segment.content.encode());
+		}
+		let data = read_app_data(&mut db, &kzg, 1, 1, &metadata.commitments).unwrap();
+		assert_eq!(&data[..bytes.len()], &bytes[..]);
+
+		// The segments are verified against the given commitments.
+		let other = SidecarMetadata::try_from_app_data(&vec![4u8; 1000], 1, 1).unwrap();
+		assert_eq!(read_app_data(&mut db, &kzg, 1, 1, &other.commitments), None);
+	}
+
+	#[test]
+	fn test_backfill_progress() {
+		let progress = BackfillProgress::default();
+		assert_eq!(progress.status(), BackfillStatus::default());
+
+		progress.start(5, 10);
+		progress.record_block(Some(32));
+		progress.record_block(None);
+		progress.finish();
+
+		assert_eq!(
+			progress.status(),
+			BackfillStatus {
+				from: 5,
+				to: 10,
+				processed: 2,
+				failed: 1,
+				segments: 32,
+				started: true,
+				done: true,
+			}
+		);
+	}
+}
@@ -0,0 +1,214 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Index of the blobs of the finalized blocks.
+//!
+//! Listing the blobs of a range of blocks from the chain means reading the body of every block
+//! and extracting its blob transactions. The transaction pool listener instead indexes the blobs
+//! of each block as it is finalized, in the database under the number of the block, and
+//! [`list_blobs`] pages through the index. Only the blocks finalized while the node runs are
+//! indexed.
+use crate::{DasKv, KZGCommitment};
+use codec::{Decode, Encode};
+use melo_core_primitives::BlobPlacement;
+use std::{fmt, ops::RangeInclusive, str::FromStr};
+
+/// The prefix of the keys of the index, followed by the big-endian number of each block.
+pub const BLOB_INDEX_PREFIX: &[u8] = b"das_blob_index";
+
+/// The maximum number of blocks read by a single call to [`list_blobs`].
+pub const MAX_SCANNED_BLOCKS: u32 = 10_000;
+
+/// Returns the key of the index entry of the block `number`.
+pub fn blob_index_key(number: u32) -> Vec<u8> {
+	[BLOB_INDEX_PREFIX, &number.to_be_bytes()].concat()
+}
+
+/// A blob of an indexed block.
+#[derive(Encode, Decode, Clone, Debug, PartialEq, Eq)]
+pub struct IndexedBlob {
+	/// The hash of the block including the blob.
+	pub block_hash: Vec<u8>,
+	/// The ID of the sidecar metadata of the blob.
+	pub id: [u8; 32],
+	/// The ID of the app.
+	pub app_id: u32,
+	/// The nonce of the submission.
+	pub nonce: u32,
+	/// The length of the data in bytes.
+	pub bytes_len: u32,
+	/// The first row of the blob in the block matrix.
+	pub start_row: u32,
+	/// The commitments of the rows of the blob.
+	pub commitments: Vec<KZGCommitment>,
+}
+
+impl IndexedBlob {
+	/// Returns the indexed blob of `placement`, included in the block `block_hash`.
+	pub fn new(block_hash: &[u8], placement: &BlobPlacement) -> Self {
+		let metadata = &placement.metadata;
+		Self {
+			block_hash: block_hash.to_vec(),
+			id: metadata.id(),
+			app_id: metadata.app_id,
+			nonce: metadata.nonce,
+			bytes_len: metadata.bytes_len,
+			start_row: placement.start_row,
+			commitments: metadata.commitments.clone(),
+		}
+	}
+}
+
+/// The position of a blob in the index, from which a listing resumes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlobCursor {
+	/// The number of the block.
+	pub block: u32,
+	/// The index of the blob in the block.
+	pub index: u32,
+}
+
+impl fmt::Display for BlobCursor {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}:{}", self.block, self.index)
+	}
+}
+
+impl FromStr for BlobCursor {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (block, index) = s.split_once(':').ok_or_else(|| format!("Invalid cursor {}", s))?;
+		Ok(Self {
+			block: block.parse().map_err(|_| format!("Invalid cursor {}", s))?,
+			index: index.parse().map_err(|_| format!("Invalid cursor {}", s))?,
+		})
+	}
+}
+
+/// Indexes the blobs of `placements`, included in the block `number` of hash `block_hash`.
+///
+/// Indexing a block again replaces its entry, blocks without blobs are not written.
+pub fn index_block(
+	db: &mut impl DasKv,
+	number: u32,
+	block_hash: &[u8],
+	placements: &[BlobPlacement],
+) {
+	if placements.is_empty() {
+		return
+	}
+	let blobs: Vec<IndexedBlob> = placements
+		.iter()
+		.map(|placement| IndexedBlob::new(block_hash, placement))
+		.collect();
+	db.set(&blob_index_key(number), &blobs.encode());
+}
+
+/// Returns the indexed blobs of the block `number`.
+pub fn indexed_blobs(db: &mut impl DasKv, number: u32) -> Vec<IndexedBlob> {
+	db.get(&blob_index_key(number))
+		.and_then(|bytes| Decode::decode(&mut &bytes[..]).ok())
+		.unwrap_or_default()
+}
+
+/// Returns up to `limit` indexed blobs of the blocks of `blocks`, of `app_id` if given, with the
+/// number of the block of each, starting at `cursor` if given.
+///
+/// At most [`MAX_SCANNED_BLOCKS`] blocks are read. The cursor to resume the listing from is
+/// returned along with the blobs, unless the range is exhausted.
+pub fn list_blobs(
+	db: &mut impl DasKv,
+	app_id: Option<u32>,
+	blocks: RangeInclusive<u32>,
+	limit: usize,
+	cursor: Option<BlobCursor>,
+) -> (Vec<(u32, IndexedBlob)>, Option<BlobCursor>) {
+	let start = cursor.unwrap_or(BlobCursor { block: *blocks.start(), index: 0 });
+	let end = *blocks.end();
+	let mut listed = Vec::new();
+
+	let mut block = start.block.max(*blocks.start());
+	let mut scanned = 0;
+	while block <= end {
+		if scanned == MAX_SCANNED_BLOCKS {
+			return (listed, Some(BlobCursor { block, index: 0 }))
+		}
+		scanned += 1;
+
+		let skip = if block == start.block { start.index as usize } else { 0 };
+		for (index, blob) in indexed_blobs(db, block).into_iter().enumerate().skip(skip) {
+			if app_id.map_or(false, |app_id| app_id != blob.app_id) {
+				continue
+			}
+			if listed.len() == limit {
+				return (listed, Some(BlobCursor { block, index: index as u32 }))
+			}
+			listed.push((block, blob));
+		}
+
+		block = match block.checked_add(1) {
+			Some(next) => next,
+			None => break,
+		};
+	}
+	(listed, None)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use melo_core_primitives::SidecarMetadata;
+	use melo_das_db::mock_db::MockDb;
+
+	fn placements(app_ids: &[u32]) -> Vec<BlobPlacement> {
+		let metadata = app_ids
+			.iter()
+			.map(|app_id| {
+				SidecarMetadata::new(*app_id, 100, 1, vec![KZGCommitment::default()], vec![])
+			})
+			.collect();
+		BlobPlacement::place(metadata, 0)
+	}
+
+	#[test]
+	fn test_list_blobs_pages() {
+		let mut db = MockDb::new();
+		index_block(&mut db, 1, &[1; 32], &placements(&[1, 2]));
+		index_block(&mut db, 3, &[3; 32], &placements(&[1]));
+		index_block(&mut db, 4, &[4; 32], &placements(&[]));
+
+		let (page, cursor) = list_blobs(&mut db, None, 0..=10, 2, None);
+		assert_eq!(page.iter().map(|(block, _)| *block).collect::<Vec<_>>(), vec![1, 1]);
+		assert_eq!(cursor, Some(BlobCursor { block: 3, index: 0 }));
+
+		let (page, cursor) = list_blobs(&mut db, None, 0..=10, 2, cursor);
+		assert_eq!(page.len(), 1);
+		assert_eq!(page[0].1.block_hash, vec![3; 32]);
+		assert_eq!(cursor, None);
+
+		let (page, _) = list_blobs(&mut db, Some(1), 0..=10, 10, None);
+		assert_eq!(page.iter().map(|(block, _)| *block).collect::<Vec<_>>(), vec![1, 3]);
+
+		let (page, _) = list_blobs(&mut db, None, 2..=10, 10, None);
+		assert_eq!(page.len(), 1);
+	}
+
+	#[test]
+	fn test_cursor_round_trip() {
+		let cursor = BlobCursor { block: 12, index: 3 };
+		assert_eq!(cursor.to_string().parse::<BlobCursor>(), Ok(cursor));
+		assert!("12".parse::<BlobCursor>().is_err());
+	}
+}
@@ -0,0 +1,285 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Circuit breaker of the DHT operations.
+//!
+//! When the DHT network is unreachable, each put or get waits for its query to time out, and the
+//! RPC calls and the listener waiting for them pile up. The [`CircuitBreaker`] counts the
+//! operations timing out in a row: past a threshold it opens, and the operations fail at once with
+//! [`DhtDegraded`] for a cooldown period. The first operation after the cooldown is let through,
+//! closing the breaker if it succeeds and opening it again if it times out.
+use anyhow::Result;
+use prometheus_endpoint::{register, Counter, Gauge, PrometheusError, Registry, U64};
+use std::{
+	fmt,
+	future::Future,
+	sync::Mutex,
+	time::{Duration, Instant},
+};
+
+/// Configuration of a [`CircuitBreaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BreakerConfig {
+	/// The time an operation may take before it counts as timed out.
+	pub timeout: Duration,
+	/// The number of operations timing out in a row opening the breaker.
+	pub failure_threshold: u32,
+	/// The time the breaker stays open.
+	pub cooldown: Duration,
+}
+
+impl Default for BreakerConfig {
+	fn default() -> Self {
+		Self {
+			timeout: Duration::from_secs(60),
+			failure_threshold: 5,
+			cooldown: Duration::from_secs(30),
+		}
+	}
+}
+
+/// The state of a [`CircuitBreaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+	/// The operations run.
+	Closed,
+	/// The operations fail at once until the cooldown is over.
+	Open,
+	/// The cooldown is over, the next operation decides whether the breaker closes.
+	HalfOpen,
+}
+
+/// The error of an operation short-circuited by an open [`CircuitBreaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DhtDegraded {
+	/// The time left before the breaker lets an operation through.
+	pub retry_in: Duration,
+}
+
+impl fmt::Display for DhtDegraded {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "DHT network degraded, retry in {}s", self.retry_in.as_secs())
+	}
+}
+
+impl std::error::Error for DhtDegraded {}
+
+/// Returns `true` if `error` comes from an operation short-circuited by a [`CircuitBreaker`].
+pub fn is_dht_degraded(error: &anyhow::Error) -> bool {
+	error.downcast_ref::<DhtDegraded>().is_some()
+}
+
+#[derive(Clone)]
+struct BreakerMetrics {
+	healthy: Gauge<U64>,
+	timeouts: Counter<U64>,
+	short_circuited: Counter<U64>,
+}
+
+impl BreakerMetrics {
+	fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			healthy: register(
+				Gauge::new(
+					"das_dht_healthy",
+					"Whether the DHT operations run (1) or are short-circuited by the breaker (0)",
+				)?,
+				registry,
+			)?,
+			timeouts: register(
+				Counter::new("das_dht_timeouts_total", "Total number of DHT operations timed out")?,
+				registry,
+			)?,
+			short_circuited: register(
+				Counter::new(
+					"das_dht_short_circuited_total",
+					"Total number of DHT operations failed at once by the open breaker",
+				)?,
+				registry,
+			)?,
+		})
+	}
+}
+
+#[derive(Default)]
+struct Failures {
+	in_a_row: u32,
+	open_until: Option<Instant>,
+}
+
+/// Circuit breaker of the DHT operations, shared by the services using the DHT network.
+pub struct CircuitBreaker {
+	config: BreakerConfig,
+	failures: Mutex<Failures>,
+	metrics: Option<BreakerMetrics>,
+}
+
+impl Default for CircuitBreaker {
+	fn default() -> Self {
+		Self::new(BreakerConfig::default())
+	}
+}
+
+impl fmt::Debug for CircuitBreaker {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("CircuitBreaker")
+			.field("config", &self.config)
+			.field("state", &self.state())
+			.finish()
+	}
+}
+
+impl CircuitBreaker {
+	/// Creates a closed breaker.
+	pub fn new(config: BreakerConfig) -> Self {
+		Self { config, failures: Default::default(), metrics: None }
+	}
+
+	/// Registers the health metrics of the breaker to `registry`.
+	pub fn with_registry(mut self, registry: &Registry) -> Result<Self, PrometheusError> {
+		let metrics = BreakerMetrics::register(registry)?;
+		metrics.healthy.set(1);
+		self.metrics = Some(metrics);
+		Ok(self)
+	}
+
+	/// Returns the state of the breaker.
+	pub fn state(&self) -> BreakerState {
+		match self.failures.lock().expect("Breaker lock poisoned").open_until {
+			Some(until) if Instant::now() < until => BreakerState::Open,
+			Some(_) => BreakerState::HalfOpen,
+			None => BreakerState::Closed,
+		}
+	}
+
+	/// Returns `true` if the operations are short-circuited.
+	pub fn is_open(&self) -> bool {
+		self.state() == BreakerState::Open
+	}
+
+	/// Runs `operation` unless the breaker is open, failing it with [`DhtDegraded`] if it is.
+	///
+	/// An operation taking longer than the timeout of the breaker is dropped and counts towards
+	/// opening it, any other outcome closes it.
+	pub async fn run<T>(&self, operation: impl Future<Output = Result<T>>) -> Result<T> {
+		if let Some(retry_in) = self.retry_in() {
+			if let Some(metrics) = &self.metrics {
+				metrics.short_circuited.inc();
+			}
+			return Err(DhtDegraded { retry_in }.into())
+		}
+
+		match tokio::time::timeout(self.config.timeout, operation).await {
+			Ok(result) => {
+				self.record(false);
+				result
+			},
+			Err(_) => {
+				self.record(true);
+				Err(anyhow::anyhow!("DHT operation timed out after {:?}", self.config.timeout))
+			},
+		}
+	}
+
+	/// Returns the time left before the breaker lets an operation through, `None` if it does.
+	fn retry_in(&self) -> Option<Duration> {
+		let failures = self.failures.lock().expect("Breaker lock poisoned");
+		failures
+			.open_until
+			.and_then(|until| until.checked_duration_since(Instant::now()))
+	}
+
+	/// Records the outcome of an operation.
+	fn record(&self, timed_out: bool) {
+		let mut failures = self.failures.lock().expect("Breaker lock poisoned");
+		if !timed_out {
+			*failures = Failures::default();
+		} else {
+			failures.in_a_row += 1;
+			// Once open, a single timeout after the cooldown opens the breaker again
+			if failures.in_a_row >= self.config.failure_threshold || failures.open_until.is_some() {
+				if failures.open_until.is_none() {
+					log::warn!(
+						"⚡ {} DHT operations timed out in a row, short-circuiting them for {:?}",
+						failures.in_a_row,
+						self.config.cooldown
+					);
+				}
+				failures.open_until = Some(Instant::now() + self.config.cooldown);
+			}
+		}
+
+		if let Some(metrics) = &self.metrics {
+			if timed_out {
+				metrics.timeouts.inc();
+			}
+			metrics.healthy.set(failures.open_until.is_none() as u64);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn breaker() -> CircuitBreaker {
+		CircuitBreaker::new(BreakerConfig {
+			timeout: Duration::from_millis(10),
+			failure_threshold: 2,
+			cooldown: Duration::from_millis(50),
+		})
+	}
+
+	#[test]
+	fn test_breaker_opens_after_timeouts() {
+		let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+		let breaker = breaker();
+		let stalled = || futures::future::pending::<Result<()>>();
+
+		assert!(runtime.block_on(breaker.run(stalled())).is_err());
+		assert_eq!(breaker.state(), BreakerState::Closed);
+		assert!(runtime.block_on(breaker.run(stalled())).is_err());
+		assert_eq!(breaker.state(), BreakerState::Open);
+
+		// Short-circuited, even if the operation would succeed
+		let error = runtime.block_on(breaker.run(async { Ok(()) })).unwrap_err();
+		assert!(is_dht_degraded(&error));
+
+		// Half-open after the cooldown, a timeout opens it again
+		std::thread::sleep(Duration::from_millis(60));
+		assert_eq!(breaker.state(), BreakerState::HalfOpen);
+		let error = runtime.block_on(breaker.run(stalled())).unwrap_err();
+		assert!(!is_dht_degraded(&error));
+		assert_eq!(breaker.state(), BreakerState::Open);
+
+		// A success after the cooldown closes it
+		std::thread::sleep(Duration::from_millis(60));
+		assert!(runtime.block_on(breaker.run(async { Ok(()) })).is_ok());
+		assert_eq!(breaker.state(), BreakerState::Closed);
+	}
+
+	#[test]
+	fn test_breaker_resets_on_success() {
+		let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+		let breaker = breaker();
+
+		assert!(runtime.block_on(breaker.run(futures::future::pending::<Result<()>>())).is_err());
+		// Errors other than timeouts do not count
+		assert!(runtime
+			.block_on(breaker.run(async { Err::<(), _>(anyhow::anyhow!("no peers")) }))
+			.is_err());
+		assert!(runtime.block_on(breaker.run(futures::future::pending::<Result<()>>())).is_err());
+		assert_eq!(breaker.state(), BreakerState::Closed);
+	}
+}
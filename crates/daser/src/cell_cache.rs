@@ -0,0 +1,208 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Memoized verification of cells.
+//!
+//! Verifying a cell against the commitment of its row takes a pairing, and popular cells, such as
+//! the rows of an app fetched by many clients, are verified over and over by the sampling and by
+//! the segment server. The [`CellCache`] remembers the content, data and proof, of the cells
+//! recently verified by their commitment and position, so that the same content verifies again
+//! without a pairing. Content differing from the remembered one is verified as usual.
+use crate::{network::verify_segment, KZGCommitment, Position, Segment, SegmentData};
+use codec::Encode;
+use lru::LruCache;
+use melo_core_primitives::decode_segment_value;
+use melo_das_primitives::KZG;
+use prometheus_endpoint::{register, Counter, PrometheusError, Registry, U64};
+use sp_core::hashing::blake2_256;
+use std::{fmt, num::NonZeroUsize, sync::Mutex};
+
+/// The default number of cells remembered as verified.
+pub const DEFAULT_CELL_CACHE_SIZE: usize = 16_384;
+
+#[derive(Clone)]
+struct CellCacheMetrics {
+	hits: Counter<U64>,
+	misses: Counter<U64>,
+}
+
+impl CellCacheMetrics {
+	fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			hits: register(
+				Counter::new(
+					"das_cell_cache_hits_total",
+					"Total number of cells verified from the cell cache",
+				)?,
+				registry,
+			)?,
+			misses: register(
+				Counter::new(
+					"das_cell_cache_misses_total",
+					"Total number of cells verified with a pairing",
+				)?,
+				registry,
+			)?,
+		})
+	}
+}
+
+/// The verified cells, by the hash of their commitment and position.
+pub struct CellCache {
+	cells: Mutex<LruCache<[u8; 32], SegmentData>>,
+	metrics: Option<CellCacheMetrics>,
+}
+
+impl Default for CellCache {
+	fn default() -> Self {
+		Self::new(DEFAULT_CELL_CACHE_SIZE)
+	}
+}
+
+impl fmt::Debug for CellCache {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let cells = self.cells.lock().expect("Lock is not poisoned; qed");
+		f.debug_struct("CellCache")
+			.field("len", &cells.len())
+			.field("cap", &cells.cap())
+			.finish()
+	}
+}
+
+impl CellCache {
+	/// Creates a cache remembering up to `capacity` cells.
+	pub fn new(capacity: usize) -> Self {
+		let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+		Self { cells: Mutex::new(LruCache::new(capacity)), metrics: None }
+	}
+
+	/// Registers the hit and miss counters of the cache to `registry`.
+	pub fn with_registry(mut self, registry: &Registry) -> Result<Self, PrometheusError> {
+		self.metrics = Some(CellCacheMetrics::register(registry)?);
+		Ok(self)
+	}
+
+	/// Returns the content of the cell at `position` verified against `commitment`, if it is
+	/// remembered.
+	pub fn get(&self, commitment: &KZGCommitment, position: &Position) -> Option<SegmentData> {
+		self.cells
+			.lock()
+			.expect("Lock is not poisoned; qed")
+			.get(&cell_key(commitment, position))
+			.cloned()
+	}
+
+	/// Returns `true` if `segment` verifies against `commitment`, without a pairing if the same
+	/// content was recently verified.
+	pub fn verify(&self, kzg: &KZG, segment: &Segment, commitment: &KZGCommitment) -> bool {
+		let key = cell_key(commitment, &segment.position);
+		if self.cells.lock().expect("Lock is not poisoned; qed").get(&key) == Some(&segment.content)
+		{
+			if let Some(metrics) = &self.metrics {
+				metrics.hits.inc();
+			}
+			return true
+		}
+
+		if let Some(metrics) = &self.metrics {
+			metrics.misses.inc();
+		}
+		let verified = verify_segment(kzg, segment, commitment);
+		if verified {
+			self.cells
+				.lock()
+				.expect("Lock is not poisoned; qed")
+				.put(key, segment.content.clone());
+		}
+		verified
+	}
+
+	/// Returns the first of `values`, the stored values of the segment at `position`, which
+	/// decodes to a segment verifying against `commitment`, as
+	/// [`verify_values`](crate::verify_values) does.
+	pub fn verify_values(
+		&self,
+		kzg: &KZG,
+		values: &[Vec<u8>],
+		commitment: &KZGCommitment,
+		position: &Position,
+	) -> Option<Segment> {
+		values
+			.iter()
+			.filter_map(|value| decode_segment_value(value))
+			.map(|content| Segment { position: position.clone(), content })
+			.find(|segment| self.verify(kzg, segment, commitment))
+	}
+}
+
+fn cell_key(commitment: &KZGCommitment, position: &Position) -> [u8; 32] {
+	blake2_256(&(commitment, position).encode())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{FIELD_ELEMENTS_PER_BLOB, SEGMENTS_PER_BLOB};
+	use melo_core_primitives::config::FIELD_ELEMENTS_PER_SEGMENT;
+	use melo_erasure_coding::{bytes_to_blobs, bytes_to_segments};
+
+	fn segments_and_commitment(kzg: &KZG) -> (Vec<Segment>, KZGCommitment) {
+		let bytes = (0..1000).map(|i| i as u8).collect::<Vec<_>>();
+		let segments =
+			bytes_to_segments(&bytes, FIELD_ELEMENTS_PER_BLOB, FIELD_ELEMENTS_PER_SEGMENT, kzg)
+				.unwrap();
+		let blobs = bytes_to_blobs(&bytes, FIELD_ELEMENTS_PER_BLOB).unwrap();
+		(segments, blobs[0].commit(kzg).unwrap())
+	}
+
+	#[test]
+	fn test_verified_cells_are_remembered() {
+		let kzg = KZG::default_embedded();
+		let (segments, commitment) = segments_and_commitment(&kzg);
+		let cache = CellCache::new(2);
+		let segment = &segments[1];
+		assert!(segment.verify(&kzg, &commitment, SEGMENTS_PER_BLOB).unwrap());
+
+		assert_eq!(cache.get(&commitment, &segment.position), None);
+		assert!(cache.verify(&kzg, segment, &commitment));
+		assert_eq!(cache.get(&commitment, &segment.position), Some(segment.content.clone()));
+		assert!(cache.verify(&kzg, segment, &commitment));
+
+		// Other content at the same position is verified, and fails.
+		let mut tampered = segment.clone();
+		tampered.content.data[0] = Default::default();
+		assert!(!cache.verify(&kzg, &tampered, &commitment));
+		assert_eq!(cache.get(&commitment, &segment.position), Some(segment.content.clone()));
+
+		// The least recently verified cells are forgotten.
+		assert!(cache.verify(&kzg, &segments[2], &commitment));
+		assert!(cache.verify(&kzg, &segments[3], &commitment));
+		assert_eq!(cache.get(&commitment, &segment.position), None);
+	}
+
+	#[test]
+	fn test_verify_values_skips_invalid_values() {
+		let kzg = KZG::default_embedded();
+		let (segments, commitment) = segments_and_commitment(&kzg);
+		let cache = CellCache::default();
+		let segment = &segments[0];
+
+		let values = vec![vec![1, 2, 3], segment.content.encode()];
+		assert_eq!(
+			cache.verify_values(&kzg, &values, &commitment, &segment.position),
+			Some(segment.clone())
+		);
+		assert_eq!(cache.verify_values(&kzg, &values, &commitment, &segments[1].position), None);
+	}
+}
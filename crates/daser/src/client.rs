@@ -12,14 +12,19 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 use crate::{
-	anyhow, Arc, Context, DasKv, DasNetworkOperations, KZGCommitment, Ok, Reliability,
-	ReliabilityId, Result, SAMPLES_PER_BLOCK,
+	anyhow,
+	resample::{now, record_confidence, ConfidencePoint},
+	sample_key,
+	weighting::{FailureHistory, SamplingWeights},
+	Arc, Context, DasKv, DasNetworkOperations, KZGCommitment, Ok, Reliability, ReliabilityId,
+	Result, SharedSamplingParams,
 };
 
 use codec::{Decode, Encode};
 use futures::lock::Mutex;
 use log::{debug, info};
 use melo_core_primitives::{
+	config::Rows,
 	reliability::{
 		LastProcessedBlock, ReliabilitySample, ReliabilityType, LATEST_PROCESSED_BLOCK_KEY,
 	},
@@ -41,6 +46,9 @@ where
 	/// The network used to fetch samples.
 	pub network: DaserNetwork,
 	database: Arc<Mutex<DB>>,
+	params: SharedSamplingParams,
+	weights: SamplingWeights,
+	failures: std::sync::Mutex<FailureHistory>,
 	_phantom: PhantomData<Header>,
 }
 
@@ -111,26 +119,125 @@ where
 {
 	/// Creates a new [`SamplingClient`] instance.
 	pub fn new(network: DaserNetwork, database: Arc<Mutex<DB>>) -> Self {
-		SamplingClient { network, database, _phantom: PhantomData }
+		SamplingClient {
+			network,
+			database,
+			params: Default::default(),
+			weights: Default::default(),
+			failures: Default::default(),
+			_phantom: PhantomData,
+		}
+	}
+
+	/// Samples with the parameters of `params`, which can be changed while the client runs.
+	pub fn with_params(mut self, params: SharedSamplingParams) -> Self {
+		self.params = params;
+		self
+	}
+
+	/// Draws the samples of the blocks with `weights` instead of uniformly.
+	pub fn with_weights(mut self, weights: SamplingWeights) -> Self {
+		self.weights = weights;
+		self
+	}
+
+	/// Returns the parameters the client samples with.
+	pub fn params(&self) -> &SharedSamplingParams {
+		&self.params
+	}
+
+	/// Returns the database the samples and segments are stored in.
+	pub fn database(&self) -> Arc<Mutex<DB>> {
+		self.database.clone()
+	}
+
+	/// Returns whether the samples saved for `id` show the data available.
+	///
+	/// Returns `false` if the data has not been sampled.
+	pub async fn is_available(&self, id: &ReliabilityId) -> bool {
+		let mut db_guard = self.database.lock().await;
+		let thresholds = self.params.get().thresholds();
+		id.get_confidence(&mut *db_guard)
+			.map_or(false, |confidence| confidence.is_available_with(&thresholds))
+	}
+
+	/// Stores the `segments` of the data of `app_id` and `nonce` in the database, so that the node
+	/// can serve them without fetching them again.
+	pub async fn store_app_segments(&self, app_id: u32, nonce: u32, segments: &[Segment]) {
+		let mut db_guard = self.database.lock().await;
+		for segment in segments {
+			db_guard.set(&sample_key(app_id, nonce, &segment.position), &segment.content.encode());
+		}
+	}
+
+	/// Samples the block again with the samples it was first sampled with, updating its confidence
+	/// and appending it to the confidence history of the block.
+	///
+	/// Returns the new confidence, or `None` if the block was never sampled.
+	pub async fn resample_block<Header>(&self, header: &Header) -> Result<Option<Reliability>>
+	where
+		Header: HeaderWithCommitment + Sync,
+	{
+		let block_hash = header.hash().encode();
+		let id = ReliabilityId::block_confidence(&block_hash);
+		let mut confidence = match id.get_confidence(&mut *self.database.lock().await) {
+			Some(confidence) if !confidence.samples.is_empty() => confidence,
+			_ => return Ok(None),
+		};
+
+		let sample_commitments = confidence
+			.samples
+			.iter()
+			.map(|sample| confidence.commitments.get(sample.position.y as usize).copied())
+			.collect::<Option<Vec<_>>>()
+			.context("Sample out of the commitments of the block")?;
+		for sample in confidence.samples.iter_mut() {
+			sample.is_availability = false;
+		}
+
+		self.sample(&id, &mut confidence, &sample_commitments).await?;
+		self.record_confidence(&block_hash, &confidence).await;
+		Ok(Some(confidence))
+	}
+
+	/// Appends `confidence` to the confidence history of the block `block_hash`.
+	async fn record_confidence(&self, block_hash: &[u8], confidence: &Reliability) {
+		let mut db_guard = self.database.lock().await;
+		let point = ConfidencePoint::new(confidence, now(), self.params.get().base_factor);
+		record_confidence(&mut *db_guard, block_hash, point);
 	}
 
 	/// Actually samples the network.
+	///
+	/// The samples not fetched within the timeout of the parameters count as failed.
 	async fn sample(
 		&self,
 		confidence_id: &ReliabilityId,
 		confidence: &mut Reliability,
 		commitments: &[KZGCommitment],
 	) -> Result<()> {
-		for (sample, commitment) in confidence.samples.iter_mut().zip(commitments.iter()) {
-			if self.network.fetch_sample(sample, commitment).await.is_some() {
-				sample.set_success();
-			} else {
-				debug!("Sampled failed: {:?}", sample.id);
+		let mut fetched = Vec::new();
+		let fetch = async {
+			for (sample, commitment) in confidence.samples.iter_mut().zip(commitments.iter()) {
+				if let Some(segment_data) = self.network.fetch_sample(sample, commitment).await {
+					sample.set_success();
+					fetched.push((sample.get_id().to_vec(), segment_data));
+				} else {
+					debug!("Sampled failed: {:?}", sample.id);
+				}
 			}
+		};
+		if tokio::time::timeout(self.params.get().timeout, fetch).await.is_err() {
+			debug!("Sampling of {:?} timed out", confidence_id.0);
 		}
 
 		let mut db_guard = self.database.lock().await;
 
+		// Cache the fetched segments, so that the offchain worker can verify the samples again.
+		for (id, segment_data) in fetched {
+			db_guard.set(&id, &segment_data.encode());
+		}
+
 		confidence.save(confidence_id, &mut *db_guard);
 
 		Ok(())
@@ -207,12 +314,28 @@ impl<H: HeaderWithCommitment + Sync, DB: DasKv + Send, D: DasNetworkOperations +
 			let mut confidence = Reliability::new(ReliabilityType::Block, &extended_commits);
 
 			let app_lookups = header.extension().app_lookup.clone();
+			let n = self.params.get().samples_per_block as usize;
 
-			let sample_commitments = confidence
-				.set_sample(SAMPLES_PER_BLOCK, &app_lookups, Some(&block_hash))
-				.map_err(|e| anyhow!(e))?;
+			let sample_commitments = if self.weights.is_uniform() {
+				confidence.set_sample(n, &app_lookups, Some(&block_hash))
+			} else {
+				let positions = self.weights.positions(
+					&mut rand::thread_rng(),
+					n,
+					Rows(extended_commits.len() as u32),
+					&app_lookups,
+					&self.failures.lock().unwrap_or_else(|e| e.into_inner()),
+				);
+				confidence.set_sample_positions(positions, &app_lookups, Some(&block_hash))
+			}
+			.map_err(|e| anyhow!(e))?;
 
 			self.sample(&id, &mut confidence, &sample_commitments).await?;
+			self.failures
+				.lock()
+				.unwrap_or_else(|e| e.into_inner())
+				.record(&confidence.samples);
+			self.record_confidence(&block_hash, &confidence).await;
 		}
 
 		let at = header.number();
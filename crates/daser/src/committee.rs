@@ -0,0 +1,135 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Bookkeeping of the sidecars stored by a member of a data availability committee.
+//!
+//! The sidecars mirrored to a member may never be included in a block, so they are not pruned
+//! along with the data of the blocks. Each of them is instead recorded under the number of the last
+//! finalized block when it was stored, and the pruner removes it along with the data of that
+//! block, once the retention period is over. Until then, its bytes count towards the usage of the
+//! node which mirrored it, which the member bounds.
+use crate::{prune::PruneReport, DasKv};
+use codec::{Decode, Encode};
+use melo_core_primitives::sidecar_key;
+
+/// The prefix of the keys of the sidecars stored at each block, followed by the big-endian number
+/// of the block.
+pub const COMMITTEE_STORED_PREFIX: &[u8] = b"das_committee_stored";
+
+/// The prefix of the keys of the bytes stored for each node, followed by the key of the node.
+pub const COMMITTEE_USAGE_PREFIX: &[u8] = b"das_committee_usage";
+
+/// The default number of bytes a member stores for each node, 1 GiB.
+pub const DEFAULT_COMMITTEE_QUOTA: u64 = 1 << 30;
+
+/// Returns the key of the sidecars stored at the block `number`.
+pub fn stored_key(number: u32) -> Vec<u8> {
+	[COMMITTEE_STORED_PREFIX, &number.to_be_bytes()].concat()
+}
+
+/// Returns the key of the bytes stored for the node of key `node`.
+pub fn usage_key(node: &[u8; 32]) -> Vec<u8> {
+	[COMMITTEE_USAGE_PREFIX, &node[..]].concat()
+}
+
+/// A sidecar stored for a committee.
+#[derive(Encode, Decode, Clone, Debug, PartialEq, Eq)]
+pub struct StoredSidecar {
+	/// The ID of the sidecar metadata.
+	pub id: [u8; 32],
+	/// The key of the node which mirrored the sidecar.
+	pub node: [u8; 32],
+	/// The size of the stored value.
+	pub bytes: u64,
+}
+
+/// Returns the bytes stored for the node of key `node`.
+pub fn committee_usage(db: &mut impl DasKv, node: &[u8; 32]) -> u64 {
+	db.get(&usage_key(node))
+		.and_then(|bytes| Decode::decode(&mut &bytes[..]).ok())
+		.unwrap_or(0)
+}
+
+/// Records `sidecar`, stored at the block `number`, and counts its bytes towards its node.
+pub fn record_stored(db: &mut impl DasKv, number: u32, sidecar: StoredSidecar) {
+	let usage = committee_usage(db, &sidecar.node).saturating_add(sidecar.bytes);
+	db.set(&usage_key(&sidecar.node), &usage.encode());
+
+	let mut stored = stored_sidecars(db, number);
+	stored.push(sidecar);
+	db.set(&stored_key(number), &stored.encode());
+}
+
+/// Returns the sidecars stored at the block `number`.
+pub fn stored_sidecars(db: &mut impl DasKv, number: u32) -> Vec<StoredSidecar> {
+	db.get(&stored_key(number))
+		.and_then(|bytes| Decode::decode(&mut &bytes[..]).ok())
+		.unwrap_or_default()
+}
+
+/// Removes the sidecars stored at the block `number`, releasing their bytes from the usage of
+/// their nodes, and reports the values removed.
+pub fn release_stored(db: &mut impl DasKv, number: u32) -> PruneReport {
+	let mut report = PruneReport::default();
+	for sidecar in stored_sidecars(db, number) {
+		let key = sidecar_key(&sidecar.id);
+		if let Some(value) = db.get(&key) {
+			db.remove(&key);
+			report.values += 1;
+			report.bytes += value.len() as u64;
+		}
+		let usage = committee_usage(db, &sidecar.node).saturating_sub(sidecar.bytes);
+		if usage == 0 {
+			db.remove(&usage_key(&sidecar.node));
+		} else {
+			db.set(&usage_key(&sidecar.node), &usage.encode());
+		}
+	}
+	db.remove(&stored_key(number));
+	report
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use melo_das_db::mock_db::MockDb;
+
+	fn store(db: &mut MockDb, number: u32, id: u8, node: u8, bytes: usize) {
+		db.set(&sidecar_key(&[id; 32]), &vec![0u8; bytes]);
+		record_stored(
+			db,
+			number,
+			StoredSidecar { id: [id; 32], node: [node; 32], bytes: bytes as u64 },
+		);
+	}
+
+	#[test]
+	fn test_release_stored() {
+		let mut db = MockDb::new();
+		store(&mut db, 1, 1, 1, 10);
+		store(&mut db, 1, 2, 2, 20);
+		store(&mut db, 2, 3, 1, 30);
+		assert_eq!(committee_usage(&mut db, &[1; 32]), 40);
+		assert_eq!(committee_usage(&mut db, &[2; 32]), 20);
+
+		let report = release_stored(&mut db, 1);
+		assert_eq!(report, PruneReport { blocks: 0, values: 2, bytes: 30 });
+		assert!(!db.contains(&sidecar_key(&[1; 32])));
+		assert!(db.contains(&sidecar_key(&[3; 32])));
+		assert!(stored_sidecars(&mut db, 1).is_empty());
+		assert_eq!(committee_usage(&mut db, &[1; 32]), 30);
+		assert_eq!(committee_usage(&mut db, &[2; 32]), 0);
+		assert!(!db.contains(&usage_key(&[2; 32])));
+	}
+}
@@ -0,0 +1,166 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Availability-aware block import.
+//!
+//! [`AvailabilityBlockImport`] wraps the block import of the node and refuses the blocks received
+//! from the network whose data can not be shown available. The data of a block is available if the
+//! node sampled the blobs of every app of the block while they were in the transaction pool, or
+//! otherwise if a sampling round of the block succeeds. Blocks failing both are marked as bad.
+use crate::{
+	Arc, Context, DasKv, DasNetworkOperations, ReliabilityId, Result, Sampling, SamplingClient,
+};
+
+use codec::Encode;
+use log::{debug, warn};
+use melo_core_primitives::traits::HeaderWithCommitment;
+use sc_consensus::{BlockCheckParams, BlockImport, BlockImportParams, ImportResult};
+use sp_consensus::{BlockOrigin, CacheKeyId, Error as ConsensusError};
+use sp_runtime::traits::Block as BlockT;
+use std::collections::HashMap;
+
+/// A block import refusing the blocks whose data is unavailable.
+pub struct AvailabilityBlockImport<I, H, DB, D: DasNetworkOperations + Sync> {
+	inner: I,
+	das_client: Arc<SamplingClient<H, DB, D>>,
+}
+
+impl<I: Clone, H, DB, D: DasNetworkOperations + Sync> Clone
+	for AvailabilityBlockImport<I, H, DB, D>
+{
+	fn clone(&self) -> Self {
+		Self { inner: self.inner.clone(), das_client: self.das_client.clone() }
+	}
+}
+
+impl<I, H, DB, D> AvailabilityBlockImport<I, H, DB, D>
+where
+	H: HeaderWithCommitment + Sync,
+	DB: DasKv + Send,
+	D: DasNetworkOperations + Sync,
+{
+	/// Creates a new block import checking the availability of the blocks before passing them to
+	/// `inner`.
+	pub fn new(inner: I, das_client: Arc<SamplingClient<H, DB, D>>) -> Self {
+		Self { inner, das_client }
+	}
+
+	/// Returns whether the data of the block of `header` is available.
+	///
+	/// Blocks without blobs are always available.
+	async fn is_available<Header>(&self, header: &Header) -> Result<bool>
+	where
+		Header: HeaderWithCommitment + Sync,
+	{
+		let commitments = header.commitments().context("Commitments not found in the header")?;
+		if commitments.is_empty() {
+			return Ok(true)
+		}
+
+		if self.has_sidecars(header).await {
+			return Ok(true)
+		}
+
+		self.das_client.sample_block(header).await?;
+
+		let block_hash = HeaderWithCommitment::hash(header).encode();
+		Ok(self
+			.das_client
+			.is_available(&ReliabilityId::block_confidence(&block_hash))
+			.await)
+	}
+
+	/// Returns whether the node holds the sidecars of every app of the block of `header`, that is
+	/// whether it sampled them successfully while they were in the transaction pool.
+	async fn has_sidecars<Header>(&self, header: &Header) -> bool
+	where
+		Header: HeaderWithCommitment + Sync,
+	{
+		for lookup in header.extension().app_lookup.iter() {
+			let id = ReliabilityId::app_confidence(lookup.app_id, lookup.nonce);
+			if !self.das_client.is_available(&id).await {
+				return false
+			}
+		}
+		true
+	}
+}
+
+/// Returns whether the data of the blocks imported from `origin` has to be available.
+///
+/// The blocks authored locally are built from the transaction pool, and the blocks of the initial
+/// sync are too old for their data to be sampled, so only new blocks of the network are checked.
+fn requires_availability(origin: &BlockOrigin) -> bool {
+	matches!(origin, BlockOrigin::NetworkBroadcast | BlockOrigin::ConsensusBroadcast)
+}
+
+#[async_trait::async_trait]
+impl<B, I, H, DB, D> BlockImport<B> for AvailabilityBlockImport<I, H, DB, D>
+where
+	B: BlockT,
+	B::Header: HeaderWithCommitment + Sync,
+	I: BlockImport<B, Error = ConsensusError> + Send + Sync,
+	H: HeaderWithCommitment + Send + Sync,
+	DB: DasKv + Send + Sync,
+	D: DasNetworkOperations + Send + Sync,
+{
+	type Error = ConsensusError;
+	type Transaction = I::Transaction;
+
+	async fn check_block(
+		&mut self,
+		block: BlockCheckParams<B>,
+	) -> Result<ImportResult, Self::Error> {
+		self.inner.check_block(block).await
+	}
+
+	async fn import_block(
+		&mut self,
+		block: BlockImportParams<B, Self::Transaction>,
+		cache: HashMap<CacheKeyId, Vec<u8>>,
+	) -> Result<ImportResult, Self::Error> {
+		if requires_availability(&block.origin) {
+			let number = *HeaderWithCommitment::number(&block.header);
+			match self.is_available(&block.header).await {
+				Ok(true) => debug!("✅ Data of block {} is available", number),
+				Ok(false) => {
+					warn!("🚫 Refusing block {}: its data is not available", number);
+					return Ok(ImportResult::KnownBad)
+				},
+				Err(e) =>
+					return Err(ConsensusError::ClientImport(format!(
+						"Failed to check the data availability of block {}: {:?}",
+						number, e
+					))),
+			}
+		}
+
+		self.inner.import_block(block, cache).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_requires_availability() {
+		assert!(requires_availability(&BlockOrigin::NetworkBroadcast));
+		assert!(requires_availability(&BlockOrigin::ConsensusBroadcast));
+		assert!(!requires_availability(&BlockOrigin::Own));
+		assert!(!requires_availability(&BlockOrigin::NetworkInitialSync));
+		assert!(!requires_availability(&BlockOrigin::File));
+		assert!(!requires_availability(&BlockOrigin::Genesis));
+	}
+}
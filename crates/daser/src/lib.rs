@@ -24,10 +24,59 @@ pub use melo_das_db::traits::DasKv;
 pub use melo_das_primitives::{KZGCommitment, Position, Segment, SegmentData};
 pub use std::sync::Arc;
 
+pub mod backfill;
+pub mod blob_index;
+pub mod breaker;
+pub mod cell_cache;
 pub mod client;
+pub mod committee;
+pub mod import;
 pub mod network;
+pub mod outbox;
+pub mod prepublish;
+pub mod prune;
+pub mod repair;
+pub mod resample;
+pub mod sample_queue;
+pub mod sampling;
+pub mod solution_relay;
 pub mod tx_pool_handler;
+pub mod tx_priority;
+pub mod verifier;
+pub mod weighting;
+pub mod withholding;
 
+pub use backfill::{read_app_data, start_backfill, BackfillProgress, BackfillStatus};
+pub use blob_index::{index_block, list_blobs, BlobCursor, IndexedBlob, BLOB_INDEX_PREFIX};
+pub use breaker::{is_dht_degraded, BreakerConfig, BreakerState, CircuitBreaker, DhtDegraded};
+pub use cell_cache::{CellCache, DEFAULT_CELL_CACHE_SIZE};
 pub use client::{Sampling, SamplingClient, FetchData};
-pub use network::{DasNetworkOperations, DasNetworkServiceWrapper};
+pub use committee::{
+	committee_usage, record_stored, StoredSidecar, COMMITTEE_STORED_PREFIX, DEFAULT_COMMITTEE_QUOTA,
+};
+pub use import::AvailabilityBlockImport;
+pub use network::{verify_values, DasNetworkOperations, DasNetworkServiceWrapper, RowRepair};
+pub use outbox::{start_outbox_task, Outbox, OutboxConfig, OutboxEntry, OUTBOX_PREFIX};
+pub use prepublish::{
+	max_prepublish_timeout, publication_key, publication_receipt, settle_published, PrePublisher,
+	PublicationReceipt, SidecarPublisher, DEFAULT_PREPUBLISH_TIMEOUT, PUBLICATION_PREFIX,
+};
+pub use prune::{block_placements, start_prune_task, PruneConfig, PruneReport, Pruner};
+pub use repair::{start_repair_task, RepairConfig};
+pub use resample::{
+	confidence_history, start_resample_task, ConfidencePoint, ResampleConfig,
+	MAX_CONFIDENCE_HISTORY,
+};
+pub use sample_queue::{
+	SampleJob, SampleQueue, SampleQueueConfig, SampleQueueMetrics, DEFAULT_SAMPLE_CONCURRENCY,
+	DEFAULT_SAMPLE_QUEUE_CAPACITY,
+};
+pub use sampling::{SamplingParams, SharedSamplingParams, DEFAULT_SAMPLE_TIMEOUT};
+pub use solution_relay::{start_solution_relay, Candidate, PeerQuotas, SolutionRelayConfig};
 pub use tx_pool_handler::{start_tx_pool_listener, TPListenerParams};
+pub use tx_priority::{DataAwarePool, Inclusion};
+pub use verifier::{verify_parallel, SidecarVerifier, DEFAULT_VERIFIED_CACHE_SIZE};
+pub use weighting::{
+	parse_app_weight, FailureHistory, SamplingWeights, DEFAULT_UNIFORM_SHARE, FAILURE_WINDOW,
+};
+pub use withholding::{WithholdingCollector, DEFAULT_WITHHOLDING_CACHE_SIZE};
@@ -16,22 +16,36 @@
 //!
 //! This module contains the DasNetworkServiceWrapper struct which wraps the DasNetworkService. It
 //! provides methods for fetching values, preparing keys, and verifying values.
+//!
+//! The wrapper runs its DHT operations on any [`DasDht`], the DAS network service in a node and
+//! an [`melo_das_network_protocol::InMemoryDht`] in the tests.
+//!
+//! Segments are named by their local database keys, which the wrapper turns into DHT keys with
+//! the layout of [`melo_das_network::SegmentKey`], falling back to the legacy keys while they are
+//! accepted.
+//!
+//! The DHT operations of the wrapper run through a [`CircuitBreaker`], shared by its clones,
+//! which fails them at once with [`crate::DhtDegraded`] while the DHT network keeps timing out.
 use codec::Encode;
-use melo_erasure_coding::{bytes_to_segments, erasure_coding::extend_and_reorder_elements};
+use melo_erasure_coding::{
+	bytes_to_segments_parallel, erasure_coding::extend_and_reorder_elements,
+};
 
 use crate::{
-	anyhow, sample_key, sample_key_from_block, Arc, Context, KZGCommitment, Ok, Position, Result,
-	Sample, Segment, SegmentData, EXTENDED_SEGMENTS_PER_BLOB, FIELD_ELEMENTS_PER_BLOB,
-	SEGMENTS_PER_BLOB,
+	anyhow, breaker::CircuitBreaker, cell_cache::CellCache, sample_key, sample_key_from_block, Arc,
+	Context, KZGCommitment, Ok, Position, Result, Sample, Segment, SegmentData,
+	EXTENDED_SEGMENTS_PER_BLOB, FIELD_ELEMENTS_PER_BLOB, SEGMENTS_PER_BLOB,
 };
 use melo_core_primitives::{
-	config::FIELD_ELEMENTS_PER_SEGMENT, traits::HeaderWithCommitment, Decode,
+	config::FIELD_ELEMENTS_PER_SEGMENT, decode_segment_value, encode_segment_value,
+	traits::HeaderWithCommitment, Compression,
 };
-use melo_das_network::{KademliaKey, Service as DasNetworkService};
-use melo_das_primitives::KZG;
+use melo_das_network::{LegacyKeys, PeerId, Service as DasNetworkService};
+use melo_das_network_protocol::DasDht;
+use melo_das_primitives::{crypto::SCALAR_SAFE_BYTES, KZG};
 use melo_erasure_coding::{
 	extend_col::extend_segments_col as extend,
-	recovery::recovery_order_row_from_segments as recovery,
+	recovery::{recovery_blob_from_order_row, recovery_order_row_from_segments as recovery},
 };
 use sp_api::HeaderT;
 
@@ -63,11 +77,18 @@ pub trait DasNetworkOperations {
 	/// * `segments` - A slice of `Segment` to be put into the network.
 	/// * `app_id` - The ID of the application.
 	/// * `nonce` - A nonce value.
+	/// * `compression` - The compression of the values of the segments.
 	///
 	/// # Returns
 	///
 	/// Returns a `Result` indicating success or failure.
-	async fn put_app_segments(&self, segments: &[Segment], app_id: u32, nonce: u32) -> Result<()>;
+	async fn put_app_segments(
+		&self,
+		segments: &[Segment],
+		app_id: u32,
+		nonce: u32,
+		compression: Compression,
+	) -> Result<()>;
 
 	/// Puts bytes into the DAS network.
 	///
@@ -76,11 +97,18 @@ pub trait DasNetworkOperations {
 	/// * `bytes` - A slice of bytes to be put into the network.
 	/// * `app_id` - The ID of the application.
 	/// * `nonce` - A nonce value.
+	/// * `compression` - The compression of the values of the segments.
 	///
 	/// # Returns
 	///
 	/// Returns a `Result` indicating success or failure.
-	async fn put_bytes(&self, bytes: &[u8], app_id: u32, nonce: u32) -> Result<()>;
+	async fn put_bytes(
+		&self,
+		bytes: &[u8],
+		app_id: u32,
+		nonce: u32,
+		compression: Compression,
+	) -> Result<()>;
 
 	/// Fetches segment data from the DAS network.
 	///
@@ -159,6 +187,33 @@ pub trait DasNetworkOperations {
 	/// Returns a reference to the KZG instance.
 	fn kzg(&self) -> Arc<KZG>;
 
+	/// Returns the first of `values`, the stored values of the segment at `position`, which
+	/// decodes to a segment verifying against `commitment`.
+	fn verify_values(
+		&self,
+		values: &[Vec<u8>],
+		commitment: &KZGCommitment,
+		position: &Position,
+	) -> Option<Segment> {
+		verify_values(&self.kzg(), values, commitment, position)
+	}
+
+	/// Returns `true` if the DHT operations are short-circuited because the DHT network keeps
+	/// timing out.
+	fn is_degraded(&self) -> bool {
+		false
+	}
+
+	/// Returns the peers among the closest to the segment of the local key `key` which return it
+	/// over the segment protocol, acknowledging that they store it. Only the peers returning a
+	/// segment that verifies against the `commitment` of its row at `position` are counted.
+	async fn storage_acks(
+		&self,
+		key: &[u8],
+		commitment: &KZGCommitment,
+		position: &Position,
+	) -> Result<Vec<PeerId>>;
+
 	/// Removes records from the DAS network.
 	///
 	/// # Arguments
@@ -224,36 +279,217 @@ pub trait DasNetworkOperations {
 	) -> Result<(Vec<Option<Segment>>, Vec<usize>, bool)>
 	where
 		Header: HeaderWithCommitment + std::marker::Sync;
+
+	/// Fetches the application data of the given app and nonce from the DAS network.
+	///
+	/// Every row is verified against its commitment and recovered if some of its segments are
+	/// missing. The returned bytes are padded to whole blobs.
+	///
+	/// # Arguments
+	///
+	/// * `app_id` - The ID of the application.
+	/// * `nonce` - A nonce value.
+	/// * `commitments` - The commitments of the rows, in order.
+	///
+	/// # Returns
+	///
+	/// Returns `None` if any of the rows cannot be recovered.
+	async fn fetch_app_data(
+		&self,
+		app_id: u32,
+		nonce: u32,
+		commitments: &[KZGCommitment],
+	) -> Result<Option<Vec<u8>>>;
+
+	/// Fetches the segments of the application data of the given app and nonce from the DAS
+	/// network.
+	///
+	/// Every row is verified against its commitment and recovered if some of its segments are
+	/// missing. The positions of the segments are relative to the application data.
+	///
+	/// # Arguments
+	///
+	/// * `app_id` - The ID of the application.
+	/// * `nonce` - A nonce value.
+	/// * `commitments` - The commitments of the rows, in order.
+	///
+	/// # Returns
+	///
+	/// Returns `None` if any of the rows cannot be recovered.
+	async fn fetch_app_segments(
+		&self,
+		app_id: u32,
+		nonce: u32,
+		commitments: &[KZGCommitment],
+	) -> Result<Option<Vec<Segment>>>;
+
+	/// Repairs a row of the extended data of a block.
+	///
+	/// Fetches the segments of the row, and if some of them are missing while enough remain to
+	/// reconstruct the row, recovers the row and puts the missing segments back into the network.
+	///
+	/// # Arguments
+	///
+	/// * `header` - A reference to the header of the block.
+	/// * `index` - The index of the row in the extended data.
+	///
+	/// # Returns
+	///
+	/// Returns the state of the row, see [`RowRepair`].
+	async fn repair_row<Header>(&self, header: &Header, index: u32) -> Result<RowRepair>
+	where
+		Header: HeaderWithCommitment + std::marker::Sync;
+}
+
+/// The outcome of a row repair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowRepair {
+	/// Every segment of the row is in the network.
+	Healthy,
+	/// The given number of missing segments were recovered and put back into the network.
+	Repaired(usize),
+	/// The given number of segments are missing, too many to recover the row.
+	Unrecoverable(usize),
 }
 
 /// DasNetworkServiceWrapper is a struct that wraps the DasNetworkService and KZG structs.
 /// It provides methods for fetching values, preparing keys, and verifying values.
 #[derive(Clone, Debug)]
 pub struct DasNetworkServiceWrapper {
-	network: Arc<DasNetworkService>,
+	network: Arc<dyn DasDht>,
+	service: Option<Arc<DasNetworkService>>,
 	/// The KZG instance.
 	pub kzg: Arc<KZG>,
+	legacy_keys: LegacyKeys,
+	breaker: Arc<CircuitBreaker>,
+	cells: Arc<CellCache>,
+	extend_threads: usize,
 }
 
 impl DasNetworkServiceWrapper {
 	/// Creates a new instance of DasNetworkServiceWrapper.
 	pub fn new(network: Arc<DasNetworkService>, kzg: Arc<KZG>) -> Self {
-		DasNetworkServiceWrapper { network, kzg }
+		let mut wrapper = Self::from_dht(network.clone(), kzg);
+		wrapper.service = Some(network);
+		wrapper
+	}
+
+	/// Creates a new instance of DasNetworkServiceWrapper running its DHT operations on `dht`.
+	pub fn from_dht(dht: Arc<dyn DasDht>, kzg: Arc<KZG>) -> Self {
+		DasNetworkServiceWrapper {
+			network: dht,
+			service: None,
+			kzg,
+			legacy_keys: Default::default(),
+			breaker: Default::default(),
+			cells: Default::default(),
+			extend_threads: 1,
+		}
+	}
+
+	/// Handles the DHT keys of the legacy layout as `legacy_keys` says.
+	pub fn with_legacy_keys(mut self, legacy_keys: LegacyKeys) -> Self {
+		self.legacy_keys = legacy_keys;
+		self
+	}
+
+	/// Runs the DHT operations through `breaker`.
+	pub fn with_breaker(mut self, breaker: Arc<CircuitBreaker>) -> Self {
+		self.breaker = breaker;
+		self
+	}
+
+	/// Remembers the cells it verifies in `cells`, which may be shared with the segment server.
+	pub fn with_cell_cache(mut self, cells: Arc<CellCache>) -> Self {
+		self.cells = cells;
+		self
+	}
+
+	/// Extends the rows of the data put into the DHT on up to `threads` threads, `0` for one per
+	/// core. The rows are extended one after the other by default.
+	pub fn with_extend_threads(mut self, threads: usize) -> Self {
+		self.extend_threads = threads;
+		self
+	}
+
+	/// Returns the wrapped DAS network service, `None` if the wrapper runs on another [`DasDht`].
+	pub fn service(&self) -> Option<&DasNetworkService> {
+		self.service.as_deref()
 	}
 
 	/// Fetches a segment of data from the network.
+	///
+	/// Falls back to the archive nodes when the segment is missing from the DHT, which is the case
+	/// of the segments of old blocks.
 	async fn fetch_value(
 		&self,
 		key: &[u8],
 		position: &Position,
 		commitment: &KZGCommitment,
 	) -> Option<SegmentData> {
-		let values = self.network.get_value(KademliaKey::new(&key)).await.ok()?;
-		self.verify_values(&values, commitment, position).map(|segment| segment.content)
+		let keys = self.legacy_keys.dht_keys(key);
+		for key in keys.iter() {
+			if let std::result::Result::Ok(values) =
+				self.breaker.run(self.network.get_value(key.clone())).await
+			{
+				if let Some(segment) = self.verify_values(&values, commitment, position) {
+					return Some(segment.content)
+				}
+			}
+		}
+		for key in keys.iter() {
+			if let std::result::Result::Ok(value) = self.network.get_archived_value(key).await {
+				if let Some(segment) = self.verify_values(&[value], commitment, position) {
+					return Some(segment.content)
+				}
+			}
+		}
+		None
 	}
 
-	/// Prepares keys for a given header.
-	pub fn prepare_keys<Header>(&self, header: &Header) -> Result<Vec<KademliaKey>>
+	/// Gets the values of the segments of the local keys `keys` from the DHT, in order.
+	///
+	/// The values missing under the current DHT key of a segment are fetched under its legacy key
+	/// if accepted.
+	async fn get_segment_values(&self, keys: &[Vec<u8>]) -> Result<Vec<Option<Vec<Vec<u8>>>>> {
+		let dht_keys = keys.iter().map(|key| self.legacy_keys.dht_keys(key)).collect::<Vec<_>>();
+		let current = dht_keys.iter().map(|keys| keys[0].clone()).collect::<Vec<_>>();
+		let mut values_set = self.breaker.run(self.network.get_values(&current)).await?;
+
+		let (missing, legacy): (Vec<_>, Vec<_>) = values_set
+			.iter()
+			.zip(dht_keys.iter())
+			.enumerate()
+			.filter(|(_, (values, _))| values.as_ref().map_or(true, Vec::is_empty))
+			.filter_map(|(i, (_, keys))| keys.get(1).map(|key| (i, key.clone())))
+			.unzip();
+		if !legacy.is_empty() {
+			let legacy_values = self.breaker.run(self.network.get_values(&legacy)).await?;
+			for (i, values) in missing.into_iter().zip(legacy_values) {
+				values_set[i] = values;
+			}
+		}
+
+		Ok(values_set)
+	}
+
+	/// Puts the values of the segments of the local keys of `values` into the DHT, under each of
+	/// their DHT keys.
+	async fn put_segment_values(&self, values: Vec<(Vec<u8>, Vec<u8>)>) -> Result<()> {
+		let values = values
+			.into_iter()
+			.flat_map(|(key, value)| {
+				self.legacy_keys
+					.dht_keys(&key)
+					.into_iter()
+					.map(move |dht_key| (dht_key, value.clone()))
+			})
+			.collect::<Vec<_>>();
+		self.breaker.run(self.network.put_values(values)).await
+	}
+
+	/// Prepares the local keys of the segments of a given header.
+	pub fn prepare_keys<Header>(&self, header: &Header) -> Result<Vec<Vec<u8>>>
 	where
 		Header: HeaderWithCommitment,
 	{
@@ -265,8 +501,7 @@ impl DasNetworkServiceWrapper {
 				(0..EXTENDED_SEGMENTS_PER_BLOB).flat_map(move |x| {
 					(0..app_lookup.count).map(move |y| {
 						let position = Position { x: x as u32, y: y as u32 };
-						let key = sample_key(app_lookup.app_id, app_lookup.nonce, &position);
-						KademliaKey::new(&key)
+						sample_key(app_lookup.app_id, app_lookup.nonce, &position)
 					})
 				})
 			})
@@ -279,7 +514,7 @@ impl DasNetworkServiceWrapper {
 		header: &Header,
 		index: &[u32],
 		row_count: u32,
-	) -> Result<Vec<KademliaKey>>
+	) -> Result<Vec<Vec<u8>>>
 	where
 		Header: HeaderWithCommitment,
 	{
@@ -298,7 +533,7 @@ impl DasNetworkServiceWrapper {
 
 					if let Some((app_lookup, _)) = extension.get_lookup(y) {
 						let key = sample_key(app_lookup.app_id, app_lookup.nonce, &position);
-						keys.push(KademliaKey::new(&key));
+						keys.push(key);
 					} else {
 						return Err(anyhow!("prepare_cols_keys: get_lookup failed"))
 					}
@@ -310,7 +545,7 @@ impl DasNetworkServiceWrapper {
 				for x in 0..EXTENDED_SEGMENTS_PER_BLOB {
 					let position = Position { x: x as u32, y };
 					let key = sample_key_from_block(&header.hash().encode(), &position);
-					keys.push(KademliaKey::new(&key));
+					keys.push(key);
 				}
 			}
 		}
@@ -323,7 +558,7 @@ impl DasNetworkServiceWrapper {
 		header: &Header,
 		index: &[u32],
 		row_count: u32,
-	) -> Result<Vec<KademliaKey>>
+	) -> Result<Vec<Vec<u8>>>
 	where
 		Header: HeaderWithCommitment,
 	{
@@ -340,7 +575,7 @@ impl DasNetworkServiceWrapper {
 
 				if let Some((app_lookup, _)) = extension.get_lookup(y) {
 					let key = sample_key(app_lookup.app_id, app_lookup.nonce, &position);
-					keys.push(KademliaKey::new(&key));
+					keys.push(key);
 				} else {
 					return Err(anyhow!("prepare_rows_keys: get_lookup failed"))
 				}
@@ -349,7 +584,7 @@ impl DasNetworkServiceWrapper {
 			for y in row_count..row_count * 2 {
 				let position = Position { x, y };
 				let key = sample_key_from_block(&header.hash().encode(), &position);
-				keys.push(KademliaKey::new(&key));
+				keys.push(key);
 			}
 
 			if x > EXTENDED_SEGMENTS_PER_BLOB as u32 {
@@ -359,22 +594,25 @@ impl DasNetworkServiceWrapper {
 
 		Ok(keys)
 	}
+}
 
-	/// Verifies the values of a segment.
-	pub fn verify_values(
+#[async_trait::async_trait]
+impl DasNetworkOperations for DasNetworkServiceWrapper {
+	fn kzg(&self) -> Arc<KZG> {
+		self.kzg.clone()
+	}
+
+	fn verify_values(
 		&self,
 		values: &[Vec<u8>],
 		commitment: &KZGCommitment,
 		position: &Position,
 	) -> Option<Segment> {
-		verify_values(&self.kzg, values, commitment, position)
+		self.cells.verify_values(&self.kzg, values, commitment, position)
 	}
-}
 
-#[async_trait::async_trait]
-impl DasNetworkOperations for DasNetworkServiceWrapper {
-	fn kzg(&self) -> Arc<KZG> {
-		self.kzg.clone()
+	fn is_degraded(&self) -> bool {
+		self.breaker.is_open()
 	}
 
 	fn extend_segments_col(&self, segments: &[Segment]) -> Result<Vec<Segment>> {
@@ -395,40 +633,50 @@ impl DasNetworkOperations for DasNetworkServiceWrapper {
 		let values = segments
 			.iter()
 			.map(|segment| {
-				let key = KademliaKey::new(&sample_key_from_block(
-					&header.hash().encode(),
-					&segment.position,
-				));
+				let key = sample_key_from_block(&header.hash().encode(), &segment.position);
 				let value = segment.content.encode();
 				(key, value)
 			})
 			.collect::<Vec<_>>();
-		self.network.put_values(values).await?;
+		self.put_segment_values(values).await?;
 		Ok(())
 	}
 
-	async fn put_app_segments(&self, segments: &[Segment], app_id: u32, nonce: u32) -> Result<()> {
+	async fn put_app_segments(
+		&self,
+		segments: &[Segment],
+		app_id: u32,
+		nonce: u32,
+		compression: Compression,
+	) -> Result<()> {
 		let values = segments
 			.iter()
 			.map(|segment| {
-				let key = KademliaKey::new(&sample_key(app_id, nonce, &segment.position));
-				let value = segment.content.encode();
+				let key = sample_key(app_id, nonce, &segment.position);
+				let value = encode_segment_value(&segment.content, compression);
 				(key, value)
 			})
 			.collect::<Vec<_>>();
-		self.network.put_values(values).await?;
+		self.put_segment_values(values).await?;
 		Ok(())
 	}
 
-	async fn put_bytes(&self, bytes: &[u8], app_id: u32, nonce: u32) -> Result<()> {
-		let segments = bytes_to_segments(
+	async fn put_bytes(
+		&self,
+		bytes: &[u8],
+		app_id: u32,
+		nonce: u32,
+		compression: Compression,
+	) -> Result<()> {
+		let segments = bytes_to_segments_parallel(
 			bytes,
 			FIELD_ELEMENTS_PER_BLOB,
 			FIELD_ELEMENTS_PER_SEGMENT,
 			&self.kzg,
+			self.extend_threads,
 		)
 		.map_err(|e| anyhow!(e))?;
-		self.put_app_segments(&segments, app_id, nonce).await
+		self.put_app_segments(&segments, app_id, nonce, compression).await
 	}
 
 	async fn fetch_segment_data(
@@ -457,7 +705,7 @@ impl DasNetworkOperations for DasNetworkServiceWrapper {
 		let commitments = header.commitments().context("Header does not contain commitments.")?;
 		let keys = self.prepare_keys(header)?;
 
-		let values_set = self.network.get_values(&keys).await?;
+		let values_set = self.get_segment_values(&keys).await?;
 
 		rows_values_set_handler(&values_set, &commitments, &self.kzg, true)
 	}
@@ -476,7 +724,7 @@ impl DasNetworkOperations for DasNetworkServiceWrapper {
 			extend_and_reorder_elements(self.kzg.get_fs(), &commitments).map_err(|e| anyhow!(e))?;
 		let keys = self.prepare_rows_keys(header, index, commitments.len() as u32)?;
 
-		let values_set = self.network.get_values(&keys).await?;
+		let values_set = self.get_segment_values(&keys).await?;
 
 		rows_values_set_handler(&values_set, &commits_exted, &self.kzg, false)
 	}
@@ -496,16 +744,176 @@ impl DasNetworkOperations for DasNetworkServiceWrapper {
 			extend_and_reorder_elements(self.kzg.get_fs(), &commitments).map_err(|e| anyhow!(e))?;
 		let keys = self.prepare_cols_keys(header, index, commitments.len() as u32)?;
 
-		let values_set = self.network.get_values(&keys).await?;
+		let values_set = self.get_segment_values(&keys).await?;
 
 		// Custom handler for column-based data
 		cols_values_set_handler(&values_set, &commits_exted, &self.kzg, false)
 	}
 
+	async fn storage_acks(
+		&self,
+		key: &[u8],
+		commitment: &KZGCommitment,
+		position: &Position,
+	) -> Result<Vec<PeerId>> {
+		let key = self.legacy_keys.dht_keys(key).swap_remove(0);
+		let peers = self.breaker.run(self.network.get_closest_peers(&key)).await?;
+		let requests = peers.into_iter().map(|peer_id| {
+			let key = key.to_vec();
+			async move {
+				let values = self.network.request_segments(peer_id, vec![key]).await.ok()?;
+				let value = values.into_iter().next().flatten()?;
+				// A peer returning bytes that are not the segment does not store it.
+				self.verify_values(&[value], commitment, position).map(|_| peer_id)
+			}
+		});
+		Ok(futures::future::join_all(requests).await.into_iter().flatten().collect())
+	}
+
 	async fn remove_records(&self, keys: Vec<&[u8]>) -> Result<()> {
-		let keys = keys.into_iter().map(|key| KademliaKey::new(&key)).collect::<Vec<_>>();
+		let keys = keys
+			.into_iter()
+			.flat_map(|key| self.legacy_keys.dht_keys(key))
+			.collect::<Vec<_>>();
 		self.network.remove_records(&keys).await
 	}
+
+	async fn fetch_app_data(
+		&self,
+		app_id: u32,
+		nonce: u32,
+		commitments: &[KZGCommitment],
+	) -> Result<Option<Vec<u8>>> {
+		let keys = (0..commitments.len())
+			.flat_map(|y| {
+				(0..EXTENDED_SEGMENTS_PER_BLOB).map(move |x| {
+					let position = Position { x: x as u32, y: y as u32 };
+					sample_key(app_id, nonce, &position)
+				})
+			})
+			.collect::<Vec<_>>();
+
+		let values_set = self.get_segment_values(&keys).await?;
+
+		let bytes_per_blob = FIELD_ELEMENTS_PER_BLOB * SCALAR_SAFE_BYTES;
+		let mut bytes = Vec::with_capacity(commitments.len() * bytes_per_blob);
+
+		for (y, row_values) in values_set.chunks(EXTENDED_SEGMENTS_PER_BLOB).enumerate() {
+			let segments = row_values
+				.iter()
+				.enumerate()
+				.map(|(x, values)| {
+					values.as_ref().and_then(|values| {
+						let position = Position { x: x as u32, y: y as u32 };
+						self.verify_values(values, &commitments[y], &position)
+					})
+				})
+				.collect::<Vec<_>>();
+
+			match recovery_blob_from_order_row(&segments, &self.kzg) {
+				std::result::Result::Ok(blob) => bytes.extend(blob.to_bytes()),
+				Err(_) => return Ok(None),
+			}
+		}
+
+		Ok(Some(bytes))
+	}
+
+	async fn fetch_app_segments(
+		&self,
+		app_id: u32,
+		nonce: u32,
+		commitments: &[KZGCommitment],
+	) -> Result<Option<Vec<Segment>>> {
+		let keys = (0..commitments.len())
+			.flat_map(|y| {
+				(0..EXTENDED_SEGMENTS_PER_BLOB).map(move |x| {
+					let position = Position { x: x as u32, y: y as u32 };
+					sample_key(app_id, nonce, &position)
+				})
+			})
+			.collect::<Vec<_>>();
+
+		let values_set = self.get_segment_values(&keys).await?;
+
+		let mut all_segments = Vec::with_capacity(keys.len());
+
+		for (y, row_values) in values_set.chunks(EXTENDED_SEGMENTS_PER_BLOB).enumerate() {
+			let segments = row_values
+				.iter()
+				.enumerate()
+				.map(|(x, values)| {
+					values.as_ref().and_then(|values| {
+						let position = Position { x: x as u32, y: y as u32 };
+						self.verify_values(values, &commitments[y], &position)
+					})
+				})
+				.collect::<Vec<_>>();
+
+			if segments.iter().all(Option::is_some) {
+				all_segments.extend(segments.into_iter().flatten());
+				continue
+			}
+
+			match recovery(&segments, &self.kzg) {
+				std::result::Result::Ok(row) => all_segments.extend(row),
+				Err(_) => return Ok(None),
+			}
+		}
+
+		Ok(Some(all_segments))
+	}
+
+	async fn repair_row<Header>(&self, header: &Header, index: u32) -> Result<RowRepair>
+	where
+		Header: HeaderWithCommitment + std::marker::Sync,
+	{
+		let commitments = header.commitments().context("Header does not contain commitments.")?;
+
+		let commits_exted =
+			extend_and_reorder_elements(self.kzg.get_fs(), &commitments).map_err(|e| anyhow!(e))?;
+		let commitment = commits_exted.get(index as usize).context("Row index is too large.")?;
+		let keys = self.prepare_rows_keys(header, &[index], commitments.len() as u32)?;
+
+		let values_set = self.get_segment_values(&keys).await?;
+
+		let segments = values_set
+			.iter()
+			.enumerate()
+			.map(|(x, values)| {
+				values.as_ref().and_then(|values| {
+					self.verify_values(values, commitment, &Position { x: x as u32, y: index })
+				})
+			})
+			.collect::<Vec<_>>();
+
+		let missing = segments.iter().filter(|segment| segment.is_none()).count();
+		if missing == 0 {
+			return Ok(RowRepair::Healthy)
+		}
+		if missing > segments.len() / 2 {
+			return Ok(RowRepair::Unrecoverable(missing))
+		}
+
+		// The proofs of the recovered segments are computed again, so that the peers fetching them
+		// can verify them like the original ones.
+		let recovered = recovery(&segments, &self.kzg).map_err(|e| anyhow!(e))?;
+		let values = keys
+			.into_iter()
+			.zip(segments.iter())
+			.zip(recovered)
+			.filter(|((_, segment), _)| segment.is_none())
+			.map(|((key, _), segment)| {
+				match segment.verify(&self.kzg, commitment, SEGMENTS_PER_BLOB) {
+					std::result::Result::Ok(true) => Ok((key, segment.content.encode())),
+					_ => Err(anyhow!("Recovered segment {:?} does not verify", segment.position)),
+				}
+			})
+			.collect::<Result<Vec<_>>>()?;
+		self.put_segment_values(values).await?;
+
+		Ok(RowRepair::Repaired(missing))
+	}
 }
 
 fn cols_values_set_handler(
@@ -624,7 +1032,9 @@ fn rows_values_handler(
 	}
 }
 
-fn verify_values(
+/// Returns the first of `values`, the stored values of the segment at `position`, which decodes to
+/// a segment verifying against the `commitment` of its row.
+pub fn verify_values(
 	kzg: &KZG,
 	values: &[Vec<u8>],
 	commitment: &KZGCommitment,
@@ -633,18 +1043,11 @@ fn verify_values(
 	values
 		.iter()
 		.filter_map(|value| {
-			// Attempt to decode the value into a SegmentData
-			if let std::result::Result::Ok(segment_data) = SegmentData::decode(&mut &value[..]) {
+			// Attempt to decode the value, compressed or not, into a SegmentData
+			if let Some(segment_data) = decode_segment_value(value) {
 				let segment = Segment { position: position.clone(), content: segment_data };
-				// Safely check the segment and verify it
-				if let std::result::Result::Ok(checked_segment) = segment.checked() {
-					if let std::result::Result::Ok(vd) =
-						checked_segment.verify(kzg, commitment, SEGMENTS_PER_BLOB)
-					{
-						if vd {
-							return Some(segment)
-						}
-					}
+				if verify_segment(kzg, &segment, commitment) {
+					return Some(segment)
 				}
 			}
 			None
@@ -652,13 +1055,28 @@ fn verify_values(
 		.find(|segment| segment.position == *position)
 }
 
+/// Returns `true` if `segment` is well formed and verifies against the `commitment` of its row.
+pub(crate) fn verify_segment(kzg: &KZG, segment: &Segment, commitment: &KZGCommitment) -> bool {
+	// Safely check the segment and verify it
+	match segment.checked() {
+		std::result::Result::Ok(checked_segment) => matches!(
+			checked_segment.verify(kzg, commitment, SEGMENTS_PER_BLOB),
+			std::result::Result::Ok(true)
+		),
+		Err(_) => false,
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::{is_dht_degraded, BreakerConfig};
 	use codec::Encode;
+	use melo_das_network_protocol::{Fault, InMemoryDht, DEFAULT_REPLICATION};
 	use melo_das_primitives::Blob;
 	use melo_erasure_coding::{bytes_to_blobs, bytes_to_segments};
 	use rand::Rng;
+	use std::time::Duration;
 
 	fn random_bytes(len: usize) -> Vec<u8> {
 		let mut rng = rand::thread_rng();
@@ -885,4 +1303,126 @@ mod tests {
 
 		assert!(!is_availability);
 	}
+
+	fn runtime() -> tokio::runtime::Runtime {
+		tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap()
+	}
+
+	fn in_memory_network(dht: Arc<InMemoryDht>) -> DasNetworkServiceWrapper {
+		DasNetworkServiceWrapper::from_dht(dht, Arc::new(KZG::default_embedded()))
+	}
+
+	#[test]
+	fn test_in_memory_put_and_fetch() {
+		let runtime = runtime();
+		let dht = Arc::new(InMemoryDht::new(8));
+		let network = in_memory_network(dht.clone());
+
+		let bytes = random_bytes(500);
+		let blobs = bytes_to_blobs(&bytes, FIELD_ELEMENTS_PER_BLOB).unwrap();
+		let commitments = create_commitments(&blobs).unwrap();
+
+		runtime.block_on(network.put_bytes(&bytes, 1, 0, Compression::None)).unwrap();
+		let fetched = runtime.block_on(network.fetch_app_data(1, 0, &commitments)).unwrap();
+		assert_eq!(&fetched.unwrap()[..bytes.len()], &bytes[..]);
+
+		// The peers closest to a segment acknowledge storing it
+		let position = Position { x: 0, y: 0 };
+		let key = sample_key(1, 0, &position);
+		let acks = runtime
+			.block_on(network.storage_acks(&key, &commitments[0], &position))
+			.unwrap();
+		assert_eq!(acks.len(), DEFAULT_REPLICATION);
+
+		// The records are lost with the peers holding them
+		for peer_id in dht.peers() {
+			dht.set_online(&peer_id, false);
+		}
+		let fetched = runtime.block_on(network.fetch_app_data(1, 0, &commitments)).unwrap();
+		assert_eq!(fetched, None);
+		assert!(runtime.block_on(network.put_bytes(&bytes, 1, 1, Compression::None)).is_err());
+	}
+
+	#[test]
+	fn test_storage_acks_verify_the_segments() {
+		let runtime = runtime();
+		let dht = Arc::new(InMemoryDht::new(8));
+		let network = in_memory_network(dht.clone());
+
+		let bytes = random_bytes(500);
+		let blobs = bytes_to_blobs(&bytes, FIELD_ELEMENTS_PER_BLOB).unwrap();
+		let commitments = create_commitments(&blobs).unwrap();
+		runtime.block_on(network.put_bytes(&bytes, 1, 0, Compression::None)).unwrap();
+
+		let position = Position { x: 1, y: 0 };
+		let key = sample_key(1, 0, &position);
+		let dht_key = LegacyKeys::default().dht_keys(&key).swap_remove(0);
+		let holders = dht.holders(dht_key.as_ref());
+		assert_eq!(holders.len(), DEFAULT_REPLICATION);
+
+		// A peer returning wrong bytes does not acknowledge the segment
+		dht.corrupt(&holders[0], dht_key.as_ref(), random_bytes(64));
+		let acks = runtime
+			.block_on(network.storage_acks(&key, &commitments[0], &position))
+			.unwrap();
+		assert_eq!(acks.len(), DEFAULT_REPLICATION - 1);
+		assert!(!acks.contains(&holders[0]));
+
+		// Nor does a peer returning the segment of another position
+		let other = sample_key(1, 0, &Position { x: 2, y: 0 });
+		let other_key = LegacyKeys::default().dht_keys(&other).swap_remove(0);
+		let segment = runtime.block_on(dht.get_value(other_key)).unwrap().swap_remove(0);
+		dht.corrupt(&holders[1], dht_key.as_ref(), segment);
+		let acks = runtime
+			.block_on(network.storage_acks(&key, &commitments[0], &position))
+			.unwrap();
+		assert_eq!(acks.len(), DEFAULT_REPLICATION - 2);
+		assert!(!acks.contains(&holders[1]));
+	}
+
+	#[test]
+	fn test_in_memory_failure_does_not_degrade() {
+		let runtime = runtime();
+		let dht = Arc::new(InMemoryDht::new(4));
+		let network = in_memory_network(dht.clone());
+		let bytes = random_bytes(100);
+
+		dht.fail_next(1, Fault::Fail);
+		let error = runtime
+			.block_on(network.put_bytes(&bytes, 1, 0, Compression::None))
+			.unwrap_err();
+		assert!(!is_dht_degraded(&error));
+		assert!(runtime.block_on(network.put_bytes(&bytes, 1, 0, Compression::None)).is_ok());
+		assert!(!network.is_degraded());
+	}
+
+	#[test]
+	fn test_in_memory_stalls_open_the_breaker() {
+		let runtime = runtime();
+		let dht = Arc::new(InMemoryDht::new(4));
+		let breaker = CircuitBreaker::new(BreakerConfig {
+			timeout: Duration::from_millis(10),
+			failure_threshold: 2,
+			cooldown: Duration::from_secs(60),
+		});
+		let network = in_memory_network(dht.clone()).with_breaker(Arc::new(breaker));
+		let bytes = random_bytes(100);
+
+		dht.set_fault(Some(Fault::Stall));
+		for _ in 0..2 {
+			let error = runtime
+				.block_on(network.put_bytes(&bytes, 1, 0, Compression::None))
+				.unwrap_err();
+			assert!(!is_dht_degraded(&error));
+		}
+		assert!(network.is_degraded());
+
+		// Short-circuited without reaching the DHT
+		let operations = dht.operations();
+		let error = runtime
+			.block_on(network.put_bytes(&bytes, 1, 0, Compression::None))
+			.unwrap_err();
+		assert!(is_dht_degraded(&error));
+		assert_eq!(dht.operations(), operations);
+	}
 }
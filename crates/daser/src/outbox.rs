@@ -0,0 +1,383 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Outbox of the sidecars of the blocks authored locally.
+//!
+//! The author of a block is accountable for the availability of its data, but a put into the DHT
+//! does not tell whether any peer kept the segments. When the node authors a block with blobs,
+//! the sidecars of the block held in the database are written to the outbox, which is kept in the
+//! database too so that it survives restarts. The outbox task periodically republishes the
+//! segments of each pending sidecar, then asks the peers closest to a few random segments of the
+//! sidecar for them over the segment protocol. A peer returning a segment acknowledges that it
+//! stores the sidecar, and the sidecar is marked durable, leaving the outbox, once
+//! `required_acks` distinct peers acknowledged it.
+use crate::{
+	anyhow, prune::block_sidecars, sample_key, Arc, Context, DasKv, DasNetworkOperations, Position,
+	Result, SamplingClient, EXTENDED_SEGMENTS_PER_BLOB,
+};
+use codec::{Decode, Encode};
+use futures::StreamExt;
+use log::{debug, info, warn};
+use melo_core_primitives::{
+	sidecar_key,
+	traits::{Extractor, HeaderWithCommitment},
+	SidecarMetadata,
+};
+use rand::Rng;
+use sc_client_api::{BlockBackend, BlockchainEvents};
+use sp_api::ProvideRuntimeApi;
+use sp_consensus::BlockOrigin;
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT, NumberFor};
+use std::time::Duration;
+
+const LOG_TARGET: &str = "das_outbox";
+
+/// The prefix of the keys of the outbox. The ids of the pending sidecars are stored under the
+/// prefix itself, and the entry of each sidecar under the prefix followed by its id.
+pub const OUTBOX_PREFIX: &[u8] = b"das_outbox";
+
+/// Returns the key of the outbox entry of the sidecar `id`.
+pub fn outbox_key(id: &[u8]) -> Vec<u8> {
+	[OUTBOX_PREFIX, id].concat()
+}
+
+/// Configuration of the outbox task.
+#[derive(Clone, Debug)]
+pub struct OutboxConfig {
+	/// Interval between two republishing rounds.
+	pub interval: Duration,
+	/// Number of distinct peers that have to acknowledge a sidecar for it to be durable.
+	pub required_acks: u32,
+	/// Number of random segments of a sidecar whose peers are asked for acknowledgements in each
+	/// round.
+	pub probes: u32,
+	/// Maximum number of pending sidecars republished in each round.
+	pub sidecars_per_round: u32,
+}
+
+impl Default for OutboxConfig {
+	fn default() -> Self {
+		OutboxConfig {
+			interval: Duration::from_secs(60),
+			required_acks: 3,
+			probes: 4,
+			sidecars_per_round: 16,
+		}
+	}
+}
+
+/// The state of a sidecar in the outbox.
+#[derive(Encode, Decode, Clone, Debug, Default, PartialEq, Eq)]
+pub struct OutboxEntry {
+	/// The number of the block including the sidecar.
+	pub block: u32,
+	/// The number of times the sidecar was republished.
+	pub attempts: u32,
+	/// The distinct peers which acknowledged the sidecar, as the bytes of their peer ids.
+	pub acks: Vec<Vec<u8>>,
+	/// Whether enough peers acknowledged the sidecar.
+	pub durable: bool,
+}
+
+impl OutboxEntry {
+	/// Records the acknowledgement of `peer`, returning `false` if it was already recorded.
+	pub fn acknowledge(&mut self, peer: Vec<u8>) -> bool {
+		if self.acks.contains(&peer) {
+			return false
+		}
+		self.acks.push(peer);
+		true
+	}
+}
+
+/// Returns the ids of the sidecars pending in the outbox of `db`, oldest first.
+pub fn pending(db: &mut impl DasKv) -> Vec<[u8; 32]> {
+	db.get(OUTBOX_PREFIX)
+		.and_then(|bytes| Decode::decode(&mut &bytes[..]).ok())
+		.unwrap_or_default()
+}
+
+/// Returns the outbox entry of the sidecar `id`.
+pub fn entry(db: &mut impl DasKv, id: &[u8; 32]) -> Option<OutboxEntry> {
+	db.get(&outbox_key(id)).and_then(|bytes| Decode::decode(&mut &bytes[..]).ok())
+}
+
+/// Writes the sidecar `id`, included in block `block`, to the outbox of `db`. Returns `false` if
+/// the sidecar has an entry already.
+pub fn enqueue(db: &mut impl DasKv, id: &[u8; 32], block: u32) -> bool {
+	let key = outbox_key(id);
+	if db.contains(&key) {
+		return false
+	}
+	db.set(&key, &OutboxEntry { block, ..Default::default() }.encode());
+	let mut ids = pending(db);
+	ids.push(*id);
+	db.set(OUTBOX_PREFIX, &ids.encode());
+	true
+}
+
+/// Saves the outbox entry of the sidecar `id`, which leaves the pending sidecars once durable.
+pub fn update(db: &mut impl DasKv, id: &[u8; 32], entry: &OutboxEntry) {
+	db.set(&outbox_key(id), &entry.encode());
+	if entry.durable {
+		forget(db, id);
+	}
+}
+
+/// Removes the sidecar `id` from the pending sidecars, leaving its entry as it is.
+fn forget(db: &mut impl DasKv, id: &[u8; 32]) {
+	let ids = pending(db).into_iter().filter(|pending| pending != id).collect::<Vec<_>>();
+	db.set(OUTBOX_PREFIX, &ids.encode());
+}
+
+/// Republishes the sidecars of the outbox of a sampling client until they are durable.
+pub struct Outbox<H, DB, D: DasNetworkOperations + Sync> {
+	das_client: Arc<SamplingClient<H, DB, D>>,
+	config: OutboxConfig,
+}
+
+impl<H, DB, D> Outbox<H, DB, D>
+where
+	H: HeaderWithCommitment + Send + Sync,
+	DB: DasKv + Send + Sync,
+	D: DasNetworkOperations + Send + Sync,
+{
+	/// Creates the outbox of the sidecars held in the database of `das_client`.
+	pub fn new(das_client: Arc<SamplingClient<H, DB, D>>, config: OutboxConfig) -> Self {
+		Self { das_client, config }
+	}
+
+	/// Writes the sidecars of `sidecars`, included in block `block`, which the node holds to the
+	/// outbox. Returns the number of sidecars added.
+	pub async fn enqueue_block(&self, block: u32, sidecars: &[SidecarMetadata]) -> u32 {
+		let database = self.das_client.database();
+		let mut db = database.lock().await;
+		let mut added = 0;
+		for metadata in sidecars {
+			let id = metadata.id();
+			if !db.contains(&sidecar_key(&id)) {
+				warn!(
+					"⚠️ Authored block {} includes the blob of app {} nonce {} without its data",
+					block, metadata.app_id, metadata.nonce
+				);
+				continue
+			}
+			if enqueue(&mut *db, &id, block) {
+				added += 1;
+			}
+		}
+		added
+	}
+
+	/// Republishes the oldest pending sidecars, and collects their acknowledgements.
+	pub async fn round(&self) {
+		let database = self.das_client.database();
+		let ids = pending(&mut *database.lock().await);
+		for id in ids.iter().take(self.config.sidecars_per_round as usize) {
+			match self.republish(id).await {
+				Ok(entry) if entry.durable => info!(
+					"📮 Sidecar 0x{} of block {} is durable, acknowledged by {} peers",
+					hex(id),
+					entry.block,
+					entry.acks.len()
+				),
+				Ok(entry) => debug!(
+					target: LOG_TARGET,
+					"Sidecar 0x{} of block {} acknowledged by {} of {} peers after {} attempts",
+					hex(id),
+					entry.block,
+					entry.acks.len(),
+					self.config.required_acks,
+					entry.attempts
+				),
+				Err(e) => debug!(
+					target: LOG_TARGET,
+					"Failed to republish sidecar 0x{}: {:?}",
+					hex(id),
+					e
+				),
+			}
+		}
+	}
+
+	/// Republishes the sidecar `id` and asks the peers of some of its segments whether they store
+	/// them, saving the updated outbox entry.
+	async fn republish(&self, id: &[u8; 32]) -> Result<OutboxEntry> {
+		let database = self.das_client.database();
+		let (entry, encoded) = {
+			let mut db = database.lock().await;
+			(entry(&mut *db, id), db.get(&sidecar_key(id)))
+		};
+		let (mut entry, encoded) = match (entry, encoded) {
+			(Some(entry), Some(encoded)) => (entry, encoded),
+			// The data was pruned or evicted, there is nothing left to republish.
+			_ => {
+				forget(&mut *database.lock().await, id);
+				return Err(anyhow!("Sidecar no longer held"))
+			},
+		};
+
+		let (metadata, data) = <(SidecarMetadata, Vec<u8>)>::decode(&mut &encoded[..])
+			.context("Failed to decode the sidecar")?;
+		let bytes = metadata.decode_payload(&data).map_err(|e| anyhow!(e))?;
+
+		let network = &self.das_client.network;
+		entry.attempts += 1;
+		network
+			.put_bytes(&bytes, metadata.app_id, metadata.nonce, metadata.compression)
+			.await?;
+
+		let positions = probe_positions(
+			&mut rand::thread_rng(),
+			metadata.commitments.len() as u32,
+			self.config.probes,
+		);
+		for position in positions {
+			let key = sample_key(metadata.app_id, metadata.nonce, &position);
+			let commitment = &metadata.commitments[position.y as usize];
+			match network.storage_acks(&key, commitment, &position).await {
+				Ok(peers) =>
+					for peer in peers {
+						entry.acknowledge(peer.to_bytes());
+					},
+				Err(e) => debug!(target: LOG_TARGET, "Failed to collect acknowledgements: {:?}", e),
+			}
+		}
+		entry.durable = entry.acks.len() >= self.config.required_acks as usize;
+
+		update(&mut *database.lock().await, id, &entry);
+		Ok(entry)
+	}
+}
+
+/// Picks `probes` random positions among the extended segments of `blobs` blobs.
+fn probe_positions(rng: &mut impl Rng, blobs: u32, probes: u32) -> Vec<Position> {
+	if blobs == 0 {
+		return vec![]
+	}
+	(0..probes)
+		.map(|_| Position {
+			x: rng.gen_range(0..EXTENDED_SEGMENTS_PER_BLOB as u32),
+			y: rng.gen_range(0..blobs),
+		})
+		.collect()
+}
+
+fn hex(id: &[u8; 32]) -> String {
+	id.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Writes the sidecars of the block `hash` of `header`, authored by the node, to `outbox`.
+async fn enqueue_authored<Client, B, H, DB, D>(
+	client: &Client,
+	outbox: &Outbox<H, DB, D>,
+	hash: B::Hash,
+	header: &B::Header,
+) where
+	B: BlockT,
+	B::Header: HeaderWithCommitment,
+	Client: BlockBackend<B> + ProvideRuntimeApi<B>,
+	Client::Api: Extractor<B>,
+	H: HeaderWithCommitment + Send + Sync,
+	DB: DasKv + Send + Sync,
+	D: DasNetworkOperations + Send + Sync,
+	NumberFor<B>: Into<u32>,
+{
+	let number: u32 = (*header.number()).into();
+	match block_sidecars(client, hash, header) {
+		Ok(sidecars) if !sidecars.is_empty() => {
+			let added = outbox.enqueue_block(number, &sidecars).await;
+			debug!(
+				target: LOG_TARGET,
+				"Wrote {} sidecars of authored block {} to the outbox", added, number
+			);
+		},
+		Ok(_) => (),
+		Err(e) => warn!("⚠️ Failed to read the sidecars of authored block {}: {:?}", number, e),
+	}
+}
+
+/// Writes the sidecars of the blocks authored by the node to the outbox, and periodically
+/// republishes them until they are durable.
+pub async fn start_outbox_task<Client, B, H, DB, D>(client: Arc<Client>, outbox: Outbox<H, DB, D>)
+where
+	B: BlockT,
+	B::Header: HeaderWithCommitment,
+	Client: BlockchainEvents<B> + BlockBackend<B> + ProvideRuntimeApi<B> + 'static,
+	Client::Api: Extractor<B>,
+	H: HeaderWithCommitment + Send + Sync,
+	DB: DasKv + Send + Sync,
+	D: DasNetworkOperations + Send + Sync,
+	NumberFor<B>: Into<u32>,
+{
+	info!("📮 Starting the outbox of the authored sidecars.");
+
+	let mut import_notification_stream = client.import_notification_stream();
+	let mut interval = tokio::time::interval(outbox.config.interval);
+
+	loop {
+		tokio::select! {
+			Some(notification) = import_notification_stream.next() => {
+				if notification.origin == BlockOrigin::Own {
+					enqueue_authored(&*client, &outbox, notification.hash, &notification.header)
+						.await;
+				}
+			},
+			_ = interval.tick() => outbox.round().await,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use melo_das_db::mock_db::MockDb;
+	use rand::{rngs::StdRng, SeedableRng};
+
+	#[test]
+	fn test_enqueue_and_update() {
+		let mut db = MockDb::new();
+		let (a, b) = ([1u8; 32], [2u8; 32]);
+
+		assert!(enqueue(&mut db, &a, 7));
+		assert!(enqueue(&mut db, &b, 8));
+		assert!(!enqueue(&mut db, &a, 9));
+		assert_eq!(pending(&mut db), vec![a, b]);
+		assert_eq!(entry(&mut db, &a), Some(OutboxEntry { block: 7, ..Default::default() }));
+
+		let mut updated = entry(&mut db, &a).unwrap();
+		assert!(updated.acknowledge(vec![1]));
+		assert!(!updated.acknowledge(vec![1]));
+		update(&mut db, &a, &updated);
+		assert_eq!(pending(&mut db), vec![a, b]);
+		assert_eq!(entry(&mut db, &a).unwrap().acks, vec![vec![1]]);
+
+		updated.durable = true;
+		update(&mut db, &a, &updated);
+		assert_eq!(pending(&mut db), vec![b]);
+		assert!(entry(&mut db, &a).unwrap().durable);
+		// A durable sidecar is not written to the outbox again.
+		assert!(!enqueue(&mut db, &a, 7));
+	}
+
+	#[test]
+	fn test_probe_positions() {
+		let mut rng = StdRng::seed_from_u64(7);
+		assert!(probe_positions(&mut rng, 0, 4).is_empty());
+
+		let positions = probe_positions(&mut rng, 3, 16);
+		assert_eq!(positions.len(), 16);
+		assert!(positions.iter().all(|p| p.y < 3 && (p.x as usize) < EXTENDED_SEGMENTS_PER_BLOB));
+	}
+}
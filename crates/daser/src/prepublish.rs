@@ -0,0 +1,375 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Publication of the sidecars of a block before it is sealed.
+//!
+//! The author of a block is accountable for the availability of its data, yet a block proposer
+//! only sees transactions. When the [`DataAwarePool`](crate::DataAwarePool) handed to the
+//! proposer has a [`PrePublisher`], the ready blob transactions go through it before the block is
+//! built:
+//!
+//! - the sidecars held by the node are published to the DHT network, and a [`PublicationReceipt`]
+//!   is recorded for each, so that a sidecar is published once whatever the number of blocks
+//!   proposed while its transaction waits in the pool;
+//! - the data the node neither holds nor sampled as available is fetched from the DHT network.
+//!
+//! A transaction whose sidecars could not all be published or fetched within the timeout is
+//! [`Inclusion::Withheld`], left out of the block rather than included unavailable.
+use crate::{anyhow, Arc, Context, DasKv, DasNetworkOperations, Inclusion, Result};
+use codec::{Decode, Encode};
+use futures::{future::join_all, lock::Mutex};
+use log::{debug, warn};
+use melo_core_primitives::{sidecar_key, SidecarMetadata};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The default time the proposer waits for the sidecars of a transaction to be published, under
+/// the [`max_prepublish_timeout`] of 6 seconds slots proposed in half of their time.
+pub const DEFAULT_PREPUBLISH_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// The prefix of the keys of the publication receipts.
+pub const PUBLICATION_PREFIX: &[u8] = b"das_published";
+
+/// Returns the key of the publication receipt of the sidecar `id`.
+pub fn publication_key(id: &[u8]) -> Vec<u8> {
+	[PUBLICATION_PREFIX, id].concat()
+}
+
+/// The record of the publication of a sidecar by the author of a block.
+#[derive(Encode, Decode, Clone, Debug, PartialEq, Eq)]
+pub struct PublicationReceipt {
+	/// The time of the publication, in milliseconds since the Unix epoch.
+	pub published_at: u64,
+	/// The number of blobs published.
+	pub blobs: u32,
+}
+
+/// Returns the longest time the sidecars can be given to be published when a block is proposed in
+/// `proposal_time`.
+///
+/// The proposer only waits for the ready transactions an eighth of its time, and falls back to
+/// [`DataAwarePool::ready`](crate::DataAwarePool), which publishes nothing, past it. A third of
+/// that wait is left to the other steps of the transaction pool.
+pub fn max_prepublish_timeout(proposal_time: Duration) -> Duration {
+	proposal_time / 12
+}
+
+/// Returns the publication receipt of the sidecar `id`.
+pub fn publication_receipt(db: &mut impl DasKv, id: &[u8]) -> Option<PublicationReceipt> {
+	db.get(&publication_key(id))
+		.and_then(|bytes| Decode::decode(&mut &bytes[..]).ok())
+}
+
+/// Returns the inclusions of transactions classified by [`crate::inclusion`] when their sidecars
+/// can not be published nor fetched anymore, as when the proposer stopped waiting for them.
+///
+/// The blob transactions whose sidecars were not all published already, or whose data was not
+/// sampled as available, are [`Inclusion::Withheld`].
+pub fn settle_published(
+	db: &mut impl DasKv,
+	classified: Vec<(Inclusion, Vec<SidecarMetadata>)>,
+) -> Vec<Inclusion> {
+	classified
+		.into_iter()
+		.map(|(inclusion, sidecars)| match inclusion {
+			Inclusion::Boosted
+				if sidecars
+					.iter()
+					.all(|metadata| db.contains(&publication_key(&metadata.id()))) =>
+				Inclusion::Boosted,
+			Inclusion::Boosted | Inclusion::Deferred => Inclusion::Withheld,
+			inclusion => inclusion,
+		})
+		.collect()
+}
+
+/// Publishes and fetches the data of sidecars.
+#[async_trait::async_trait]
+pub trait SidecarPublisher: Send + Sync {
+	/// Publishes `data`, the payload of the sidecar `metadata`, to the DHT network.
+	async fn publish(&self, metadata: &SidecarMetadata, data: &[u8]) -> Result<()>;
+
+	/// Returns `true` if the data of the sidecar `metadata` can be fetched from the DHT network.
+	async fn is_retrievable(&self, metadata: &SidecarMetadata) -> Result<bool>;
+}
+
+#[async_trait::async_trait]
+impl<D> SidecarPublisher for D
+where
+	D: DasNetworkOperations + Send + Sync,
+{
+	async fn publish(&self, metadata: &SidecarMetadata, data: &[u8]) -> Result<()> {
+		let bytes = metadata.decode_payload(data).map_err(|e| anyhow!(e))?;
+		self.put_bytes(&bytes, metadata.app_id, metadata.nonce, metadata.compression)
+			.await
+	}
+
+	async fn is_retrievable(&self, metadata: &SidecarMetadata) -> Result<bool> {
+		let data = self
+			.fetch_app_data(metadata.app_id, metadata.nonce, &metadata.commitments)
+			.await?;
+		Ok(data.is_some())
+	}
+}
+
+/// Publishes the sidecars of the blob transactions ready for a block before it is built.
+#[derive(Clone)]
+pub struct PrePublisher {
+	publisher: Arc<dyn SidecarPublisher>,
+	timeout: Duration,
+}
+
+impl PrePublisher {
+	/// Creates a new [`PrePublisher`], giving the sidecars of each transaction `timeout` to be
+	/// published through `publisher`.
+	pub fn new(publisher: Arc<dyn SidecarPublisher>, timeout: Duration) -> Self {
+		Self { publisher, timeout }
+	}
+
+	/// Publishes or fetches the sidecars of transactions classified by [`crate::inclusion`] as
+	/// their `Inclusion` says, all at once.
+	///
+	/// Returns the inclusion of each transaction, [`Inclusion::Withheld`] for those whose data is
+	/// not out in time.
+	pub async fn settle<DB: DasKv>(
+		&self,
+		database: &Mutex<DB>,
+		classified: Vec<(Inclusion, Vec<SidecarMetadata>)>,
+	) -> Vec<Inclusion> {
+		join_all(classified.into_iter().map(|(inclusion, sidecars)| async move {
+			if sidecars.is_empty() {
+				return inclusion
+			}
+			match tokio::time::timeout(
+				self.timeout,
+				self.settle_one(database, inclusion, &sidecars),
+			)
+			.await
+			{
+				Ok(inclusion) => inclusion,
+				Err(_) => {
+					warn!(
+						"⏱️ The data of app {} nonce {} is not out after {:?}, leaving it out of the block",
+						sidecars[0].app_id, sidecars[0].nonce, self.timeout,
+					);
+					Inclusion::Withheld
+				},
+			}
+		}))
+		.await
+	}
+
+	async fn settle_one<DB: DasKv>(
+		&self,
+		database: &Mutex<DB>,
+		inclusion: Inclusion,
+		sidecars: &[SidecarMetadata],
+	) -> Inclusion {
+		match inclusion {
+			Inclusion::Boosted => {
+				let published =
+					join_all(sidecars.iter().map(|metadata| self.publish(database, metadata)))
+						.await;
+				for (metadata, result) in sidecars.iter().zip(published) {
+					if let Err(e) = result {
+						warn!(
+							"⚠️ Failed to publish the data of app {} nonce {}: {:?}",
+							metadata.app_id, metadata.nonce, e
+						);
+						return Inclusion::Withheld
+					}
+				}
+				Inclusion::Boosted
+			},
+			Inclusion::Deferred => {
+				let fetched = join_all(
+					sidecars.iter().map(|metadata| self.publisher.is_retrievable(metadata)),
+				)
+				.await;
+				if fetched.into_iter().all(|fetched| matches!(fetched, Ok(true))) {
+					Inclusion::Normal
+				} else {
+					debug!(
+						"🙈 The data of app {} nonce {} is not held nor retrievable",
+						sidecars[0].app_id, sidecars[0].nonce
+					);
+					Inclusion::Withheld
+				}
+			},
+			inclusion => inclusion,
+		}
+	}
+
+	/// Publishes the sidecar `metadata` held in `database`, unless it was published already.
+	async fn publish<DB: DasKv>(
+		&self,
+		database: &Mutex<DB>,
+		metadata: &SidecarMetadata,
+	) -> Result<()> {
+		let id = metadata.id();
+		if database.lock().await.contains(&publication_key(&id)) {
+			return Ok(())
+		}
+
+		let encoded = database.lock().await.get(&sidecar_key(&id)).context("Sidecar not held")?;
+		let (_, data) = <(SidecarMetadata, Vec<u8>)>::decode(&mut &encoded[..])?;
+		self.publisher.publish(metadata, &data).await?;
+
+		let receipt = PublicationReceipt {
+			published_at: SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.map_or(0, |elapsed| elapsed.as_millis() as u64),
+			blobs: metadata.blob_count() as u32,
+		};
+		database.lock().await.set(&publication_key(&id), &receipt.encode());
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use melo_das_db::mock_db::MockDb;
+	use std::sync::Mutex as SyncMutex;
+
+	#[derive(Default)]
+	struct Publisher {
+		published: SyncMutex<Vec<u32>>,
+		retrievable: bool,
+		fail: bool,
+		stall: bool,
+	}
+
+	#[async_trait::async_trait]
+	impl SidecarPublisher for Publisher {
+		async fn publish(&self, metadata: &SidecarMetadata, _data: &[u8]) -> Result<()> {
+			if self.stall {
+				futures::future::pending::<()>().await;
+			}
+			if self.fail {
+				return Err(anyhow!("DHT unreachable"))
+			}
+			self.published.lock().unwrap().push(metadata.app_id);
+			Ok(())
+		}
+
+		async fn is_retrievable(&self, _metadata: &SidecarMetadata) -> Result<bool> {
+			Ok(self.retrievable)
+		}
+	}
+
+	fn metadata(app_id: u32) -> SidecarMetadata {
+		SidecarMetadata {
+			app_id,
+			bytes_len: 0,
+			nonce: 0,
+			commitments: Vec::new(),
+			proofs: Vec::new(),
+			compression: Default::default(),
+			encryption: Default::default(),
+		}
+	}
+
+	fn hold(db: &mut MockDb, metadata: &SidecarMetadata) {
+		db.set(&sidecar_key(&metadata.id()), &(metadata, Vec::<u8>::new()).encode());
+	}
+
+	fn settle(
+		publisher: Publisher,
+		db: MockDb,
+		classified: Vec<(Inclusion, Vec<SidecarMetadata>)>,
+	) -> (Vec<Inclusion>, Arc<Publisher>, MockDb) {
+		let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+		let publisher = Arc::new(publisher);
+		let prepublisher = PrePublisher::new(publisher.clone(), Duration::from_millis(20));
+		let database = Mutex::new(db);
+		let inclusions = runtime.block_on(prepublisher.settle(&database, classified));
+		(inclusions, publisher, database.into_inner())
+	}
+
+	#[test]
+	fn test_settle_publishes_held_sidecars_once() {
+		let mut db = MockDb::new();
+		let held = metadata(1);
+		hold(&mut db, &held);
+
+		let classified =
+			vec![(Inclusion::Boosted, vec![held.clone()]), (Inclusion::Normal, Vec::new())];
+		let (inclusions, publisher, mut db) = settle(Publisher::default(), db, classified.clone());
+		assert_eq!(inclusions, vec![Inclusion::Boosted, Inclusion::Normal]);
+		assert_eq!(*publisher.published.lock().unwrap(), vec![1]);
+		assert_eq!(publication_receipt(&mut db, &held.id()).map(|receipt| receipt.blobs), Some(0));
+
+		// Published already
+		let (inclusions, publisher, _) = settle(Publisher::default(), db, classified);
+		assert_eq!(inclusions, vec![Inclusion::Boosted, Inclusion::Normal]);
+		assert!(publisher.published.lock().unwrap().is_empty());
+	}
+
+	#[test]
+	fn test_settle_withholds_unpublished_data() {
+		let mut db = MockDb::new();
+		let held = metadata(1);
+		hold(&mut db, &held);
+		let classified = vec![(Inclusion::Boosted, vec![held.clone()])];
+
+		let failing = Publisher { fail: true, ..Default::default() };
+		let (inclusions, _, mut db) = settle(failing, db, classified.clone());
+		assert_eq!(inclusions, vec![Inclusion::Withheld]);
+		assert_eq!(publication_receipt(&mut db, &held.id()), None);
+
+		let stalled = Publisher { stall: true, ..Default::default() };
+		let (inclusions, _, _) = settle(stalled, db, classified);
+		assert_eq!(inclusions, vec![Inclusion::Withheld]);
+	}
+
+	#[test]
+	fn test_settle_published_withholds_unpublished_data() {
+		let mut db = MockDb::new();
+		let (published, held) = (metadata(1), metadata(2));
+		hold(&mut db, &published);
+		hold(&mut db, &held);
+		let receipt = PublicationReceipt { published_at: 0, blobs: 0 };
+		db.set(&publication_key(&published.id()), &receipt.encode());
+
+		let classified = vec![
+			(Inclusion::Boosted, vec![published.clone()]),
+			(Inclusion::Boosted, vec![published, held]),
+			(Inclusion::Normal, vec![metadata(3)]),
+			(Inclusion::Deferred, vec![metadata(4)]),
+			(Inclusion::Normal, Vec::new()),
+		];
+		assert_eq!(
+			settle_published(&mut db, classified),
+			vec![
+				Inclusion::Boosted,
+				Inclusion::Withheld,
+				Inclusion::Normal,
+				Inclusion::Withheld,
+				Inclusion::Normal
+			]
+		);
+	}
+
+	#[test]
+	fn test_settle_fetches_deferred_data() {
+		let classified = vec![(Inclusion::Deferred, vec![metadata(1)])];
+
+		let retrievable = Publisher { retrievable: true, ..Default::default() };
+		let (inclusions, _, db) = settle(retrievable, MockDb::new(), classified.clone());
+		assert_eq!(inclusions, vec![Inclusion::Normal]);
+
+		let (inclusions, _, _) = settle(Publisher::default(), db, classified);
+		assert_eq!(inclusions, vec![Inclusion::Withheld]);
+	}
+}
@@ -0,0 +1,344 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pruning of the local data of old blocks.
+//!
+//! The data of a block only has to be kept available for the retention period of the parameters
+//! in use. The pruning task periodically removes the data of the finalized blocks older than the
+//! horizon, the last finalized block minus the retention period, from the database: the sidecars
+//! of their blob transactions along with their outbox entries, the segments of their rows, and
+//! their confidences along with the confidence history of the block, and the sidecars stored for
+//! a data availability committee while the block was the last finalized one.
+//! `das_pruneLocalData` prunes the blocks before a given one on demand.
+//!
+//! The blocks are pruned in order, and the first block not pruned yet is saved in the database so
+//! that pruning resumes where it stopped.
+use crate::{
+	anyhow, committee::release_stored, outbox::outbox_key, resample::history_key, Arc, Context,
+	DasKv, ReliabilityId, Result, EXTENDED_SEGMENTS_PER_BLOB,
+};
+use codec::{Decode, Encode};
+use futures::lock::Mutex;
+use log::{debug, info};
+use melo_core_primitives::{
+	reliability::{sample_key, sample_key_from_block},
+	sidecar_key,
+	traits::{DasParamsApi, Extractor, HeaderWithCommitment},
+	BlobPlacement, Position, SidecarMetadata,
+};
+use sc_client_api::{BlockBackend, HeaderBackend};
+use sp_api::ProvideRuntimeApi;
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+use std::{marker::PhantomData, time::Duration};
+
+const LOG_TARGET: &str = "das_prune";
+
+/// The key of the number of the first block whose data was not pruned.
+const PRUNED_BEFORE_KEY: &[u8] = b"das_pruned_before";
+
+/// Configuration of the pruning task.
+#[derive(Clone, Debug)]
+pub struct PruneConfig {
+	/// Interval between two pruning rounds.
+	pub interval: Duration,
+	/// Maximum number of blocks pruned in each round.
+	pub blocks_per_round: u32,
+}
+
+impl Default for PruneConfig {
+	fn default() -> Self {
+		PruneConfig { interval: Duration::from_secs(600), blocks_per_round: 1_000 }
+	}
+}
+
+/// The data removed by a pruning.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PruneReport {
+	/// The number of blocks pruned.
+	pub blocks: u32,
+	/// The number of values removed.
+	pub values: u32,
+	/// The number of bytes reclaimed.
+	pub bytes: u64,
+}
+
+impl PruneReport {
+	fn merge(&mut self, other: PruneReport) {
+		self.blocks += other.blocks;
+		self.values += other.values;
+		self.bytes += other.bytes;
+	}
+}
+
+/// Returns the keys of the data of the block of `header`, whose blob transactions submitted
+/// `sidecars`.
+pub fn block_keys<Header: HeaderWithCommitment>(
+	header: &Header,
+	sidecars: &[SidecarMetadata],
+) -> Vec<Vec<u8>> {
+	let block_hash = header.hash().encode();
+	let app_lookups = &header.extension().app_lookup;
+	let rows: u32 = app_lookups.iter().map(|lookup| lookup.count as u32).sum();
+
+	let mut keys = vec![ReliabilityId::block_confidence(&block_hash).0, history_key(&block_hash)];
+	for lookup in app_lookups {
+		keys.push(ReliabilityId::app_confidence(lookup.app_id, lookup.nonce).0);
+		for y in 0..lookup.count as u32 {
+			for x in 0..EXTENDED_SEGMENTS_PER_BLOB as u32 {
+				keys.push(sample_key(lookup.app_id, lookup.nonce, &Position { x, y }));
+			}
+		}
+	}
+	for y in 0..rows * 2 {
+		for x in 0..EXTENDED_SEGMENTS_PER_BLOB as u32 {
+			keys.push(sample_key_from_block(&block_hash, &Position { x, y }));
+		}
+	}
+	for metadata in sidecars {
+		keys.push(sidecar_key(&metadata.id()));
+		keys.push(outbox_key(&metadata.id()));
+	}
+	keys
+}
+
+/// Removes the values of `keys` from `db`, reporting the values removed and their size.
+pub fn remove_values(db: &mut impl DasKv, keys: &[Vec<u8>]) -> PruneReport {
+	let mut report = PruneReport::default();
+	for key in keys {
+		if let Some(value) = db.get(key) {
+			db.remove(key);
+			report.values += 1;
+			report.bytes += value.len() as u64;
+		}
+	}
+	report
+}
+
+/// Returns the number of the first block whose data was not pruned.
+pub fn pruned_before(db: &mut impl DasKv) -> u32 {
+	db.get(PRUNED_BEFORE_KEY)
+		.and_then(|bytes| Decode::decode(&mut &bytes[..]).ok())
+		.unwrap_or(0)
+}
+
+/// Prunes the data of the finalized blocks of a client from a database.
+pub struct Pruner<Client, B, DB> {
+	client: Arc<Client>,
+	database: Arc<Mutex<DB>>,
+	_marker: PhantomData<B>,
+}
+
+impl<Client, B, DB> Pruner<Client, B, DB>
+where
+	B: BlockT,
+	B::Header: HeaderWithCommitment,
+	Client: HeaderBackend<B> + BlockBackend<B> + ProvideRuntimeApi<B>,
+	Client::Api: Extractor<B> + DasParamsApi<B>,
+	DB: DasKv,
+	NumberFor<B>: Into<u32> + From<u32>,
+{
+	/// Creates a pruner of the data of the blocks of `client` stored in `database`.
+	pub fn new(client: Arc<Client>, database: Arc<Mutex<DB>>) -> Self {
+		Self { client, database, _marker: PhantomData }
+	}
+
+	/// Returns the first block whose data has to be kept, the last finalized block minus the
+	/// retention period in use.
+	pub fn horizon(&self) -> Result<u32> {
+		let info = self.client.info();
+		let params = self
+			.client
+			.runtime_api()
+			.das_params(info.finalized_hash)
+			.map_err(|e| anyhow!("Failed to read the DAS parameters: {:?}", e))?;
+		Ok(info.finalized_number.into().saturating_sub(params.retention_period))
+	}
+
+	/// Prunes the data of the blocks before `before`, at most `max_blocks` of them, from the first
+	/// block not pruned yet. Blocks that are not finalized are never pruned.
+	pub async fn prune_before(&self, before: u32, max_blocks: u32) -> Result<PruneReport> {
+		let finalized: u32 = self.client.info().finalized_number.into();
+		let from = pruned_before(&mut *self.database.lock().await);
+		let to = before.min(finalized.saturating_add(1)).min(from.saturating_add(max_blocks));
+
+		let mut report = PruneReport::default();
+		for number in from..to {
+			let keys = self.block_keys(number)?;
+			let mut db = self.database.lock().await;
+			let mut removed = remove_values(&mut *db, &keys);
+			removed.merge(release_stored(&mut *db, number));
+			db.set(PRUNED_BEFORE_KEY, &(number + 1).encode());
+			debug!(
+				target: LOG_TARGET,
+				"Pruned {} values, {} bytes, of block {}",
+				removed.values,
+				removed.bytes,
+				number
+			);
+			report.merge(PruneReport { blocks: 1, ..removed });
+		}
+		Ok(report)
+	}
+
+	/// Returns the keys of the data of the block `number`.
+	fn block_keys(&self, number: u32) -> Result<Vec<Vec<u8>>> {
+		let hash = self
+			.client
+			.hash(number.into())?
+			.with_context(|| format!("Hash of block {} not found", number))?;
+		let header = self
+			.client
+			.header(hash)?
+			.with_context(|| format!("Header of block {} not found", number))?;
+
+		let sidecars = block_sidecars(&*self.client, hash, &header)?;
+
+		Ok(block_keys(&header, &sidecars))
+	}
+}
+
+/// Returns the sidecars submitted by the blob transactions of the block `hash` of `header`.
+pub(crate) fn block_sidecars<Client, B>(
+	client: &Client,
+	hash: B::Hash,
+	header: &B::Header,
+) -> Result<Vec<SidecarMetadata>>
+where
+	B: BlockT,
+	B::Header: HeaderWithCommitment,
+	Client: BlockBackend<B> + ProvideRuntimeApi<B>,
+	Client::Api: Extractor<B>,
+{
+	Ok(block_placements(client, hash, header)?
+		.into_iter()
+		.map(|placement| placement.metadata)
+		.collect())
+}
+
+/// Returns the sidecars submitted by the blob transactions of the block `hash` of `header`, along
+/// with the rows they take in the block matrix.
+pub fn block_placements<Client, B>(
+	client: &Client,
+	hash: B::Hash,
+	header: &B::Header,
+) -> Result<Vec<BlobPlacement>>
+where
+	B: BlockT,
+	B::Header: HeaderWithCommitment,
+	Client: BlockBackend<B> + ProvideRuntimeApi<B>,
+	Client::Api: Extractor<B>,
+{
+	if header.extension().app_lookup.is_empty() {
+		return Ok(vec![])
+	}
+	let extrinsics = client.block_body(hash)?.unwrap_or_default();
+	if extrinsics.is_empty() {
+		return Ok(vec![])
+	}
+	let encoded = extrinsics.iter().map(|extrinsic| extrinsic.encode()).collect();
+	Ok(client
+		.runtime_api()
+		.extract_placements_batch(hash, encoded)
+		.map_err(|e| anyhow!("Failed to extract the blobs of block {:?}: {:?}", hash, e))?
+		.into_iter()
+		.flatten()
+		.flatten()
+		.collect())
+}
+
+/// Periodically prunes the data of the finalized blocks older than the retention period.
+pub async fn start_prune_task<Client, B, DB>(
+	pruner: Arc<Pruner<Client, B, DB>>,
+	config: PruneConfig,
+) where
+	B: BlockT,
+	B::Header: HeaderWithCommitment,
+	Client: HeaderBackend<B> + BlockBackend<B> + ProvideRuntimeApi<B>,
+	Client::Api: Extractor<B> + DasParamsApi<B>,
+	DB: DasKv,
+	NumberFor<B>: Into<u32> + From<u32>,
+{
+	info!("🧹 Starting local data pruning task.");
+
+	let mut interval = tokio::time::interval(config.interval);
+
+	loop {
+		interval.tick().await;
+
+		let result = match pruner.horizon() {
+			Ok(horizon) => pruner.prune_before(horizon, config.blocks_per_round).await,
+			Err(e) => Err(e),
+		};
+		match result {
+			Ok(report) if report.blocks > 0 => info!(
+				"🧹 Pruned the data of {} blocks, {} bytes reclaimed",
+				report.blocks, report.bytes
+			),
+			Ok(_) => (),
+			Err(e) => debug!(target: LOG_TARGET, "Failed to prune the local data: {:?}", e),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use melo_core_primitives::{traits::ExtendedHeader, AppLookup, Header, HeaderExtension};
+	use melo_das_db::mock_db::MockDb;
+	use sp_runtime::traits::BlakeTwo256;
+
+	#[test]
+	fn test_block_keys() {
+		let extension = HeaderExtension {
+			app_lookup: vec![
+				AppLookup { app_id: 1, nonce: 1, count: 2 },
+				AppLookup { app_id: 2, nonce: 5, count: 1 },
+			],
+			..Default::default()
+		};
+		let header = Header::<u32, BlakeTwo256>::new_ext(
+			1,
+			Default::default(),
+			Default::default(),
+			Default::default(),
+			Default::default(),
+			extension,
+		);
+
+		let keys = block_keys(&header, &[]);
+		let per_row = EXTENDED_SEGMENTS_PER_BLOB;
+		// Two block keys, an app confidence per app, the segments of the original rows and
+		// those of the whole extended matrix.
+		assert_eq!(keys.len(), 2 + 2 + 3 * per_row + 6 * per_row);
+		assert!(keys.contains(&ReliabilityId::app_confidence(2, 5).0));
+		assert!(keys.contains(&sample_key(1, 1, &Position { x: 0, y: 1 })));
+	}
+
+	#[test]
+	fn test_remove_values() {
+		let mut db = MockDb::new();
+		db.set(b"a", &[0u8; 10]);
+		db.set(b"b", &[0u8; 5]);
+		db.set(b"c", &[0u8; 1]);
+
+		let report = remove_values(&mut db, &[b"a".to_vec(), b"b".to_vec(), b"d".to_vec()]);
+		assert_eq!(report, PruneReport { blocks: 0, values: 2, bytes: 15 });
+		assert!(!db.contains(b"a"));
+		assert!(db.contains(b"c"));
+
+		assert_eq!(pruned_before(&mut db), 0);
+		db.set(PRUNED_BEFORE_KEY, &7u32.encode());
+		assert_eq!(pruned_before(&mut db), 7);
+	}
+}
@@ -0,0 +1,144 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Segment repair.
+//!
+//! The segments of a block are lost little by little as the peers storing them leave the network.
+//! The repair task periodically checks random rows of the finalized blocks, and puts the missing
+//! segments of a row back into the network while the row can still be reconstructed from the
+//! remaining ones. The proofs of the reconstructed segments are computed again from the polynomial
+//! of the row, and the segments are only put back once they verify against its commitment.
+use crate::{Arc, DasKv, DasNetworkOperations, RowRepair, SamplingClient};
+use log::{debug, info, warn};
+use melo_core_primitives::traits::HeaderWithCommitment;
+use rand::Rng;
+use sc_client_api::HeaderBackend;
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+use std::time::Duration;
+
+const LOG_TARGET: &str = "das_repair";
+
+/// Configuration of the repair task.
+#[derive(Clone, Debug)]
+pub struct RepairConfig {
+	/// Interval between two repair rounds.
+	pub interval: Duration,
+	/// Number of rows checked in each round.
+	pub rows_per_round: u32,
+	/// Number of finalized blocks, counted back from the last one, whose rows are checked.
+	pub depth: u32,
+}
+
+impl Default for RepairConfig {
+	fn default() -> Self {
+		RepairConfig { interval: Duration::from_secs(60), rows_per_round: 8, depth: 14_400 }
+	}
+}
+
+/// Periodically repairs random rows of the finalized blocks.
+pub async fn start_repair_task<Client, B, H, DB, D>(
+	client: Arc<Client>,
+	das_client: Arc<SamplingClient<H, DB, D>>,
+	config: RepairConfig,
+) where
+	B: BlockT,
+	B::Header: HeaderWithCommitment + Sync,
+	Client: HeaderBackend<B> + 'static,
+	DB: DasKv + Send + Sync,
+	H: HeaderWithCommitment + Send + Sync,
+	D: DasNetworkOperations + Send + Sync,
+	NumberFor<B>: Into<u32>,
+{
+	info!("🩹 Starting segment repair task.");
+
+	let mut interval = tokio::time::interval(config.interval);
+
+	loop {
+		interval.tick().await;
+
+		let finalized: u32 = client.info().finalized_number.into();
+		let rows = pick_rows(&mut rand::thread_rng(), finalized, &config);
+
+		for (number, row) in rows {
+			let header = match client.hash(number.into()) {
+				Ok(Some(hash)) => match client.header(hash) {
+					Ok(Some(header)) => header,
+					_ => continue,
+				},
+				_ => continue,
+			};
+
+			let rows_count = header.col_num().unwrap_or_default() * 2;
+			if rows_count == 0 {
+				continue
+			}
+			let row = row % rows_count;
+
+			match das_client.network.repair_row(&header, row).await {
+				Ok(RowRepair::Healthy) =>
+					debug!(target: LOG_TARGET, "Row {} of block {} is healthy", row, number),
+				Ok(RowRepair::Repaired(missing)) => info!(
+					"🩹 Republished {} missing segments of row {} of block {}",
+					missing, row, number
+				),
+				Ok(RowRepair::Unrecoverable(missing)) => warn!(
+					"⚠️ Row {} of block {} misses {} segments and can not be repaired",
+					row, number, missing
+				),
+				Err(e) => debug!(
+					target: LOG_TARGET,
+					"Failed to repair row {} of block {}: {:?}",
+					row,
+					number,
+					e
+				),
+			}
+		}
+	}
+}
+
+/// Picks the rows checked in a round, as `(block number, row)` pairs.
+///
+/// Blocks are drawn among the last `config.depth` finalized blocks, excluding the genesis. Rows
+/// are drawn at random and have to be reduced to the number of rows of their block.
+fn pick_rows(rng: &mut impl Rng, finalized: u32, config: &RepairConfig) -> Vec<(u32, u32)> {
+	if finalized == 0 {
+		return vec![]
+	}
+
+	let from = finalized.saturating_sub(config.depth).max(1);
+	(0..config.rows_per_round)
+		.map(|_| (rng.gen_range(from..=finalized), rng.gen()))
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_pick_rows() {
+		let mut rng = rand::thread_rng();
+		let config = RepairConfig { rows_per_round: 16, depth: 10, ..Default::default() };
+
+		assert!(pick_rows(&mut rng, 0, &config).is_empty());
+
+		let rows = pick_rows(&mut rng, 100, &config);
+		assert_eq!(rows.len(), 16);
+		assert!(rows.iter().all(|(number, _)| (90..=100).contains(number)));
+
+		let rows = pick_rows(&mut rng, 5, &config);
+		assert!(rows.iter().all(|(number, _)| (1..=5).contains(number)));
+	}
+}
@@ -0,0 +1,232 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Re-sampling of old blocks.
+//!
+//! A block is sampled once when it is imported, but its data can disappear later, while it is
+//! still within the retention window. The re-sampling task periodically fetches the samples of
+//! random finalized blocks of the window again, so that the confidence of a block decays when its
+//! samples start failing. Every sampling of a block appends a [`ConfidencePoint`] to its history.
+use crate::{Arc, DasKv, DasNetworkOperations, Reliability, SamplingClient};
+use codec::{Decode, Encode};
+use log::{debug, info, warn};
+use melo_core_primitives::{
+	config::DATA_RETENTION_PERIOD, reliability::Permill, traits::HeaderWithCommitment,
+};
+use rand::Rng;
+use sc_client_api::HeaderBackend;
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const LOG_TARGET: &str = "das_resample";
+
+/// The prefix of the keys of the confidence histories.
+const HISTORY_PREFIX: &[u8] = b"confidence_history";
+
+/// The maximum number of points kept in the confidence history of a block.
+pub const MAX_CONFIDENCE_HISTORY: usize = 64;
+
+/// Configuration of the re-sampling task.
+#[derive(Clone, Debug)]
+pub struct ResampleConfig {
+	/// Interval between two re-sampling rounds.
+	pub interval: Duration,
+	/// Number of blocks re-sampled in each round.
+	pub blocks_per_round: u32,
+	/// Number of finalized blocks, counted back from the last one, that are re-sampled.
+	pub depth: u32,
+}
+
+impl Default for ResampleConfig {
+	fn default() -> Self {
+		ResampleConfig {
+			interval: Duration::from_secs(120),
+			blocks_per_round: 2,
+			depth: DATA_RETENTION_PERIOD,
+		}
+	}
+}
+
+/// The confidence of a block at one of its samplings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+pub struct ConfidencePoint {
+	/// The time of the sampling, in seconds since the Unix epoch.
+	pub at: u64,
+	/// The confidence of the block, in parts per million.
+	pub confidence: Option<u32>,
+	/// The number of samples fetched.
+	pub success: u32,
+	/// The number of samples.
+	pub samples: u32,
+}
+
+impl ConfidencePoint {
+	/// Creates the point of `confidence` at `at`, with the base factor of the confidence
+	/// `base_factor`.
+	pub fn new(confidence: &Reliability, at: u64, base_factor: Permill) -> Self {
+		ConfidencePoint {
+			at,
+			confidence: confidence.value_with(base_factor),
+			success: confidence.samples.iter().filter(|sample| sample.is_availability).count()
+				as u32,
+			samples: confidence.samples.len() as u32,
+		}
+	}
+}
+
+/// Returns the confidence history of the block `block_hash`, oldest first.
+pub fn confidence_history(db: &mut impl DasKv, block_hash: &[u8]) -> Vec<ConfidencePoint> {
+	db.get(&history_key(block_hash))
+		.and_then(|bytes| Decode::decode(&mut &bytes[..]).ok())
+		.unwrap_or_default()
+}
+
+/// Appends `point` to the confidence history of the block `block_hash`, dropping the oldest points
+/// beyond [`MAX_CONFIDENCE_HISTORY`].
+pub fn record_confidence(db: &mut impl DasKv, block_hash: &[u8], point: ConfidencePoint) {
+	let mut history = confidence_history(db, block_hash);
+	history.push(point);
+	let excess = history.len().saturating_sub(MAX_CONFIDENCE_HISTORY);
+	history.drain(..excess);
+	db.set(&history_key(block_hash), &history.encode());
+}
+
+pub(crate) fn history_key(block_hash: &[u8]) -> Vec<u8> {
+	[HISTORY_PREFIX, block_hash].concat()
+}
+
+/// Returns the current time in seconds since the Unix epoch.
+pub(crate) fn now() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// Periodically re-samples random finalized blocks of the retention window.
+pub async fn start_resample_task<Client, B, H, DB, D>(
+	client: Arc<Client>,
+	das_client: Arc<SamplingClient<H, DB, D>>,
+	config: ResampleConfig,
+) where
+	B: BlockT,
+	B::Header: HeaderWithCommitment + Sync,
+	Client: HeaderBackend<B> + 'static,
+	DB: DasKv + Send + Sync,
+	H: HeaderWithCommitment + Send + Sync,
+	D: DasNetworkOperations + Send + Sync,
+	NumberFor<B>: Into<u32>,
+{
+	info!("🔁 Starting block re-sampling task.");
+
+	let mut interval = tokio::time::interval(config.interval);
+
+	loop {
+		interval.tick().await;
+
+		let finalized: u32 = client.info().finalized_number.into();
+		let blocks = pick_blocks(&mut rand::thread_rng(), finalized, &config);
+
+		for number in blocks {
+			let header = match client.hash(number.into()) {
+				Ok(Some(hash)) => match client.header(hash) {
+					Ok(Some(header)) => header,
+					_ => continue,
+				},
+				_ => continue,
+			};
+
+			let params = das_client.params().get();
+			let thresholds = params.thresholds();
+			match das_client.resample_block(&header).await {
+				Ok(Some(confidence)) if !confidence.is_available_with(&thresholds) => warn!(
+					"⚠️ Block {} is no longer available, confidence {:?}",
+					number,
+					confidence.value_with(params.base_factor)
+				),
+				Ok(Some(confidence)) => debug!(
+					target: LOG_TARGET,
+					"Re-sampled block {}, confidence {:?}",
+					number,
+					confidence.value_with(params.base_factor)
+				),
+				Ok(None) => debug!(target: LOG_TARGET, "Block {} was never sampled", number),
+				Err(e) => debug!(
+					target: LOG_TARGET,
+					"Failed to re-sample block {}: {:?}",
+					number,
+					e
+				),
+			}
+		}
+	}
+}
+
+/// Picks the numbers of the blocks re-sampled in a round.
+///
+/// Blocks are drawn among the last `config.depth` finalized blocks, excluding the genesis.
+fn pick_blocks(rng: &mut impl Rng, finalized: u32, config: &ResampleConfig) -> Vec<u32> {
+	if finalized == 0 {
+		return vec![]
+	}
+
+	let from = finalized.saturating_sub(config.depth).max(1);
+	(0..config.blocks_per_round).map(|_| rng.gen_range(from..=finalized)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use melo_core_primitives::reliability::{ReliabilityType, Sample, BLOCK_FAILURE_PROBABILITY};
+	use melo_das_db::mock_db::MockDb;
+
+	#[test]
+	fn test_pick_blocks() {
+		let mut rng = rand::thread_rng();
+		let config = ResampleConfig { blocks_per_round: 4, depth: 10, ..Default::default() };
+
+		assert!(pick_blocks(&mut rng, 0, &config).is_empty());
+
+		let blocks = pick_blocks(&mut rng, 100, &config);
+		assert_eq!(blocks.len(), 4);
+		assert!(blocks.iter().all(|number| (90..=100).contains(number)));
+
+		let blocks = pick_blocks(&mut rng, 5, &config);
+		assert!(blocks.iter().all(|number| (1..=5).contains(number)));
+	}
+
+	#[test]
+	fn test_confidence_history() {
+		let mut db = MockDb::new();
+		let block_hash = [1u8; 32];
+		assert!(confidence_history(&mut db, &block_hash).is_empty());
+
+		let mut confidence = Reliability::new(ReliabilityType::Block, &[]);
+		confidence.samples = vec![Sample { is_availability: true, ..Default::default() }; 4];
+		let first = ConfidencePoint::new(&confidence, 1, BLOCK_FAILURE_PROBABILITY);
+		assert_eq!(first.success, 4);
+		record_confidence(&mut db, &block_hash, first);
+
+		// The confidence decays as samples fail.
+		confidence.samples[0].is_availability = false;
+		let second = ConfidencePoint::new(&confidence, 2, BLOCK_FAILURE_PROBABILITY);
+		assert!(second.confidence < first.confidence);
+		record_confidence(&mut db, &block_hash, second);
+		assert_eq!(confidence_history(&mut db, &block_hash), vec![first, second]);
+
+		for at in 3..=(MAX_CONFIDENCE_HISTORY as u64 + 1) {
+			record_confidence(&mut db, &block_hash, ConfidencePoint { at, ..second });
+		}
+		let history = confidence_history(&mut db, &block_hash);
+		assert_eq!(history.len(), MAX_CONFIDENCE_HISTORY);
+		assert_eq!(history[0], second);
+	}
+}
@@ -0,0 +1,241 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Work queue of the transaction pool listener.
+//!
+//! The listener queues the sampling of the blobs of the transactions imported to the pool instead
+//! of sampling them inline, so that it keeps draining the import notifications, which the pool
+//! drops once their channel is full. The [`SampleQueue`] holds at most `capacity` samplings,
+//! ordered by the priority of their transactions in the pool, the order in which blocks include
+//! them. When it is full, the sampling of lowest priority is dropped and counted. At most
+//! `concurrency` samplings run at once.
+use crate::KZGCommitment;
+use prometheus_endpoint::{register, Counter, Gauge, PrometheusError, Registry, U64};
+use std::{cmp::Reverse, collections::BTreeMap};
+
+/// The default number of samplings the queue holds.
+pub const DEFAULT_SAMPLE_QUEUE_CAPACITY: usize = 1024;
+
+/// The default number of samplings running at once.
+pub const DEFAULT_SAMPLE_CONCURRENCY: usize = 4;
+
+/// Configuration of the [`SampleQueue`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SampleQueueConfig {
+	/// The number of samplings the queue holds, not counting those running.
+	pub capacity: usize,
+	/// The number of samplings running at once.
+	pub concurrency: usize,
+}
+
+impl Default for SampleQueueConfig {
+	fn default() -> Self {
+		Self { capacity: DEFAULT_SAMPLE_QUEUE_CAPACITY, concurrency: DEFAULT_SAMPLE_CONCURRENCY }
+	}
+}
+
+/// The sampling of the data of an application submitted by a transaction.
+#[derive(Debug, Clone)]
+pub struct SampleJob {
+	/// The application of the data.
+	pub app_id: u32,
+	/// The nonce of the submission.
+	pub nonce: u32,
+	/// The commitments of the blobs of the data.
+	pub commitments: Vec<KZGCommitment>,
+	/// The priority of the transaction in the pool.
+	pub priority: u64,
+}
+
+/// Metrics of the [`SampleQueue`].
+#[derive(Clone)]
+pub struct SampleQueueMetrics {
+	queued: Gauge<U64>,
+	running: Gauge<U64>,
+	dropped: Counter<U64>,
+}
+
+impl SampleQueueMetrics {
+	/// Registers the metrics of the queue to `registry`.
+	pub fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			queued: register(
+				Gauge::new("das_sample_queue_len", "Number of samplings waiting in the queue")?,
+				registry,
+			)?,
+			running: register(
+				Gauge::new("das_sample_queue_running", "Number of samplings running")?,
+				registry,
+			)?,
+			dropped: register(
+				Counter::new(
+					"das_sample_queue_dropped_total",
+					"Total number of samplings dropped by the full queue",
+				)?,
+				registry,
+			)?,
+		})
+	}
+}
+
+/// A bounded queue of samplings, the highest priorities first.
+pub struct SampleQueue {
+	config: SampleQueueConfig,
+	/// The queued samplings by priority, then by order of arrival.
+	jobs: BTreeMap<(u64, Reverse<u64>), SampleJob>,
+	/// The number of samplings pushed so far, ordering those of equal priority.
+	pushed: u64,
+	running: usize,
+	metrics: Option<SampleQueueMetrics>,
+}
+
+impl SampleQueue {
+	/// Creates an empty queue.
+	pub fn new(config: SampleQueueConfig) -> Self {
+		Self { config, jobs: BTreeMap::new(), pushed: 0, running: 0, metrics: None }
+	}
+
+	/// Records the length of the queue and the samplings dropped to `metrics`.
+	pub fn with_metrics(mut self, metrics: SampleQueueMetrics) -> Self {
+		self.metrics = Some(metrics);
+		self
+	}
+
+	/// Returns the number of samplings waiting in the queue.
+	pub fn len(&self) -> usize {
+		self.jobs.len()
+	}
+
+	/// Returns `true` if no sampling waits in the queue.
+	pub fn is_empty(&self) -> bool {
+		self.jobs.is_empty()
+	}
+
+	/// Returns the number of samplings running.
+	pub fn running(&self) -> usize {
+		self.running
+	}
+
+	/// Queues `job`.
+	///
+	/// Returns the sampling dropped if the queue is full: the one of lowest priority, the latest
+	/// among equals, which may be `job` itself.
+	pub fn push(&mut self, job: SampleJob) -> Option<SampleJob> {
+		let key = (job.priority, Reverse(self.pushed));
+		self.pushed += 1;
+
+		let dropped = if self.jobs.len() < self.config.capacity {
+			self.jobs.insert(key, job);
+			None
+		} else {
+			match self.jobs.first_key_value() {
+				Some((lowest, _)) if *lowest < key => {
+					let lowest = *lowest;
+					self.jobs.insert(key, job);
+					self.jobs.remove(&lowest)
+				},
+				_ => Some(job),
+			}
+		};
+
+		if let Some(metrics) = &self.metrics {
+			if dropped.is_some() {
+				metrics.dropped.inc();
+			}
+		}
+		self.update_metrics();
+		dropped
+	}
+
+	/// Takes the sampling of highest priority to run, unless `concurrency` samplings are running.
+	pub fn start(&mut self) -> Option<SampleJob> {
+		if self.running >= self.config.concurrency {
+			return None
+		}
+		let (_, job) = self.jobs.pop_last()?;
+		self.running += 1;
+		self.update_metrics();
+		Some(job)
+	}
+
+	/// Records the end of a sampling taken by [`SampleQueue::start`].
+	pub fn finish(&mut self) {
+		self.running = self.running.saturating_sub(1);
+		self.update_metrics();
+	}
+
+	fn update_metrics(&self) {
+		if let Some(metrics) = &self.metrics {
+			metrics.queued.set(self.jobs.len() as u64);
+			metrics.running.set(self.running as u64);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn job(app_id: u32, priority: u64) -> SampleJob {
+		SampleJob { app_id, nonce: 0, commitments: Vec::new(), priority }
+	}
+
+	fn queue(capacity: usize, concurrency: usize) -> SampleQueue {
+		SampleQueue::new(SampleQueueConfig { capacity, concurrency })
+	}
+
+	#[test]
+	fn test_sample_queue_order() {
+		let mut queue = queue(8, 8);
+		for (app_id, priority) in [(1, 10), (2, 30), (3, 10), (4, 20)] {
+			assert!(queue.push(job(app_id, priority)).is_none());
+		}
+
+		// Highest priority first, in order of arrival among equals
+		let order = std::iter::from_fn(|| queue.start()).map(|job| job.app_id).collect::<Vec<_>>();
+		assert_eq!(order, vec![2, 4, 1, 3]);
+		assert!(queue.is_empty());
+	}
+
+	#[test]
+	fn test_sample_queue_drops_lowest_priority() {
+		let mut queue = queue(2, 8);
+		assert!(queue.push(job(1, 10)).is_none());
+		assert!(queue.push(job(2, 20)).is_none());
+
+		// A job of higher priority evicts the lowest
+		assert_eq!(queue.push(job(3, 30)).map(|job| job.app_id), Some(1));
+		// A job of lower or equal priority is dropped itself
+		assert_eq!(queue.push(job(4, 20)).map(|job| job.app_id), Some(4));
+		assert_eq!(queue.push(job(5, 5)).map(|job| job.app_id), Some(5));
+		assert_eq!(queue.len(), 2);
+	}
+
+	#[test]
+	fn test_sample_queue_concurrency() {
+		let mut queue = queue(8, 2);
+		for app_id in 0..4 {
+			queue.push(job(app_id, 0));
+		}
+
+		assert!(queue.start().is_some());
+		assert!(queue.start().is_some());
+		assert!(queue.start().is_none());
+		assert_eq!(queue.running(), 2);
+
+		queue.finish();
+		assert!(queue.start().is_some());
+		assert_eq!(queue.len(), 1);
+	}
+}
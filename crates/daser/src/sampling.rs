@@ -0,0 +1,146 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Sampling parameters.
+//!
+//! The [`SamplingClient`](crate::SamplingClient) reads its parameters from a
+//! [`SharedSamplingParams`] at each sampling, so that they can be changed while the node runs.
+use crate::{Arc, SAMPLES_PER_BLOCK};
+use melo_core_primitives::{
+	config::BLOCK_AVAILABILITY_THRESHOLD,
+	reliability::{
+		AvailabilityThresholds, Permill, APP_AVAILABILITY_THRESHOLD_PERMILL,
+		BLOCK_FAILURE_PROBABILITY,
+	},
+};
+use std::{sync::RwLock, time::Duration};
+
+/// The default time after which the samples not fetched yet count as failed.
+pub const DEFAULT_SAMPLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The parameters of the sampling of the node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SamplingParams {
+	/// The number of segments sampled in each block.
+	pub samples_per_block: u32,
+	/// The number of consecutive samples of a block that have to be fetched for the block to be
+	/// available.
+	pub block_threshold: u32,
+	/// The share of the samples of an application that have to be fetched for its data to be
+	/// available.
+	pub app_threshold: Permill,
+	/// The probability of a sample to be fetched although the data is unavailable, the base of
+	/// the confidence of a block.
+	pub base_factor: Permill,
+	/// The time after which the samples not fetched yet count as failed.
+	pub timeout: Duration,
+}
+
+impl Default for SamplingParams {
+	fn default() -> Self {
+		SamplingParams {
+			samples_per_block: SAMPLES_PER_BLOCK as u32,
+			block_threshold: BLOCK_AVAILABILITY_THRESHOLD,
+			app_threshold: APP_AVAILABILITY_THRESHOLD_PERMILL,
+			base_factor: BLOCK_FAILURE_PROBABILITY,
+			timeout: DEFAULT_SAMPLE_TIMEOUT,
+		}
+	}
+}
+
+impl SamplingParams {
+	/// Returns the thresholds above which sampled data is available.
+	pub fn thresholds(&self) -> AvailabilityThresholds {
+		AvailabilityThresholds { app: self.app_threshold, block: self.block_threshold }
+	}
+
+	/// Checks that the parameters are consistent.
+	///
+	/// A block can only be available if its threshold can be reached with its samples, and the
+	/// confidence of a block grows with its samples only if the base factor is below one.
+	pub fn validate(&self) -> Result<(), String> {
+		if self.samples_per_block == 0 {
+			return Err("At least one sample per block is required".into())
+		}
+		if self.block_threshold == 0 || self.block_threshold > self.samples_per_block {
+			return Err(format!(
+				"The block threshold must be between 1 and the {} samples per block",
+				self.samples_per_block
+			))
+		}
+		if self.base_factor == Permill::one() {
+			return Err("The base factor must be below one".into())
+		}
+		if self.timeout.is_zero() {
+			return Err("The sample timeout must not be zero".into())
+		}
+		Ok(())
+	}
+}
+
+/// The sampling parameters shared by the services of the node.
+#[derive(Debug, Clone, Default)]
+pub struct SharedSamplingParams(Arc<RwLock<SamplingParams>>);
+
+impl SharedSamplingParams {
+	/// Shares `params`, which must be valid.
+	pub fn new(params: SamplingParams) -> Result<Self, String> {
+		params.validate()?;
+		Ok(SharedSamplingParams(Arc::new(RwLock::new(params))))
+	}
+
+	/// Returns the parameters in use.
+	pub fn get(&self) -> SamplingParams {
+		*self.0.read().unwrap_or_else(|e| e.into_inner())
+	}
+
+	/// Replaces the parameters in use by `params`, unless they are invalid.
+	pub fn set(&self, params: SamplingParams) -> Result<(), String> {
+		params.validate()?;
+		*self.0.write().unwrap_or_else(|e| e.into_inner()) = params;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_sampling_params_validation() {
+		let params = SamplingParams::default();
+		assert!(params.validate().is_ok());
+		assert_eq!(params.thresholds(), AvailabilityThresholds::default());
+
+		assert!(SamplingParams { samples_per_block: 0, ..params }.validate().is_err());
+		assert!(SamplingParams { block_threshold: 0, ..params }.validate().is_err());
+		assert!(SamplingParams { block_threshold: params.samples_per_block + 1, ..params }
+			.validate()
+			.is_err());
+		assert!(SamplingParams { base_factor: Permill::one(), ..params }.validate().is_err());
+		assert!(SamplingParams { timeout: Duration::ZERO, ..params }.validate().is_err());
+	}
+
+	#[test]
+	fn test_shared_params_keep_valid_params() {
+		let shared = SharedSamplingParams::default();
+		let params = SamplingParams { samples_per_block: 16, block_threshold: 10, ..shared.get() };
+
+		assert!(shared.set(params).is_ok());
+		assert_eq!(shared.clone().get(), params);
+
+		assert!(shared.set(SamplingParams { samples_per_block: 0, ..params }).is_err());
+		assert_eq!(shared.get(), params);
+	}
+}
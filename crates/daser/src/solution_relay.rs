@@ -0,0 +1,195 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Solution relay.
+//!
+//! The solutions the farmers publish on the DAS network are validated by the relaying nodes
+//! before they are gossiped further. A solution is dropped when its peer is over its quota or its
+//! quality is below the threshold of the node, both checked before the costly verification of its
+//! proofs. It is then submitted to the transaction pool as a claim, which verifies it: the claims
+//! are ordered by the quality of their solution, so that the block producers include the best
+//! ones. Only the solutions accepted by the pool are relayed, and the peers sending invalid ones
+//! are penalized.
+use crate::Arc;
+use futures::{channel::mpsc, StreamExt};
+use log::{debug, info};
+use melo_das_network::{InboundSolution, PeerId, Service, SolutionAnnouncement, SolutionVerdict};
+use sc_client_api::HeaderBackend;
+use sc_transaction_pool_api::{
+	error::{Error as PoolError, IntoPoolError},
+	TransactionPool, TransactionSource,
+};
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+use std::{
+	collections::HashMap,
+	time::{Duration, Instant},
+};
+
+const LOG_TARGET: &str = "solution_relay";
+
+/// The number of tracked peers above which the quotas of idle peers are dropped.
+const MAX_IDLE_PEERS: usize = 1024;
+
+/// Configuration of the solution relay.
+#[derive(Clone, Debug)]
+pub struct SolutionRelayConfig {
+	/// The minimum quality of the solutions relayed.
+	pub min_quality: u32,
+	/// The maximum number of solutions accepted from a peer in each `quota_period`.
+	pub peer_quota: u32,
+	/// The period of the quota of the peers.
+	pub quota_period: Duration,
+}
+
+impl Default for SolutionRelayConfig {
+	fn default() -> Self {
+		SolutionRelayConfig { min_quality: 0, peer_quota: 32, quota_period: Duration::from_secs(6) }
+	}
+}
+
+/// A claim built from a solution announcement.
+pub struct Candidate<Extrinsic> {
+	/// The quality of the solution.
+	pub quality: u32,
+	/// The unsigned claim of the solution.
+	pub extrinsic: Extrinsic,
+}
+
+/// The number of solutions received from each peer in its current quota period.
+pub struct PeerQuotas {
+	limit: u32,
+	period: Duration,
+	peers: HashMap<PeerId, (Instant, u32)>,
+}
+
+impl PeerQuotas {
+	/// Creates the quotas of `limit` solutions per `period`.
+	pub fn new(limit: u32, period: Duration) -> Self {
+		PeerQuotas { limit, period, peers: HashMap::new() }
+	}
+
+	/// Counts a solution received from `peer` at `now`, returning whether it is within the quota
+	/// of the peer.
+	pub fn admit(&mut self, peer: PeerId, now: Instant) -> bool {
+		if self.peers.len() > MAX_IDLE_PEERS {
+			let period = self.period;
+			self.peers.retain(|_, (start, _)| now.duration_since(*start) < period);
+		}
+
+		let (start, count) = self.peers.entry(peer).or_insert((now, 0));
+		if now.duration_since(*start) >= self.period {
+			*start = now;
+			*count = 0;
+		}
+		if *count >= self.limit {
+			return false
+		}
+		*count += 1;
+		true
+	}
+}
+
+/// Runs the solution relay, validating the solutions received by the DAS network `network`.
+///
+/// `to_candidate` builds the claim of an announcement, `None` if the announcement can not be
+/// decoded.
+pub async fn start_solution_relay<B, Client, TP, F>(
+	client: Arc<Client>,
+	transaction_pool: Arc<TP>,
+	network: Service,
+	mut inbound: mpsc::Receiver<InboundSolution>,
+	config: SolutionRelayConfig,
+	to_candidate: F,
+) where
+	B: BlockT,
+	Client: HeaderBackend<B> + 'static,
+	TP: TransactionPool<Block = B> + 'static,
+	F: Fn(&SolutionAnnouncement) -> Option<Candidate<B::Extrinsic>> + Send,
+{
+	info!("🏹 Relaying the solutions of the farmers");
+
+	let mut quotas = PeerQuotas::new(config.peer_quota, config.quota_period);
+
+	while let Some(InboundSolution { message_id, source, announcement }) = inbound.next().await {
+		let verdict = if !quotas.admit(source, Instant::now()) {
+			debug!(target: LOG_TARGET, "Solution of {:?} over its quota", source);
+			SolutionVerdict::Ignore
+		} else {
+			match to_candidate(&announcement) {
+				None => SolutionVerdict::Reject,
+				Some(candidate) if candidate.quality < config.min_quality =>
+					SolutionVerdict::Ignore,
+				Some(candidate) => {
+					let at = BlockId::hash(client.info().best_hash);
+					match transaction_pool
+						.submit_one(&at, TransactionSource::External, candidate.extrinsic)
+						.await
+					{
+						Ok(_) => SolutionVerdict::Accept,
+						Err(e) => pool_verdict(e.into_pool_error().ok()),
+					}
+				},
+			}
+		};
+
+		debug!(target: LOG_TARGET, "Solution from {:?}: {:?}", source, verdict);
+		if let Err(e) = network.report_solution(message_id, source, verdict).await {
+			debug!(target: LOG_TARGET, "Failed to report the verdict: {:?}", e);
+		}
+	}
+}
+
+/// Returns the verdict on a solution whose claim the transaction pool rejected with `error`.
+///
+/// Only invalid claims penalize the peer. A claim already in the pool or outranked by better
+/// ones was valid when it was sent.
+fn pool_verdict(error: Option<PoolError>) -> SolutionVerdict {
+	match error {
+		Some(PoolError::InvalidTransaction(_)) | Some(PoolError::UnknownTransaction(_)) =>
+			SolutionVerdict::Reject,
+		_ => SolutionVerdict::Ignore,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_runtime::transaction_validity::InvalidTransaction;
+
+	#[test]
+	fn test_peer_quota_resets_each_period() {
+		let mut quotas = PeerQuotas::new(2, Duration::from_secs(6));
+		let peer = PeerId::random();
+		let now = Instant::now();
+
+		assert!(quotas.admit(peer, now));
+		assert!(quotas.admit(peer, now));
+		assert!(!quotas.admit(peer, now + Duration::from_secs(1)));
+		assert!(quotas.admit(PeerId::random(), now));
+		assert!(quotas.admit(peer, now + Duration::from_secs(6)));
+	}
+
+	#[test]
+	fn test_pool_verdict() {
+		assert_eq!(
+			pool_verdict(Some(PoolError::InvalidTransaction(InvalidTransaction::Custom(20)))),
+			SolutionVerdict::Reject
+		);
+		assert_eq!(
+			pool_verdict(Some(PoolError::AlreadyImported(Box::new(())))),
+			SolutionVerdict::Ignore
+		);
+		assert_eq!(pool_verdict(None), SolutionVerdict::Ignore);
+	}
+}
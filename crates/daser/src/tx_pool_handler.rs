@@ -19,16 +19,20 @@
 //!
 //! The transaction pool listener is responsible for the following:
 //!
-//! - Monitoring the transaction pool for incoming transactions and processing them accordingly.
+//! - Monitoring the transaction pool for incoming transactions and processing them accordingly. The
+//!   samplings of their data go through a bounded [`SampleQueue`], see [`crate::sample_queue`].
 //! - Monitoring the network for new blocks and processing them accordingly.
 //! - Sampling blocks after finalization to determine block data availability.
+//! - Indexing the blobs of the finalized blocks, see [`crate::blob_index`].
 use crate::{
-	Arc, DasKv, DasNetworkOperations, Sampling, SamplingClient, EXTENDED_SEGMENTS_PER_BLOB,
+	blob_index::index_block, prune::block_placements, Arc, DasKv, DasNetworkOperations,
+	ReliabilityId, SampleJob, SampleQueue, SampleQueueConfig, SampleQueueMetrics, Sampling,
+	SamplingClient, WithholdingCollector, EXTENDED_SEGMENTS_PER_BLOB,
 };
-use futures::StreamExt;
+use futures::{FutureExt, StreamExt};
 use log::{error, info, warn};
 use melo_core_primitives::{config::BLOCK_SAMPLE_LIMIT, traits::Extractor, Encode};
-use sc_client_api::{client::BlockchainEvents, HeaderBackend};
+use sc_client_api::{client::BlockchainEvents, BlockBackend, HeaderBackend};
 use sc_transaction_pool_api::{InPoolTransaction, TransactionPool};
 use sp_api::ProvideRuntimeApi;
 use sp_runtime::traits::{Block as BlockT, NumberFor};
@@ -41,12 +45,18 @@ use sp_api::HeaderT;
 // Define a constant for logging with a target string
 const LOG_TARGET: &str = "tx_pool_listener";
 
+/// The maximum number of transactions imported in one burst whose blobs are extracted at once.
+const MAX_TX_BATCH: usize = 64;
+
 /// Parameters required for the transaction pool listener.
 #[derive(Clone)]
 pub struct TPListenerParams<Client, H, TP, DB, D: DasNetworkOperations + std::marker::Sync> {
 	pub client: Arc<Client>,
 	pub das_client: Arc<SamplingClient<H, DB, D>>,
 	pub transaction_pool: Arc<TP>,
+	pub withholding: Option<Arc<WithholdingCollector>>,
+	pub sample_queue: SampleQueueConfig,
+	pub queue_metrics: Option<SampleQueueMetrics>,
 	_phantom: PhantomData<DB>,
 }
 
@@ -58,7 +68,34 @@ impl<Client, H, TP, DB, D: DasNetworkOperations + std::marker::Sync>
 		das_client: Arc<SamplingClient<H, DB, D>>,
 		transaction_pool: Arc<TP>,
 	) -> Self {
-		Self { client, das_client, transaction_pool, _phantom: PhantomData }
+		Self {
+			client,
+			das_client,
+			transaction_pool,
+			withholding: None,
+			sample_queue: Default::default(),
+			queue_metrics: None,
+			_phantom: PhantomData,
+		}
+	}
+
+	/// Attests, through `withholding`, the finalized blocks whose sampling shows the data
+	/// unavailable.
+	pub fn with_withholding(mut self, withholding: Arc<WithholdingCollector>) -> Self {
+		self.withholding = Some(withholding);
+		self
+	}
+
+	/// Queues the samplings of the blob transactions as `config` says.
+	pub fn with_sample_queue(mut self, config: SampleQueueConfig) -> Self {
+		self.sample_queue = config;
+		self
+	}
+
+	/// Records the length of the sampling queue and the samplings dropped to `metrics`.
+	pub fn with_queue_metrics(mut self, metrics: SampleQueueMetrics) -> Self {
+		self.queue_metrics = Some(metrics);
+		self
 	}
 }
 
@@ -72,18 +109,24 @@ pub async fn start_tx_pool_listener<
 	H,
 	D: DasNetworkOperations + std::marker::Sync,
 >(
-	TPListenerParams { client, das_client, transaction_pool, _phantom }: TPListenerParams<
-		Client,
-		H,
-		TP,
-		DB,
-		D,
-	>,
+	TPListenerParams {
+		client,
+		das_client,
+		transaction_pool,
+		withholding,
+		sample_queue,
+		queue_metrics,
+		_phantom,
+	}: TPListenerParams<Client, H, TP, DB, D>,
 ) where
 	TP: TransactionPool<Block = B> + 'static,
 	B: BlockT + Send + Sync + 'static,
 	<B as BlockT>::Header: HeaderWithCommitment,
-	Client: ProvideRuntimeApi<B> + HeaderBackend<B> + BlockchainEvents<B> + 'static,
+	Client: ProvideRuntimeApi<B>
+		+ HeaderBackend<B>
+		+ BlockBackend<B>
+		+ BlockchainEvents<B>
+		+ 'static,
 	Client::Api: Extractor<B>,
 	DB: DasKv + 'static + Send + Sync,
 	H: HeaderWithCommitment + Send + Sync + 'static,
@@ -95,44 +138,99 @@ pub async fn start_tx_pool_listener<
 	let mut new_best_block_stream = client.import_notification_stream();
 	let mut finality_notification_stream = client.finality_notification_stream();
 
+	let mut queue = SampleQueue::new(sample_queue);
+	if let Some(metrics) = queue_metrics {
+		queue = queue.with_metrics(metrics);
+	}
+	let mut samplings = FuturesUnordered::new();
+
 	loop {
+		// Start the queued samplings, those of the transactions closest to inclusion first
+		while let Some(job) = queue.start() {
+			let das_client = das_client.clone();
+			samplings.push(async move {
+				das_client.sample_application(job.app_id, job.nonce, &job.commitments).await
+			});
+		}
+
 		tokio::select! {
+			Some(result) = samplings.next(), if !samplings.is_empty() => {
+				queue.finish();
+				if let Err(e) = result {
+					warn!("⚠️ Error during sampling application: {:?}", e);
+				}
+			},
 			Some(notification) = import_notification_stream.next() => {
+				// Take the transactions imported in the same burst, to extract their blobs in a
+				// single call to the runtime
+				let mut notifications = vec![notification];
+				while notifications.len() < MAX_TX_BATCH {
+					match import_notification_stream.next().now_or_never() {
+						Some(Some(notification)) => notifications.push(notification),
+						_ => break,
+					}
+				}
+
 				// Process ready transactions in the transaction pool
 				// TODO: Handle cases where the data is still not reached
-				if let Some(transaction) = transaction_pool.ready_transaction(&notification) {
-					let encoded = transaction.data().encode();
-					let at = client.info().best_hash;
-
-					// Extract relevant information from the encoded transaction data
-					match client.runtime_api().extract(at, &encoded) {
-						Ok(Some(data)) => {
-							for params in data {
+				let transactions: Vec<_> = notifications
+					.iter()
+					.filter_map(|notification| transaction_pool.ready_transaction(notification))
+					.collect();
+				if transactions.is_empty() { continue; }
+
+				let encoded =
+					transactions.iter().map(|transaction| transaction.data().encode()).collect();
+				let at = client.info().best_hash;
+
+				// Extract relevant information from the encoded transaction data
+				let results = match client.runtime_api().extract_placements_batch(at, encoded) {
+					Ok(results) => results,
+					Err(err) => {
+						tracing::debug!(
+							target: LOG_TARGET,
+							"Failed to extract data from {} extrinsics. Error: {:?}",
+							transactions.len(),
+							err,
+						);
+						continue;
+					},
+				};
+
+				for (transaction, result) in transactions.iter().zip(results) {
+					match result {
+						Some(placements) => {
+							for placement in placements {
+								let rows = placement.rows();
+								let params = placement.metadata;
 								tracing::debug!(
 									target: LOG_TARGET,
-									"New blob transaction found. Hash: {:?}", at,
+									"New blob transaction found. Hash: {:?}, app: {}, rows: {:?}",
+									at,
+									params.app_id,
+									rows,
 								);
 
-								if let Err(e) = das_client
-									.sample_application(params.app_id, params.nonce, &params.commitments)
-									.await
-								{
-									warn!("⚠️ Error during sampling application: {:?}", e);
-									continue;
+								let job = SampleJob {
+									app_id: params.app_id,
+									nonce: params.nonce,
+									commitments: params.commitments,
+									priority: *transaction.priority(),
+								};
+								if let Some(dropped) = queue.push(job) {
+									warn!(
+										"⚠️ Sampling queue full, dropped the sampling of app {} nonce {}",
+										dropped.app_id,
+										dropped.nonce,
+									);
 								}
 							}
 						},
-						Ok(None) => tracing::debug!(
+						None => tracing::debug!(
 							target: LOG_TARGET,
 							"Decoding of extrinsic failed. Transaction: {:?}",
 							transaction.hash(),
 						),
-						Err(err) => tracing::debug!(
-							target: LOG_TARGET,
-							"Failed to extract data from extrinsic. Transaction: {:?}. Error: {:?}",
-							transaction.hash(),
-							err,
-						),
 					};
 				}
 			},
@@ -153,6 +251,12 @@ pub async fn start_tx_pool_listener<
 					continue;
 				}
 
+				// The extended data would not reach the network, leave it to the repair task
+				if das_client.network.is_degraded() {
+					warn!("⚡ DHT network degraded, not extending block {}", block_number);
+					continue;
+				}
+
 				let fetch_result = das_client.network.fetch_block(&header).await;
 				let (segments, is_availability) = match fetch_result {
 					Ok(data) => data,
@@ -192,6 +296,11 @@ pub async fn start_tx_pool_listener<
 			// Sample blocks after finalization to determine block data availability
 			// TODO: Sync progress from runtime to eliminate uncertainty in local sampling
 			Some(notification) = finality_notification_stream.next() => {
+				// Index the blobs of the blocks finalized by the notification, oldest first
+				for hash in notification.tree_route.iter().chain(std::iter::once(&notification.hash)) {
+					index_finalized(&*client, &das_client, *hash).await;
+				}
+
 				let header = notification.header;
 				let block_number = *HeaderT::number(&header);
 				let latest_sampled_block = das_client.last_at().await;
@@ -214,8 +323,18 @@ pub async fn start_tx_pool_listener<
 								Ok(header_option) => {
 									if let Some(header) = header_option {
 										let das_client_clone = das_client.clone();
+										let withholding = withholding.clone();
 										sampling_tasks.push(async move {
-											das_client_clone.sample_block(&header).await
+											das_client_clone.sample_block(&header).await?;
+											if let Some(withholding) = withholding {
+												let block_hash = header.hash().encode();
+												let confidence = ReliabilityId::block_confidence(&block_hash)
+													.get_confidence(&mut *das_client_clone.database().lock().await);
+												if let Some(receipt) = confidence.and_then(|confidence| withholding.attest(&block_hash, &confidence)) {
+													warn!("🙈 Block {:?} is withheld, attested by {:?}", receipt.block_hash, receipt.signer);
+												}
+											}
+											Ok(())
 										});
 									}
 								},
@@ -245,6 +364,37 @@ pub async fn start_tx_pool_listener<
 	}
 }
 
+/// Indexes the blobs of the finalized block `hash` in the database of `das_client`.
+async fn index_finalized<Client, B, H, DB, D>(
+	client: &Client,
+	das_client: &SamplingClient<H, DB, D>,
+	hash: B::Hash,
+) where
+	B: BlockT,
+	<B as BlockT>::Header: HeaderWithCommitment,
+	Client: ProvideRuntimeApi<B> + HeaderBackend<B> + BlockBackend<B>,
+	Client::Api: Extractor<B>,
+	DB: DasKv,
+	D: DasNetworkOperations + std::marker::Sync,
+	NumberFor<B>: Into<u32>,
+{
+	let header = match client.header(hash) {
+		Ok(Some(header)) => header,
+		_ => {
+			warn!("⚠️ Header of finalized block {:?} not found, not indexing its blobs", hash);
+			return
+		},
+	};
+	let number: u32 = (*HeaderT::number(&header)).into();
+	match block_placements(client, hash, &header) {
+		Ok(placements) => {
+			let database = das_client.database();
+			index_block(&mut *database.lock().await, number, &hash.encode(), &placements);
+		},
+		Err(e) => warn!("⚠️ Failed to index the blobs of block {}: {:?}", number, e),
+	}
+}
+
 fn full_col<T>(segments: &[Option<T>], index: usize, len: usize) -> Result<Vec<T>, String>
 where
 	T: Clone,
@@ -0,0 +1,427 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Inclusion order of the blob transactions.
+//!
+//! The priority of a transaction is set by the runtime, which can not tell whether the data of a
+//! blob transaction is available. [`DataAwarePool`] wraps the transaction pool handed to the block
+//! proposer and reorders the transactions ready for a block: blob transactions whose sidecars the
+//! node holds, and therefore verified, come first, and those whose data the node neither holds nor
+//! sampled as available come last, making it less likely that unavailable data is included.
+//!
+//! The order of the transactions depending on each other is kept.
+//!
+//! With a [`PrePublisher`], the sidecars of the ready blob transactions are also published before
+//! the block is built, and the transactions whose data is not out in time are withheld. The
+//! proposer only waits for the ready transactions for a while, then takes those of
+//! [`TransactionPool::ready`], which are ordered the same but withheld unless published already.
+use crate::{
+	prepublish::{settle_published, PrePublisher},
+	Arc, DasKv,
+};
+use futures::lock::Mutex;
+use melo_core_primitives::{
+	reliability::ReliabilityId, sidecar_key, traits::Extractor, Encode, SidecarMetadata,
+};
+use sc_client_api::HeaderBackend;
+use sc_transaction_pool_api::{
+	ImportNotificationStream, InPoolTransaction, PoolFuture, PoolStatus, ReadyTransactions,
+	TransactionFor, TransactionPool, TransactionSource, TransactionStatusStreamFor, TransactionTag,
+	TxHash,
+};
+use sp_api::ProvideRuntimeApi;
+use sp_runtime::{
+	generic::BlockId,
+	traits::{Block as BlockT, NumberFor},
+};
+use std::{
+	collections::{HashMap, HashSet},
+	future::Future,
+	pin::Pin,
+};
+
+const LOG_TARGET: &str = "tx_priority";
+
+/// The place of a transaction in the order of inclusion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Inclusion {
+	/// A blob transaction whose sidecars are all held by the node.
+	Boosted,
+	/// A transaction without blobs, or whose blobs were sampled as available.
+	Normal,
+	/// A blob transaction whose data was not seen by the node.
+	Deferred,
+	/// A blob transaction whose data could not be published nor fetched before the block is
+	/// built. It is never yielded, nor are the transactions depending on it.
+	Withheld,
+}
+
+/// A transaction pool reordering the ready transactions by the availability of their data.
+///
+/// Only [`TransactionPool::ready_at`] and [`TransactionPool::ready`], used by the block proposer,
+/// are reordered, the other methods are those of the wrapped pool.
+pub struct DataAwarePool<Client, TP, DB> {
+	client: Arc<Client>,
+	pool: Arc<TP>,
+	database: Arc<Mutex<DB>>,
+	prepublish: Option<PrePublisher>,
+}
+
+impl<Client, TP, DB> DataAwarePool<Client, TP, DB> {
+	/// Wraps `pool`, looking up the data of the transactions in `database`.
+	pub fn new(client: Arc<Client>, pool: Arc<TP>, database: Arc<Mutex<DB>>) -> Self {
+		Self { client, pool, database, prepublish: None }
+	}
+
+	/// Publishes the sidecars of the ready blob transactions through `prepublish` before handing
+	/// them to the proposer.
+	pub fn with_prepublish(mut self, prepublish: PrePublisher) -> Self {
+		self.prepublish = Some(prepublish);
+		self
+	}
+}
+
+/// Returns how the blobs of `sidecars` should be included, given the data found in `db`.
+pub fn inclusion(sidecars: &[SidecarMetadata], db: &mut impl DasKv) -> Inclusion {
+	if sidecars.is_empty() {
+		return Inclusion::Normal
+	}
+	// Sidecars are only kept once their data is verified.
+	if sidecars.iter().all(|metadata| db.contains(&sidecar_key(&metadata.id()))) {
+		return Inclusion::Boosted
+	}
+	let sampled = sidecars.iter().all(|metadata| {
+		ReliabilityId::app_confidence(metadata.app_id, metadata.nonce)
+			.get_confidence(db)
+			.map_or(false, |confidence| confidence.is_availability())
+	});
+	if sampled {
+		Inclusion::Normal
+	} else {
+		Inclusion::Deferred
+	}
+}
+
+impl<Client, TP, DB> DataAwarePool<Client, TP, DB>
+where
+	TP: TransactionPool,
+	Client: ProvideRuntimeApi<TP::Block> + HeaderBackend<TP::Block>,
+	Client::Api: Extractor<TP::Block>,
+	DB: DasKv,
+{
+	/// Returns the sidecars of each of `transactions`, or `None` if the runtime failed to
+	/// extract them.
+	fn sidecars(
+		&self,
+		transactions: &[Arc<TP::InPoolTransaction>],
+	) -> Option<Vec<Vec<SidecarMetadata>>> {
+		let encoded = transactions.iter().map(|tx| tx.data().encode()).collect();
+		let at = self.client.info().best_hash;
+		match self.client.runtime_api().extract_placements_batch(at, encoded) {
+			Ok(placements) => Some(
+				placements
+					.into_iter()
+					.map(|placements| {
+						placements
+							.unwrap_or_default()
+							.into_iter()
+							.map(|placement| placement.metadata)
+							.collect()
+					})
+					.collect(),
+			),
+			Err(e) => {
+				tracing::debug!(
+					target: LOG_TARGET,
+					"Failed to extract the blobs of {} transactions: {:?}",
+					transactions.len(),
+					e,
+				);
+				None
+			},
+		}
+	}
+
+	/// Returns how each of `transactions` should be included, with its sidecars.
+	async fn classify(
+		&self,
+		transactions: &[Arc<TP::InPoolTransaction>],
+	) -> Vec<(Inclusion, Vec<SidecarMetadata>)> {
+		let sidecars = self.sidecars(transactions);
+		classified(sidecars, transactions.len(), &mut *self.database.lock().await)
+	}
+}
+
+/// Returns how each of `count` transactions of `sidecars` should be included, with its sidecars,
+/// all of them [`Inclusion::Normal`] if their sidecars are unknown.
+fn classified(
+	sidecars: Option<Vec<Vec<SidecarMetadata>>>,
+	count: usize,
+	db: &mut impl DasKv,
+) -> Vec<(Inclusion, Vec<SidecarMetadata>)> {
+	match sidecars {
+		Some(sidecars) => sidecars
+			.into_iter()
+			.map(|sidecars| (inclusion(&sidecars, db), sidecars))
+			.collect(),
+		None => vec![(Inclusion::Normal, Vec::new()); count],
+	}
+}
+
+impl<Client, TP, DB> TransactionPool for DataAwarePool<Client, TP, DB>
+where
+	TP: TransactionPool + 'static,
+	TP::InPoolTransaction: Send + Sync,
+	Client: ProvideRuntimeApi<TP::Block> + HeaderBackend<TP::Block> + Send + Sync + 'static,
+	Client::Api: Extractor<TP::Block>,
+	DB: DasKv + Send + Sync + 'static,
+{
+	type Block = TP::Block;
+	type Hash = TP::Hash;
+	type InPoolTransaction = TP::InPoolTransaction;
+	type Error = TP::Error;
+
+	fn submit_at(
+		&self,
+		at: &BlockId<Self::Block>,
+		source: TransactionSource,
+		xts: Vec<TransactionFor<Self>>,
+	) -> PoolFuture<Vec<Result<TxHash<Self>, Self::Error>>, Self::Error> {
+		self.pool.submit_at(at, source, xts)
+	}
+
+	fn submit_one(
+		&self,
+		at: &BlockId<Self::Block>,
+		source: TransactionSource,
+		xt: TransactionFor<Self>,
+	) -> PoolFuture<TxHash<Self>, Self::Error> {
+		self.pool.submit_one(at, source, xt)
+	}
+
+	fn submit_and_watch(
+		&self,
+		at: &BlockId<Self::Block>,
+		source: TransactionSource,
+		xt: TransactionFor<Self>,
+	) -> PoolFuture<Pin<Box<TransactionStatusStreamFor<Self>>>, Self::Error> {
+		self.pool.submit_and_watch(at, source, xt)
+	}
+
+	fn ready_at(
+		&self,
+		at: NumberFor<Self::Block>,
+	) -> Pin<
+		Box<
+			dyn Future<
+					Output = Box<dyn ReadyTransactions<Item = Arc<Self::InPoolTransaction>> + Send>,
+				> + Send,
+		>,
+	> {
+		let ready = self.pool.ready_at(at);
+		let this = Self {
+			client: self.client.clone(),
+			pool: self.pool.clone(),
+			database: self.database.clone(),
+			prepublish: self.prepublish.clone(),
+		};
+		Box::pin(async move {
+			let transactions: Vec<_> = ready.await.collect();
+			let classified = this.classify(&transactions).await;
+			let inclusions = match &this.prepublish {
+				Some(prepublish) => prepublish.settle(&this.database, classified).await,
+				None => classified.into_iter().map(|(inclusion, _)| inclusion).collect(),
+			};
+			Box::new(Reordered::new(inclusions.into_iter().zip(transactions).collect()))
+				as Box<dyn ReadyTransactions<Item = _> + Send>
+		})
+	}
+
+	fn ready(&self) -> Box<dyn ReadyTransactions<Item = Arc<Self::InPoolTransaction>> + Send> {
+		// Taken by the proposer once it stopped waiting for `ready_at`, so nothing is published.
+		let transactions: Vec<_> = self.pool.ready().collect();
+		let sidecars = self.sidecars(&transactions);
+		let mut db = futures::executor::block_on(self.database.lock());
+		let classified = classified(sidecars, transactions.len(), &mut *db);
+		let inclusions = match &self.prepublish {
+			Some(_) => settle_published(&mut *db, classified),
+			None => classified.into_iter().map(|(inclusion, _)| inclusion).collect(),
+		};
+		Box::new(Reordered::new(inclusions.into_iter().zip(transactions).collect()))
+	}
+
+	fn remove_invalid(&self, hashes: &[TxHash<Self>]) -> Vec<Arc<Self::InPoolTransaction>> {
+		self.pool.remove_invalid(hashes)
+	}
+
+	fn status(&self) -> PoolStatus {
+		self.pool.status()
+	}
+
+	fn import_notification_stream(&self) -> ImportNotificationStream<TxHash<Self>> {
+		self.pool.import_notification_stream()
+	}
+
+	fn on_broadcasted(&self, propagations: HashMap<TxHash<Self>, Vec<String>>) {
+		self.pool.on_broadcasted(propagations)
+	}
+
+	fn hash_of(&self, xt: &TransactionFor<Self>) -> TxHash<Self> {
+		self.pool.hash_of(xt)
+	}
+
+	fn ready_transaction(&self, hash: &TxHash<Self>) -> Option<Arc<Self::InPoolTransaction>> {
+		self.pool.ready_transaction(hash)
+	}
+}
+
+/// Ready transactions yielded by [`Inclusion`], then in the order of the pool, each once the
+/// transactions it requires were yielded. [`Inclusion::Withheld`] transactions are left out.
+pub struct Reordered<T> {
+	/// The transactions not yielded yet, in the order of the pool.
+	pending: Vec<(Inclusion, Arc<T>)>,
+	/// The tags provided by the ready transactions, the others are provided by the chain.
+	in_pool: HashSet<TransactionTag>,
+	/// The tags provided by the transactions yielded.
+	provided: HashSet<TransactionTag>,
+}
+
+impl<T: InPoolTransaction> Reordered<T> {
+	/// Reorders `pending`, ready transactions in the order of the pool.
+	pub fn new(pending: Vec<(Inclusion, Arc<T>)>) -> Self {
+		let in_pool = pending.iter().flat_map(|(_, tx)| tx.provides().iter().cloned()).collect();
+		Self { pending, in_pool, provided: HashSet::new() }
+	}
+
+	fn is_ready(&self, tx: &T) -> bool {
+		tx.requires()
+			.iter()
+			.all(|tag| self.provided.contains(tag) || !self.in_pool.contains(tag))
+	}
+}
+
+impl<T: InPoolTransaction> Iterator for Reordered<T> {
+	type Item = Arc<T>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let index = self
+			.pending
+			.iter()
+			.enumerate()
+			.filter(|(_, (inclusion, tx))| *inclusion != Inclusion::Withheld && self.is_ready(tx))
+			.min_by_key(|(index, (inclusion, _))| (*inclusion, *index))
+			.map(|(index, _)| index)?;
+		let (_, tx) = self.pending.remove(index);
+		self.provided.extend(tx.provides().iter().cloned());
+		Some(tx)
+	}
+}
+
+impl<T: InPoolTransaction> ReadyTransactions for Reordered<T> {
+	fn report_invalid(&mut self, tx: &Self::Item) {
+		// The transactions requiring an invalid one are never yielded.
+		for tag in tx.provides() {
+			self.provided.remove(tag);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sc_transaction_pool_api::{TransactionLongevity, TransactionPriority};
+
+	struct Tx {
+		id: u8,
+		requires: Vec<TransactionTag>,
+		provides: Vec<TransactionTag>,
+	}
+
+	impl InPoolTransaction for Tx {
+		type Transaction = u8;
+		type Hash = u8;
+
+		fn data(&self) -> &u8 {
+			&self.id
+		}
+		fn hash(&self) -> &u8 {
+			&self.id
+		}
+		fn priority(&self) -> &TransactionPriority {
+			&0
+		}
+		fn longevity(&self) -> &TransactionLongevity {
+			&0
+		}
+		fn requires(&self) -> &[TransactionTag] {
+			&self.requires
+		}
+		fn provides(&self) -> &[TransactionTag] {
+			&self.provides
+		}
+		fn is_propagable(&self) -> bool {
+			true
+		}
+	}
+
+	fn tx(id: u8, inclusion: Inclusion, requires: &[u8], provides: &[u8]) -> (Inclusion, Arc<Tx>) {
+		let tags = |tags: &[u8]| tags.iter().map(|tag| vec![*tag]).collect();
+		(inclusion, Arc::new(Tx { id, requires: tags(requires), provides: tags(provides) }))
+	}
+
+	#[test]
+	fn test_reordered_by_inclusion() {
+		let ready = Reordered::new(vec![
+			tx(1, Inclusion::Deferred, &[], &[1]),
+			tx(2, Inclusion::Normal, &[], &[2]),
+			tx(3, Inclusion::Boosted, &[], &[3]),
+			tx(4, Inclusion::Normal, &[], &[4]),
+		]);
+		assert_eq!(ready.map(|tx| tx.id).collect::<Vec<_>>(), vec![3, 2, 4, 1]);
+	}
+
+	#[test]
+	fn test_reordered_keeps_dependencies() {
+		// The second transaction of a sender can not be boosted ahead of the first one.
+		let ready = Reordered::new(vec![
+			tx(1, Inclusion::Deferred, &[0], &[1]),
+			tx(2, Inclusion::Boosted, &[1], &[2]),
+			tx(3, Inclusion::Normal, &[], &[3]),
+		]);
+		assert_eq!(ready.map(|tx| tx.id).collect::<Vec<_>>(), vec![3, 1, 2]);
+	}
+
+	#[test]
+	fn test_reordered_leaves_out_withheld() {
+		let ready = Reordered::new(vec![
+			tx(1, Inclusion::Withheld, &[], &[1]),
+			tx(2, Inclusion::Boosted, &[1], &[2]),
+			tx(3, Inclusion::Deferred, &[], &[3]),
+		]);
+		assert_eq!(ready.map(|tx| tx.id).collect::<Vec<_>>(), vec![3]);
+	}
+
+	#[test]
+	fn test_reordered_skips_dependents_of_invalid() {
+		let mut ready = Reordered::new(vec![
+			tx(1, Inclusion::Boosted, &[], &[1]),
+			tx(2, Inclusion::Boosted, &[1], &[2]),
+			tx(3, Inclusion::Normal, &[], &[3]),
+		]);
+		let first = ready.next().unwrap();
+		assert_eq!(first.id, 1);
+		ready.report_invalid(&first);
+		assert_eq!(ready.map(|tx| tx.id).collect::<Vec<_>>(), vec![3]);
+	}
+}
@@ -0,0 +1,195 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verification of the data of sidecars against their commitments and proofs.
+//!
+//! [`SidecarVerifier`] runs the verification on the blocking task pool, splitting the blobs of a
+//! sidecar between threads, or on the [`KzgWorkerPool`] of the node if it is given one. It
+//! remembers the data it recently verified so that the same data is not verified twice, for
+//! instance when a submission is retried.
+use crate::FIELD_ELEMENTS_PER_BLOB;
+use lru::LruCache;
+use melo_core_primitives::{KzgWorkerPool, Sidecar, SidecarMetadata};
+use melo_das_primitives::{Blob, KZG};
+use melo_erasure_coding::bytes_to_blobs;
+use std::{num::NonZeroUsize, sync::Mutex, thread};
+
+/// The default number of sidecars remembered as verified.
+pub const DEFAULT_VERIFIED_CACHE_SIZE: usize = 1024;
+
+/// Verifies the data of sidecars, remembering the data recently verified.
+pub struct SidecarVerifier {
+	/// The hash of the data verified for each sidecar, by the hash of its metadata.
+	verified: Mutex<LruCache<[u8; 32], [u8; 32]>>,
+	/// The pool the verifications run on, the blocking task pool if `None`.
+	pool: Option<KzgWorkerPool>,
+}
+
+impl Default for SidecarVerifier {
+	fn default() -> Self {
+		Self::new(DEFAULT_VERIFIED_CACHE_SIZE)
+	}
+}
+
+impl SidecarVerifier {
+	/// Creates a verifier remembering up to `capacity` sidecars.
+	pub fn new(capacity: usize) -> Self {
+		let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+		Self { verified: Mutex::new(LruCache::new(capacity)), pool: None }
+	}
+
+	/// Runs the verifications on `pool` rather than on the blocking task pool.
+	pub fn with_pool(mut self, pool: KzgWorkerPool) -> Self {
+		self.pool = Some(pool);
+		self
+	}
+
+	/// Returns whether data matching the sidecar of `data_hash` was recently verified.
+	pub fn is_verified(&self, data_hash: &[u8; 32]) -> bool {
+		self.verified.lock().expect("Lock is not poisoned; qed").contains(data_hash)
+	}
+
+	/// Remembers `bytes` as verified for `metadata`, whose commitments and proofs were computed
+	/// from them locally.
+	pub fn mark_verified(&self, metadata: &SidecarMetadata, bytes: &[u8]) {
+		self.verified
+			.lock()
+			.expect("Lock is not poisoned; qed")
+			.put(metadata.id(), Sidecar::calculate_id(bytes));
+	}
+
+	/// Verifies `bytes` against the commitments and proofs of `metadata` on the KZG worker pool or
+	/// the blocking task pool, unless the same data was recently verified.
+	pub async fn verify(&self, metadata: &SidecarMetadata, bytes: Vec<u8>) -> Result<bool, String> {
+		let data_hash = metadata.id();
+		let bytes_hash = Sidecar::calculate_id(&bytes);
+		if self.verified.lock().expect("Lock is not poisoned; qed").get(&data_hash) ==
+			Some(&bytes_hash)
+		{
+			return Ok(true)
+		}
+
+		let owned = metadata.clone();
+		let verified = match &self.pool {
+			Some(pool) => pool.verify_sidecar(owned, bytes).await?,
+			None => tokio::task::spawn_blocking(move || verify_parallel(&owned, &bytes))
+				.await
+				.map_err(|e| format!("Verification task failed: {}", e))??,
+		};
+
+		if verified {
+			self.verified
+				.lock()
+				.expect("Lock is not poisoned; qed")
+				.put(data_hash, bytes_hash);
+		}
+		Ok(verified)
+	}
+}
+
+/// Verifies `bytes` against the commitments and proofs of `metadata`, as
+/// [`SidecarMetadata::verify_bytes`] does.
+///
+/// A batch proof takes a single pairing check whatever the number of blobs. With a proof per
+/// blob, the blobs are split between the available threads, each checking its share in a batch.
+pub fn verify_parallel(metadata: &SidecarMetadata, bytes: &[u8]) -> Result<bool, String> {
+	let kzg = KZG::default_embedded();
+	let blobs = bytes_to_blobs(bytes, FIELD_ELEMENTS_PER_BLOB)?;
+
+	if metadata.has_batch_proof() {
+		return Blob::verify_blobs_proof_batch(
+			&blobs,
+			&metadata.commitments,
+			&metadata.proofs[0],
+			&kzg,
+			FIELD_ELEMENTS_PER_BLOB,
+		)
+	}
+
+	if blobs.is_empty() ||
+		blobs.len() != metadata.commitments.len() ||
+		blobs.len() != metadata.proofs.len()
+	{
+		return Err(format!(
+			"Invalid input length. Expected {} got commitments: {} and proofs: {}",
+			blobs.len(),
+			metadata.commitments.len(),
+			metadata.proofs.len()
+		))
+	}
+
+	let threads = thread::available_parallelism().map_or(1, |n| n.get()).min(blobs.len());
+	let chunk_size = (blobs.len() + threads - 1) / threads;
+	let kzg = &kzg;
+
+	thread::scope(|scope| {
+		let handles = blobs
+			.chunks(chunk_size)
+			.zip(metadata.commitments.chunks(chunk_size))
+			.zip(metadata.proofs.chunks(chunk_size))
+			.map(|((blobs, commitments), proofs)| {
+				scope.spawn(move || {
+					Blob::verify_batch(blobs, commitments, proofs, kzg, FIELD_ELEMENTS_PER_BLOB)
+				})
+			})
+			.collect::<Vec<_>>();
+
+		handles.into_iter().try_fold(true, |verified, handle| {
+			let chunk_verified =
+				handle.join().map_err(|_| "Verification thread panicked".to_string())??;
+			Ok(verified && chunk_verified)
+		})
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use melo_das_primitives::config::BYTES_PER_BLOB;
+
+	#[test]
+	fn test_verify_parallel() {
+		let data = (0..BYTES_PER_BLOB * 3).map(|i| i as u8).collect::<Vec<_>>();
+
+		let metadata = SidecarMetadata::try_from_app_data(&data, 1, 1).unwrap();
+		assert_eq!(verify_parallel(&metadata, &data), Ok(true));
+		assert_eq!(verify_parallel(&metadata, &data), metadata.verify_bytes(&data));
+
+		let batch = SidecarMetadata::try_from_app_data_with_batch_proof(&data, 1, 1).unwrap();
+		assert_eq!(verify_parallel(&batch, &data), Ok(true));
+
+		let mut tampered = data.clone();
+		tampered[BYTES_PER_BLOB * 2 + 5] ^= 1;
+		assert_eq!(verify_parallel(&metadata, &tampered), Ok(false));
+		assert_eq!(verify_parallel(&batch, &tampered), Ok(false));
+	}
+
+	#[test]
+	fn test_verifier_remembers_verified_data() {
+		let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+		let data = (0..1000).map(|i| i as u8).collect::<Vec<_>>();
+		let metadata = SidecarMetadata::try_from_app_data(&data, 1, 1).unwrap();
+		let verifier = SidecarVerifier::new(2);
+
+		assert!(!verifier.is_verified(&metadata.id()));
+		assert_eq!(runtime.block_on(verifier.verify(&metadata, data.clone())), Ok(true));
+		assert!(verifier.is_verified(&metadata.id()));
+
+		// Other data is still verified against the commitments.
+		let mut tampered = data.clone();
+		tampered[0] ^= 1;
+		assert_eq!(runtime.block_on(verifier.verify(&metadata, tampered)), Ok(false));
+		assert_eq!(runtime.block_on(verifier.verify(&metadata, data)), Ok(true));
+	}
+}
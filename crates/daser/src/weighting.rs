@@ -0,0 +1,277 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Weighted sampling.
+//!
+//! By default the samples of a block are drawn uniformly from its extended matrix. With
+//! [`SamplingWeights`], only a baseline share of them is, and the others are drawn from rows picked
+//! in proportion to their weight: the rows of the applications the node cares about, and the rows
+//! whose samples failed in the last blocks, are sampled more often. The [`FailureHistory`] keeps
+//! the rows of the failed samples of the last [`FAILURE_WINDOW`] blocks.
+use crate::{Position, Sample};
+use melo_core_primitives::{
+	config::{Rows, MATRIX},
+	reliability::Permill,
+	AppLookup,
+};
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng};
+use std::collections::{BTreeMap, VecDeque};
+
+/// The number of last sampled blocks whose failed samples weigh on the rows.
+pub const FAILURE_WINDOW: usize = 16;
+
+/// The default share of the samples drawn uniformly when the sampling is weighted.
+pub const DEFAULT_UNIFORM_SHARE: Permill = Permill::from_percent(50);
+
+/// The weights of the rows of a block in its sampling.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SamplingWeights {
+	/// The share of the samples of a block drawn uniformly from its whole matrix, whatever the
+	/// weights.
+	pub uniform_share: Permill,
+	/// The weight of the rows of each application, the other rows weigh 1.
+	pub app_weights: BTreeMap<u32, u32>,
+	/// The weight added to a row for each of its samples that failed in the last
+	/// [`FAILURE_WINDOW`] blocks.
+	pub failure_weight: u32,
+}
+
+impl Default for SamplingWeights {
+	fn default() -> Self {
+		SamplingWeights {
+			uniform_share: DEFAULT_UNIFORM_SHARE,
+			app_weights: BTreeMap::new(),
+			failure_weight: 0,
+		}
+	}
+}
+
+impl SamplingWeights {
+	/// Returns `true` if all the samples are drawn uniformly.
+	pub fn is_uniform(&self) -> bool {
+		self.uniform_share == Permill::one() ||
+			(self.app_weights.is_empty() && self.failure_weight == 0)
+	}
+
+	/// Checks that the weights are consistent.
+	pub fn validate(&self) -> Result<(), String> {
+		match self.app_weights.iter().find(|(_, weight)| **weight == 0) {
+			Some((app_id, _)) => Err(format!("The weight of app {} must be at least 1", app_id)),
+			None => Ok(()),
+		}
+	}
+
+	/// Returns the weight of the row `y` of a block of `rows` rows laid out by `app_lookups`.
+	///
+	/// Only the original rows belong to an application, the rows of the extension weigh 1.
+	fn row_weight(&self, rows: Rows, y: u32, app_lookups: &[AppLookup], failures: u32) -> u32 {
+		let app_weight = if y < rows.0 / 2 {
+			AppLookup::get_lookup(app_lookups, y)
+				.and_then(|(lookup, _)| self.app_weights.get(&lookup.app_id).copied())
+				.unwrap_or(1)
+		} else {
+			1
+		};
+		app_weight.saturating_add(self.failure_weight.saturating_mul(failures))
+	}
+
+	/// Draws `n` distinct positions of the extended matrix of `rows` rows, laid out by
+	/// `app_lookups`, weighing the rows with their failures in `history`.
+	///
+	/// The positions are fewer than `n` only if the matrix has fewer cells.
+	pub fn positions<R: Rng>(
+		&self,
+		rng: &mut R,
+		n: usize,
+		rows: Rows,
+		app_lookups: &[AppLookup],
+		history: &FailureHistory,
+	) -> Vec<Position> {
+		if rows.0 == 0 {
+			return Vec::new()
+		}
+
+		let n = n.min(MATRIX.cells(rows).0 as usize);
+		let cols = MATRIX.extended_cols();
+		let mut positions = Vec::with_capacity(n);
+
+		let weighted = n - self.uniform_share.mul_ceil(n as u32) as usize;
+		let weights = (0..rows.0)
+			.map(|y| self.row_weight(rows, y, app_lookups, history.failures(y)))
+			.collect::<Vec<_>>();
+		if let Ok(rows_index) = WeightedIndex::new(&weights) {
+			// A heavy row may run out of cells, so the draws are bounded and the uniform samples
+			// make up for the missing ones.
+			let mut draws = 0;
+			while positions.len() < weighted && draws < weighted * 16 {
+				draws += 1;
+				let pos =
+					Position { x: rng.gen_range(0..cols.0), y: rows_index.sample(rng) as u32 };
+				if !positions.contains(&pos) {
+					positions.push(pos);
+				}
+			}
+		}
+
+		while positions.len() < n {
+			let pos = Position { x: rng.gen_range(0..cols.0), y: rng.gen_range(0..rows.0) };
+			if !positions.contains(&pos) {
+				positions.push(pos);
+			}
+		}
+
+		positions
+	}
+}
+
+/// Parses the weight of the rows of an application, given as `APP_ID=WEIGHT`.
+pub fn parse_app_weight(s: &str) -> Result<(u32, u32), String> {
+	let (app_id, weight) =
+		s.split_once('=').ok_or_else(|| format!("Expected APP_ID=WEIGHT, got {}", s))?;
+	let app_id = app_id.trim().parse().map_err(|e| format!("Invalid app ID {}: {}", app_id, e))?;
+	let weight = weight.trim().parse().map_err(|e| format!("Invalid weight {}: {}", weight, e))?;
+	Ok((app_id, weight))
+}
+
+/// The rows of the failed samples of the last [`FAILURE_WINDOW`] sampled blocks.
+#[derive(Debug, Clone, Default)]
+pub struct FailureHistory {
+	blocks: VecDeque<Vec<u32>>,
+}
+
+impl FailureHistory {
+	/// Records the failed `samples` of a newly sampled block, forgetting the failures of the
+	/// oldest block beyond the window.
+	pub fn record(&mut self, samples: &[Sample]) {
+		if self.blocks.len() == FAILURE_WINDOW {
+			self.blocks.pop_front();
+		}
+		self.blocks.push_back(
+			samples
+				.iter()
+				.filter(|sample| !sample.is_availability)
+				.map(|sample| sample.position.y)
+				.collect(),
+		);
+	}
+
+	/// Returns the number of failed samples in the row `y` in the window.
+	pub fn failures(&self, y: u32) -> u32 {
+		self.blocks.iter().flatten().filter(|row| **row == y).count() as u32
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::SampleId;
+
+	fn sample(y: u32, is_availability: bool) -> Sample {
+		Sample { id: SampleId::default(), position: Position { x: 0, y }, is_availability }
+	}
+
+	#[test]
+	fn test_uniform_weights() {
+		let weights = SamplingWeights::default();
+		assert!(weights.is_uniform());
+		assert!(SamplingWeights { failure_weight: 1, uniform_share: Permill::one(), ..weights }
+			.is_uniform());
+		assert!(!SamplingWeights { failure_weight: 1, ..Default::default() }.is_uniform());
+
+		let weights =
+			SamplingWeights { app_weights: BTreeMap::from([(1, 0)]), ..Default::default() };
+		assert!(weights.validate().is_err());
+
+		assert_eq!(parse_app_weight("3=20"), Ok((3, 20)));
+		assert!(parse_app_weight("3").is_err());
+		assert!(parse_app_weight("a=1").is_err());
+	}
+
+	#[test]
+	fn test_positions_are_distinct() {
+		let weights = SamplingWeights {
+			uniform_share: Permill::zero(),
+			app_weights: BTreeMap::from([(1, 1_000)]),
+			..Default::default()
+		};
+		let app_lookups = vec![AppLookup { app_id: 1, nonce: 1, count: 1 }];
+		let rows = Rows(4);
+		let n = MATRIX.extended_cols().0 as usize + 64;
+		let mut rng = rand::thread_rng();
+
+		// More samples than the cells of the heavy row.
+		let positions =
+			weights.positions(&mut rng, n, rows, &app_lookups, &FailureHistory::default());
+		assert_eq!(positions.len(), n);
+		for (i, pos) in positions.iter().enumerate() {
+			assert!(pos.y < rows.0);
+			assert!(!positions[..i].contains(pos));
+		}
+
+		assert!(weights
+			.positions(&mut rng, 8, Rows(0), &app_lookups, &FailureHistory::default())
+			.is_empty());
+	}
+
+	#[test]
+	fn test_app_rows_are_oversampled() {
+		let weights = SamplingWeights {
+			uniform_share: Permill::from_percent(25),
+			app_weights: BTreeMap::from([(2, 10_000)]),
+			..Default::default()
+		};
+		let app_lookups = vec![
+			AppLookup { app_id: 1, nonce: 1, count: 3 },
+			AppLookup { app_id: 2, nonce: 1, count: 1 },
+		];
+		let mut rng = rand::thread_rng();
+
+		let positions =
+			weights.positions(&mut rng, 40, Rows(16), &app_lookups, &FailureHistory::default());
+		assert_eq!(positions.len(), 40);
+		// The 30 weighted samples fall in the row of app 2 but for rare exceptions.
+		assert!(positions.iter().filter(|pos| pos.y == 3).count() >= 28);
+	}
+
+	#[test]
+	fn test_failing_rows_are_oversampled() {
+		let weights = SamplingWeights {
+			uniform_share: Permill::zero(),
+			failure_weight: 100,
+			..Default::default()
+		};
+		let mut history = FailureHistory::default();
+		history.record(&[sample(5, false), sample(5, false), sample(6, true)]);
+		assert_eq!(history.failures(5), 2);
+		assert_eq!(history.failures(6), 0);
+
+		let mut rng = rand::thread_rng();
+		let positions = weights.positions(&mut rng, 20, Rows(8), &[], &history);
+		assert!(positions.iter().filter(|pos| pos.y == 5).count() >= 15);
+	}
+
+	#[test]
+	fn test_failures_leave_the_window() {
+		let mut history = FailureHistory::default();
+		history.record(&[sample(1, false)]);
+		for _ in 1..FAILURE_WINDOW {
+			history.record(&[sample(2, true)]);
+		}
+		assert_eq!(history.failures(1), 1);
+
+		history.record(&[sample(2, false)]);
+		assert_eq!(history.failures(1), 0);
+		assert_eq!(history.failures(2), 1);
+	}
+}
@@ -0,0 +1,219 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Collection of the attestations that blocks are withheld.
+//!
+//! When a finalized block fails its sampling, the node signs an unavailable
+//! [`AvailabilityReceipt`] for it with its receipt key. The receipts of the other attesters are
+//! added through the RPC, and once enough of them are collected, anyone can submit them to
+//! `pallet-withholding` to report the author of the block.
+//!
+//! Only the receipts of the attesters set on chain are kept, see
+//! [`WithholdingCollector::set_attesters`], and at most as many of them for each block as there
+//! can be attesters.
+use crate::{Arc, Reliability};
+use lru::LruCache;
+use melo_core_primitives::receipt::{AvailabilityReceipt, RECEIPT_KEY_TYPE};
+use sp_core::{sr25519, H256};
+use sp_keystore::KeystorePtr;
+use std::{
+	num::NonZeroUsize,
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Mutex, RwLock,
+	},
+};
+
+/// The default number of blocks the attestations are kept for.
+pub const DEFAULT_WITHHOLDING_CACHE_SIZE: usize = 256;
+
+/// Keeps the withholding attestations of the recent blocks.
+pub struct WithholdingCollector {
+	receipts: Mutex<LruCache<H256, Vec<AvailabilityReceipt>>>,
+	attesters: RwLock<Vec<sr25519::Public>>,
+	max_per_block: AtomicUsize,
+	keystore: Option<KeystorePtr>,
+}
+
+impl Default for WithholdingCollector {
+	fn default() -> Self {
+		Self::new(DEFAULT_WITHHOLDING_CACHE_SIZE)
+	}
+}
+
+impl WithholdingCollector {
+	/// Creates a collector keeping the attestations of the last `capacity` blocks.
+	pub fn new(capacity: usize) -> Self {
+		let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+		Self {
+			receipts: Mutex::new(LruCache::new(capacity)),
+			attesters: Default::default(),
+			max_per_block: AtomicUsize::new(0),
+			keystore: None,
+		}
+	}
+
+	/// Signs the attestations of the node with the first key of type [`RECEIPT_KEY_TYPE`] in
+	/// `keystore`.
+	pub fn with_keystore(mut self, keystore: KeystorePtr) -> Self {
+		self.keystore = Some(keystore);
+		self
+	}
+
+	/// Replaces the attesters whose receipts are accepted by `attesters`, and the maximum number
+	/// of attestations kept for a block by `max_attesters`, as the `WithholdingApi` of the runtime
+	/// returns them.
+	pub fn set_attesters(&self, attesters: Vec<sr25519::Public>, max_attesters: u32) {
+		*self.attesters.write().expect("Lock poisoned") = attesters;
+		self.max_per_block.store(max_attesters as usize, Ordering::Relaxed);
+	}
+
+	/// Returns whether `signer` is one of the attesters.
+	pub fn is_attester(&self, signer: &sr25519::Public) -> bool {
+		self.attesters.read().expect("Lock poisoned").contains(signer)
+	}
+
+	/// Adds the attestation `receipt`.
+	///
+	/// Returns `false` if the receipt is not signed by its signer, its signer is not an attester,
+	/// it shows the data available, its signer already attested the block or the block has as many
+	/// attestations as there can be attesters.
+	pub fn add(&self, receipt: AvailabilityReceipt) -> bool {
+		if receipt.available || !self.is_attester(&receipt.signer) || receipt.verify().is_err() {
+			return false
+		}
+		let mut receipts = self.receipts.lock().expect("Lock poisoned");
+		let attestations = receipts.get_or_insert_mut(receipt.block_hash, Vec::new);
+		if attestations.len() >= self.max_per_block.load(Ordering::Relaxed) ||
+			attestations.iter().any(|attestation| attestation.signer == receipt.signer)
+		{
+			return false
+		}
+		attestations.push(receipt);
+		true
+	}
+
+	/// Returns the attestations collected for the block `block_hash`.
+	pub fn attestations(&self, block_hash: &H256) -> Vec<AvailabilityReceipt> {
+		let mut receipts = self.receipts.lock().expect("Lock poisoned");
+		receipts.get(block_hash).cloned().unwrap_or_default()
+	}
+
+	/// Attests that the block `block_hash` is withheld if `confidence` shows its data unavailable,
+	/// and keeps the attestation if the node is one of the attesters.
+	///
+	/// Returns `None` if the data is available or the node has no key to sign with.
+	pub fn attest(
+		&self,
+		block_hash: &[u8],
+		confidence: &Reliability,
+	) -> Option<AvailabilityReceipt> {
+		if confidence.is_availability() || block_hash.len() != H256::len_bytes() {
+			return None
+		}
+		let keystore = self.keystore.as_ref()?;
+		let signer = *keystore.sr25519_public_keys(RECEIPT_KEY_TYPE).first()?;
+
+		let mut receipt =
+			AvailabilityReceipt::unsigned(H256::from_slice(block_hash), confidence, signer);
+		receipt.signature = keystore
+			.sr25519_sign(RECEIPT_KEY_TYPE, &signer, &receipt.payload())
+			.ok()
+			.flatten()?;
+		self.add(receipt.clone());
+		Some(receipt)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Sample;
+	use melo_core_primitives::reliability::ReliabilityType;
+	use melo_das_primitives::Position;
+	use sp_core::{sr25519, Pair};
+	use sp_keystore::{testing::MemoryKeystore, Keystore};
+
+	fn sampled(success: usize) -> Reliability {
+		let mut confidence = Reliability::new(ReliabilityType::Block, &[]);
+		confidence.samples = (0..8)
+			.map(|x| Sample {
+				position: Position { x, y: 0 },
+				is_availability: (x as usize) < success,
+				..Default::default()
+			})
+			.collect();
+		confidence
+	}
+
+	#[test]
+	fn test_add_attestations() {
+		let collector = WithholdingCollector::default();
+		let hash = H256::repeat_byte(1);
+		let pair = sr25519::Pair::from_seed(&[1u8; 32]);
+		let other = sr25519::Pair::from_seed(&[2u8; 32]);
+
+		// Only the receipts of the attesters are kept
+		let receipt = AvailabilityReceipt::sign(hash, &sampled(3), &pair);
+		assert!(!collector.add(receipt.clone()));
+		collector.set_attesters(vec![pair.public(), other.public()], 2);
+		assert!(collector.add(receipt.clone()));
+		// A signer attests a block once
+		assert!(!collector.add(receipt.clone()));
+		assert_eq!(collector.attestations(&hash), vec![receipt]);
+
+		// Receipts of available data are not attestations
+		assert!(!collector.add(AvailabilityReceipt::sign(hash, &sampled(8), &other)));
+
+		let mut tampered = AvailabilityReceipt::sign(hash, &sampled(3), &other);
+		tampered.block_hash = H256::repeat_byte(2);
+		assert!(!collector.add(tampered));
+		assert!(collector.attestations(&H256::repeat_byte(2)).is_empty());
+	}
+
+	#[test]
+	fn test_attestations_per_block_are_capped() {
+		const MAX: usize = 4;
+		let collector = WithholdingCollector::default();
+		let hash = H256::repeat_byte(1);
+		let pairs = (0..=MAX).map(|i| sr25519::Pair::from_seed(&[i as u8; 32])).collect::<Vec<_>>();
+		collector.set_attesters(pairs.iter().map(|pair| pair.public()).collect(), MAX as u32);
+
+		for pair in &pairs[..MAX] {
+			assert!(collector.add(AvailabilityReceipt::sign(hash, &sampled(3), pair)));
+		}
+		let last = &pairs[MAX];
+		assert!(!collector.add(AvailabilityReceipt::sign(hash, &sampled(3), last)));
+		assert_eq!(collector.attestations(&hash).len(), MAX);
+	}
+
+	#[test]
+	fn test_attest() {
+		let hash = H256::repeat_byte(1);
+		assert!(WithholdingCollector::default().attest(hash.as_bytes(), &sampled(3)).is_none());
+
+		let keystore = Arc::new(MemoryKeystore::new());
+		let signer = keystore.sr25519_generate_new(RECEIPT_KEY_TYPE, None).unwrap();
+		let collector = WithholdingCollector::default().with_keystore(keystore);
+		collector.set_attesters(vec![signer], 1);
+
+		assert!(collector.attest(hash.as_bytes(), &sampled(8)).is_none());
+
+		let receipt = collector.attest(hash.as_bytes(), &sampled(3)).unwrap();
+		assert_eq!(receipt.signer, signer);
+		assert!(!receipt.available);
+		assert_eq!(receipt.verify(), Ok(()));
+		assert_eq!(collector.attestations(&hash), vec![receipt]);
+	}
+}
@@ -0,0 +1,286 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! SQLite index of a Melodot chain for explorers.
+//!
+//! The node writes the blocks of the best chain and their blobs as they are imported, then the
+//! claims of the farmers and the statistics of the apps as the blocks are finalized, and the
+//! confidence of the blocks once they are sampled. The schema is documented in `schema.sql`, an
+//! explorer reads the database directly.
+
+use rusqlite::{params, Connection, OptionalExtension, Result};
+use std::path::Path;
+
+/// The schema of the index, applied when the database is opened.
+pub const SCHEMA: &str = include_str!("schema.sql");
+
+/// A blob to index.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlobRecord {
+	/// The hash of the sidecar metadata of the blob.
+	pub hash: [u8; 32],
+	/// The ID of the app.
+	pub app_id: u32,
+	/// The nonce of the submission.
+	pub nonce: u32,
+	/// The length of the data in bytes.
+	pub size: u32,
+	/// The first row of the blob in the block matrix.
+	pub start_row: u32,
+	/// The number of rows of the blob.
+	pub rows: u32,
+	/// The merkle root of the commitments of the rows of the blob.
+	pub commitments_root: [u8; 32],
+}
+
+/// A reward claimed by a farmer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClaimRecord {
+	/// The index of the claim event in the block.
+	pub event_index: u32,
+	/// The account the reward is paid to.
+	pub account: Vec<u8>,
+	/// The reward.
+	pub reward: u128,
+}
+
+/// The statistics of an app over the finalized blocks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AppStats {
+	/// The number of blobs submitted.
+	pub blobs: u64,
+	/// The number of bytes submitted.
+	pub bytes: u64,
+	/// The first block including a blob of the app.
+	pub first_block: u32,
+	/// The last block including a blob of the app.
+	pub last_block: u32,
+}
+
+/// The index, an SQLite database following [`SCHEMA`].
+pub struct Indexer {
+	conn: Connection,
+}
+
+impl Indexer {
+	/// Opens the index at `path`, creating it if needed.
+	pub fn open(path: &Path) -> Result<Self> {
+		Self::with_connection(Connection::open(path)?)
+	}
+
+	/// Opens an index held in memory.
+	pub fn open_in_memory() -> Result<Self> {
+		Self::with_connection(Connection::open_in_memory()?)
+	}
+
+	fn with_connection(conn: Connection) -> Result<Self> {
+		conn.execute_batch(SCHEMA)?;
+		Ok(Self { conn })
+	}
+
+	/// Returns whether the block `number` is indexed as finalized.
+	pub fn is_finalized(&self, number: u32) -> Result<bool> {
+		Ok(self
+			.conn
+			.query_row("SELECT finalized FROM blocks WHERE number = ?", params![number], |row| {
+				row.get::<_, bool>(0)
+			})
+			.optional()?
+			.unwrap_or_default())
+	}
+
+	/// Indexes the block `number` of hash `hash` with its `blobs`, as imported on the best chain.
+	///
+	/// The block replaces the one indexed at the same height, unless that one is finalized.
+	pub fn import_block(&mut self, number: u32, hash: &[u8], blobs: &[BlobRecord]) -> Result<()> {
+		if self.is_finalized(number)? {
+			return Ok(())
+		}
+		let tx = self.conn.transaction()?;
+		write_block(&tx, number, hash, blobs, false)?;
+		tx.commit()
+	}
+
+	/// Indexes the block `number` of hash `hash` as finalized, with its `blobs` and `claims`, and
+	/// adds its blobs to the statistics of their apps.
+	///
+	/// Finalizing a block twice has no effect.
+	pub fn finalize_block(
+		&mut self,
+		number: u32,
+		hash: &[u8],
+		blobs: &[BlobRecord],
+		claims: &[ClaimRecord],
+	) -> Result<()> {
+		if self.is_finalized(number)? {
+			return Ok(())
+		}
+		let tx = self.conn.transaction()?;
+		write_block(&tx, number, hash, blobs, true)?;
+		for claim in claims {
+			tx.execute(
+				"INSERT OR REPLACE INTO claims (block_number, event_index, account, reward)
+					VALUES (?, ?, ?, ?)",
+				params![number, claim.event_index, claim.account, claim.reward.to_string()],
+			)?;
+		}
+		for blob in blobs {
+			tx.execute(
+				"INSERT INTO apps (app_id, blobs, bytes, first_block, last_block)
+					VALUES (?1, 1, ?2, ?3, ?3)
+					ON CONFLICT (app_id) DO UPDATE SET
+						blobs = blobs + 1,
+						bytes = bytes + excluded.bytes,
+						first_block = MIN(first_block, excluded.first_block),
+						last_block = MAX(last_block, excluded.last_block)",
+				params![blob.app_id, blob.size, number],
+			)?;
+		}
+		tx.commit()
+	}
+
+	/// Records the confidence of the node in the block `number` and whether its samples show the
+	/// data available.
+	pub fn set_confidence(
+		&mut self,
+		number: u32,
+		confidence: Option<u32>,
+		available: bool,
+	) -> Result<()> {
+		self.conn.execute(
+			"UPDATE blocks SET confidence = ?, available = ? WHERE number = ?",
+			params![confidence, available, number],
+		)?;
+		Ok(())
+	}
+
+	/// Returns the numbers and hashes of the finalized blocks with blobs from `from` on whose
+	/// samples do not show the data available yet.
+	pub fn unsampled_blocks(&self, from: u32) -> Result<Vec<(u32, Vec<u8>)>> {
+		let mut stmt = self.conn.prepare(
+			"SELECT number, hash FROM blocks
+				WHERE number >= ? AND finalized = 1 AND blobs > 0 AND available IS NOT 1
+				ORDER BY number",
+		)?;
+		let blocks = stmt.query_map(params![from], |row| Ok((row.get(0)?, row.get(1)?)))?;
+		blocks.collect()
+	}
+
+	/// Returns the statistics of the app `app_id`, if it submitted blobs to a finalized block.
+	pub fn app_stats(&self, app_id: u32) -> Result<Option<AppStats>> {
+		self.conn
+			.query_row(
+				"SELECT blobs, bytes, first_block, last_block FROM apps WHERE app_id = ?",
+				params![app_id],
+				|row| {
+					Ok(AppStats {
+						blobs: row.get(0)?,
+						bytes: row.get(1)?,
+						first_block: row.get(2)?,
+						last_block: row.get(3)?,
+					})
+				},
+			)
+			.optional()
+	}
+}
+
+/// Replaces the block `number` and its blobs in `conn`.
+fn write_block(
+	conn: &Connection,
+	number: u32,
+	hash: &[u8],
+	blobs: &[BlobRecord],
+	finalized: bool,
+) -> Result<()> {
+	conn.execute("DELETE FROM blobs WHERE block_number = ?", params![number])?;
+	conn.execute(
+		"INSERT OR REPLACE INTO blocks (number, hash, blobs, finalized) VALUES (?, ?, ?, ?)",
+		params![number, hash, blobs.len() as u32, finalized],
+	)?;
+	for (idx, blob) in blobs.iter().enumerate() {
+		conn.execute(
+			"INSERT INTO blobs
+				(block_number, idx, hash, app_id, nonce, size, start_row, rows, commitments_root)
+				VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+			params![
+				number,
+				idx as u32,
+				&blob.hash[..],
+				blob.app_id,
+				blob.nonce,
+				blob.size,
+				blob.start_row,
+				blob.rows,
+				&blob.commitments_root[..],
+			],
+		)?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn blob(app_id: u32, size: u32) -> BlobRecord {
+		BlobRecord {
+			hash: [app_id as u8; 32],
+			app_id,
+			nonce: 1,
+			size,
+			start_row: 0,
+			rows: 1,
+			commitments_root: [0; 32],
+		}
+	}
+
+	#[test]
+	fn test_reorg_replaces_unfinalized_blocks() {
+		let mut indexer = Indexer::open_in_memory().unwrap();
+		indexer.import_block(1, &[1; 32], &[blob(1, 10), blob(2, 20)]).unwrap();
+		indexer.import_block(1, &[2; 32], &[blob(1, 10)]).unwrap();
+
+		let blobs: u32 = indexer
+			.conn
+			.query_row("SELECT COUNT(*) FROM blobs WHERE block_number = 1", [], |row| row.get(0))
+			.unwrap();
+		assert_eq!(blobs, 1);
+		assert_eq!(indexer.app_stats(1).unwrap(), None);
+	}
+
+	#[test]
+	fn test_finalize_block_counts_once() {
+		let mut indexer = Indexer::open_in_memory().unwrap();
+		let claims = vec![ClaimRecord { event_index: 3, account: vec![7; 32], reward: u128::MAX }];
+		indexer.finalize_block(2, &[2; 32], &[blob(1, 10)], &claims).unwrap();
+		indexer.finalize_block(2, &[2; 32], &[blob(1, 10)], &claims).unwrap();
+		indexer.finalize_block(5, &[5; 32], &[blob(1, 30)], &[]).unwrap();
+		indexer.import_block(5, &[6; 32], &[]).unwrap();
+
+		assert_eq!(
+			indexer.app_stats(1).unwrap(),
+			Some(AppStats { blobs: 2, bytes: 40, first_block: 2, last_block: 5 })
+		);
+		let reward: String = indexer
+			.conn
+			.query_row("SELECT reward FROM claims WHERE block_number = 2", [], |row| row.get(0))
+			.unwrap();
+		assert_eq!(reward, u128::MAX.to_string());
+
+		assert_eq!(indexer.unsampled_blocks(0).unwrap().len(), 2);
+		indexer.set_confidence(2, Some(9999), true).unwrap();
+		assert_eq!(indexer.unsampled_blocks(0).unwrap(), vec![(5, vec![5; 32])]);
+	}
+}
@@ -0,0 +1,59 @@
+-- Schema of the Melodot explorer index.
+--
+-- Hashes and accounts are stored as raw bytes, amounts as decimal strings since they do not fit
+-- in a 64-bit integer. Rows of blocks that are not finalized yet are replaced on reorgs.
+
+-- The blocks of the best chain, one per number.
+CREATE TABLE IF NOT EXISTS blocks (
+	number INTEGER PRIMARY KEY,
+	hash BLOB NOT NULL,
+	-- The number of blobs of the block.
+	blobs INTEGER NOT NULL,
+	-- Whether the block is finalized, 0 or 1.
+	finalized INTEGER NOT NULL DEFAULT 0,
+	-- The confidence of the node in the availability of the block, NULL until it is sampled.
+	confidence INTEGER,
+	-- Whether the samples show the data available, 0 or 1, NULL until the block is sampled.
+	available INTEGER
+);
+
+-- The blobs of the blocks, in the order of their rows.
+CREATE TABLE IF NOT EXISTS blobs (
+	block_number INTEGER NOT NULL,
+	-- The index of the blob in the block.
+	idx INTEGER NOT NULL,
+	-- The hash of the sidecar metadata of the blob.
+	hash BLOB NOT NULL,
+	app_id INTEGER NOT NULL,
+	nonce INTEGER NOT NULL,
+	-- The length of the data in bytes.
+	size INTEGER NOT NULL,
+	-- The first row of the blob in the block matrix, and its number of rows.
+	start_row INTEGER NOT NULL,
+	rows INTEGER NOT NULL,
+	-- The merkle root of the commitments of the rows of the blob.
+	commitments_root BLOB NOT NULL,
+	PRIMARY KEY (block_number, idx)
+);
+CREATE INDEX IF NOT EXISTS blobs_by_app ON blobs (app_id, block_number);
+CREATE INDEX IF NOT EXISTS blobs_by_hash ON blobs (hash);
+
+-- The rewards claimed by the farmers in the finalized blocks.
+CREATE TABLE IF NOT EXISTS claims (
+	block_number INTEGER NOT NULL,
+	-- The index of the claim event in the block.
+	event_index INTEGER NOT NULL,
+	account BLOB NOT NULL,
+	reward TEXT NOT NULL,
+	PRIMARY KEY (block_number, event_index)
+);
+CREATE INDEX IF NOT EXISTS claims_by_account ON claims (account);
+
+-- The statistics of each app over the finalized blocks.
+CREATE TABLE IF NOT EXISTS apps (
+	app_id INTEGER PRIMARY KEY,
+	blobs INTEGER NOT NULL,
+	bytes INTEGER NOT NULL,
+	first_block INTEGER NOT NULL,
+	last_block INTEGER NOT NULL
+);
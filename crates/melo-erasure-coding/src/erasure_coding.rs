@@ -158,6 +158,32 @@ pub fn recover(fs: &FsFFTSettings, shards: &[Option<BlsScalar>]) -> Result<Vec<B
 	}
 }
 
+/// Recovers the data extended with [`extend`] from the given `shards`, in which the source data is
+/// at the even indices and the parity data at the odd ones.
+///
+/// This is the order of the segments of an extended column, once the original rows are interleaved
+/// with the parity rows. At least half of the shards must be present.
+///
+/// # Arguments
+///
+/// * `fs` - A reference to an `FsFFTSettings` instance.
+/// * `shards` - A slice of `Option<BlsScalar>` instances to recover data from.
+///
+/// # Returns
+///
+/// Returns a `Result` containing a `Vec` of the recovered `BlsScalar` instances, in the order of
+/// `shards`, or an error message.
+pub fn recover_interleaved(
+	fs: &FsFFTSettings,
+	shards: &[Option<BlsScalar>],
+) -> Result<Vec<BlsScalar>, String> {
+	if !shards.contains(&None) {
+		return Ok(shards.iter().flatten().copied().collect())
+	}
+	FsPoly::recover_poly_from_samples(BlsScalar::slice_option_to_repr(shards), fs)
+		.map(|poly| BlsScalar::vec_from_repr(poly.coeffs))
+}
+
 /// Recovers a polynomial from the given shards using the provided FFT settings.
 ///
 /// It checks if `shards` contains no `None` values, and if so, directly computes the polynomial
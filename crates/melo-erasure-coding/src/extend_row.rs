@@ -0,0 +1,119 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use kzg::FK20MultiSettings;
+use melo_das_primitives::{
+	crypto::{KZGProof, Position, KZG},
+	polynomial::Polynomial,
+	segment::Segment,
+};
+use rust_kzg_blst::types::fk20_multi_settings::FsFK20MultiSettings;
+
+use crate::{erasure_coding::extend_poly, String, ToString, Vec};
+
+/// Extends the rows of a block and computes the proofs of their segments on up to `threads`
+/// threads.
+///
+/// Each row is extended to twice its size and split into segments of `chunk_size` field elements,
+/// with their FK20 proofs, as [`crate::segment::poly_to_segment_vec`] does. The FK20 settings are
+/// computed once and shared by all the rows, which must be of the same length.
+///
+/// # Arguments
+///
+/// * `rows` - The polynomials of the rows to extend.
+/// * `kzg` - A reference to a `KZG` struct.
+/// * `chunk_size` - The number of field elements of a segment. Must be a power of two.
+/// * `threads` - The maximum number of threads extending the rows, `0` for one per core. The rows
+///   are extended on the calling thread with `1` or without the `parallel` feature.
+///
+/// # Returns
+///
+/// * `Result<Vec<Segment>, String>` - The segments of all the rows, row after row. The segments of
+///   the `y`-th row of `rows` are at `y`.
+///
+/// # Errors
+///
+/// Returns an error message if the rows are not of the same length, if `chunk_size` is not a
+/// power of two, or if the extension fails.
+pub fn extend_rows_parallel(
+	rows: &[Polynomial],
+	kzg: &KZG,
+	chunk_size: usize,
+	threads: usize,
+) -> Result<Vec<Segment>, String> {
+	let poly_len = match rows.first() {
+		Some(row) => row.checked()?.0.coeffs.len(),
+		None => return Ok(Vec::default()),
+	};
+	if rows.iter().any(|row| row.0.coeffs.len() != poly_len) {
+		return Err("rows must be of the same length".to_string())
+	}
+	if !chunk_size.is_power_of_two() {
+		return Err("chunk_size must be a power of two".to_string())
+	}
+
+	let fk = FsFK20MultiSettings::new(&kzg.ks, 2 * poly_len, chunk_size)?;
+	let segments = map_rows(rows, threads, |y, row| extend_row(&fk, row, y, chunk_size))?;
+
+	Ok(segments.into_iter().flatten().collect())
+}
+
+/// Extends `poly`, the `y`-th row, and splits it into segments of `chunk_size` field elements with
+/// the proofs of `fk`.
+pub(crate) fn extend_row(
+	fk: &FsFK20MultiSettings,
+	poly: &Polynomial,
+	y: usize,
+	chunk_size: usize,
+) -> Result<Vec<Segment>, String> {
+	let all_proofs = fk.data_availability(&poly.0)?;
+	let extended_poly = extend_poly(&fk.kzg_settings.fs, poly)?;
+
+	let segments = extended_poly
+		.chunks(chunk_size)
+		.enumerate()
+		.map(|(i, chunk)| {
+			let position = Position { y: y as u32, x: i as u32 };
+			Segment::new(position, chunk, KZGProof(all_proofs[i]))
+		})
+		.collect();
+
+	Ok(segments)
+}
+
+#[cfg(feature = "parallel")]
+fn map_rows<F>(rows: &[Polynomial], threads: usize, f: F) -> Result<Vec<Vec<Segment>>, String>
+where
+	F: Fn(usize, &Polynomial) -> Result<Vec<Segment>, String> + Sync,
+{
+	use rayon::prelude::*;
+
+	if threads == 1 || rows.len() == 1 {
+		return rows.iter().enumerate().map(|(y, row)| f(y, row)).collect()
+	}
+
+	let pool = rayon::ThreadPoolBuilder::new()
+		.num_threads(threads)
+		.build()
+		.map_err(|e| e.to_string())?;
+	pool.install(|| rows.par_iter().enumerate().map(|(y, row)| f(y, row)).collect())
+}
+
+#[cfg(not(feature = "parallel"))]
+fn map_rows<F>(rows: &[Polynomial], _threads: usize, f: F) -> Result<Vec<Vec<Segment>>, String>
+where
+	F: Fn(usize, &Polynomial) -> Result<Vec<Segment>, String> + Sync,
+{
+	rows.iter().enumerate().map(|(y, row)| f(y, row)).collect()
+}
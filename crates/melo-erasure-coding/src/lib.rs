@@ -14,8 +14,11 @@
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
-use melo_das_primitives::{blob::Blob, crypto::SCALAR_SAFE_BYTES, KZG};
+pub use melo_das_primitives::chunking::{blob_count, blobs_to_bytes, bytes_to_blobs};
+use melo_das_primitives::{blob::Blob, chunking::bytes_per_blob, config::MatrixDimensions, KZG};
 
+#[cfg(test)]
+mod proptests;
 #[cfg(test)]
 mod tests;
 
@@ -25,10 +28,12 @@ pub use alloc::{
 	vec,
 	vec::Vec,
 };
-use segment::poly_to_segment_vec;
+use extend_row::extend_rows_parallel;
 
 pub mod erasure_coding;
 pub mod extend_col;
+pub mod extend_row;
+pub mod matrix;
 pub mod recovery;
 pub mod segment;
 
@@ -66,7 +71,7 @@ pub fn bytes_vec_to_blobs(
 		return Err("bytes_vec should not contain empty bytes; qed".to_string())
 	}
 
-	let bytes_per_blob = get_bytes_per_blob(field_elements_per_blob)?;
+	let bytes_per_blob = bytes_per_blob(field_elements_per_blob)?;
 	let blobs = bytes_vec
 		.iter()
 		.flat_map(|bytes| {
@@ -82,48 +87,6 @@ pub fn bytes_vec_to_blobs(
 	Ok(blobs)
 }
 
-/// Converts a `bytes` into a vector of `Blob`s, where each `Blob` contains
-/// `field_elements_per_blob` field elements.
-///
-/// # Arguments
-///
-/// * `bytes` - A vector of bytes to convert.
-/// * `field_elements_per_blob` - The number of field elements to include in each `Blob`.
-///
-/// # Returns
-///
-/// * `Result<Vec<Blob>, String>` - A vector of `Blob`s, or an error message if the conversion
-///   fails.
-///
-/// # Errors
-///
-/// Returns an error message if:
-///
-/// * `bytes` is an empty byte vector.
-/// * `field_elements_per_blob` is not a power of two.
-/// * `field_elements_per_blob` is zero.
-///
-/// # Notes
-///
-/// * If the input `bytes` do not contain enough bytes to create a `Blob`, the remaining bytes
-/// are padded with zeroes. When using this function, the final recovered data should be determined
-/// based on the length of the original data.
-// TODO: test
-pub fn bytes_to_blobs(bytes: &[u8], field_elements_per_blob: usize) -> Result<Vec<Blob>, String> {
-	if bytes.is_empty() {
-		return Err("bytes should not contain empty bytes; qed".to_string())
-	}
-	let bytes_per_blob = get_bytes_per_blob(field_elements_per_blob)?;
-	let blobs = bytes
-		.chunks(bytes_per_blob)
-		.map(|chunk| {
-			Blob::try_from_bytes_pad(chunk, bytes_per_blob)
-				.expect("Failed to convert bytes to Blob; qed")
-		})
-		.collect();
-	Ok(blobs)
-}
-
 /// Converts a byte slice into a vector of segments.
 ///
 /// # Arguments
@@ -135,7 +98,9 @@ pub fn bytes_to_blobs(bytes: &[u8], field_elements_per_blob: usize) -> Result<Ve
 ///
 /// # Errors
 ///
-/// Returns an error if the byte slice is empty or if the number of bytes per blob cannot be determined.
+/// Returns an error if the byte slice is empty, if the number of bytes per blob cannot be
+/// determined or if segments of `field_elements_per_segment` field elements do not split a blob
+/// evenly.
 ///
 /// # Returns
 ///
@@ -145,32 +110,37 @@ pub fn bytes_to_segments(
 	field_elements_per_blob: usize,
 	field_elements_per_segment: usize,
 	kzg: &KZG,
+) -> Result<Vec<melo_das_primitives::Segment>, String> {
+	bytes_to_segments_parallel(bytes, field_elements_per_blob, field_elements_per_segment, kzg, 1)
+}
+
+/// Converts a byte slice into a vector of segments as [`bytes_to_segments`] does, extending the
+/// rows on up to `threads` threads, `0` for one per core.
+///
+/// # Errors
+///
+/// Returns an error in the same cases as [`bytes_to_segments`].
+pub fn bytes_to_segments_parallel(
+	bytes: &[u8],
+	field_elements_per_blob: usize,
+	field_elements_per_segment: usize,
+	kzg: &KZG,
+	threads: usize,
 ) -> Result<Vec<melo_das_primitives::Segment>, String> {
 	if bytes.is_empty() {
 		return Err("bytes should not contain empty bytes; qed".to_string())
 	}
-	let bytes_per_blob = get_bytes_per_blob(field_elements_per_blob)?;
-	let segments = bytes
+	if !MatrixDimensions::new(field_elements_per_blob, field_elements_per_segment).is_valid() {
+		return Err("segments should split a blob evenly; qed".to_string())
+	}
+	let bytes_per_blob = bytes_per_blob(field_elements_per_blob)?;
+	let rows = bytes
 		.chunks(bytes_per_blob)
-		.enumerate()
-		.flat_map(|(y, chunk)| {
-			let ploy = Blob::try_from_bytes_pad(chunk, bytes_per_blob)
-				.expect("Failed to convert bytes to Blob; qed")
-				.to_poly();
-			poly_to_segment_vec(&ploy, kzg, y, field_elements_per_segment)
+		.map(|chunk| {
+			Blob::try_from_bytes_pad(chunk, bytes_per_blob)
 				.expect("Failed to convert bytes to Blob; qed")
+				.to_poly()
 		})
 		.collect::<Vec<_>>();
-	Ok(segments)
-}
-
-fn get_bytes_per_blob(field_elements_per_blob: usize) -> Result<usize, String> {
-	let bytes_per_blob = SCALAR_SAFE_BYTES * field_elements_per_blob;
-	if !field_elements_per_blob.is_power_of_two() {
-		return Err("field_elements_per_blob should be a power of 2; qed".to_string())
-	}
-	if field_elements_per_blob == 0 {
-		return Err("field_elements_per_blob should be greater than 0; qed".to_string())
-	}
-	Ok(bytes_per_blob)
+	extend_rows_parallel(&rows, kzg, field_elements_per_segment, threads)
 }
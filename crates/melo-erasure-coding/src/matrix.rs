@@ -0,0 +1,216 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Recovery of the extended matrix of a block from arbitrary samples.
+//!
+//! The rows of the extended matrix are the blobs of the block, extended to twice as many segments,
+//! and the rows `k..2k` are the parity rows of the columns, as built by
+//! [`extend_segments_col`](crate::extend_col::extend_segments_col). A row can be recovered from
+//! half of its segments and a column from half of its segments, so the solver alternates between
+//! the two until the matrix is complete or no row or column can be recovered any more.
+use melo_das_primitives::{
+	crypto::{BlsScalar, KZGProof, Position, KZG},
+	segment::Segment,
+};
+
+use crate::{
+	erasure_coding::{extend_poly, recover_interleaved, recover_poly},
+	recovery::recompute_segment_proofs,
+	vec, String, ToString, Vec,
+};
+
+/// The extended matrix of a block, as far as it could be recovered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtendedMatrix {
+	/// The number of rows before extension.
+	pub k: usize,
+	/// The number of segments of a row before extension.
+	pub chunk_count: usize,
+	/// The segments of the matrix, row by row, `None` where they could not be recovered.
+	pub segments: Vec<Option<Segment>>,
+	/// The positions of the segments that could not be recovered.
+	pub unrecoverable: Vec<Position>,
+}
+
+impl ExtendedMatrix {
+	/// Returns the number of rows of the matrix.
+	pub fn rows(&self) -> usize {
+		self.k * 2
+	}
+
+	/// Returns the number of columns of the matrix.
+	pub fn cols(&self) -> usize {
+		self.chunk_count * 2
+	}
+
+	/// Returns the segment at `position`, if it was recovered.
+	pub fn get(&self, position: &Position) -> Option<&Segment> {
+		let (x, y) = (position.x as usize, position.y as usize);
+		if x >= self.cols() || y >= self.rows() {
+			return None
+		}
+		self.segments[y * self.cols() + x].as_ref()
+	}
+
+	/// Returns the segments of the row `y`.
+	pub fn row(&self, y: usize) -> &[Option<Segment>] {
+		&self.segments[y * self.cols()..(y + 1) * self.cols()]
+	}
+
+	/// Returns whether every segment of the matrix was recovered.
+	pub fn is_complete(&self) -> bool {
+		self.unrecoverable.is_empty()
+	}
+}
+
+/// Recovers the extended matrix of `k` rows of `chunk_count` segments from arbitrary `samples`.
+///
+/// The rows with at least half of their segments and the columns with at least half of their
+/// segments are recovered in turn, until the matrix is complete or no progress is made. The proofs
+/// of the recovered segments are computed from the polynomials of their rows with
+/// [`recompute_segment_proofs`]. The samples are not
+/// verified, the caller is responsible for verifying them against the commitments of the block.
+///
+/// # Arguments
+///
+/// * `samples` - The segments available, in any order.
+/// * `k` - The number of rows before extension. Must be a power of two.
+/// * `chunk_count` - The number of segments of a row before extension. Must be a power of two.
+/// * `kzg` - A `KZG` instance to use for recovery.
+///
+/// # Returns
+///
+/// A `Result` containing the [`ExtendedMatrix`], listing the positions that could not be
+/// recovered, or an error message if the dimensions or the samples are invalid.
+pub fn recover_matrix(
+	samples: &[Segment],
+	k: usize,
+	chunk_count: usize,
+	kzg: &KZG,
+) -> Result<ExtendedMatrix, String> {
+	if !k.is_power_of_two() || !chunk_count.is_power_of_two() {
+		return Err("k and chunk_count must be a power of two".to_string())
+	}
+	let size = samples.first().ok_or_else(|| "no segments provided".to_string())?.size();
+	if !size.is_power_of_two() {
+		return Err("segment size must be a power of two".to_string())
+	}
+	let (rows, cols) = (k * 2, chunk_count * 2);
+
+	let mut cells: Vec<Option<Vec<BlsScalar>>> = vec![None; rows * cols];
+	let mut proofs = vec![None; rows * cols];
+	for sample in samples {
+		let (x, y) = (sample.position.x as usize, sample.position.y as usize);
+		if x >= cols || y >= rows {
+			return Err("segment out of the matrix".to_string())
+		}
+		if sample.size() != size {
+			return Err("segments are not of the same size".to_string())
+		}
+		cells[y * cols + x] = Some(sample.content.data.clone());
+		proofs[y * cols + x] = Some(sample.content.proof);
+	}
+
+	let fs = kzg.get_fs();
+	loop {
+		let mut progress = false;
+
+		for y in 0..rows {
+			let row = &mut cells[y * cols..(y + 1) * cols];
+			let known = row.iter().filter(|cell| cell.is_some()).count();
+			if known == cols || known < chunk_count {
+				continue
+			}
+			let shards = row
+				.iter()
+				.flat_map(|cell| match cell {
+					Some(data) => data.iter().copied().map(Some).collect(),
+					None => vec![None; size],
+				})
+				.collect::<Vec<_>>();
+			let recovered = extend_poly(fs, &recover_poly(fs, &shards)?)?;
+			for (x, cell) in row.iter_mut().enumerate().filter(|(_, cell)| cell.is_none()) {
+				*cell = Some(recovered[x * size..(x + 1) * size].to_vec());
+			}
+			progress = true;
+		}
+
+		for x in 0..cols {
+			// Interleave the original rows with the parity rows, the order the column was
+			// extended in.
+			let col =
+				(0..rows).map(|i| (i % 2) * k + i / 2).map(|y| y * cols + x).collect::<Vec<_>>();
+			let known = col.iter().filter(|&&i| cells[i].is_some()).count();
+			if known == rows || known < k {
+				continue
+			}
+			let mut recovered = vec![Vec::with_capacity(size); rows];
+			for lane in 0..size {
+				let shards = col
+					.iter()
+					.map(|&i| cells[i].as_ref().map(|data| data[lane]))
+					.collect::<Vec<_>>();
+				for (data, scalar) in recovered.iter_mut().zip(recover_interleaved(fs, &shards)?) {
+					data.push(scalar);
+				}
+			}
+			for (&i, data) in col.iter().zip(recovered) {
+				cells[i].get_or_insert(data);
+			}
+			progress = true;
+		}
+
+		if !progress {
+			break
+		}
+	}
+
+	let mut segments = vec![None; rows * cols];
+	let mut unrecoverable = Vec::new();
+	for y in 0..rows {
+		let row = &cells[y * cols..(y + 1) * cols];
+		if row.iter().any(|cell| cell.is_none()) {
+			// Without the polynomial of the row, only the segments sampled keep a proof.
+			for (x, cell) in row.iter().enumerate() {
+				let position = Position { x: x as u32, y: y as u32 };
+				match (cell, proofs[y * cols + x]) {
+					(Some(data), Some(proof)) =>
+						segments[y * cols + x] = Some(Segment::new(position, data, proof)),
+					_ => unrecoverable.push(position),
+				}
+			}
+			continue
+		}
+
+		// The row is complete, compute the proofs missing from its polynomial.
+		let mut recovered = Vec::new();
+		for (x, cell) in row.iter().enumerate() {
+			let position = Position { x: x as u32, y: y as u32 };
+			let data = cell.as_ref().expect("The row is complete; qed");
+			match proofs[y * cols + x] {
+				Some(proof) => segments[y * cols + x] = Some(Segment::new(position, data, proof)),
+				None => recovered.push(Segment::new(position, data, KZGProof::default())),
+			}
+		}
+		if !recovered.is_empty() {
+			let shards = row.iter().flatten().flatten().copied().map(Some).collect::<Vec<_>>();
+			recompute_segment_proofs(&recover_poly(fs, &shards)?, kzg, &mut recovered)?;
+		}
+		for segment in recovered {
+			segments[y * cols + segment.position.x as usize] = Some(segment);
+		}
+	}
+
+	Ok(ExtendedMatrix { k, chunk_count, segments, unrecoverable })
+}
@@ -0,0 +1,97 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Property-based round trips of the erasure coding: data is split into extended segments, at
+//! most half of them are dropped, and the recovered segments and blob still match the commitment
+//! of the data.
+use crate::{
+	bytes_to_segments,
+	recovery::{recovery_blob_from_order_row, recovery_order_row_from_segments},
+};
+use melo_das_primitives::{
+	blob::Blob,
+	crypto::SCALAR_SAFE_BYTES,
+	testutils::{blob_bytes, drop_unkept, recoverable_positions, unrecoverable_positions},
+	KZG,
+};
+use proptest::prelude::*;
+
+const FIELD_ELEMENTS_PER_BLOB: usize = 256;
+const FIELD_ELEMENTS_PER_SEGMENT: usize = 16;
+const SEGMENTS_PER_BLOB: usize = FIELD_ELEMENTS_PER_BLOB / FIELD_ELEMENTS_PER_SEGMENT;
+const EXTENDED_SEGMENTS_PER_BLOB: usize = SEGMENTS_PER_BLOB * 2;
+
+proptest! {
+	#![proptest_config(ProptestConfig::with_cases(16))]
+
+	#[test]
+	fn prop_recovered_row_verifies(
+		bytes in blob_bytes(FIELD_ELEMENTS_PER_BLOB),
+		kept in recoverable_positions(EXTENDED_SEGMENTS_PER_BLOB),
+	) {
+		let kzg = KZG::default_embedded();
+		let commitment = Blob::try_from_bytes_pad(&bytes, SCALAR_SAFE_BYTES * FIELD_ELEMENTS_PER_BLOB)
+			.unwrap()
+			.commit(&kzg)
+			.unwrap();
+		let segments =
+			bytes_to_segments(&bytes, FIELD_ELEMENTS_PER_BLOB, FIELD_ELEMENTS_PER_SEGMENT, &kzg)
+				.unwrap();
+		prop_assert_eq!(segments.len(), EXTENDED_SEGMENTS_PER_BLOB);
+
+		let recovered =
+			recovery_order_row_from_segments(&drop_unkept(&segments, &kept), &kzg).unwrap();
+
+		prop_assert_eq!(recovered.len(), segments.len());
+		for (segment, original) in recovered.iter().zip(&segments) {
+			prop_assert_eq!(segment.position, original.position);
+			prop_assert_eq!(&segment.content.data, &original.content.data);
+			prop_assert!(segment.verify(&kzg, &commitment, SEGMENTS_PER_BLOB).unwrap());
+		}
+	}
+
+	#[test]
+	fn prop_recovered_blob_matches_data(
+		bytes in blob_bytes(FIELD_ELEMENTS_PER_BLOB),
+		kept in recoverable_positions(EXTENDED_SEGMENTS_PER_BLOB),
+	) {
+		let kzg = KZG::default_embedded();
+		let blob = Blob::try_from_bytes_pad(&bytes, SCALAR_SAFE_BYTES * FIELD_ELEMENTS_PER_BLOB)
+			.unwrap();
+		let (commitment, proof) = blob.commit_and_proof(&kzg, FIELD_ELEMENTS_PER_BLOB).unwrap();
+		let segments =
+			bytes_to_segments(&bytes, FIELD_ELEMENTS_PER_BLOB, FIELD_ELEMENTS_PER_SEGMENT, &kzg)
+				.unwrap();
+
+		let recovered =
+			recovery_blob_from_order_row(&drop_unkept(&segments, &kept), &kzg).unwrap();
+
+		prop_assert_eq!(recovered.to_bytes_by_len(bytes.len()), bytes);
+		prop_assert_eq!(recovered.commit(&kzg).unwrap(), commitment);
+		prop_assert!(recovered.verify(&kzg, &commitment, &proof, FIELD_ELEMENTS_PER_BLOB).unwrap());
+	}
+
+	#[test]
+	fn prop_too_few_segments_are_not_recovered(
+		bytes in blob_bytes(FIELD_ELEMENTS_PER_BLOB),
+		kept in unrecoverable_positions(EXTENDED_SEGMENTS_PER_BLOB),
+	) {
+		let kzg = KZG::default_embedded();
+		let segments =
+			bytes_to_segments(&bytes, FIELD_ELEMENTS_PER_BLOB, FIELD_ELEMENTS_PER_SEGMENT, &kzg)
+				.unwrap();
+
+		prop_assert!(recovery_blob_from_order_row(&drop_unkept(&segments, &kept), &kzg).is_err());
+	}
+}
@@ -19,30 +19,35 @@ use crate::{
 	erasure_coding::{extend_poly, recover_poly},
 	segment::{order_segments_row, segment_datas_to_row},
 };
+use kzg::FK20MultiSettings;
 use melo_das_primitives::{
-	crypto::{Position, KZG},
+	blob::Blob,
+	crypto::{KZGProof, Position, KZG},
+	polynomial::Polynomial,
 	segment::{Segment, SegmentData},
 };
+use rust_kzg_blst::types::fk20_multi_settings::FsFK20MultiSettings;
 
 use crate::{String, ToString, Vec};
 
-/// Recover the segment datas from the given segment datas, KZG, chunk count, y, and segments size.
-/// 
+/// Recover the segment datas from the given segment datas, KZG, y, and segments size.
+///
+/// The proofs of the recovered segments are computed with
+/// [`recompute_segment_proofs`], so that they verify like the segments of the original row.
+///
 /// # Arguments
-/// 
+///
 /// * `segment_datas` - A slice of optional segment data.
 /// * `kzg` - A reference to a KZG instance.
-/// * `chunk_count` - The number of chunks.
 /// * `y` - The y coordinate.
 /// * `segments_size` - The size of the segments.
-/// 
+///
 /// # Returns
-/// 
+///
 /// A Result containing a vector of segments or an error message as a string.
 pub fn recover_segment_datas(
 	segment_datas: &[Option<SegmentData>],
 	kzg: &KZG,
-	chunk_count: usize,
 	y: u32,
 	segments_size: usize,
 ) -> Result<Vec<Segment>, String> {
@@ -51,23 +56,71 @@ pub fn recover_segment_datas(
 
 	let recovery_row = extend_poly(kzg.get_fs(), &poly)?;
 
-	segment_datas
-		.iter()
-		.enumerate()
-		.map(|(i, segment_data)| {
-			let position = Position { x: i as u32, y };
-			match segment_data {
-				Some(segment_data) => Ok(Segment { position, content: segment_data.clone() }),
-				None => {
-					let index = i * segments_size;
-					let data = recovery_row[index..(index + segments_size)].to_vec();
-					let segment_data =
-						SegmentData::from_data(&position, &data, kzg, &poly, chunk_count)?;
-					Ok(Segment { position, content: segment_data })
-				},
-			}
-		})
-		.collect()
+	let mut segments = Vec::with_capacity(segment_datas.len());
+	let mut recovered = Vec::new();
+	for (i, segment_data) in segment_datas.iter().enumerate() {
+		let position = Position { x: i as u32, y };
+		match segment_data {
+			Some(segment_data) =>
+				segments.push(Some(Segment { position, content: segment_data.clone() })),
+			None => {
+				let index = i * segments_size;
+				let data = &recovery_row[index..(index + segments_size)];
+				segments.push(None);
+				recovered.push(Segment::new(position, data, KZGProof::default()));
+			},
+		}
+	}
+
+	recompute_segment_proofs(&poly, kzg, &mut recovered)?;
+	for segment in recovered {
+		segments[segment.position.x as usize] = Some(segment);
+	}
+	Ok(segments.into_iter().flatten().collect())
+}
+
+/// Recomputes the proofs of `segments`, segments of the row of polynomial `row_poly` recovered by
+/// erasure coding, so that they can be verified against the commitment of the row.
+///
+/// The proofs of all the segments of the row are computed at once with FK20, which is cheaper than
+/// computing them one by one as soon as a few segments are missing.
+///
+/// # Arguments
+///
+/// * `row_poly` - The polynomial of the row, as recovered by [`recover_poly`].
+/// * `kzg` - A reference to a KZG instance.
+/// * `segments` - The segments of the row to set the proofs of. They must be of the same size.
+///
+/// # Errors
+///
+/// Returns an error message if the segments are not of the same size or out of the row.
+pub fn recompute_segment_proofs(
+	row_poly: &Polynomial,
+	kzg: &KZG,
+	segments: &mut [Segment],
+) -> Result<(), String> {
+	let size = match segments.first() {
+		Some(segment) => segment.size(),
+		None => return Ok(()),
+	};
+	if segments.iter().any(|segment| segment.size() != size) {
+		return Err("segments are not of the same size".to_string())
+	}
+	if !size.is_power_of_two() {
+		return Err("segment size must be a power of two".to_string())
+	}
+
+	let poly_len = row_poly.checked()?.0.coeffs.len();
+	let fk = FsFK20MultiSettings::new(&kzg.ks, 2 * poly_len, size)?;
+	let proofs = fk.data_availability(&row_poly.0)?;
+
+	for segment in segments.iter_mut() {
+		let proof = proofs
+			.get(segment.position.x as usize)
+			.ok_or_else(|| "segment out of the row".to_string())?;
+		segment.content.proof = KZGProof(*proof);
+	}
+	Ok(())
 }
 
 /// Recover a row of segments from a vector of segments, using the provided KZG instance and chunk
@@ -100,7 +153,6 @@ pub fn recovery_row_from_segments(
 	recover_segment_datas(
 		&order_segments.iter().map(|s| s.as_ref().cloned()).collect::<Vec<_>>(),
 		kzg,
-		chunk_count,
 		y,
 		segments_size,
 	)
@@ -141,7 +193,7 @@ pub fn recovery_order_row_from_segments(
                 .map(|s| s.as_ref().map(|segment| segment.content.clone()))
                 .collect::<Vec<_>>();
 
-            recover_segment_datas(&segment_datas, kzg, chunk_count, y, size)
+            recover_segment_datas(&segment_datas, kzg, y, size)
         } else {
             Err("segments are not from the same row or not of the same size".to_string())
         }
@@ -153,3 +205,35 @@ pub fn recovery_order_row_from_segments(
 // TODO
 // pub fn recovery_col_from_segments(kzg: &KZG, segments: &Vec<Segment>, k: usize) ->
 // Result<Vec<Segment>, String> {}
+
+/// Recovers the original blob from an ordered row of segments.
+///
+/// At least half of the segments must be present, and all present segments must be of the same
+/// size. The returned blob is padded, the caller is responsible for truncating it to the original
+/// data length.
+pub fn recovery_blob_from_order_row(
+	order_segments: &[Option<Segment>],
+	kzg: &KZG,
+) -> Result<Blob, String> {
+	let size = order_segments
+		.iter()
+		.find_map(|s| s.as_ref().map(|segment| segment.size()))
+		.ok_or_else(|| "no segments provided".to_string())?;
+
+	if order_segments.iter().flatten().any(|segment| segment.size() != size) {
+		return Err("segments are not of the same size".to_string())
+	}
+
+	let available = order_segments.iter().filter(|s| s.is_some()).count();
+	if available * 2 < order_segments.len() {
+		return Err("not enough segments to recover the blob".to_string())
+	}
+
+	let segment_datas = order_segments
+		.iter()
+		.map(|s| s.as_ref().map(|segment| segment.content.clone()))
+		.collect::<Vec<_>>();
+
+	let row = segment_datas_to_row(&segment_datas, size);
+	recover_poly(kzg.get_fs(), &row).map(|poly| poly.to_blob())
+}
@@ -13,12 +13,12 @@
 // limitations under the License.
 
 use kzg::FK20MultiSettings;
-use melo_das_primitives::crypto::{BlsScalar, KZGProof, Position, KZG};
+use melo_das_primitives::crypto::{BlsScalar, KZG};
 use melo_das_primitives::polynomial::Polynomial;
 use melo_das_primitives::segment::{Segment, SegmentData};
 use rust_kzg_blst::types::fk20_multi_settings::FsFK20MultiSettings;
 
-use crate::erasure_coding::extend_poly;
+use crate::extend_row::extend_row;
 use crate::{String, Vec, ToString, vec};
 
 /// Orders a vector of `Segment`s into a row of `SegmentData` using the provided chunk count.
@@ -137,18 +137,5 @@ pub fn poly_to_segment_vec(poly: &Polynomial, kzg: &KZG, y: usize, chunk_size: u
     }
 
     let fk = FsFK20MultiSettings::new(&kzg.ks, 2 * poly_len, chunk_size)?;
-    let all_proofs = fk.data_availability(&poly.0)?;
-    let extended_poly = extend_poly(&fk.kzg_settings.fs, poly)?;
-
-    let segments = extended_poly
-        .chunks(chunk_size)
-        .enumerate()
-        .map(|(i, chunk)| {
-            let position = Position { y: y as u32, x: i as u32 };
-            let proof = all_proofs[i];
-            Segment::new(position, chunk, KZGProof(proof))
-        })
-        .collect::<Vec<_>>();
-
-    Ok(segments)
+    extend_row(&fk, poly, y, chunk_size)
 }
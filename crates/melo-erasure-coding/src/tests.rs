@@ -1,5 +1,6 @@
 use crate::{
-	bytes_to_segments, bytes_vec_to_blobs, erasure_coding::*, extend_col::*, recovery::*,
+	blob_count, blobs_to_bytes, bytes_to_blobs, bytes_to_segments, bytes_to_segments_parallel,
+	bytes_vec_to_blobs, erasure_coding::*, extend_col::*, extend_row::*, matrix::*, recovery::*,
 	segment::*,
 };
 
@@ -9,6 +10,7 @@ use kzg::{FFTFr, FK20MultiSettings, Fr, G1};
 use melo_das_primitives::{
 	blob::Blob,
 	crypto::{BlsScalar, KZGCommitment, KZGProof, Position, ReprConvert, KZG},
+	eip4844::{versioned_hash, Eip4844Blob, EIP4844_BYTES_PER_BLOB, VERSIONED_HASH_VERSION_KZG},
 	polynomial::Polynomial,
 	segment::{Segment, SegmentData},
 };
@@ -171,6 +173,120 @@ fn test_blob_verify_batch() {
 	assert!(result);
 }
 
+#[test]
+fn test_blobs_proof_batch() {
+	let blob_count: usize = 4;
+	let field_elements_per_blob: usize = 4096;
+	let blob_data_len: usize = 31 * field_elements_per_blob;
+	let kzg = KZG::default_embedded();
+
+	let blobs = (0..blob_count)
+		.map(|_| Blob::try_from_bytes_pad(&random_bytes(blob_data_len), blob_data_len).unwrap())
+		.collect::<Vec<_>>();
+	let commitments = blobs.iter().map(|blob| blob.commit(&kzg).unwrap()).collect::<Vec<_>>();
+
+	let proof =
+		Blob::compute_blobs_proof_batch(&blobs, &commitments, &kzg, field_elements_per_blob)
+			.unwrap();
+	assert!(Blob::verify_blobs_proof_batch(
+		&blobs,
+		&commitments,
+		&proof,
+		&kzg,
+		field_elements_per_blob
+	)
+	.unwrap());
+
+	// The proof of a single blob is its own proof
+	let single = Blob::compute_blobs_proof_batch(
+		&blobs[..1],
+		&commitments[..1],
+		&kzg,
+		field_elements_per_blob,
+	)
+	.unwrap();
+	assert_eq!(single, blobs[0].kzg_proof(&commitments[0], &kzg, field_elements_per_blob).unwrap());
+
+	// Swapped blobs do not match the proof
+	let mut swapped = blobs.clone();
+	swapped.swap(0, 1);
+	assert!(!Blob::verify_blobs_proof_batch(
+		&swapped,
+		&commitments,
+		&proof,
+		&kzg,
+		field_elements_per_blob
+	)
+	.unwrap());
+
+	// Neither does a modified commitment
+	let mut commitments_mut = commitments.clone();
+	commitments_mut[2] = KZGCommitment(commitments_mut[2].0.add(&FsG1::rand()));
+	assert!(!Blob::verify_blobs_proof_batch(
+		&blobs,
+		&commitments_mut,
+		&proof,
+		&kzg,
+		field_elements_per_blob
+	)
+	.unwrap());
+
+	assert!(Blob::verify_blobs_proof_batch(
+		&blobs,
+		&commitments[1..],
+		&proof,
+		&kzg,
+		field_elements_per_blob
+	)
+	.is_err());
+}
+
+#[test]
+fn test_eip4844_blob() {
+	let kzg = KZG::default_embedded();
+	let field_elements_per_blob: usize = 2048;
+
+	// Clearing the first byte of each field element keeps it canonical
+	let mut bytes = random_bytes(EIP4844_BYTES_PER_BLOB);
+	bytes.chunks_mut(32).for_each(|chunk| chunk[0] = 0);
+	let blob = Eip4844Blob::try_from_bytes(&bytes).unwrap();
+	assert_eq!(blob.to_bytes(), bytes);
+
+	assert!(Eip4844Blob::try_from_bytes(&bytes[1..]).is_err());
+	assert!(Eip4844Blob::try_from_bytes(&vec![0xff; EIP4844_BYTES_PER_BLOB]).is_err());
+
+	// The blob holds the evaluations of its polynomial in bit-reversed order
+	let poly = blob.to_poly(&kzg).unwrap();
+	assert_eq!(poly.eval_all(kzg.get_fs()).unwrap(), blob.field_elements());
+
+	let commitment = blob.commitment(&kzg).unwrap();
+	let proof = blob.compute_proof(&kzg, &commitment).unwrap();
+	assert!(blob.verify_proof(&kzg, &commitment, &proof).unwrap());
+	assert!(!blob
+		.verify_proof(&kzg, &commitment, &KZGProof(proof.0.add(&FsG1::rand())))
+		.unwrap());
+	assert_eq!(versioned_hash(&commitment)[0], VERSIONED_HASH_VERSION_KZG);
+
+	let blobs = blob.to_melodot_blobs(field_elements_per_blob).unwrap();
+	assert_eq!(blobs.len(), 3);
+	assert_eq!(Eip4844Blob::from_melodot_blobs(&blobs).unwrap(), blob);
+	assert!(Eip4844Blob::from_melodot_blobs(&blobs[..2]).is_err());
+
+	let commitments = blobs.iter().map(|blob| blob.commit(&kzg).unwrap()).collect::<Vec<_>>();
+	assert!(Eip4844Blob::check_equivalence(&kzg, &commitment, &blobs, &commitments).unwrap());
+
+	// Another Ethereum commitment is not equivalent
+	let other = KZGCommitment(commitment.0.add(&FsG1::rand()));
+	assert!(!Eip4844Blob::check_equivalence(&kzg, &other, &blobs, &commitments).unwrap());
+
+	// Neither are other Melodot commitments
+	let mut swapped = commitments.clone();
+	swapped.swap(0, 1);
+	assert!(!Eip4844Blob::check_equivalence(&kzg, &commitment, &blobs, &swapped).unwrap());
+
+	assert!(Eip4844Blob::check_equivalence(&kzg, &commitment, &blobs, &commitments[1..]).is_err());
+}
+
 fn blob_bytes_conversion_case(field_elements_per_blob: usize, minimize: usize) {
 	let blob_data_len: usize = 31 * field_elements_per_blob;
 
@@ -531,6 +647,46 @@ fn test_recovery_row_from_segments() {
 	assert!(result.is_err());
 }
 
+#[test]
+fn test_recompute_segment_proofs() {
+	let chunk_len: usize = 16;
+	let chunk_count: usize = 4;
+	let poly = random_poly(chunk_len * chunk_count);
+
+	let kzg = KZG::default_embedded();
+	let commitment = kzg.commit(&poly).unwrap();
+	let segments = poly_to_segment_vec(&poly, &kzg, 0, chunk_len).unwrap();
+
+	// Drop the proofs of some segments, and recompute them
+	let mut recovered = segments
+		.iter()
+		.skip(3)
+		.map(|segment| {
+			Segment::new(segment.position.clone(), &segment.content.data, KZGProof::default())
+		})
+		.collect::<Vec<_>>();
+	recompute_segment_proofs(&poly, &kzg, &mut recovered).unwrap();
+	assert_eq!(recovered, segments[3..]);
+
+	// The segments recovered from an ordered row verify like the original ones
+	let mut order_segments = segments.iter().cloned().map(Some).collect::<Vec<_>>();
+	for i in random_vec(2 * chunk_count).into_iter().take(chunk_count) {
+		order_segments[i] = None;
+	}
+	let recovered = recovery_order_row_from_segments(&order_segments, &kzg).unwrap();
+	for segment in recovered.iter() {
+		assert!(segment.verify(&kzg, &commitment, chunk_count).unwrap());
+	}
+
+	let mut wrong_size = recovered.clone();
+	wrong_size[0].content.data.pop();
+	assert!(recompute_segment_proofs(&poly, &kzg, &mut wrong_size).is_err());
+
+	let mut outside = recovered;
+	outside[0].position.x = (2 * chunk_count) as u32;
+	assert!(recompute_segment_proofs(&poly, &kzg, &mut outside).is_err());
+}
+
 #[test]
 fn test_proof_multi() {
 	let chunk_len: usize = 16;
@@ -826,6 +982,32 @@ fn test_bytes_vec_to_blobs_returns_err() {
 	bytes_vec_to_blobs_returns_err_case(vec![20 * 31], 0);
 }
 
+#[test]
+fn test_bytes_to_blobs_round_trip() {
+	let field_elements_per_blob: usize = 4;
+	for len in [1, 31, 4 * 31, 4 * 31 + 1, 10 * 31 + 7] {
+		let bytes = random_bytes(len);
+		let blobs = bytes_to_blobs(&bytes, field_elements_per_blob).unwrap();
+		assert_eq!(blobs.len(), blob_count(len, field_elements_per_blob).unwrap());
+		assert!(blobs.iter().all(|blob| blob.len() == field_elements_per_blob));
+		assert_eq!(blobs_to_bytes(&blobs, len).unwrap(), bytes);
+	}
+}
+
+#[test]
+fn test_blobs_to_bytes_returns_err() {
+	let blobs = bytes_to_blobs(&random_bytes(5 * 31), 4).unwrap();
+	assert!(blobs_to_bytes(&blobs, 8 * 31 + 1).is_err());
+	assert!(blobs_to_bytes(&blobs, 4 * 31).is_err());
+	assert!(blobs_to_bytes(&blobs, 4 * 31 + 1).is_ok());
+	assert!(blobs_to_bytes(&[], 1).is_err());
+	assert_eq!(blobs_to_bytes(&[], 0).unwrap(), Vec::<u8>::new());
+
+	assert!(bytes_to_blobs(&[], 4).is_err());
+	assert!(bytes_to_blobs(&random_bytes(31), 3).is_err());
+	assert!(bytes_to_blobs(&random_bytes(31), 0).is_err());
+}
+
 fn test_bytes_to_segments_case(bytes_len: usize) {
 	let field_elements_per_blob = 2048;
 	let field_elements_per_segment = 16;
@@ -873,6 +1055,47 @@ fn test_bytes_to_segments() {
 	test_bytes_to_segments_case(1);
 }
 
+#[test]
+fn test_bytes_to_segments_uneven_segments() {
+	let kzg = KZG::default_embedded();
+	let bytes = random_bytes(128);
+
+	assert!(bytes_to_segments(&bytes, 2048, 12, &kzg).is_err());
+	assert!(bytes_to_segments(&bytes, 2048, 0, &kzg).is_err());
+	assert!(bytes_to_segments(&bytes, 16, 32, &kzg).is_err());
+}
+
+#[test]
+fn test_extend_rows_parallel() {
+	let kzg = KZG::default_embedded();
+	let chunk_size = 4;
+	let mut rows = (0..4).map(|_| random_poly(16)).collect::<Vec<_>>();
+
+	let expected = rows
+		.iter()
+		.enumerate()
+		.flat_map(|(y, row)| poly_to_segment_vec(row, &kzg, y, chunk_size).unwrap())
+		.collect::<Vec<_>>();
+	for threads in [0, 1, 2] {
+		assert_eq!(extend_rows_parallel(&rows, &kzg, chunk_size, threads).unwrap(), expected);
+	}
+
+	assert!(extend_rows_parallel(&[], &kzg, chunk_size, 2).unwrap().is_empty());
+	assert!(extend_rows_parallel(&rows, &kzg, 3, 2).is_err());
+	rows.push(random_poly(8));
+	assert!(extend_rows_parallel(&rows, &kzg, chunk_size, 2).is_err());
+}
+
+#[test]
+fn test_bytes_to_segments_parallel() {
+	let kzg = KZG::default_embedded();
+	let bytes = random_bytes(31 * 64 * 3);
+
+	let segments = bytes_to_segments(&bytes, 64, 16, &kzg).unwrap();
+	assert_eq!(segments.len(), 3 * 8);
+	assert_eq!(bytes_to_segments_parallel(&bytes, 64, 16, &kzg, 0).unwrap(), segments);
+}
+
 #[test]
 fn test_recover_poly_and_extend_poly() {
 	// Build a random polynomial+
@@ -934,3 +1157,176 @@ fn test_recover_poly_and_extend_poly() {
 		}
 	}
 }
+
+#[test]
+fn test_recovery_blob_from_order_row() {
+	let field_elements_per_blob = 2048;
+	let field_elements_per_segment = 16;
+	let bytes_per_blob = 31 * field_elements_per_blob;
+
+	let kzg = KZG::default_embedded();
+	let bytes = random_bytes(1000);
+
+	let segments =
+		bytes_to_segments(&bytes, field_elements_per_blob, field_elements_per_segment, &kzg)
+			.unwrap();
+	let mut order_segments = segments.into_iter().map(Some).collect::<Vec<_>>();
+
+	// Drop half of the segments at random
+	let half = order_segments.len() / 2;
+	for i in random_vec(order_segments.len()).into_iter().take(half) {
+		order_segments[i] = None;
+	}
+
+	let blob = recovery_blob_from_order_row(&order_segments, &kzg).unwrap();
+	assert_eq!(blob.to_bytes().len(), bytes_per_blob);
+	assert_eq!(blob.to_bytes_by_len(bytes.len()), bytes);
+
+	// One more missing segment makes recovery impossible
+	let i = order_segments.iter().position(|s| s.is_some()).unwrap();
+	order_segments[i] = None;
+	assert!(recovery_blob_from_order_row(&order_segments, &kzg).is_err());
+
+	assert!(recovery_blob_from_order_row(&[None, None], &kzg).is_err());
+}
+
+// Builds the extended matrix of `k` random rows of `chunk_count` segments of `chunk_len` field
+// elements, row by row, with the commitments of its rows.
+fn extended_matrix(
+	k: usize,
+	chunk_count: usize,
+	chunk_len: usize,
+	kzg: &KZG,
+) -> (Vec<Segment>, Vec<KZGCommitment>) {
+	let polys = (0..k).map(|_| random_poly(chunk_len * chunk_count)).collect::<Vec<_>>();
+	let commitments = polys.iter().map(|poly| kzg.commit(poly).unwrap()).collect::<Vec<_>>();
+	let extended_commitments = extend_and_reorder_elements(kzg.get_fs(), &commitments).unwrap();
+
+	let mut matrix = polys
+		.iter()
+		.enumerate()
+		.flat_map(|(y, poly)| poly_to_segment_vec(poly, kzg, y, chunk_len).unwrap())
+		.collect::<Vec<_>>();
+	let cols = chunk_count * 2;
+	let parity_cols = (0..cols)
+		.map(|x| {
+			let col = (0..k).map(|y| matrix[y * cols + x].clone()).collect::<Vec<_>>();
+			extend_segments_col(kzg.get_fs(), &col).unwrap()
+		})
+		.collect::<Vec<_>>();
+	for i in 0..k {
+		matrix.extend(parity_cols.iter().map(|col| col[i].clone()));
+	}
+	(matrix, extended_commitments)
+}
+
+#[test]
+fn test_recover_matrix() {
+	let (k, chunk_count, chunk_len) = (4, 4, 16);
+	let kzg = KZG::default_embedded();
+	let (matrix, commitments) = extended_matrix(k, chunk_count, chunk_len, &kzg);
+	let cols = chunk_count * 2;
+
+	// The first row keeps 2 segments and the last column 1, so that neither can be recovered
+	// without the other
+	let samples = matrix
+		.iter()
+		.filter(|s| {
+			let (x, y) = (s.position.x as usize, s.position.y as usize);
+			!(y == 0 && x < 6) && !(x == cols - 1 && y > 0)
+		})
+		.cloned()
+		.collect::<Vec<_>>();
+
+	let recovered = recover_matrix(&samples, k, chunk_count, &kzg).unwrap();
+	assert!(recovered.is_complete());
+	assert_eq!(recovered.rows(), k * 2);
+	for segment in matrix.iter() {
+		let recovered_segment = recovered.get(&segment.position).unwrap();
+		assert_eq!(recovered_segment.content.data, segment.content.data);
+		let commitment = &commitments[segment.position.y as usize];
+		assert!(recovered_segment.verify(&kzg, commitment, chunk_count).unwrap());
+	}
+	assert_eq!(recovered.row(0).len(), cols);
+
+	// Nothing to recover
+	let recovered = recover_matrix(&matrix, k, chunk_count, &kzg).unwrap();
+	assert!(recovered.is_complete());
+	assert!(recovered.segments.iter().zip(matrix.iter()).all(|(r, s)| r.as_ref() == Some(s)));
+}
+
+#[test]
+fn test_recover_matrix_stalls() {
+	let (k, chunk_count, chunk_len) = (4, 4, 16);
+	let kzg = KZG::default_embedded();
+	let (matrix, _) = extended_matrix(k, chunk_count, chunk_len, &kzg);
+
+	// Rows 3.. and columns 3.. keep 3 segments out of 8, and so can not be recovered
+	let samples = matrix
+		.iter()
+		.filter(|s| s.position.x < 3 || s.position.y < 3)
+		.cloned()
+		.collect::<Vec<_>>();
+	let recovered = recover_matrix(&samples, k, chunk_count, &kzg).unwrap();
+	assert!(!recovered.is_complete());
+	assert_eq!(recovered.unrecoverable.len(), 25);
+	assert!(recovered
+		.unrecoverable
+		.iter()
+		.all(|position| position.x >= 3 && position.y >= 3 && recovered.get(position).is_none()));
+	for sample in samples.iter() {
+		assert_eq!(recovered.get(&sample.position), Some(sample));
+	}
+
+	assert!(recover_matrix(&[], k, chunk_count, &kzg).is_err());
+	assert!(recover_matrix(&samples, 3, chunk_count, &kzg).is_err());
+	let mut outside = samples.clone();
+	outside[0].position.y = (k * 2) as u32;
+	assert!(recover_matrix(&outside, k, chunk_count, &kzg).is_err());
+}
+
+#[test]
+fn test_verify_malformed_segment() {
+	let (k, chunk_count, chunk_len) = (1, 4, 16);
+	let kzg = KZG::default_embedded();
+	let (matrix, commitments) = extended_matrix(k, chunk_count, chunk_len, &kzg);
+	let segment = &matrix[1];
+	assert!(segment.verify(&kzg, &commitments[0], chunk_count).unwrap());
+
+	// Counts that are not powers of two, or chunks wider than the settings, are rejected
+	assert!(segment.verify(&kzg, &commitments[0], 0).is_err());
+	assert!(segment.verify(&kzg, &commitments[0], 3).is_err());
+	assert!(segment.verify(&kzg, &commitments[0], kzg.max_width()).is_err());
+
+	let mut wide = segment.clone();
+	wide.content.data = vec![BlsScalar::default(); kzg.max_width()];
+	assert!(wide.checked().is_ok());
+	assert!(wide.verify(&kzg, &commitments[0], chunk_count).is_err());
+}
+
+#[test]
+fn test_update_commitment() {
+	let kzg = KZG::default_embedded();
+	let bytes_per_blob = 31 * 64;
+	let bytes = random_bytes(bytes_per_blob);
+
+	let blob = Blob::try_from_bytes_pad(&bytes[..31 * 20], bytes_per_blob).unwrap();
+	let commitment = blob.commit(&kzg).unwrap();
+
+	// Data appended to the padding
+	let appended = Blob::try_from_bytes_pad(&bytes[..31 * 40], bytes_per_blob).unwrap();
+	let updated = blob.update_commitment(&kzg, &commitment, &appended).unwrap();
+	assert_eq!(updated, appended.commit(&kzg).unwrap());
+
+	// A changed suffix
+	let mut changed = appended.clone();
+	changed.0[30] = BlsScalar::from(rand::random::<[u8; 31]>());
+	let updated = appended.update_commitment(&kzg, &updated, &changed).unwrap();
+	assert_eq!(updated, changed.commit(&kzg).unwrap());
+
+	// Unchanged
+	assert_eq!(changed.update_commitment(&kzg, &updated, &changed).unwrap(), updated);
+
+	let width = kzg.ks.secret_g1.len();
+	assert!(kzg.update_commitment(&updated, width - 1, &[], &changed.0[..2]).is_err());
+}
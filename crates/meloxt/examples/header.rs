@@ -40,6 +40,19 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
 	debug!("Current head block extrinsic: {:?}", head_block.block.extrinsics);
 	debug!("Current head block header: {:?}", head_block.block.header);
 
+	// Check that the rows of each app are all its rows in the block
+	let header = head_block.block.header;
+	if header.namespace_root().is_some() {
+		for lookup in header.extension.app_lookup.iter() {
+			let (rows, proof) =
+				header.extension.namespace_proof(lookup.app_id).ok_or("Invalid commitments")?;
+			if !header.verify_app_rows(lookup.app_id, &rows, &proof) {
+				return Err(format!("Incomplete rows of app {}", lookup.app_id).into())
+			}
+		}
+		info!("{} Rows of {} apps are complete", SUCCESS, header.extension.app_lookup.len());
+	}
+
     info!("{} : Header", ALL_SUCCESS);
 
 	Ok(())
@@ -0,0 +1,65 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::{ensure, Result};
+use log::{error, info};
+use meloxt::{info_msg::*, init_logger, test_harness::TestNode};
+use primitive_types::H256;
+use subxt::rpc::rpc_params;
+
+#[tokio::main]
+pub async fn main() {
+	init_logger().unwrap();
+
+	if let Err(err) = run().await {
+		error!("{}", err);
+	}
+}
+
+async fn run() -> Result<()> {
+	info!("{} republish blob is unsafe", START_EXAMPLE);
+
+	// A node serving the unsafe calls to local clients looks the sidecar up, and does not hold it.
+	let mut node = TestNode::builder().arg("--rpc-methods").arg("unsafe").spawn().await?;
+	let republished: bool = node
+		.client()
+		.api
+		.rpc()
+		.request("das_republishBlob", rpc_params![H256::repeat_byte(0x42)])
+		.await?;
+	ensure!(!republished, "The node republished a sidecar it does not hold");
+	node.kill().await?;
+	info!("{}: Republishing allowed with unsafe calls", SUCCESS);
+
+	// A node serving only the safe calls denies it.
+	let mut node = TestNode::builder().arg("--rpc-methods").arg("safe").spawn().await?;
+	let denied = node
+		.client()
+		.api
+		.rpc()
+		.request::<bool>("das_republishBlob", rpc_params![H256::repeat_byte(0x42)])
+		.await
+		.map_err(|e| e.to_string());
+	ensure!(
+		matches!(&denied, Err(e) if e.contains("unsafe")),
+		"Republishing was not denied: {:?}",
+		denied
+	);
+	node.kill().await?;
+	info!("{}: Republishing denied with safe calls only", SUCCESS);
+
+	info!("{} : Republish blob is unsafe", ALL_SUCCESS);
+
+	Ok(())
+}
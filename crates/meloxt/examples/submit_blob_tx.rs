@@ -14,8 +14,7 @@
 
 use futures::StreamExt;
 use log::{debug, error, info};
-use melo_core_primitives::SidecarMetadata;
-use melo_das_rpc::BlobTxSatus;
+use melo_core_primitives::{BlobTxSatus, SidecarMetadata};
 use meloxt::{
 	commitments_to_runtime, info_msg::*, init_logger, melodot, sidecar_metadata,
 	sidecar_metadata_to_runtime, Client, ClientBuilder, ClientSync,
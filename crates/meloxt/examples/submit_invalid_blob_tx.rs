@@ -13,12 +13,11 @@
 // limitations under the License.
 
 use log::{error, info};
-use melo_core_primitives::SidecarMetadata;
+use melo_core_primitives::{BlobTxSatus, SidecarMetadata};
 use melo_das_primitives::crypto::{KZGCommitment as KZGCommitmentT, KZGProof as KZGProofT};
-use melo_das_rpc::BlobTxSatus;
 use meloxt::{
 	commitments_to_runtime, info_msg::*, init_logger, melodot, sidecar_metadata,
-	sidecar_metadata_to_runtime, wait_for_block, Client, ClientBuilder, ClientSync,
+	sidecar_metadata_to_runtime, Client, ClientBuilder, ClientSync,
 };
 use primitive_types::H256;
 use subxt::rpc::{rpc_params, RpcParams};
@@ -56,6 +55,12 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
 	// Invalid bytes_len
 	submit_invalid_bytes_len(&client, bytes.clone(), metadata.clone()).await?;
 
+	// Oversized data
+	submit_oversized(&client, bytes.clone(), metadata.clone()).await?;
+
+	// Missing commitment
+	submit_missing_commitment(&client, bytes.clone(), metadata.clone()).await?;
+
 	// Invalid commitments
 	submit_invalid_commitments(&client, bytes.clone(), metadata.clone()).await?;
 
@@ -97,6 +102,38 @@ async fn submit_invalid_bytes_len(
 	rpc_err_handler(client, "10005".to_string(), "InvalidBytesLen".to_string(), &params).await
 }
 
+async fn submit_oversized(
+	client: &Client,
+	bytes: Vec<u8>,
+	metadata: SidecarMetadata,
+) -> Result<(), Box<dyn std::error::Error>> {
+	let mut metadata = metadata;
+	metadata.bytes_len = u32::MAX;
+
+	let (hex_bytes, hex_extrinsic) = create_params(&client, bytes, &metadata).await?;
+
+	let params = rpc_params![hex_bytes, hex_extrinsic];
+
+	rpc_err_handler(client, "10008".to_string(), "BlobTooLarge".to_string(), &params).await
+}
+
+async fn submit_missing_commitment(
+	client: &Client,
+	bytes: Vec<u8>,
+	metadata: SidecarMetadata,
+) -> Result<(), Box<dyn std::error::Error>> {
+	let mut metadata = metadata;
+	metadata.commitments.pop();
+	metadata.proofs.pop();
+
+	let (hex_bytes, hex_extrinsic) = create_params(&client, bytes, &metadata).await?;
+
+	let params = rpc_params![hex_bytes, hex_extrinsic];
+
+	rpc_err_handler(client, "10010".to_string(), "CommitmentCountMismatch".to_string(), &params)
+		.await
+}
+
 async fn submit_invalid_commitments(
 	client: &Client,
 	bytes: Vec<u8>,
@@ -116,17 +153,7 @@ async fn submit_invalid_commitments(
 
 	let params = rpc_params![hex_bytes, hex_extrinsic];
 
-	let res: BlobTxSatus<H256> = client.api.rpc().request("das_submitBlobTx", params).await?;
-
-	if let Some(err) = res.err {
-		info!("{}: Submit invalid commitments, tx failed: {:?}", SUCCESS, err);
-	} else {
-		info!("{}: Submit invalid commitments, but tx success", ERROR);
-	}
-
-	wait_for_block(&client).await?;
-
-	Ok(())
+	rpc_err_handler(client, "10011".to_string(), "InvalidCommitments".to_string(), &params).await
 }
 
 async fn submit_invalid_proofs(
@@ -144,17 +171,7 @@ async fn submit_invalid_proofs(
 
 	let params = rpc_params![hex_bytes, hex_extrinsic];
 
-	let res: BlobTxSatus<H256> = client.api.rpc().request("das_submitBlobTx", params).await?;
-
-	if let Some(err) = res.err {
-		info!("{}: Submit invalid proofs, tx failed: {:?}", SUCCESS, err);
-	} else {
-		info!("{}: Submit invalid proofs, but tx success", ERROR);
-	}
-
-	wait_for_block(&client).await?;
-
-	Ok(())
+	rpc_err_handler(client, "10011".to_string(), "InvalidProofs".to_string(), &params).await
 }
 
 async fn create_params(
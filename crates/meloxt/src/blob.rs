@@ -0,0 +1,50 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::AccountId;
+use codec::{Decode, Encode};
+use melo_core_primitives::SidecarMetadata;
+use melo_das_primitives::{KZGCommitment, KZGProof};
+
+/// The metadata of a blob as stored in `MeloStore::Metadata`.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct BlobMetadata {
+	/// Unique identifier for the application that uses this blob.
+	pub app_id: u32,
+	/// Account ID of the entity that submitted this blob.
+	pub from: AccountId,
+	/// List of KZG commitments associated with this blob.
+	pub commitments: Vec<KZGCommitment>,
+	/// List of KZG proofs associated with this blob.
+	pub proofs: Vec<KZGProof>,
+	/// Length of the data in bytes that this metadata represents.
+	pub bytes_len: u32,
+	/// Flag indicating whether the blob data is available or not.
+	pub is_available: bool,
+	/// Nonce for the application that uses this blob.
+	pub nonce: u32,
+}
+
+impl BlobMetadata {
+	/// Returns the sidecar metadata this blob was submitted with.
+	pub fn sidecar_metadata(&self) -> SidecarMetadata {
+		SidecarMetadata::new(
+			self.app_id,
+			self.bytes_len,
+			self.nonce,
+			self.commitments.clone(),
+			self.proofs.clone(),
+		)
+	}
+}
@@ -0,0 +1,92 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+	melodot::{farmers_fortune, melo_store},
+	Client, MeloConfig, H256,
+};
+use anyhow::Result;
+use futures::{future, stream::BoxStream, StreamExt};
+use subxt::{blocks::Block, OnlineClient};
+
+/// A decoded event related to an application.
+#[derive(Debug)]
+pub enum AppEvent {
+	/// Data of the application was submitted.
+	DataSubmitted(melo_store::events::DataReceived),
+	/// The application was registered.
+	AppIdRegistered(melo_store::events::AppIdRegistered),
+	/// An unavailability report was submitted for a block.
+	ReportReceived(melo_store::events::ReportReceived),
+	/// A farmer claimed a reward for storing data.
+	RewardClaimed(farmers_fortune::events::RewardClaimed),
+}
+
+/// The application events of a single finalized block.
+#[derive(Debug)]
+pub struct BlockAppEvents {
+	/// The hash of the block.
+	pub block_hash: H256,
+	/// The number of the block.
+	pub block_number: u32,
+	/// The events in the order they were emitted.
+	pub events: Vec<AppEvent>,
+}
+
+impl<S> Client<S> {
+	/// Subscribes to the events of the given application in finalized blocks.
+	///
+	/// `DataReceived` and `AppIdRegistered` events are filtered by `app_id`. Reports and reward
+	/// claims are not bound to an application and are always yielded. Blocks without any matching
+	/// event are skipped.
+	pub async fn watch_app_events(
+		&self,
+		app_id: u32,
+	) -> Result<BoxStream<'static, Result<BlockAppEvents>>> {
+		let blocks = self.api.blocks().subscribe_finalized().await?;
+
+		let stream = blocks
+			.then(move |block| async move { app_events(block?, app_id).await })
+			.filter(|res| future::ready(!matches!(res, Ok(block) if block.events.is_empty())));
+
+		Ok(stream.boxed())
+	}
+}
+
+async fn app_events(
+	block: Block<MeloConfig, OnlineClient<MeloConfig>>,
+	app_id: u32,
+) -> Result<BlockAppEvents> {
+	let mut events = Vec::new();
+
+	for event in block.events().await?.iter() {
+		let event = event?;
+
+		if let Some(ev) = event.as_event::<melo_store::events::DataReceived>()? {
+			if ev.app_id == app_id {
+				events.push(AppEvent::DataSubmitted(ev));
+			}
+		} else if let Some(ev) = event.as_event::<melo_store::events::AppIdRegistered>()? {
+			if ev.app_id == app_id {
+				events.push(AppEvent::AppIdRegistered(ev));
+			}
+		} else if let Some(ev) = event.as_event::<melo_store::events::ReportReceived>()? {
+			events.push(AppEvent::ReportReceived(ev));
+		} else if let Some(ev) = event.as_event::<farmers_fortune::events::RewardClaimed>()? {
+			events.push(AppEvent::RewardClaimed(ev));
+		}
+	}
+
+	Ok(BlockAppEvents { block_hash: block.hash(), block_number: block.number(), events })
+}
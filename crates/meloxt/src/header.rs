@@ -13,14 +13,22 @@
 // limitations under the License.
 
 use codec::{Decode, Encode};
+use core::ops::Range;
 use melo_das_primitives::KZGCommitment;
 pub use primitive_types::{H256, U256};
 use serde::{Deserialize, Serialize};
 // use sp_runtime::traits::BlakeTwo256;
 use sp_runtime::traits::{BlakeTwo256, Hash};
-use subxt::config::{substrate::{Digest, BlakeTwo256 as SubtxBlakeTwo256},  Hasher, Header as SubtxHeader};
+use subxt::config::{substrate::{Digest, DigestItem, BlakeTwo256 as SubtxBlakeTwo256},  Hasher, Header as SubtxHeader};
 
-use melo_core_primitives::{traits::HeaderWithCommitment, HeaderExtension};
+use melo_core_primitives::{
+	inclusion::MELO_ENGINE_ID,
+	namespace::{
+		NamespaceId, NamespaceProof, NamespacedNode, NamespacedRow, MELO_NAMESPACE_ENGINE_ID,
+	},
+	traits::HeaderWithCommitment,
+	ExtensionDigest, HeaderExtension,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
 #[serde(rename_all = "camelCase")]
@@ -61,6 +69,61 @@ where
 	TryFrom::try_from(u256).map_err(|_| serde::de::Error::custom("Try from failed"))
 }
 
+impl MelodotHeader {
+	/// Returns the DAS metadata deposited into the digest, if any.
+	pub fn extension_digest(&self) -> Option<ExtensionDigest> {
+		self.digest.logs.iter().find_map(|item| match item {
+			DigestItem::Consensus(id, data) if id == &MELO_ENGINE_ID =>
+				ExtensionDigest::decode(&mut &data[..]).ok(),
+			_ => None,
+		})
+	}
+
+	/// Returns the namespace root deposited into the digest, if any.
+	pub fn namespace_root(&self) -> Option<NamespacedNode> {
+		self.digest.logs.iter().find_map(|item| match item {
+			DigestItem::Consensus(id, data) if id == &MELO_NAMESPACE_ENGINE_ID =>
+				NamespacedNode::decode(&mut &data[..]).ok(),
+			_ => None,
+		})
+	}
+
+	/// Verifies with `proof` that `rows` are all the rows of the given app in this block.
+	///
+	/// Returns `false` if the block has no namespace root.
+	pub fn verify_app_rows(
+		&self,
+		app_id: u32,
+		rows: &[NamespacedRow],
+		proof: &NamespaceProof,
+	) -> bool {
+		self.namespace_root()
+			.map_or(false, |root| proof.verify(&root, NamespaceId::from_app_id(app_id), rows))
+	}
+
+	/// Returns the row ranges that belong to the given app.
+	///
+	/// The app lookup of the extension digest is used when present, so that the rows can be
+	/// determined without the full commitment list.
+	pub fn app_rows(&self, app_id: u32) -> Vec<Range<u32>> {
+		match self.extension_digest() {
+			Some(extension_digest) => extension_digest.app_rows(app_id),
+			None => self.extension.app_rows(app_id),
+		}
+	}
+
+	/// Returns the commitments of the rows that belong to the given app, along with their row
+	/// indices.
+	pub fn app_commitments(&self, app_id: u32) -> Option<Vec<(u32, KZGCommitment)>> {
+		let commitments = self.commitments()?;
+		self.app_rows(app_id)
+			.into_iter()
+			.flatten()
+			.map(|row| commitments.get(row as usize).map(|c| (row, *c)))
+			.collect()
+	}
+}
+
 impl SubtxHeader for MelodotHeader {
 	type Hasher = SubtxBlakeTwo256;
 	type Number = u32;
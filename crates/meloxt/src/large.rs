@@ -0,0 +1,245 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Payloads larger than a submission takes, split across consecutive blocks.
+//!
+//! [`TxManager::submit_large`] cuts a payload into chunks of the maximum length of a submission,
+//! submits each once the previous one is finalized, the nonces of an app following each other,
+//! and submits last a [`Manifest`] blob listing the chunks. [`Client::get_large`] fetches the
+//! manifest and the chunks, each verified against its commitments as [`ClientSync::get_blob`]
+//! does, and checks the reassembled payload against the length and hash of the manifest.
+
+use crate::{Client, ClientSync, MeloConfig, TxManager, H256};
+use anyhow::{anyhow, Result};
+use codec::{Decode, Encode};
+use futures::StreamExt;
+use log::info;
+use melo_core_primitives::{BlobLimits, DasParams, SidecarMetadata};
+use subxt::{
+	config::{substrate::BlakeTwo256, Hasher},
+	rpc::rpc_params,
+	tx::Signer,
+};
+
+/// The number of finalized blocks a chunk is waited for before the submission is given up.
+pub const MAX_INCLUSION_BLOCKS: usize = 64;
+
+/// The ID of the `AppDataApi` runtime API, the BLAKE2b-64 hash of its name.
+const APP_DATA_API_ID: &str = "0xed2733dd0c0947f4";
+
+/// The first version of `AppDataApi` reporting the blob limits.
+const BLOB_LIMITS_API_VERSION: u64 = 5;
+
+/// A chunk of a large payload.
+#[derive(Encode, Decode, Clone, Debug, PartialEq, Eq)]
+pub struct ChunkRef {
+	/// The hash of the block including the chunk.
+	pub block_hash: H256,
+	/// The hash of the sidecar metadata of the chunk.
+	pub data_hash: H256,
+	/// The length of the chunk in bytes.
+	pub len: u32,
+}
+
+/// The blob linking the chunks of a large payload.
+#[derive(Encode, Decode, Clone, Debug, PartialEq, Eq)]
+pub struct Manifest {
+	/// The length of the payload in bytes.
+	pub len: u64,
+	/// The Blake2-256 hash of the payload.
+	pub payload_hash: H256,
+	/// The chunks of the payload, in order.
+	pub chunks: Vec<ChunkRef>,
+}
+
+/// A large payload submitted across blocks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LargeBlob {
+	/// The hash of the block including the manifest.
+	pub block_hash: H256,
+	/// The hash of the sidecar metadata of the manifest.
+	pub manifest_hash: H256,
+	/// The manifest.
+	pub manifest: Manifest,
+}
+
+impl<S> Client<S> {
+	/// Returns the limits a submission of `app_id` to the next block has to respect.
+	///
+	/// Runtimes that can not report their limits yet are assumed to have those of an empty block
+	/// under the default DAS parameters.
+	pub async fn blob_limits(&self, app_id: u32) -> Result<BlobLimits> {
+		if self.app_data_api_version().await?.unwrap_or_default() < BLOB_LIMITS_API_VERSION {
+			return Ok(BlobLimits::of_empty_block(&DasParams::default()))
+		}
+		let bytes = self
+			.api
+			.rpc()
+			.state_call("AppDataApi_blob_limits", Some(&app_id.encode()[..]), None)
+			.await?;
+		Ok(BlobLimits::decode(&mut &bytes[..])?)
+	}
+
+	/// Returns the version of `AppDataApi` implemented by the runtime of the best block, `None`
+	/// if it implements none.
+	async fn app_data_api_version(&self) -> Result<Option<u64>> {
+		let version: serde_json::Value =
+			self.api.rpc().request("state_getRuntimeVersion", rpc_params![]).await?;
+		Ok(version["apis"]
+			.as_array()
+			.into_iter()
+			.flatten()
+			.find(|api| api[0] == APP_DATA_API_ID)
+			.and_then(|api| api[1].as_u64()))
+	}
+}
+
+impl<S> Client<S>
+where
+	S: Signer<MeloConfig> + Send + Sync,
+{
+	/// Retrieves a payload submitted with [`TxManager::submit_large`].
+	///
+	/// # Arguments
+	///
+	/// * `block_hash` - The hash of the block including the manifest.
+	/// * `manifest_hash` - The hash of the sidecar metadata of the manifest.
+	///
+	/// # Returns
+	///
+	/// Returns the payload, or `None` if the manifest or one of the chunks is unknown or not
+	/// retrievable. Fails if the chunks do not reassemble into the payload of the manifest.
+	pub async fn get_large(
+		&self,
+		block_hash: H256,
+		manifest_hash: H256,
+	) -> Result<Option<Vec<u8>>> {
+		let manifest = match self.get_blob(block_hash, manifest_hash).await? {
+			Some(bytes) => Manifest::decode(&mut &bytes[..])
+				.map_err(|e| anyhow!("Invalid manifest {:?}: {}", manifest_hash, e))?,
+			None => return Ok(None),
+		};
+
+		let mut payload = Vec::with_capacity(manifest.len as usize);
+		for chunk in &manifest.chunks {
+			let bytes = match self.get_blob(chunk.block_hash, chunk.data_hash).await? {
+				Some(bytes) => bytes,
+				None => return Ok(None),
+			};
+			if bytes.len() != chunk.len as usize {
+				return Err(anyhow!(
+					"Chunk {:?} holds {} bytes, the manifest lists {}",
+					chunk.data_hash,
+					bytes.len(),
+					chunk.len
+				))
+			}
+			payload.extend(bytes);
+		}
+
+		if payload.len() as u64 != manifest.len ||
+			BlakeTwo256::hash(&payload) != manifest.payload_hash
+		{
+			return Err(anyhow!("The chunks do not match the payload of the manifest"))
+		}
+		Ok(Some(payload))
+	}
+}
+
+impl<S> TxManager<S>
+where
+	S: Signer<MeloConfig> + Send + Sync,
+{
+	/// Submits `data` of `app_id`, whatever its length, as blobs across consecutive blocks.
+	///
+	/// The data is cut into chunks of the maximum length of a submission, each submitted once the
+	/// previous one is finalized, then the [`Manifest`] of the chunks is submitted.
+	///
+	/// # Returns
+	///
+	/// Returns the block and the hash of the manifest, to pass to [`Client::get_large`].
+	pub async fn submit_large(&self, app_id: u32, data: &[u8]) -> Result<LargeBlob> {
+		if data.is_empty() {
+			return Err(anyhow!("No data to submit"))
+		}
+
+		let max_len = self.client().blob_limits(app_id).await?.max_bytes_len as usize;
+		if max_len == 0 {
+			return Err(anyhow!("App {} can not submit data", app_id))
+		}
+
+		let mut chunks = Vec::new();
+		for (index, chunk) in data.chunks(max_len).enumerate() {
+			let (block_hash, metadata) = self.submit_finalized(app_id, chunk).await?;
+			info!(
+				"Chunk {} of {} bytes of app {} included in block {:?}",
+				index,
+				chunk.len(),
+				app_id,
+				block_hash
+			);
+			chunks.push(ChunkRef {
+				block_hash,
+				data_hash: H256(metadata.id()),
+				len: chunk.len() as u32,
+			});
+		}
+
+		let manifest =
+			Manifest { len: data.len() as u64, payload_hash: BlakeTwo256::hash(data), chunks };
+		let encoded = manifest.encode();
+		if encoded.len() > max_len {
+			return Err(anyhow!(
+				"The manifest of {} chunks exceeds a submission",
+				manifest.chunks.len()
+			))
+		}
+		let (block_hash, metadata) = self.submit_finalized(app_id, &encoded).await?;
+
+		Ok(LargeBlob { block_hash, manifest_hash: H256(metadata.id()), manifest })
+	}
+
+	/// Submits `bytes` as the next blob of `app_id` and waits for it to be finalized.
+	///
+	/// Returns the hash of the block including the blob, and its metadata.
+	async fn submit_finalized(&self, app_id: u32, bytes: &[u8]) -> Result<(H256, SidecarMetadata)> {
+		let client = self.client();
+		let nonce = client.nonce(app_id).await?;
+		let metadata =
+			SidecarMetadata::try_from_app_data(bytes, app_id, nonce + 1).map_err(|e| anyhow!(e))?;
+
+		// Subscribe first, so that the block including the blob is not missed.
+		let mut blocks = client.api.blocks().subscribe_finalized().await?;
+		self.submit_blob(bytes, &metadata).await?;
+
+		for _ in 0..MAX_INCLUSION_BLOCKS {
+			let block = blocks.next().await.ok_or_else(|| anyhow!("Subscription closed"))??;
+			let included = client
+				.blob_metadata(block.hash())
+				.await?
+				.iter()
+				.any(|blob| blob.sidecar_metadata().id() == metadata.id());
+			if included {
+				return Ok((block.hash(), metadata))
+			}
+		}
+
+		Err(anyhow!(
+			"Blob of app {} nonce {} not finalized within {} blocks",
+			app_id,
+			metadata.nonce,
+			MAX_INCLUSION_BLOCKS
+		))
+	}
+}
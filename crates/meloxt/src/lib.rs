@@ -12,13 +12,23 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use codec::Decode;
-use melo_core_primitives::SidecarMetadata;
+use melo_core_primitives::{
+	padding,
+	storage_proof::{metadata_storage_key, CommitmentsProof, StoredBlob},
+	Encryption, EncryptionKey, PlotHashing, SidecarMetadata,
+};
+use melo_das_primitives::{
+	chunking::{blobs_to_bytes, bytes_to_blobs},
+	config::FIELD_ELEMENTS_PER_BLOB,
+};
 use subxt::{
 	config::substrate::BlakeTwo256,
 	ext::scale_encode::EncodeAsType,
 	utils::{AccountId32, MultiAddress, MultiSignature},
+	rpc::rpc_params,
+	tx::Signer,
 	Config, OnlineClient, PolkadotConfig,
 };
 use subxt_signer::sr25519::{
@@ -30,12 +40,41 @@ use subxt_signer::sr25519::{
 #[subxt::subxt(runtime_metadata_path = "melodot_metadata.scale")]
 pub mod melodot {}
 
+pub mod blob;
+pub use blob::BlobMetadata;
+
+pub mod events;
+pub use events::{AppEvent, BlockAppEvents};
+
 pub mod header;
 pub use header::MelodotHeader;
 
+pub mod large;
+pub use large::{ChunkRef, LargeBlob, Manifest};
+
 mod log;
 pub use crate::log::init_logger;
 
+pub mod rewards;
+pub use rewards::{RewardEstimate, RewardParams};
+
+pub mod signer;
+#[cfg(feature = "native")]
+pub use signer::RemoteSigner;
+pub use signer::SignerSource;
+
+#[cfg(feature = "native")]
+pub mod test_harness;
+
+#[cfg(feature = "native")]
+pub mod testnet;
+
+pub mod tx_manager;
+pub use tx_manager::{RetryConfig, TxError, TxManager};
+
+#[cfg(feature = "web")]
+pub mod wasm;
+
 mod helper;
 pub use helper::*;
 
@@ -61,14 +100,16 @@ impl Config for MeloConfig {
 }
 
 /// Client structure containing the API for blockchain interactions and a signer for transactions.
-pub struct Client {
+///
+/// The signer can be any [`Signer`] backend, see the [`signer`] module.
+pub struct Client<S = Keypair> {
 	pub api: OnlineClient<MeloConfig>,
-	pub signer: Keypair,
+	pub signer: S,
 }
 
-impl Client {
+impl<S> Client<S> {
 	/// Update the signer for the client.
-	pub fn set_signer(&mut self, signer: Keypair) {
+	pub fn set_signer(&mut self, signer: S) {
 		self.signer = signer;
 	}
 
@@ -86,6 +127,33 @@ impl Client {
 		let address = subxt::dynamic::storage(pallet_name, entry_name, vec![key]);
 		Ok(self.api.storage().address_bytes(&address)?)
 	}
+
+	/// Returns the proof-of-space challenge of the block `number`, read from the state of the
+	/// block `at`.
+	///
+	/// `pallet-farmers-fortune` only keeps the challenges of the last two blocks, so `at` is
+	/// usually the block `number` itself.
+	pub async fn challenge(&self, number: u32, at: H256) -> Result<Option<H256>> {
+		let key = self.storage_key("FarmersFortune", "Challenges", &number)?;
+		match self.api.rpc().storage(&key, Some(at)).await? {
+			Some(data) => Ok(Some(H256::decode(&mut &data.0[..])?)),
+			None => Ok(None),
+		}
+	}
+
+	/// Returns the hashing scheme of plots active at the block `at`, the best block if `None`.
+	pub async fn plot_hashing(&self, at: Option<H256>) -> Result<PlotHashing> {
+		let address = subxt::dynamic::storage(
+			"DasConfig",
+			"ActivePlotHashing",
+			Vec::<subxt::dynamic::Value>::new(),
+		);
+		let key = self.api.storage().address_bytes(&address)?;
+		match self.api.rpc().storage(&key, at).await? {
+			Some(data) => Ok(PlotHashing::decode(&mut &data.0[..])?),
+			None => Ok(PlotHashing::default()),
+		}
+	}
 }
 
 #[async_trait::async_trait]
@@ -97,10 +165,78 @@ pub trait ClientSync {
 		bytes: Vec<u8>,
 		metadata: &SidecarMetadata,
 	) -> Result<(String, String)>;
+
+	/// Returns the metadata of all blobs submitted in the block with the given hash.
+	async fn blob_metadata(&self, block_hash: H256) -> Result<Vec<BlobMetadata>>;
+
+	/// Retrieves the original bytes of a blob.
+	///
+	/// The data is fetched through `das_getBlob` and verified locally against the KZG commitments
+	/// and proofs recorded on chain.
+	///
+	/// # Arguments
+	///
+	/// * `block_hash` - The hash of the block in which the blob was submitted.
+	/// * `data_hash` - The hash of the blob's sidecar metadata.
+	///
+	/// # Returns
+	///
+	/// Returns the unpadded bytes, or `None` if the blob is unknown or not retrievable.
+	async fn get_blob(&self, block_hash: H256, data_hash: H256) -> Result<Option<Vec<u8>>>;
+
+	/// Retrieves the data of a blob submitted framed by [`padding::pad`], as
+	/// [`ClientSync::get_blob`] does.
+	///
+	/// Returns exactly the data that was framed, or an error if the blob is not a valid frame.
+	async fn get_padded_blob(&self, block_hash: H256, data_hash: H256) -> Result<Option<Vec<u8>>>;
+
+	/// Encrypts `data` with `key` for the next submission of `app_id`.
+	///
+	/// # Returns
+	///
+	/// Returns the encrypted bytes to submit and their metadata, flagged with `encryption`.
+	async fn encrypt_app_data(
+		&self,
+		app_id: u32,
+		data: &[u8],
+		key: &EncryptionKey,
+		encryption: Encryption,
+	) -> Result<(Vec<u8>, SidecarMetadata)>;
+
+	/// Retrieves the data of a blob submitted encrypted with `key` and `encryption`, as
+	/// [`ClientSync::get_blob`] does, and decrypts it.
+	///
+	/// `data_hash` is the hash of the metadata flagged with `encryption`, as returned by
+	/// [`ClientSync::encrypt_app_data`]. Returns an error if the data does not decrypt with `key`.
+	async fn get_decrypted_blob(
+		&self,
+		block_hash: H256,
+		data_hash: H256,
+		key: &EncryptionKey,
+		encryption: Encryption,
+	) -> Result<Option<Vec<u8>>>;
+
+	/// Builds a storage proof of the commitments posted in the block with the given hash.
+	///
+	/// The proof covers the `MeloStore::Metadata` entry of the block, and is checked against the
+	/// state root of the header served by the node. A client holding a header it trusts verifies it
+	/// again with [`CommitmentsProof::verify_app`], without trusting the node.
+	///
+	/// # Returns
+	///
+	/// Returns the proof and the blobs of `app_id` it shows posted.
+	async fn prove_commitments(
+		&self,
+		block_hash: H256,
+		app_id: u32,
+	) -> Result<(CommitmentsProof, Vec<StoredBlob>)>;
 }
 
 #[async_trait::async_trait]
-impl ClientSync for Client {
+impl<S> ClientSync for Client<S>
+where
+	S: Signer<MeloConfig> + Send + Sync,
+{
 	async fn nonce(&self, app_id: u32) -> Result<u32> {
 		let address = self.storage_key("MeloStore", "Nonces", &app_id)?;
 
@@ -137,26 +273,163 @@ impl ClientSync for Client {
 	
 		Ok((hex_bytes, hex_extrinsic))
 	}
+
+	async fn blob_metadata(&self, block_hash: H256) -> Result<Vec<BlobMetadata>> {
+		let header = self
+			.api
+			.rpc()
+			.header(Some(block_hash))
+			.await?
+			.ok_or_else(|| anyhow!("Block {:?} not found", block_hash))?;
+
+		let address = self.storage_key("MeloStore", "Metadata", &header.number)?;
+
+		let maybe_metadata = self.api.rpc().storage(&address, Some(block_hash)).await?;
+
+		let metadata = match maybe_metadata {
+			None => Vec::new(),
+			Some(metadata) => Decode::decode(&mut &metadata.0[..])?,
+		};
+		Ok(metadata)
+	}
+
+	async fn prove_commitments(
+		&self,
+		block_hash: H256,
+		app_id: u32,
+	) -> Result<(CommitmentsProof, Vec<StoredBlob>)> {
+		let header = self
+			.api
+			.rpc()
+			.header(Some(block_hash))
+			.await?
+			.ok_or_else(|| anyhow!("Block {:?} not found", block_hash))?;
+
+		let key = metadata_storage_key(header.number);
+		let read_proof = self.api.rpc().read_proof([&key[..]], Some(block_hash)).await?;
+		let proof = CommitmentsProof {
+			block_hash,
+			proof: read_proof.proof.into_iter().map(|node| node.0).collect(),
+		};
+
+		let blobs = proof
+			.verify_app(&header.state_root, header.number, app_id)
+			.map_err(|e| anyhow!("Invalid commitments proof: {:?}", e))?;
+		Ok((proof, blobs))
+	}
+
+	async fn get_blob(&self, block_hash: H256, data_hash: H256) -> Result<Option<Vec<u8>>> {
+		let maybe_metadata = self
+			.blob_metadata(block_hash)
+			.await?
+			.into_iter()
+			.map(|metadata| metadata.sidecar_metadata())
+			.find(|metadata| metadata.id() == data_hash.0);
+
+		let metadata = match maybe_metadata {
+			Some(metadata) => metadata,
+			None => return Ok(None),
+		};
+
+		let params = rpc_params![metadata.app_id, metadata.nonce, metadata.commitments.clone()];
+		let maybe_hex: Option<String> = self.api.rpc().request("das_getBlob", params).await?;
+
+		let padded = match maybe_hex {
+			Some(hex_bytes) => hex::decode(hex_bytes.trim_start_matches("0x"))?,
+			None => return Ok(None),
+		};
+
+		// The node returns whole blobs, restore the submitted bytes as they were packed.
+		let bytes = bytes_to_blobs(&padded, FIELD_ELEMENTS_PER_BLOB)
+			.and_then(|blobs| blobs_to_bytes(&blobs, metadata.bytes_len as usize))
+			.map_err(|e| anyhow!("Retrieved data does not hold the submitted data: {}", e))?;
+
+		match metadata.verify_bytes(&bytes) {
+			Ok(true) => Ok(Some(bytes)),
+			Ok(false) => Err(anyhow!("Retrieved data does not match the commitments")),
+			Err(e) => Err(anyhow!("Failed to verify retrieved data: {}", e)),
+		}
+	}
+
+	async fn get_padded_blob(&self, block_hash: H256, data_hash: H256) -> Result<Option<Vec<u8>>> {
+		match self.get_blob(block_hash, data_hash).await? {
+			Some(bytes) => padding::unpad(&bytes)
+				.map(|data| Some(data.to_vec()))
+				.map_err(|e| anyhow!("Invalid padding of retrieved data: {:?}", e)),
+			None => Ok(None),
+		}
+	}
+
+	async fn encrypt_app_data(
+		&self,
+		app_id: u32,
+		data: &[u8],
+		key: &EncryptionKey,
+		encryption: Encryption,
+	) -> Result<(Vec<u8>, SidecarMetadata)> {
+		let nonce = self.nonce(app_id).await? + 1;
+		let bytes = encryption
+			.encrypt_with_nonce(key, app_id, nonce, data, rand::random())
+			.map_err(|e| anyhow!(e))?;
+		let metadata = SidecarMetadata::try_from_app_data(&bytes, app_id, nonce)
+			.map_err(|e| anyhow!(e))?
+			.with_encryption(encryption);
+		Ok((bytes, metadata))
+	}
+
+	async fn get_decrypted_blob(
+		&self,
+		block_hash: H256,
+		data_hash: H256,
+		key: &EncryptionKey,
+		encryption: Encryption,
+	) -> Result<Option<Vec<u8>>> {
+		// The chain does not record the encryption, the metadata is flagged back to be found.
+		let maybe_metadata = self
+			.blob_metadata(block_hash)
+			.await?
+			.into_iter()
+			.map(|metadata| metadata.sidecar_metadata())
+			.find(|metadata| metadata.clone().with_encryption(encryption).id() == data_hash.0);
+
+		let metadata = match maybe_metadata {
+			Some(metadata) => metadata,
+			None => return Ok(None),
+		};
+
+		match self.get_blob(block_hash, H256(metadata.id())).await? {
+			Some(bytes) => encryption
+				.decrypt(key, metadata.app_id, metadata.nonce, &bytes)
+				.map(Some)
+				.map_err(|e| anyhow!(e)),
+			None => Ok(None),
+		}
+	}
 }
 
 /// A builder pattern for creating a `Client` instance.
-pub struct ClientBuilder {
+pub struct ClientBuilder<S = Keypair> {
 	pub url: String,
-	pub signer: Keypair,
+	pub signer: S,
 }
 
-impl ClientBuilder {
+impl<S: Clone> ClientBuilder<S> {
 	/// Constructor for `ClientBuilder`.
-	pub fn new(url: &str, signer: Keypair) -> Self {
+	pub fn new(url: &str, signer: S) -> Self {
 		Self { url: url.to_string(), signer }
 	}
 
 	/// Asynchronously build and return a `Client` instance.
-	pub async fn build(&self) -> Result<Client> {
+	pub async fn build(&self) -> Result<Client<S>> {
 		let api = OnlineClient::<MeloConfig>::from_url(&self.url).await?;
 		Ok(Client { api, signer: self.signer.clone() })
 	}
 
+	/// Replace the signer, possibly with a different backend.
+	pub fn with_signer<T>(self, signer: T) -> ClientBuilder<T> {
+		ClientBuilder { url: self.url, signer }
+	}
+
 	/// Set the URL for the API client.
 	pub fn set_url(mut self, url: &str) -> Self {
 		self.url = url.to_string();
@@ -164,6 +437,13 @@ impl ClientBuilder {
 	}
 }
 
+impl ClientBuilder<Keypair> {
+	/// Use the keypair loaded from the given source as signer.
+	pub fn with_signer_source(self, source: &SignerSource) -> Result<Self> {
+		Ok(self.with_signer(source.keypair()?))
+	}
+}
+
 // Default implementation for `ClientBuilder`.
 impl Default for ClientBuilder {
 	fn default() -> Self {
@@ -0,0 +1,298 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `melo-cli`, a command line client for blob operations.
+//!
+//! All commands print their result as JSON on stdout.
+
+use anyhow::{anyhow, Result};
+use melo_core_primitives::{padding, Encryption, EncryptionKey, SidecarMetadata};
+use meloxt::{
+	melodot,
+	rewards::{self, DEFAULT_CLAIMS_WINDOW},
+	ClientBuilder, ClientSync, SignerSource, TxManager, H256,
+};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use structopt::StructOpt;
+use subxt::rpc::rpc_params;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "melo-cli", about = "Submit and retrieve Melodot blobs.")]
+struct Cli {
+	/// Websocket endpoint of the node.
+	#[structopt(long, default_value = "ws://127.0.0.1:9944")]
+	url: String,
+
+	/// Keystore file holding the secret URI of the signing account.
+	#[structopt(long, parse(from_os_str))]
+	keystore: Option<PathBuf>,
+
+	/// Secret URI of the signing account, used if no keystore is given.
+	#[structopt(long, default_value = "//Alice")]
+	suri: String,
+
+	#[structopt(subcommand)]
+	command: Command,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+	/// Submits the content of a file as a blob.
+	Submit {
+		/// The application to submit the blob for.
+		#[structopt(long)]
+		app_id: u32,
+		/// Prove all the blobs of the file with a single batch proof.
+		#[structopt(long)]
+		batch_proof: bool,
+		/// Frames the file with its length, so that it is retrieved exactly with `get --padded`.
+		#[structopt(long)]
+		padded: bool,
+		/// Encrypts the file with this hex-encoded 32-byte key of the application.
+		#[structopt(long, parse(try_from_str = parse_key))]
+		encryption_key: Option<EncryptionKey>,
+		/// The file to submit.
+		#[structopt(parse(from_os_str))]
+		file: PathBuf,
+	},
+	/// Retrieves and verifies a blob.
+	Get {
+		/// The block the blob was submitted in.
+		#[structopt(long)]
+		block_hash: H256,
+		/// The hash of the blob's metadata.
+		#[structopt(long)]
+		data_hash: H256,
+		/// Writes the blob to this file instead of printing it as hex.
+		#[structopt(long, parse(from_os_str))]
+		out: Option<PathBuf>,
+		/// Strips the framing of a blob submitted with `submit --padded`.
+		#[structopt(long)]
+		padded: bool,
+		/// Decrypts a blob submitted with `submit --encryption-key` with this key.
+		#[structopt(long, parse(try_from_str = parse_key))]
+		encryption_key: Option<EncryptionKey>,
+	},
+	/// Lists the blobs of a block and their availability.
+	Status {
+		/// The block to query.
+		#[structopt(long)]
+		block_hash: H256,
+	},
+	/// Shows the sampling confidence of a block.
+	Confidence {
+		/// The block to query.
+		#[structopt(long)]
+		block_hash: H256,
+	},
+	/// Estimates the farming rewards of plots of the given sizes from a measured plot.
+	Rewards {
+		/// The number of pieces of the measured plot, as `melo-plot-inspect stats` reports it.
+		#[structopt(long)]
+		plot_pieces: u64,
+		/// The number of matched pairs of the measured plot.
+		#[structopt(long)]
+		plot_pairs: u64,
+		/// The plot sizes to estimate, in pieces, the measured plot if none is given.
+		#[structopt(long)]
+		pieces: Vec<u64>,
+		/// The number of recent blocks whose claims are averaged, 100 by default.
+		#[structopt(long)]
+		window: Option<u32>,
+	},
+	/// Application management.
+	App(AppCommand),
+}
+
+#[derive(Debug, StructOpt)]
+enum AppCommand {
+	/// Registers a new application.
+	Register,
+}
+
+fn parse_key(s: &str) -> Result<EncryptionKey> {
+	let bytes = hex::decode(s.trim_start_matches("0x"))?;
+	let key = bytes.try_into().map_err(|_| anyhow!("Expected a 32-byte key"))?;
+	Ok(EncryptionKey(key))
+}
+
+#[tokio::main]
+async fn main() {
+	let cli = Cli::from_args();
+
+	match run(cli).await {
+		Ok(output) => println!("{}", output),
+		Err(err) => {
+			println!("{}", json!({ "error": err.to_string() }));
+			std::process::exit(1);
+		},
+	}
+}
+
+async fn run(cli: Cli) -> Result<Value> {
+	let source = match cli.keystore {
+		Some(path) => SignerSource::Keystore(path),
+		None => SignerSource::Uri(cli.suri),
+	};
+	let client = ClientBuilder::new(&cli.url, source.keypair()?).build().await?;
+
+	match cli.command {
+		Command::Submit { app_id, batch_proof, padded, encryption_key, file } => {
+			let mut bytes = std::fs::read(&file)?;
+			if padded {
+				bytes = padding::pad(&bytes);
+			}
+			let (bytes, metadata) = match encryption_key {
+				Some(key) if !batch_proof =>
+					client
+						.encrypt_app_data(app_id, &bytes, &key, Encryption::ChaCha20Poly1305)
+						.await?,
+				Some(_) => return Err(anyhow!("Encrypted files are submitted without batch proof")),
+				None => {
+					let nonce = client.nonce(app_id).await?;
+					let metadata = if batch_proof {
+						SidecarMetadata::try_from_app_data_with_batch_proof(
+							&bytes,
+							app_id,
+							nonce + 1,
+						)
+					} else {
+						SidecarMetadata::try_from_app_data(&bytes, app_id, nonce + 1)
+					}
+					.map_err(|e| anyhow!(e))?;
+					(bytes, metadata)
+				},
+			};
+
+			let manager = TxManager::new(client, Default::default());
+			let tx_hash = manager.submit_blob(&bytes, &metadata).await?;
+
+			Ok(json!({
+				"txHash": tx_hash,
+				"dataHash": H256(metadata.id()),
+				"appId": app_id,
+				"nonce": metadata.nonce,
+				"bytesLen": metadata.bytes_len,
+			}))
+		},
+		Command::Get { block_hash, data_hash, out, padded, encryption_key } => {
+			let maybe_bytes = match &encryption_key {
+				Some(key) =>
+					client
+						.get_decrypted_blob(
+							block_hash,
+							data_hash,
+							key,
+							Encryption::ChaCha20Poly1305,
+						)
+						.await?,
+				None => client.get_blob(block_hash, data_hash).await?,
+			};
+			let mut bytes =
+				maybe_bytes.ok_or_else(|| anyhow!("Blob not found or not retrievable"))?;
+			if padded {
+				bytes = padding::unpad(&bytes)
+					.map_err(|e| anyhow!("Invalid padding of retrieved data: {:?}", e))?
+					.to_vec();
+			}
+
+			match out {
+				Some(path) => {
+					std::fs::write(&path, &bytes)?;
+					Ok(json!({ "bytesLen": bytes.len(), "out": path }))
+				},
+				None => Ok(json!({
+					"bytesLen": bytes.len(),
+					"data": format!("0x{}", hex::encode(&bytes)),
+				})),
+			}
+		},
+		Command::Status { block_hash } => {
+			let blobs = client
+				.blob_metadata(block_hash)
+				.await?
+				.into_iter()
+				.map(|blob| {
+					json!({
+						"dataHash": H256(blob.sidecar_metadata().id()),
+						"appId": blob.app_id,
+						"nonce": blob.nonce,
+						"from": blob.from.to_string(),
+						"bytesLen": blob.bytes_len,
+						"isAvailable": blob.is_available,
+					})
+				})
+				.collect::<Vec<_>>();
+
+			Ok(json!({ "blockHash": block_hash, "blobs": blobs }))
+		},
+		Command::Confidence { block_hash } => {
+			let confidence: Option<u32> =
+				client.api.rpc().request("das_blockConfidence", rpc_params![block_hash]).await?;
+			let is_available: Option<bool> =
+				client.api.rpc().request("das_isAvailable", rpc_params![block_hash]).await?;
+
+			Ok(json!({
+				"blockHash": block_hash,
+				"confidence": confidence,
+				"isAvailable": is_available,
+			}))
+		},
+		Command::Rewards { plot_pieces, plot_pairs, pieces, window } => {
+			let params = client.reward_params(window.unwrap_or(DEFAULT_CLAIMS_WINDOW)).await?;
+			let sizes = if pieces.is_empty() { vec![plot_pieces] } else { pieces };
+			let curve = rewards::reward_curve(&params, plot_pieces, plot_pairs, sizes)
+				.into_iter()
+				.map(|point| {
+					json!({
+						"pieces": point.pieces,
+						"pairs": point.estimate.pairs,
+						"winProbability": point.estimate.win_probability,
+						"claimsPerDay": point.estimate.claims_per_day,
+						"rewardPerDay": point.estimate.reward_per_day.to_string(),
+					})
+				})
+				.collect::<Vec<_>>();
+
+			Ok(json!({
+				"rewardAmount": params.reward_amount.to_string(),
+				"maxClaimantsPerBlock": params.max_claimants_per_block,
+				"blockTimeMs": params.block_time_ms,
+				"claimantsPerBlock": params.claimants_per_block,
+				"curve": curve,
+			}))
+		},
+		Command::App(AppCommand::Register) => {
+			let register_app_tx = melodot::tx().melo_store().register_app();
+
+			let events = client
+				.api
+				.tx()
+				.sign_and_submit_then_watch_default(&register_app_tx, &client.signer)
+				.await?
+				.wait_for_finalized_success()
+				.await?;
+
+			let registered = events
+				.find_first::<melodot::melo_store::events::AppIdRegistered>()?
+				.ok_or_else(|| anyhow!("AppIdRegistered event not found"))?;
+
+			Ok(json!({
+				"blockHash": events.block_hash(),
+				"appId": registered.app_id,
+			}))
+		},
+	}
+}
@@ -0,0 +1,158 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Farming reward estimation.
+//!
+//! A farmer claims the reward of a block when a matched pair of its plot solves the challenge of
+//! the previous block: the Z value of the pair must equal the 16-bit Z value of the challenge, and
+//! the pieces of both cells must be selected by the hashes of their blocks, each with a chance of
+//! one half. A pair thus solves a challenge with a chance of 2^-18, and a plot of `pairs` pairs
+//! wins a block with a chance of `1 - (1 - 2^-18)^pairs`. The estimates assume a pre-cell is
+//! always found in the previous block, which holds while `PRE_CELL_LEADING_ZEROS` is 0.
+//!
+//! At most `MaxClaimantsPerBlock` farmers are rewarded per block, so when the recent blocks are
+//! crowded a solution is only claimed in proportion to the slots left. The reward of a claim is
+//! the fixed `RewardAmount`, before any pool takes its share.
+//!
+//! The matched pairs of a plot are reported by `melo-plot-inspect stats`. A pair is made of two
+//! adjacent cells with the same 32-bit Y value, taken from any of the pieces, so the pairs grow
+//! with the square of the number of pieces. [`reward_curve`] projects a measured plot to other
+//! sizes this way.
+use crate::{melodot, Client};
+use anyhow::Result;
+
+/// The default number of recent blocks whose claims are averaged.
+pub const DEFAULT_CLAIMS_WINDOW: u32 = 100;
+
+/// The bits a matched pair has to match to solve a challenge, 16 bits of Z value and one bit for
+/// the selection of each of its pieces.
+const PAIR_SOLUTION_BITS: i32 = 18;
+
+const MILLISECS_PER_DAY: f64 = 24.0 * 60.0 * 60.0 * 1000.0;
+
+/// The on-chain parameters of the farming rewards.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RewardParams {
+	/// The reward of a claim.
+	pub reward_amount: u128,
+	/// The maximum number of claims rewarded per block.
+	pub max_claimants_per_block: u32,
+	/// The expected time between two blocks, in milliseconds.
+	pub block_time_ms: u64,
+	/// The average number of claims per block over the recent blocks.
+	pub claimants_per_block: f64,
+}
+
+impl RewardParams {
+	/// Returns the expected number of blocks per day.
+	pub fn blocks_per_day(&self) -> f64 {
+		MILLISECS_PER_DAY / self.block_time_ms.max(1) as f64
+	}
+
+	/// Returns the chance of a solution to be rewarded, given the recent competition for the
+	/// claims of a block.
+	pub fn claim_share(&self) -> f64 {
+		(self.max_claimants_per_block as f64 / (self.claimants_per_block + 1.0)).min(1.0)
+	}
+}
+
+/// The expected rewards of a plot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RewardEstimate {
+	/// The matched pairs of the plot.
+	pub pairs: u64,
+	/// The chance of the plot to be rewarded for a block.
+	pub win_probability: f64,
+	/// The expected number of rewarded claims per day.
+	pub claims_per_day: f64,
+	/// The expected reward per day.
+	pub reward_per_day: u128,
+}
+
+/// Estimates the rewards of a plot of `pairs` matched pairs.
+pub fn estimate(params: &RewardParams, pairs: u64) -> RewardEstimate {
+	let miss = (pairs as f64 * (-(2f64.powi(-PAIR_SOLUTION_BITS))).ln_1p()).exp();
+	let win_probability = (1.0 - miss) * params.claim_share();
+	let claims_per_day = win_probability * params.blocks_per_day();
+
+	RewardEstimate {
+		pairs,
+		win_probability,
+		claims_per_day,
+		reward_per_day: (claims_per_day * params.reward_amount as f64) as u128,
+	}
+}
+
+/// A point of the capacity-vs-reward curve.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CurvePoint {
+	/// The number of pieces of the plot.
+	pub pieces: u64,
+	/// The expected rewards of the plot.
+	pub estimate: RewardEstimate,
+}
+
+/// Estimates the rewards of plots of each of `sizes` pieces, projected from a plot of
+/// `plot_pieces` pieces holding `plot_pairs` matched pairs.
+pub fn reward_curve(
+	params: &RewardParams,
+	plot_pieces: u64,
+	plot_pairs: u64,
+	sizes: impl IntoIterator<Item = u64>,
+) -> Vec<CurvePoint> {
+	sizes
+		.into_iter()
+		.map(|pieces| {
+			let pairs = if plot_pieces == 0 {
+				0
+			} else {
+				let scale = pieces as f64 / plot_pieces as f64;
+				(plot_pairs as f64 * scale * scale).round() as u64
+			};
+			CurvePoint { pieces, estimate: estimate(params, pairs) }
+		})
+		.collect()
+}
+
+impl<S> Client<S> {
+	/// Returns the reward parameters at the best block, the competition averaged over the claims
+	/// of the last `window` blocks.
+	pub async fn reward_params(&self, window: u32) -> Result<RewardParams> {
+		let constants = self.api.constants();
+		let reward_amount =
+			constants.at(&melodot::constants().farmers_fortune().reward_amount())?;
+		let max_claimants_per_block =
+			constants.at(&melodot::constants().farmers_fortune().max_claimants_per_block())?;
+		let block_time_ms = constants.at(&melodot::constants().babe().expected_block_time())?;
+
+		let block = self.api.blocks().at_latest().await?;
+		let storage = block.storage();
+		let first = block.number().saturating_sub(window.max(1)) + 1;
+		let mut claims = 0;
+		for number in first..=block.number() {
+			let claimants = storage
+				.fetch_or_default(&melodot::storage().farmers_fortune().claimants_for_block(number))
+				.await?;
+			claims += claimants.0.len();
+		}
+		let blocks = block.number() + 1 - first;
+
+		Ok(RewardParams {
+			reward_amount,
+			max_claimants_per_block,
+			block_time_ms,
+			claimants_per_block: claims as f64 / blocks.max(1) as f64,
+		})
+	}
+}
@@ -0,0 +1,155 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Signer backends for `Client`.
+//!
+//! Any type implementing [`subxt::tx::Signer`] for [`MeloConfig`] can sign transactions, so
+//! hardware wallets or custom key stores only need to implement that trait. This module provides
+//! the common backends: secret URIs (dev seeds, mnemonics, raw seeds), keystore files and remote
+//! signing services.
+
+use anyhow::{anyhow, Context, Result};
+use std::{path::PathBuf, str::FromStr};
+use subxt_signer::{sr25519::Keypair, SecretUri};
+#[cfg(feature = "native")]
+use {
+	crate::{AccountId, Address, MeloConfig, Signature},
+	jsonrpsee::{
+		core::client::ClientT,
+		rpc_params,
+		ws_client::{WsClient, WsClientBuilder},
+	},
+	log::error,
+	std::sync::Arc,
+	subxt::{
+		config::polkadot::PolkadotExtrinsicParamsBuilder,
+		tx::{Signer, SubmittableExtrinsic, TxPayload},
+		OnlineClient, PolkadotConfig,
+	},
+	tokio::runtime::{Handle, RuntimeFlavor},
+};
+
+/// Where the key of a local signer comes from.
+#[derive(Clone, Debug)]
+pub enum SignerSource {
+	/// A secret URI, e.g. `//Alice`, a mnemonic phrase or a hex encoded seed, optionally followed
+	/// by derivation junctions and `///password`.
+	Uri(String),
+	/// A substrate keystore file containing the secret URI as a JSON string.
+	Keystore(PathBuf),
+}
+
+impl SignerSource {
+	/// Loads the sr25519 keypair described by this source.
+	pub fn keypair(&self) -> Result<Keypair> {
+		let suri = match self {
+			Self::Uri(suri) => suri.clone(),
+			Self::Keystore(path) => {
+				let content = std::fs::read_to_string(path)
+					.with_context(|| format!("Failed to read keystore file {:?}", path))?;
+				serde_json::from_str::<String>(&content)
+					.with_context(|| format!("Invalid keystore file {:?}", path))?
+			},
+		};
+
+		let uri = SecretUri::from_str(&suri).map_err(|e| anyhow!("Invalid secret uri: {}", e))?;
+		Keypair::from_uri(&uri).map_err(|e| anyhow!("Failed to load keypair: {}", e))
+	}
+}
+
+/// A signer that delegates signing to a remote service over JSON-RPC.
+///
+/// The service must expose `signer_signPayload(account_id, payload)`, taking the SS58 account and
+/// the hex encoded payload and returning the hex encoded sr25519 signature.
+///
+/// [`RemoteSigner::create_signed`] signs a transaction asynchronously and reports the failures of
+/// the remote service. As a [`Signer`], signing blocks the current worker thread, so it requires a
+/// multi-threaded tokio runtime.
+#[cfg(feature = "native")]
+#[derive(Clone)]
+pub struct RemoteSigner {
+	client: Arc<WsClient>,
+	account_id: AccountId,
+}
+
+#[cfg(feature = "native")]
+impl RemoteSigner {
+	/// Connects to the remote signer at `url` for the given account.
+	pub async fn connect(url: &str, account_id: AccountId) -> Result<Self> {
+		let client = WsClientBuilder::default().build(url).await?;
+		Ok(Self { client: Arc::new(client), account_id })
+	}
+
+	/// Requests a signature of `payload` from the remote service.
+	pub async fn sign_payload(&self, payload: &[u8]) -> Result<Signature> {
+		let params =
+			rpc_params![self.account_id.to_string(), format!("0x{}", hex::encode(payload))];
+		let signature: String = self.client.request("signer_signPayload", params).await?;
+
+		let bytes = hex::decode(signature.trim_start_matches("0x"))?;
+		let signature: [u8; 64] =
+			bytes.try_into().map_err(|_| anyhow!("Invalid signature length"))?;
+		Ok(Signature::Sr25519(signature))
+	}
+
+	/// Creates a transaction signed by the remote service, without blocking the current thread.
+	///
+	/// # Arguments
+	///
+	/// * `api` - The client of the chain the transaction is for.
+	/// * `call` - The call of the transaction.
+	/// * `params` - The parameters of the signed extensions.
+	pub async fn create_signed<Call: TxPayload>(
+		&self,
+		api: &OnlineClient<MeloConfig>,
+		call: &Call,
+		params: PolkadotExtrinsicParamsBuilder<PolkadotConfig>,
+	) -> Result<SubmittableExtrinsic<MeloConfig, OnlineClient<MeloConfig>>> {
+		let partial = api.tx().create_partial_signed(call, &self.account_id, params).await?;
+		let signature = self.sign_payload(&partial.signer_payload()).await?;
+		Ok(partial.sign_with_address_and_signature(&self.address(), &signature))
+	}
+}
+
+#[cfg(feature = "native")]
+impl Signer<MeloConfig> for RemoteSigner {
+	fn account_id(&self) -> AccountId {
+		self.account_id.clone()
+	}
+
+	fn address(&self) -> Address {
+		self.account_id.clone().into()
+	}
+
+	/// Blocks the current worker thread until the remote service answers.
+	///
+	/// `Signer::sign` cannot fail, so if it is not called within a multi-threaded tokio runtime or
+	/// the remote service fails, the error is logged and an empty signature is returned, which the
+	/// node rejects as a bad proof.
+	fn sign(&self, signer_payload: &[u8]) -> Signature {
+		let handle = match Handle::try_current() {
+			Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => handle,
+			_ => {
+				error!("❌ The remote signer requires a multi-threaded tokio runtime");
+				return Signature::Sr25519([0u8; 64])
+			},
+		};
+
+		tokio::task::block_in_place(|| handle.block_on(self.sign_payload(signer_payload)))
+			.unwrap_or_else(|e| {
+				error!("❌ Remote signer failed to sign the payload: {:?}", e);
+				Signature::Sr25519([0u8; 64])
+			})
+	}
+}
@@ -0,0 +1,365 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Integration-test harness for data availability flows.
+//!
+//! [`TestNode`] starts a `melodot-node --dev --tmp --sealing manual` process and connects a
+//! [`Client`] to it, so that blocks are only sealed when [`TestNode::produce_block`] asks for
+//! them. The node listens on ports picked by the OS, read back from its output. The node is
+//! killed when the harness is dropped. Nodes of other chains are started with
+//! [`TestNodeBuilder::chain`] and author with BABE, see the [`crate::testnet`] module.
+//!
+//! ```ignore
+//! let node = TestNode::builder().spawn().await?;
+//! let (tx_hash, metadata) = node.submit_blob(1, &bytes).await?;
+//! let block = node.produce_block().await?;
+//! node.assert_blob_in_dht(&metadata).await?;
+//! ```
+
+use crate::{Client, ClientBuilder, ClientSync, MelodotHeader, TxManager, H256};
+use anyhow::{anyhow, ensure, Context, Result};
+use futures::StreamExt;
+use melo_core_primitives::SidecarMetadata;
+use std::{net::SocketAddr, path::PathBuf, process::Stdio, time::Duration};
+use subxt::rpc::rpc_params;
+use subxt_signer::sr25519::{dev, Keypair};
+use tokio::{
+	io::{AsyncBufReadExt, AsyncRead, BufReader},
+	process::{Child, Command},
+	sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+};
+
+/// Environment variable overriding the node binary used by the harness.
+pub const NODE_BIN_ENV: &str = "MELODOT_NODE_BIN";
+
+/// The node binary used if [`NODE_BIN_ENV`] is not set.
+pub const DEFAULT_NODE_BIN: &str = "target/release/melodot-node";
+
+/// The line a node logs once its RPC server is up, followed by the address of the server.
+const NODE_RPC_MARKER: &str = "Running JSON-RPC server: addr=";
+
+/// The line a node logs once its DAS network is listening, followed by the address.
+const NODE_DAS_MARKER: &str = "DAS network listening on ";
+
+/// Builder of a [`TestNode`].
+pub struct TestNodeBuilder {
+	binary: PathBuf,
+	signer: Keypair,
+	chain: Option<String>,
+	args: Vec<String>,
+	startup_timeout: Duration,
+}
+
+impl Default for TestNodeBuilder {
+	fn default() -> Self {
+		let binary = std::env::var(NODE_BIN_ENV).unwrap_or_else(|_| DEFAULT_NODE_BIN.to_string());
+		Self {
+			binary: binary.into(),
+			signer: dev::alice(),
+			chain: None,
+			args: Vec::new(),
+			startup_timeout: Duration::from_secs(60),
+		}
+	}
+}
+
+impl TestNodeBuilder {
+	/// Sets the node binary.
+	pub fn binary(mut self, binary: impl Into<PathBuf>) -> Self {
+		self.binary = binary.into();
+		self
+	}
+
+	/// Sets the signer of the connected client.
+	pub fn signer(mut self, signer: Keypair) -> Self {
+		self.signer = signer;
+		self
+	}
+
+	/// Runs the node on `chain`, such as `local`, instead of the development chain.
+	pub fn chain(mut self, chain: impl Into<String>) -> Self {
+		self.chain = Some(chain.into());
+		self
+	}
+
+	/// Appends extra command line arguments for the node.
+	pub fn arg(mut self, arg: impl Into<String>) -> Self {
+		self.args.push(arg.into());
+		self
+	}
+
+	/// Sets how long to wait for the node's RPC server and DAS network to come up.
+	pub fn startup_timeout(mut self, timeout: Duration) -> Self {
+		self.startup_timeout = timeout;
+		self
+	}
+
+	/// Starts the node and connects a client to it.
+	pub async fn spawn(self) -> Result<TestNode> {
+		let manual_seal = self.chain.is_none();
+		let chain = match &self.chain {
+			Some(chain) => vec!["--chain", chain.as_str()],
+			None => vec!["--dev", "--sealing", "manual"],
+		};
+		let mut command = Command::new(&self.binary);
+		command
+			.args(chain)
+			.args(["--tmp", "--no-telemetry", "--no-mdns", "--no-prometheus"])
+			.args(["--rpc-port", "0", "--port", "0", "--das-listen-port", "0"])
+			.args(&self.args);
+		let (process, mut output) = spawn_with_output(&mut command)
+			.with_context(|| format!("Failed to start {:?}", self.binary))?;
+
+		let (mut rpc_addr, mut das_port) = (None, None);
+		let (rpc_addr, das_port) = wait_for_output(&mut output, self.startup_timeout, |line| {
+			rpc_addr = rpc_addr.or_else(|| socket_addr_after(line, NODE_RPC_MARKER));
+			das_port = das_port
+				.or_else(|| line.split_once(NODE_DAS_MARKER).and_then(|(_, addr)| tcp_port(addr)));
+			rpc_addr.zip(das_port)
+		})
+		.await
+		.context("The RPC server or DAS network of the node did not come up")?;
+
+		let url = format!("ws://127.0.0.1:{}", rpc_addr.port());
+		let client = ClientBuilder::new(&url, self.signer).build().await?;
+
+		let listen_addrs: Vec<String> =
+			client.api.rpc().request("system_localListenAddresses", rpc_params![]).await?;
+		let p2p_port = listen_addrs
+			.iter()
+			.filter(|addr| addr.starts_with("/ip4/"))
+			.find_map(|addr| tcp_port(addr))
+			.ok_or_else(|| anyhow!("The node does not listen on IPv4: {:?}", listen_addrs))?;
+
+		Ok(TestNode {
+			process,
+			url,
+			p2p_port,
+			das_port,
+			manual_seal,
+			tx_manager: TxManager::new(client, Default::default()),
+		})
+	}
+}
+
+/// A running development node with a connected client.
+pub struct TestNode {
+	process: Child,
+	url: String,
+	p2p_port: u16,
+	das_port: u16,
+	manual_seal: bool,
+	tx_manager: TxManager<Keypair>,
+}
+
+impl TestNode {
+	/// Returns a builder with the default settings.
+	pub fn builder() -> TestNodeBuilder {
+		TestNodeBuilder::default()
+	}
+
+	/// The websocket endpoint of the node.
+	pub fn url(&self) -> &str {
+		&self.url
+	}
+
+	/// The port of the Substrate network of the node.
+	pub fn p2p_port(&self) -> u16 {
+		self.p2p_port
+	}
+
+	/// The port of the DAS network of the node.
+	pub fn das_port(&self) -> u16 {
+		self.das_port
+	}
+
+	/// Kills the node process and waits for it to exit.
+	pub async fn kill(&mut self) -> Result<()> {
+		self.process.kill().await.context("Failed to kill the node")
+	}
+
+	/// Returns whether the node process is still running.
+	pub fn is_running(&mut self) -> bool {
+		matches!(self.process.try_wait(), Ok(None))
+	}
+
+	/// The client connected to the node.
+	pub fn client(&self) -> &Client {
+		self.tx_manager.client()
+	}
+
+	/// The transaction manager of the harness' signer.
+	pub fn tx_manager(&self) -> &TxManager<Keypair> {
+		&self.tx_manager
+	}
+
+	/// Seals a new block and returns its header.
+	///
+	/// The development chain is sealed on demand, with the transactions ready in the pool, and
+	/// the block is finalized right away. Nodes of other chains author with BABE, so this waits
+	/// for the next best block instead.
+	pub async fn produce_block(&self) -> Result<MelodotHeader> {
+		self.produce_blocks(1).await
+	}
+
+	/// Seals `n` new blocks, or waits for them on chains authored with BABE, and returns the
+	/// header of the last one.
+	pub async fn produce_blocks(&self, n: usize) -> Result<MelodotHeader> {
+		ensure!(n > 0, "At least one block must be produced");
+
+		if self.manual_seal {
+			let mut last = None;
+			for _ in 0..n {
+				last = Some(self.seal_block().await?);
+			}
+			return Ok(last.expect("n > 0; qed"))
+		}
+
+		let mut sub = self.client().api.rpc().subscribe_best_block_headers().await?;
+		let mut last = None;
+		for _ in 0..n {
+			let header = sub.next().await.ok_or_else(|| anyhow!("Subscription closed"))??;
+			last = Some(header);
+		}
+		Ok(last.expect("n > 0; qed"))
+	}
+
+	/// Seals and finalizes a block through `engine_createBlock`.
+	async fn seal_block(&self) -> Result<MelodotHeader> {
+		let created: serde_json::Value = self
+			.client()
+			.api
+			.rpc()
+			.request("engine_createBlock", rpc_params![true, true, Option::<H256>::None])
+			.await?;
+		let hash: H256 = serde_json::from_value(created["hash"].clone())
+			.context("Invalid response of engine_createBlock")?;
+
+		self.client()
+			.api
+			.rpc()
+			.header(Some(hash))
+			.await?
+			.ok_or_else(|| anyhow!("Sealed block {:?} not found", hash))
+	}
+
+	/// Commits to `bytes` and submits them as a blob of `app_id`.
+	///
+	/// Returns the transaction hash and the metadata of the blob.
+	pub async fn submit_blob(&self, app_id: u32, bytes: &[u8]) -> Result<(H256, SidecarMetadata)> {
+		let nonce = self.client().nonce(app_id).await?;
+		let metadata =
+			SidecarMetadata::try_from_app_data(bytes, app_id, nonce + 1).map_err(|e| anyhow!(e))?;
+		let tx_hash = self.tx_manager.submit_blob(bytes, &metadata).await?;
+		Ok((tx_hash, metadata))
+	}
+
+	/// Fetches the blob of `metadata` from the DHT through `das_getBlob`.
+	///
+	/// Returns the unpadded bytes, or `None` if the node could not retrieve them.
+	pub async fn dht_blob(&self, metadata: &SidecarMetadata) -> Result<Option<Vec<u8>>> {
+		let params = rpc_params![metadata.app_id, metadata.nonce, metadata.commitments.clone()];
+		let maybe_hex: Option<String> =
+			self.client().api.rpc().request("das_getBlob", params).await?;
+
+		maybe_hex
+			.map(|hex_bytes| {
+				let mut bytes = hex::decode(hex_bytes.trim_start_matches("0x"))?;
+				bytes.truncate(metadata.bytes_len as usize);
+				Ok::<_, anyhow::Error>(bytes)
+			})
+			.transpose()
+	}
+
+	/// Asserts that the blob of `metadata` is retrievable from the DHT and verifies against its
+	/// commitments.
+	pub async fn assert_blob_in_dht(&self, metadata: &SidecarMetadata) -> Result<()> {
+		let bytes = self.dht_blob(metadata).await?.ok_or_else(|| anyhow!("Blob not in DHT"))?;
+		ensure!(
+			metadata.verify_bytes(&bytes).map_err(|e| anyhow!(e))?,
+			"Blob in DHT does not match its commitments"
+		);
+		Ok(())
+	}
+
+	/// Asserts that the blob of `metadata` is not retrievable from the DHT.
+	pub async fn assert_blob_not_in_dht(&self, metadata: &SidecarMetadata) -> Result<()> {
+		ensure!(self.dht_blob(metadata).await?.is_none(), "Blob unexpectedly found in DHT");
+		Ok(())
+	}
+}
+
+/// Spawns `command`, killed on drop, and returns the lines of its standard output and error.
+///
+/// The output keeps being read in the background once the receiver is dropped, so that the
+/// process never blocks on a full pipe.
+pub(crate) fn spawn_with_output(
+	command: &mut Command,
+) -> Result<(Child, UnboundedReceiver<String>)> {
+	let mut process = command
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.kill_on_drop(true)
+		.spawn()?;
+
+	let (tx, rx) = unbounded_channel();
+	if let Some(stdout) = process.stdout.take() {
+		forward(stdout, tx.clone());
+	}
+	if let Some(stderr) = process.stderr.take() {
+		forward(stderr, tx);
+	}
+
+	Ok((process, rx))
+}
+
+/// Forwards the lines read from `pipe` to `tx`, until the pipe is closed.
+fn forward(pipe: impl AsyncRead + Unpin + Send + 'static, tx: UnboundedSender<String>) {
+	tokio::spawn(async move {
+		let mut lines = BufReader::new(pipe).lines();
+		while let Ok(Some(line)) = lines.next_line().await {
+			let _ = tx.send(line);
+		}
+	});
+}
+
+/// Reads `output` until `found` returns a value, for at most `timeout`.
+pub(crate) async fn wait_for_output<T>(
+	output: &mut UnboundedReceiver<String>,
+	timeout: Duration,
+	mut found: impl FnMut(&str) -> Option<T>,
+) -> Result<T> {
+	tokio::time::timeout(timeout, async {
+		while let Some(line) = output.recv().await {
+			if let Some(value) = found(&line) {
+				return Ok(value)
+			}
+		}
+		Err(anyhow!("The process exited"))
+	})
+	.await
+	.map_err(|_| anyhow!("Not found in the output within {:?}", timeout))?
+}
+
+/// Parses the socket address following `marker` in a line of output.
+pub(crate) fn socket_addr_after(line: &str, marker: &str) -> Option<SocketAddr> {
+	let (_, rest) = line.split_once(marker)?;
+	rest.split(|c: char| c.is_whitespace() || c == ',').next()?.parse().ok()
+}
+
+/// Parses the TCP port of a multiaddress such as `/ip4/127.0.0.1/tcp/30333/p2p/...`.
+fn tcp_port(multiaddr: &str) -> Option<u16> {
+	let (_, rest) = multiaddr.split_once("/tcp/")?;
+	rest.split('/').next()?.parse().ok()
+}
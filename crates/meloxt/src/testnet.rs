@@ -0,0 +1,329 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Local multi-node testnets for end-to-end scenarios.
+//!
+//! [`spawn`] starts a network of the `local` chain on ports picked by the OS: validators with the
+//! keys of the development accounts, the first three of them authoring blocks, farmers plotting
+//! with the development accounts and light clients sampling through the first validator. The
+//! validators bootstrap from the first one, and the DAS nodes find each other with mDNS. Every
+//! process is killed when the [`Testnet`] is dropped.
+//!
+//! ```ignore
+//! let mut net = testnet::spawn(3, 1, 2).await?;
+//! let (_, metadata) = net.submit_blob(1, &bytes).await?;
+//! let header = net.validator(0)?.produce_blocks(2).await?;
+//! net.kill_validator(2).await?;
+//! net.assert_confidence(header.hash(), 1).await?;
+//! ```
+
+use crate::{
+	test_harness::{socket_addr_after, spawn_with_output, wait_for_output, TestNode},
+	SignerSource, H256,
+};
+use anyhow::{anyhow, ensure, Context, Result};
+use jsonrpsee::{core::client::ClientT, rpc_params, ws_client::WsClientBuilder};
+use melo_core_primitives::SidecarMetadata;
+use std::{path::PathBuf, time::Duration};
+use tokio::process::{Child, Command};
+
+/// Environment variable overriding the farmer binary of the testnets.
+pub const FARMER_BIN_ENV: &str = "MELODOT_FARMER_BIN";
+
+/// The farmer binary used if [`FARMER_BIN_ENV`] is not set.
+pub const DEFAULT_FARMER_BIN: &str = "target/release/melodot-farmer";
+
+/// Environment variable overriding the light client binary of the testnets.
+pub const LIGHT_BIN_ENV: &str = "MELODOT_LIGHT_BIN";
+
+/// The light client binary used if [`LIGHT_BIN_ENV`] is not set.
+pub const DEFAULT_LIGHT_BIN: &str = "target/release/melodot-light";
+
+/// The names and secret URIs of the development accounts, endowed on the `local` chain, whose
+/// keys the validators and farmers run with.
+const DEV_ACCOUNTS: [(&str, &str); 6] = [
+	("alice", "//Alice"),
+	("bob", "//Bob"),
+	("charlie", "//Charlie"),
+	("dave", "//Dave"),
+	("eve", "//Eve"),
+	("ferdie", "//Ferdie"),
+];
+
+/// The node key of the first validator, which the other validators bootstrap from.
+const BOOTNODE_KEY: &str = "0000000000000000000000000000000000000000000000000000000000000001";
+
+/// The peer ID of [`BOOTNODE_KEY`].
+const BOOTNODE_PEER_ID: &str = "12D3KooWEyoppNCUx8Yx66oV9fJnriXwCcXwDDUA2kj6vnc6iDEp";
+
+/// How long to wait for the RPC server of a farmer or light client to come up.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The line a farmer logs once its RPC server is up, followed by the address of the server.
+const FARMER_RPC_MARKER: &str = "Dashboard RPC listening on ";
+
+/// The line a light client logs once its RPC server is up, followed by the address of the server.
+const LIGHT_RPC_MARKER: &str = "RPC server started at: ";
+
+/// A farmer or light client of a testnet, running in its own directory.
+pub struct TestProcess {
+	process: Child,
+	rpc_url: String,
+	dir: PathBuf,
+}
+
+impl TestProcess {
+	/// Starts `binary` with `args` in a new directory named after `name`, and waits for it to log
+	/// the address of its RPC server after `rpc_marker`.
+	async fn spawn(
+		binary: PathBuf,
+		name: &str,
+		args: Vec<String>,
+		rpc_marker: &str,
+	) -> Result<Self> {
+		let dir =
+			std::env::temp_dir().join(format!("melodot-testnet-{}-{}", std::process::id(), name));
+		std::fs::create_dir_all(&dir)?;
+		Self::spawn_in(binary, dir, args, rpc_marker).await
+	}
+
+	async fn spawn_in(
+		binary: PathBuf,
+		dir: PathBuf,
+		args: Vec<String>,
+		rpc_marker: &str,
+	) -> Result<Self> {
+		let (process, mut output) =
+			spawn_with_output(Command::new(&binary).args(&args).current_dir(&dir))
+				.with_context(|| format!("Failed to start {:?}", binary))?;
+
+		let rpc_addr = wait_for_output(&mut output, STARTUP_TIMEOUT, |line| {
+			socket_addr_after(line, rpc_marker)
+		})
+		.await
+		.with_context(|| format!("The RPC server of {:?} did not come up", binary))?;
+
+		Ok(Self { process, rpc_url: format!("ws://127.0.0.1:{}", rpc_addr.port()), dir })
+	}
+
+	/// The websocket endpoint of the RPC server of the process.
+	pub fn rpc_url(&self) -> &str {
+		&self.rpc_url
+	}
+
+	/// Kills the process and waits for it to exit.
+	pub async fn kill(&mut self) -> Result<()> {
+		self.process.kill().await.context("Failed to kill the process")
+	}
+
+	/// Returns whether the process is still running.
+	pub fn is_running(&mut self) -> bool {
+		matches!(self.process.try_wait(), Ok(None))
+	}
+}
+
+impl Drop for TestProcess {
+	fn drop(&mut self) {
+		let _ = self.process.start_kill();
+		let _ = std::fs::remove_dir_all(&self.dir);
+	}
+}
+
+/// A running local network.
+pub struct Testnet {
+	validators: Vec<TestNode>,
+	farmers: Vec<TestProcess>,
+	light_clients: Vec<TestProcess>,
+}
+
+/// Starts a local network of `n_validators` validators, `m_farmers` farmers and `k_light_clients`
+/// light clients, with DAS enabled on every node.
+///
+/// The binaries are read from [`crate::test_harness::NODE_BIN_ENV`], [`FARMER_BIN_ENV`] and
+/// [`LIGHT_BIN_ENV`]. There are at most six validators and six farmers, one for each development
+/// account.
+pub async fn spawn(
+	n_validators: usize,
+	m_farmers: usize,
+	k_light_clients: usize,
+) -> Result<Testnet> {
+	ensure!(
+		(1..=DEV_ACCOUNTS.len()).contains(&n_validators),
+		"A testnet runs 1 to {} validators",
+		DEV_ACCOUNTS.len()
+	);
+	ensure!(
+		m_farmers <= DEV_ACCOUNTS.len(),
+		"A testnet runs at most {} farmers",
+		DEV_ACCOUNTS.len()
+	);
+
+	let mut validators: Vec<TestNode> = Vec::with_capacity(n_validators);
+	for (name, uri) in &DEV_ACCOUNTS[..n_validators] {
+		let builder = TestNode::builder()
+			.chain("local")
+			.signer(SignerSource::Uri(uri.to_string()).keypair()?)
+			.arg(format!("--{}", name));
+		let builder = match validators.first() {
+			Some(bootnode) => builder.arg("--bootnodes").arg(format!(
+				"/ip4/127.0.0.1/tcp/{}/p2p/{}",
+				bootnode.p2p_port(),
+				BOOTNODE_PEER_ID
+			)),
+			None => builder.arg("--node-key").arg(BOOTNODE_KEY),
+		};
+		validators.push(builder.spawn().await?);
+	}
+	let node_url = validators[0].url().to_string();
+
+	let farmer_bin = binary(FARMER_BIN_ENV, DEFAULT_FARMER_BIN);
+	let mut farmers = Vec::with_capacity(m_farmers);
+	for (name, uri) in &DEV_ACCOUNTS[..m_farmers] {
+		let dir = std::env::temp_dir().join(format!(
+			"melodot-testnet-{}-farmer-{}",
+			std::process::id(),
+			name
+		));
+		std::fs::create_dir_all(&dir)?;
+
+		let config = format!(
+			"node_url = \"{}\"\n\
+			 plots_dir = \"plots\"\n\
+			 suri = \"{}\"\n\
+			 rpc_listen_addr = \"127.0.0.1:0\"\n\
+			 prometheus_addr = \"127.0.0.1:0\"\n\
+			 das_listen_port = 0\n",
+			node_url, uri,
+		);
+		std::fs::write(dir.join("farmer.toml"), config)?;
+
+		let args = vec!["--config".to_string(), "farmer.toml".to_string()];
+		farmers
+			.push(TestProcess::spawn_in(farmer_bin.clone(), dir, args, FARMER_RPC_MARKER).await?);
+	}
+
+	let light_bin = binary(LIGHT_BIN_ENV, DEFAULT_LIGHT_BIN);
+	let mut light_clients = Vec::with_capacity(k_light_clients);
+	for i in 0..k_light_clients {
+		let args = vec![
+			"--rpc-remote-url".to_string(),
+			node_url.clone(),
+			"--rpc-listen-addr".to_string(),
+			"127.0.0.1:0".to_string(),
+			"--das-net-listen-port".to_string(),
+			"0".to_string(),
+		];
+		let name = format!("light-{}", i);
+		light_clients
+			.push(TestProcess::spawn(light_bin.clone(), &name, args, LIGHT_RPC_MARKER).await?);
+	}
+
+	Ok(Testnet { validators, farmers, light_clients })
+}
+
+impl Testnet {
+	/// The validators, in the order of the development accounts.
+	pub fn validators(&self) -> &[TestNode] {
+		&self.validators
+	}
+
+	/// The `i`-th validator.
+	pub fn validator(&self, i: usize) -> Result<&TestNode> {
+		self.validators.get(i).ok_or_else(|| anyhow!("No validator {}", i))
+	}
+
+	/// The `i`-th farmer.
+	pub fn farmer(&self, i: usize) -> Result<&TestProcess> {
+		self.farmers.get(i).ok_or_else(|| anyhow!("No farmer {}", i))
+	}
+
+	/// The `i`-th light client.
+	pub fn light_client(&self, i: usize) -> Result<&TestProcess> {
+		self.light_clients.get(i).ok_or_else(|| anyhow!("No light client {}", i))
+	}
+
+	/// Kills the `i`-th validator.
+	pub async fn kill_validator(&mut self, i: usize) -> Result<()> {
+		self.validators
+			.get_mut(i)
+			.ok_or_else(|| anyhow!("No validator {}", i))?
+			.kill()
+			.await
+	}
+
+	/// Kills the `i`-th farmer.
+	pub async fn kill_farmer(&mut self, i: usize) -> Result<()> {
+		self.farmers.get_mut(i).ok_or_else(|| anyhow!("No farmer {}", i))?.kill().await
+	}
+
+	/// Kills the `i`-th light client.
+	pub async fn kill_light_client(&mut self, i: usize) -> Result<()> {
+		self.light_clients
+			.get_mut(i)
+			.ok_or_else(|| anyhow!("No light client {}", i))?
+			.kill()
+			.await
+	}
+
+	/// Submits `bytes` as a blob of `app_id` through the first running validator.
+	///
+	/// Returns the transaction hash and the metadata of the blob.
+	pub async fn submit_blob(
+		&mut self,
+		app_id: u32,
+		bytes: &[u8],
+	) -> Result<(H256, SidecarMetadata)> {
+		let validator = self
+			.validators
+			.iter_mut()
+			.find(|validator| validator.is_running())
+			.ok_or_else(|| anyhow!("No validator running"))?;
+		validator.submit_blob(app_id, bytes).await
+	}
+
+	/// Asserts that every running validator and light client has sampled the block `block_hash`
+	/// with a confidence of at least `min`, as returned by `das_blockConfidence`.
+	pub async fn assert_confidence(&mut self, block_hash: H256, min: u32) -> Result<()> {
+		let mut urls = Vec::new();
+		for validator in self.validators.iter_mut().filter(|validator| validator.is_running()) {
+			urls.push(validator.url().to_string());
+		}
+		for light_client in self.light_clients.iter_mut().filter(|client| client.is_running()) {
+			urls.push(light_client.rpc_url().to_string());
+		}
+
+		for url in urls {
+			let confidence = confidence(&url, block_hash).await?;
+			ensure!(
+				confidence.map_or(false, |confidence| confidence >= min),
+				"Confidence of {:?} at {} is {:?}, below {}",
+				block_hash,
+				url,
+				confidence,
+				min
+			);
+		}
+		Ok(())
+	}
+}
+
+/// Returns the confidence in the block `block_hash` of the DAS node serving RPC at `url`.
+pub async fn confidence(url: &str, block_hash: H256) -> Result<Option<u32>> {
+	let client = WsClientBuilder::default().build(url).await?;
+	Ok(client.request("das_blockConfidence", rpc_params![block_hash]).await?)
+}
+
+fn binary(env: &str, default: &str) -> PathBuf {
+	std::env::var(env).unwrap_or_else(|_| default.to_string()).into()
+}
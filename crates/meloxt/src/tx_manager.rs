@@ -0,0 +1,315 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Transaction submission with nonce tracking and retries.
+//!
+//! A [`TxManager`] hands out account nonces locally, so that concurrent submissions from the same
+//! account do not race on the nonce read from chain. Submissions rejected by the transaction pool
+//! are classified into a [`TxError`] and retried where a retry can succeed: stale or future nonces
+//! resynchronize the nonce from chain, and a too low priority bumps the tip.
+
+use crate::{melodot, sidecar_metadata_to_runtime, AccountId, Client, MeloConfig, H256};
+use log::{debug, warn};
+use melo_core_primitives::SidecarMetadata;
+use melo_core_primitives::{BlobTxError, BlobTxSatus, PoolRejection};
+use subxt::{
+	config::{
+		polkadot::{PlainTip, PolkadotExtrinsicParamsBuilder},
+		substrate::BlakeTwo256,
+		Hasher,
+	},
+	rpc::rpc_params,
+	tx::{Signer, SubmittableExtrinsic, TxPayload},
+	OnlineClient,
+};
+use tokio::sync::Mutex;
+
+/// Errors of a managed transaction submission.
+#[derive(Debug, thiserror::Error)]
+pub enum TxError {
+	/// The nonce was already used by an included transaction.
+	#[error("Transaction nonce is stale")]
+	StaleNonce,
+	/// The nonce is ahead of the account's next nonce.
+	#[error("Transaction nonce is in the future")]
+	FutureNonce,
+	/// A transaction with the same nonce and a higher priority is already in the pool.
+	#[error("Transaction priority is too low")]
+	PriorityTooLow,
+	/// The transaction was rejected by the pool for any other reason.
+	#[error("Invalid transaction: {0}")]
+	Invalid(String),
+	/// The node could not be reached or returned an unexpected response.
+	#[error("Rpc error: {0}")]
+	Rpc(#[from] subxt::Error),
+	/// All attempts failed, the last error is attached.
+	#[error("Transaction failed after {attempts} attempts: {last}")]
+	RetriesExhausted { attempts: u32, last: Box<TxError> },
+}
+
+impl TxError {
+	/// Classifies an error message returned by the transaction pool.
+	pub fn from_pool_message(msg: &str) -> Self {
+		let lower = msg.to_lowercase();
+		if lower.contains("outdated") || lower.contains("stale") {
+			TxError::StaleNonce
+		} else if lower.contains("future") {
+			TxError::FutureNonce
+		} else if lower.contains("priority is too low") {
+			TxError::PriorityTooLow
+		} else {
+			TxError::Invalid(msg.to_string())
+		}
+	}
+
+	/// Converts the reason reported by `das_submitBlobTx` for a rejected transaction.
+	pub fn from_pool_rejection(reason: PoolRejection, message: String) -> Self {
+		match reason {
+			PoolRejection::StaleNonce => TxError::StaleNonce,
+			PoolRejection::FutureNonce => TxError::FutureNonce,
+			PoolRejection::PriorityTooLow => TxError::PriorityTooLow,
+			PoolRejection::AlreadyImported | PoolRejection::Invalid => TxError::Invalid(message),
+		}
+	}
+
+	fn from_subxt(err: subxt::Error) -> Self {
+		if let subxt::Error::Rpc(e) = &err {
+			let classified = Self::from_pool_message(&e.to_string());
+			if classified.is_retriable() {
+				return classified
+			}
+		}
+		TxError::Rpc(err)
+	}
+
+	/// Returns `true` if submitting again can succeed.
+	pub fn is_retriable(&self) -> bool {
+		matches!(self, TxError::StaleNonce | TxError::FutureNonce | TxError::PriorityTooLow)
+	}
+}
+
+/// Retry policy of a [`TxManager`].
+#[derive(Clone, Debug)]
+pub struct RetryConfig {
+	/// Maximum number of attempts per transaction.
+	pub max_attempts: u32,
+	/// The tip of the first attempt.
+	pub initial_tip: u128,
+	/// The amount added to the tip after a rejection for low priority.
+	pub tip_increment: u128,
+}
+
+impl Default for RetryConfig {
+	fn default() -> Self {
+		Self { max_attempts: 5, initial_tip: 0, tip_increment: 1_000_000 }
+	}
+}
+
+/// Submits transactions of a single account, managing nonces and retries.
+pub struct TxManager<S> {
+	client: Client<S>,
+	config: RetryConfig,
+	/// The next nonce to use, `None` if it must be read from chain.
+	next_nonce: Mutex<Option<u64>>,
+}
+
+impl<S> TxManager<S>
+where
+	S: Signer<MeloConfig> + Send + Sync,
+{
+	/// Creates a new transaction manager.
+	pub fn new(client: Client<S>, config: RetryConfig) -> Self {
+		Self { client, config, next_nonce: Mutex::new(None) }
+	}
+
+	/// Returns the underlying client.
+	pub fn client(&self) -> &Client<S> {
+		&self.client
+	}
+
+	fn account_id(&self) -> AccountId {
+		self.client.signer.account_id()
+	}
+
+	/// Reserves the next nonce of the account.
+	async fn reserve_nonce(&self) -> Result<u64, TxError> {
+		let mut next_nonce = self.next_nonce.lock().await;
+		let nonce = match *next_nonce {
+			Some(nonce) => nonce,
+			None => self.client.api.rpc().system_account_next_index(&self.account_id()).await?,
+		};
+		*next_nonce = Some(nonce + 1);
+		Ok(nonce)
+	}
+
+	/// Forgets the local nonce, so that the next reservation reads it from chain.
+	async fn resync_nonce(&self) {
+		*self.next_nonce.lock().await = None;
+	}
+
+	async fn sign<Call: TxPayload>(
+		&self,
+		call: &Call,
+		nonce: u64,
+		tip: u128,
+	) -> Result<SubmittableExtrinsic<MeloConfig, OnlineClient<MeloConfig>>, TxError> {
+		let params = PolkadotExtrinsicParamsBuilder::new().tip(PlainTip::new(tip));
+		Ok(self.client.api.tx().create_signed_with_nonce(
+			call,
+			&self.client.signer,
+			nonce,
+			params,
+		)?)
+	}
+
+	/// Runs `attempt` until it succeeds, fails with a non retriable error or runs out of attempts.
+	async fn with_retries<T, F, Fut>(&self, mut attempt: F) -> Result<T, TxError>
+	where
+		F: FnMut(u64, u128) -> Fut,
+		Fut: std::future::Future<Output = Result<T, TxError>>,
+	{
+		let mut tip = self.config.initial_tip;
+		let mut attempts = 0;
+
+		loop {
+			attempts += 1;
+			let nonce = self.reserve_nonce().await?;
+
+			let err = match attempt(nonce, tip).await {
+				Ok(res) => return Ok(res),
+				Err(err) => err,
+			};
+
+			// The reserved nonce may not have been used, start over from chain state.
+			self.resync_nonce().await;
+
+			if !err.is_retriable() {
+				return Err(err)
+			}
+			if attempts >= self.config.max_attempts {
+				return Err(TxError::RetriesExhausted { attempts, last: Box::new(err) })
+			}
+
+			if matches!(err, TxError::PriorityTooLow) {
+				tip = tip.saturating_add(self.config.tip_increment);
+			}
+			warn!("Transaction rejected: {}, retrying with nonce from chain and tip {}", err, tip);
+		}
+	}
+
+	/// Signs and submits `call`, returning the transaction hash.
+	pub async fn submit<Call: TxPayload>(&self, call: &Call) -> Result<H256, TxError> {
+		self.with_retries(|nonce, tip| async move {
+			let extrinsic = self.sign(call, nonce, tip).await?;
+			extrinsic.submit().await.map_err(TxError::from_subxt)
+		})
+		.await
+	}
+
+	/// Submits a blob transaction through `das_submitBlobTx`, returning the transaction hash.
+	///
+	/// Each signed extrinsic is submitted with the hash of its bytes as request ID, and resent once
+	/// with the same ID when the request fails, so that the node does not submit it twice.
+	pub async fn submit_blob(
+		&self,
+		bytes: &[u8],
+		metadata: &SidecarMetadata,
+	) -> Result<H256, TxError> {
+		let call = melodot::tx().melo_store().submit_data(sidecar_metadata_to_runtime(metadata));
+		let hex_bytes = format!("0x{}", hex::encode(bytes));
+
+		self.with_retries(|nonce, tip| {
+			let call = &call;
+			let hex_bytes = hex_bytes.clone();
+			async move {
+				let extrinsic = self.sign(call, nonce, tip).await?;
+				let hex_extrinsic = format!("0x{}", hex::encode(extrinsic.encoded()));
+
+				let request_id =
+					format!("0x{}", hex::encode(BlakeTwo256::hash(extrinsic.encoded())));
+
+				let request = || {
+					let params =
+						rpc_params![hex_bytes.clone(), hex_extrinsic.clone(), request_id.clone()];
+					self.client.api.rpc().request::<BlobTxSatus<H256>>("das_submitBlobTx", params)
+				};
+				let res = match request().await.map_err(TxError::from_subxt) {
+					Err(TxError::Rpc(e)) => {
+						warn!("Blob transaction request failed: {}, resending it", e);
+						request().await.map_err(TxError::from_subxt)?
+					},
+					res => res?,
+				};
+				debug!("Blob transaction submitted: {:?}", res);
+
+				match res.err {
+					None => Ok(res.tx_hash),
+					Some(BlobTxError::DhtPutFailed { message }) |
+					Some(BlobTxError::DhtDegraded { message }) => {
+						warn!(
+							"Blob transaction submitted, but its data was not published: {}",
+							message
+						);
+						Ok(res.tx_hash)
+					},
+					Some(BlobTxError::PoolRejected { reason, message }) =>
+						Err(TxError::from_pool_rejection(reason, message)),
+				}
+			}
+		})
+		.await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_from_pool_message() {
+		let cases = [
+			("1010: Invalid Transaction: Transaction is outdated", "StaleNonce"),
+			("Transaction nonce is stale", "StaleNonce"),
+			("1010: Invalid Transaction: Transaction will be valid in the future", "FutureNonce"),
+			("1014: Priority is too low: (0 vs 0)", "PriorityTooLow"),
+			("1012: Transaction is temporarily banned", "Invalid"),
+			("1013: Transaction Already Imported", "Invalid"),
+			("1010: Invalid Transaction: Inability to pay some fees", "Invalid"),
+		];
+
+		for (msg, expected) in cases {
+			let classified = match TxError::from_pool_message(msg) {
+				TxError::StaleNonce => "StaleNonce",
+				TxError::FutureNonce => "FutureNonce",
+				TxError::PriorityTooLow => "PriorityTooLow",
+				TxError::Invalid(invalid) => {
+					assert_eq!(invalid, msg);
+					"Invalid"
+				},
+				other => panic!("unexpected classification of {:?}: {:?}", msg, other),
+			};
+			assert_eq!(classified, expected, "{}", msg);
+		}
+	}
+
+	#[test]
+	fn test_only_nonce_and_priority_errors_are_retriable() {
+		assert!(TxError::from_pool_message("Transaction is outdated").is_retriable());
+		assert!(
+			TxError::from_pool_message("Transaction will be valid in the future").is_retriable()
+		);
+		assert!(TxError::from_pool_message("Priority is too low: (1 vs 1)").is_retriable());
+		assert!(!TxError::from_pool_message("Transaction Already Imported").is_retriable());
+	}
+}
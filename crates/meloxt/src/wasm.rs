@@ -0,0 +1,111 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! JavaScript bindings for browser dApps, built with the `web` feature.
+//!
+//! Byte arrays are exchanged as `Uint8Array`, hashes as `0x` prefixed hex strings and structured
+//! values (metadata, segments) in their SCALE encoding.
+
+use crate::{ClientBuilder, ClientSync, SignerSource, TxManager, H256};
+use codec::Decode;
+use js_sys::Promise;
+use melo_core_primitives::{config::SEGMENTS_PER_BLOB, SidecarMetadata};
+use melo_das_primitives::{KZGCommitment, Segment, KZG};
+use std::{rc::Rc, str::FromStr};
+use subxt_signer::sr25519::Keypair;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::future_to_promise;
+
+fn js_error(err: impl std::fmt::Display) -> JsValue {
+	JsError::new(&err.to_string()).into()
+}
+
+fn parse_hash(hash: &str) -> Result<H256, JsValue> {
+	H256::from_str(hash).map_err(js_error)
+}
+
+/// A Melodot client usable from JavaScript.
+#[wasm_bindgen]
+pub struct WebClient {
+	manager: Rc<TxManager<Keypair>>,
+}
+
+#[wasm_bindgen]
+impl WebClient {
+	/// Connects to the node at `url`, signing with the given secret URI.
+	///
+	/// Resolves to a `WebClient`.
+	pub fn connect(url: String, suri: String) -> Promise {
+		future_to_promise(async move {
+			let signer = SignerSource::Uri(suri).keypair().map_err(js_error)?;
+			let client = ClientBuilder::new(&url, signer).build().await.map_err(js_error)?;
+			let manager = TxManager::new(client, Default::default());
+			Ok(WebClient { manager: Rc::new(manager) }.into())
+		})
+	}
+
+	/// Commits to `data` and submits it as a blob of application `app_id`.
+	///
+	/// Resolves to the transaction hash.
+	#[wasm_bindgen(js_name = submitBlob)]
+	pub fn submit_blob(&self, app_id: u32, data: Vec<u8>) -> Promise {
+		let manager = self.manager.clone();
+		future_to_promise(async move {
+			let nonce = manager.client().nonce(app_id).await.map_err(js_error)?;
+			let metadata =
+				SidecarMetadata::try_from_app_data(&data, app_id, nonce + 1).map_err(js_error)?;
+			let tx_hash = manager.submit_blob(&data, &metadata).await.map_err(js_error)?;
+			Ok(JsValue::from_str(&format!("{:?}", tx_hash)))
+		})
+	}
+
+	/// Retrieves and verifies the blob `data_hash` submitted in block `block_hash`.
+	///
+	/// Resolves to the original bytes, or `undefined` if the blob is not available.
+	#[wasm_bindgen(js_name = getBlob)]
+	pub fn get_blob(&self, block_hash: String, data_hash: String) -> Promise {
+		let manager = self.manager.clone();
+		future_to_promise(async move {
+			let block_hash = parse_hash(&block_hash)?;
+			let data_hash = parse_hash(&data_hash)?;
+			let blob = manager.client().get_blob(block_hash, data_hash).await.map_err(js_error)?;
+			Ok(blob
+				.map(|bytes| js_sys::Uint8Array::from(&bytes[..]).into())
+				.unwrap_or_default())
+		})
+	}
+}
+
+/// Verifies `data` against its SCALE encoded `SidecarMetadata`.
+#[wasm_bindgen(js_name = verifyBlob)]
+pub fn verify_blob(data: &[u8], metadata: &[u8]) -> Result<bool, JsValue> {
+	let metadata = SidecarMetadata::decode(&mut &metadata[..]).map_err(js_error)?;
+	if metadata.bytes_len as usize != data.len() {
+		return Ok(false)
+	}
+	metadata.verify_bytes(data).map_err(js_error)
+}
+
+/// Verifies a SCALE encoded sampled `Segment` against the commitment of its row.
+#[wasm_bindgen(js_name = verifySample)]
+pub fn verify_sample(segment: &[u8], commitment: &[u8]) -> Result<bool, JsValue> {
+	let segment = Segment::decode(&mut &segment[..]).map_err(js_error)?;
+	let commitment = KZGCommitment::decode(&mut &commitment[..]).map_err(js_error)?;
+	let kzg = KZG::default_embedded();
+	segment
+		.checked()
+		.map_err(js_error)?
+		.verify(&kzg, &commitment, SEGMENTS_PER_BLOB)
+		.map_err(js_error)
+}
@@ -0,0 +1,104 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+#[allow(unused_imports)]
+use crate::Pallet as BandwidthIncentive;
+use frame_benchmarking::v1::{account, benchmarks, impl_benchmark_test_suite, whitelisted_caller};
+use frame_support::traits::Currency;
+use frame_system::RawOrigin;
+use sp_core::crypto::KeyTypeId;
+use sp_runtime::traits::Bounded;
+
+/// The key type of the requester keys generated in the keystore.
+const REQUESTER_KEY_TYPE: KeyTypeId = KeyTypeId(*b"bwir");
+
+/// Generates a requester key in the keystore and returns it with its signature registering it by
+/// `who`.
+fn requester_of<T: Config>(who: &T::AccountId) -> (ed25519::Public, ed25519::Signature) {
+	let requester = sp_io::crypto::ed25519_generate(REQUESTER_KEY_TYPE, None);
+	let signature =
+		sp_io::crypto::ed25519_sign(REQUESTER_KEY_TYPE, &requester, &registration_payload(who))
+			.expect("The key is in the keystore; qed");
+	(requester, signature)
+}
+
+benchmarks! {
+	claim {
+		let r in 1 .. T::MaxReceiptsPerClaim::get();
+		let caller: T::AccountId = whitelisted_caller();
+		let owner: T::AccountId = account("owner", 0, 0);
+		let period = BandwidthIncentive::<T>::current_period();
+		T::Currency::make_free_balance_be(
+			&BandwidthIncentive::<T>::pot_account(),
+			BalanceOf::<T>::max_value() / 4u32.into(),
+		);
+		T::Currency::make_free_balance_be(&owner, BalanceOf::<T>::max_value() / 4u32.into());
+		// Each requester can pay for all the segments of its receipt.
+		let deposit = T::RewardPerSegment::get()
+			.saturating_mul(T::MaxSegmentsPerReceipt::get().into());
+		let receipts = (0..r)
+			.map(|_| {
+				let requester = sp_io::crypto::ed25519_generate(REQUESTER_KEY_TYPE, None);
+				T::Currency::reserve(&owner, deposit).expect("The owner is funded; qed");
+				Requesters::<T>::insert(requester, (owner.clone(), deposit));
+				let mut receipt = ServingReceipt::unsigned(
+					caller.clone(),
+					requester,
+					period,
+					T::MaxSegmentsPerReceipt::get(),
+					u64::MAX,
+				);
+				receipt.signature =
+					sp_io::crypto::ed25519_sign(REQUESTER_KEY_TYPE, &requester, &receipt.payload())
+						.expect("The key is in the keystore; qed");
+				receipt
+			})
+			.collect::<Vec<_>>();
+		let receipts: BoundedVec<_, T::MaxReceiptsPerClaim> =
+			receipts.try_into().map_err(|_| "Too many receipts")?;
+	}: _(RawOrigin::Signed(caller.clone()), receipts)
+	verify {
+		assert_eq!(
+			ServedSegments::<T>::get(&caller),
+			(r as u64 * T::MaxSegmentsPerReceipt::get() as u64)
+				.min(T::MaxSegmentsPerPeriod::get())
+		);
+	}
+
+	register_requester {
+		let caller: T::AccountId = whitelisted_caller();
+		T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value() / 2u32.into());
+		let (requester, signature) = requester_of::<T>(&caller);
+	}: _(RawOrigin::Signed(caller.clone()), requester, signature)
+	verify {
+		assert_eq!(Requesters::<T>::get(requester), Some((caller, T::RequesterDeposit::get())));
+	}
+
+	deregister_requester {
+		let caller: T::AccountId = whitelisted_caller();
+		T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value() / 2u32.into());
+		let (requester, signature) = requester_of::<T>(&caller);
+		BandwidthIncentive::<T>::register_requester(
+			RawOrigin::Signed(caller.clone()).into(),
+			requester,
+			signature,
+		)?;
+	}: _(RawOrigin::Signed(caller), requester)
+	verify {
+		assert!(Requesters::<T>::get(requester).is_none());
+	}
+}
+
+impl_benchmark_test_suite!(BandwidthIncentive, crate::mock::new_test_ext(), crate::mock::Runtime);
@@ -0,0 +1,328 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Bandwidth Incentive Pallet
+//!
+//! Rewards the nodes, typically farmers, serving segments to their peers over the DAS network.
+//!
+//! A serving node claims its [`ServingReceipt`]s, each signed by a peer it served segments to
+//! during a serving period. The node is paid `RewardPerSegment` for each segment, up to
+//! `MaxSegmentsPerReceipt` segments per receipt and `MaxSegmentsPerPeriod` segments per period in
+//! which it claims. The receipts of a peer are claimed in the order of their periods, once each,
+//! within `ClaimPeriods` periods.
+//!
+//! The peers sign with the keys of their DAS network identities, which are free to create, so only
+//! the receipts of the requesters registered with [`Pallet::register_requester`], each bonding
+//! `RequesterDeposit`, are rewarded, and never to the account that registered the requester. The
+//! rewards are paid from the pot of the pallet, which has to be funded, the
+//! [`Pallet::pot_account`], and stop once it is empty. The reward paid for the receipts of a
+//! requester is moved from its deposit to the pot, so the receipts of a requester are rewarded up
+//! to its deposit, and serving oneself through a second account earns nothing.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use frame_support::{
+	pallet_prelude::*,
+	traits::{BalanceStatus, Currency, ExistenceRequirement, ReservableCurrency, UnixTime},
+	PalletId,
+};
+use frame_system::pallet_prelude::*;
+pub use melo_core_primitives::serving::{registration_payload, serving_period, ServingReceipt};
+use sp_core::ed25519;
+use sp_io::crypto::ed25519_verify;
+use sp_runtime::traits::{AccountIdConversion, SaturatedConversion, Saturating, Zero};
+use sp_std::prelude::*;
+
+pub use pallet::*;
+
+pub mod weights;
+pub use weights::*;
+
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+mod mock;
+mod tests;
+
+pub type BalanceOf<T> =
+	<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+
+	#[pallet::pallet]
+	pub struct Pallet<T>(_);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// The overarching event type for the runtime.
+		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+		/// Weight information for this pallet's extrinsics.
+		type WeightInfo: WeightInfo;
+
+		/// The currency the rewards are paid in and the requesters bond.
+		type Currency: ReservableCurrency<Self::AccountId>;
+
+		/// The id of the pot the rewards are paid from.
+		#[pallet::constant]
+		type PotId: Get<PalletId>;
+
+		/// The time the serving periods are derived from.
+		type UnixTime: UnixTime;
+
+		/// The reward for each segment served.
+		#[pallet::constant]
+		type RewardPerSegment: Get<BalanceOf<Self>>;
+
+		/// The maximum number of segments rewarded per receipt.
+		#[pallet::constant]
+		type MaxSegmentsPerReceipt: Get<u32>;
+
+		/// The maximum number of segments a serving node is rewarded for in the claims of a period.
+		#[pallet::constant]
+		type MaxSegmentsPerPeriod: Get<u64>;
+
+		/// The amount reserved for registering a requester.
+		#[pallet::constant]
+		type RequesterDeposit: Get<BalanceOf<Self>>;
+
+		/// The maximum number of receipts claimed at once.
+		#[pallet::constant]
+		type MaxReceiptsPerClaim: Get<u32>;
+
+		/// The number of periods a receipt can be claimed for after its period.
+		#[pallet::constant]
+		type ClaimPeriods: Get<u32>;
+	}
+
+	/// The last period claimed by each serving node, by requester.
+	#[pallet::storage]
+	#[pallet::getter(fn last_claimed)]
+	pub type LastClaimed<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		Blake2_128Concat,
+		ed25519::Public,
+		u32,
+		OptionQuery,
+	>;
+
+	/// The total number of segments each serving node was rewarded for.
+	#[pallet::storage]
+	#[pallet::getter(fn served_segments)]
+	pub type ServedSegments<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, u64, ValueQuery>;
+
+	/// The last period each serving node claimed in, with the segments it was rewarded for in it.
+	#[pallet::storage]
+	#[pallet::getter(fn period_segments)]
+	pub type PeriodSegments<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, (u32, u64), ValueQuery>;
+
+	/// The account that registered each requester, with what is left of the deposit it reserved.
+	#[pallet::storage]
+	#[pallet::getter(fn requester)]
+	pub type Requesters<T: Config> =
+		StorageMap<_, Blake2_128Concat, ed25519::Public, (T::AccountId, BalanceOf<T>), OptionQuery>;
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// A serving node was rewarded for the segments of its receipts.
+		ServingRewarded { server: T::AccountId, receipts: u32, segments: u64, reward: BalanceOf<T> },
+		/// A requester was registered, its deposit reserved from `who`.
+		RequesterRegistered { who: T::AccountId, requester: ed25519::Public },
+		/// A requester was deregistered and what is left of its deposit returned to `who`.
+		RequesterDeregistered { who: T::AccountId, requester: ed25519::Public },
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The receipt is not for the caller.
+		NotServer,
+		/// The receipt is not signed by its requester.
+		BadSignature,
+		/// The period of the receipt has not started.
+		FuturePeriod,
+		/// The period of the receipt is more than `ClaimPeriods` periods old.
+		PeriodExpired,
+		/// A receipt of the requester was already claimed for the period or a later one.
+		AlreadyClaimed,
+		/// The requester is already registered.
+		AlreadyRegistered,
+		/// The requester is not registered by the caller.
+		NotRegistered,
+		/// The caller can not reserve `RequesterDeposit`.
+		InsufficientDeposit,
+		/// The pot can not pay the reward.
+		PotExhausted,
+		/// The requester of the receipt is registered by the caller.
+		SelfServed,
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Claim the reward for the segments served by the caller, as acknowledged by `receipts`.
+		///
+		/// The whole claim fails if any receipt is invalid, is signed by a requester registered by
+		/// the caller or the pot can not pay the reward. The receipts of requesters not registered
+		/// are skipped, and those beyond `MaxSegmentsPerPeriod` or the deposit of their requester
+		/// are claimed without being rewarded. The reward of each receipt is moved from the deposit
+		/// of its requester to the pot.
+		#[pallet::call_index(0)]
+		#[pallet::weight(T::WeightInfo::claim(receipts.len() as u32))]
+		pub fn claim(
+			origin: OriginFor<T>,
+			receipts: BoundedVec<ServingReceipt<T::AccountId>, T::MaxReceiptsPerClaim>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let current = Self::current_period();
+			let reward_per_segment = T::RewardPerSegment::get();
+			let (period, mut served) = PeriodSegments::<T>::get(&who);
+			if period != current {
+				served = 0;
+			}
+
+			let mut segments = 0u64;
+			let mut rewarded = 0u32;
+			let mut charges = Vec::new();
+			for receipt in receipts.iter() {
+				ensure!(receipt.server == who, Error::<T>::NotServer);
+				ensure!(receipt.period <= current, Error::<T>::FuturePeriod);
+				ensure!(
+					receipt.period.saturating_add(T::ClaimPeriods::get()) >= current,
+					Error::<T>::PeriodExpired
+				);
+				ensure!(receipt.verify(), Error::<T>::BadSignature);
+				let (owner, deposit) = match Requesters::<T>::get(receipt.requester) {
+					Some(registration) => registration,
+					None => continue,
+				};
+				ensure!(owner != who, Error::<T>::SelfServed);
+
+				LastClaimed::<T>::try_mutate(&who, receipt.requester, |last| {
+					ensure!(
+						last.map_or(true, |last| receipt.period > last),
+						Error::<T>::AlreadyClaimed
+					);
+					*last = Some(receipt.period);
+					Ok::<_, Error<T>>(())
+				})?;
+
+				let affordable = if reward_per_segment.is_zero() {
+					u64::MAX
+				} else {
+					(deposit / reward_per_segment).saturated_into::<u64>()
+				};
+				let receipt_segments =
+					u64::from(receipt.segments.min(T::MaxSegmentsPerReceipt::get()))
+						.min(T::MaxSegmentsPerPeriod::get().saturating_sub(served))
+						.min(affordable);
+				let charge = reward_per_segment
+					.saturating_mul(receipt_segments.saturated_into::<BalanceOf<T>>());
+				if !charge.is_zero() {
+					Requesters::<T>::insert(
+						receipt.requester,
+						(owner.clone(), deposit.saturating_sub(charge)),
+					);
+					charges.push((owner, charge));
+				}
+				served = served.saturating_add(receipt_segments);
+				segments = segments.saturating_add(receipt_segments);
+				rewarded += 1;
+			}
+			PeriodSegments::<T>::insert(&who, (current, served));
+
+			let reward =
+				reward_per_segment.saturating_mul(segments.saturated_into::<BalanceOf<T>>());
+			let pot = Self::pot_account();
+			if !reward.is_zero() {
+				T::Currency::transfer(&pot, &who, reward, ExistenceRequirement::KeepAlive)
+					.map_err(|_| Error::<T>::PotExhausted)?;
+			}
+			for (owner, charge) in charges {
+				T::Currency::repatriate_reserved(&owner, &pot, charge, BalanceStatus::Free)?;
+			}
+			ServedSegments::<T>::mutate(&who, |served| *served = served.saturating_add(segments));
+
+			Self::deposit_event(Event::ServingRewarded {
+				server: who,
+				receipts: rewarded,
+				segments,
+				reward,
+			});
+			Ok(())
+		}
+
+		/// Register `requester`, reserving `RequesterDeposit` from the caller, so that the
+		/// receipts it signs are rewarded.
+		///
+		/// `signature` is the signature of `requester` over the [`registration_payload`] of the
+		/// caller.
+		#[pallet::call_index(1)]
+		#[pallet::weight(T::WeightInfo::register_requester())]
+		pub fn register_requester(
+			origin: OriginFor<T>,
+			requester: ed25519::Public,
+			signature: ed25519::Signature,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(!Requesters::<T>::contains_key(requester), Error::<T>::AlreadyRegistered);
+			ensure!(
+				ed25519_verify(&signature, &registration_payload(&who), &requester),
+				Error::<T>::BadSignature
+			);
+
+			let deposit = T::RequesterDeposit::get();
+			T::Currency::reserve(&who, deposit).map_err(|_| Error::<T>::InsufficientDeposit)?;
+			Requesters::<T>::insert(requester, (who.clone(), deposit));
+
+			Self::deposit_event(Event::RequesterRegistered { who, requester });
+			Ok(())
+		}
+
+		/// Deregister `requester`, registered by the caller, returning what is left of its deposit.
+		#[pallet::call_index(2)]
+		#[pallet::weight(T::WeightInfo::deregister_requester())]
+		pub fn deregister_requester(
+			origin: OriginFor<T>,
+			requester: ed25519::Public,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let (owner, deposit) =
+				Requesters::<T>::get(requester).ok_or(Error::<T>::NotRegistered)?;
+			ensure!(owner == who, Error::<T>::NotRegistered);
+
+			T::Currency::unreserve(&who, deposit);
+			Requesters::<T>::remove(requester);
+
+			Self::deposit_event(Event::RequesterDeregistered { who, requester });
+			Ok(())
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Returns the current serving period.
+	pub fn current_period() -> u32 {
+		serving_period(T::UnixTime::now().as_secs())
+	}
+
+	/// Returns the account of the pot the rewards are paid from.
+	pub fn pot_account() -> T::AccountId {
+		T::PotId::get().into_account_truncating()
+	}
+}
@@ -0,0 +1,182 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Test utilities
+
+#![cfg(test)]
+
+use frame_support::{
+	parameter_types,
+	traits::{ConstU32, ConstU64, UnixTime},
+	PalletId,
+};
+use melo_core_primitives::serving::{registration_payload, SERVING_PERIOD_SECS};
+use sp_core::{ed25519, Pair, H256};
+use sp_keystore::{testing::MemoryKeystore, KeystoreExt};
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, IdentityLookup},
+};
+use std::{cell::RefCell, time::Duration};
+
+use crate as pallet_bandwidth_incentive;
+use crate::{Config, Pallet, ServingReceipt};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Runtime>;
+type Block = frame_system::mocking::MockBlock<Runtime>;
+
+frame_support::construct_runtime!(
+	pub struct Runtime where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
+		BandwidthIncentive: pallet_bandwidth_incentive::{Pallet, Call, Storage, Event<T>},
+	}
+);
+
+impl frame_system::Config for Runtime {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type RuntimeOrigin = RuntimeOrigin;
+	type Index = u64;
+	type BlockNumber = u64;
+	type RuntimeCall = RuntimeCall;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type RuntimeEvent = RuntimeEvent;
+	type BlockHashCount = ConstU64<250>;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<u64>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+	type OnSetCode = ();
+	type MaxConsumers = ConstU32<16>;
+}
+
+parameter_types! {
+	pub const ExistentialDeposit: u64 = 1;
+	pub const PotId: PalletId = PalletId(*b"melo/bwi");
+}
+
+impl pallet_balances::Config for Runtime {
+	type AccountStore = System;
+	type Balance = u64;
+	type DustRemoval = ();
+	type RuntimeEvent = RuntimeEvent;
+	type ExistentialDeposit = ExistentialDeposit;
+	type MaxLocks = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = ();
+	type WeightInfo = ();
+	type FreezeIdentifier = ();
+	type MaxFreezes = ();
+	type HoldIdentifier = ();
+	type MaxHolds = ();
+}
+
+thread_local! {
+	static NOW: RefCell<u64> = RefCell::new(0);
+}
+
+/// The unix time, set by [`set_period`].
+pub struct MockTime;
+
+impl UnixTime for MockTime {
+	fn now() -> Duration {
+		Duration::from_secs(NOW.with(|now| *now.borrow()))
+	}
+}
+
+/// Moves the time to the start of the serving `period`.
+pub fn set_period(period: u32) {
+	NOW.with(|now| *now.borrow_mut() = period as u64 * SERVING_PERIOD_SECS);
+}
+
+pub const REWARD_PER_SEGMENT: u64 = 10;
+pub const MAX_SEGMENTS_PER_RECEIPT: u32 = 100;
+pub const MAX_SEGMENTS_PER_PERIOD: u64 = 250;
+pub const REQUESTER_DEPOSIT: u64 = 2_000;
+pub const CLAIM_PERIODS: u32 = 2;
+pub const POT: u64 = 10_000;
+/// The account registering the requesters 1 to 3.
+pub const OWNER: u64 = 20;
+
+impl Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type WeightInfo = ();
+	type Currency = Balances;
+	type PotId = PotId;
+	type UnixTime = MockTime;
+	type RewardPerSegment = ConstU64<REWARD_PER_SEGMENT>;
+	type MaxSegmentsPerReceipt = ConstU32<MAX_SEGMENTS_PER_RECEIPT>;
+	type MaxSegmentsPerPeriod = ConstU64<MAX_SEGMENTS_PER_PERIOD>;
+	type RequesterDeposit = ConstU64<REQUESTER_DEPOSIT>;
+	type MaxReceiptsPerClaim = ConstU32<4>;
+	type ClaimPeriods = ConstU32<CLAIM_PERIODS>;
+}
+
+/// Returns the key pair of the requester `i`.
+pub fn requester(i: u8) -> ed25519::Pair {
+	ed25519::Pair::from_seed(&[i; 32])
+}
+
+/// Returns the receipt of `segments` served by `server` to the requester `i` during `period`,
+/// signed by the requester.
+pub fn receipt(server: u64, i: u8, period: u32, segments: u32) -> ServingReceipt<u64> {
+	let pair = requester(i);
+	let mut receipt =
+		ServingReceipt::unsigned(server, pair.public(), period, segments, segments as u64 * 1024);
+	receipt.signature = pair.sign(&receipt.payload());
+	receipt
+}
+
+/// Registers the requester `i` by `who`.
+pub fn register(who: u64, i: u8) -> sp_runtime::DispatchResult {
+	let pair = requester(i);
+	BandwidthIncentive::register_requester(
+		RuntimeOrigin::signed(who),
+		pair.public(),
+		pair.sign(&registration_payload(&who)),
+	)
+}
+
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	let mut t = frame_system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+	pallet_balances::GenesisConfig::<Runtime> {
+		balances: vec![(Pallet::<Runtime>::pot_account(), POT), (OWNER, 10_000)],
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+	let mut ext: sp_io::TestExternalities = t.into();
+	// The benchmarks generate the keys of the requesters.
+	ext.register_extension(KeystoreExt::new(MemoryKeystore::new()));
+	ext.execute_with(|| {
+		System::set_block_number(1);
+		for i in 1..=3 {
+			register(OWNER, i).unwrap();
+		}
+	});
+	set_period(0);
+	ext
+}
@@ -0,0 +1,237 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#![cfg(test)]
+
+use super::*;
+use crate::mock::*;
+use frame_support::{assert_noop, assert_ok};
+
+const SERVER: u64 = 10;
+
+fn claim(receipts: Vec<ServingReceipt<u64>>) -> DispatchResult {
+	BandwidthIncentive::claim(RuntimeOrigin::signed(SERVER), receipts.try_into().unwrap())
+}
+
+#[test]
+fn claim_should_reward_the_server() {
+	new_test_ext().execute_with(|| {
+		set_period(5);
+		assert_ok!(claim(vec![receipt(SERVER, 1, 4, 20), receipt(SERVER, 2, 5, 30)]));
+
+		assert_eq!(Balances::free_balance(SERVER), 50 * REWARD_PER_SEGMENT);
+		// The pot is refilled from the deposits of the requesters.
+		assert_eq!(Balances::free_balance(BandwidthIncentive::pot_account()), POT);
+		assert_eq!(
+			BandwidthIncentive::requester(requester(2).public()),
+			Some((OWNER, REQUESTER_DEPOSIT - 30 * REWARD_PER_SEGMENT))
+		);
+		assert_eq!(
+			Balances::reserved_balance(OWNER),
+			3 * REQUESTER_DEPOSIT - 50 * REWARD_PER_SEGMENT
+		);
+		assert_eq!(BandwidthIncentive::served_segments(SERVER), 50);
+		assert_eq!(BandwidthIncentive::last_claimed(SERVER, requester(1).public()), Some(4));
+		System::assert_last_event(
+			Event::ServingRewarded {
+				server: SERVER,
+				receipts: 2,
+				segments: 50,
+				reward: 50 * REWARD_PER_SEGMENT,
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn claim_should_cap_the_segments_of_a_receipt() {
+	new_test_ext().execute_with(|| {
+		set_period(1);
+		assert_ok!(claim(vec![receipt(SERVER, 1, 1, MAX_SEGMENTS_PER_RECEIPT * 2)]));
+		assert_eq!(BandwidthIncentive::served_segments(SERVER), MAX_SEGMENTS_PER_RECEIPT as u64);
+	});
+}
+
+#[test]
+fn claim_should_cap_the_segments_of_a_period() {
+	new_test_ext().execute_with(|| {
+		set_period(1);
+		let receipts = (1..=3).map(|i| receipt(SERVER, i, 0, MAX_SEGMENTS_PER_RECEIPT)).collect();
+		assert_ok!(claim(receipts));
+		assert_eq!(BandwidthIncentive::served_segments(SERVER), MAX_SEGMENTS_PER_PERIOD);
+
+		// The receipts are claimed without reward once the cap of the period is reached.
+		assert_ok!(claim(vec![receipt(SERVER, 1, 1, 10)]));
+		assert_eq!(BandwidthIncentive::served_segments(SERVER), MAX_SEGMENTS_PER_PERIOD);
+		assert_eq!(BandwidthIncentive::last_claimed(SERVER, requester(1).public()), Some(1));
+		System::assert_last_event(
+			Event::ServingRewarded { server: SERVER, receipts: 1, segments: 0, reward: 0 }.into(),
+		);
+
+		set_period(2);
+		assert_ok!(claim(vec![receipt(SERVER, 1, 2, 10), receipt(SERVER, 2, 2, 10)]));
+		assert_eq!(BandwidthIncentive::served_segments(SERVER), MAX_SEGMENTS_PER_PERIOD + 20);
+		assert_eq!(BandwidthIncentive::period_segments(SERVER), (2, 20));
+	});
+}
+
+#[test]
+fn receipts_of_unregistered_requesters_should_not_be_rewarded() {
+	new_test_ext().execute_with(|| {
+		set_period(1);
+		assert_ok!(claim(vec![receipt(SERVER, 1, 1, 10), receipt(SERVER, 4, 1, 20)]));
+		assert_eq!(BandwidthIncentive::served_segments(SERVER), 10);
+		assert_eq!(BandwidthIncentive::last_claimed(SERVER, requester(4).public()), None);
+		System::assert_last_event(
+			Event::ServingRewarded {
+				server: SERVER,
+				receipts: 1,
+				segments: 10,
+				reward: 10 * REWARD_PER_SEGMENT,
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn claim_should_cap_the_segments_to_the_deposit_of_the_requester() {
+	new_test_ext().execute_with(|| {
+		// The deposit pays for 200 segments.
+		let affordable = REQUESTER_DEPOSIT / REWARD_PER_SEGMENT;
+		for period in 0..2 {
+			set_period(period);
+			assert_ok!(claim(vec![receipt(SERVER, 1, period, 150)]));
+		}
+		assert_eq!(BandwidthIncentive::served_segments(SERVER), affordable);
+		assert_eq!(BandwidthIncentive::requester(requester(1).public()), Some((OWNER, 0)));
+
+		// The receipts of the requester are no longer rewarded once its deposit is spent.
+		set_period(2);
+		assert_ok!(claim(vec![receipt(SERVER, 1, 2, 10)]));
+		assert_eq!(BandwidthIncentive::served_segments(SERVER), affordable);
+		assert_eq!(Balances::free_balance(SERVER), REQUESTER_DEPOSIT);
+		assert_eq!(Balances::free_balance(BandwidthIncentive::pot_account()), POT);
+
+		// Only what is left of the deposit is returned.
+		assert_ok!(BandwidthIncentive::deregister_requester(
+			RuntimeOrigin::signed(OWNER),
+			requester(1).public()
+		));
+		assert_eq!(Balances::reserved_balance(OWNER), 2 * REQUESTER_DEPOSIT);
+		assert_eq!(Balances::free_balance(OWNER), 10_000 - 3 * REQUESTER_DEPOSIT);
+	});
+}
+
+#[test]
+fn receipts_of_requesters_registered_by_the_server_should_fail() {
+	new_test_ext().execute_with(|| {
+		set_period(1);
+		Balances::make_free_balance_be(&SERVER, REQUESTER_DEPOSIT * 2);
+		assert_ok!(register(SERVER, 4));
+		assert_noop!(
+			claim(vec![receipt(SERVER, 1, 1, 10), receipt(SERVER, 4, 1, 10)]),
+			Error::<Runtime>::SelfServed
+		);
+	});
+}
+
+#[test]
+fn claim_should_fail_once_the_pot_is_exhausted() {
+	new_test_ext().execute_with(|| {
+		set_period(1);
+		Balances::make_free_balance_be(&BandwidthIncentive::pot_account(), REWARD_PER_SEGMENT);
+		assert_noop!(claim(vec![receipt(SERVER, 1, 1, 10)]), Error::<Runtime>::PotExhausted);
+	});
+}
+
+#[test]
+fn requesters_should_be_registered_with_a_deposit() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(
+			BandwidthIncentive::requester(requester(1).public()),
+			Some((OWNER, REQUESTER_DEPOSIT))
+		);
+		assert_eq!(Balances::reserved_balance(OWNER), 3 * REQUESTER_DEPOSIT);
+
+		assert_noop!(register(OWNER, 1), Error::<Runtime>::AlreadyRegistered);
+		assert_noop!(register(SERVER, 4), Error::<Runtime>::InsufficientDeposit);
+		let pair = requester(4);
+		assert_noop!(
+			BandwidthIncentive::register_requester(
+				RuntimeOrigin::signed(OWNER),
+				pair.public(),
+				pair.sign(&registration_payload(&SERVER)),
+			),
+			Error::<Runtime>::BadSignature
+		);
+
+		assert_noop!(
+			BandwidthIncentive::deregister_requester(
+				RuntimeOrigin::signed(SERVER),
+				requester(1).public()
+			),
+			Error::<Runtime>::NotRegistered
+		);
+		assert_ok!(BandwidthIncentive::deregister_requester(
+			RuntimeOrigin::signed(OWNER),
+			requester(1).public()
+		));
+		assert_eq!(BandwidthIncentive::requester(requester(1).public()), None);
+		assert_eq!(Balances::reserved_balance(OWNER), 2 * REQUESTER_DEPOSIT);
+	});
+}
+
+#[test]
+fn receipts_should_be_claimed_once() {
+	new_test_ext().execute_with(|| {
+		set_period(3);
+		assert_ok!(claim(vec![receipt(SERVER, 1, 2, 10)]));
+		assert_noop!(claim(vec![receipt(SERVER, 1, 2, 10)]), Error::<Runtime>::AlreadyClaimed);
+		assert_noop!(claim(vec![receipt(SERVER, 1, 1, 10)]), Error::<Runtime>::AlreadyClaimed);
+		assert_noop!(
+			claim(vec![receipt(SERVER, 1, 3, 10), receipt(SERVER, 1, 3, 10)]),
+			Error::<Runtime>::AlreadyClaimed
+		);
+
+		assert_ok!(claim(vec![receipt(SERVER, 1, 3, 10)]));
+		assert_eq!(BandwidthIncentive::served_segments(SERVER), 20);
+	});
+}
+
+#[test]
+fn invalid_receipts_should_fail() {
+	new_test_ext().execute_with(|| {
+		set_period(10);
+		assert_noop!(claim(vec![receipt(SERVER + 1, 1, 9, 10)]), Error::<Runtime>::NotServer);
+		assert_noop!(claim(vec![receipt(SERVER, 1, 11, 10)]), Error::<Runtime>::FuturePeriod);
+		assert_noop!(
+			claim(vec![receipt(SERVER, 1, 10 - CLAIM_PERIODS - 1, 10)]),
+			Error::<Runtime>::PeriodExpired
+		);
+
+		let mut inflated = receipt(SERVER, 1, 9, 10);
+		inflated.segments += 1;
+		assert_noop!(claim(vec![inflated]), Error::<Runtime>::BadSignature);
+
+		// A claim fails as a whole.
+		let mut forged = receipt(SERVER, 2, 9, 10);
+		forged.requester = requester(3).public();
+		assert_noop!(
+			claim(vec![receipt(SERVER, 1, 9, 10), forged]),
+			Error::<Runtime>::BadSignature
+		);
+		assert_eq!(Balances::free_balance(SERVER), 0);
+	});
+}
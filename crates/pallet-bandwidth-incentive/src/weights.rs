@@ -0,0 +1,86 @@
+//! Weights for pallet_bandwidth_incentive
+//!
+//! The pallet has not been benchmarked yet, these weights are estimates from the storage
+//! accesses of each call. Replace them with the output of `melodot-node benchmark pallet
+//! --pallet=pallet-bandwidth-incentive` once it is run on the reference hardware.
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use core::marker::PhantomData;
+
+/// Weight functions needed for pallet_bandwidth_incentive.
+pub trait WeightInfo {
+	fn claim(r: u32, ) -> Weight;
+	fn register_requester() -> Weight;
+	fn deregister_requester() -> Weight;
+}
+
+/// Weights for pallet_bandwidth_incentive (estimates).
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	/// Storage: BandwidthIncentive LastClaimed (r:64 w:64)
+	/// Storage: BandwidthIncentive Requesters (r:64 w:64)
+	/// Storage: BandwidthIncentive PeriodSegments (r:1 w:1)
+	/// Storage: System Account (r:66 w:66)
+	/// Storage: BandwidthIncentive ServedSegments (r:1 w:1)
+	/// The range of component `r` is `[1, 64]`.
+	fn claim(r: u32, ) -> Weight {
+		Weight::from_parts(45_000_000, 6196)
+			.saturating_add(Weight::from_parts(82_000_000, 0).saturating_mul(r.into()))
+			.saturating_add(T::DbWeight::get().reads(4_u64))
+			.saturating_add(T::DbWeight::get().reads((3_u64).saturating_mul(r.into())))
+			.saturating_add(T::DbWeight::get().writes(4_u64))
+			.saturating_add(T::DbWeight::get().writes((3_u64).saturating_mul(r.into())))
+			.saturating_add(Weight::from_parts(0, 7761).saturating_mul(r.into()))
+	}
+	/// Storage: BandwidthIncentive Requesters (r:1 w:1)
+	/// Storage: System Account (r:1 w:1)
+	fn register_requester() -> Weight {
+		Weight::from_parts(60_000_000, 3593)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Storage: BandwidthIncentive Requesters (r:1 w:1)
+	/// Storage: System Account (r:1 w:1)
+	fn deregister_requester() -> Weight {
+		Weight::from_parts(30_000_000, 3593)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	/// Storage: BandwidthIncentive LastClaimed (r:64 w:64)
+	/// Storage: BandwidthIncentive Requesters (r:64 w:64)
+	/// Storage: BandwidthIncentive PeriodSegments (r:1 w:1)
+	/// Storage: System Account (r:66 w:66)
+	/// Storage: BandwidthIncentive ServedSegments (r:1 w:1)
+	/// The range of component `r` is `[1, 64]`.
+	fn claim(r: u32, ) -> Weight {
+		Weight::from_parts(45_000_000, 6196)
+			.saturating_add(Weight::from_parts(82_000_000, 0).saturating_mul(r.into()))
+			.saturating_add(RocksDbWeight::get().reads(4_u64))
+			.saturating_add(RocksDbWeight::get().reads((3_u64).saturating_mul(r.into())))
+			.saturating_add(RocksDbWeight::get().writes(4_u64))
+			.saturating_add(RocksDbWeight::get().writes((3_u64).saturating_mul(r.into())))
+			.saturating_add(Weight::from_parts(0, 7761).saturating_mul(r.into()))
+	}
+	/// Storage: BandwidthIncentive Requesters (r:1 w:1)
+	/// Storage: System Account (r:1 w:1)
+	fn register_requester() -> Weight {
+		Weight::from_parts(60_000_000, 3593)
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	/// Storage: BandwidthIncentive Requesters (r:1 w:1)
+	/// Storage: System Account (r:1 w:1)
+	fn deregister_requester() -> Weight {
+		Weight::from_parts(30_000_000, 3593)
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+}
@@ -0,0 +1,65 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+#[allow(unused_imports)]
+use crate::Pallet as DasConfig;
+use frame_benchmarking::v1::{benchmarks, impl_benchmark_test_suite};
+use frame_support::traits::UnfilteredDispatchable;
+use frame_system::Pallet as System;
+
+benchmarks! {
+	schedule_params {
+		let origin = T::UpdateOrigin::try_successful_origin()
+			.map_err(|_| "UpdateOrigin has no successful origin")?;
+		let params = DasParams { max_block_rows: T::MaxBlockRows::get(), ..Default::default() };
+		let activate_at = System::<T>::block_number().saturating_add(T::MinActivationDelay::get());
+		let call = Call::<T>::schedule_params { params, activate_at };
+	}: { call.dispatch_bypass_filter(origin)? }
+	verify {
+		assert_eq!(PendingParams::<T>::get(), Some((activate_at, params)));
+	}
+
+	cancel_pending_params {
+		let origin = T::UpdateOrigin::try_successful_origin()
+			.map_err(|_| "UpdateOrigin has no successful origin")?;
+		PendingParams::<T>::put((T::MinActivationDelay::get(), DasParams::default()));
+		let call = Call::<T>::cancel_pending_params {};
+	}: { call.dispatch_bypass_filter(origin)? }
+	verify {
+		assert_eq!(PendingParams::<T>::get(), None);
+	}
+
+	set_trusted_setup_hash {
+		let origin = T::UpdateOrigin::try_successful_origin()
+			.map_err(|_| "UpdateOrigin has no successful origin")?;
+		let hash = Some(H256::repeat_byte(1));
+		let call = Call::<T>::set_trusted_setup_hash { hash };
+	}: { call.dispatch_bypass_filter(origin)? }
+	verify {
+		assert_eq!(TrustedSetupHash::<T>::get(), hash);
+	}
+
+	schedule_plot_hashing {
+		let origin = T::UpdateOrigin::try_successful_origin()
+			.map_err(|_| "UpdateOrigin has no successful origin")?;
+		let activate_at = System::<T>::block_number().saturating_add(T::MinActivationDelay::get());
+		let call = Call::<T>::schedule_plot_hashing { hashing: PlotHashing::Keccak, activate_at };
+	}: { call.dispatch_bypass_filter(origin)? }
+	verify {
+		assert_eq!(PendingPlotHashing::<T>::get(), Some((activate_at, PlotHashing::Keccak)));
+	}
+}
+
+impl_benchmark_test_suite!(DasConfig, crate::mock::new_test_ext(), crate::mock::Runtime);
@@ -0,0 +1,275 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # DAS Config Pallet
+//!
+//! Holds the tunable parameters of data availability: the maximum size of a submission, the
+//! rows and bytes of a block and the retention window.
+//!
+//! The parameters are changed by the `UpdateOrigin`, usually governance. A change is scheduled
+//! for a future block, at least `MinActivationDelay` blocks ahead, so that nodes and clients can
+//! prepare for it, and becomes active at the beginning of that block. Other pallets read the
+//! active parameters through the [`Get<DasParams>`] implementation of the pallet.
+//!
+//! The pallet also registers the hash of the KZG trusted setup of the network, which nodes
+//! compare with the hash of their own settings at startup, and the hashing scheme of the plots
+//! of farmers. A new scheme is scheduled like new parameters, it invalidates the plots made with
+//! the previous one, which farmers rebuild before it activates.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use frame_support::{pallet_prelude::*, traits::EnsureOrigin};
+use frame_system::pallet_prelude::*;
+pub use melo_core_primitives::{DasParams, PlotHashing};
+use sp_core::H256;
+use sp_runtime::traits::Saturating;
+
+pub use pallet::*;
+
+pub mod weights;
+pub use weights::*;
+
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+mod mock;
+mod tests;
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+
+	#[pallet::pallet]
+	pub struct Pallet<T>(_);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// The overarching event type for the runtime.
+		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+		/// Weight information for this pallet's extrinsics.
+		type WeightInfo: WeightInfo;
+
+		/// The origin allowed to change the parameters.
+		type UpdateOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+		/// The upper bound of `max_block_rows`, set by the storage bounds of the pallets reading
+		/// the parameters.
+		#[pallet::constant]
+		type MaxBlockRows: Get<u32>;
+
+		/// The minimum number of blocks between the scheduling of new parameters and their
+		/// activation.
+		#[pallet::constant]
+		type MinActivationDelay: Get<Self::BlockNumber>;
+	}
+
+	/// The parameters in use.
+	#[pallet::storage]
+	#[pallet::getter(fn active_params)]
+	pub type ActiveParams<T: Config> = StorageValue<_, DasParams, ValueQuery>;
+
+	/// The parameters scheduled to replace the active ones, with the block they activate at.
+	#[pallet::storage]
+	#[pallet::getter(fn pending_params)]
+	pub type PendingParams<T: Config> = StorageValue<_, (T::BlockNumber, DasParams), OptionQuery>;
+
+	/// The hash of the KZG trusted setup of the network, if registered.
+	#[pallet::storage]
+	#[pallet::getter(fn trusted_setup_hash)]
+	pub type TrustedSetupHash<T: Config> = StorageValue<_, H256, OptionQuery>;
+
+	/// The hashing scheme of the plots of farmers.
+	#[pallet::storage]
+	#[pallet::getter(fn plot_hashing)]
+	pub type ActivePlotHashing<T: Config> = StorageValue<_, PlotHashing, ValueQuery>;
+
+	/// The hashing scheme scheduled to replace the active one, with the block it activates at.
+	#[pallet::storage]
+	#[pallet::getter(fn pending_plot_hashing)]
+	pub type PendingPlotHashing<T: Config> =
+		StorageValue<_, (T::BlockNumber, PlotHashing), OptionQuery>;
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig {
+		pub params: DasParams,
+		pub trusted_setup_hash: Option<H256>,
+		pub plot_hashing: PlotHashing,
+	}
+
+	#[cfg(feature = "std")]
+	impl Default for GenesisConfig {
+		fn default() -> Self {
+			Self {
+				params: DasParams::default(),
+				trusted_setup_hash: Some(melo_core_primitives::embedded_trusted_setup_hash()),
+				plot_hashing: PlotHashing::default(),
+			}
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig {
+		fn build(&self) {
+			assert!(Pallet::<T>::check_params(&self.params), "Invalid genesis DAS parameters");
+			ActiveParams::<T>::put(self.params);
+			if let Some(hash) = self.trusted_setup_hash {
+				TrustedSetupHash::<T>::put(hash);
+			}
+			ActivePlotHashing::<T>::put(self.plot_hashing);
+		}
+	}
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// New parameters were scheduled to activate at a block.
+		ParamsScheduled { params: DasParams, activate_at: T::BlockNumber },
+		/// The scheduled parameters became active.
+		ParamsActivated { params: DasParams },
+		/// The scheduled parameters were cancelled.
+		PendingParamsCancelled,
+		/// The hash of the trusted setup was changed.
+		TrustedSetupHashSet { hash: Option<H256> },
+		/// A new plot hashing scheme was scheduled to activate at a block.
+		PlotHashingScheduled { hashing: PlotHashing, activate_at: T::BlockNumber },
+		/// The scheduled plot hashing scheme became active.
+		PlotHashingActivated { hashing: PlotHashing },
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The parameters are inconsistent or exceed the bounds of the runtime.
+		InvalidParams,
+		/// The activation block is closer than `MinActivationDelay` blocks.
+		ActivationTooEarly,
+		/// No parameters are scheduled.
+		NoPendingParams,
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(now: T::BlockNumber) -> Weight {
+			let params_weight = match PendingParams::<T>::get() {
+				Some((activate_at, params)) if activate_at <= now => {
+					PendingParams::<T>::kill();
+					ActiveParams::<T>::put(params);
+					Self::deposit_event(Event::ParamsActivated { params });
+					T::DbWeight::get().reads_writes(1, 2)
+				},
+				_ => T::DbWeight::get().reads(1),
+			};
+			let hashing_weight = match PendingPlotHashing::<T>::get() {
+				Some((activate_at, hashing)) if activate_at <= now => {
+					PendingPlotHashing::<T>::kill();
+					ActivePlotHashing::<T>::put(hashing);
+					Self::deposit_event(Event::PlotHashingActivated { hashing });
+					T::DbWeight::get().reads_writes(1, 2)
+				},
+				_ => T::DbWeight::get().reads(1),
+			};
+			params_weight.saturating_add(hashing_weight)
+		}
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Schedule `params` to replace the active parameters at block `activate_at`.
+		/// Parameters already scheduled are replaced.
+		#[pallet::call_index(0)]
+		#[pallet::weight(T::WeightInfo::schedule_params())]
+		pub fn schedule_params(
+			origin: OriginFor<T>,
+			params: DasParams,
+			activate_at: T::BlockNumber,
+		) -> DispatchResult {
+			T::UpdateOrigin::ensure_origin(origin)?;
+			ensure!(Self::check_params(&params), Error::<T>::InvalidParams);
+
+			let now = <frame_system::Pallet<T>>::block_number();
+			ensure!(
+				activate_at >= now.saturating_add(T::MinActivationDelay::get()),
+				Error::<T>::ActivationTooEarly
+			);
+
+			PendingParams::<T>::put((activate_at, params));
+			Self::deposit_event(Event::ParamsScheduled { params, activate_at });
+			Ok(())
+		}
+
+		/// Cancel the scheduled parameters.
+		#[pallet::call_index(1)]
+		#[pallet::weight(T::WeightInfo::cancel_pending_params())]
+		pub fn cancel_pending_params(origin: OriginFor<T>) -> DispatchResult {
+			T::UpdateOrigin::ensure_origin(origin)?;
+			ensure!(PendingParams::<T>::take().is_some(), Error::<T>::NoPendingParams);
+			Self::deposit_event(Event::PendingParamsCancelled);
+			Ok(())
+		}
+
+		/// Register `hash` as the hash of the KZG trusted setup of the network, or remove it if
+		/// `None`. Nodes with other settings refuse to start once the change is imported.
+		#[pallet::call_index(2)]
+		#[pallet::weight(T::WeightInfo::set_trusted_setup_hash())]
+		pub fn set_trusted_setup_hash(origin: OriginFor<T>, hash: Option<H256>) -> DispatchResult {
+			T::UpdateOrigin::ensure_origin(origin)?;
+			TrustedSetupHash::<T>::set(hash);
+			Self::deposit_event(Event::TrustedSetupHashSet { hash });
+			Ok(())
+		}
+
+		/// Schedule `hashing` to replace the hashing scheme of plots at block `activate_at`.
+		/// A scheme already scheduled is replaced.
+		#[pallet::call_index(3)]
+		#[pallet::weight(T::WeightInfo::schedule_plot_hashing())]
+		pub fn schedule_plot_hashing(
+			origin: OriginFor<T>,
+			hashing: PlotHashing,
+			activate_at: T::BlockNumber,
+		) -> DispatchResult {
+			T::UpdateOrigin::ensure_origin(origin)?;
+
+			let now = <frame_system::Pallet<T>>::block_number();
+			ensure!(
+				activate_at >= now.saturating_add(T::MinActivationDelay::get()),
+				Error::<T>::ActivationTooEarly
+			);
+
+			PendingPlotHashing::<T>::put((activate_at, hashing));
+			Self::deposit_event(Event::PlotHashingScheduled { hashing, activate_at });
+			Ok(())
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Returns whether `params` are consistent and within the bounds of the runtime.
+	pub fn check_params(params: &DasParams) -> bool {
+		params.is_valid() && params.max_block_rows <= T::MaxBlockRows::get()
+	}
+}
+
+impl<T: Config> Get<DasParams> for Pallet<T> {
+	fn get() -> DasParams {
+		ActiveParams::<T>::get()
+	}
+}
+
+/// The active hashing scheme of plots, read by the pallets verifying proofs of space.
+pub struct ActivePlotHashingOf<T>(PhantomData<T>);
+
+impl<T: Config> Get<PlotHashing> for ActivePlotHashingOf<T> {
+	fn get() -> PlotHashing {
+		ActivePlotHashing::<T>::get()
+	}
+}
@@ -0,0 +1,183 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#![cfg(test)]
+
+use super::*;
+use crate::mock::*;
+use frame_support::{assert_noop, assert_ok, traits::Hooks};
+use sp_runtime::DispatchError;
+
+fn new_params() -> DasParams {
+	DasParams { retention_period: 100, max_block_rows: 50, ..Default::default() }
+}
+
+#[test]
+fn genesis_params_should_be_active() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(DasConfig::active_params(), DasParams::default());
+		assert_eq!(<DasConfig as Get<DasParams>>::get(), DasParams::default());
+		assert_eq!(DasConfig::pending_params(), None);
+		assert_eq!(DasConfig::trusted_setup_hash(), Some(TRUSTED_SETUP_HASH));
+		assert_eq!(DasConfig::plot_hashing(), PlotHashing::Blake2);
+	});
+}
+
+#[test]
+fn should_activate_params_at_the_scheduled_block() {
+	new_test_ext().execute_with(|| {
+		let activate_at = 1 + MIN_ACTIVATION_DELAY;
+		assert_ok!(DasConfig::schedule_params(RuntimeOrigin::root(), new_params(), activate_at));
+		assert_eq!(DasConfig::pending_params(), Some((activate_at, new_params())));
+		System::assert_last_event(
+			Event::ParamsScheduled { params: new_params(), activate_at }.into(),
+		);
+
+		// Nothing changes before the activation block.
+		DasConfig::on_initialize(activate_at - 1);
+		assert_eq!(DasConfig::active_params(), DasParams::default());
+
+		DasConfig::on_initialize(activate_at);
+		assert_eq!(DasConfig::active_params(), new_params());
+		assert_eq!(DasConfig::pending_params(), None);
+		System::assert_last_event(Event::ParamsActivated { params: new_params() }.into());
+	});
+}
+
+#[test]
+fn should_fail_to_schedule_params_from_non_root() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			DasConfig::schedule_params(RuntimeOrigin::signed(1), new_params(), 100),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			DasConfig::cancel_pending_params(RuntimeOrigin::signed(1)),
+			DispatchError::BadOrigin
+		);
+	});
+}
+
+#[test]
+fn should_fail_to_schedule_invalid_params() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			DasConfig::schedule_params(
+				RuntimeOrigin::root(),
+				DasParams { retention_period: 0, ..Default::default() },
+				100
+			),
+			Error::<Runtime>::InvalidParams
+		);
+		assert_noop!(
+			DasConfig::schedule_params(
+				RuntimeOrigin::root(),
+				DasParams { max_block_rows: MAX_BLOCK_ROWS + 1, ..Default::default() },
+				100
+			),
+			Error::<Runtime>::InvalidParams
+		);
+	});
+}
+
+#[test]
+fn should_fail_to_schedule_params_too_early() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			DasConfig::schedule_params(RuntimeOrigin::root(), new_params(), MIN_ACTIVATION_DELAY),
+			Error::<Runtime>::ActivationTooEarly
+		);
+	});
+}
+
+#[test]
+fn should_cancel_pending_params() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			DasConfig::cancel_pending_params(RuntimeOrigin::root()),
+			Error::<Runtime>::NoPendingParams
+		);
+
+		assert_ok!(DasConfig::schedule_params(RuntimeOrigin::root(), new_params(), 100));
+		assert_ok!(DasConfig::cancel_pending_params(RuntimeOrigin::root()));
+		assert_eq!(DasConfig::pending_params(), None);
+		System::assert_last_event(Event::PendingParamsCancelled.into());
+
+		DasConfig::on_initialize(100);
+		assert_eq!(DasConfig::active_params(), DasParams::default());
+	});
+}
+
+#[test]
+fn should_set_trusted_setup_hash() {
+	new_test_ext().execute_with(|| {
+		let hash = Some(H256::repeat_byte(1));
+		assert_noop!(
+			DasConfig::set_trusted_setup_hash(RuntimeOrigin::signed(1), hash),
+			DispatchError::BadOrigin
+		);
+
+		assert_ok!(DasConfig::set_trusted_setup_hash(RuntimeOrigin::root(), hash));
+		assert_eq!(DasConfig::trusted_setup_hash(), hash);
+		System::assert_last_event(Event::TrustedSetupHashSet { hash }.into());
+
+		assert_ok!(DasConfig::set_trusted_setup_hash(RuntimeOrigin::root(), None));
+		assert_eq!(DasConfig::trusted_setup_hash(), None);
+	});
+}
+
+#[test]
+fn should_activate_plot_hashing_at_the_scheduled_block() {
+	new_test_ext().execute_with(|| {
+		let activate_at = 1 + MIN_ACTIVATION_DELAY;
+		assert_ok!(DasConfig::schedule_plot_hashing(
+			RuntimeOrigin::root(),
+			PlotHashing::Blake3,
+			activate_at
+		));
+		assert_eq!(DasConfig::pending_plot_hashing(), Some((activate_at, PlotHashing::Blake3)));
+		System::assert_last_event(
+			Event::PlotHashingScheduled { hashing: PlotHashing::Blake3, activate_at }.into(),
+		);
+
+		DasConfig::on_initialize(activate_at - 1);
+		assert_eq!(ActivePlotHashingOf::<Runtime>::get(), PlotHashing::Blake2);
+
+		DasConfig::on_initialize(activate_at);
+		assert_eq!(ActivePlotHashingOf::<Runtime>::get(), PlotHashing::Blake3);
+		assert_eq!(DasConfig::pending_plot_hashing(), None);
+		// The parameters are left untouched.
+		assert_eq!(DasConfig::active_params(), DasParams::default());
+		System::assert_last_event(
+			Event::PlotHashingActivated { hashing: PlotHashing::Blake3 }.into(),
+		);
+	});
+}
+
+#[test]
+fn should_fail_to_schedule_plot_hashing() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			DasConfig::schedule_plot_hashing(RuntimeOrigin::signed(1), PlotHashing::Keccak, 100),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			DasConfig::schedule_plot_hashing(
+				RuntimeOrigin::root(),
+				PlotHashing::Keccak,
+				MIN_ACTIVATION_DELAY
+			),
+			Error::<Runtime>::ActivationTooEarly
+		);
+	});
+}
@@ -0,0 +1,71 @@
+//! Weights for pallet_das_config
+//!
+//! The pallet has not been benchmarked yet, these weights are estimates from the storage
+//! accesses of each call. Replace them with the output of `melodot-node benchmark pallet
+//! --pallet=pallet-das-config` once it is run on the reference hardware.
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use core::marker::PhantomData;
+
+/// Weight functions needed for pallet_das_config.
+pub trait WeightInfo {
+	fn schedule_params() -> Weight;
+	fn cancel_pending_params() -> Weight;
+	fn set_trusted_setup_hash() -> Weight;
+	fn schedule_plot_hashing() -> Weight;
+}
+
+/// Weights for pallet_das_config (estimates).
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	/// Storage: DasConfig PendingParams (r:0 w:1)
+	fn schedule_params() -> Weight {
+		Weight::from_parts(11_000_000, 0)
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: DasConfig PendingParams (r:1 w:1)
+	fn cancel_pending_params() -> Weight {
+		Weight::from_parts(12_000_000, 1513)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: DasConfig TrustedSetupHash (r:0 w:1)
+	fn set_trusted_setup_hash() -> Weight {
+		Weight::from_parts(10_000_000, 0)
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: DasConfig PendingPlotHashing (r:0 w:1)
+	fn schedule_plot_hashing() -> Weight {
+		Weight::from_parts(10_000_000, 0)
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	/// Storage: DasConfig PendingParams (r:0 w:1)
+	fn schedule_params() -> Weight {
+		Weight::from_parts(11_000_000, 0)
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: DasConfig PendingParams (r:1 w:1)
+	fn cancel_pending_params() -> Weight {
+		Weight::from_parts(12_000_000, 1513)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: DasConfig TrustedSetupHash (r:0 w:1)
+	fn set_trusted_setup_hash() -> Weight {
+		Weight::from_parts(10_000_000, 0)
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: DasConfig PendingPlotHashing (r:0 w:1)
+	fn schedule_plot_hashing() -> Weight {
+		Weight::from_parts(10_000_000, 0)
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+}
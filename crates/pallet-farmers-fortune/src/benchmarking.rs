@@ -15,11 +15,16 @@
 use super::*;
 #[allow(unused_imports)]
 use crate::Pallet as FarmersFortune;
-use frame_benchmarking::v1::{benchmarks, impl_benchmark_test_suite};
+use frame_benchmarking::v1::{benchmarks, impl_benchmark_test_suite, whitelisted_caller};
+use frame_support::sp_io::crypto::{sr25519_generate, sr25519_sign};
 use frame_system::{Pallet as System, RawOrigin};
-use melo_das_primitives::KZGCommitment;
-use melo_proof_of_space::{mock::*, CellMetadata, PieceMetadata, PiecePosition, PreCell};
+use melo_core_primitives::{config::SEGMENTS_PER_BLOB, kzg_verifier, SegmentCheck};
+use melo_das_primitives::{config::FIELD_ELEMENTS_PER_BLOB, KZGCommitment};
+use melo_proof_of_space::{
+	assignment::ASSIGNED_ROWS, mock::*, CellMetadata, PieceMetadata, PiecePosition, PreCell,
+};
 use pallet_melo_store::Pallet as MeloStore;
+use sp_runtime::MultiSignature;
 
 benchmarks! {
 	where_clause {
@@ -32,11 +37,13 @@ benchmarks! {
 			BalanceOf<T>: From<u128>,
 			T: Config<CommitmentFromPosition = MeloStore<T>>,
 			T: pallet_melo_store::Config,
+			T::BindingSignature: From<MultiSignature>,
 	}
 
 	claim  {
 		let caller: T::AccountId = [0u8; 32].into();
 		T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::from(100_000_000_0000u128));
+		FarmerBindings::<T>::insert(&caller, FarmerId::new(caller.clone()));
 
 		System::<T>::set_block_number(6u32.into());
 
@@ -44,7 +51,7 @@ benchmarks! {
 		let block_num5: T::BlockNumber = 5u32.into();
 		let block_num3: T::BlockNumber = 3u32.into();
 
-		<frame_system::BlockHash<T>>::insert(block_num5, header_hash);
+		Challenges::<T>::insert(block_num5, header_hash);
 		<frame_system::BlockHash<T>>::insert(block_num3, header_hash);
 
 		let segs = get_mock_row(&BLS_SCALAR11, &BLS_SCALAR12, 0, &PROOF_11, &PROOF_12, 16);
@@ -59,15 +66,77 @@ benchmarks! {
 		let win_cell_left = Cell::new(left_cell_metadata, segs[0].clone());
 		let win_cell_right = Cell::new(right_cell_metadata, segs[1].clone());
 
-		let commit_vec = vec![commit.clone(),commit.clone(),commit.clone()];
+		// With as many rows as assigned to each farmer, every row is assigned to the caller.
+		let commit_vec = vec![commit.clone(); ASSIGNED_ROWS as usize];
 		let _ = MeloStore::<T>::push_commitments_ext(block_num3, commit_vec.as_slice()).unwrap();
 		let _ = MeloStore::<T>::push_commitments_ext(block_num5, commit_vec.as_slice()).unwrap();
-	}: _(RawOrigin::Signed(caller),
+	}: _(RawOrigin::Signed(caller.clone()),
 		pre_cell.clone(),
 		Box::new(win_cell_left.clone()),
 		Box::new(win_cell_right.clone())
 	)
-	verify {}
+	verify {
+		assert!(ClaimantsForBlock::<T>::get(System::<T>::block_number()).contains(&caller));
+	}
+
+	register_farmer {
+		let public = sr25519_generate(0.into(), None);
+		let reward_account: T::AccountId = public.0.into();
+		let farmer_id = FarmerId::new(reward_account.clone());
+		let signature: T::BindingSignature = MultiSignature::Sr25519(
+			sr25519_sign(0.into(), &public, &farmer_id.binding_message()).unwrap()
+		).into();
+		T::Currency::make_free_balance_be(
+			&reward_account,
+			T::FarmerBond::get() + T::Currency::minimum_balance(),
+		);
+		let caller: T::AccountId = whitelisted_caller();
+	}: _(RawOrigin::Signed(caller), reward_account.clone(), farmer_id.clone(), signature)
+	verify {
+		assert_eq!(T::Currency::reserved_balance(&reward_account), T::FarmerBond::get());
+		assert_eq!(FarmerBindings::<T>::get(&reward_account), Some(farmer_id));
+	}
+
+	rotate_farmer_key {
+		let public = sr25519_generate(0.into(), None);
+		let old_account: T::AccountId = public.0.into();
+		let new_account: T::AccountId = [1u8; 32].into();
+		let farmer_id = FarmerId::new(old_account.clone());
+		FarmerBindings::<T>::insert(&old_account, &farmer_id);
+		FarmerOwners::<T>::insert(&farmer_id, &old_account);
+		let signature: T::BindingSignature = MultiSignature::Sr25519(
+			sr25519_sign(0.into(), &public, &farmer_id.rotation_message(&new_account, 0)).unwrap()
+		).into();
+		let caller: T::AccountId = whitelisted_caller();
+	}: _(RawOrigin::Signed(caller), old_account.clone(), new_account.clone(), signature)
+	verify {
+		assert_eq!(FarmerBindings::<T>::get(&new_account), Some(farmer_id));
+		assert!(FarmerBindings::<T>::get(&old_account).is_none());
+		assert_eq!(RotationNonces::<T>::get(&farmer_id), 1);
+	}
+
+	on_initialize {
+		let n: T::BlockNumber = 6u32.into();
+		Challenges::<T>::insert(T::BlockNumber::from(4u32), T::Hash::default());
+	}: {
+		FarmersFortune::<T>::on_initialize(n);
+	}
+	verify {
+		assert!(Challenges::<T>::contains_key(n));
+		assert!(!Challenges::<T>::contains_key(T::BlockNumber::from(4u32)));
+	}
+
+	// The cost of verifying the KZG proof of a segment of `l` field elements. The proof does not
+	// match the data, but the verification goes through the same steps as for a valid one.
+	verify_segment {
+		let l in 1 .. FIELD_ELEMENTS_PER_BLOB as u32;
+
+		let commit = KZGCommitment::try_from(COMMIT1).unwrap();
+		let seg = get_mock_seg(&BLS_SCALAR11, 0, 0, &PROOF_11, (l as usize).next_power_of_two());
+		let check = SegmentCheck::new(commit, seg, SEGMENTS_PER_BLOB as u32);
+	}: {
+		let _ = kzg_verifier::verify_segment(check.clone());
+	}
 }
 
 impl_benchmark_test_suite!(FarmersFortune, crate::mock::new_test_ext(), crate::mock::Runtime);
@@ -16,16 +16,22 @@
 
 use frame_support::{
 	pallet_prelude::*,
-	sp_runtime::traits::CheckedSub,
-	traits::{Currency, Get},
+	sp_runtime::traits::{CheckedSub, IdentifyAccount, Verify},
+	traits::{Currency, Get, ReservableCurrency},
 };
 use frame_system::pallet_prelude::*;
-use melo_core_primitives::{config::PRE_CELL_LEADING_ZEROS, traits::CommitmentFromPosition};
-use melo_proof_of_space::{Cell, FarmerId, PreCell, Solution};
+use melo_core_primitives::{
+	config::{FIELD_ELEMENTS_PER_SEGMENT, PRE_CELL_LEADING_ZEROS},
+	traits::{ChallengeSource, CommitmentFromPosition, RewardPayout},
+};
+use melo_proof_of_space::{
+	assignment::is_assigned, Cell, CompactSolution, FarmerId, PlotHashing, PreCell, Solution, H256,
+};
 use sp_std::prelude::*;
 
 pub use pallet::*;
 
+pub mod migrations;
 pub mod weights;
 pub use weights::*;
 
@@ -42,7 +48,11 @@ pub mod pallet {
 
 	use super::*;
 
+	/// The storage version of the pallet, see [`crate::migrations`].
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
 	#[pallet::pallet]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	#[pallet::config]
@@ -57,15 +67,46 @@ pub mod pallet {
         type CommitmentFromPosition: CommitmentFromPosition<BlockNumber = Self::BlockNumber>;
 
         /// Defines the currency type used for handling balances.
-        type Currency: Currency<Self::AccountId>;
+        type Currency: ReservableCurrency<Self::AccountId>;
 
         /// The fixed reward amount for successful claims.
         #[pallet::constant]
         type RewardAmount: Get<BalanceOf<Self>>;
 
+        /// Splits the reward of a claim between the winner and the accounts it shares it with,
+        /// such as the operator of its farming pool.
+        type RewardPayout: RewardPayout<Self::AccountId, BalanceOf<Self>>;
+
         /// Maximum number of claimants allowed per block.
         #[pallet::constant]
         type MaxClaimantsPerBlock: Get<u32>;
+
+        /// The base priority of unsigned claims, increased by the quality of the solution.
+        #[pallet::constant]
+        type SolutionUnsignedPriority: Get<TransactionPriority>;
+
+        /// The signature a reward account binds its farmer ID with.
+        type BindingSignature: Verify<Signer = Self::BindingSigner> + Parameter;
+
+        /// The signer of a binding signature, identifying the reward account.
+        type BindingSigner: IdentifyAccount<AccountId = Self::AccountId>;
+
+        /// The number of blocks during which the claims of a rotated-away reward account are
+        /// credited to the account its farmer ID was handed over to.
+        #[pallet::constant]
+        type RotationGracePeriod: Get<Self::BlockNumber>;
+
+        /// The amount reserved from a reward account when it binds a farmer ID, which can be
+        /// slashed for the misbehaviour of the ID.
+        #[pallet::constant]
+        type FarmerBond: Get<BalanceOf<Self>>;
+
+        /// Provides the epoch randomness and the slot the challenge of each block is derived
+        /// from.
+        type ChallengeSource: ChallengeSource;
+
+        /// The hashing scheme of the plots solutions are found in.
+        type PlotHashing: Get<PlotHashing>;
 	}
 
 	#[pallet::storage]
@@ -78,11 +119,57 @@ pub mod pallet {
 		ValueQuery,
 	>;
 
+	/// The farmer ID bound to each reward account.
+	#[pallet::storage]
+	#[pallet::getter(fn farmer_binding)]
+	pub type FarmerBindings<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, FarmerId, OptionQuery>;
+
+	/// The reward account each farmer ID is bound to.
+	#[pallet::storage]
+	#[pallet::getter(fn farmer_owner)]
+	pub type FarmerOwners<T: Config> =
+		StorageMap<_, Blake2_128Concat, FarmerId, T::AccountId, OptionQuery>;
+
+	/// The account the bond of each farmer ID was reserved from, with the reserved amount. The
+	/// bond stays with that account when the ID is handed over to another one.
+	#[pallet::storage]
+	#[pallet::getter(fn farmer_bond)]
+	pub type FarmerBonds<T: Config> =
+		StorageMap<_, Blake2_128Concat, FarmerId, (T::AccountId, BalanceOf<T>), OptionQuery>;
+
+	/// The number of times each farmer ID was handed over to another reward account, signed
+	/// along with each rotation.
+	#[pallet::storage]
+	#[pallet::getter(fn rotation_nonce)]
+	pub type RotationNonces<T: Config> = StorageMap<_, Blake2_128Concat, FarmerId, u32, ValueQuery>;
+
+	/// The account each rotated-away reward account handed its farmer ID over to, with the last
+	/// block of the grace period during which its claims are credited to the new account.
+	#[pallet::storage]
+	#[pallet::getter(fn key_rotation)]
+	pub type KeyRotations<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, (T::AccountId, T::BlockNumber), OptionQuery>;
+
+	/// The proof-of-space challenge of the last two blocks, derived from the epoch randomness and
+	/// the slot of each block. The solutions claimed in a block are found for the challenge of
+	/// its parent.
+	#[pallet::storage]
+	#[pallet::getter(fn challenge)]
+	pub type Challenges<T: Config> =
+		StorageMap<_, Twox64Concat, T::BlockNumber, T::Hash, OptionQuery>;
+
 	#[pallet::event]
 	#[pallet::generate_deposit(pub(super) fn deposit_event)]
 	pub enum Event<T: Config> {
 		/// Event emitted when a reward is claimed.
 		RewardClaimed(T::AccountId, BalanceOf<T>),
+		/// Event emitted when a farmer ID is bound to a reward account.
+		FarmerRegistered(T::AccountId, FarmerId),
+		/// Event emitted when a farmer ID is handed over from a reward account to another.
+		FarmerKeyRotated(T::AccountId, T::AccountId, FarmerId),
+		/// Event emitted when a part of the reward claimed by an account is paid to another.
+		RewardShared(T::AccountId, T::AccountId, BalanceOf<T>),
 	}
 
 	#[pallet::error]
@@ -101,6 +188,34 @@ pub mod pallet {
         StorageLimitReached,
         /// Error for underflow in block number calculations.
         BlockNumberUnderflow,
+        /// Error when the claimant has not bound a farmer ID.
+        FarmerNotRegistered,
+        /// Error when the reward account has already bound a farmer ID.
+        AlreadyRegistered,
+        /// Error when the farmer ID is bound to another reward account.
+        FarmerIdInUse,
+        /// Error when the reward account can not reserve the bond of a farmer ID.
+        InsufficientBond,
+        /// Error when the binding signature does not match the reward account.
+        InvalidBindingSignature,
+        /// Error when the rotation signature does not match the current reward account and
+        /// rotation nonce.
+        InvalidRotationSignature,
+        /// Error when the challenge of the block the solution was found for is not stored.
+        ChallengeNotFound,
+        /// Error when a winning cell is out of the pieces assigned to the farmer.
+        UnassignedPiece,
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(n: BlockNumberFor<T>) -> Weight {
+			Challenges::<T>::insert(n, Self::current_challenge());
+			if let Some(stale) = CheckedSub::checked_sub(&n, &T::BlockNumber::from(2u32)) {
+				Challenges::<T>::remove(stale);
+			}
+			T::WeightInfo::on_initialize()
+		}
 	}
 
 	#[pallet::call]
@@ -108,7 +223,11 @@ pub mod pallet {
 		/// Claim a reward for providing a valid solution.
         /// This function involves verifying the solution and rewarding the claimant.
 		#[pallet::call_index(0)]
-		#[pallet::weight(T::WeightInfo::claim())]
+		#[pallet::weight(Pallet::<T>::claim_weight(&[
+			pre_cell.seg.size(),
+			win_cell_left.seg.size(),
+			win_cell_right.seg.size(),
+		]))]
 		#[allow(clippy::large_enum_variant)]
 		pub fn claim(
 			origin: OriginFor<T>,
@@ -117,79 +236,304 @@ pub mod pallet {
 			win_cell_right: Box<Cell<BlockNumberFor<T>>>,
 		) -> DispatchResultWithPostInfo {
 			let who = ensure_signed(origin)?;
-			let now = <frame_system::Pallet<T>>::block_number();
+			Self::do_claim(who, &pre_cell, &win_cell_left, &win_cell_right)?;
+			Ok(().into())
+		}
 
-			let mut claimants = ClaimantsForBlock::<T>::get(now);
+		/// Claim a reward for `farmer` with a compact solution, without a signature.
+		/// The solution can only be produced by `farmer`, so the claim needs no authentication.
+		/// Its priority in the transaction pool grows with the quality of the solution.
+		#[pallet::call_index(1)]
+		#[pallet::weight(Pallet::<T>::claim_weight(&[
+			solution.pre_cell.seg.size(),
+			solution.win_cell_left.seg.size(),
+			solution.right_seg.size(),
+		]))]
+		pub fn claim_unsigned(
+			origin: OriginFor<T>,
+			farmer: T::AccountId,
+			solution: Box<CompactSolution<BlockNumberFor<T>>>,
+		) -> DispatchResultWithPostInfo {
+			ensure_none(origin)?;
+			let (pre_cell, win_cell_left, win_cell_right) = solution.into_cells();
+			Self::do_claim(farmer, &pre_cell, &win_cell_left, &win_cell_right)?;
+			Ok(().into())
+		}
+
+		/// Bind `farmer_id` to `reward_account`, which signs the binding message of the ID.
+		/// Each reward account binds a single farmer ID once, and rewards are only paid for
+		/// solutions of the bound ID. `FarmerBond` is reserved from `reward_account`, so that
+		/// farmer IDs are not free to grind. Any account can submit the binding.
+		#[pallet::call_index(2)]
+		#[pallet::weight(T::WeightInfo::register_farmer())]
+		pub fn register_farmer(
+			origin: OriginFor<T>,
+			reward_account: T::AccountId,
+			farmer_id: FarmerId,
+			signature: T::BindingSignature,
+		) -> DispatchResultWithPostInfo {
+			ensure_signed(origin)?;
 			ensure!(
-				claimants.len() < T::MaxClaimantsPerBlock::get() as usize,
-				Error::<T>::MaxClaimantsReached
+				!FarmerBindings::<T>::contains_key(&reward_account),
+				Error::<T>::AlreadyRegistered
+			);
+			ensure!(!FarmerOwners::<T>::contains_key(&farmer_id), Error::<T>::FarmerIdInUse);
+			ensure!(
+				signature.verify(&farmer_id.binding_message()[..], &reward_account),
+				Error::<T>::InvalidBindingSignature
 			);
-			ensure!(!claimants.contains(&who), Error::<T>::AlreadyClaimed);
 
-			let pre_block_num = CheckedSub::checked_sub(
-				&now,
-				&T::BlockNumber::from(1u32),
-			)
-			.ok_or(Error::<T>::BlockNumberUnderflow)?;
+			let bond = T::FarmerBond::get();
+			T::Currency::reserve(&reward_account, bond).map_err(|_| Error::<T>::InsufficientBond)?;
 
-			let pre_block_hash = <frame_system::Pallet<T>>::block_hash(pre_block_num);
-			let win_block_hash_left =
-				<frame_system::Pallet<T>>::block_hash(win_cell_left.metadata.block_number());
-			let win_block_hash_right =
-				<frame_system::Pallet<T>>::block_hash(win_cell_right.metadata.block_number());
-
-			let left_block_num = win_cell_left.metadata.block_number();
-			let right_block_num = win_cell_right.metadata.block_number();
-
-			// Get commitments from positions
-			let pre_commit =
-				T::CommitmentFromPosition::commitments(pre_block_num, &pre_cell.seg.position)
-					.ok_or(Error::<T>::PreCommitNotFound)?;
-
-			let left_commit =
-				T::CommitmentFromPosition::commitments(left_block_num, &win_cell_left.seg.position)
-					.ok_or(Error::<T>::WinCommitNotFound)?;
-
-			let right_commit = T::CommitmentFromPosition::commitments(
-				right_block_num,
-				&win_cell_right.seg.position,
-			)
-			.ok_or(Error::<T>::WinCommitNotFound)?;
-
-			let farmer_id = FarmerId::new::<T::AccountId>(who.clone());
-
-			let solution = Solution::<T::Hash, BlockNumberFor<T>>::new(
-				&pre_block_hash,
-				&farmer_id,
-				&pre_cell,
-				&win_cell_left,
-				&win_cell_right,
-			);
+			FarmerBonds::<T>::insert(&farmer_id, (&reward_account, bond));
+			FarmerBindings::<T>::insert(&reward_account, &farmer_id);
+			FarmerOwners::<T>::insert(&farmer_id, &reward_account);
+
+			Self::deposit_event(Event::FarmerRegistered(reward_account, farmer_id));
+			Ok(().into())
+		}
 
+		/// Hand the farmer ID bound to `old_account` over to `new_account`. `old_account` signs
+		/// the rotation message of its ID naming `new_account`, with the current rotation nonce
+		/// of the ID, which is then incremented.
+		///
+		/// The farmer ID, and so the plots, stay the same. Rewards are only paid to
+		/// `new_account` from now on, and the claims of `old_account` are credited to
+		/// `new_account` during `RotationGracePeriod` blocks, so that the claims already in
+		/// flight are not lost. The bond of the ID stays reserved from the account it was
+		/// reserved from. Any account can submit the rotation.
+		#[pallet::call_index(3)]
+		#[pallet::weight(T::WeightInfo::rotate_farmer_key())]
+		pub fn rotate_farmer_key(
+			origin: OriginFor<T>,
+			old_account: T::AccountId,
+			new_account: T::AccountId,
+			signature: T::BindingSignature,
+		) -> DispatchResultWithPostInfo {
+			ensure_signed(origin)?;
+			let farmer_id =
+				FarmerBindings::<T>::get(&old_account).ok_or(Error::<T>::FarmerNotRegistered)?;
 			ensure!(
-				solution.verify(
-					&pre_commit,
-					&left_commit,
-					&right_commit,
-					&win_block_hash_left,
-					&win_block_hash_right,
-					PRE_CELL_LEADING_ZEROS,
-					1,
-				),
-				Error::<T>::InvalidSolution
+				!FarmerBindings::<T>::contains_key(&new_account),
+				Error::<T>::AlreadyRegistered
+			);
+			let nonce = RotationNonces::<T>::get(&farmer_id);
+			ensure!(
+				signature
+					.verify(&farmer_id.rotation_message(&new_account, nonce)[..], &old_account),
+				Error::<T>::InvalidRotationSignature
 			);
 
-			claimants.try_push(who.clone()).map_err(|_| Error::<T>::StorageLimitReached)?;
-			ClaimantsForBlock::<T>::insert(now, claimants);
-
-			let reward = T::RewardAmount::get();
-			T::Currency::deposit_creating(&who, reward);
+			let grace_end = <frame_system::Pallet<T>>::block_number()
+				.saturating_add(T::RotationGracePeriod::get());
 
-			Self::deposit_event(Event::RewardClaimed(who, reward));
+			FarmerBindings::<T>::remove(&old_account);
+			FarmerBindings::<T>::insert(&new_account, &farmer_id);
+			FarmerOwners::<T>::insert(&farmer_id, &new_account);
+			KeyRotations::<T>::insert(&old_account, (&new_account, grace_end));
+			RotationNonces::<T>::insert(&farmer_id, nonce.saturating_add(1));
 
+			Self::deposit_event(Event::FarmerKeyRotated(old_account, new_account, farmer_id));
 			Ok(().into())
 		}
 	}
+
+	pub(crate) const INVALID_SOLUTION: u8 = 20;
+	pub(crate) const FARMER_NOT_REGISTERED: u8 = 21;
+
+	#[pallet::validate_unsigned]
+	impl<T: Config> ValidateUnsigned for Pallet<T> {
+		type Call = Call<T>;
+
+		fn validate_unsigned(_source: TransactionSource, call: &Self::Call) -> TransactionValidity {
+			if let Call::claim_unsigned { farmer, solution } = call {
+				let now = <frame_system::Pallet<T>>::block_number();
+				let (pre_cell, win_cell_left, win_cell_right) =
+					solution.as_ref().clone().into_cells();
+				let farmer = Self::claimant(farmer.clone(), now);
+
+				let quality = match Self::verify_claim(
+					&farmer,
+					now,
+					&pre_cell,
+					&win_cell_left,
+					&win_cell_right,
+				) {
+					Ok(quality) => quality,
+					Err(Error::<T>::AlreadyClaimed) => return InvalidTransaction::Stale.into(),
+					Err(Error::<T>::MaxClaimantsReached) =>
+						return InvalidTransaction::ExhaustsResources.into(),
+					Err(Error::<T>::FarmerNotRegistered) =>
+						return InvalidTransaction::Custom(FARMER_NOT_REGISTERED).into(),
+					Err(_) => return InvalidTransaction::Custom(INVALID_SOLUTION).into(),
+				};
+
+				ValidTransaction::with_tag_prefix("FarmersFortune")
+					.priority(T::SolutionUnsignedPriority::get().saturating_add(quality.into()))
+					.and_provides((now, farmer))
+					.longevity(1)
+					.propagate(true)
+					.build()
+			} else {
+				InvalidTransaction::Call.into()
+			}
+		}
+	}
 }
 
-impl<T: Config> Pallet<T> {}
+impl<T: Config> Pallet<T> {
+	/// Returns the weight of a claim whose solution holds segments of `segment_lens` field
+	/// elements.
+	///
+	/// The `claim` benchmark verifies the three segments of a solution with segments of
+	/// `FIELD_ELEMENTS_PER_SEGMENT` field elements, the extra cost of verifying longer segments is
+	/// measured by the `verify_segment` benchmark.
+	pub fn claim_weight(segment_lens: &[usize]) -> Weight {
+		let segment_len = segment_lens.iter().copied().max().unwrap_or_default();
+		let extra = T::WeightInfo::verify_segment(segment_len as u32)
+			.saturating_sub(T::WeightInfo::verify_segment(FIELD_ELEMENTS_PER_SEGMENT as u32));
+
+		T::WeightInfo::claim().saturating_add(extra.saturating_mul(segment_lens.len() as u64))
+	}
+
+	/// Returns the challenge derived from the current epoch randomness and slot.
+	pub(crate) fn current_challenge() -> T::Hash {
+		Solution::<T::Hash, BlockNumberFor<T>>::derive_challenge::<T::Hashing>(
+			&T::ChallengeSource::epoch_randomness(),
+			T::ChallengeSource::current_slot(),
+		)
+	}
+
+	/// Returns the account credited with the claims of `who` at block `now`.
+	///
+	/// A reward account whose farmer ID was rotated away is credited to the new account during
+	/// the grace period of the rotation, unless it bound another farmer ID since.
+	pub fn claimant(who: T::AccountId, now: BlockNumberFor<T>) -> T::AccountId {
+		if FarmerBindings::<T>::contains_key(&who) {
+			return who
+		}
+		match KeyRotations::<T>::get(&who) {
+			Some((new_account, grace_end)) if now <= grace_end => new_account,
+			_ => who,
+		}
+	}
+
+	/// Checks that `who` may claim a reward at block `now` with the given solution cells.
+	/// The solution must have been found with the farmer ID bound to `who`.
+	///
+	/// Returns the quality of the solution.
+	fn verify_claim(
+		who: &T::AccountId,
+		now: BlockNumberFor<T>,
+		pre_cell: &PreCell,
+		win_cell_left: &Cell<BlockNumberFor<T>>,
+		win_cell_right: &Cell<BlockNumberFor<T>>,
+	) -> Result<u32, Error<T>> {
+		let claimants = ClaimantsForBlock::<T>::get(now);
+		ensure!(
+			claimants.len() < T::MaxClaimantsPerBlock::get() as usize,
+			Error::<T>::MaxClaimantsReached
+		);
+		ensure!(!claimants.contains(who), Error::<T>::AlreadyClaimed);
+
+		let farmer_id = FarmerBindings::<T>::get(who).ok_or(Error::<T>::FarmerNotRegistered)?;
+
+		let pre_block_num = CheckedSub::checked_sub(&now, &T::BlockNumber::from(1u32))
+			.ok_or(Error::<T>::BlockNumberUnderflow)?;
+
+		let challenge = Challenges::<T>::get(pre_block_num).ok_or(Error::<T>::ChallengeNotFound)?;
+		let win_block_hash_left =
+			<frame_system::Pallet<T>>::block_hash(win_cell_left.metadata.block_number());
+		let win_block_hash_right =
+			<frame_system::Pallet<T>>::block_hash(win_cell_right.metadata.block_number());
+
+		let left_block_num = win_cell_left.metadata.block_number();
+		let right_block_num = win_cell_right.metadata.block_number();
+
+		// Get commitments from positions
+		let pre_commit =
+			T::CommitmentFromPosition::commitments(pre_block_num, &pre_cell.seg.position)
+				.ok_or(Error::<T>::PreCommitNotFound)?;
+
+		let left_commit =
+			T::CommitmentFromPosition::commitments(left_block_num, &win_cell_left.seg.position)
+				.ok_or(Error::<T>::WinCommitNotFound)?;
+
+		let right_commit =
+			T::CommitmentFromPosition::commitments(right_block_num, &win_cell_right.seg.position)
+				.ok_or(Error::<T>::WinCommitNotFound)?;
+
+		// Farmers only store the pieces assigned to them, see `melo_proof_of_space::assignment`.
+		for (block_num, block_hash, cell) in [
+			(left_block_num, &win_block_hash_left, win_cell_left),
+			(right_block_num, &win_block_hash_right, win_cell_right),
+		] {
+			let block_hash =
+				H256::decode(&mut block_hash.as_ref()).map_err(|_| Error::<T>::InvalidSolution)?;
+			ensure!(
+				is_assigned(
+					&farmer_id,
+					&block_hash,
+					&cell.metadata.piece_metadata.pos,
+					T::CommitmentFromPosition::extended_rows(block_num),
+				),
+				Error::<T>::UnassignedPiece
+			);
+		}
+
+		let solution = Solution::<T::Hash, BlockNumberFor<T>>::new(
+			&challenge,
+			&farmer_id,
+			pre_cell,
+			win_cell_left,
+			win_cell_right,
+		)
+		.with_hashing(T::PlotHashing::get());
+
+		ensure!(
+			solution.verify(
+				&pre_commit,
+				&left_commit,
+				&right_commit,
+				&win_block_hash_left,
+				&win_block_hash_right,
+				PRE_CELL_LEADING_ZEROS,
+				1,
+			),
+			Error::<T>::InvalidSolution
+		);
+
+		Ok(solution.quality(PRE_CELL_LEADING_ZEROS))
+	}
+
+	/// Verifies the solution cells and rewards `who`, or the account its farmer ID was handed
+	/// over to, sharing the reward as `RewardPayout` splits it.
+	fn do_claim(
+		who: T::AccountId,
+		pre_cell: &PreCell,
+		win_cell_left: &Cell<BlockNumberFor<T>>,
+		win_cell_right: &Cell<BlockNumberFor<T>>,
+	) -> DispatchResult {
+		let now = <frame_system::Pallet<T>>::block_number();
+		let who = Self::claimant(who, now);
+
+		Self::verify_claim(&who, now, pre_cell, win_cell_left, win_cell_right)?;
+
+		ClaimantsForBlock::<T>::try_mutate(now, |claimants| {
+			claimants.try_push(who.clone()).map_err(|_| Error::<T>::StorageLimitReached)
+		})?;
+
+		let reward = T::RewardAmount::get();
+		for (account, amount) in T::RewardPayout::payout(&who, reward) {
+			T::Currency::deposit_creating(&account, amount);
+			if account != who {
+				Self::deposit_event(Event::RewardShared(who.clone(), account, amount));
+			}
+		}
+
+		Self::deposit_event(Event::RewardClaimed(who, reward));
+
+		Ok(())
+	}
+}
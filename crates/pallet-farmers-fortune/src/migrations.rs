@@ -0,0 +1,94 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Storage migrations.
+//!
+//! The storage version of the pallet is bumped with each change of the layout of its storage,
+//! e.g. of the claims or the farmer bindings, along with a migration from the previous version
+//! which moves the existing data to the new layout, or stores the data the new one relies on.
+//! The runtime lists the migrations in its `Migrations`, which run on the first block after an
+//! upgrade. Each migration only applies to the version it migrates from.
+use super::*;
+use frame_support::traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion};
+use sp_std::marker::PhantomData;
+
+const LOG_TARGET: &str = "runtime::farmers-fortune";
+
+/// The migration to the first storage version.
+pub mod v1 {
+	use super::*;
+
+	/// Sets the storage version of the pallet, which had none, to 1.
+	///
+	/// Before version 1, the claims were checked against the block hashes and no challenge was
+	/// stored, while the claims of a block are now checked against the challenge of its parent.
+	/// The migration stores the challenge of the parent of the upgrade block, so that solutions
+	/// can be claimed from that block on. It runs before the hooks of the block, when the epoch
+	/// randomness and the slot are still those of the parent.
+	///
+	/// Under try-runtime, each farmer binding is checked to match the owner of its farmer ID
+	/// after the migration.
+	pub struct MigrateToV1<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			let on_chain = Pallet::<T>::on_chain_storage_version();
+			if on_chain != 0 {
+				log::info!(target: LOG_TARGET, "Skipping the migration to v1 from {:?}", on_chain);
+				return T::DbWeight::get().reads(1)
+			}
+
+			let now = <frame_system::Pallet<T>>::block_number();
+			if let Some(parent) = CheckedSub::checked_sub(&now, &T::BlockNumber::from(1u32)) {
+				Challenges::<T>::insert(parent, Pallet::<T>::current_challenge());
+			}
+
+			StorageVersion::new(1).put::<Pallet<T>>();
+			log::info!(target: LOG_TARGET, "Migrated the storage to v1");
+			T::DbWeight::get().reads_writes(4, 2)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+			Ok((FarmerBindings::<T>::iter_keys().count() as u32).encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), &'static str> {
+			let bindings = u32::decode(&mut &state[..]).map_err(|_| "Invalid pre-upgrade state")?;
+			ensure!(
+				Pallet::<T>::on_chain_storage_version() >= 1,
+				"The storage version was not set to 1"
+			);
+			ensure!(
+				FarmerBindings::<T>::iter().count() as u32 == bindings,
+				"Some farmer bindings do not decode"
+			);
+			for (account, farmer_id) in FarmerBindings::<T>::iter() {
+				ensure!(
+					FarmerOwners::<T>::get(farmer_id).as_ref() == Some(&account),
+					"A farmer binding does not match the owner of its farmer ID"
+				);
+			}
+			let now = <frame_system::Pallet<T>>::block_number();
+			if let Some(parent) = CheckedSub::checked_sub(&now, &T::BlockNumber::from(1u32)) {
+				ensure!(
+					Challenges::<T>::contains_key(parent),
+					"The challenge of the parent block was not stored"
+				);
+			}
+			Ok(())
+		}
+	}
+}
@@ -20,16 +20,17 @@
 #![cfg(test)]
 
 use frame_support::{
-	parameter_types,
+	assert_ok, parameter_types,
 	traits::{ConstU32, ConstU64},
 };
 use lazy_static::lazy_static;
-use melo_core_primitives::traits::CommitmentFromPosition;
+use melo_core_primitives::traits::{ChallengeSource, CommitmentFromPosition};
 use melo_das_primitives::{KZGCommitment, Position};
+use melo_proof_of_space::assignment::ASSIGNED_ROWS;
 use pallet_balances::AccountData;
 use sp_core::H256;
 use sp_runtime::{
-	testing::Header,
+	testing::{Header, TestSignature, UintAuthorityId},
 	traits::{BlakeTwo256, IdentityLookup},
 };
 use sp_std::sync::RwLock;
@@ -52,13 +53,14 @@ frame_support::construct_runtime!(
 	{
 		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
 		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
-		FarmersFortune: melo_farmers_fortune::{Pallet, Call, Storage, Event<T>},
+		FarmersFortune: melo_farmers_fortune::{Pallet, Call, Storage, Event<T>, ValidateUnsigned},
 	}
 );
 
 lazy_static! {
 	static ref MOCK_COMMITMENTS: RwLock<HashMap<(u64, Position), KZGCommitment>> =
 		RwLock::new(HashMap::new());
+	static ref MOCK_ROWS: RwLock<HashMap<u64, u32>> = RwLock::new(HashMap::new());
 }
 
 pub struct MockCommitmentFromPosition;
@@ -73,6 +75,21 @@ impl CommitmentFromPosition for MockCommitmentFromPosition {
 			.get(&(block_number, position.clone()))
 			.cloned()
 	}
+
+	/// Blocks have `ASSIGNED_ROWS` extended rows unless set otherwise, so that every row is
+	/// assigned to every farmer.
+	fn extended_rows(block_number: Self::BlockNumber) -> u32 {
+		MOCK_ROWS
+			.read()
+			.expect("RwLock is poisoned")
+			.get(&block_number)
+			.copied()
+			.unwrap_or(ASSIGNED_ROWS)
+	}
+}
+
+pub fn set_mock_rows(block_number: u64, rows: u32) {
+	MOCK_ROWS.write().expect("RwLock is poisoned").insert(block_number, rows);
 }
 
 pub fn insert_mock_commitment(block_number: u64, position: Position, commitment: KZGCommitment) {
@@ -82,6 +99,21 @@ pub fn insert_mock_commitment(block_number: u64, position: Position, commitment:
 		.insert((block_number, position), commitment);
 }
 
+pub const MOCK_EPOCH_RANDOMNESS: [u8; 32] = [7; 32];
+
+pub struct MockChallengeSource;
+
+/// The slot of each block is its number.
+impl ChallengeSource for MockChallengeSource {
+	fn epoch_randomness() -> [u8; 32] {
+		MOCK_EPOCH_RANDOMNESS
+	}
+
+	fn current_slot() -> u64 {
+		System::block_number()
+	}
+}
+
 parameter_types! {
 	pub const ExistentialDeposit: u64 = 1;
 }
@@ -131,6 +163,7 @@ impl frame_system::Config for Runtime {
 
 parameter_types! {
 	pub const RewardAmount: Balance = 1000;
+	pub const FarmerBond: Balance = 500;
 }
 
 impl Config for Runtime {
@@ -139,7 +172,29 @@ impl Config for Runtime {
 	type CommitmentFromPosition = MockCommitmentFromPosition;
 	type Currency = Balances;
 	type RewardAmount = RewardAmount;
+	type RewardPayout = ();
 	type MaxClaimantsPerBlock = ConstU32<2>;
+	type SolutionUnsignedPriority = ConstU64<100>;
+	type BindingSignature = TestSignature;
+	type BindingSigner = UintAuthorityId;
+	type RotationGracePeriod = ConstU64<10>;
+	type FarmerBond = FarmerBond;
+	type ChallengeSource = MockChallengeSource;
+	type PlotHashing = ();
+}
+
+/// Binds the farmer ID derived from `account` to it, as the farmer client does. The account is
+/// funded with the bond of the ID and the existential deposit.
+pub fn register_farmer(account: u64) {
+	Balances::make_free_balance_be(&account, FarmerBond::get() + ExistentialDeposit::get());
+	let farmer_id = FarmerId::new(account);
+	let signature = TestSignature(account, farmer_id.binding_message());
+	assert_ok!(FarmersFortune::register_farmer(
+		RuntimeOrigin::signed(account),
+		account,
+		farmer_id,
+		signature
+	));
 }
 
 pub fn new_test_ext() -> sp_io::TestExternalities {
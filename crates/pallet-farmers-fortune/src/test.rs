@@ -17,6 +17,11 @@ use super::*;
 use crate as melo_farmers_fortune;
 use crate::mock::*;
 use melo_proof_of_space::{mock::*, CellMetadata, PieceMetadata, PiecePosition, PreCell};
+use sp_runtime::{
+	testing::TestSignature,
+	traits::BlakeTwo256,
+	transaction_validity::{InvalidTransaction, TransactionSource},
+};
 
 use frame_support::{assert_noop, assert_ok};
 
@@ -26,8 +31,9 @@ use sp_core::H256;
 #[test]
 fn claim_reward_should_work() {
 	new_test_ext().execute_with(|| {
+		register_farmer(0);
 		System::set_block_number(6);
-		<frame_system::BlockHash<Runtime>>::insert(5, H256::from(BLOCK_HASH1));
+		Challenges::<Runtime>::insert(5, H256::from(BLOCK_HASH1));
 		<frame_system::BlockHash<Runtime>>::insert(3, H256::from(BLOCK_HASH1));
 
 		let segs = get_mock_row(&BLS_SCALAR11, &BLS_SCALAR12, 0, &PROOF_11, &PROOF_12, 16);
@@ -101,8 +107,9 @@ fn claim_reward_should_work() {
 #[test]
 fn claim_reward_works_for_different_farmer_ids() {
 	new_test_ext().execute_with(|| {
+		register_farmer(0);
 		System::set_block_number(16);
-		<frame_system::BlockHash<Runtime>>::insert(15, H256::from(BLOCK_HASH1));
+		Challenges::<Runtime>::insert(15, H256::from(BLOCK_HASH1));
 		<frame_system::BlockHash<Runtime>>::insert(13, H256::from(BLOCK_HASH1));
 
 		let segs = get_mock_row(&BLS_SCALAR11, &BLS_SCALAR12, 0, &PROOF_11, &PROOF_12, 16);
@@ -137,3 +144,496 @@ fn claim_reward_works_for_different_farmer_ids() {
 		);
 	});
 }
+
+#[test]
+fn claim_unsigned_should_work() {
+	new_test_ext().execute_with(|| {
+		register_farmer(0);
+		System::set_block_number(26);
+		Challenges::<Runtime>::insert(25, H256::from(BLOCK_HASH1));
+		<frame_system::BlockHash<Runtime>>::insert(23, H256::from(BLOCK_HASH1));
+
+		let segs = get_mock_row(&BLS_SCALAR11, &BLS_SCALAR12, 0, &PROOF_11, &PROOF_12, 16);
+		let commit = KZGCommitment::try_from(COMMIT1).unwrap();
+
+		let pre_cell = PreCell::new(PiecePosition::Row(0), segs[0].clone());
+		let piece_metadata = PieceMetadata::new(23, PiecePosition::Row(0));
+
+		let win_cell_left =
+			Cell::new(CellMetadata::new(piece_metadata.clone(), 0), segs[0].clone());
+		let win_cell_right = Cell::new(CellMetadata::new(piece_metadata, 1), segs[1].clone());
+
+		let solution = CompactSolution::from_cells(&pre_cell, &win_cell_left, &win_cell_right)
+			.expect("Cells are a pair");
+
+		insert_mock_commitment(25, Position { x: 0, y: 0 }, commit);
+		insert_mock_commitment(23, Position { x: 0, y: 0 }, commit);
+		insert_mock_commitment(23, Position { x: 1, y: 0 }, commit);
+
+		let call = melo_farmers_fortune::Call::claim_unsigned {
+			farmer: 0,
+			solution: Box::new(solution.clone()),
+		};
+
+		let quality = Solution::<H256, u64>::new(
+			&H256::from(BLOCK_HASH1),
+			&FarmerId::new(0u64),
+			&pre_cell,
+			&win_cell_left,
+			&win_cell_right,
+		)
+		.quality(PRE_CELL_LEADING_ZEROS);
+
+		let valid = FarmersFortune::validate_unsigned(TransactionSource::External, &call).unwrap();
+		assert_eq!(valid.priority, 100 + quality as u64);
+
+		assert_noop!(
+			FarmersFortune::claim_unsigned(RuntimeOrigin::signed(0), 0, Box::new(solution.clone())),
+			sp_runtime::DispatchError::BadOrigin
+		);
+
+		assert_ok!(FarmersFortune::claim_unsigned(RuntimeOrigin::none(), 0, Box::new(solution)));
+		assert_eq!(Balances::free_balance(0), ExistentialDeposit::get() + RewardAmount::get());
+
+		assert_eq!(
+			FarmersFortune::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Stale.into()
+		);
+	});
+}
+
+#[test]
+fn claim_unsigned_rejects_invalid_solution() {
+	new_test_ext().execute_with(|| {
+		register_farmer(0);
+		System::set_block_number(36);
+		Challenges::<Runtime>::insert(35, H256::from(BLOCK_HASH1));
+		<frame_system::BlockHash<Runtime>>::insert(33, H256::from(BLOCK_HASH1));
+
+		let segs = get_mock_row(&BLS_SCALAR11, &BLS_SCALAR12, 0, &PROOF_11, &PROOF_12, 16);
+		let wrong_commit = KZGCommitment::try_from(COMMIT2).unwrap();
+
+		let pre_cell = PreCell::new(PiecePosition::Row(0), segs[0].clone());
+		let piece_metadata = PieceMetadata::new(33, PiecePosition::Row(0));
+
+		let win_cell_left =
+			Cell::new(CellMetadata::new(piece_metadata.clone(), 0), segs[0].clone());
+		let win_cell_right = Cell::new(CellMetadata::new(piece_metadata, 1), segs[1].clone());
+
+		let solution = CompactSolution::from_cells(&pre_cell, &win_cell_left, &win_cell_right)
+			.expect("Cells are a pair");
+
+		insert_mock_commitment(35, Position { x: 0, y: 0 }, wrong_commit);
+		insert_mock_commitment(33, Position { x: 0, y: 0 }, wrong_commit);
+		insert_mock_commitment(33, Position { x: 1, y: 0 }, wrong_commit);
+
+		let call =
+			melo_farmers_fortune::Call::claim_unsigned { farmer: 0, solution: Box::new(solution) };
+
+		assert_eq!(
+			FarmersFortune::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Custom(INVALID_SOLUTION).into()
+		);
+	});
+}
+
+#[test]
+fn register_farmer_should_work() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let farmer_id = FarmerId::new(1u64);
+
+		assert_noop!(
+			FarmersFortune::register_farmer(
+				RuntimeOrigin::signed(2),
+				1,
+				farmer_id.clone(),
+				TestSignature(2, farmer_id.binding_message())
+			),
+			melo_farmers_fortune::Error::<Runtime>::InvalidBindingSignature
+		);
+		assert_noop!(
+			FarmersFortune::register_farmer(
+				RuntimeOrigin::signed(2),
+				1,
+				farmer_id.clone(),
+				TestSignature(1, FarmerId::new(2u64).binding_message())
+			),
+			melo_farmers_fortune::Error::<Runtime>::InvalidBindingSignature
+		);
+
+		// The reward account must be able to reserve the bond.
+		Balances::make_free_balance_be(&1, FarmerBond::get());
+		assert_noop!(
+			FarmersFortune::register_farmer(
+				RuntimeOrigin::signed(2),
+				1,
+				farmer_id.clone(),
+				TestSignature(1, farmer_id.binding_message())
+			),
+			melo_farmers_fortune::Error::<Runtime>::InsufficientBond
+		);
+
+		// The binding can be submitted by any account.
+		Balances::make_free_balance_be(&1, FarmerBond::get() + ExistentialDeposit::get());
+		assert_ok!(FarmersFortune::register_farmer(
+			RuntimeOrigin::signed(2),
+			1,
+			farmer_id.clone(),
+			TestSignature(1, farmer_id.binding_message())
+		));
+		assert_eq!(FarmersFortune::farmer_binding(1), Some(farmer_id.clone()));
+		assert_eq!(FarmersFortune::farmer_owner(&farmer_id), Some(1));
+		assert_eq!(FarmersFortune::farmer_bond(&farmer_id), Some((1, FarmerBond::get())));
+		assert_eq!(Balances::reserved_balance(1), FarmerBond::get());
+		assert_eq!(Balances::free_balance(1), ExistentialDeposit::get());
+		System::assert_last_event(
+			melo_farmers_fortune::Event::FarmerRegistered(1, farmer_id.clone()).into(),
+		);
+
+		let other_id = FarmerId::new(3u64);
+		assert_noop!(
+			FarmersFortune::register_farmer(
+				RuntimeOrigin::signed(1),
+				1,
+				other_id.clone(),
+				TestSignature(1, other_id.binding_message())
+			),
+			melo_farmers_fortune::Error::<Runtime>::AlreadyRegistered
+		);
+		assert_noop!(
+			FarmersFortune::register_farmer(
+				RuntimeOrigin::signed(3),
+				3,
+				farmer_id.clone(),
+				TestSignature(3, farmer_id.binding_message())
+			),
+			melo_farmers_fortune::Error::<Runtime>::FarmerIdInUse
+		);
+	});
+}
+
+#[test]
+fn rotate_farmer_key_should_work() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let farmer_id = FarmerId::new(1u64);
+
+		assert_noop!(
+			FarmersFortune::rotate_farmer_key(
+				RuntimeOrigin::signed(3),
+				1,
+				2,
+				TestSignature(1, farmer_id.rotation_message(&2u64, 0))
+			),
+			melo_farmers_fortune::Error::<Runtime>::FarmerNotRegistered
+		);
+
+		register_farmer(1);
+		register_farmer(3);
+
+		// The new account must not hold a farmer ID already.
+		assert_noop!(
+			FarmersFortune::rotate_farmer_key(
+				RuntimeOrigin::signed(3),
+				1,
+				3,
+				TestSignature(1, farmer_id.rotation_message(&3u64, 0))
+			),
+			melo_farmers_fortune::Error::<Runtime>::AlreadyRegistered
+		);
+		// Only the current account can hand its farmer ID over, to the account it signed for.
+		assert_noop!(
+			FarmersFortune::rotate_farmer_key(
+				RuntimeOrigin::signed(3),
+				1,
+				2,
+				TestSignature(2, farmer_id.rotation_message(&2u64, 0))
+			),
+			melo_farmers_fortune::Error::<Runtime>::InvalidRotationSignature
+		);
+		assert_noop!(
+			FarmersFortune::rotate_farmer_key(
+				RuntimeOrigin::signed(3),
+				1,
+				2,
+				TestSignature(1, farmer_id.rotation_message(&4u64, 0))
+			),
+			melo_farmers_fortune::Error::<Runtime>::InvalidRotationSignature
+		);
+
+		assert_ok!(FarmersFortune::rotate_farmer_key(
+			RuntimeOrigin::signed(3),
+			1,
+			2,
+			TestSignature(1, farmer_id.rotation_message(&2u64, 0))
+		));
+		assert_eq!(FarmersFortune::farmer_binding(1), None);
+		assert_eq!(FarmersFortune::farmer_binding(2), Some(farmer_id.clone()));
+		assert_eq!(FarmersFortune::farmer_owner(&farmer_id), Some(2));
+		assert_eq!(FarmersFortune::key_rotation(1), Some((2, 11)));
+		assert_eq!(FarmersFortune::rotation_nonce(&farmer_id), 1);
+		System::assert_last_event(
+			melo_farmers_fortune::Event::FarmerKeyRotated(1, 2, farmer_id.clone()).into(),
+		);
+
+		assert_eq!(FarmersFortune::claimant(1, 11), 2);
+		assert_eq!(FarmersFortune::claimant(1, 12), 1);
+		assert_eq!(FarmersFortune::claimant(2, 12), 2);
+
+		// The bond stays reserved from the old account.
+		assert_eq!(FarmersFortune::farmer_bond(&farmer_id), Some((1, FarmerBond::get())));
+		assert_eq!(Balances::reserved_balance(1), FarmerBond::get());
+		assert_eq!(Balances::reserved_balance(2), 0);
+
+		// Once the ID is handed back, the first rotation cannot be replayed.
+		assert_ok!(FarmersFortune::rotate_farmer_key(
+			RuntimeOrigin::signed(3),
+			2,
+			1,
+			TestSignature(2, farmer_id.rotation_message(&1u64, 1))
+		));
+		assert_noop!(
+			FarmersFortune::rotate_farmer_key(
+				RuntimeOrigin::signed(3),
+				1,
+				2,
+				TestSignature(1, farmer_id.rotation_message(&2u64, 0))
+			),
+			melo_farmers_fortune::Error::<Runtime>::InvalidRotationSignature
+		);
+		assert_ok!(FarmersFortune::rotate_farmer_key(
+			RuntimeOrigin::signed(3),
+			1,
+			2,
+			TestSignature(1, farmer_id.rotation_message(&2u64, 2))
+		));
+		assert_eq!(FarmersFortune::rotation_nonce(&farmer_id), 3);
+
+		// The old account can bind a new farmer ID, its claims are then its own again.
+		let new_id = FarmerId::new(4u64);
+		Balances::make_free_balance_be(&1, FarmerBond::get() + ExistentialDeposit::get());
+		assert_ok!(FarmersFortune::register_farmer(
+			RuntimeOrigin::signed(1),
+			1,
+			new_id.clone(),
+			TestSignature(1, new_id.binding_message())
+		));
+		assert_eq!(FarmersFortune::claimant(1, 5), 1);
+	});
+}
+
+#[test]
+fn claims_of_rotated_account_are_credited_during_grace_period() {
+	new_test_ext().execute_with(|| {
+		register_farmer(0);
+		System::set_block_number(56);
+		Challenges::<Runtime>::insert(55, H256::from(BLOCK_HASH1));
+		<frame_system::BlockHash<Runtime>>::insert(53, H256::from(BLOCK_HASH1));
+
+		let segs = get_mock_row(&BLS_SCALAR11, &BLS_SCALAR12, 0, &PROOF_11, &PROOF_12, 16);
+		let commit = KZGCommitment::try_from(COMMIT1).unwrap();
+
+		let pre_cell = PreCell::new(PiecePosition::Row(0), segs[0].clone());
+		let piece_metadata = PieceMetadata::new(53, PiecePosition::Row(0));
+
+		let win_cell_left =
+			Cell::new(CellMetadata::new(piece_metadata.clone(), 0), segs[0].clone());
+		let win_cell_right = Cell::new(CellMetadata::new(piece_metadata, 1), segs[1].clone());
+
+		let solution = CompactSolution::from_cells(&pre_cell, &win_cell_left, &win_cell_right)
+			.expect("Cells are a pair");
+
+		insert_mock_commitment(55, Position { x: 0, y: 0 }, commit);
+		insert_mock_commitment(53, Position { x: 0, y: 0 }, commit);
+		insert_mock_commitment(53, Position { x: 1, y: 0 }, commit);
+
+		// The plots of the farmer ID of account 0 keep working for account 5.
+		assert_ok!(FarmersFortune::rotate_farmer_key(
+			RuntimeOrigin::signed(5),
+			0,
+			5,
+			TestSignature(0, FarmerId::new(0u64).rotation_message(&5u64, 0))
+		));
+
+		let call = melo_farmers_fortune::Call::claim_unsigned {
+			farmer: 0,
+			solution: Box::new(solution.clone()),
+		};
+		assert_ok!(FarmersFortune::validate_unsigned(TransactionSource::External, &call));
+
+		assert_ok!(FarmersFortune::claim_unsigned(RuntimeOrigin::none(), 0, Box::new(solution)));
+		assert_eq!(Balances::free_balance(5), RewardAmount::get());
+		assert_eq!(Balances::free_balance(0), ExistentialDeposit::get());
+		assert!(FarmersFortune::claimants(56).contains(&5));
+
+		// Account 5 already claimed for this block through account 0.
+		assert_eq!(
+			FarmersFortune::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Stale.into()
+		);
+
+		System::set_block_number(67);
+		assert_eq!(
+			FarmersFortune::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Custom(FARMER_NOT_REGISTERED).into()
+		);
+	});
+}
+
+#[test]
+fn claim_requires_registered_farmer() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(46);
+
+		let segs = get_mock_row(&BLS_SCALAR11, &BLS_SCALAR12, 0, &PROOF_11, &PROOF_12, 16);
+		let pre_cell = PreCell::new(PiecePosition::Row(0), segs[0].clone());
+		let piece_metadata = PieceMetadata::new(43, PiecePosition::Row(0));
+
+		let win_cell_left =
+			Cell::new(CellMetadata::new(piece_metadata.clone(), 0), segs[0].clone());
+		let win_cell_right = Cell::new(CellMetadata::new(piece_metadata, 1), segs[1].clone());
+
+		assert_noop!(
+			FarmersFortune::claim(
+				RuntimeOrigin::signed(0),
+				pre_cell.clone(),
+				Box::new(win_cell_left.clone()),
+				Box::new(win_cell_right.clone()),
+			),
+			melo_farmers_fortune::Error::<Runtime>::FarmerNotRegistered
+		);
+
+		let solution = CompactSolution::from_cells(&pre_cell, &win_cell_left, &win_cell_right)
+			.expect("Cells are a pair");
+		let call =
+			melo_farmers_fortune::Call::claim_unsigned { farmer: 0, solution: Box::new(solution) };
+		assert_eq!(
+			FarmersFortune::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Custom(FARMER_NOT_REGISTERED).into()
+		);
+	});
+}
+
+#[test]
+fn claim_rejects_unassigned_pieces() {
+	new_test_ext().execute_with(|| {
+		register_farmer(0);
+		System::set_block_number(86);
+		Challenges::<Runtime>::insert(85, H256::from(BLOCK_HASH1));
+		<frame_system::BlockHash<Runtime>>::insert(83, H256::from(BLOCK_HASH1));
+
+		let commit = KZGCommitment::try_from(COMMIT1).unwrap();
+		insert_mock_commitment(85, Position { x: 0, y: 0 }, commit);
+		insert_mock_commitment(83, Position { x: 0, y: 0 }, commit);
+		insert_mock_commitment(83, Position { x: 1, y: 0 }, commit);
+		set_mock_rows(83, 64);
+
+		let farmer_id = FarmersFortune::farmer_binding(0).unwrap();
+		let assigned = melo_proof_of_space::assignment::assigned_rows(
+			&farmer_id,
+			&H256::from(BLOCK_HASH1),
+			64,
+		);
+		let row = (0..64).find(|row| !assigned.contains(row)).unwrap();
+
+		let segs = get_mock_row(&BLS_SCALAR11, &BLS_SCALAR12, 0, &PROOF_11, &PROOF_12, 16);
+		let pre_cell = PreCell::new(PiecePosition::Row(0), segs[0].clone());
+		let piece_metadata = PieceMetadata::new(83, PiecePosition::Row(row));
+		let win_cell_left =
+			Cell::new(CellMetadata::new(piece_metadata.clone(), 0), segs[0].clone());
+		let win_cell_right = Cell::new(CellMetadata::new(piece_metadata, 1), segs[1].clone());
+
+		assert_noop!(
+			FarmersFortune::claim(
+				RuntimeOrigin::signed(0),
+				pre_cell,
+				Box::new(win_cell_left),
+				Box::new(win_cell_right),
+			),
+			melo_farmers_fortune::Error::<Runtime>::UnassignedPiece
+		);
+	});
+}
+
+#[test]
+fn claim_weight_grows_with_segment_length() {
+	let default_len = FIELD_ELEMENTS_PER_SEGMENT;
+	let base = FarmersFortune::claim_weight(&[default_len; 3]);
+	assert_eq!(base, <() as WeightInfo>::claim());
+
+	// Shorter segments are charged as much as the benchmarked ones.
+	assert_eq!(FarmersFortune::claim_weight(&[1, 1, 1]), base);
+
+	let extra = <() as WeightInfo>::verify_segment(default_len as u32 * 2)
+		.saturating_sub(<() as WeightInfo>::verify_segment(default_len as u32));
+	assert_eq!(
+		FarmersFortune::claim_weight(&[default_len, default_len * 2, default_len]),
+		base.saturating_add(extra.saturating_mul(3))
+	);
+}
+
+#[test]
+fn on_initialize_stores_challenge_of_slot() {
+	new_test_ext().execute_with(|| {
+		for n in 1..=3 {
+			System::set_block_number(n);
+			FarmersFortune::on_initialize(n);
+		}
+
+		let expected =
+			Solution::<H256, u64>::derive_challenge::<BlakeTwo256>(&MOCK_EPOCH_RANDOMNESS, 3);
+		assert_eq!(FarmersFortune::challenge(3), Some(expected));
+		assert!(FarmersFortune::challenge(2).is_some());
+		assert_ne!(FarmersFortune::challenge(2), Some(expected));
+		assert_eq!(FarmersFortune::challenge(1), None);
+	});
+}
+
+#[test]
+fn claim_requires_challenge_of_parent() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(76);
+		<frame_system::BlockHash<Runtime>>::insert(73, H256::from(BLOCK_HASH1));
+		register_farmer(1);
+
+		let segs = get_mock_row(&BLS_SCALAR11, &BLS_SCALAR12, 0, &PROOF_11, &PROOF_12, 16);
+		let pre_cell = PreCell::new(PiecePosition::Row(0), segs[0].clone());
+		let piece_metadata = PieceMetadata::new(73, PiecePosition::Row(0));
+
+		let win_cell_left =
+			Cell::new(CellMetadata::new(piece_metadata.clone(), 0), segs[0].clone());
+		let win_cell_right = Cell::new(CellMetadata::new(piece_metadata, 1), segs[1].clone());
+
+		assert_noop!(
+			FarmersFortune::claim(
+				RuntimeOrigin::signed(1),
+				pre_cell,
+				Box::new(win_cell_left),
+				Box::new(win_cell_right),
+			),
+			melo_farmers_fortune::Error::<Runtime>::ChallengeNotFound
+		);
+	});
+}
+
+#[test]
+fn migrate_to_v1_keeps_bindings() {
+	use frame_support::traits::{GetStorageVersion, OnRuntimeUpgrade};
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(4);
+		register_farmer(1);
+		assert_eq!(FarmersFortune::on_chain_storage_version(), 0);
+
+		migrations::v1::MigrateToV1::<Runtime>::on_runtime_upgrade();
+		assert_eq!(FarmersFortune::on_chain_storage_version(), 1);
+		assert_eq!(FarmersFortune::farmer_binding(1), Some(FarmerId::new(1u64)));
+		// The claims of the upgrade block have the challenge of its parent.
+		assert_eq!(FarmersFortune::challenge(3), Some(FarmersFortune::current_challenge()));
+
+		// A second run leaves the storage as it is.
+		migrations::v1::MigrateToV1::<Runtime>::on_runtime_upgrade();
+		assert_eq!(FarmersFortune::on_chain_storage_version(), 1);
+	});
+}
@@ -6,6 +6,9 @@
 //! WORST CASE MAP SIZE: `1000000`
 //! HOSTNAME: `iZbp1afye3y82ktye25uj4Z`, CPU: `Intel(R) Xeon(R) Platinum`
 //! EXECUTION: Some(Wasm), WASM-EXECUTION: Compiled, CHAIN: Some("dev"), DB CACHE: 1024
+//!
+//! The segments of a claim are verified through host functions since this run, and the other
+//! calls were added after it: all the weights are estimates until the command below is run again.
 
 // Executed Command:
 // ./target/release/melodot-node
@@ -36,26 +39,63 @@ use core::marker::PhantomData;
 /// Weight functions needed for pallet_farmers_fortune.
 pub trait WeightInfo {
 	fn claim() -> Weight;
+	fn register_farmer() -> Weight;
+	fn rotate_farmer_key() -> Weight;
+	fn verify_segment(l: u32, ) -> Weight;
+	fn on_initialize() -> Weight;
 }
 
 /// Weights for pallet_farmers_fortune using the Substrate node and recommended hardware.
 pub struct SubstrateWeight<T>(PhantomData<T>);
 impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 	/// Storage: FarmersFortune ClaimantsForBlock (r:1 w:1)
-	/// Proof: FarmersFortune ClaimantsForBlock (max_values: None, max_size: Some(3222), added: 5697, mode: MaxEncodedLen)
+	/// Storage: FarmersFortune FarmerBindings (r:1 w:0)
+	/// Storage: FarmersFortune KeyRotations (r:1 w:0)
+	/// Storage: FarmersFortune Challenges (r:1 w:0)
 	/// Storage: System BlockHash (r:2 w:0)
-	/// Proof: System BlockHash (max_values: None, max_size: Some(44), added: 2519, mode: MaxEncodedLen)
 	/// Storage: MeloStore CommitmentsExt (r:2 w:0)
-	/// Proof: MeloStore CommitmentsExt (max_values: None, max_size: Some(4815), added: 7290, mode: MaxEncodedLen)
 	/// Storage: System Account (r:1 w:1)
-	/// Proof: System Account (max_values: None, max_size: Some(128), added: 2603, mode: MaxEncodedLen)
+	/// Not benchmarked yet: estimated as the native verification of the three segments of a
+	/// solution, `verify_segment(16)` each, and the reads of the claim.
 	fn claim() -> Weight {
-		// Proof Size summary in bytes:
-		//  Measured:  `719`
-		//  Estimated: `15570`
-		// Minimum execution time: 805_372_042_000 picoseconds.
-		Weight::from_parts(809_074_147_000, 15570)
-			.saturating_add(T::DbWeight::get().reads(6_u64))
+		Weight::from_parts(8_500_000_000, 23215)
+			.saturating_add(T::DbWeight::get().reads(9_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Storage: FarmersFortune FarmerBindings (r:1 w:1)
+	/// Storage: FarmersFortune FarmerOwners (r:1 w:1)
+	/// Storage: System Account (r:1 w:1)
+	/// Storage: FarmersFortune FarmerBonds (r:0 w:1)
+	/// Not benchmarked yet: estimated from the verification of the binding signature.
+	fn register_farmer() -> Weight {
+		Weight::from_parts(64_000_000, 7713)
+			.saturating_add(T::DbWeight::get().reads(3_u64))
+			.saturating_add(T::DbWeight::get().writes(4_u64))
+	}
+	/// Storage: FarmersFortune FarmerBindings (r:2 w:2)
+	/// Storage: FarmersFortune FarmerOwners (r:0 w:1)
+	/// Storage: FarmersFortune KeyRotations (r:0 w:1)
+	/// Storage: FarmersFortune RotationNonces (r:1 w:1)
+	/// Not benchmarked yet: estimated from the verification of the rotation signature.
+	fn rotate_farmer_key() -> Weight {
+		Weight::from_parts(70_000_000, 7613)
+			.saturating_add(T::DbWeight::get().reads(3_u64))
+			.saturating_add(T::DbWeight::get().writes(5_u64))
+	}
+	/// The range of component `l` is `[1, 2048]`.
+	/// Not benchmarked yet: estimated as a pairing check through the `kzg_verifier` host
+	/// functions, plus the interpolation of the `l` field elements of the segment.
+	fn verify_segment(l: u32, ) -> Weight {
+		Weight::from_parts(2_000_000_000, 0)
+			.saturating_add(Weight::from_parts(20_000_000, 0).saturating_mul(l.into()))
+	}
+	/// Storage: Babe Randomness (r:1 w:0)
+	/// Storage: Babe CurrentSlot (r:1 w:0)
+	/// Storage: FarmersFortune Challenges (r:0 w:2)
+	/// Not benchmarked yet: estimated as two reads and two writes of small values.
+	fn on_initialize() -> Weight {
+		Weight::from_parts(15_000_000, 1517)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
 			.saturating_add(T::DbWeight::get().writes(2_u64))
 	}
 }
@@ -63,20 +103,53 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 // For backwards compatibility and tests
 impl WeightInfo for () {
 	/// Storage: FarmersFortune ClaimantsForBlock (r:1 w:1)
-	/// Proof: FarmersFortune ClaimantsForBlock (max_values: None, max_size: Some(3222), added: 5697, mode: MaxEncodedLen)
+	/// Storage: FarmersFortune FarmerBindings (r:1 w:0)
+	/// Storage: FarmersFortune KeyRotations (r:1 w:0)
+	/// Storage: FarmersFortune Challenges (r:1 w:0)
 	/// Storage: System BlockHash (r:2 w:0)
-	/// Proof: System BlockHash (max_values: None, max_size: Some(44), added: 2519, mode: MaxEncodedLen)
 	/// Storage: MeloStore CommitmentsExt (r:2 w:0)
-	/// Proof: MeloStore CommitmentsExt (max_values: None, max_size: Some(4815), added: 7290, mode: MaxEncodedLen)
 	/// Storage: System Account (r:1 w:1)
-	/// Proof: System Account (max_values: None, max_size: Some(128), added: 2603, mode: MaxEncodedLen)
+	/// Not benchmarked yet: estimated as the native verification of the three segments of a
+	/// solution, `verify_segment(16)` each, and the reads of the claim.
 	fn claim() -> Weight {
-		// Proof Size summary in bytes:
-		//  Measured:  `719`
-		//  Estimated: `15570`
-		// Minimum execution time: 805_372_042_000 picoseconds.
-		Weight::from_parts(809_074_147_000, 15570)
-			.saturating_add(RocksDbWeight::get().reads(6_u64))
+		Weight::from_parts(8_500_000_000, 23215)
+			.saturating_add(RocksDbWeight::get().reads(9_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	/// Storage: FarmersFortune FarmerBindings (r:1 w:1)
+	/// Storage: FarmersFortune FarmerOwners (r:1 w:1)
+	/// Storage: System Account (r:1 w:1)
+	/// Storage: FarmersFortune FarmerBonds (r:0 w:1)
+	/// Not benchmarked yet: estimated from the verification of the binding signature.
+	fn register_farmer() -> Weight {
+		Weight::from_parts(64_000_000, 7713)
+			.saturating_add(RocksDbWeight::get().reads(3_u64))
+			.saturating_add(RocksDbWeight::get().writes(4_u64))
+	}
+	/// Storage: FarmersFortune FarmerBindings (r:2 w:2)
+	/// Storage: FarmersFortune FarmerOwners (r:0 w:1)
+	/// Storage: FarmersFortune KeyRotations (r:0 w:1)
+	/// Storage: FarmersFortune RotationNonces (r:1 w:1)
+	/// Not benchmarked yet: estimated from the verification of the rotation signature.
+	fn rotate_farmer_key() -> Weight {
+		Weight::from_parts(70_000_000, 7613)
+			.saturating_add(RocksDbWeight::get().reads(3_u64))
+			.saturating_add(RocksDbWeight::get().writes(5_u64))
+	}
+	/// The range of component `l` is `[1, 2048]`.
+	/// Not benchmarked yet: estimated as a pairing check through the `kzg_verifier` host
+	/// functions, plus the interpolation of the `l` field elements of the segment.
+	fn verify_segment(l: u32, ) -> Weight {
+		Weight::from_parts(2_000_000_000, 0)
+			.saturating_add(Weight::from_parts(20_000_000, 0).saturating_mul(l.into()))
+	}
+	/// Storage: Babe Randomness (r:1 w:0)
+	/// Storage: Babe CurrentSlot (r:1 w:0)
+	/// Storage: FarmersFortune Challenges (r:0 w:2)
+	/// Not benchmarked yet: estimated as two reads and two writes of small values.
+	fn on_initialize() -> Weight {
+		Weight::from_parts(15_000_000, 1517)
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
 			.saturating_add(RocksDbWeight::get().writes(2_u64))
 	}
 }
@@ -0,0 +1,90 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+#[allow(unused_imports)]
+use crate::Pallet as FarmingPools;
+use frame_benchmarking::v1::{account, benchmarks, impl_benchmark_test_suite, whitelisted_caller};
+use frame_system::RawOrigin;
+
+fn create_pool<T: Config>(operator: T::AccountId) -> PoolId {
+	let pool_id = NextPoolId::<T>::get();
+	NextPoolId::<T>::put(pool_id + 1);
+	T::Currency::make_free_balance_be(
+		&operator,
+		T::PoolDeposit::get() + T::Currency::minimum_balance(),
+	);
+	T::Currency::reserve(&operator, T::PoolDeposit::get()).expect("The operator is funded");
+	Pools::<T>::insert(
+		pool_id,
+		PoolInfo {
+			operator,
+			pool_share: Perbill::from_percent(10),
+			members: 0,
+			deposit: T::PoolDeposit::get(),
+		},
+	);
+	pool_id
+}
+
+benchmarks! {
+	create_pool {
+		let caller: T::AccountId = whitelisted_caller();
+		T::Currency::make_free_balance_be(
+			&caller,
+			T::PoolDeposit::get() + T::Currency::minimum_balance(),
+		);
+	}: _(RawOrigin::Signed(caller.clone()), Perbill::from_percent(10))
+	verify {
+		assert_eq!(Pools::<T>::get(0).map(|pool| pool.operator), Some(caller.clone()));
+		assert_eq!(T::Currency::reserved_balance(&caller), T::PoolDeposit::get());
+	}
+
+	set_pool_share {
+		let caller: T::AccountId = whitelisted_caller();
+		let pool_id = create_pool::<T>(caller.clone());
+		let pool_share = T::MaxPoolShare::get();
+	}: _(RawOrigin::Signed(caller), pool_id, pool_share)
+	verify {
+		assert!(PendingPoolShares::<T>::get(pool_id).is_some());
+	}
+
+	join_pool {
+		let caller: T::AccountId = whitelisted_caller();
+		let pool_id = create_pool::<T>(account("operator", 0, 0));
+	}: _(RawOrigin::Signed(caller.clone()), pool_id)
+	verify {
+		assert_eq!(Memberships::<T>::get(&caller), Some(pool_id));
+	}
+
+	leave_pool {
+		let caller: T::AccountId = whitelisted_caller();
+		let pool_id = create_pool::<T>(account("operator", 0, 0));
+		FarmingPools::<T>::join_pool(RawOrigin::Signed(caller.clone()).into(), pool_id)?;
+	}: _(RawOrigin::Signed(caller.clone()))
+	verify {
+		assert_eq!(Memberships::<T>::get(&caller), None);
+	}
+
+	destroy_pool {
+		let caller: T::AccountId = whitelisted_caller();
+		let pool_id = create_pool::<T>(caller.clone());
+	}: _(RawOrigin::Signed(caller.clone()), pool_id)
+	verify {
+		assert!(Pools::<T>::get(pool_id).is_none());
+		assert!(T::Currency::reserved_balance(&caller).is_zero());
+	}
+}
+
+impl_benchmark_test_suite!(FarmingPools, crate::mock::new_test_ext(), crate::mock::Runtime);
@@ -0,0 +1,314 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Farming Pools Pallet
+//!
+//! Tracks the farming pools and their members. A pool is run by an operator, whose pool server
+//! collects the partial solutions of the members, submits their winning solutions and pays them
+//! according to the partial solutions they sent.
+//!
+//! When a member wins a reward, `pool_share` of it is paid to the operator, which the operator
+//! shares between the members off-chain, and the member keeps the rest. The split is applied by
+//! the claiming pallet through the [`RewardPayout`] implementation of this pallet.
+//!
+//! The pool share is at most `MaxPoolShare`. The operator lowers it at once, and raises it with
+//! a notice of `PoolShareNotice` blocks, during which the members can leave the pool before the
+//! higher share applies to them. Creating a pool reserves `PoolDeposit` from the operator, which
+//! is returned when the operator destroys the pool.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use frame_support::{
+	pallet_prelude::*,
+	traits::{Currency, ReservableCurrency},
+};
+use frame_system::pallet_prelude::*;
+pub use melo_core_primitives::traits::RewardPayout;
+use sp_runtime::{
+	traits::{AtLeast32BitUnsigned, Zero},
+	Perbill,
+};
+use sp_std::prelude::*;
+
+pub use pallet::*;
+
+pub mod weights;
+pub use weights::*;
+
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+mod mock;
+mod tests;
+
+/// The identifier of a pool.
+pub type PoolId = u32;
+
+type BalanceOf<T> =
+	<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+
+/// A farming pool.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct PoolInfo<AccountId, Balance> {
+	/// The account running the pool, paid the pool share of the rewards of the members.
+	pub operator: AccountId,
+	/// The part of the rewards of the members paid to the operator.
+	pub pool_share: Perbill,
+	/// The number of members of the pool.
+	pub members: u32,
+	/// The deposit reserved from the operator for the pool.
+	pub deposit: Balance,
+}
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+
+	#[pallet::pallet]
+	pub struct Pallet<T>(_);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// The overarching event type for the runtime.
+		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+		/// Weight information for this pallet's extrinsics.
+		type WeightInfo: WeightInfo;
+
+		/// The currency the deposits of the pools are reserved in.
+		type Currency: ReservableCurrency<Self::AccountId>;
+
+		/// The maximum number of members of a pool.
+		#[pallet::constant]
+		type MaxPoolMembers: Get<u32>;
+
+		/// The maximum part of the rewards of the members paid to the operator of a pool.
+		#[pallet::constant]
+		type MaxPoolShare: Get<Perbill>;
+
+		/// The number of blocks after which a higher pool share applies.
+		#[pallet::constant]
+		type PoolShareNotice: Get<Self::BlockNumber>;
+
+		/// The deposit reserved from the operator of a pool until the pool is destroyed.
+		#[pallet::constant]
+		type PoolDeposit: Get<BalanceOf<Self>>;
+	}
+
+	/// The identifier of the next pool created.
+	#[pallet::storage]
+	#[pallet::getter(fn next_pool_id)]
+	pub type NextPoolId<T: Config> = StorageValue<_, PoolId, ValueQuery>;
+
+	/// The pools, by identifier.
+	#[pallet::storage]
+	#[pallet::getter(fn pools)]
+	pub type Pools<T: Config> =
+		StorageMap<_, Twox64Concat, PoolId, PoolInfo<T::AccountId, BalanceOf<T>>, OptionQuery>;
+
+	/// The higher pool share of each pool raising it, with the block it applies from.
+	#[pallet::storage]
+	#[pallet::getter(fn pending_pool_share)]
+	pub type PendingPoolShares<T: Config> =
+		StorageMap<_, Twox64Concat, PoolId, (Perbill, T::BlockNumber), OptionQuery>;
+
+	/// The pool each reward account farms for.
+	#[pallet::storage]
+	#[pallet::getter(fn membership)]
+	pub type Memberships<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, PoolId, OptionQuery>;
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// A pool was created.
+		PoolCreated { pool_id: PoolId, operator: T::AccountId, pool_share: Perbill },
+		/// A pool was destroyed, and the deposit of its operator returned.
+		PoolDestroyed { pool_id: PoolId },
+		/// The share of the rewards paid to the operator of a pool was lowered.
+		PoolShareChanged { pool_id: PoolId, pool_share: Perbill },
+		/// The share of the rewards paid to the operator of a pool is raised from `apply_at`.
+		PoolShareScheduled { pool_id: PoolId, pool_share: Perbill, apply_at: T::BlockNumber },
+		/// An account joined a pool.
+		MemberJoined { pool_id: PoolId, who: T::AccountId },
+		/// An account left a pool.
+		MemberLeft { pool_id: PoolId, who: T::AccountId },
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The pool does not exist.
+		PoolNotFound,
+		/// The caller is not the operator of the pool.
+		NotOperator,
+		/// The account is already a member of a pool.
+		AlreadyMember,
+		/// The account is not a member of a pool.
+		NotMember,
+		/// The pool has `MaxPoolMembers` members.
+		PoolFull,
+		/// The pool share is above `MaxPoolShare`.
+		PoolShareTooHigh,
+		/// The operator cannot pay the deposit of the pool.
+		InsufficientDeposit,
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Create a pool operated by the caller, paid `pool_share` of the rewards of the members.
+		/// `PoolDeposit` is reserved from the caller until the pool is destroyed.
+		#[pallet::call_index(0)]
+		#[pallet::weight(T::WeightInfo::create_pool())]
+		pub fn create_pool(origin: OriginFor<T>, pool_share: Perbill) -> DispatchResult {
+			let operator = ensure_signed(origin)?;
+			ensure!(pool_share <= T::MaxPoolShare::get(), Error::<T>::PoolShareTooHigh);
+
+			let deposit = T::PoolDeposit::get();
+			T::Currency::reserve(&operator, deposit)
+				.map_err(|_| Error::<T>::InsufficientDeposit)?;
+
+			let pool_id = NextPoolId::<T>::get();
+			NextPoolId::<T>::put(pool_id.saturating_add(1));
+			Pools::<T>::insert(
+				pool_id,
+				PoolInfo { operator: operator.clone(), pool_share, members: 0, deposit },
+			);
+
+			Self::deposit_event(Event::PoolCreated { pool_id, operator, pool_share });
+			Ok(())
+		}
+
+		/// Change the share of the rewards of the members paid to the operator of `pool_id`.
+		/// Only the operator can change it, up to `MaxPoolShare`.
+		///
+		/// A lower share applies to the next claims. A higher one applies after
+		/// `PoolShareNotice` blocks, replacing the higher share scheduled before if any.
+		#[pallet::call_index(1)]
+		#[pallet::weight(T::WeightInfo::set_pool_share())]
+		pub fn set_pool_share(
+			origin: OriginFor<T>,
+			pool_id: PoolId,
+			pool_share: Perbill,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(pool_share <= T::MaxPoolShare::get(), Error::<T>::PoolShareTooHigh);
+
+			let mut pool = Pools::<T>::get(pool_id).ok_or(Error::<T>::PoolNotFound)?;
+			ensure!(pool.operator == who, Error::<T>::NotOperator);
+
+			let now = <frame_system::Pallet<T>>::block_number();
+			pool.pool_share = Self::pool_share_at(pool_id, &pool, now);
+			if pool_share > pool.pool_share {
+				let apply_at = now.saturating_add(T::PoolShareNotice::get());
+				PendingPoolShares::<T>::insert(pool_id, (pool_share, apply_at));
+				Pools::<T>::insert(pool_id, pool);
+				Self::deposit_event(Event::PoolShareScheduled { pool_id, pool_share, apply_at });
+			} else {
+				PendingPoolShares::<T>::remove(pool_id);
+				pool.pool_share = pool_share;
+				Pools::<T>::insert(pool_id, pool);
+				Self::deposit_event(Event::PoolShareChanged { pool_id, pool_share });
+			}
+			Ok(())
+		}
+
+		/// Join `pool_id`, sharing the rewards of the caller with the pool from now on.
+		#[pallet::call_index(2)]
+		#[pallet::weight(T::WeightInfo::join_pool())]
+		pub fn join_pool(origin: OriginFor<T>, pool_id: PoolId) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(!Memberships::<T>::contains_key(&who), Error::<T>::AlreadyMember);
+
+			Pools::<T>::try_mutate(pool_id, |pool| {
+				let pool = pool.as_mut().ok_or(Error::<T>::PoolNotFound)?;
+				ensure!(pool.members < T::MaxPoolMembers::get(), Error::<T>::PoolFull);
+				pool.members += 1;
+				Ok::<_, Error<T>>(())
+			})?;
+			Memberships::<T>::insert(&who, pool_id);
+
+			Self::deposit_event(Event::MemberJoined { pool_id, who });
+			Ok(())
+		}
+
+		/// Leave the pool of the caller, which then keeps its whole rewards.
+		#[pallet::call_index(3)]
+		#[pallet::weight(T::WeightInfo::leave_pool())]
+		pub fn leave_pool(origin: OriginFor<T>) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let pool_id = Memberships::<T>::take(&who).ok_or(Error::<T>::NotMember)?;
+
+			Pools::<T>::mutate(pool_id, |pool| {
+				if let Some(pool) = pool {
+					pool.members = pool.members.saturating_sub(1);
+				}
+			});
+
+			Self::deposit_event(Event::MemberLeft { pool_id, who });
+			Ok(())
+		}
+
+		/// Destroy `pool_id`, returning the deposit of its operator. Only the operator can
+		/// destroy it. The members keep their whole rewards from then on, and leave the pool
+		/// to join another.
+		#[pallet::call_index(4)]
+		#[pallet::weight(T::WeightInfo::destroy_pool())]
+		pub fn destroy_pool(origin: OriginFor<T>, pool_id: PoolId) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let pool = Pools::<T>::get(pool_id).ok_or(Error::<T>::PoolNotFound)?;
+			ensure!(pool.operator == who, Error::<T>::NotOperator);
+
+			Pools::<T>::remove(pool_id);
+			PendingPoolShares::<T>::remove(pool_id);
+			T::Currency::unreserve(&pool.operator, pool.deposit);
+
+			Self::deposit_event(Event::PoolDestroyed { pool_id });
+			Ok(())
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Returns the pool share of `pool` at block `now`, the higher share scheduled for the pool
+	/// once it applies.
+	pub fn pool_share_at(
+		pool_id: PoolId,
+		pool: &PoolInfo<T::AccountId, BalanceOf<T>>,
+		now: T::BlockNumber,
+	) -> Perbill {
+		match PendingPoolShares::<T>::get(pool_id) {
+			Some((pool_share, apply_at)) if now >= apply_at => pool_share,
+			_ => pool.pool_share,
+		}
+	}
+}
+
+impl<T: Config, Balance: AtLeast32BitUnsigned + Copy> RewardPayout<T::AccountId, Balance>
+	for Pallet<T>
+{
+	fn payout(winner: &T::AccountId, reward: Balance) -> Vec<(T::AccountId, Balance)> {
+		let pool = Memberships::<T>::get(winner)
+			.and_then(|pool_id| Pools::<T>::get(pool_id).map(|pool| (pool_id, pool)));
+		match pool {
+			Some((pool_id, pool)) if pool.operator != *winner => {
+				let now = <frame_system::Pallet<T>>::block_number();
+				let pool_part = Self::pool_share_at(pool_id, &pool, now).mul_floor(reward);
+				[(winner.clone(), reward.saturating_sub(pool_part)), (pool.operator, pool_part)]
+					.into_iter()
+					.filter(|(_, amount)| !amount.is_zero())
+					.collect()
+			},
+			_ => Vec::from([(winner.clone(), reward)]),
+		}
+	}
+}
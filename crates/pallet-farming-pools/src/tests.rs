@@ -0,0 +1,236 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#![cfg(test)]
+
+use super::*;
+use crate::mock::*;
+use frame_support::{assert_noop, assert_ok};
+
+fn create_pool(share: u32) -> PoolId {
+	let pool_id = FarmingPools::next_pool_id();
+	assert_ok!(FarmingPools::create_pool(
+		RuntimeOrigin::signed(OPERATOR),
+		Perbill::from_percent(share)
+	));
+	pool_id
+}
+
+#[test]
+fn create_pool_should_work() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(create_pool(10), 0);
+		assert_eq!(create_pool(20), 1);
+		assert_eq!(
+			FarmingPools::pools(1),
+			Some(PoolInfo {
+				operator: OPERATOR,
+				pool_share: Perbill::from_percent(20),
+				members: 0,
+				deposit: PoolDeposit::get(),
+			})
+		);
+		assert_eq!(Balances::reserved_balance(OPERATOR), 2 * PoolDeposit::get());
+		System::assert_last_event(
+			Event::PoolCreated {
+				pool_id: 1,
+				operator: OPERATOR,
+				pool_share: Perbill::from_percent(20),
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn create_pool_should_fail() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			FarmingPools::create_pool(RuntimeOrigin::signed(OPERATOR), Perbill::from_percent(51)),
+			Error::<Runtime>::PoolShareTooHigh
+		);
+		assert_noop!(
+			FarmingPools::create_pool(RuntimeOrigin::signed(1), Perbill::from_percent(10)),
+			Error::<Runtime>::InsufficientDeposit
+		);
+	});
+}
+
+#[test]
+fn destroy_pool_should_return_deposit() {
+	new_test_ext().execute_with(|| {
+		let pool_id = create_pool(10);
+		assert_ok!(FarmingPools::join_pool(RuntimeOrigin::signed(1), pool_id));
+		assert_noop!(
+			FarmingPools::destroy_pool(RuntimeOrigin::signed(1), pool_id),
+			Error::<Runtime>::NotOperator
+		);
+
+		assert_ok!(FarmingPools::destroy_pool(RuntimeOrigin::signed(OPERATOR), pool_id));
+		assert_eq!(FarmingPools::pools(pool_id), None);
+		assert_eq!(Balances::reserved_balance(OPERATOR), 0);
+		System::assert_last_event(Event::PoolDestroyed { pool_id }.into());
+		assert_noop!(
+			FarmingPools::destroy_pool(RuntimeOrigin::signed(OPERATOR), pool_id),
+			Error::<Runtime>::PoolNotFound
+		);
+
+		// The members of a destroyed pool keep their whole rewards, and can leave it.
+		assert_eq!(<FarmingPools as RewardPayout<u64, u64>>::payout(&1, 1000), vec![(1, 1000)]);
+		assert_ok!(FarmingPools::leave_pool(RuntimeOrigin::signed(1)));
+	});
+}
+
+#[test]
+fn only_operator_should_set_pool_share() {
+	new_test_ext().execute_with(|| {
+		let pool_id = create_pool(10);
+		assert_noop!(
+			FarmingPools::set_pool_share(RuntimeOrigin::signed(1), pool_id, Perbill::zero()),
+			Error::<Runtime>::NotOperator
+		);
+		assert_noop!(
+			FarmingPools::set_pool_share(RuntimeOrigin::signed(OPERATOR), 5, Perbill::zero()),
+			Error::<Runtime>::PoolNotFound
+		);
+
+		assert_noop!(
+			FarmingPools::set_pool_share(
+				RuntimeOrigin::signed(OPERATOR),
+				pool_id,
+				Perbill::from_percent(51)
+			),
+			Error::<Runtime>::PoolShareTooHigh
+		);
+
+		// A lower share applies at once.
+		assert_ok!(FarmingPools::set_pool_share(
+			RuntimeOrigin::signed(OPERATOR),
+			pool_id,
+			Perbill::from_percent(5)
+		));
+		assert_eq!(FarmingPools::pools(pool_id).unwrap().pool_share, Perbill::from_percent(5));
+		System::assert_last_event(
+			Event::PoolShareChanged { pool_id, pool_share: Perbill::from_percent(5) }.into(),
+		);
+	});
+}
+
+#[test]
+fn higher_pool_share_should_apply_after_notice() {
+	new_test_ext().execute_with(|| {
+		let pool_id = create_pool(10);
+		assert_ok!(FarmingPools::join_pool(RuntimeOrigin::signed(1), pool_id));
+
+		assert_ok!(FarmingPools::set_pool_share(
+			RuntimeOrigin::signed(OPERATOR),
+			pool_id,
+			Perbill::from_percent(50)
+		));
+		let apply_at = 1 + PoolShareNotice::get();
+		assert_eq!(
+			FarmingPools::pending_pool_share(pool_id),
+			Some((Perbill::from_percent(50), apply_at))
+		);
+		System::assert_last_event(
+			Event::PoolShareScheduled { pool_id, pool_share: Perbill::from_percent(50), apply_at }
+				.into(),
+		);
+
+		// The members are paid with the current share until the notice ends.
+		System::set_block_number(apply_at - 1);
+		assert_eq!(
+			<FarmingPools as RewardPayout<u64, u64>>::payout(&1, 1000),
+			vec![(1, 900), (OPERATOR, 100)]
+		);
+		System::set_block_number(apply_at);
+		assert_eq!(
+			<FarmingPools as RewardPayout<u64, u64>>::payout(&1, 1000),
+			vec![(1, 500), (OPERATOR, 500)]
+		);
+
+		// Lowering the share cancels the higher one scheduled.
+		assert_ok!(FarmingPools::set_pool_share(
+			RuntimeOrigin::signed(OPERATOR),
+			pool_id,
+			Perbill::from_percent(40)
+		));
+		assert_eq!(FarmingPools::pending_pool_share(pool_id), None);
+		assert_eq!(
+			<FarmingPools as RewardPayout<u64, u64>>::payout(&1, 1000),
+			vec![(1, 600), (OPERATOR, 400)]
+		);
+	});
+}
+
+#[test]
+fn join_and_leave_pool_should_work() {
+	new_test_ext().execute_with(|| {
+		let pool_id = create_pool(10);
+		assert_noop!(
+			FarmingPools::join_pool(RuntimeOrigin::signed(1), 5),
+			Error::<Runtime>::PoolNotFound
+		);
+
+		assert_ok!(FarmingPools::join_pool(RuntimeOrigin::signed(1), pool_id));
+		assert_eq!(FarmingPools::membership(1), Some(pool_id));
+		System::assert_last_event(Event::MemberJoined { pool_id, who: 1 }.into());
+		assert_noop!(
+			FarmingPools::join_pool(RuntimeOrigin::signed(1), pool_id),
+			Error::<Runtime>::AlreadyMember
+		);
+
+		assert_ok!(FarmingPools::join_pool(RuntimeOrigin::signed(2), pool_id));
+		assert_noop!(
+			FarmingPools::join_pool(RuntimeOrigin::signed(3), pool_id),
+			Error::<Runtime>::PoolFull
+		);
+
+		assert_ok!(FarmingPools::leave_pool(RuntimeOrigin::signed(1)));
+		assert_eq!(FarmingPools::membership(1), None);
+		assert_eq!(FarmingPools::pools(pool_id).unwrap().members, 1);
+		assert_noop!(
+			FarmingPools::leave_pool(RuntimeOrigin::signed(1)),
+			Error::<Runtime>::NotMember
+		);
+
+		// The freed seat can be taken.
+		assert_ok!(FarmingPools::join_pool(RuntimeOrigin::signed(3), pool_id));
+	});
+}
+
+#[test]
+fn payout_should_split_rewards_of_members() {
+	new_test_ext().execute_with(|| {
+		let pool_id = create_pool(30);
+
+		// Accounts outside of a pool keep their whole reward.
+		assert_eq!(<FarmingPools as RewardPayout<u64, u64>>::payout(&1, 1000), vec![(1, 1000)]);
+
+		assert_ok!(FarmingPools::join_pool(RuntimeOrigin::signed(1), pool_id));
+		assert_eq!(
+			<FarmingPools as RewardPayout<u64, u64>>::payout(&1, 1000),
+			vec![(1, 700), (OPERATOR, 300)]
+		);
+
+		// Parts that round down to nothing are not paid.
+		assert_eq!(<FarmingPools as RewardPayout<u64, u64>>::payout(&1, 3), vec![(1, 3)]);
+
+		// The operator farming in its own pool keeps its reward.
+		assert_ok!(FarmingPools::join_pool(RuntimeOrigin::signed(OPERATOR), pool_id));
+		assert_eq!(
+			<FarmingPools as RewardPayout<u64, u64>>::payout(&OPERATOR, 1000),
+			vec![(OPERATOR, 1000)]
+		);
+	});
+}
@@ -0,0 +1,104 @@
+//! Weights for pallet_farming_pools
+//!
+//! The pallet has not been benchmarked yet, these weights are estimates from the storage
+//! accesses of each call. Replace them with the output of `melodot-node benchmark pallet
+//! --pallet=pallet-farming-pools` once it is run on the reference hardware.
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use core::marker::PhantomData;
+
+/// Weight functions needed for pallet_farming_pools.
+pub trait WeightInfo {
+	fn create_pool() -> Weight;
+	fn set_pool_share() -> Weight;
+	fn join_pool() -> Weight;
+	fn leave_pool() -> Weight;
+	fn destroy_pool() -> Weight;
+}
+
+/// Weights for pallet_farming_pools (estimates).
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	/// Storage: FarmingPools NextPoolId (r:1 w:1)
+	/// Storage: System Account (r:1 w:1)
+	/// Storage: FarmingPools Pools (r:0 w:1)
+	fn create_pool() -> Weight {
+		Weight::from_parts(40_000_000, 4103)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(3_u64))
+	}
+	/// Storage: FarmingPools Pools (r:1 w:1)
+	/// Storage: FarmingPools PendingPoolShares (r:1 w:1)
+	fn set_pool_share() -> Weight {
+		Weight::from_parts(20_000_000, 3517)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Storage: FarmingPools Memberships (r:1 w:1)
+	/// Storage: FarmingPools Pools (r:1 w:1)
+	fn join_pool() -> Weight {
+		Weight::from_parts(20_000_000, 3517)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Storage: FarmingPools Memberships (r:1 w:1)
+	/// Storage: FarmingPools Pools (r:1 w:1)
+	fn leave_pool() -> Weight {
+		Weight::from_parts(21_000_000, 3517)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Storage: FarmingPools Pools (r:1 w:1)
+	/// Storage: FarmingPools PendingPoolShares (r:0 w:1)
+	/// Storage: System Account (r:1 w:1)
+	fn destroy_pool() -> Weight {
+		Weight::from_parts(38_000_000, 4103)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(3_u64))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	/// Storage: FarmingPools NextPoolId (r:1 w:1)
+	/// Storage: System Account (r:1 w:1)
+	/// Storage: FarmingPools Pools (r:0 w:1)
+	fn create_pool() -> Weight {
+		Weight::from_parts(40_000_000, 4103)
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().writes(3_u64))
+	}
+	/// Storage: FarmingPools Pools (r:1 w:1)
+	/// Storage: FarmingPools PendingPoolShares (r:1 w:1)
+	fn set_pool_share() -> Weight {
+		Weight::from_parts(20_000_000, 3517)
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	/// Storage: FarmingPools Memberships (r:1 w:1)
+	/// Storage: FarmingPools Pools (r:1 w:1)
+	fn join_pool() -> Weight {
+		Weight::from_parts(20_000_000, 3517)
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	/// Storage: FarmingPools Memberships (r:1 w:1)
+	/// Storage: FarmingPools Pools (r:1 w:1)
+	fn leave_pool() -> Weight {
+		Weight::from_parts(21_000_000, 3517)
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	/// Storage: FarmingPools Pools (r:1 w:1)
+	/// Storage: FarmingPools PendingPoolShares (r:0 w:1)
+	/// Storage: System Account (r:1 w:1)
+	fn destroy_pool() -> Weight {
+		Weight::from_parts(38_000_000, 4103)
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().writes(3_u64))
+	}
+}
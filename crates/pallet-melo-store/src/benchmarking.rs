@@ -90,6 +90,38 @@ pub fn create_report<T: Config>(
 	Ok((input_report, signature))
 }
 
+pub fn create_availability_vote<T: Config>(
+	k: u32,
+) -> Result<
+	(
+		crate::AvailabilityVote<frame_system::pallet_prelude::BlockNumberFor<T>>,
+		<T::AuthorityId as RuntimeAppPublic>::Signature,
+	),
+	&'static str,
+> {
+	let mut keys = Vec::new();
+	for _ in 0..k {
+		keys.push(T::AuthorityId::generate_pair(None));
+	}
+	let bounded_keys = WeakBoundedVec::<_, T::MaxKeys>::try_from(keys.clone())
+		.map_err(|()| "More than the maximum number of keys provided")?;
+	Keys::<T>::put(bounded_keys);
+
+	let at_block = <frame_system::Pallet<T>>::block_number();
+
+	let input_vote = AvailabilityVote {
+		at_block,
+		authority_index: k - 1,
+		is_available: true,
+		validators_len: k,
+	};
+
+	let encoded_vote = input_vote.encode();
+	let authority_id = keys.get((k - 1) as usize).ok_or("out of range")?;
+	let signature = authority_id.sign(&encoded_vote).ok_or("couldn't make signature")?;
+	Ok((input_vote, signature))
+}
+
 benchmarks! {
 	register_app {
 		let caller: T::AccountId = whitelisted_caller();
@@ -99,7 +131,12 @@ benchmarks! {
 	}
 
 	submit_data {
-		let k in 1 .. T::MaxBlobNum::get();
+		let k in 1 .. {
+			let das_params = T::DasParams::get();
+			T::MaxBlobNum::get()
+				.min(das_params.max_block_rows)
+				.min(das_params.max_blob_bytes / DATA_BYTES_PER_BLOB as u32)
+		};
 
 		let caller: T::AccountId = whitelisted_caller();
 		AppId::<T>::put(1u32);
@@ -107,7 +144,7 @@ benchmarks! {
 		let commitments: Vec<KZGCommitment> = vec![Default::default(); k as usize];
 		let proofs: Vec<KZGProof> = vec![Default::default(); k as usize];
 
-		let bytes_len = (k * BYTES_PER_BLOB as u32) as u32;
+		let bytes_len = (k * DATA_BYTES_PER_BLOB as u32) as u32;
 		let app_id = 1u32;
 
 		let params = SidecarMetadata {
@@ -116,6 +153,8 @@ benchmarks! {
 			proofs: proofs.clone(),
 			bytes_len,
 			nonce: k,
+			compression: Default::default(),
+			encryption: Default::default(),
 		};
 
 	}: _(RawOrigin::Signed(caller.clone()), params)
@@ -163,5 +202,21 @@ benchmarks! {
 			.dispatch_bypass_filter(RawOrigin::None.into())?;
 	}
 
+	validate_unsigned_and_then_vote_availability {
+		let k in 1 .. MAX_KEYS;
+		let (input_vote, signature) = create_availability_vote::<T>(k)?;
+		let call = Call::vote_availability { availability_vote: input_vote, signature };
+		let call_enc = call.encode();
+	}: {
+		MeloStore::<T>::validate_unsigned(TransactionSource::InBlock, &call).map_err(<&str>::from)?;
+		<Call<T> as Decode>::decode(&mut &*call_enc)
+			.expect("call is encoded above, encoding must be correct")
+			.dispatch_bypass_filter(RawOrigin::None.into())?;
+	}
+	verify {
+		let at_block = <frame_system::Pallet<T>>::block_number();
+		ensure!(AvailabilityTallies::<T>::get(at_block).available == 1, "Vote should be counted.");
+	}
+
 	impl_benchmark_test_suite!(MeloStore, crate::mock::new_test_ext(), crate::mock::Runtime);
 }
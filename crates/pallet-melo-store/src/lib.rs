@@ -20,6 +20,7 @@ mod mock;
 #[cfg(test)]
 mod tests;
 
+pub mod migrations;
 pub mod weights;
 pub use weights::*;
 
@@ -33,12 +34,13 @@ use frame_system::{
 	offchain::{SendTransactionTypes, SubmitTransaction},
 	pallet_prelude::*,
 };
-use melo_das_primitives::{blob::Blob, config::BYTES_PER_BLOB};
+use melo_das_primitives::{blob::Blob, config::DATA_BYTES_PER_BLOB};
 use melo_erasure_coding::erasure_coding::extend_fs_g1;
 
 pub use pallet::*;
 use scale_info::TypeInfo;
 use sp_application_crypto::RuntimeAppPublic;
+use sp_core::H256;
 use sp_runtime::{
 	offchain::storage::{MutateStorageError, StorageRetrievalError, StorageValueRef},
 	traits::{AtLeast32BitUnsigned, Saturating},
@@ -48,10 +50,12 @@ use sp_std::prelude::*;
 
 use melo_core_primitives::{
 	config::{BLOCK_SAMPLE_LIMIT, MAX_UNAVAILABLE_BLOCK_INTERVAL},
-	extension::AppLookup,
+	extension::{AppLookup, ExtensionDigest},
+	inclusion::{commitments_root, BlockCommitments},
+	namespace::namespace_root,
 	reliability::{ReliabilityId, ReliabilityManager},
-	traits::{CommitmentFromPosition, HeaderCommitList},
-	SidecarMetadata,
+	traits::{CommitmentFromPosition, DataAvailability, HeaderCommitList, OnDataAvailable},
+	BlobLimits, DasParams, SidecarMetadata,
 };
 
 use melo_das_db::offchain::OffchainKv;
@@ -59,10 +63,17 @@ use melo_das_primitives::crypto::{KZGCommitment, KZGProof, Position, KZG};
 
 // A prefix constant used for the off-chain database.
 const DB_PREFIX: &[u8] = b"melodot/melo-store/unavailable-data-report";
+// A prefix constant used for the off-chain database to lock availability votes.
+const VOTE_DB_PREFIX: &[u8] = b"melodot/melo-store/availability-vote";
 // A threshold constant used to determine when to delay the acknowledgment of unavailability.
 pub const DELAY_CHECK_THRESHOLD: u32 = 1;
+/// The maximum number of blocks whose commitments are compacted at a block, so that the blocks
+/// past the retention period are caught up with after an upgrade or a shorter period.
+pub const MAX_COMPACTIONS_PER_BLOCK: u32 = 4;
 // Weight constant for each blob.
 pub const WEIGHT_PER_BLOB: Weight = Weight::from_parts(1024, 0);
+// The share of the authorities that has to agree on the availability of the data of a block.
+pub const AVAILABILITY_VOTE_THRESHOLD: Permill = Permill::from_percent(50);
 
 // Typedef for Authorization Index.
 pub type AuthIndex = u32;
@@ -124,11 +135,42 @@ where
 	pub validators_len: u32,
 }
 
+// Struct to represent a vote on the availability of the data submitted at a block.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct AvailabilityVote<BlockNumber>
+where
+	BlockNumber: PartialEq + Eq + Decode + Encode,
+{
+	/// Block number the data was submitted at.
+	pub at_block: BlockNumber,
+	/// Index of the authority voting.
+	pub authority_index: AuthIndex,
+	/// Whether the samples of the authority show the data available.
+	pub is_available: bool,
+	/// Total length of session validator set.
+	pub validators_len: u32,
+}
+
+// Struct to represent the availability votes aggregated for a block.
+#[derive(
+	Encode, Decode, Clone, Copy, Default, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen,
+)]
+pub struct AvailabilityTally {
+	/// Number of authorities whose samples show the data available.
+	pub available: u32,
+	/// Number of authorities whose samples show the data unavailable.
+	pub unavailable: u32,
+}
+
 #[frame_support::pallet]
 pub mod pallet {
 	use super::*;
 
+	/// The storage version of the pallet, see [`crate::migrations`].
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
 	#[pallet::pallet]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	pub type KZGCommitmentListFor<T> = BoundedVec<KZGCommitment, <T as Config>::MaxBlobNum>;
@@ -191,9 +233,21 @@ pub mod pallet {
 		#[pallet::constant]
 		type MaxExtedLen: Get<u32>;
 
+		/// The parameters of data availability, which bound the data submitted at a block.
+		type DasParams: Get<DasParams>;
+
 		/// This defines the priority for unsigned transactions in the Melo context.
 		#[pallet::constant]
 		type MeloUnsignedPriority: Get<TransactionPriority>;
+
+		/// Whether the rows are tagged with the namespaces of their apps, and the root of their
+		/// namespaced Merkle tree deposited into the block digest.
+		#[pallet::constant]
+		type NamespacedRows: Get<bool>;
+
+		/// The pallets notified when the authorities confirm the availability of the data of a
+		/// block.
+		type OnDataAvailable: OnDataAvailable<BlockNumberFor<Self>>;
 	}
 
 	/// Represents metadata associated with the AppData. It's preserved for future verification.
@@ -246,6 +300,31 @@ pub mod pallet {
 		WeakBoundedVec<AuthIndex, T::MaxBlobNum>,
 	>;
 
+	/// Records the availability vote of each authority on the data submitted at a block.
+	#[pallet::storage]
+	#[pallet::getter(fn availability_vote)]
+	pub(super) type AvailabilityVotes<T: Config> =
+		StorageDoubleMap<_, Twox64Concat, BlockNumberFor<T>, Twox64Concat, AuthIndex, bool>;
+
+	/// Aggregates the availability votes on the data submitted at a block. Removed with the votes
+	/// once the voting window of the block is over.
+	#[pallet::storage]
+	#[pallet::getter(fn availability_tally)]
+	pub(super) type AvailabilityTallies<T: Config> =
+		StorageMap<_, Twox64Concat, BlockNumberFor<T>, AvailabilityTally, ValueQuery>;
+
+	/// The root of the commitments of the blocks past the retention period, whose commitments
+	/// and proofs were dropped from `Metadata`. The commitments stay in the state of archive nodes
+	/// at the blocks before the compaction.
+	#[pallet::storage]
+	#[pallet::getter(fn compacted_commitments)]
+	pub(super) type CompactedCommitments<T: Config> =
+		StorageMap<_, Twox64Concat, BlockNumberFor<T>, H256, OptionQuery>;
+
+	/// The first block whose commitments are not compacted yet.
+	#[pallet::storage]
+	pub(super) type NextCompaction<T: Config> = StorageValue<_, BlockNumberFor<T>, ValueQuery>;
+
 	/// Enumerates all the possible events that can be emitted by this pallet.
 	#[pallet::event]
 	#[pallet::generate_deposit(pub(super) fn deposit_event)]
@@ -263,6 +342,19 @@ pub mod pallet {
 		ReportReceived { at_block: BlockNumberFor<T>, from: AuthIndex },
 		/// Denotes the successful registration of a new application ID.
 		AppIdRegistered { app_id: u32, from: T::AccountId },
+		/// Signifies that an availability vote has been submitted.
+		AvailabilityVoted { at_block: BlockNumberFor<T>, from: AuthIndex, is_available: bool },
+		/// The votes of the authorities confirmed the availability of the data submitted at a
+		/// block. `confidence` is the share of the authorities attesting the availability, and
+		/// `attesters` their number.
+		DataAvailabilityConfirmed {
+			at_block: BlockNumberFor<T>,
+			confidence: Permill,
+			attesters: u32,
+		},
+		/// The commitments of the data submitted at a block were compacted into their `root`,
+		/// past the retention period.
+		CommitmentsCompacted { at_block: BlockNumberFor<T>, root: H256 },
 	}
 
 	/// Enumerates all possible errors that might occur while using this pallet.
@@ -292,6 +384,10 @@ pub mod pallet {
 		InvalidKey,
 		/// The nonce is invalid.
 		NonceError,
+		/// The authority already voted on the availability of the block.
+		DuplicateAvailabilityVote,
+		/// The data submitted at a single block exceeds the maximum total length in bytes.
+		ExceedMaxBytesPerBlock,
 	}
 
 	#[pallet::call]
@@ -300,6 +396,8 @@ pub mod pallet {
 		/// This call allows a user to submit data, its commitments, and proofs.
 		/// The function ensures various constraints like the length of the data, validity of the
 		/// app id, and other integrity checks.
+		/// The blobs of the data carry either one proof each or a single batch proof, which is
+		/// cheaper to submit.
 		#[pallet::call_index(0)]
 		#[pallet::weight(
 			WEIGHT_PER_BLOB
@@ -311,7 +409,9 @@ pub mod pallet {
 		pub fn submit_data(origin: OriginFor<T>, params: SidecarMetadata) -> DispatchResult {
 			let who = ensure_signed(origin)?;
 			ensure!(params.check(), Error::<T>::SubmittedDataIsInvalid);
-			let blob_num = Blob::blob_count(params.bytes_len as usize, BYTES_PER_BLOB);
+			let das_params = T::DasParams::get();
+			ensure!(params.bytes_len <= das_params.max_blob_bytes, Error::<T>::ExceedMaxBlobLimit);
+			let blob_num = Blob::blob_count(params.bytes_len as usize, DATA_BYTES_PER_BLOB);
 			ensure!(blob_num <= T::MaxBlobNum::get() as usize, Error::<T>::ExceedMaxBlobLimit);
 
 			let current_app_id = AppId::<T>::get();
@@ -322,6 +422,20 @@ pub mod pallet {
 
 			ensure!(params.nonce == current_nonce.saturating_add(1), Error::<T>::NonceError);
 
+			let current_block_number = <frame_system::Pallet<T>>::block_number();
+
+			// The blobs of a block share its rows and its byte capacity.
+			ensure!(
+				Self::submitted_rows(current_block_number).saturating_add(blob_num as u32) <=
+					Self::max_block_rows(&das_params),
+				Error::<T>::ExceedMaxBlobPerBlock
+			);
+			ensure!(
+				Self::submitted_bytes(current_block_number).saturating_add(params.bytes_len) <=
+					das_params.max_block_bytes,
+				Error::<T>::ExceedMaxBytesPerBlock
+			);
+
 			let mut commitment_list: BoundedVec<KZGCommitment, T::MaxBlobNum> =
 				BoundedVec::default();
 			commitment_list
@@ -345,8 +459,6 @@ pub mod pallet {
 				nonce: params.nonce,
 			};
 
-			let current_block_number = <frame_system::Pallet<T>>::block_number();
-
 			let mut metadata_len = 0;
 			Metadata::<T>::try_mutate(current_block_number, |metadata_vec| {
 				metadata_len = metadata_vec.len();
@@ -440,20 +552,91 @@ pub mod pallet {
 			Self::deposit_event(Event::AppIdRegistered { app_id, from: who });
 			Ok(())
 		}
+
+		/// Vote on the availability of the data submitted at a block.
+		/// Validators sample the data with the segments cached by their node in the offchain
+		/// worker, and vote with this function. The votes are aggregated per block, see
+		/// `block_availability`.
+		#[pallet::call_index(3)]
+		#[pallet::weight(<T as Config>::WeightInfo::validate_unsigned_and_then_vote_availability(
+			availability_vote.validators_len,
+		))]
+		pub fn vote_availability(
+			origin: OriginFor<T>,
+			availability_vote: AvailabilityVote<BlockNumberFor<T>>,
+			_signature: <T::AuthorityId as RuntimeAppPublic>::Signature,
+		) -> DispatchResult {
+			ensure_none(origin)?;
+			let current_block_number: BlockNumberFor<T> = <frame_system::Pallet<T>>::block_number();
+			let AvailabilityVote { at_block, authority_index, is_available, .. } =
+				availability_vote;
+
+			ensure!(at_block <= current_block_number, Error::<T>::ReportForFutureBlock);
+
+			ensure!(
+				at_block + DELAY_CHECK_THRESHOLD.into() >= current_block_number,
+				Error::<T>::ExceedUnavailableDataConfirmTime
+			);
+
+			let keys = Keys::<T>::get();
+			ensure!(keys.get(authority_index as usize).is_some(), Error::<T>::InvalidKey);
+
+			ensure!(
+				!AvailabilityVotes::<T>::contains_key(at_block, authority_index),
+				Error::<T>::DuplicateAvailabilityVote
+			);
+
+			let was_confirmed = Self::block_availability(at_block) == Some(true);
+			AvailabilityVotes::<T>::insert(at_block, authority_index, is_available);
+			let tally = AvailabilityTallies::<T>::mutate(at_block, |tally| {
+				if is_available {
+					tally.available.saturating_inc();
+				} else {
+					tally.unavailable.saturating_inc();
+				}
+				*tally
+			});
+
+			Self::deposit_event(Event::AvailabilityVoted {
+				at_block,
+				from: authority_index,
+				is_available,
+			});
+
+			if !was_confirmed && Self::block_availability(at_block) == Some(true) {
+				let confidence = Permill::from_rational(tally.available, keys.len() as u32);
+				let blobs = Self::iter_metadata(at_block)
+					.map(|metadata| (metadata.app_id, metadata.nonce))
+					.collect::<Vec<_>>();
+				T::OnDataAvailable::on_data_available(at_block, confidence, &blobs);
+
+				Self::deposit_event(Event::DataAvailabilityConfirmed {
+					at_block,
+					confidence,
+					attesters: tally.available,
+				});
+			}
+			Ok(())
+		}
 	}
 
 	#[pallet::hooks]
 	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(now: BlockNumberFor<T>) -> Weight {
+			Self::compact_commitments(now)
+		}
+
 		fn on_finalize(now: BlockNumberFor<T>) {
+			Self::deposit_extension_digest();
+
 			// Deletion of expired polling data
 			if T::BlockNumber::from(DELAY_CHECK_THRESHOLD + 1) >= now {
 				return
 			}
-			let _ = UnavailableVote::<T>::clear_prefix(
-				now - (DELAY_CHECK_THRESHOLD + 1).into(),
-				T::MaxBlobNum::get(),
-				None,
-			);
+			let expired = now - (DELAY_CHECK_THRESHOLD + 1).into();
+			let _ = UnavailableVote::<T>::clear_prefix(expired, T::MaxBlobNum::get(), None);
+			let _ = AvailabilityVotes::<T>::clear_prefix(expired, T::MaxKeys::get(), None);
+			AvailabilityTallies::<T>::remove(expired);
 		}
 
 		fn offchain_worker(now: BlockNumberFor<T>) {
@@ -469,6 +652,16 @@ pub mod pallet {
 						)
 					}
 				}
+				for res in Self::send_availability_votes(now).into_iter().flatten() {
+					if let Err(e) = res {
+						log::debug!(
+							target: "runtime::melo-store",
+							"Skipping availability vote at {:?}: {:?}",
+							now,
+							e,
+						)
+					}
+				}
 			// TODO - report unavailability.
 			} else {
 				log::trace!(
@@ -514,6 +707,31 @@ pub mod pallet {
 					.longevity(DELAY_CHECK_THRESHOLD as u64)
 					.propagate(true)
 					.build()
+			} else if let Call::vote_availability { availability_vote, signature } = call {
+				let keys = Keys::<T>::get();
+
+				let authority_id = match keys.get(availability_vote.authority_index as usize) {
+					Some(id) => id,
+					None => return InvalidTransaction::Stale.into(),
+				};
+
+				if keys.len() as u32 != availability_vote.validators_len {
+					return InvalidTransaction::Custom(INVALID_VALIDATORS_LEN).into()
+				}
+
+				let signature_valid = availability_vote
+					.using_encoded(|encoded_vote| authority_id.verify(&encoded_vote, signature));
+
+				if !signature_valid {
+					return InvalidTransaction::BadProof.into()
+				}
+
+				ValidTransaction::with_tag_prefix("MeloStoreAvailability")
+					.priority(T::MeloUnsignedPriority::get())
+					.and_provides((availability_vote.at_block, availability_vote.authority_index))
+					.longevity(DELAY_CHECK_THRESHOLD as u64)
+					.propagate(true)
+					.build()
 			} else {
 				InvalidTransaction::Call.into()
 			}
@@ -585,6 +803,45 @@ impl<T: Config> Pallet<T> {
 		unavail_blocks
 	}
 
+	/// Samples the data submitted at a given block with the segments cached in the local storage,
+	/// checking them against the commitments on chain.
+	///
+	/// Returns `None` if no data is available at the block, or if the local node has not sampled
+	/// all of it.
+	///
+	/// # Arguments
+	/// * `at_block` - The block number the data was submitted at.
+	pub fn sample_availability(at_block: BlockNumberFor<T>) -> Option<bool> {
+		let mut db = OffchainKv::new(None);
+		let mut is_available = None;
+		for metadata in Self::iter_metadata(at_block) {
+			let mut confidence = ReliabilityId::app_confidence(metadata.app_id, metadata.nonce)
+				.get_confidence(&mut db)?;
+			let blob_available = confidence.verify_cached(&metadata.commitments, &mut db);
+			is_available = Some(is_available.unwrap_or(true) && blob_available);
+		}
+		is_available
+	}
+
+	/// Returns the availability of the data submitted at a given block agreed on by the
+	/// authorities, or `None` if the votes do not reach `AVAILABILITY_VOTE_THRESHOLD` yet. The
+	/// votes are only kept during the voting window of the block, `None` is returned after it.
+	///
+	/// # Arguments
+	/// * `at_block` - The block number the data was submitted at.
+	pub fn block_availability(at_block: BlockNumberFor<T>) -> Option<bool> {
+		let tally = AvailabilityTallies::<T>::get(at_block);
+		let threshold = AVAILABILITY_VOTE_THRESHOLD.mul_floor(Keys::<T>::get().len() as u32);
+
+		if tally.available > threshold {
+			Some(true)
+		} else if tally.unavailable > threshold {
+			Some(false)
+		} else {
+			None
+		}
+	}
+
 	fn iter_metadata(at_block: BlockNumberFor<T>) -> impl Iterator<Item = BlobMetadata<T>> {
 		Metadata::<T>::get(at_block)
 			.into_iter()
@@ -613,6 +870,81 @@ impl<T: Config> Pallet<T> {
 		)
 	}
 
+	/// Fetch the row ranges that belong to an app at a given block.
+	///
+	/// The ranges index into the commitments returned by `get_commitments`, which are the rows
+	/// committed to in the header extension.
+	///
+	/// # Arguments
+	/// * `at_block` - The block number to fetch the rows from.
+	/// * `app_id` - The id of the app.
+	pub fn app_rows(at_block: BlockNumberFor<T>, app_id: u32) -> Vec<sp_std::ops::Range<u32>> {
+		let (_, app_lookups) = Self::get_commitments_and_app_lookups(at_block);
+		AppLookup::row_ranges(&app_lookups, app_id)
+	}
+
+	/// Fetch the number of rows taken by the data submitted at a given block.
+	///
+	/// Unlike `app_rows`, data whose availability is not yet known is counted, so the result is
+	/// the first row of the next data submitted at the block.
+	///
+	/// # Arguments
+	/// * `at_block` - The block number to count the rows of.
+	pub fn submitted_rows(at_block: BlockNumberFor<T>) -> u32 {
+		Metadata::<T>::get(at_block)
+			.iter()
+			.map(|metadata| metadata.commitments.len() as u32)
+			.sum()
+	}
+
+	/// Fetch the total length in bytes of the data submitted at a given block.
+	///
+	/// # Arguments
+	/// * `at_block` - The block number to count the bytes of.
+	pub fn submitted_bytes(at_block: BlockNumberFor<T>) -> u32 {
+		Metadata::<T>::get(at_block)
+			.iter()
+			.fold(0u32, |bytes, metadata| bytes.saturating_add(metadata.bytes_len))
+	}
+
+	/// The maximum number of rows of a block, bounded by the storage bound `MaxBlobNum`.
+	fn max_block_rows(das_params: &DasParams) -> u32 {
+		das_params.max_block_rows.min(T::MaxBlobNum::get())
+	}
+
+	/// Fetch the limits a submission of `app_id` at a given block has to respect.
+	///
+	/// The blobs submitted at a block share the rows of its extended commitments and its bytes, as
+	/// bounded by the DAS parameters, so the quotas of an app are what is left of them.
+	/// Unregistered apps have no quota, and neither has any app once the block holds `MaxKeys`
+	/// submissions.
+	///
+	/// # Arguments
+	/// * `at_block` - The block number the data would be submitted at.
+	/// * `app_id` - The id of the app.
+	pub fn blob_limits(at_block: BlockNumberFor<T>, app_id: u32) -> BlobLimits {
+		let das_params = T::DasParams::get();
+		let max_blob_num = Self::max_block_rows(&das_params);
+		let max_bytes_per_block = das_params.max_block_bytes;
+		let max_bytes_len = max_blob_num
+			.saturating_mul(DATA_BYTES_PER_BLOB as u32)
+			.min(das_params.max_blob_bytes)
+			.min(max_bytes_per_block);
+
+		let (app_quota, bytes_quota) = if app_id > AppId::<T>::get() ||
+			Metadata::<T>::decode_len(at_block).unwrap_or_default() >= T::MaxKeys::get() as usize
+		{
+			(0, 0)
+		} else {
+			(
+				max_blob_num.saturating_sub(Self::submitted_rows(at_block)),
+				max_bytes_per_block.saturating_sub(Self::submitted_bytes(at_block)),
+			)
+		};
+
+		BlobLimits { max_bytes_len, app_quota, bytes_quota }
+	}
+
 	/// Fetch the list of KZG commitments at a given block.
 	///
 	/// This function retrieves the KZG commitments associated with the specified block.
@@ -630,6 +962,87 @@ impl<T: Config> Pallet<T> {
 			.collect()
 	}
 
+	/// Fetch the commitments of the rows of a given block, or their root once compacted.
+	///
+	/// # Arguments
+	/// * `at_block` - The block number from which to fetch commitments.
+	pub fn block_commitments(at_block: BlockNumberFor<T>) -> BlockCommitments {
+		match CompactedCommitments::<T>::get(at_block) {
+			Some(root) => BlockCommitments::Compacted(root),
+			None => BlockCommitments::Full(Self::get_commitments(at_block)),
+		}
+	}
+
+	/// Compact the commitments of the blocks past the retention period of the DAS parameters, at
+	/// most `MAX_COMPACTIONS_PER_BLOCK` of them from the first block not compacted yet.
+	///
+	/// # Arguments
+	/// * `now` - The current block number.
+	///
+	/// # Returns
+	/// The weight consumed.
+	pub(crate) fn compact_commitments(now: BlockNumberFor<T>) -> Weight {
+		let retention_period: BlockNumberFor<T> = T::DasParams::get().retention_period.into();
+		let horizon = now.saturating_sub(retention_period);
+
+		let mut next = NextCompaction::<T>::get();
+		let mut compacted = 0u64;
+		while next < horizon && compacted < MAX_COMPACTIONS_PER_BLOCK as u64 {
+			Self::compact_block(next);
+			next.saturating_inc();
+			compacted += 1;
+		}
+		if compacted > 0 {
+			NextCompaction::<T>::put(next);
+		}
+
+		T::DbWeight::get().reads_writes(2 + compacted * 2, compacted * 3 + compacted.min(1))
+	}
+
+	/// Replace the commitments and proofs of the blobs submitted at a given block with the root
+	/// of the commitments, the one of the header extension.
+	///
+	/// The metadata of the blobs is kept, so that their submission and availability can still be
+	/// looked up.
+	fn compact_block(at_block: BlockNumberFor<T>) {
+		CommitmentsExt::<T>::remove(at_block);
+
+		let mut metadata = Metadata::<T>::get(at_block);
+		if metadata.is_empty() {
+			return
+		}
+
+		let root = commitments_root(&Self::get_commitments(at_block));
+		for blob in metadata.iter_mut() {
+			blob.commitments = Default::default();
+			blob.proofs = Default::default();
+		}
+		Metadata::<T>::insert(at_block, metadata);
+		CompactedCommitments::<T>::insert(at_block, root);
+
+		Self::deposit_event(Event::CommitmentsCompacted { at_block, root });
+	}
+
+	/// Deposit the DAS metadata of the header extension into the block digest, so that light
+	/// clients can read the rows, columns, commitments root and app lookups from the header.
+	///
+	/// If `NamespacedRows` is set, the namespace root of the rows is deposited as well, so that
+	/// light clients can check that they were given all the rows of an app.
+	pub(crate) fn deposit_extension_digest() {
+		let (commitments, app_lookups) = <Self as HeaderCommitList>::last();
+		if commitments.is_empty() {
+			return
+		}
+
+		if T::NamespacedRows::get() {
+			let root = namespace_root(&commitments, &app_lookups);
+			<frame_system::Pallet<T>>::deposit_log(root.to_digest_item());
+		}
+
+		let extension_digest = ExtensionDigest::new(&commitments, app_lookups);
+		<frame_system::Pallet<T>>::deposit_log(extension_digest.to_digest_item());
+	}
+
 	/// Assemble and send unavailability reports for any data that is unavailable.
 	///
 	/// # Arguments
@@ -663,6 +1076,23 @@ impl<T: Config> Pallet<T> {
 		Ok(reports)
 	}
 
+	/// Send the availability votes of the local authorities on the data submitted at the current
+	/// block.
+	///
+	/// # Arguments
+	/// * `now` - The current block number.
+	pub(crate) fn send_availability_votes(
+		now: BlockNumberFor<T>,
+	) -> OffchainResult<T, impl Iterator<Item = OffchainResult<T, ()>>> {
+		let votes = Self::sample_availability(now).into_iter().flat_map(move |is_available| {
+			Self::local_authority_keys().map(move |(authority_index, key)| {
+				Self::send_single_availability_vote(authority_index, key, now, is_available)
+			})
+		});
+
+		Ok(votes)
+	}
+
 	/// Push a vector of commitments to the CommitmentsExt storage.
 	/// 
 	/// # Arguments
@@ -729,6 +1159,41 @@ impl<T: Config> Pallet<T> {
 		})
 	}
 
+	// Helper method to send a single availability vote.
+	fn send_single_availability_vote(
+		authority_index: u32,
+		key: T::AuthorityId,
+		at_block: BlockNumberFor<T>,
+		is_available: bool,
+	) -> OffchainResult<T, ()> {
+		let prepare_availability_vote = || -> OffchainResult<T, Call<T>> {
+			let validators_len = Keys::<T>::decode_len().unwrap_or_default() as u32;
+			let availability_vote =
+				AvailabilityVote { at_block, authority_index, is_available, validators_len };
+
+			let signature =
+				key.sign(&availability_vote.encode()).ok_or(OffchainErr::FailedSigning)?;
+
+			Ok(Call::vote_availability { availability_vote, signature })
+		};
+
+		Self::with_lock(VOTE_DB_PREFIX, authority_index, at_block, at_block, || {
+			let call = prepare_availability_vote()?;
+			log::info!(
+				target: "runtime::melo-store",
+				"[index: {:?}] Voting availability of {:?}: {:?}",
+				authority_index,
+				at_block,
+				call,
+			);
+
+			SubmitTransaction::<T, Call<T>>::submit_unsigned_transaction(call.into())
+				.map_err(|_| OffchainErr::SubmitTransaction)?;
+
+			Ok(())
+		})
+	}
+
 	// Locking mechanism to prevent double reporting by the same authority.
 	fn with_report_lock<R>(
 		authority_index: u32,
@@ -736,7 +1201,19 @@ impl<T: Config> Pallet<T> {
 		now: BlockNumberFor<T>,
 		f: impl FnOnce() -> OffchainResult<T, R>,
 	) -> OffchainResult<T, R> {
-		let mut key = DB_PREFIX.to_vec();
+		Self::with_lock(DB_PREFIX, authority_index, at_block, now, f)
+	}
+
+	// Locking mechanism to prevent the same authority from sending a transaction twice, the lock
+	// of each kind of transaction is stored under its own `prefix`.
+	fn with_lock<R>(
+		prefix: &[u8],
+		authority_index: u32,
+		at_block: BlockNumberFor<T>,
+		now: BlockNumberFor<T>,
+		f: impl FnOnce() -> OffchainResult<T, R>,
+	) -> OffchainResult<T, R> {
+		let mut key = prefix.to_vec();
 		key.extend(authority_index.encode());
 
 		let storage = StorageValueRef::persistent(&key);
@@ -856,14 +1333,34 @@ impl<T: Config> HeaderCommitList for Pallet<T> {
 	}
 }
 
+impl<T: Config> DataAvailability<BlockNumberFor<T>> for Pallet<T> {
+	fn is_blob_available(at_block: BlockNumberFor<T>, app_id: u32, nonce: u32) -> bool {
+		Self::block_availability(at_block) == Some(true) &&
+			Self::iter_metadata(at_block)
+				.any(|metadata| metadata.app_id == app_id && metadata.nonce == nonce)
+	}
+}
+
 impl<T: Config> CommitmentFromPosition for Pallet<T> {
 	type BlockNumber = T::BlockNumber;
 
+	fn extended_rows(block_num: T::BlockNumber) -> u32 {
+		match CommitmentsExt::<T>::get(block_num) {
+			Some(ext) => ext.len() as u32,
+			None => Self::get_commitments(block_num).len() as u32 * 2,
+		}
+	}
+
 	fn commitments(block_num: T::BlockNumber, position: &Position) -> Option<KZGCommitment> {
 		if block_num > <frame_system::Pallet<T>>::block_number() - DELAY_CHECK_THRESHOLD.into() {
 			return None
 		}
 
+		// Only the root of the commitments is kept past the retention period.
+		if CompactedCommitments::<T>::contains_key(block_num) {
+			return None
+		}
+
 		let y_usize = position.y as usize;
 
 		let commitments_ext = CommitmentsExt::<T>::get(block_num);
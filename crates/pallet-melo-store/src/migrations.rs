@@ -0,0 +1,74 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Storage migrations.
+//!
+//! The storage version of the pallet is bumped with each change of the layout of its storage,
+//! e.g. of the commitments of the blobs, along with a migration from the previous version. The
+//! runtime lists the migrations in its `Migrations`, which run on the first block after an
+//! upgrade. Each migration only applies to the version it migrates from, so that it can be left
+//! in the list after it ran.
+use super::*;
+use frame_support::traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion};
+use sp_std::marker::PhantomData;
+
+const LOG_TARGET: &str = "runtime::melo-store";
+
+/// The migration to the first storage version.
+pub mod v1 {
+	use super::*;
+
+	/// Sets the storage version of the pallet, which had none, to 1.
+	///
+	/// The items of version 0 keep their layout, the items added since start empty. The blobs
+	/// submitted before the upgrade are compacted like the others once past the retention
+	/// period, by `on_initialize` from the first block, a few blocks at a time, so that the
+	/// migration itself does not iterate over them. Under try-runtime, the blob metadata of all
+	/// the blocks is checked to decode after the migration.
+	pub struct MigrateToV1<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			let on_chain = Pallet::<T>::on_chain_storage_version();
+			if on_chain != 0 {
+				log::info!(target: LOG_TARGET, "Skipping the migration to v1 from {:?}", on_chain);
+				return T::DbWeight::get().reads(1)
+			}
+
+			StorageVersion::new(1).put::<Pallet<T>>();
+			log::info!(target: LOG_TARGET, "Migrated the storage to v1");
+			T::DbWeight::get().reads_writes(1, 1)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+			Ok((Metadata::<T>::iter_keys().count() as u32).encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), &'static str> {
+			let entries = u32::decode(&mut &state[..]).map_err(|_| "Invalid pre-upgrade state")?;
+			ensure!(
+				Pallet::<T>::on_chain_storage_version() >= 1,
+				"The storage version was not set to 1"
+			);
+			// Entries that do not decode are skipped by the iteration.
+			ensure!(
+				Metadata::<T>::iter_values().count() as u32 == entries,
+				"Some blob metadata does not decode"
+			);
+			Ok(())
+		}
+	}
+}
@@ -24,6 +24,8 @@ use frame_support::{
 	traits::{ConstU32, ConstU64},
 	weights::Weight,
 };
+use melo_core_primitives::{traits::OnDataAvailable, DasParams};
+use melo_das_primitives::config::BYTES_PER_BLOB;
 use pallet_im_online as imonline;
 use pallet_session::historical as pallet_session_historical;
 use sp_core::H256;
@@ -191,10 +193,28 @@ impl frame_support::traits::EstimateNextSessionRotation<u64> for TestNextSession
 }
 
 pub const MAX_BLOB_NUM: u32 = 10;
+pub const MAX_BYTES_PER_BLOCK: u32 = 4 * BYTES_PER_BLOB as u32;
 
 parameter_types! {
 	pub const MaxBlobNum: u32 = MAX_BLOB_NUM;
 	pub const MaxExtedLen: u32 = MAX_BLOB_NUM * 2;
+	pub static MockDasParams: DasParams = DasParams {
+		max_blob_bytes: MAX_BYTES_PER_BLOCK,
+		max_block_rows: MAX_BLOB_NUM,
+		max_block_bytes: MAX_BYTES_PER_BLOCK,
+		..Default::default()
+	};
+	pub static NamespacedRows: bool = true;
+	pub static AvailableData: Vec<(u64, Permill, Vec<(u32, u32)>)> = vec![];
+}
+
+/// Records the data confirmed available.
+pub struct RecordAvailableData;
+
+impl OnDataAvailable<u64> for RecordAvailableData {
+	fn on_data_available(at_block: u64, confidence: Permill, blobs: &[(u32, u32)]) {
+		AvailableData::mutate(|data| data.push((at_block, confidence, blobs.to_vec())));
+	}
 }
 
 impl Config for Runtime {
@@ -204,7 +224,10 @@ impl Config for Runtime {
 	type MaxKeys = ConstU32<10_000>;
 	type MaxBlobNum = MaxBlobNum;
 	type MaxExtedLen = MaxExtedLen;
+	type DasParams = MockDasParams;
 	type MeloUnsignedPriority = ConstU64<{ 1 << 20 }>;
+	type NamespacedRows = NamespacedRows;
+	type OnDataAvailable = RecordAvailableData;
 }
 
 impl<LocalCall> frame_system::offchain::SendTransactionTypes<LocalCall> for Runtime
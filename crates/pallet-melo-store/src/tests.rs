@@ -16,6 +16,7 @@ use super::*;
 use crate as pallet_melo_store;
 use crate::mock::*;
 use frame_support::{assert_noop, assert_ok};
+use melo_core_primitives::namespace::{NamespaceId, NamespaceProof, NamespacedNode};
 use sp_core::offchain::{
 	testing::{TestOffchainExt, TestTransactionPoolExt},
 	OffchainDbExt, OffchainWorkerExt, TransactionPoolExt,
@@ -50,6 +51,34 @@ pub fn report_unavailability(
 	MeloStore::report(RuntimeOrigin::none(), report, signature)
 }
 
+// Utility function to vote on availability
+pub fn vote_availability(
+	who: u32,
+	at_block: u64,
+	is_available: bool,
+	validators_len: u32,
+) -> DispatchResult {
+	let vote = pallet_melo_store::AvailabilityVote {
+		at_block,
+		authority_index: who,
+		is_available,
+		validators_len,
+	};
+	let signature = UintAuthorityId((who + 1).into()).sign(&vote.encode()).unwrap();
+
+	MeloStore::pre_dispatch(&crate::Call::vote_availability {
+		availability_vote: vote.clone(),
+		signature: signature.clone(),
+	})
+	.map_err(|e| match e {
+		TransactionValidityError::Invalid(InvalidTransaction::Custom(INVALID_VALIDATORS_LEN)) =>
+			"invalid validators len",
+		e @ _ => <&'static str>::from(e),
+	})?;
+
+	MeloStore::vote_availability(RuntimeOrigin::none(), vote, signature)
+}
+
 // Utility function to report unavailability
 pub fn submit_init_data() -> DispatchResult {
 	MeloStore::register_app(RuntimeOrigin::signed(1))?;
@@ -77,7 +106,7 @@ pub fn submit_data(
 }
 
 fn commits_and_proofs(bytes_len: u32, reduction: usize) -> (Vec<KZGCommitment>, Vec<KZGProof>) {
-	let len = Blob::blob_count(bytes_len as usize, BYTES_PER_BLOB);
+	let len = Blob::blob_count(bytes_len as usize, DATA_BYTES_PER_BLOB);
 	let adjusted_len = if len > reduction { len - reduction } else { 0 };
 
 	let commits: Vec<KZGCommitment> = (0..adjusted_len).map(|_| KZGCommitment::rand()).collect();
@@ -128,7 +157,7 @@ fn should_submit_data_successfully() {
 fn should_fail_when_submitting_data_exceeds_limit() {
 	new_test_ext().execute_with(|| {
 		let app_id = 1;
-		let bytes_len = MAX_BLOB_NUM * (BYTES_PER_BLOB as u32) + 1; // Exceeding the limit
+		let bytes_len = MAX_BLOB_NUM * (DATA_BYTES_PER_BLOB as u32) + 1; // Exceeding the limit
 		let (commitments, proofs) = commits_and_proofs(bytes_len, 0);
 
 		assert_ok!(MeloStore::register_app(RuntimeOrigin::signed(1)));
@@ -246,10 +275,10 @@ fn should_report_unavailable_data_successfully_with_multiple_app_id_and_data() {
 		set_keys();
 		let now = System::block_number();
 
-		for app_id in 1..=10u32 {
+		for app_id in 1..=5u32 {
 			assert_ok!(MeloStore::register_app(RuntimeOrigin::signed(app_id as u64)));
 
-			for nonce in 1..=10 {
+			for nonce in 1..=2 {
 				let bytes_len = 10;
 				let (commitments, proofs) = commits_and_proofs(bytes_len, 0);
 
@@ -269,7 +298,7 @@ fn should_report_unavailable_data_successfully_with_multiple_app_id_and_data() {
 		System::set_block_number((now + (DELAY_CHECK_THRESHOLD as u64)).into());
 
 		let mut index_set = vec![];
-		for index in 0..10 * 10 {
+		for index in 0..5 * 2 {
 			index_set.push(index);
 		}
 
@@ -445,6 +474,34 @@ fn should_fail_with_mismatched_proofs_count() {
 	});
 }
 
+#[test]
+fn should_accept_a_batch_proof() {
+	new_test_ext().execute_with(|| {
+		let bytes_len = DATA_BYTES_PER_BLOB as u32 * 3;
+		let (commitments, proofs) = commits_and_proofs(bytes_len, 0);
+
+		assert_ok!(MeloStore::register_app(RuntimeOrigin::signed(1)));
+
+		// Neither one proof per blob nor a batch proof.
+		assert_noop!(
+			MeloStore::submit_data(
+				RuntimeOrigin::signed(2),
+				SidecarMetadata::new(1, bytes_len, 1, commitments.clone(), proofs[..2].to_vec()),
+			),
+			Error::<Runtime>::SubmittedDataIsInvalid
+		);
+
+		assert_ok!(MeloStore::submit_data(
+			RuntimeOrigin::signed(2),
+			SidecarMetadata::new(1, bytes_len, 1, commitments.clone(), proofs[..1].to_vec()),
+		));
+
+		let metadata = &Metadata::<Runtime>::get(System::block_number())[0];
+		assert_eq!(metadata.commitments.to_vec(), commitments);
+		assert_eq!(metadata.proofs.to_vec(), proofs[..1].to_vec());
+	});
+}
+
 #[test]
 fn should_change_metadata_availability_when_reports_exceed_threshold() {
 	new_test_ext().execute_with(|| {
@@ -595,3 +652,438 @@ fn should_acquire_and_release_report_lock_correctly() {
 // 		assert!(res.next().is_none());
 // 	});
 // }
+
+#[test]
+fn should_deposit_extension_digest_for_confirmed_data() {
+	new_test_ext().execute_with(|| {
+		let now = System::block_number();
+		let (commitments, proofs) = commits_and_proofs(10, 0);
+		assert_ok!(submit_data(1, 1, 10, 1, commitments.clone(), proofs));
+
+		// Nothing is confirmed yet, so no digest is deposited.
+		MeloStore::deposit_extension_digest();
+		assert_eq!(ExtensionDigest::from_digest(&System::digest()), None);
+
+		System::set_block_number(now + DELAY_CHECK_THRESHOLD as u64);
+		MeloStore::deposit_extension_digest();
+
+		let extension_digest = ExtensionDigest::from_digest(&System::digest()).unwrap();
+		assert_eq!(extension_digest.rows, commitments.len() as u32);
+		assert_eq!(
+			extension_digest.app_lookup,
+			vec![AppLookup { app_id: 1, nonce: 1, count: commitments.len() as u16 }]
+		);
+		assert_eq!(
+			extension_digest.commitments_root,
+			melo_core_primitives::inclusion::commitments_root(&commitments)
+		);
+	});
+}
+
+#[test]
+fn should_deposit_namespace_root_of_rows() {
+	new_test_ext().execute_with(|| {
+		let now = System::block_number();
+		let (commitments, proofs) = commits_and_proofs(10, 0);
+		assert_ok!(submit_data(1, 1, 10, 1, commitments.clone(), proofs));
+		System::set_block_number(now + DELAY_CHECK_THRESHOLD as u64);
+
+		NamespacedRows::set(false);
+		MeloStore::deposit_extension_digest();
+		assert_eq!(NamespacedNode::from_digest(&System::digest()), None);
+
+		NamespacedRows::set(true);
+		MeloStore::deposit_extension_digest();
+		let root = NamespacedNode::from_digest(&System::digest()).unwrap();
+		let app_lookup = vec![AppLookup { app_id: 1, nonce: 1, count: commitments.len() as u16 }];
+		assert_eq!(root, namespace_root(&commitments, &app_lookup));
+
+		// The rows of the app are proven complete against the deposited root
+		let namespace = NamespaceId::from_app_id(1);
+		let (rows, proof) = NamespaceProof::generate(&commitments, &app_lookup, namespace);
+		assert_eq!(rows.len(), commitments.len());
+		assert!(proof.verify(&root, namespace, &rows));
+		assert!(!proof.verify(&root, namespace, &rows[1..]));
+	});
+}
+
+#[test]
+fn should_return_app_rows_of_available_data() {
+	new_test_ext().execute_with(|| {
+		let now = System::block_number();
+		let (commitments, proofs) = commits_and_proofs(DATA_BYTES_PER_BLOB as u32 * 2, 0);
+		assert_ok!(submit_data(1, 1, DATA_BYTES_PER_BLOB as u32 * 2, 1, commitments, proofs));
+		let (commitments, proofs) = commits_and_proofs(10, 0);
+		assert_ok!(submit_data(1, 2, 10, 1, commitments, proofs));
+
+		assert_eq!(MeloStore::app_rows(now, 1), vec![0..2]);
+		assert_eq!(MeloStore::app_rows(now, 2), vec![2..3]);
+		assert!(MeloStore::app_rows(now, 3).is_empty());
+	});
+}
+
+#[test]
+fn should_count_submitted_rows() {
+	new_test_ext().execute_with(|| {
+		let now = System::block_number();
+		assert_eq!(MeloStore::submitted_rows(now), 0);
+
+		let (commitments, proofs) = commits_and_proofs(DATA_BYTES_PER_BLOB as u32 * 2, 0);
+		assert_ok!(submit_data(1, 1, DATA_BYTES_PER_BLOB as u32 * 2, 1, commitments, proofs));
+		let (commitments, proofs) = commits_and_proofs(10, 0);
+		assert_ok!(submit_data(1, 2, 10, 1, commitments, proofs));
+
+		assert_eq!(MeloStore::submitted_rows(now), 3);
+		assert_eq!(MeloStore::submitted_rows(now + 1), 0);
+	});
+}
+
+#[test]
+fn should_compute_blob_limits() {
+	new_test_ext().execute_with(|| {
+		let now = System::block_number();
+		let max_bytes_len = MAX_BYTES_PER_BLOCK;
+		let bytes_len = DATA_BYTES_PER_BLOB as u32 * 2;
+
+		// No app is registered yet.
+		assert_eq!(
+			MeloStore::blob_limits(now, 1),
+			BlobLimits { max_bytes_len, app_quota: 0, bytes_quota: 0 }
+		);
+
+		let (commitments, proofs) = commits_and_proofs(bytes_len, 0);
+		assert_ok!(submit_data(1, 1, bytes_len, 1, commitments, proofs));
+
+		assert_eq!(
+			MeloStore::blob_limits(now, 1),
+			BlobLimits {
+				max_bytes_len,
+				app_quota: MAX_BLOB_NUM - 2,
+				bytes_quota: MAX_BYTES_PER_BLOCK - bytes_len
+			}
+		);
+		assert_eq!(
+			MeloStore::blob_limits(now + 1, 1),
+			BlobLimits { max_bytes_len, app_quota: MAX_BLOB_NUM, bytes_quota: MAX_BYTES_PER_BLOCK }
+		);
+		assert_eq!(MeloStore::blob_limits(now, 2).app_quota, 0);
+	});
+}
+
+#[test]
+fn should_fail_when_block_rows_are_exhausted() {
+	new_test_ext().execute_with(|| {
+		let now = System::block_number();
+		assert_ok!(MeloStore::register_app(RuntimeOrigin::signed(1)));
+
+		for nonce in 1..=MAX_BLOB_NUM {
+			let (commitments, proofs) = commits_and_proofs(10, 0);
+			assert_ok!(MeloStore::submit_data(
+				RuntimeOrigin::signed(1),
+				SidecarMetadata::new(1, 10, nonce, commitments, proofs),
+			));
+		}
+		assert_eq!(MeloStore::submitted_rows(now), MAX_BLOB_NUM);
+
+		let (commitments, proofs) = commits_and_proofs(10, 0);
+		assert_noop!(
+			MeloStore::submit_data(
+				RuntimeOrigin::signed(1),
+				SidecarMetadata::new(1, 10, MAX_BLOB_NUM + 1, commitments, proofs),
+			),
+			Error::<Runtime>::ExceedMaxBlobPerBlock
+		);
+	});
+}
+
+#[test]
+fn should_fail_when_block_bytes_are_exhausted() {
+	new_test_ext().execute_with(|| {
+		let now = System::block_number();
+		assert_ok!(MeloStore::register_app(RuntimeOrigin::signed(1)));
+
+		let (commitments, proofs) = commits_and_proofs(MAX_BYTES_PER_BLOCK, 0);
+		assert_ok!(MeloStore::submit_data(
+			RuntimeOrigin::signed(1),
+			SidecarMetadata::new(1, MAX_BYTES_PER_BLOCK, 1, commitments, proofs),
+		));
+		assert_eq!(MeloStore::submitted_bytes(now), MAX_BYTES_PER_BLOCK);
+
+		let (commitments, proofs) = commits_and_proofs(10, 0);
+		assert_noop!(
+			MeloStore::submit_data(
+				RuntimeOrigin::signed(1),
+				SidecarMetadata::new(1, 10, 2, commitments, proofs),
+			),
+			Error::<Runtime>::ExceedMaxBytesPerBlock
+		);
+	});
+}
+
+#[test]
+fn should_follow_das_params() {
+	new_test_ext().execute_with(|| {
+		let now = System::block_number();
+		assert_ok!(MeloStore::register_app(RuntimeOrigin::signed(1)));
+		MockDasParams::mutate(|params| {
+			params.max_blob_bytes = DATA_BYTES_PER_BLOB as u32;
+			params.max_block_rows = 2;
+		});
+
+		assert_eq!(
+			MeloStore::blob_limits(now, 1),
+			BlobLimits {
+				max_bytes_len: DATA_BYTES_PER_BLOB as u32,
+				app_quota: 2,
+				bytes_quota: MAX_BYTES_PER_BLOCK
+			}
+		);
+
+		let bytes_len = DATA_BYTES_PER_BLOB as u32 + 1;
+		let (commitments, proofs) = commits_and_proofs(bytes_len, 0);
+		assert_noop!(
+			MeloStore::submit_data(
+				RuntimeOrigin::signed(1),
+				SidecarMetadata::new(1, bytes_len, 1, commitments, proofs),
+			),
+			Error::<Runtime>::ExceedMaxBlobLimit
+		);
+
+		for nonce in 1..=2 {
+			let (commitments, proofs) = commits_and_proofs(10, 0);
+			assert_ok!(MeloStore::submit_data(
+				RuntimeOrigin::signed(1),
+				SidecarMetadata::new(1, 10, nonce, commitments, proofs),
+			));
+		}
+		let (commitments, proofs) = commits_and_proofs(10, 0);
+		assert_noop!(
+			MeloStore::submit_data(
+				RuntimeOrigin::signed(1),
+				SidecarMetadata::new(1, 10, 3, commitments, proofs),
+			),
+			Error::<Runtime>::ExceedMaxBlobPerBlock
+		);
+	});
+}
+
+#[test]
+fn should_aggregate_availability_votes() {
+	new_test_ext().execute_with(|| {
+		set_keys();
+
+		let now = System::block_number();
+		assert_eq!(MeloStore::block_availability(now), None);
+
+		assert_noop!(vote_availability(100, now, true, 3), "Transaction is outdated");
+		assert_noop!(vote_availability(0, now, true, 100), "invalid validators len");
+
+		assert_ok!(vote_availability(0, now, true, 3));
+		assert!(events().contains(&Event::<Runtime>::AvailabilityVoted {
+			at_block: now,
+			from: 0,
+			is_available: true
+		}));
+		assert_eq!(MeloStore::availability_vote(now, 0), Some(true));
+		assert_eq!(MeloStore::block_availability(now), None);
+
+		assert_noop!(
+			vote_availability(0, now, false, 3),
+			Error::<Runtime>::DuplicateAvailabilityVote
+		);
+
+		assert_ok!(vote_availability(1, now, false, 3));
+		assert_eq!(MeloStore::block_availability(now), None);
+		assert!(!events()
+			.iter()
+			.any(|event| matches!(event, Event::<Runtime>::DataAvailabilityConfirmed { .. })));
+
+		assert_ok!(vote_availability(2, now, true, 3));
+		assert_eq!(
+			MeloStore::availability_tally(now),
+			AvailabilityTally { available: 2, unavailable: 1 }
+		);
+		assert_eq!(MeloStore::block_availability(now), Some(true));
+		assert!(events().contains(&Event::<Runtime>::DataAvailabilityConfirmed {
+			at_block: now,
+			confidence: Permill::from_rational(2u32, 3u32),
+			attesters: 2,
+		}));
+	});
+}
+
+#[test]
+fn should_notify_the_data_confirmed_available() {
+	new_test_ext().execute_with(|| {
+		set_keys();
+
+		let now = System::block_number();
+		let (commitments, proofs) = commits_and_proofs(10, 0);
+		assert_ok!(submit_data(2, 1, 10, 1, commitments, proofs));
+		assert!(!MeloStore::is_blob_available(now, 1, 1));
+
+		assert_ok!(vote_availability(0, now, true, 3));
+		assert!(AvailableData::get().is_empty());
+		assert!(!MeloStore::is_blob_available(now, 1, 1));
+
+		assert_ok!(vote_availability(1, now, true, 3));
+		assert_eq!(
+			AvailableData::get(),
+			vec![(now, Permill::from_rational(2u32, 3u32), vec![(1, 1)])]
+		);
+		assert!(MeloStore::is_blob_available(now, 1, 1));
+		assert!(!MeloStore::is_blob_available(now, 1, 2));
+
+		// The data is only notified once.
+		assert_ok!(vote_availability(2, now, true, 3));
+		assert_eq!(AvailableData::get().len(), 1);
+	});
+}
+
+#[test]
+fn should_clear_expired_availability_votes() {
+	new_test_ext().execute_with(|| {
+		set_keys();
+
+		let now = 10;
+		System::set_block_number(now);
+		assert_ok!(vote_availability(0, now, true, 3));
+		assert_ok!(vote_availability(1, now, true, 3));
+		assert_eq!(MeloStore::block_availability(now), Some(true));
+
+		let expiry = now + DELAY_CHECK_THRESHOLD as u64 + 1;
+		MeloStore::on_finalize(expiry - 1);
+		assert_eq!(MeloStore::availability_tally(now).available, 2);
+
+		MeloStore::on_finalize(expiry);
+		assert_eq!(MeloStore::availability_vote(now, 0), None);
+		assert_eq!(MeloStore::availability_tally(now), AvailabilityTally::default());
+		assert_eq!(MeloStore::block_availability(now), None);
+	});
+}
+
+#[test]
+fn should_fail_when_voting_outside_window() {
+	new_test_ext().execute_with(|| {
+		set_keys();
+
+		let now = System::block_number();
+		assert_noop!(
+			vote_availability(0, now + 1, true, 3),
+			Error::<Runtime>::ReportForFutureBlock
+		);
+
+		System::set_block_number(now + DELAY_CHECK_THRESHOLD as u64 + 1);
+		assert_noop!(
+			vote_availability(0, now, true, 3),
+			Error::<Runtime>::ExceedUnavailableDataConfirmTime
+		);
+	});
+}
+
+#[test]
+fn should_sample_availability_with_cached_segments() {
+	use melo_core_primitives::{
+		config::{FIELD_ELEMENTS_PER_BLOB, FIELD_ELEMENTS_PER_SEGMENT},
+		reliability::{sample_key, Reliability, ReliabilityType, Sample, SampleId},
+	};
+	use melo_das_db::traits::DasKv;
+	use melo_erasure_coding::bytes_to_segments;
+
+	let mut ext = new_test_ext();
+	let (offchain, _state) = TestOffchainExt::new();
+	ext.register_extension(OffchainDbExt::new(offchain.clone()));
+	ext.register_extension(OffchainWorkerExt::new(offchain));
+
+	ext.execute_with(|| {
+		let now = System::block_number();
+		assert_eq!(MeloStore::sample_availability(now), None);
+
+		let bytes = vec![7u8; 1000];
+		let params = SidecarMetadata::try_from_app_data(&bytes, 1, 1).unwrap();
+		assert_ok!(submit_data(
+			2,
+			1,
+			params.bytes_len,
+			1,
+			params.commitments.clone(),
+			params.proofs.clone()
+		));
+
+		// The local node has not sampled the data.
+		assert_eq!(MeloStore::sample_availability(now), None);
+
+		let kzg = KZG::default_embedded();
+		let segments =
+			bytes_to_segments(&bytes, FIELD_ELEMENTS_PER_BLOB, FIELD_ELEMENTS_PER_SEGMENT, &kzg)
+				.unwrap();
+		let mut db = OffchainKv::new(None);
+		let mut confidence = Reliability::new(ReliabilityType::App, &params.commitments);
+		confidence.samples = segments
+			.iter()
+			.take(4)
+			.map(|segment| Sample {
+				id: SampleId::app_sample(1, 1, &segment.position),
+				position: segment.position.clone(),
+				is_availability: true,
+			})
+			.collect();
+		confidence.save(&ReliabilityId::app_confidence(1, 1), &mut db);
+
+		// The samples are recorded as successful, but their segments are not cached.
+		assert_eq!(MeloStore::sample_availability(now), Some(false));
+
+		for segment in segments.iter().take(4) {
+			db.set(&sample_key(1, 1, &segment.position), &segment.content.encode());
+		}
+		assert_eq!(MeloStore::sample_availability(now), Some(true));
+	});
+}
+
+#[test]
+fn should_migrate_to_v1_once() {
+	use frame_support::traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion};
+
+	new_test_ext().execute_with(|| {
+		let now = System::block_number();
+		let (commitments, proofs) = commits_and_proofs(10, 0);
+		assert_ok!(submit_data(1, 1, 10, 1, commitments, proofs));
+		assert_eq!(MeloStore::on_chain_storage_version(), 0);
+
+		migrations::v1::MigrateToV1::<Runtime>::on_runtime_upgrade();
+		assert_eq!(MeloStore::on_chain_storage_version(), 1);
+		assert_eq!(MeloStore::on_chain_storage_version(), MeloStore::current_storage_version());
+		assert_eq!(Metadata::<Runtime>::get(now).len(), 1);
+
+		StorageVersion::new(2).put::<MeloStore>();
+		migrations::v1::MigrateToV1::<Runtime>::on_runtime_upgrade();
+		assert_eq!(MeloStore::on_chain_storage_version(), 2);
+	});
+}
+
+#[test]
+fn should_compact_commitments_past_retention_period() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let bytes_len = 10;
+		let (commitments, proofs) = commits_and_proofs(bytes_len, 0);
+		assert_ok!(submit_data(1, 1, bytes_len, 1, commitments.clone(), proofs));
+		MockDasParams::mutate(|params| params.retention_period = 2);
+
+		MeloStore::compact_commitments(3);
+		assert_eq!(MeloStore::block_commitments(1), BlockCommitments::Full(commitments.clone()));
+
+		// At most `MAX_COMPACTIONS_PER_BLOCK` blocks are compacted at a time.
+		MeloStore::compact_commitments(10);
+		assert_eq!(NextCompaction::<Runtime>::get(), 1 + MAX_COMPACTIONS_PER_BLOCK as u64);
+
+		let root = commitments_root(&commitments);
+		assert_eq!(MeloStore::block_commitments(1), BlockCommitments::Compacted(root));
+		assert!(Metadata::<Runtime>::get(1)[0].commitments.is_empty());
+		assert!(Metadata::<Runtime>::get(1)[0].proofs.is_empty());
+		assert!(events().contains(&Event::<Runtime>::CommitmentsCompacted { at_block: 1, root }));
+
+		MeloStore::compact_commitments(11);
+		assert_eq!(NextCompaction::<Runtime>::get(), 9);
+	});
+}
@@ -6,6 +6,9 @@
 //! WORST CASE MAP SIZE: `1000000`
 //! HOSTNAME: `PC-20180331FBFF`, CPU: `Intel(R) Core(TM) i7-4790K CPU @ 4.00GHz`
 //! EXECUTION: Some(Wasm), WASM-EXECUTION: Compiled, CHAIN: Some("dev"), DB CACHE: 1024
+//!
+//! `validate_unsigned_and_then_vote_availability` was added after this run and is an estimate
+//! until the command below is run again.
 
 // Executed Command:
 // ./target/release/melodot-node
@@ -38,6 +41,7 @@ pub trait WeightInfo {
 	fn register_app() -> Weight;
 	fn submit_data(k: u32, ) -> Weight;
 	fn validate_unsigned_and_then_report(k: u32, e: u32, ) -> Weight;
+	fn validate_unsigned_and_then_vote_availability(k: u32, ) -> Weight;
 }
 
 /// Weights for pallet_melo_store using the Substrate node and recommended hardware.
@@ -94,6 +98,19 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 			.saturating_add(T::DbWeight::get().writes((1_u64).saturating_mul(e.into())))
 			.saturating_add(Weight::from_parts(0, 2901).saturating_mul(e.into()))
 	}
+	/// Storage: MeloStore Keys (r:1 w:0)
+	/// Storage: MeloStore AvailabilityVotes (r:1 w:1)
+	/// Storage: MeloStore AvailabilityTallies (r:1 w:1)
+	/// Storage: MeloStore Metadata (r:1 w:0)
+	/// The range of component `k` is `[1, 1000]`.
+	/// Not benchmarked yet: estimated as `validate_unsigned_and_then_report` without the
+	/// unavailable blobs, the data of the block being read when the vote confirms it.
+	fn validate_unsigned_and_then_vote_availability(k: u32, ) -> Weight {
+		Weight::from_parts(242_300_000, 96773479)
+			.saturating_add(Weight::from_parts(429_668, 0).saturating_mul(k.into()))
+			.saturating_add(T::DbWeight::get().reads(4_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
 }
 
 // For backwards compatibility and tests
@@ -149,4 +166,17 @@ impl WeightInfo for () {
 			.saturating_add(RocksDbWeight::get().writes((1_u64).saturating_mul(e.into())))
 			.saturating_add(Weight::from_parts(0, 2901).saturating_mul(e.into()))
 	}
+	/// Storage: MeloStore Keys (r:1 w:0)
+	/// Storage: MeloStore AvailabilityVotes (r:1 w:1)
+	/// Storage: MeloStore AvailabilityTallies (r:1 w:1)
+	/// Storage: MeloStore Metadata (r:1 w:0)
+	/// The range of component `k` is `[1, 1000]`.
+	/// Not benchmarked yet: estimated as `validate_unsigned_and_then_report` without the
+	/// unavailable blobs, the data of the block being read when the vote confirms it.
+	fn validate_unsigned_and_then_vote_availability(k: u32, ) -> Weight {
+		Weight::from_parts(242_300_000, 96773479)
+			.saturating_add(Weight::from_parts(429_668, 0).saturating_mul(k.into()))
+			.saturating_add(RocksDbWeight::get().reads(4_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
 }
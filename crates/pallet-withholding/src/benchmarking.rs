@@ -0,0 +1,78 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+#[allow(unused_imports)]
+use crate::Pallet as Withholding;
+use frame_benchmarking::v1::{benchmarks, impl_benchmark_test_suite, whitelisted_caller};
+use frame_support::traits::UnfilteredDispatchable;
+use frame_system::{Pallet as System, RawOrigin};
+use melo_core_primitives::{
+	receipt::RECEIPT_KEY_TYPE,
+	reliability::{Reliability, ReliabilityType},
+};
+use sp_core::H256;
+
+/// Generates `count` attester keys in the keystore.
+fn generate_attesters(count: u32) -> Vec<sr25519::Public> {
+	(0..count)
+		.map(|_| sp_io::crypto::sr25519_generate(RECEIPT_KEY_TYPE, None))
+		.collect()
+}
+
+benchmarks! {
+	report_withholding {
+		let a in 1 .. T::MaxAttesters::get();
+		let attesters = generate_attesters(a);
+		Attesters::<T>::put(
+			Withholding::<T>::check_attesters(attesters.clone()).map_err(|_| "Invalid attesters")?,
+		);
+
+		let author = T::ValidatorSet::validators().into_iter().next().ok_or("No validator")?;
+		let at_block = System::<T>::block_number();
+		BlockAuthors::<T>::insert(at_block, author);
+		let block_hash = H256::from_slice(System::<T>::block_hash(at_block).as_ref());
+		System::<T>::set_block_number(at_block.saturating_add(One::one()));
+
+		// No sample was fetched, the data is unavailable.
+		let confidence = Reliability::new(ReliabilityType::Block, &[]);
+		let attestations = attesters
+			.iter()
+			.map(|signer| {
+				let mut receipt = AvailabilityReceipt::unsigned(block_hash, &confidence, *signer);
+				receipt.signature =
+					sp_io::crypto::sr25519_sign(RECEIPT_KEY_TYPE, signer, &receipt.payload())
+						.expect("The key is in the keystore; qed");
+				receipt
+			})
+			.collect::<Vec<_>>();
+		let caller: T::AccountId = whitelisted_caller();
+	}: _(RawOrigin::Signed(caller.clone()), at_block, attestations)
+	verify {
+		assert_eq!(Reporters::<T>::get(at_block), Some(caller));
+	}
+
+	set_attesters {
+		let a in 0 .. T::MaxAttesters::get();
+		let origin = T::UpdateOrigin::try_successful_origin()
+			.map_err(|_| "UpdateOrigin has no successful origin")?;
+		let attesters = generate_attesters(a);
+		let call = Call::<T>::set_attesters { attesters: attesters.clone() };
+	}: { call.dispatch_bypass_filter(origin)? }
+	verify {
+		assert_eq!(Attesters::<T>::get().into_inner(), attesters);
+	}
+}
+
+impl_benchmark_test_suite!(Withholding, crate::mock::new_test_ext(), crate::mock::Runtime);
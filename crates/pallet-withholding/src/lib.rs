@@ -0,0 +1,365 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Withholding Pallet
+//!
+//! Punishes the authors of blocks whose data is withheld.
+//!
+//! The pallet records the author of each block. Within `ReportWindow` blocks, anyone can report
+//! the author of a block whose data could not be sampled, with the [`AvailabilityReceipt`]s of
+//! more than `AttestationThreshold` of the attesters showing the data unavailable. A receipt lists
+//! the positions its node sampled successfully, which are too few for the data to be available.
+//! The report is turned into a [`WithholdingOffence`] against the author, slashed by the
+//! offences pallet, and the reporter is credited with it.
+//!
+//! The attesters are the sampling nodes trusted to sign receipts, set by the `UpdateOrigin`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Decode, Encode};
+use frame_support::{
+	pallet_prelude::*,
+	traits::{EnsureOrigin, ValidatorSet, ValidatorSetWithIdentification},
+};
+use frame_system::pallet_prelude::*;
+pub use melo_core_primitives::receipt::AvailabilityReceipt;
+use scale_info::TypeInfo;
+use sp_core::sr25519;
+use sp_runtime::{
+	traits::{Convert, One, Saturating},
+	Perbill, Permill, RuntimeDebug,
+};
+use sp_staking::{
+	offence::{Kind, Offence, ReportOffence},
+	SessionIndex,
+};
+use sp_std::prelude::*;
+
+pub use pallet::*;
+
+pub mod weights;
+pub use weights::*;
+
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+mod mock;
+mod tests;
+
+/// The id of a validator.
+pub type ValidatorId<T> = <<T as Config>::ValidatorSet as ValidatorSet<
+	<T as frame_system::Config>::AccountId,
+>>::ValidatorId;
+
+/// A validator with its full identification.
+pub type IdentificationTuple<T> = (
+	ValidatorId<T>,
+	<<T as Config>::ValidatorSet as ValidatorSetWithIdentification<
+		<T as frame_system::Config>::AccountId,
+	>>::Identification,
+);
+
+/// The offence of an author whose block data is withheld.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct WithholdingOffence<BlockNumber, Offender> {
+	/// The block whose data is withheld.
+	pub at_block: BlockNumber,
+	/// The session the offence is reported in.
+	pub session_index: SessionIndex,
+	/// The number of validators in the session.
+	pub validator_set_count: u32,
+	/// The author of the block.
+	pub offender: Offender,
+	/// The fraction of the stake of the author slashed.
+	pub slash_fraction: Perbill,
+}
+
+impl<BlockNumber, Offender> Offence<Offender> for WithholdingOffence<BlockNumber, Offender>
+where
+	BlockNumber: Clone + Encode + Decode + Ord,
+	Offender: Clone,
+{
+	const ID: Kind = *b"melo:withholding";
+	type TimeSlot = BlockNumber;
+
+	fn offenders(&self) -> Vec<Offender> {
+		vec![self.offender.clone()]
+	}
+
+	fn session_index(&self) -> SessionIndex {
+		self.session_index
+	}
+
+	fn validator_set_count(&self) -> u32 {
+		self.validator_set_count
+	}
+
+	fn time_slot(&self) -> Self::TimeSlot {
+		self.at_block.clone()
+	}
+
+	fn slash_fraction(&self, _offenders_count: u32) -> Perbill {
+		self.slash_fraction
+	}
+}
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+
+	#[pallet::pallet]
+	pub struct Pallet<T>(_);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// The overarching event type for the runtime.
+		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+		/// Weight information for this pallet's extrinsics.
+		type WeightInfo: WeightInfo;
+
+		/// The validators, among which the authors of the blocks are, identified by their account
+		/// so that the authors kept are bounded.
+		type ValidatorSet: ValidatorSetWithIdentification<
+			Self::AccountId,
+			ValidatorId = Self::AccountId,
+		>;
+
+		/// The handler of the offences of the authors.
+		type ReportWithholding: ReportOffence<
+			Self::AccountId,
+			IdentificationTuple<Self>,
+			WithholdingOffence<Self::BlockNumber, IdentificationTuple<Self>>,
+		>;
+
+		/// The origin allowed to set the attesters.
+		type UpdateOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+		/// The maximum number of attesters.
+		#[pallet::constant]
+		type MaxAttesters: Get<u32>;
+
+		/// The share of the attesters that has to be exceeded by the attestations of a report.
+		#[pallet::constant]
+		type AttestationThreshold: Get<Permill>;
+
+		/// The number of blocks after a block during which its author can be reported.
+		#[pallet::constant]
+		type ReportWindow: Get<Self::BlockNumber>;
+
+		/// The fraction of the stake of an author slashed for withholding the data of a block.
+		#[pallet::constant]
+		type SlashFraction: Get<Perbill>;
+	}
+
+	/// The keys of the sampling nodes whose receipts are accepted as attestations.
+	#[pallet::storage]
+	#[pallet::getter(fn attesters)]
+	pub type Attesters<T: Config> =
+		StorageValue<_, BoundedVec<sr25519::Public, T::MaxAttesters>, ValueQuery>;
+
+	/// The author of each block of the report window.
+	#[pallet::storage]
+	#[pallet::getter(fn block_author)]
+	pub type BlockAuthors<T: Config> =
+		StorageMap<_, Twox64Concat, T::BlockNumber, ValidatorId<T>, OptionQuery>;
+
+	/// The reporter of each block of the report window whose author was reported.
+	#[pallet::storage]
+	#[pallet::getter(fn reporter)]
+	pub type Reporters<T: Config> =
+		StorageMap<_, Twox64Concat, T::BlockNumber, T::AccountId, OptionQuery>;
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig {
+		pub attesters: Vec<sr25519::Public>,
+	}
+
+	#[cfg(feature = "std")]
+	impl Default for GenesisConfig {
+		fn default() -> Self {
+			Self { attesters: Vec::new() }
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig {
+		fn build(&self) {
+			let attesters = Pallet::<T>::check_attesters(self.attesters.clone())
+				.expect("Invalid genesis attesters");
+			Attesters::<T>::put(attesters);
+		}
+	}
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// The attesters were replaced.
+		AttestersSet { count: u32 },
+		/// The author of a block was reported for withholding its data.
+		WithholdingReported {
+			at_block: T::BlockNumber,
+			author: ValidatorId<T>,
+			reporter: T::AccountId,
+			attestations: u32,
+		},
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// There are more attesters than `MaxAttesters`.
+		TooManyAttesters,
+		/// The same attester is listed twice.
+		DuplicateAttester,
+		/// The block is not before the current one.
+		ReportForFutureBlock,
+		/// The block is older than the report window.
+		BlockOutOfWindow,
+		/// The author of the block is unknown.
+		UnknownAuthor,
+		/// The author of the block was already reported.
+		AlreadyReported,
+		/// An attestation is not a receipt of the block showing its data unavailable, signed by
+		/// an attester.
+		InvalidAttestation,
+		/// The attestations do not exceed `AttestationThreshold` of the attesters.
+		NotEnoughAttestations,
+		/// The offence was already reported.
+		DuplicateOffence,
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(now: T::BlockNumber) -> Weight {
+			// Forget the blocks leaving the report window.
+			let expired = now.saturating_sub(T::ReportWindow::get().saturating_add(One::one()));
+			BlockAuthors::<T>::remove(expired);
+			Reporters::<T>::remove(expired);
+			T::DbWeight::get().writes(2)
+		}
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Report the author of block `at_block` for withholding its data, with the receipts of
+		/// the attesters showing it unavailable.
+		#[pallet::call_index(0)]
+		#[pallet::weight(T::WeightInfo::report_withholding(attestations.len() as u32))]
+		pub fn report_withholding(
+			origin: OriginFor<T>,
+			at_block: T::BlockNumber,
+			attestations: Vec<AvailabilityReceipt>,
+		) -> DispatchResult {
+			let reporter = ensure_signed(origin)?;
+
+			let now = <frame_system::Pallet<T>>::block_number();
+			ensure!(at_block < now, Error::<T>::ReportForFutureBlock);
+			ensure!(
+				at_block.saturating_add(T::ReportWindow::get()) >= now,
+				Error::<T>::BlockOutOfWindow
+			);
+			ensure!(!Reporters::<T>::contains_key(at_block), Error::<T>::AlreadyReported);
+
+			let author = BlockAuthors::<T>::get(at_block).ok_or(Error::<T>::UnknownAuthor)?;
+			let block_hash = <frame_system::Pallet<T>>::block_hash(at_block);
+			let count = Self::check_attestations(block_hash.as_ref(), &attestations)?;
+
+			let identification = <T::ValidatorSet as ValidatorSetWithIdentification<
+				T::AccountId,
+			>>::IdentificationOf::convert(author.clone())
+			.ok_or(Error::<T>::UnknownAuthor)?;
+			let offence = WithholdingOffence {
+				at_block,
+				session_index: T::ValidatorSet::session_index(),
+				validator_set_count: T::ValidatorSet::validators().len() as u32,
+				offender: (author.clone(), identification),
+				slash_fraction: T::SlashFraction::get(),
+			};
+			T::ReportWithholding::report_offence(vec![reporter.clone()], offence)
+				.map_err(|_| Error::<T>::DuplicateOffence)?;
+
+			Reporters::<T>::insert(at_block, reporter.clone());
+			Self::deposit_event(Event::WithholdingReported {
+				at_block,
+				author,
+				reporter,
+				attestations: count,
+			});
+			Ok(())
+		}
+
+		/// Replace the attesters by `attesters`.
+		#[pallet::call_index(1)]
+		#[pallet::weight(T::WeightInfo::set_attesters(attesters.len() as u32))]
+		pub fn set_attesters(
+			origin: OriginFor<T>,
+			attesters: Vec<sr25519::Public>,
+		) -> DispatchResult {
+			T::UpdateOrigin::ensure_origin(origin)?;
+			let attesters = Self::check_attesters(attesters)?;
+			let count = attesters.len() as u32;
+			Attesters::<T>::put(attesters);
+			Self::deposit_event(Event::AttestersSet { count });
+			Ok(())
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Returns `attesters` bounded, unless they exceed `MaxAttesters` or repeat a key.
+	pub fn check_attesters(
+		attesters: Vec<sr25519::Public>,
+	) -> Result<BoundedVec<sr25519::Public, T::MaxAttesters>, Error<T>> {
+		for (i, attester) in attesters.iter().enumerate() {
+			ensure!(!attesters[..i].contains(attester), Error::<T>::DuplicateAttester);
+		}
+		attesters.try_into().map_err(|_| Error::<T>::TooManyAttesters)
+	}
+
+	/// Checks that `attestations` are receipts of the block `block_hash` showing its data
+	/// unavailable, each signed by a different attester, and that they exceed
+	/// `AttestationThreshold` of the attesters.
+	///
+	/// Returns the number of attestations.
+	pub fn check_attestations(
+		block_hash: &[u8],
+		attestations: &[AvailabilityReceipt],
+	) -> Result<u32, Error<T>> {
+		let attesters = Attesters::<T>::get();
+		ensure!(attestations.len() <= attesters.len(), Error::<T>::TooManyAttesters);
+
+		for (i, receipt) in attestations.iter().enumerate() {
+			ensure!(
+				receipt.block_hash.as_bytes() == block_hash &&
+					!receipt.available && attesters.contains(&receipt.signer),
+				Error::<T>::InvalidAttestation
+			);
+			ensure!(
+				attestations[..i].iter().all(|other| other.signer != receipt.signer),
+				Error::<T>::DuplicateAttester
+			);
+			receipt.verify().map_err(|_| Error::<T>::InvalidAttestation)?;
+		}
+
+		let count = attestations.len() as u32;
+		let threshold = T::AttestationThreshold::get().mul_floor(attesters.len() as u32);
+		ensure!(count > threshold, Error::<T>::NotEnoughAttestations);
+		Ok(count)
+	}
+}
+
+impl<T: Config> pallet_authorship::EventHandler<ValidatorId<T>, T::BlockNumber> for Pallet<T> {
+	fn note_author(author: ValidatorId<T>) {
+		BlockAuthors::<T>::insert(<frame_system::Pallet<T>>::block_number(), author);
+	}
+}
@@ -0,0 +1,172 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test utilities
+
+#![cfg(test)]
+
+use frame_support::{
+	parameter_types,
+	traits::{ConstU32, ConstU64, GenesisBuild, ValidatorSet, ValidatorSetWithIdentification},
+};
+use frame_system::EnsureRoot;
+use sp_core::{sr25519, Pair, H256};
+use sp_keystore::{testing::MemoryKeystore, KeystoreExt};
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, ConvertInto, IdentityLookup},
+	Perbill, Permill,
+};
+use sp_staking::{
+	offence::{OffenceError, ReportOffence},
+	SessionIndex,
+};
+use std::cell::RefCell;
+
+use crate as pallet_withholding;
+use crate::{Config, WithholdingOffence};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Runtime>;
+type Block = frame_system::mocking::MockBlock<Runtime>;
+
+frame_support::construct_runtime!(
+	pub struct Runtime where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		Withholding: pallet_withholding::{Pallet, Call, Storage, Config, Event<T>},
+	}
+);
+
+impl frame_system::Config for Runtime {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type RuntimeOrigin = RuntimeOrigin;
+	type Index = u64;
+	type BlockNumber = u64;
+	type RuntimeCall = RuntimeCall;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type RuntimeEvent = RuntimeEvent;
+	type BlockHashCount = ConstU64<250>;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+	type OnSetCode = ();
+	type MaxConsumers = ConstU32<16>;
+}
+
+pub const VALIDATORS: [u64; 3] = [11, 12, 13];
+pub const MAX_ATTESTERS: u32 = 4;
+pub const REPORT_WINDOW: u64 = 10;
+
+pub struct TestValidatorSet;
+
+impl ValidatorSet<u64> for TestValidatorSet {
+	type ValidatorId = u64;
+	type ValidatorIdOf = ConvertInto;
+
+	fn session_index() -> SessionIndex {
+		1
+	}
+
+	fn validators() -> Vec<u64> {
+		VALIDATORS.to_vec()
+	}
+}
+
+impl ValidatorSetWithIdentification<u64> for TestValidatorSet {
+	type Identification = u64;
+	type IdentificationOf = ConvertInto;
+}
+
+pub type Offence = WithholdingOffence<u64, (u64, u64)>;
+
+thread_local! {
+	pub static OFFENCES: RefCell<Vec<(Vec<u64>, Offence)>> = RefCell::new(vec![]);
+}
+
+/// Records the offences reported, rejecting the second report of an offender at a block.
+pub struct OffenceHandler;
+
+impl ReportOffence<u64, (u64, u64), Offence> for OffenceHandler {
+	fn report_offence(reporters: Vec<u64>, offence: Offence) -> Result<(), OffenceError> {
+		OFFENCES.with(|offences| {
+			let mut offences = offences.borrow_mut();
+			if offences.iter().any(|(_, known)| known == &offence) {
+				return Err(OffenceError::DuplicateReport)
+			}
+			offences.push((reporters, offence));
+			Ok(())
+		})
+	}
+
+	fn is_known_offence(offenders: &[(u64, u64)], time_slot: &u64) -> bool {
+		OFFENCES.with(|offences| {
+			offences.borrow().iter().any(|(_, offence)| {
+				&offence.at_block == time_slot && offenders.contains(&offence.offender)
+			})
+		})
+	}
+}
+
+parameter_types! {
+	pub const AttestationThreshold: Permill = Permill::from_percent(50);
+	pub const SlashFraction: Perbill = Perbill::from_percent(10);
+}
+
+impl Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type WeightInfo = ();
+	type ValidatorSet = TestValidatorSet;
+	type ReportWithholding = OffenceHandler;
+	type UpdateOrigin = EnsureRoot<u64>;
+	type MaxAttesters = ConstU32<MAX_ATTESTERS>;
+	type AttestationThreshold = AttestationThreshold;
+	type ReportWindow = ConstU64<REPORT_WINDOW>;
+	type SlashFraction = SlashFraction;
+}
+
+/// Returns the key pair of the attester `i`, the first three are attesters at genesis.
+pub fn attester(i: u8) -> sr25519::Pair {
+	sr25519::Pair::from_seed(&[i; 32])
+}
+
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	let mut t = frame_system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+	GenesisBuild::<Runtime>::assimilate_storage(
+		&pallet_withholding::GenesisConfig {
+			attesters: (1..=3).map(|i| attester(i).public()).collect(),
+		},
+		&mut t,
+	)
+	.unwrap();
+	let mut ext: sp_io::TestExternalities = t.into();
+	// The benchmarks generate the keys of the attesters.
+	ext.register_extension(KeystoreExt::new(MemoryKeystore::new()));
+	ext.execute_with(|| System::set_block_number(1));
+	OFFENCES.with(|offences| offences.borrow_mut().clear());
+	ext
+}
@@ -0,0 +1,208 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(test)]
+
+use super::*;
+use crate::mock::*;
+use frame_support::{assert_noop, assert_ok, traits::Hooks};
+use melo_core_primitives::{
+	reliability::{Reliability, ReliabilityType, Sample},
+	Position,
+};
+use pallet_authorship::EventHandler;
+use sp_core::{Pair, H256};
+use sp_runtime::DispatchError;
+
+const AT_BLOCK: u64 = 5;
+const AUTHOR: u64 = 12;
+const REPORTER: u64 = 100;
+
+fn block_hash() -> H256 {
+	H256::repeat_byte(5)
+}
+
+/// Returns the receipt of the attester `index` on the block of `hash`, with `success` of 8 samples
+/// fetched.
+fn receipt(index: u8, hash: H256, success: u32) -> AvailabilityReceipt {
+	let mut confidence = Reliability::new(ReliabilityType::Block, &[]);
+	confidence.samples = (0..8)
+		.map(|x| Sample {
+			position: Position { x, y: 0 },
+			is_availability: x < success,
+			..Default::default()
+		})
+		.collect();
+	AvailabilityReceipt::sign(hash, &confidence, &attester(index))
+}
+
+fn withheld(attesters: &[u8]) -> Vec<AvailabilityReceipt> {
+	attesters.iter().map(|&i| receipt(i, block_hash(), 2)).collect()
+}
+
+/// Authors `AT_BLOCK` with `AUTHOR` and moves to the next block.
+fn author_block() {
+	System::set_block_number(AT_BLOCK);
+	Withholding::note_author(AUTHOR);
+	frame_system::BlockHash::<Runtime>::insert(AT_BLOCK, block_hash());
+	System::set_block_number(AT_BLOCK + 1);
+}
+
+#[test]
+fn genesis_attesters_should_be_set() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(
+			Withholding::attesters().into_inner(),
+			(1..=3).map(|i| attester(i).public()).collect::<Vec<_>>()
+		);
+	});
+}
+
+#[test]
+fn should_record_authors_within_the_window() {
+	new_test_ext().execute_with(|| {
+		author_block();
+		assert_eq!(Withholding::block_author(AT_BLOCK), Some(AUTHOR));
+
+		Withholding::on_initialize(AT_BLOCK + REPORT_WINDOW);
+		assert_eq!(Withholding::block_author(AT_BLOCK), Some(AUTHOR));
+		Withholding::on_initialize(AT_BLOCK + REPORT_WINDOW + 1);
+		assert_eq!(Withholding::block_author(AT_BLOCK), None);
+	});
+}
+
+#[test]
+fn should_report_withholding_author() {
+	new_test_ext().execute_with(|| {
+		author_block();
+		assert_ok!(Withholding::report_withholding(
+			RuntimeOrigin::signed(REPORTER),
+			AT_BLOCK,
+			withheld(&[1, 3]),
+		));
+
+		System::assert_last_event(
+			Event::WithholdingReported {
+				at_block: AT_BLOCK,
+				author: AUTHOR,
+				reporter: REPORTER,
+				attestations: 2,
+			}
+			.into(),
+		);
+		assert_eq!(Withholding::reporter(AT_BLOCK), Some(REPORTER));
+		OFFENCES.with(|offences| {
+			assert_eq!(
+				*offences.borrow(),
+				vec![(
+					vec![REPORTER],
+					WithholdingOffence {
+						at_block: AT_BLOCK,
+						session_index: 1,
+						validator_set_count: 3,
+						offender: (AUTHOR, AUTHOR),
+						slash_fraction: SlashFraction::get(),
+					}
+				)]
+			);
+		});
+
+		assert_noop!(
+			Withholding::report_withholding(
+				RuntimeOrigin::signed(REPORTER + 1),
+				AT_BLOCK,
+				withheld(&[1, 2, 3]),
+			),
+			Error::<Runtime>::AlreadyReported
+		);
+	});
+}
+
+#[test]
+fn should_require_a_quorum_of_attestations() {
+	new_test_ext().execute_with(|| {
+		author_block();
+		let report = |attestations| {
+			Withholding::report_withholding(RuntimeOrigin::signed(REPORTER), AT_BLOCK, attestations)
+		};
+
+		assert_noop!(report(withheld(&[2])), Error::<Runtime>::NotEnoughAttestations);
+		assert_noop!(report(withheld(&[2, 2])), Error::<Runtime>::DuplicateAttester);
+		// Not an attester.
+		assert_noop!(report(withheld(&[1, 4])), Error::<Runtime>::InvalidAttestation);
+
+		// A receipt showing the data available.
+		let mut attestations = withheld(&[1]);
+		attestations.push(receipt(2, block_hash(), 8));
+		assert_noop!(report(attestations), Error::<Runtime>::InvalidAttestation);
+
+		// A receipt of another block.
+		let mut attestations = withheld(&[1]);
+		attestations.push(receipt(2, H256::repeat_byte(6), 2));
+		assert_noop!(report(attestations), Error::<Runtime>::InvalidAttestation);
+
+		// A tampered receipt.
+		let mut attestations = withheld(&[1, 2]);
+		attestations[1].positions.clear();
+		assert_noop!(report(attestations), Error::<Runtime>::InvalidAttestation);
+
+		assert!(OFFENCES.with(|offences| offences.borrow().is_empty()));
+	});
+}
+
+#[test]
+fn should_only_report_blocks_of_the_window() {
+	new_test_ext().execute_with(|| {
+		author_block();
+		let report = |at_block| {
+			Withholding::report_withholding(
+				RuntimeOrigin::signed(REPORTER),
+				at_block,
+				withheld(&[1, 2]),
+			)
+		};
+
+		assert_noop!(report(AT_BLOCK + 1), Error::<Runtime>::ReportForFutureBlock);
+		assert_noop!(report(AT_BLOCK - 1), Error::<Runtime>::UnknownAuthor);
+
+		System::set_block_number(AT_BLOCK + REPORT_WINDOW + 1);
+		assert_noop!(report(AT_BLOCK), Error::<Runtime>::BlockOutOfWindow);
+	});
+}
+
+#[test]
+fn should_set_attesters() {
+	new_test_ext().execute_with(|| {
+		let attesters: Vec<_> = (4..=5).map(|i| attester(i).public()).collect();
+		assert_noop!(
+			Withholding::set_attesters(RuntimeOrigin::signed(REPORTER), attesters.clone()),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			Withholding::set_attesters(RuntimeOrigin::root(), vec![attesters[0], attesters[0]]),
+			Error::<Runtime>::DuplicateAttester
+		);
+		assert_noop!(
+			Withholding::set_attesters(
+				RuntimeOrigin::root(),
+				(1..=MAX_ATTESTERS as u8 + 1).map(|i| attester(i).public()).collect()
+			),
+			Error::<Runtime>::TooManyAttesters
+		);
+
+		assert_ok!(Withholding::set_attesters(RuntimeOrigin::root(), attesters.clone()));
+		assert_eq!(Withholding::attesters().into_inner(), attesters);
+		System::assert_last_event(Event::AttestersSet { count: 2 }.into());
+	});
+}
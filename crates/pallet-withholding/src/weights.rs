@@ -0,0 +1,73 @@
+//! Weights for pallet_withholding
+//!
+//! The pallet has not been benchmarked yet, these weights are estimates from the storage
+//! accesses of each call. Replace them with the output of `melodot-node benchmark pallet
+//! --pallet=pallet-withholding` once it is run on the reference hardware.
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use core::marker::PhantomData;
+
+/// Weight functions needed for pallet_withholding.
+pub trait WeightInfo {
+	fn report_withholding(a: u32, ) -> Weight;
+	fn set_attesters(a: u32, ) -> Weight;
+}
+
+/// Weights for pallet_withholding (estimates).
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	/// Storage: Withholding Reporters (r:1 w:1)
+	/// Storage: Withholding BlockAuthors (r:1 w:0)
+	/// Storage: System BlockHash (r:1 w:0)
+	/// Storage: Withholding Attesters (r:1 w:0)
+	/// Storage: Session CurrentIndex (r:1 w:0)
+	/// Storage: Session Validators (r:1 w:0)
+	/// Storage: Offences ConcurrentReportsIndex (r:1 w:1)
+	/// Storage: Offences Reports (r:1 w:1)
+	/// The range of component `a` is `[1, 100]`.
+	fn report_withholding(a: u32, ) -> Weight {
+		Weight::from_parts(61_416_000, 4486)
+			.saturating_add(Weight::from_parts(51_233_000, 0).saturating_mul(a.into()))
+			.saturating_add(T::DbWeight::get().reads(8_u64))
+			.saturating_add(T::DbWeight::get().writes(3_u64))
+			.saturating_add(Weight::from_parts(0, 32).saturating_mul(a.into()))
+	}
+	/// Storage: Withholding Attesters (r:0 w:1)
+	/// The range of component `a` is `[0, 100]`.
+	fn set_attesters(a: u32, ) -> Weight {
+		Weight::from_parts(10_215_000, 0)
+			.saturating_add(Weight::from_parts(98_000, 0).saturating_mul(a.into()))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	/// Storage: Withholding Reporters (r:1 w:1)
+	/// Storage: Withholding BlockAuthors (r:1 w:0)
+	/// Storage: System BlockHash (r:1 w:0)
+	/// Storage: Withholding Attesters (r:1 w:0)
+	/// Storage: Session CurrentIndex (r:1 w:0)
+	/// Storage: Session Validators (r:1 w:0)
+	/// Storage: Offences ConcurrentReportsIndex (r:1 w:1)
+	/// Storage: Offences Reports (r:1 w:1)
+	/// The range of component `a` is `[1, 100]`.
+	fn report_withholding(a: u32, ) -> Weight {
+		Weight::from_parts(61_416_000, 4486)
+			.saturating_add(Weight::from_parts(51_233_000, 0).saturating_mul(a.into()))
+			.saturating_add(RocksDbWeight::get().reads(8_u64))
+			.saturating_add(RocksDbWeight::get().writes(3_u64))
+			.saturating_add(Weight::from_parts(0, 32).saturating_mul(a.into()))
+	}
+	/// Storage: Withholding Attesters (r:0 w:1)
+	/// The range of component `a` is `[0, 100]`.
+	fn set_attesters(a: u32, ) -> Weight {
+		Weight::from_parts(10_215_000, 0)
+			.saturating_add(Weight::from_parts(98_000, 0).saturating_mul(a.into()))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+}
@@ -0,0 +1,203 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Piece assignment.
+//!
+//! Defines which piece positions of a block a farmer is responsible for. Every position of a
+//! block has a key derived from the block hash and the position. A farmer is assigned the
+//! [`ASSIGNED_ROWS`] rows and [`ASSIGNED_COLUMNS`] columns whose keys are the closest to its
+//! [`FarmerId`] by XOR distance.
+//!
+//! The rule only depends on public data, so that farmers use it to decide what to store and
+//! `pallet-farmers-fortune` uses [`is_assigned`] to reject the claims whose winning cells are out
+//! of the pieces assigned to the farmer.
+
+use crate::{BlakeTwo256, Encode, FarmerId, HashT, PiecePosition, Vec, H256};
+use melo_core_primitives::config::EXTENDED_SEGMENTS_PER_BLOB;
+
+/// Number of rows assigned to each farmer per block.
+pub const ASSIGNED_ROWS: u32 = 2;
+
+/// Number of columns assigned to each farmer per block.
+pub const ASSIGNED_COLUMNS: u32 = 2;
+
+/// Returns the key of `position` in the block `block_hash`.
+pub fn position_key(block_hash: &H256, position: &PiecePosition) -> H256 {
+	BlakeTwo256::hash(&(block_hash, position).encode())
+}
+
+/// Returns the XOR distance between `farmer_id` and `key`.
+pub fn distance(farmer_id: &FarmerId, key: &H256) -> H256 {
+	let mut out = [0u8; 32];
+	out.iter_mut()
+		.zip(farmer_id.0.as_bytes().iter().zip(key.as_bytes().iter()))
+		.for_each(|(o, (a, b))| *o = a ^ b);
+	H256(out)
+}
+
+/// Returns the `n` positions closest to `farmer_id` among `positions`, sorted by index.
+///
+/// Ties, which require a hash collision, are broken by the lower index.
+pub fn closest_positions(
+	farmer_id: &FarmerId,
+	block_hash: &H256,
+	positions: impl Iterator<Item = PiecePosition>,
+	n: u32,
+) -> Vec<u32> {
+	let mut by_distance = positions
+		.map(|position| {
+			(distance(farmer_id, &position_key(block_hash, &position)), position.to_u32())
+		})
+		.collect::<Vec<_>>();
+	by_distance.sort();
+
+	let mut indices = by_distance
+		.into_iter()
+		.take(n as usize)
+		.map(|(_, index)| index)
+		.collect::<Vec<_>>();
+	indices.sort();
+	indices
+}
+
+/// Returns the rows of a block assigned to `farmer_id`.
+///
+/// # Arguments
+///
+/// * `farmer_id` - The farmer.
+/// * `block_hash` - The hash of the block.
+/// * `rows_count` - The number of extended rows of the block.
+pub fn assigned_rows(farmer_id: &FarmerId, block_hash: &H256, rows_count: u32) -> Vec<u32> {
+	closest_positions(farmer_id, block_hash, (0..rows_count).map(PiecePosition::Row), ASSIGNED_ROWS)
+}
+
+/// Returns the columns of a block assigned to `farmer_id`.
+pub fn assigned_columns(farmer_id: &FarmerId, block_hash: &H256) -> Vec<u32> {
+	closest_positions(
+		farmer_id,
+		block_hash,
+		(0..EXTENDED_SEGMENTS_PER_BLOB as u32).map(PiecePosition::Column),
+		ASSIGNED_COLUMNS,
+	)
+}
+
+/// Checks whether `position` of a block is assigned to `farmer_id`.
+///
+/// # Arguments
+///
+/// * `farmer_id` - The farmer.
+/// * `block_hash` - The hash of the block.
+/// * `position` - The position to check.
+/// * `rows_count` - The number of extended rows of the block.
+///
+/// # Returns
+///
+/// Returns `false` if the position is out of the block.
+pub fn is_assigned(
+	farmer_id: &FarmerId,
+	block_hash: &H256,
+	position: &PiecePosition,
+	rows_count: u32,
+) -> bool {
+	let (total, n) = match position {
+		PiecePosition::Row(_) => (rows_count, ASSIGNED_ROWS),
+		PiecePosition::Column(_) => (EXTENDED_SEGMENTS_PER_BLOB as u32, ASSIGNED_COLUMNS),
+	};
+	let index = position.to_u32();
+	if index >= total {
+		return false
+	}
+
+	let own = (distance(farmer_id, &position_key(block_hash, position)), index);
+	let closer = (0..total)
+		.map(|i| match position {
+			PiecePosition::Row(_) => PiecePosition::Row(i),
+			PiecePosition::Column(_) => PiecePosition::Column(i),
+		})
+		.filter(|other| {
+			(distance(farmer_id, &position_key(block_hash, other)), other.to_u32()) < own
+		})
+		.count();
+
+	(closer as u32) < n
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_assigned_rows_count_and_order() {
+		let farmer_id = FarmerId::new(1u32);
+		let block_hash = H256::repeat_byte(3);
+
+		let rows = assigned_rows(&farmer_id, &block_hash, 16);
+		assert_eq!(rows.len(), ASSIGNED_ROWS as usize);
+		assert!(rows.windows(2).all(|w| w[0] < w[1]));
+		assert!(rows.iter().all(|row| *row < 16));
+
+		let rows = assigned_rows(&farmer_id, &block_hash, 1);
+		assert_eq!(rows, vec![0]);
+	}
+
+	#[test]
+	fn test_assignment_is_deterministic() {
+		let farmer_id = FarmerId::new(7u32);
+		let block_hash = H256::repeat_byte(3);
+
+		assert_eq!(
+			assigned_rows(&farmer_id, &block_hash, 32),
+			assigned_rows(&farmer_id, &block_hash, 32)
+		);
+		assert_eq!(
+			assigned_columns(&farmer_id, &block_hash),
+			assigned_columns(&farmer_id, &block_hash)
+		);
+	}
+
+	#[test]
+	fn test_is_assigned_matches_assigned_positions() {
+		let farmer_id = FarmerId::new(42u32);
+		let block_hash = H256::repeat_byte(3);
+		let rows_count = 8;
+
+		let rows = assigned_rows(&farmer_id, &block_hash, rows_count);
+		for row in 0..rows_count {
+			assert_eq!(
+				is_assigned(&farmer_id, &block_hash, &PiecePosition::Row(row), rows_count),
+				rows.contains(&row)
+			);
+		}
+
+		let columns = assigned_columns(&farmer_id, &block_hash);
+		for column in 0..EXTENDED_SEGMENTS_PER_BLOB as u32 {
+			assert_eq!(
+				is_assigned(&farmer_id, &block_hash, &PiecePosition::Column(column), rows_count),
+				columns.contains(&column)
+			);
+		}
+
+		assert!(!is_assigned(&farmer_id, &block_hash, &PiecePosition::Row(rows_count), rows_count));
+	}
+
+	#[test]
+	fn test_different_farmers_get_different_positions() {
+		let block_hash = H256::repeat_byte(3);
+		let assignments = (0..16u32)
+			.map(|i| assigned_columns(&FarmerId::new(i + 1), &block_hash))
+			.collect::<Vec<_>>();
+
+		assert!(assignments.iter().any(|columns| *columns != assignments[0]));
+	}
+}
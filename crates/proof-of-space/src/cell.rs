@@ -15,7 +15,7 @@
 use crate::{
     Decode, Encode, PieceMetadata, PiecePosition, YPos, EXTENDED_SEGMENTS_PER_BLOB,
 };
-use melo_core_primitives::config::SEGMENTS_PER_BLOB;
+use melo_core_primitives::{config::SEGMENTS_PER_BLOB, SegmentCheck};
 use melo_das_primitives::{KZGCommitment, Segment, KZG};
 use scale_info::TypeInfo;
 use sp_core::RuntimeDebug;
@@ -83,7 +83,12 @@ where
 
     /// Verifies the KZG proof for this cell's segment of data.
     pub fn verify_kzg_proof(&self, kzg: &KZG, commitment: &KZGCommitment) -> bool {
-        self.seg.verify(kzg, commitment, SEGMENTS_PER_BLOB).unwrap_or(false)
+        self.segment_check(commitment).verify(kzg)
+    }
+
+    /// Returns the check of the KZG proof of this cell's segment against `commitment`.
+    pub fn segment_check(&self, commitment: &KZGCommitment) -> SegmentCheck {
+        SegmentCheck::new(*commitment, self.seg.clone(), SEGMENTS_PER_BLOB as u32)
     }
 }
 
@@ -110,7 +115,12 @@ impl PreCell {
 
     /// Verifies the KZG proof for this pre-cell's segment of data.
     pub fn verify_kzg_proof(&self, kzg: &KZG, commitment: &KZGCommitment) -> bool {
-        self.seg.verify(kzg, commitment, EXTENDED_SEGMENTS_PER_BLOB).unwrap_or(false)
+        self.segment_check(commitment).verify(kzg)
+    }
+
+    /// Returns the check of the KZG proof of this pre-cell's segment against `commitment`.
+    pub fn segment_check(&self, commitment: &KZGCommitment) -> SegmentCheck {
+        SegmentCheck::new(*commitment, self.seg.clone(), EXTENDED_SEGMENTS_PER_BLOB as u32)
     }
 }
 
@@ -0,0 +1,191 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The hash functions of plots.
+//!
+//! A [`PlotHasher`] is implemented for each [`PlotHashing`] scheme. [`farmer_id`], [`cell_y`]
+//! and [`cell_z`] hash with the scheme set by governance. The schemes other than
+//! [`PlotHashing::Blake2`] prefix their inputs with a domain separation tag.
+
+use crate::{utils, ChaCha8, Encode, FarmerId, KeyIvInit, Nonce, StreamCipher, Vec, H256};
+use chacha20::cipher::generic_array::GenericArray;
+pub use melo_core_primitives::PlotHashing;
+use melo_das_primitives::Segment;
+use sp_core::hashing::{blake2_256, keccak_256};
+
+/// Domain separation tag of the farmer IDs.
+pub const FARMER_ID_TAG: &[u8] = b"melodot/farmer-id";
+
+/// Domain separation tag of the Y values of cells.
+pub const CELL_Y_TAG: &[u8] = b"melodot/cell-y";
+
+/// Domain separation tag of the Z values of pairs of cells.
+pub const CELL_Z_TAG: &[u8] = b"melodot/cell-z";
+
+/// A hash function with 32-byte outputs.
+pub trait PlotHasher {
+	/// The scheme of the hash function.
+	const SCHEME: PlotHashing;
+
+	/// Hashes `data`.
+	fn hash(data: &[u8]) -> [u8; 32];
+
+	/// Hashes `data` in the domain `tag`. The tag is prefixed with its length, so that the inputs
+	/// of two domains never collide.
+	fn hash_tagged(tag: &[u8], data: &[u8]) -> [u8; 32] {
+		let mut input = Vec::with_capacity(1 + tag.len() + data.len());
+		input.push(tag.len() as u8);
+		input.extend_from_slice(tag);
+		input.extend_from_slice(data);
+		Self::hash(&input)
+	}
+}
+
+/// BLAKE2b-256.
+pub struct Blake2Hasher;
+
+impl PlotHasher for Blake2Hasher {
+	const SCHEME: PlotHashing = PlotHashing::Blake2;
+
+	fn hash(data: &[u8]) -> [u8; 32] {
+		blake2_256(data)
+	}
+}
+
+/// BLAKE3.
+pub struct Blake3Hasher;
+
+impl PlotHasher for Blake3Hasher {
+	const SCHEME: PlotHashing = PlotHashing::Blake3;
+
+	fn hash(data: &[u8]) -> [u8; 32] {
+		blake3::hash(data).into()
+	}
+}
+
+/// Keccak-256.
+pub struct KeccakHasher;
+
+impl PlotHasher for KeccakHasher {
+	const SCHEME: PlotHashing = PlotHashing::Keccak;
+
+	fn hash(data: &[u8]) -> [u8; 32] {
+		keccak_256(data)
+	}
+}
+
+/// Hashes `data` with `hashing`, in the domain `tag` unless the scheme is
+/// [`PlotHashing::Blake2`].
+fn hash(hashing: PlotHashing, tag: &[u8], data: &[u8]) -> [u8; 32] {
+	match hashing {
+		PlotHashing::Blake2 => Blake2Hasher::hash(data),
+		PlotHashing::Blake3 => Blake3Hasher::hash_tagged(tag, data),
+		PlotHashing::Keccak => KeccakHasher::hash_tagged(tag, data),
+	}
+}
+
+/// Returns the farmer ID of the encoded account `account`.
+pub fn farmer_id(hashing: PlotHashing, account: &[u8]) -> H256 {
+	H256(hash(hashing, FARMER_ID_TAG, account))
+}
+
+/// Returns the Y value of `seg` in the plot of `farmer_id`, `0` if the segment is empty.
+///
+/// [`PlotHashing::Blake2`] derives it from the ChaCha8 keystream keyed by the farmer ID and the
+/// first field element of the segment, the other schemes from the hash of both.
+pub fn cell_y(hashing: PlotHashing, farmer_id: &FarmerId, seg: &Segment) -> u32 {
+	if seg.content.data.is_empty() {
+		return 0u32
+	}
+	let bytes = utils::xor_byte_slices(&farmer_id.encode(), &seg.content.data[0].encode());
+	let output = match hashing {
+		PlotHashing::Blake2 => {
+			let nonce = Nonce::default();
+			let key = GenericArray::from_slice(&bytes);
+			let mut cipher = ChaCha8::new(key, &nonce);
+			let mut buffer = [0u8; 32];
+			cipher.apply_keystream(&mut buffer);
+			buffer
+		},
+		_ => hash(hashing, CELL_Y_TAG, &bytes),
+	};
+	utils::fold_hash(&output)
+}
+
+/// Returns the Z value of the pair of `left_cell` and `right_cell`.
+pub fn cell_z(hashing: PlotHashing, left_cell: &Segment, right_cell: &Segment) -> u16 {
+	let combined = [left_cell.encode(), right_cell.encode()].concat();
+	utils::hash_to_u16_xor(&hash(hashing, CELL_Z_TAG, &combined))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{mock::*, BlakeTwo256, HashT};
+	use melo_core_primitives::config::FIELD_ELEMENTS_PER_SEGMENT;
+
+	const SCHEMES: [PlotHashing; 3] =
+		[PlotHashing::Blake2, PlotHashing::Blake3, PlotHashing::Keccak];
+
+	#[test]
+	fn test_blake2_is_the_original_scheme() {
+		let account = 42u64.encode();
+		assert_eq!(farmer_id(PlotHashing::Blake2, &account), BlakeTwo256::hash(&account));
+
+		let seg = get_mock_seg(&BLS_SCALAR11, 0, 0, &PROOF_11, FIELD_ELEMENTS_PER_SEGMENT);
+		assert_eq!(cell_y(PlotHashing::Blake2, &FarmerId::default(), &seg), Y1);
+
+		let left = get_mock_seg(&BLS_SCALAR11, 0, 0, &PROOF_11, 16);
+		let right = get_mock_seg(&BLS_SCALAR12, 1, 0, &PROOF_12, 16);
+		assert_eq!(cell_z(PlotHashing::Blake2, &left, &right), Z1);
+	}
+
+	#[test]
+	fn test_schemes_disagree() {
+		let account = 42u64.encode();
+		let seg = get_mock_seg(&BLS_SCALAR11, 0, 0, &PROOF_11, FIELD_ELEMENTS_PER_SEGMENT);
+		let farmer = FarmerId::new(42u64);
+
+		for (i, a) in SCHEMES.iter().enumerate() {
+			for b in &SCHEMES[i + 1..] {
+				assert_ne!(farmer_id(*a, &account), farmer_id(*b, &account));
+				assert_ne!(cell_y(*a, &farmer, &seg), cell_y(*b, &farmer, &seg));
+			}
+		}
+	}
+
+	#[test]
+	fn test_tags_separate_domains() {
+		let data = b"melodot";
+		assert_ne!(Blake3Hasher::hash_tagged(CELL_Y_TAG, data), Blake3Hasher::hash(data));
+		assert_ne!(
+			Blake3Hasher::hash_tagged(CELL_Y_TAG, data),
+			Blake3Hasher::hash_tagged(CELL_Z_TAG, data)
+		);
+		// The length prefix keeps a tag from being extended with the start of the data.
+		assert_ne!(
+			KeccakHasher::hash_tagged(b"melodot/cell", b"-ydata"),
+			KeccakHasher::hash_tagged(b"melodot/cell-y", b"data")
+		);
+	}
+
+	#[test]
+	fn test_empty_segment_has_no_y() {
+		let mut seg = get_mock_seg(&BLS_SCALAR11, 0, 0, &PROOF_11, FIELD_ELEMENTS_PER_SEGMENT);
+		seg.content.data.clear();
+		for scheme in SCHEMES {
+			assert_eq!(cell_y(scheme, &FarmerId::default(), &seg), 0);
+		}
+	}
+}
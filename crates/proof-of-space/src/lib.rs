@@ -20,7 +20,7 @@ pub(crate) use chacha20::{
 	cipher::{KeyIvInit, StreamCipher},
 	ChaCha8, Nonce,
 };
-use codec::{Decode, Encode};
+use codec::{Decode, Encode, MaxEncodedLen};
 use melo_core_primitives::config::EXTENDED_SEGMENTS_PER_BLOB;
 #[cfg(feature = "std")]
 use melo_das_db::traits::DasKv;
@@ -33,22 +33,40 @@ pub(crate) use sp_runtime::traits::{BlakeTwo256, Hash as HashT};
 
 pub mod mock;
 
+pub mod assignment;
 pub mod cell;
+pub mod hashing;
 pub mod piece;
+#[cfg(feature = "std")]
+pub mod plot;
 pub mod solution;
 pub mod utils;
 pub mod y_value_manager;
 pub mod z_value_manager;
 
 pub use cell::{Cell, CellMetadata, PreCell};
+pub use hashing::{PlotHasher, PlotHashing};
 pub use piece::{Piece, PieceMetadata, PiecePosition};
 #[cfg(feature = "std")]
+pub use plot::{plot_hashing, PlotProgress, PlotUpdate};
+#[cfg(feature = "std")]
 pub use solution::find_solutions;
-pub use solution::Solution;
+pub use solution::{CompactSolution, Solution};
 pub use y_value_manager::{YValueManager, YPos};
 pub use z_value_manager::ZValueManager;
+#[cfg(feature = "std")]
+pub use z_value_manager::ZBucketStats;
+
+/// Context of the message a reward account signs to bind a [`FarmerId`] to itself.
+pub const FARMER_BINDING_CONTEXT: &[u8] = b"melodot/farmer-binding";
+
+/// Context of the message a reward account signs to hand its [`FarmerId`] over to a new account.
+pub const FARMER_ROTATION_CONTEXT: &[u8] = b"melodot/farmer-rotation";
+
+/// Context of the challenges derived by [`Solution::derive_challenge`].
+pub const CHALLENGE_CONTEXT: &[u8] = b"melodot/challenge";
 
-#[derive(Default, Encode, Decode, Clone, PartialEq, Eq, Debug, TypeInfo)]
+#[derive(Default, Encode, Decode, Clone, PartialEq, Eq, Debug, TypeInfo, MaxEncodedLen)]
 pub struct FarmerId(pub H256);
 
 impl From<H256> for FarmerId {
@@ -76,13 +94,37 @@ impl AsRef<[u8]> for FarmerId {
 }
 
 impl FarmerId {
+	/// Returns the farmer ID of `t` with the original [`PlotHashing::Blake2`] scheme.
 	pub fn new<T: Encode>(t: T) -> Self {
+		Self::new_with(PlotHashing::Blake2, t)
+	}
+
+	/// Returns the farmer ID of `t` with the scheme `hashing`.
+	pub fn new_with<T: Encode>(hashing: PlotHashing, t: T) -> Self {
 		let encoded = t.encode();
 		if encoded.iter().all(|&byte| byte == 0) {
 			FarmerId(H256::default())
 		} else {
-			let h256 = BlakeTwo256::hash(&encoded);
-			FarmerId(h256)
+			FarmerId(hashing::farmer_id(hashing, &encoded))
 		}
 	}
+
+	/// Returns the message a reward account signs to bind this ID to itself.
+	pub fn binding_message(&self) -> Vec<u8> {
+		(FARMER_BINDING_CONTEXT, self).encode()
+	}
+
+	/// Returns the message the reward account bound to this ID signs to hand it over to
+	/// `new_account`, `nonce` being the number of times the ID was handed over before.
+	///
+	/// The ID, and so the plots derived from it, stays the same, only the account it is bound to
+	/// changes. The nonce keeps the message of a rotation from being replayed once the ID is
+	/// handed back to the account that signed it.
+	pub fn rotation_message<AccountId: Encode>(
+		&self,
+		new_account: &AccountId,
+		nonce: u32,
+	) -> Vec<u8> {
+		(FARMER_ROTATION_CONTEXT, self, nonce, new_account).encode()
+	}
 }
@@ -12,10 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 #[cfg(feature = "std")]
-use crate::{CellMetadata, DasKv, YPos, ZValueManager};
-use crate::{Decode, Encode, FarmerId, Segment, Vec, YValueManager};
+use crate::{CellMetadata, DasKv, PlotUpdate};
+use crate::{Decode, Encode, FarmerId, PlotHashing, Segment, Vec, YValueManager};
 #[cfg(feature = "std")]
-use anyhow::{anyhow, Ok, Result};
+use anyhow::{anyhow, Result};
 use melo_das_primitives::Position;
 use scale_info::TypeInfo;
 
@@ -124,10 +124,11 @@ where
 	/// This is useful for processing or iterating over segments with their computed x-values.
 	pub fn x_values_iterator<'a>(
 		&'a self,
+		hashing: PlotHashing,
 		farmer_id: &'a FarmerId,
 	) -> impl Iterator<Item = (u32, &Segment)> + 'a {
 		self.segments.iter().map(move |segment| {
-			let y = YValueManager::<BlockNumber>::calculate_y(farmer_id, segment);
+			let y = YValueManager::<BlockNumber>::calculate_y(hashing, farmer_id, segment);
 			(y, segment)
 		})
 	}
@@ -160,45 +161,20 @@ where
 
 	/// Saves the `Piece` to the database. This process involves handling all data within the
 	/// `Piece`, including calculating Y and Z values, and storing the corresponding index pairs.
+	///
+	/// To save several pieces at once, use [`PlotUpdate`] instead.
 	#[cfg(feature = "std")]
-	pub fn save(&self, db: &mut impl DasKv, farmer_id: &FarmerId) -> Result<()> {
-		let metadata_clone = self.metadata.clone();
-		db.set(&self.key(), &self.encode());
-
-		self.x_values_iterator(farmer_id).enumerate().try_for_each(
-			|(index, (x, bls_scalar_ref))| {
-				let cell_metadata =
-					CellMetadata { piece_metadata: metadata_clone.clone(), offset: index as u32 };
-
-				let x_value_manager =
-					YValueManager::<BlockNumber>::new(&metadata_clone, index as u32, x);
-
-				let x_pos = YPos::from_u32(index as u32);
-
-				x_value_manager.save(db);
-
-				let match_cells = x_value_manager.match_cells(db)?;
-
-				for mc in match_cells {
-					if let Some(seg) = Self::get_cell(&mc, db)? {
-						match x_pos {
-							YPos::Left(_) => {
-								let z_value_manager =
-									ZValueManager::new(&cell_metadata, &mc, bls_scalar_ref, &seg);
-								z_value_manager.save(db);
-							},
-							YPos::Right(_) => {
-								let z_value_manager =
-									ZValueManager::new(&cell_metadata, &mc, &seg, bls_scalar_ref);
-								z_value_manager.save(db);
-							},
-						}
-					}
-				}
-				Ok(())
-			},
-		)?;
-		Ok(())
+	pub fn save(
+		&self,
+		db: &mut impl DasKv,
+		hashing: PlotHashing,
+		farmer_id: &FarmerId,
+	) -> Result<()>
+	where
+		BlockNumber: Send + Sync,
+	{
+		PlotUpdate::save_pieces(db, hashing, farmer_id, sp_std::slice::from_ref(self), |_| ())
+			.map(|_| ())
 	}
 }
 
@@ -248,7 +224,7 @@ mod tests {
 
 		let farmer_id = FarmerId::default();
 
-		assert!(piece.save(&mut db, &farmer_id).is_ok());
+		assert!(piece.save(&mut db, PlotHashing::Blake2, &farmer_id).is_ok());
 
 		let key = piece.key();
 		assert!(db.contains(&key));
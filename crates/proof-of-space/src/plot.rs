@@ -0,0 +1,442 @@
+// Copyright 2023 ZeroDAO
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Incremental plot updates.
+//!
+//! A [`PlotUpdate`] adds a batch of pieces, usually those of one block, to a plot. Every Y and Z
+//! entry affected by the batch is read once and stored pieces are decoded at most once, instead
+//! of once per matching cell. The matches are computed in parallel, and all the entries are
+//! written in a single batch.
+
+use crate::{
+	CellMetadata, DasKv, Decode, Encode, FarmerId, Piece, PlotHashing, Segment, YPos,
+	YValueManager, ZValueManager,
+};
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Key of the hashing scheme of a plot.
+pub const PLOT_HASHING_KEY: &[u8] = b"melodot/plot-hashing";
+
+/// Returns the hashing scheme the plot in `db` was made with, `None` if no piece was saved
+/// since the scheme is recorded.
+pub fn plot_hashing(db: &mut impl DasKv) -> Result<Option<PlotHashing>> {
+	db.get(PLOT_HASHING_KEY)
+		.map(|data| Decode::decode(&mut &data[..]))
+		.transpose()
+		.context("Failed to decode the hashing scheme of the plot")
+}
+
+/// The progress of a [`PlotUpdate`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PlotProgress {
+	/// Number of pieces whose cells have been matched.
+	pub pieces_done: u32,
+	/// Number of pieces in the update.
+	pub pieces_total: u32,
+	/// Number of cells indexed.
+	pub cells: u64,
+	/// Number of matched cell pairs found.
+	pub pairs: u64,
+}
+
+impl PlotProgress {
+	/// Returns whether all the pieces of the update have been processed.
+	pub fn is_done(&self) -> bool {
+		self.pieces_done == self.pieces_total
+	}
+}
+
+/// A batch of pieces to add to the plot of a farmer.
+#[derive(Debug, Clone)]
+pub struct PlotUpdate<BlockNumber>
+where
+	BlockNumber: Clone + sp_std::hash::Hash,
+{
+	farmer_id: FarmerId,
+	hashing: PlotHashing,
+	pieces: Vec<Piece<BlockNumber>>,
+}
+
+impl<BlockNumber> PlotUpdate<BlockNumber>
+where
+	BlockNumber: Clone + sp_std::hash::Hash + Encode + Decode + PartialEq,
+{
+	/// Creates an empty update of the plot of `farmer_id`, hashed with the original
+	/// [`PlotHashing::Blake2`] scheme.
+	pub fn new(farmer_id: FarmerId) -> Self {
+		Self { farmer_id, hashing: PlotHashing::Blake2, pieces: Vec::new() }
+	}
+
+	/// Sets the hashing scheme of the plot.
+	pub fn with_hashing(mut self, hashing: PlotHashing) -> Self {
+		self.hashing = hashing;
+		self
+	}
+
+	/// Adds a piece to the update.
+	pub fn push(&mut self, piece: Piece<BlockNumber>) {
+		self.pieces.push(piece);
+	}
+
+	/// The pieces of the update.
+	pub fn pieces(&self) -> &[Piece<BlockNumber>] {
+		&self.pieces
+	}
+
+	/// Returns whether the update has no pieces.
+	pub fn is_empty(&self) -> bool {
+		self.pieces.is_empty()
+	}
+
+	/// Applies the update to `db`, calling `on_progress` after each piece.
+	pub fn apply(
+		&self,
+		db: &mut impl DasKv,
+		on_progress: impl FnMut(&PlotProgress),
+	) -> Result<PlotProgress>
+	where
+		BlockNumber: Send + Sync,
+	{
+		Self::save_pieces(db, self.hashing, &self.farmer_id, &self.pieces, on_progress)
+	}
+
+	/// Saves `pieces` and indexes their cells.
+	///
+	/// New cells are matched against the cells already in the plot and against each other. Pairs
+	/// are always stored left cell first. Saving a piece again does not duplicate its Y entries
+	/// or its matches.
+	///
+	/// The Y values and the matches of the pieces are computed in parallel. The entries they
+	/// read are fetched beforehand, and everything is written to `db` in one batch at the end.
+	///
+	/// # Arguments
+	///
+	/// * `db` - The plot database.
+	/// * `hashing` - The hashing scheme of the plot, recorded in `db`, see [`plot_hashing`].
+	/// * `farmer_id` - The ID of the farmer owning the plot.
+	/// * `pieces` - The pieces to save.
+	/// * `on_progress` - Called after the cells of each piece have been matched.
+	pub fn save_pieces(
+		db: &mut impl DasKv,
+		hashing: PlotHashing,
+		farmer_id: &FarmerId,
+		pieces: &[Piece<BlockNumber>],
+		mut on_progress: impl FnMut(&PlotProgress),
+	) -> Result<PlotProgress>
+	where
+		BlockNumber: Send + Sync,
+	{
+		let mut progress = PlotProgress { pieces_total: pieces.len() as u32, ..Default::default() };
+
+		let ys = pieces
+			.par_iter()
+			.map(|piece| {
+				piece.x_values_iterator(hashing, farmer_id).map(|(y, _)| y).collect::<Vec<_>>()
+			})
+			.collect::<Vec<_>>();
+
+		// The new cells grouped by Y key, and the Y keys they are matched against.
+		let mut new_cells: BTreeMap<Vec<u8>, Vec<(CellMetadata<BlockNumber>, &Segment)>> =
+			BTreeMap::new();
+		let mut match_keys = BTreeSet::new();
+		for (piece, piece_ys) in pieces.iter().zip(ys.iter()) {
+			for (index, (y, segment)) in piece_ys.iter().zip(piece.segments.iter()).enumerate() {
+				// Zero is the Y value of empty segments, they are never matched.
+				if *y == 0 {
+					continue
+				}
+				let pos = YPos::from_u32(index as u32);
+				let key = YValueManager::<BlockNumber>::key_by_x_pos(&pos, *y);
+				let metadata = CellMetadata::new(piece.metadata.clone(), index as u32);
+				new_cells.entry(key).or_default().push((metadata, segment));
+				match_keys
+					.insert(YValueManager::<BlockNumber>::key_by_x_pos(&pos.match_x_pos(), *y));
+			}
+		}
+
+		// Pieces of the update replace their stored version.
+		let new_pieces =
+			pieces.iter().map(|piece| (piece.key(), piece)).collect::<BTreeMap<_, _>>();
+		let mut stored_cells = BTreeMap::new();
+		let mut stored_pieces = BTreeMap::new();
+		for key in new_cells.keys().chain(match_keys.iter()) {
+			if stored_cells.contains_key(key) {
+				continue
+			}
+			let cells = Self::load_cells(db, key)?;
+			for cell in &cells {
+				let piece_key = cell.piece_metadata.key();
+				if !new_pieces.contains_key(&piece_key) && !stored_pieces.contains_key(&piece_key) {
+					let piece = Self::load_piece(db, &piece_key)?;
+					stored_pieces.insert(piece_key, piece);
+				}
+			}
+			stored_cells.insert(key.clone(), cells);
+		}
+
+		let segment = |metadata: &CellMetadata<BlockNumber>| {
+			let key = metadata.piece_metadata.key();
+			match new_pieces.get(&key) {
+				Some(piece) => piece.cell(metadata.offset),
+				None => stored_pieces.get(&key)?.as_ref()?.cell(metadata.offset),
+			}
+		};
+
+		// The cells and matches of each piece.
+		let matched = pieces
+			.par_iter()
+			.zip(ys.par_iter())
+			.map(|(piece, piece_ys)| {
+				let mut cells = 0u64;
+				let mut pairs = Vec::new();
+				for (index, (y, seg)) in piece_ys.iter().zip(piece.segments.iter()).enumerate() {
+					if *y == 0 {
+						continue
+					}
+					cells += 1;
+
+					let pos = YPos::from_u32(index as u32);
+					let match_key =
+						YValueManager::<BlockNumber>::key_by_x_pos(&pos.match_x_pos(), *y);
+					let metadata = CellMetadata::new(piece.metadata.clone(), index as u32);
+
+					let mut matches = stored_cells
+						.get(&match_key)
+						.into_iter()
+						.flatten()
+						.filter_map(|stored| segment(stored).map(|s| (stored.clone(), s)))
+						.collect::<Vec<_>>();
+					// Pairs of new cells are found from their left cell only.
+					if let YPos::Left(_) = pos {
+						if let Some(new) = new_cells.get(&match_key) {
+							matches.extend(new.iter().map(|(m, s)| (m.clone(), (*s).clone())));
+						}
+					}
+
+					for (other, other_seg) in matches {
+						pairs.push(match pos {
+							YPos::Left(_) => (
+								ZValueManager::<BlockNumber>::calculate_z(hashing, seg, &other_seg),
+								(metadata.clone(), other),
+							),
+							YPos::Right(_) => (
+								ZValueManager::<BlockNumber>::calculate_z(hashing, &other_seg, seg),
+								(other, metadata.clone()),
+							),
+						});
+					}
+				}
+				(cells, pairs)
+			})
+			.collect::<Vec<_>>();
+
+		let mut new_pairs: BTreeMap<u16, Vec<_>> = BTreeMap::new();
+		for (cells, pairs) in matched {
+			progress.cells += cells;
+			for (z, pair) in pairs {
+				let entry = new_pairs.entry(z).or_default();
+				if !entry.contains(&pair) {
+					entry.push(pair);
+					progress.pairs += 1;
+				}
+			}
+			progress.pieces_done += 1;
+			on_progress(&progress);
+		}
+
+		let mut writes =
+			pieces.iter().map(|piece| (piece.key(), piece.encode())).collect::<Vec<_>>();
+
+		for (key, cells) in new_cells {
+			let mut entry = stored_cells.remove(&key).unwrap_or_default();
+			let len = entry.len();
+			for (metadata, _) in cells {
+				if !entry.contains(&metadata) {
+					entry.push(metadata);
+				}
+			}
+			if entry.len() != len {
+				writes.push((key, entry.encode()));
+			}
+		}
+
+		for (z, pairs) in new_pairs {
+			let mut entry = ZValueManager::<BlockNumber>::get(db, z)?;
+			let len = entry.len();
+			for pair in pairs {
+				if !entry.contains(&pair) {
+					entry.push(pair);
+				}
+			}
+			if entry.len() != len {
+				writes.push((Encode::encode(&z), entry.encode()));
+			}
+		}
+
+		writes.push((PLOT_HASHING_KEY.to_vec(), hashing.encode()));
+		db.set_batch(&writes);
+		Ok(progress)
+	}
+
+	/// Returns the stored cells of the Y entry `key`.
+	fn load_cells(db: &mut impl DasKv, key: &[u8]) -> Result<Vec<CellMetadata<BlockNumber>>> {
+		Ok(db
+			.get(key)
+			.map(|data| Decode::decode(&mut &data[..]))
+			.transpose()
+			.context("Failed to decode CellMetadata vector from database")?
+			.unwrap_or_default())
+	}
+
+	/// Returns the stored piece `key`.
+	fn load_piece(db: &mut impl DasKv, key: &[u8]) -> Result<Option<Piece<BlockNumber>>> {
+		db.get(key)
+			.map(|data| Decode::decode(&mut &data[..]))
+			.transpose()
+			.context("Failed to decode Piece from database")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{mock::*, PiecePosition};
+	use melo_das_db::mock_db::MockDb;
+
+	fn mock_piece(block_num: u32) -> Piece<u32> {
+		let row = get_mock_row(&BLS_SCALAR11, &BLS_SCALAR12, 0, &PROOF_11, &PROOF_12, 16);
+		Piece::new(block_num, PiecePosition::Row(0), &row)
+	}
+
+	#[test]
+	fn test_apply_indexes_pieces() {
+		let mut db = MockDb::new();
+		let farmer_id = FarmerId::default();
+
+		let mut update = PlotUpdate::new(farmer_id.clone());
+		update.push(mock_piece(1));
+		update.push(mock_piece(2));
+
+		let mut reported = Vec::new();
+		let progress = update.apply(&mut db, |p| reported.push(p.pieces_done)).unwrap();
+
+		assert!(progress.is_done());
+		assert_eq!(reported, vec![1, 2]);
+		assert!(progress.pairs > 0);
+		for piece in update.pieces() {
+			assert!(db.contains(&piece.key()));
+		}
+
+		let stats = ZValueManager::<u32>::stats(&mut db).unwrap();
+		assert_eq!(stats.pairs, progress.pairs);
+		assert!(stats.cross_piece_pairs > 0);
+	}
+
+	#[test]
+	fn test_incremental_update_matches_batch() {
+		let farmer_id = FarmerId::default();
+		let pieces = vec![mock_piece(1), mock_piece(2), mock_piece(3)];
+
+		let mut batch_db = MockDb::new();
+		PlotUpdate::save_pieces(&mut batch_db, PlotHashing::Blake2, &farmer_id, &pieces, |_| ())
+			.unwrap();
+
+		let mut incremental_db = MockDb::new();
+		for piece in &pieces {
+			let piece = [piece.clone()];
+			PlotUpdate::save_pieces(
+				&mut incremental_db,
+				PlotHashing::Blake2,
+				&farmer_id,
+				&piece,
+				|_| (),
+			)
+			.unwrap();
+		}
+
+		assert_eq!(
+			ZValueManager::<u32>::stats(&mut batch_db).unwrap(),
+			ZValueManager::<u32>::stats(&mut incremental_db).unwrap()
+		);
+	}
+
+	#[test]
+	fn test_pairs_are_stored_left_first() {
+		let mut db = MockDb::new();
+		let farmer_id = FarmerId::default();
+		let pieces = [mock_piece(1), mock_piece(2)];
+		PlotUpdate::save_pieces(&mut db, PlotHashing::Blake2, &farmer_id, &pieces, |_| ()).unwrap();
+
+		for bucket in ZValueManager::<u32>::buckets(&mut db) {
+			let (z, pairs) = bucket.unwrap();
+			for (left, right) in pairs {
+				assert!(matches!(YPos::from_u32(left.offset), YPos::Left(_)));
+				let left_seg = Piece::get_cell(&left, &mut db).unwrap().unwrap();
+				let right_seg = Piece::get_cell(&right, &mut db).unwrap().unwrap();
+				assert!(ZValueManager::<u32>::verify(
+					PlotHashing::Blake2,
+					z,
+					&farmer_id,
+					&left_seg,
+					&right_seg,
+					&left,
+					&right
+				));
+			}
+		}
+	}
+
+	#[test]
+	fn test_saving_again_is_idempotent() {
+		let mut db = MockDb::new();
+		let farmer_id = FarmerId::default();
+		let pieces = vec![mock_piece(1), mock_piece(2)];
+
+		PlotUpdate::save_pieces(&mut db, PlotHashing::Blake2, &farmer_id, &pieces, |_| ()).unwrap();
+		let before = ZValueManager::<u32>::stats(&mut db).unwrap();
+
+		PlotUpdate::save_pieces(&mut db, PlotHashing::Blake2, &farmer_id, &pieces, |_| ()).unwrap();
+		assert_eq!(ZValueManager::<u32>::stats(&mut db).unwrap(), before);
+	}
+
+	#[test]
+	fn test_update_records_its_hashing() {
+		let mut db = MockDb::new();
+		let farmer_id = FarmerId::new_with(PlotHashing::Blake3, 1u32);
+		assert_eq!(plot_hashing(&mut db).unwrap(), None);
+
+		let mut update = PlotUpdate::new(farmer_id.clone()).with_hashing(PlotHashing::Blake3);
+		update.push(mock_piece(1));
+		update.push(mock_piece(2));
+		update.apply(&mut db, |_| ()).unwrap();
+		assert_eq!(plot_hashing(&mut db).unwrap(), Some(PlotHashing::Blake3));
+
+		for bucket in ZValueManager::<u32>::buckets(&mut db) {
+			let (z, pairs) = bucket.unwrap();
+			for (left, right) in pairs {
+				let left_seg = Piece::get_cell(&left, &mut db).unwrap().unwrap();
+				let right_seg = Piece::get_cell(&right, &mut db).unwrap().unwrap();
+				let verify = |hashing| {
+					ZValueManager::<u32>::verify(
+						hashing, z, &farmer_id, &left_seg, &right_seg, &left, &right,
+					)
+				};
+				assert!(verify(PlotHashing::Blake3));
+				assert!(!verify(PlotHashing::Blake2));
+			}
+		}
+	}
+}
@@ -0,0 +1,140 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `melo-plot-inspect`, a utility to diagnose the plots of a farmer.
+
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand};
+use codec::DecodeAll;
+use melo_das_db::sqlite::SqliteDasDb;
+use melo_proof_of_space::{plot_hashing, FarmerId, Piece, PlotHashing, ZValueManager, H256};
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(name = "melo-plot-inspect", about = "Inspects the plots of a Melodot farmer.")]
+struct Cli {
+	/// Path of the plots database.
+	#[arg(long)]
+	db: PathBuf,
+
+	#[command(subcommand)]
+	command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+	/// Shows statistics of the Z buckets.
+	Stats,
+	/// Lists the non-empty Z buckets.
+	Buckets {
+		/// Only lists the buckets with at least this many pairs.
+		#[arg(long, default_value_t = 1)]
+		min_size: usize,
+	},
+	/// Lists the stored pieces.
+	Pieces,
+	/// Rebuilds the Y and Z indexes from the stored pieces.
+	Rebuild {
+		/// The farmer ID the plot was created for, as hex.
+		#[arg(long)]
+		farmer_id: H256,
+		/// The hashing scheme to rebuild the indexes with: blake2, blake3 or keccak.
+		#[arg(long, default_value = "blake2")]
+		hashing: PlotHashing,
+	},
+}
+
+fn main() -> Result<()> {
+	let cli = Cli::parse();
+
+	let path = cli.db.to_str().ok_or_else(|| anyhow!("Invalid database path {:?}", cli.db))?;
+	let mut db = SqliteDasDb::new(path)?;
+
+	match cli.command {
+		Command::Stats => {
+			let pieces = load_pieces(&db)?;
+			let stats = ZValueManager::<u32>::stats(&mut db)?;
+
+			let hashing = plot_hashing(&mut db)?;
+
+			println!("hashing:           {}", hashing.map_or("unrecorded", |h| h.as_str()));
+			println!("pieces:            {}", pieces.len());
+			println!(
+				"cells:             {}",
+				pieces.iter().map(|p| p.segments.len()).sum::<usize>()
+			);
+			println!("z buckets:         {}", stats.buckets);
+			println!("matched pairs:     {}", stats.pairs);
+			println!("collisions:        {}", stats.collisions);
+			println!("max bucket size:   {}", stats.max_bucket_size);
+			println!("same piece pairs:  {}", stats.same_piece_pairs);
+			println!("cross piece pairs: {}", stats.cross_piece_pairs);
+			println!("bucket sizes:");
+			for (size, count) in stats.bucket_sizes {
+				println!("  {:>6} pairs: {} buckets", size, count);
+			}
+		},
+		Command::Buckets { min_size } =>
+			for bucket in ZValueManager::<u32>::buckets(&mut db) {
+				let (z, pairs) = bucket?;
+				if pairs.len() < min_size {
+					continue
+				}
+				println!("z {:>5}: {} pairs", z, pairs.len());
+				for (left, right) in pairs {
+					println!(
+						"  #{} {:?} offset {} <-> #{} {:?} offset {}",
+						left.piece_metadata.block_num,
+						left.piece_metadata.pos,
+						left.offset,
+						right.piece_metadata.block_num,
+						right.piece_metadata.pos,
+						right.offset,
+					);
+				}
+			},
+		Command::Pieces =>
+			for piece in load_pieces(&db)? {
+				println!(
+					"#{} {:?}: {} segments",
+					piece.metadata.block_num,
+					piece.metadata.pos,
+					piece.segments.len()
+				);
+			},
+		Command::Rebuild { farmer_id, hashing } => {
+			let pieces = load_pieces(&db)?;
+			ZValueManager::<u32>::rebuild(&mut db, hashing, &FarmerId(farmer_id), &pieces)?;
+			let stats = ZValueManager::<u32>::stats(&mut db)?;
+			println!("Rebuilt the index of {} pieces: {} matched pairs", pieces.len(), stats.pairs);
+		},
+	}
+
+	Ok(())
+}
+
+/// Loads the pieces stored in the database.
+///
+/// An entry is a piece if its value decodes to a piece whose key is the key of the entry.
+fn load_pieces(db: &SqliteDasDb) -> Result<Vec<Piece<u32>>> {
+	let mut pieces = Vec::new();
+	db.for_each(|key, value| {
+		if let Ok(piece) = Piece::<u32>::decode_all(&mut &value[..]) {
+			if piece.key() == key {
+				pieces.push(piece);
+			}
+		}
+	})?;
+	Ok(pieces)
+}
@@ -12,15 +12,19 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+#[cfg(feature = "std")]
+use crate::{plot_hashing, DasKv, Piece};
 use crate::{
-	utils, BlakeTwo256, Cell, Decode, Encode, FarmerId, HashT, PreCell, ZValueManager,
+	utils, BlakeTwo256, Cell, CellMetadata, Decode, Encode, FarmerId, HashT, PieceMetadata,
+	PlotHashing, PreCell, YPos, ZValueManager, CHALLENGE_CONTEXT,
 };
-#[cfg(feature = "std")]
-use crate::{DasKv, Piece};
+use alloc::vec;
 #[cfg(feature = "std")]
 use anyhow::{Ok, Result};
+use melo_core_primitives::kzg_verifier;
 use melo_das_primitives::{KZGCommitment, Segment, KZG};
 use scale_info::TypeInfo;
+use sp_core::RuntimeDebug;
 
 /// Represents a potential solution in the system.
 ///
@@ -33,10 +37,12 @@ where
 	BlockNumber: Clone + sp_std::hash::Hash + Encode + Decode,
 	Hash: PartialEq + Eq + AsRef<[u8]> + 'static,
 {
-	/// Hash of the block that posted the solution.
-	block_hash: Hash,
+	/// The challenge the solution was found for, see [`Solution::derive_challenge`].
+	challenge: Hash,
 	/// Identifier of the farmer who proposed the solution.
 	farmer_id: FarmerId,
+	/// The hashing scheme of the plot the solution was found in.
+	hashing: PlotHashing,
 	/// The cell that was present in the posted block (previous cell).
 	pub pre_cell: PreCell,
 	/// The left part of the winning cell in the solution.
@@ -54,7 +60,7 @@ where
 	///
 	/// # Arguments
 	///
-	/// * `block_hash`: Hash of the block associated with the solution.
+	/// * `challenge`: The challenge the solution was found for.
 	/// * `farmer_id`: ID of the farmer proposing the solution.
 	/// * `pre_cell`: The previous cell in the block.
 	/// * `win_cell_left`: The left part of the winning cell.
@@ -64,27 +70,49 @@ where
 	///
 	/// A new instance of `Solution`.
 	pub fn new(
-		block_hash: &Hash,
+		challenge: &Hash,
 		farmer_id: &FarmerId,
 		pre_cell: &PreCell,
 		win_cell_left: &Cell<BlockNumber>,
 		win_cell_right: &Cell<BlockNumber>,
 	) -> Self {
 		Self {
-			block_hash: block_hash.clone(),
+			challenge: challenge.clone(),
 			farmer_id: farmer_id.clone(),
+			hashing: PlotHashing::Blake2,
 			pre_cell: pre_cell.clone(),
 			win_cell_left: win_cell_left.clone(),
 			win_cell_right: win_cell_right.clone(),
 		}
 	}
 
+	/// Sets the hashing scheme of the plot the solution was found in, [`PlotHashing::Blake2`] by
+	/// default.
+	pub fn with_hashing(mut self, hashing: PlotHashing) -> Self {
+		self.hashing = hashing;
+		self
+	}
+
+	/// Derives the challenge of the block of `slot` from the randomness of its epoch.
+	///
+	/// The randomness of an epoch is fixed before the epoch starts and a block producer can not
+	/// choose the slot it produces in, so the challenge can not be ground by the producer, unlike
+	/// the hash of its block.
+	pub fn derive_challenge<H: HashT<Output = Hash>>(
+		epoch_randomness: &[u8; 32],
+		slot: u64,
+	) -> Hash {
+		H::hash_of(&(CHALLENGE_CONTEXT, epoch_randomness, slot))
+	}
+
 	/// Verifies the correctness of the solution.
 	///
 	/// The verification process involves checking the pre-cell, ensuring
 	/// the solution index is valid, and validating the KZG proof for
 	/// both left and right winning cells.
 	///
+	/// The KZG proofs are verified natively through the `kzg_verifier` host functions.
+	///
 	/// # Arguments
 	///
 	/// * `pre_commit`: KZG commitment for the pre-cell.
@@ -109,26 +137,21 @@ where
 		pre_cell_leading_zero: u8,
 		n: u32,
 	) -> bool {
-		let kzg = KZG::default_embedded();
-		let z = ZValueManager::<BlockNumber>::get_challenge(self.block_hash.as_ref());
+		let z = ZValueManager::<BlockNumber>::get_challenge(self.challenge.as_ref());
 
 		Self::check_pre_cell(&self.pre_cell.seg, &self.farmer_id, pre_cell_leading_zero) &&
 			Self::is_index_valid(
 				&self.farmer_id,
-				&self.block_hash,
+				&self.challenge,
 				self.pre_cell.piece_index() as usize,
 				32,
 				n,
-			) && self.pre_cell.verify_kzg_proof(&kzg, pre_commit) &&
-			self.validate_win_cell_with_kzg(
-				&kzg,
-				win_left_commit,
-				win_right_commit,
-				win_left_block_hash,
-				win_right_block_hash,
-				n,
-				z,
-			)
+			) && self.validate_win_cell(win_left_block_hash, win_right_block_hash, n, z) &&
+			kzg_verifier::verify_segments(vec![
+				self.pre_cell.segment_check(pre_commit),
+				self.win_cell_left.segment_check(win_left_commit),
+				self.win_cell_right.segment_check(win_right_commit),
+			])
 	}
 
 	/// Checks if the pre-cell is valid.
@@ -152,12 +175,42 @@ where
 		utils::validate_leading_zeros(&xored_hash, pre_cell_leading_zero as u32)
 	}
 
-	/// Checks if the index is valid based on the XORed hash of farmer ID and block hash.
+	/// Returns the quality of the solution.
+	///
+	/// The quality is the number of leading zero bits of the pre-cell hash, XORed with the farmer
+	/// ID, beyond the required `pre_cell_leading_zero`. Solutions of higher quality are rarer.
+	///
+	/// # Arguments
+	///
+	/// * `pre_cell_leading_zero`: Required number of leading zeros in the pre-cell hash.
+	///
+	/// # Returns
+	///
+	/// The quality, `0` for a pre-cell which just meets the requirement or does not meet it.
+	pub fn quality(&self, pre_cell_leading_zero: u8) -> u32 {
+		Self::pre_cell_quality(&self.pre_cell.seg, &self.farmer_id, pre_cell_leading_zero)
+	}
+
+	/// Returns the quality of a solution whose pre-cell holds `seg`, found with `farmer_id`, see
+	/// [`Solution::quality`].
+	pub fn pre_cell_quality(seg: &Segment, farmer_id: &FarmerId, pre_cell_leading_zero: u8) -> u32 {
+		let pre_cell_hash = BlakeTwo256::hash_of(seg);
+		let xored_hash = utils::xor_byte_slices(farmer_id.as_ref(), pre_cell_hash.as_ref());
+
+		utils::leading_zeros(&xored_hash).saturating_sub(pre_cell_leading_zero as u32)
+	}
+
+	/// Returns the compact encoding of the solution, `None` if the winning cells are not a pair.
+	pub fn compact(&self) -> Option<CompactSolution<BlockNumber>> {
+		CompactSolution::from_cells(&self.pre_cell, &self.win_cell_left, &self.win_cell_right)
+	}
+
+	/// Checks if the index is valid based on the XORed hash of farmer ID and challenge.
 	///
 	/// # Arguments
 	///
 	/// * `farmer_id`: ID of the farmer.
-	/// * `block_hash`: Hash of the block.
+	/// * `challenge`: The challenge of the block.
 	/// * `index`: Index to be validated.
 	/// * `max_index`: Maximum allowable index.
 	/// * `n`: A parameter influencing the validation.
@@ -167,19 +220,19 @@ where
 	/// `true` if the index is valid, otherwise `false`.
 	pub fn is_index_valid(
 		farmer_id: &FarmerId,
-		block_hash: &Hash,
+		challenge: &Hash,
 		index: usize,
 		max_index: usize,
 		n: u32,
 	) -> bool {
-		let xored_hash = utils::xor_byte_slices(farmer_id.as_ref(), block_hash.as_ref());
+		let xored_hash = utils::xor_byte_slices(farmer_id.as_ref(), challenge.as_ref());
 		utils::is_index_valid(&xored_hash, index, max_index, n as usize)
 	}
 
 	/// Selects a set of indices based on a XORed hash.
 	///
 	/// This function computes indices based on the XORed hash derived from
-	/// `farmer_id` and `block_hash`. The selection is influenced by the
+	/// `farmer_id` and `challenge`. The selection is influenced by the
 	/// 'stretch factor' `n`, with higher values of `n` resulting in a lower
 	/// probability of index selection. The `end` parameter determines the
 	/// maximum index that can be selected.
@@ -187,7 +240,7 @@ where
 	/// # Arguments
 	///
 	/// * `farmer_id` - A reference to the FarmerId, used as part of the hash input.
-	/// * `block_hash` - A reference to the Hash::Output, used as the other part of the hash input.
+	/// * `challenge` - A reference to the Hash::Output, used as the other part of the hash input.
 	/// * `end` - The maximum index that can be considered for selection.
 	/// * `n` - The stretch factor. Higher values decrease the probability of each index being
 	///   selected.
@@ -198,12 +251,12 @@ where
 	#[cfg(feature = "std")]
 	pub fn select_indices(
 		farmer_id: &FarmerId,
-		block_hash: &Hash,
+		challenge: &Hash,
 		end: usize,
 		n: usize,
 	) -> Vec<u32> {
 		utils::select_indices(
-			&utils::xor_byte_slices(farmer_id.as_ref(), block_hash.as_ref())
+			&utils::xor_byte_slices(farmer_id.as_ref(), challenge.as_ref())
 				.try_into()
 				.expect("Expected a 32-byte array"),
 			0,
@@ -265,6 +318,7 @@ where
 			32,
 			n as usize,
 		) && ZValueManager::<BlockNumber>::verify(
+			self.hashing,
 			z,
 			&self.farmer_id,
 			&self.win_cell_left.seg,
@@ -275,7 +329,71 @@ where
 	}
 }
 
+/// An encoding of the cells of a solution without the redundant metadata of the right cell.
+///
+/// The offset of the right winning cell always follows from the offset of the left one, and both
+/// cells usually belong to the same piece, so the metadata of the right cell is omitted. This only
+/// saves a few bytes: the segments, whose field elements and proofs make up most of a claim, are
+/// kept whole, since the runtime verifies them against the commitments of their blocks.
+#[derive(Encode, Decode, RuntimeDebug, Clone, PartialEq, Eq, TypeInfo)]
+pub struct CompactSolution<BlockNumber>
+where
+	BlockNumber: Clone + sp_std::hash::Hash + Encode + Decode,
+{
+	/// The cell that was present in the posted block.
+	pub pre_cell: PreCell,
+	/// The left winning cell.
+	pub win_cell_left: Cell<BlockNumber>,
+	/// The piece of the right winning cell, `None` if it is the piece of the left one.
+	pub right_piece: Option<PieceMetadata<BlockNumber>>,
+	/// The segment of the right winning cell.
+	pub right_seg: Segment,
+}
+
+impl<BlockNumber> CompactSolution<BlockNumber>
+where
+	BlockNumber: Clone + sp_std::hash::Hash + Encode + Decode + PartialEq,
+{
+	/// Creates the compact encoding of the given cells.
+	///
+	/// Returns `None` if the winning cells are not a pair, in which case they are not a valid
+	/// solution anyway.
+	pub fn from_cells(
+		pre_cell: &PreCell,
+		win_cell_left: &Cell<BlockNumber>,
+		win_cell_right: &Cell<BlockNumber>,
+	) -> Option<Self> {
+		if !win_cell_left.metadata.is_pair(&win_cell_right.metadata) {
+			return None
+		}
+
+		let left_piece = &win_cell_left.metadata.piece_metadata;
+		let right_piece = &win_cell_right.metadata.piece_metadata;
+
+		Some(Self {
+			pre_cell: pre_cell.clone(),
+			win_cell_left: win_cell_left.clone(),
+			right_piece: if left_piece == right_piece { None } else { Some(right_piece.clone()) },
+			right_seg: win_cell_right.seg.clone(),
+		})
+	}
+
+	/// Restores the pre-cell and the left and right winning cells.
+	pub fn into_cells(self) -> (PreCell, Cell<BlockNumber>, Cell<BlockNumber>) {
+		let right_offset =
+			YPos::from_u32(self.win_cell_left.metadata.offset).match_x_pos().to_u32();
+		let right_piece = self
+			.right_piece
+			.unwrap_or_else(|| self.win_cell_left.metadata.piece_metadata.clone());
+		let win_cell_right =
+			Cell::new(CellMetadata::new(right_piece, right_offset), self.right_seg);
+
+		(self.pre_cell, self.win_cell_left, win_cell_right)
+	}
+}
+
 /// Finds solutions in the database and returns a tuple containing the winning cell and its nonce.
+/// The solutions carry the hashing scheme recorded in the database.
 /// The nonce is used to generate the key for the ChaCha8 stream cipher.
 /// The function returns a vector of tuples containing the winning cell and its nonce.
 ///
@@ -292,14 +410,14 @@ pub fn find_solutions<DB: DasKv, Hash, BlockNumber>(
 	db: &mut DB,
 	farmer_id: &FarmerId,
 	pre_cell: &PreCell,
-	block_hash: &Hash,
+	challenge: &Hash,
 ) -> Result<Vec<Solution<Hash, BlockNumber>>>
 where
 	BlockNumber: Clone + sp_std::hash::Hash + Encode + Decode + PartialEq,
 	Hash: PartialEq + Eq + AsRef<[u8]> + Clone + 'static,
 {
-	let z = ZValueManager::<BlockNumber>::get_challenge(block_hash.as_ref());
-	find_solutions_with_z(db, farmer_id, pre_cell, block_hash, z)
+	let z = ZValueManager::<BlockNumber>::get_challenge(challenge.as_ref());
+	find_solutions_with_z(db, farmer_id, pre_cell, challenge, z)
 }
 
 #[cfg(feature = "std")]
@@ -307,13 +425,14 @@ fn find_solutions_with_z<DB: DasKv, Hash, BlockNumber>(
 	db: &mut DB,
 	farmer_id: &FarmerId,
 	pre_cell: &PreCell,
-	block_hash: &Hash,
+	challenge: &Hash,
 	z: u16,
 ) -> Result<Vec<Solution<Hash, BlockNumber>>>
 where
 	BlockNumber: Clone + sp_std::hash::Hash + Encode + Decode + PartialEq,
 	Hash: PartialEq + Eq + AsRef<[u8]> + Clone + 'static,
 {
+	let hashing = plot_hashing(db)?.unwrap_or_default();
 	let cells = ZValueManager::get(db, z)?;
 
 	let res = cells
@@ -325,13 +444,16 @@ where
 				let left_cell = Cell::<BlockNumber>::new(left, left_cell_data);
 				let right_cell = Cell::<BlockNumber>::new(right, right_cell_data);
 
-				Some(Solution::<Hash, BlockNumber>::new(
-					block_hash,
-					farmer_id,
-					pre_cell,
-					&left_cell,
-					&right_cell,
-				))
+				Some(
+					Solution::<Hash, BlockNumber>::new(
+						challenge,
+						farmer_id,
+						pre_cell,
+						&left_cell,
+						&right_cell,
+					)
+					.with_hashing(hashing),
+				)
 			} else {
 				None
 			}
@@ -379,7 +501,7 @@ mod tests {
 
 		let piece = Piece::new(11, piece_pos, &row);
 
-		let _ = piece.save(&mut db, &farmer_id);
+		let _ = piece.save(&mut db, PlotHashing::Blake2, &farmer_id);
 
 		let cells = ZValueManager::<u32>::get(&mut db, Z1).unwrap();
 
@@ -430,7 +552,7 @@ mod tests {
 
 		let is_index_valid = Solution::<H256, u32>::is_index_valid(
 			&solution.farmer_id,
-			&solution.block_hash,
+			&solution.challenge,
 			solution.pre_cell.piece_index() as usize,
 			32,
 			0,
@@ -454,4 +576,107 @@ mod tests {
 
 		assert!(result);
 	}
+
+	#[test]
+	fn test_solution_quality() {
+		let row = get_mock_row(&BLS_SCALAR11, &BLS_SCALAR12, 0, &PROOF_11, &PROOF_12, 16);
+		let pre_cell = PreCell::new(PiecePosition::Row(0), row[0].clone());
+		let farmer_id = FarmerId::default();
+
+		let solution = Solution::<H256, u32>::new(
+			&BLOCK_HASH1.into(),
+			&farmer_id,
+			&pre_cell,
+			&Cell::default(),
+			&Cell::default(),
+		);
+
+		let pre_cell_hash = BlakeTwo256::hash_of(&pre_cell.seg);
+		let zeros = utils::leading_zeros(pre_cell_hash.as_ref());
+
+		assert_eq!(solution.quality(0), zeros);
+		assert_eq!(solution.quality(zeros as u8), 0);
+		assert_eq!(solution.quality(zeros as u8 + 1), 0);
+	}
+
+	#[test]
+	fn test_compact_solution_round_trip() {
+		let row = get_mock_row(&BLS_SCALAR11, &BLS_SCALAR12, 0, &PROOF_11, &PROOF_12, 16);
+		let pre_cell = PreCell::new(PiecePosition::Row(0), row[0].clone());
+		let piece_metadata = PieceMetadata::new(5, PiecePosition::Row(0));
+
+		let win_cell_left = Cell::new(CellMetadata::new(piece_metadata.clone(), 0), row[0].clone());
+		let win_cell_right = Cell::new(CellMetadata::new(piece_metadata, 1), row[1].clone());
+
+		let solution = Solution::<H256, u32>::new(
+			&BLOCK_HASH1.into(),
+			&FarmerId::default(),
+			&pre_cell,
+			&win_cell_left,
+			&win_cell_right,
+		);
+
+		let compact = solution.compact().expect("Cells are a pair");
+		assert!(compact.right_piece.is_none());
+		// The metadata of the right cell, a block number, a position and an offset of 13 bytes,
+		// is replaced by the `None` byte of the right piece.
+		assert_eq!(win_cell_right.metadata.encoded_size(), 13);
+		assert_eq!(
+			compact.encoded_size(),
+			(pre_cell.clone(), win_cell_left.clone(), win_cell_right.clone()).encoded_size() - 12
+		);
+
+		let decoded = CompactSolution::<u32>::decode(&mut &compact.encode()[..]).unwrap();
+		assert_eq!(decoded.into_cells(), (pre_cell, win_cell_left, win_cell_right));
+	}
+
+	#[test]
+	fn test_compact_solution_other_piece() {
+		let row = get_mock_row(&BLS_SCALAR11, &BLS_SCALAR12, 0, &PROOF_11, &PROOF_12, 16);
+		let pre_cell = PreCell::new(PiecePosition::Row(0), row[0].clone());
+
+		let win_cell_left = Cell::new(
+			CellMetadata::new(PieceMetadata::new(5, PiecePosition::Row(0)), 3),
+			row[1].clone(),
+		);
+		let win_cell_right = Cell::new(
+			CellMetadata::new(PieceMetadata::new(7, PiecePosition::Column(2)), 2),
+			row[0].clone(),
+		);
+
+		let compact =
+			CompactSolution::from_cells(&pre_cell, &win_cell_left, &win_cell_right).unwrap();
+		assert!(compact.right_piece.is_some());
+		// Only the offset of the right cell is left out, and the right piece takes an extra byte.
+		assert_eq!(
+			compact.encoded_size(),
+			(pre_cell.clone(), win_cell_left.clone(), win_cell_right.clone()).encoded_size() - 3
+		);
+		assert_eq!(compact.into_cells(), (pre_cell.clone(), win_cell_left.clone(), win_cell_right));
+
+		let not_pair = Cell::new(
+			CellMetadata::new(PieceMetadata::new(7, PiecePosition::Column(2)), 5),
+			row[0].clone(),
+		);
+		assert!(CompactSolution::from_cells(&pre_cell, &win_cell_left, &not_pair).is_none());
+	}
+
+	#[test]
+	fn test_derive_challenge() {
+		let randomness = [7u8; 32];
+		let challenge = Solution::<H256, u32>::derive_challenge::<BlakeTwo256>(&randomness, 42);
+
+		assert_eq!(
+			challenge,
+			Solution::<H256, u32>::derive_challenge::<BlakeTwo256>(&randomness, 42)
+		);
+		assert_ne!(
+			challenge,
+			Solution::<H256, u32>::derive_challenge::<BlakeTwo256>(&randomness, 43)
+		);
+		assert_ne!(
+			challenge,
+			Solution::<H256, u32>::derive_challenge::<BlakeTwo256>(&[8u8; 32], 42)
+		);
+	}
 }
@@ -175,6 +175,19 @@ pub fn validate_leading_zeros(data: &[u8], zeros: u32) -> bool {
 	}
 }
 
+/// Counts the leading zero bits of the data.
+///
+/// Parameters:
+/// * `data`: A byte slice reference.
+///
+/// Returns:
+/// The number of leading zero bits, `data.len() * 8` if all bits are zeros.
+pub fn leading_zeros(data: &[u8]) -> u32 {
+	let zero_bytes = data.iter().take_while(|&&b| b == 0).count();
+	let zero_bits = data.get(zero_bytes).map_or(0, |b| b.leading_zeros());
+	zero_bytes as u32 * 8 + zero_bits
+}
+
 /// Performs a bitwise exclusive OR (XOR) operation on two byte slices.
 ///
 /// Parameters:
@@ -313,6 +326,14 @@ mod tests {
 		assert!(!validate_leading_zeros(&data, 16));
 	}
 
+	#[test]
+	fn test_leading_zeros() {
+		assert_eq!(leading_zeros(&[0x00, 0x00, 0x01, 0xFF]), 23);
+		assert_eq!(leading_zeros(&[0x80]), 0);
+		assert_eq!(leading_zeros(&[0u8; 4]), 32);
+		assert_eq!(leading_zeros(&[]), 0);
+	}
+
 	#[test]
 	fn test_validate_leading_zeros_empty_data() {
 		let data: [u8; 0] = [];
@@ -14,13 +14,9 @@
 
 #[cfg(feature = "std")]
 use crate::DasKv;
-use crate::{
-	utils, CellMetadata, ChaCha8, Decode, Encode, FarmerId, KeyIvInit, Nonce, PieceMetadata,
-	StreamCipher, Vec,
-};
+use crate::{hashing, CellMetadata, Decode, Encode, FarmerId, PieceMetadata, PlotHashing, Vec};
 #[cfg(feature = "std")]
 use anyhow::{Context, Result};
-use chacha20::cipher::generic_array::GenericArray;
 use melo_das_primitives::Segment;
 
 /// Represents the Y-position in a 2D space, with options for Left and Right positions.
@@ -86,18 +82,9 @@ impl<BlockNumber> YValueManager<BlockNumber>
 where
 	BlockNumber: Clone + sp_std::hash::Hash + Encode + Decode,
 {
-	/// Calculates the Y-value based on `FarmerId` and a `Segment`.
-	pub fn calculate_y(farmer_id: &FarmerId, seg: &Segment) -> u32 {
-		if seg.content.data.is_empty() {
-			return 0u32
-		}
-		let bytes = utils::xor_byte_slices(&farmer_id.encode(), &seg.content.data[0].encode());
-		let nonce = Nonce::default();
-		let key = GenericArray::from_slice(&bytes);
-		let mut cipher = ChaCha8::new(key, &nonce);
-		let mut buffer = [0u8; 32];
-		cipher.apply_keystream(&mut buffer);
-		utils::fold_hash(&buffer)
+	/// Calculates the Y-value based on `FarmerId` and a `Segment`, with the scheme `hashing`.
+	pub fn calculate_y(hashing: PlotHashing, farmer_id: &FarmerId, seg: &Segment) -> u32 {
+		hashing::cell_y(hashing, farmer_id, seg)
 	}
 
     /// Constructs a new `YValueManager` instance.
@@ -197,8 +184,10 @@ mod tests {
 
 		let segs = poly_to_segment_vec(&poly, &kzg, 0, chunk_len).unwrap();
 
-		let y1 = YValueManager::<u32>::calculate_y(&FarmerId::default(), &segs[0]);
-		let y2 = YValueManager::<u32>::calculate_y(&FarmerId::default(), &segs[1]);
+		let y1 =
+			YValueManager::<u32>::calculate_y(PlotHashing::Blake2, &FarmerId::default(), &segs[0]);
+		let y2 =
+			YValueManager::<u32>::calculate_y(PlotHashing::Blake2, &FarmerId::default(), &segs[1]);
 
 		if y1 == y2 {
 			print!("y: {:?}\n", y1);
@@ -318,7 +307,7 @@ mod tests {
 
 		let seg = get_mock_seg(bs, 0, 0, &proof, FIELD_ELEMENTS_PER_SEGMENT);
 
-		let y = YValueManager::<u32>::calculate_y(&farmer_id, &seg);
+		let y = YValueManager::<u32>::calculate_y(PlotHashing::Blake2, &farmer_id, &seg);
 
 		assert!(y == y_e);
 	}
@@ -373,7 +362,7 @@ mod tests {
 
 		let piece = Piece::new(block_num, piece_position, &row);
 
-		let _ = piece.save(db, &farmer_id);
+		let _ = piece.save(db, PlotHashing::Blake2, &farmer_id);
 	}
 
 	#[test]
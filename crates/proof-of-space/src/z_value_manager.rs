@@ -13,10 +13,12 @@
 // limitations under the License.
 
 #[cfg(feature = "std")]
-use crate::DasKv;
-use crate::{utils, BlakeTwo256, CellMetadata, Decode, Encode, FarmerId, HashT, YValueManager};
+use crate::{plot_hashing, DasKv, Piece, PlotUpdate, YPos};
+use crate::{hashing, utils, CellMetadata, Decode, Encode, FarmerId, PlotHashing, YValueManager};
 #[cfg(feature = "std")]
 use anyhow::{Context, Result};
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
 use melo_das_primitives::Segment;
 
 /// Represents a manager for Z values in a specific blockchain context.
@@ -43,16 +45,11 @@ where
 	/// This involves encoding the segments, combining them, and then hashing the combination
 	/// to produce a final Z value.
 	///
+	/// - `hashing`: The hashing scheme of the plot.
 	/// - `left_cell`: The left segment of the blockchain.
 	/// - `right_cell`: The right segment of the blockchain.
-	pub fn calculate_z(left_cell: &Segment, right_cell: &Segment) -> u16 {
-		let left_encoded = Encode::encode(left_cell);
-		let right_encoded = Encode::encode(right_cell);
-
-		let combined = [left_encoded, right_encoded].concat();
-
-		let hash = BlakeTwo256::hash(&combined);
-		utils::hash_to_u16_xor(hash.as_bytes())
+	pub fn calculate_z(hashing: PlotHashing, left_cell: &Segment, right_cell: &Segment) -> u16 {
+		hashing::cell_z(hashing, left_cell, right_cell)
 	}
 
 	/// Generates a challenge value (Z) directly from raw data.
@@ -71,17 +68,19 @@ where
 
 	/// Creates a new instance of `ZValueManager` using the provided cell metadata and segments.
 	///
+	/// - `hashing`: The hashing scheme of the plot.
 	/// - `left`: Metadata for the left cell.
 	/// - `right`: Metadata for the right cell.
 	/// - `left_cell`: The left segment of the blockchain.
 	/// - `right_cell`: The right segment of the blockchain.
 	pub fn new(
+		hashing: PlotHashing,
 		left: &CellMetadata<BlockNumber>,
 		right: &CellMetadata<BlockNumber>,
 		left_cell: &Segment,
 		right_cell: &Segment,
 	) -> Self {
-		let z = Self::calculate_z(left_cell, right_cell);
+		let z = Self::calculate_z(hashing, left_cell, right_cell);
 		Self { z, left: left.clone(), right: right.clone() }
 	}
 
@@ -124,9 +123,91 @@ where
 			.map(|opt| opt.unwrap_or_default())
 	}
 
+	/// Iterates over the non-empty Z buckets in the database, in ascending order of Z value.
+	/// Only available when compiled with the `std` feature.
+	///
+	/// - `db`: A mutable reference to the database to query.
+	#[cfg(feature = "std")]
+	#[allow(clippy::type_complexity)]
+	pub fn buckets(
+		db: &mut impl DasKv,
+	) -> impl Iterator<
+		Item = Result<(u16, Vec<(CellMetadata<BlockNumber>, CellMetadata<BlockNumber>)>)>,
+	> + '_ {
+		(0..=u16::MAX).filter_map(move |z| match Self::get(&mut *db, z) {
+			Ok(pairs) if pairs.is_empty() => None,
+			Ok(pairs) => Some(Ok((z, pairs))),
+			Err(e) => Some(Err(e)),
+		})
+	}
+
+	/// Computes statistics of the Z buckets in the database.
+	/// Only available when compiled with the `std` feature.
+	///
+	/// - `db`: A mutable reference to the database to query.
+	#[cfg(feature = "std")]
+	pub fn stats(db: &mut impl DasKv) -> Result<ZBucketStats> {
+		let mut stats = ZBucketStats::default();
+		for bucket in Self::buckets(db) {
+			let (_, pairs) = bucket?;
+			stats.buckets += 1;
+			stats.pairs += pairs.len() as u64;
+			stats.collisions += pairs.len() as u64 - 1;
+			stats.max_bucket_size = stats.max_bucket_size.max(pairs.len());
+			*stats.bucket_sizes.entry(pairs.len()).or_default() += 1;
+			for (left, right) in pairs {
+				if left.piece_metadata == right.piece_metadata {
+					stats.same_piece_pairs += 1;
+				} else {
+					stats.cross_piece_pairs += 1;
+				}
+			}
+		}
+		Ok(stats)
+	}
+
+	/// Rebuilds the Y and Z indexes of the given pieces.
+	///
+	/// All Z buckets are dropped along with the Y buckets the cells of `pieces` belong to, with
+	/// both the scheme recorded in `db` and `hashing`, then every piece is saved again. Pieces
+	/// that are not passed lose their matches.
+	/// Only available when compiled with the `std` feature.
+	///
+	/// - `db`: A mutable reference to the database.
+	/// - `hashing`: The hashing scheme to rebuild the indexes with.
+	/// - `farmer_id`: The ID of the farmer owning the plot.
+	/// - `pieces`: The pieces of the plot.
+	#[cfg(feature = "std")]
+	pub fn rebuild(
+		db: &mut impl DasKv,
+		hashing: PlotHashing,
+		farmer_id: &FarmerId,
+		pieces: &[Piece<BlockNumber>],
+	) -> Result<()>
+	where
+		BlockNumber: Send + Sync,
+	{
+		for z in 0..=u16::MAX {
+			db.remove(&Encode::encode(&z));
+		}
+
+		let recorded = plot_hashing(db)?.unwrap_or_default();
+		for scheme in [recorded, hashing] {
+			for piece in pieces {
+				for (index, (y, _)) in piece.x_values_iterator(scheme, farmer_id).enumerate() {
+					let pos = YPos::from_u32(index as u32);
+					db.remove(&YValueManager::<BlockNumber>::key_by_x_pos(&pos, y));
+				}
+			}
+		}
+
+		PlotUpdate::save_pieces(db, hashing, farmer_id, pieces, |_| ()).map(|_| ())
+	}
+
 	/// Verifies whether the given Z value, farmer ID, and cell segments and metadata
 	/// match the expected criteria for validation.
 	///
+	/// - `hashing`: The hashing scheme of the plot.
 	/// - `z`: The Z value to verify.
 	/// - `farmer_id`: The ID of the farmer.
 	/// - `left_cell`: The left segment of the blockchain.
@@ -134,6 +215,7 @@ where
 	/// - `left_cell_metadata`: Metadata for the left cell.
 	/// - `right_cell_metadata`: Metadata for the right cell.
 	pub fn verify(
+		hashing: PlotHashing,
 		z: u16,
 		farmer_id: &FarmerId,
 		left_cell: &Segment,
@@ -141,14 +223,34 @@ where
 		left_cell_metadata: &CellMetadata<BlockNumber>,
 		right_cell_metadata: &CellMetadata<BlockNumber>,
 	) -> bool {
-		let calculated_z = Self::calculate_z(left_cell, right_cell);
+		let calculated_z = Self::calculate_z(hashing, left_cell, right_cell);
 		let is_pair = left_cell_metadata.is_pair(right_cell_metadata);
-		let is_y_equal = YValueManager::<BlockNumber>::calculate_y(farmer_id, left_cell) ==
-		YValueManager::<BlockNumber>::calculate_y(farmer_id, right_cell);
+		let is_y_equal = YValueManager::<BlockNumber>::calculate_y(hashing, farmer_id, left_cell) ==
+			YValueManager::<BlockNumber>::calculate_y(hashing, farmer_id, right_cell);
 		z == calculated_z && is_pair && is_y_equal
 	}
 }
 
+/// Statistics of the Z buckets of a plot.
+#[cfg(feature = "std")]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ZBucketStats {
+	/// Number of non-empty buckets.
+	pub buckets: u32,
+	/// Total number of matched cell pairs.
+	pub pairs: u64,
+	/// Number of pairs sharing a bucket with an earlier pair.
+	pub collisions: u64,
+	/// Size of the largest bucket.
+	pub max_bucket_size: usize,
+	/// Number of buckets by bucket size.
+	pub bucket_sizes: BTreeMap<usize, u32>,
+	/// Number of pairs whose cells belong to the same piece.
+	pub same_piece_pairs: u64,
+	/// Number of pairs whose cells belong to different pieces.
+	pub cross_piece_pairs: u64,
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -165,7 +267,7 @@ mod tests {
 		let left_seg = get_mock_seg(left_cell, 0, 0, &left_proof, 16);
 		let right_seg = get_mock_seg(right_cell, 1, 0, &right_proof, 16);
 
-		let z = ZValueManager::<u16>::calculate_z(&left_seg, &right_seg);
+		let z = ZValueManager::<u16>::calculate_z(PlotHashing::Blake2, &left_seg, &right_seg);
 		assert_eq!(z, expected_z);
 	}
 
@@ -190,7 +292,13 @@ mod tests {
 		let left_metadata = CellMetadata::<u16>::default();
 		let right_metadata = CellMetadata::<u16>::default();
 
-		let zvm = ZValueManager::new(&left_metadata, &right_metadata, &left_seg, &right_seg);
+		let zvm = ZValueManager::new(
+			PlotHashing::Blake2,
+			&left_metadata,
+			&right_metadata,
+			&left_seg,
+			&right_seg,
+		);
 		assert_eq!(zvm.z, expected_z);
 		zvm.save(db);
 	}
@@ -233,6 +341,7 @@ mod tests {
 		let right_metadata = CellMetadata::<u32>::new(piece_metadata_right, 1);
 
 		let is_valid = ZValueManager::<u32>::verify(
+			PlotHashing::Blake2,
 			123,
 			&farmer_id,
 			&left_seg,
@@ -243,6 +352,7 @@ mod tests {
 		assert!(!is_valid);
 
 		let is_valid = ZValueManager::<u32>::verify(
+			PlotHashing::Blake2,
 			u16::MAX,
 			&farmer_id,
 			&left_seg,
@@ -253,6 +363,7 @@ mod tests {
 		assert!(!is_valid);
 
 		let is_valid = ZValueManager::<u32>::verify(
+			PlotHashing::Blake2,
 			Z1,
 			&farmer_id,
 			&left_seg,
@@ -263,4 +374,91 @@ mod tests {
 
 		assert!(is_valid);
 	}
+
+	#[test]
+	fn test_buckets_and_stats() {
+		let mut db = MockDb::new();
+		assert_eq!(ZValueManager::<u16>::stats(&mut db).unwrap(), ZBucketStats::default());
+
+		z_store(&BLS_SCALAR11, &BLS_SCALAR12, &PROOF_11, &PROOF_12, Z1, &mut db);
+		z_store(&BLS_SCALAR21, &BLS_SCALAR22, &PROOF_21, &PROOF_22, Z2, &mut db);
+
+		let buckets = ZValueManager::<u16>::buckets(&mut db)
+			.collect::<Result<Vec<_>>>()
+			.unwrap()
+			.into_iter()
+			.map(|(z, pairs)| (z, pairs.len()))
+			.collect::<Vec<_>>();
+		let mut expected = vec![(Z1, 1), (Z2, 1)];
+		expected.sort();
+		assert_eq!(buckets, expected);
+
+		let stats = ZValueManager::<u16>::stats(&mut db).unwrap();
+		assert_eq!(stats.buckets, 2);
+		assert_eq!(stats.pairs, 2);
+		assert_eq!(stats.collisions, 0);
+		assert_eq!(stats.max_bucket_size, 1);
+		assert_eq!(stats.same_piece_pairs, 2);
+		assert_eq!(stats.bucket_sizes.get(&1), Some(&2));
+	}
+
+	#[test]
+	fn test_rebuild() {
+		let mut db = MockDb::new();
+		let farmer_id = FarmerId::default();
+
+		let row = get_mock_row(&BLS_SCALAR11, &BLS_SCALAR12, 0, &PROOF_11, &PROOF_12, 16);
+		let piece = Piece::new(11u32, PiecePosition::Row(0), &row);
+		piece.save(&mut db, PlotHashing::Blake2, &farmer_id).unwrap();
+
+		let before = ZValueManager::<u32>::stats(&mut db).unwrap();
+		assert!(before.pairs > 0);
+
+		// A dropped Z bucket is restored.
+		db.remove(&Encode::encode(&Z1));
+		ZValueManager::<u32>::rebuild(&mut db, PlotHashing::Blake2, &farmer_id, &[piece.clone()])
+			.unwrap();
+		assert_eq!(ZValueManager::<u32>::stats(&mut db).unwrap(), before);
+
+		// Rebuilding twice does not duplicate matches.
+		ZValueManager::<u32>::rebuild(&mut db, PlotHashing::Blake2, &farmer_id, &[piece]).unwrap();
+		assert_eq!(ZValueManager::<u32>::stats(&mut db).unwrap(), before);
+	}
+
+	#[test]
+	fn test_rebuild_with_another_hashing() {
+		let mut db = MockDb::new();
+		let farmer_id = FarmerId::default();
+
+		let row = get_mock_row(&BLS_SCALAR11, &BLS_SCALAR12, 0, &PROOF_11, &PROOF_12, 16);
+		let piece = Piece::new(11u32, PiecePosition::Row(0), &row);
+		piece.save(&mut db, PlotHashing::Blake2, &farmer_id).unwrap();
+
+		ZValueManager::<u32>::rebuild(&mut db, PlotHashing::Keccak, &farmer_id, &[piece.clone()])
+			.unwrap();
+		assert_eq!(plot_hashing(&mut db).unwrap(), Some(PlotHashing::Keccak));
+
+		// No Y entry of the previous scheme is left to match new cells against.
+		for (index, (y, _)) in piece.x_values_iterator(PlotHashing::Blake2, &farmer_id).enumerate()
+		{
+			let key = YValueManager::<u32>::key_by_x_pos(&YPos::from_u32(index as u32), y);
+			assert!(!db.contains(&key));
+		}
+		for bucket in ZValueManager::<u32>::buckets(&mut db) {
+			let (z, pairs) = bucket.unwrap();
+			for (left, right) in pairs {
+				let left_seg = Piece::get_cell(&left, &mut db).unwrap().unwrap();
+				let right_seg = Piece::get_cell(&right, &mut db).unwrap().unwrap();
+				assert!(ZValueManager::<u32>::verify(
+					PlotHashing::Keccak,
+					z,
+					&farmer_id,
+					&left_seg,
+					&right_seg,
+					&left,
+					&right
+				));
+			}
+		}
+	}
 }
@@ -0,0 +1,154 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Plot binding.
+//!
+//! The runtime only rewards solutions found with the farmer ID bound to the reward account.
+//! [`ensure_registered`] binds the ID of the local plots when the farmer starts, and
+//! [`rotate_key`] hands the ID over to another account, so that a compromised key can be replaced
+//! without replotting. [`ensure_pool_member`] makes the account a member of the farming pool it
+//! sends its solutions to.
+
+use anyhow::{anyhow, Result};
+use log::info;
+use melo_proof_of_space::{FarmerId, PlotHashing};
+use meloxt::{AccountId, Client};
+use subxt::{dynamic::Value, ext::codec::Decode};
+
+/// Returns the farmer ID bound to `account_id`, if any.
+async fn bound_farmer_id(client: &Client, account_id: &AccountId) -> Result<Option<FarmerId>> {
+	let key = client.storage_key("FarmersFortune", "FarmerBindings", account_id)?;
+	match client.api.rpc().storage(&key, None).await? {
+		Some(data) => Ok(Some(FarmerId::decode(&mut &data.0[..])?)),
+		None => Ok(None),
+	}
+}
+
+/// Returns the number of times `farmer_id` was handed over to another account.
+async fn rotation_nonce(client: &Client, farmer_id: &FarmerId) -> Result<u32> {
+	let farmer_id = Value::unnamed_composite([Value::from_bytes(farmer_id.0)]);
+	let key = client.storage_key("FarmersFortune", "RotationNonces", &farmer_id)?;
+	match client.api.rpc().storage(&key, None).await? {
+		Some(data) => Ok(u32::decode(&mut &data.0[..])?),
+		None => Ok(0),
+	}
+}
+
+/// Returns the farmer ID of the local plots, binding it to the signing account of `client`
+/// unless it is already bound.
+///
+/// An account holds the ID derived from its own key, or the ID handed over to it by
+/// [`rotate_key`], which the plots were made with. A new ID is derived with `hashing`, the plot
+/// hashing scheme active on-chain.
+pub async fn ensure_registered(client: &Client, hashing: PlotHashing) -> Result<FarmerId> {
+	let account_id = client.signer.public_key().to_account_id();
+
+	if let Some(bound) = bound_farmer_id(client, &account_id).await? {
+		info!("🔗 Farmer ID {:?} is bound to {}", bound.0, account_id);
+		return Ok(bound)
+	}
+
+	let farmer_id = FarmerId::new_with(hashing, account_id.clone());
+	let signature = client.signer.sign(&farmer_id.binding_message());
+	let register_tx = subxt::dynamic::tx(
+		"FarmersFortune",
+		"register_farmer",
+		vec![
+			Value::unnamed_composite([Value::from_bytes(account_id.0)]),
+			Value::unnamed_composite([Value::unnamed_composite([Value::from_bytes(farmer_id.0)])]),
+			Value::unnamed_variant(
+				"Sr25519",
+				[Value::unnamed_composite([Value::from_bytes(signature.0)])],
+			),
+		],
+	);
+
+	client
+		.api
+		.tx()
+		.sign_and_submit_then_watch_default(&register_tx, &client.signer)
+		.await?
+		.wait_for_finalized_success()
+		.await?;
+
+	info!("🔗 Bound farmer ID {:?} to {}", farmer_id.0, account_id);
+	Ok(farmer_id)
+}
+
+/// Hands the farmer ID bound to the signing account of `client` over to `new_account`.
+///
+/// The plots stay valid, the farmer is then run with the key of `new_account`. The claims of the
+/// signing account are still credited to `new_account` for a grace period.
+pub async fn rotate_key(client: &Client, new_account: &AccountId) -> Result<()> {
+	let account_id = client.signer.public_key().to_account_id();
+	let farmer_id = bound_farmer_id(client, &account_id)
+		.await?
+		.ok_or_else(|| anyhow!("Account {} is not bound to a farmer ID", account_id))?;
+
+	let nonce = rotation_nonce(client, &farmer_id).await?;
+	let signature = client.signer.sign(&farmer_id.rotation_message(new_account, nonce));
+	let rotate_tx = subxt::dynamic::tx(
+		"FarmersFortune",
+		"rotate_farmer_key",
+		vec![
+			Value::unnamed_composite([Value::from_bytes(account_id.0)]),
+			Value::unnamed_composite([Value::from_bytes(new_account.0)]),
+			Value::unnamed_variant(
+				"Sr25519",
+				[Value::unnamed_composite([Value::from_bytes(signature.0)])],
+			),
+		],
+	);
+
+	client
+		.api
+		.tx()
+		.sign_and_submit_then_watch_default(&rotate_tx, &client.signer)
+		.await?
+		.wait_for_finalized_success()
+		.await?;
+
+	info!("🔑 Handed farmer ID {:?} over from {} to {}", farmer_id.0, account_id, new_account);
+	Ok(())
+}
+
+/// Makes the signing account of `client` a member of the farming pool `pool_id`, unless it
+/// already is.
+///
+/// An account farms for a single pool, it has to leave the pool it is a member of first.
+pub async fn ensure_pool_member(client: &Client, pool_id: u32) -> Result<()> {
+	let account_id = client.signer.public_key().to_account_id();
+
+	let key = client.storage_key("FarmingPools", "Memberships", &account_id)?;
+	if let Some(data) = client.api.rpc().storage(&key, None).await? {
+		let member_of = u32::decode(&mut &data.0[..])?;
+		if member_of != pool_id {
+			return Err(anyhow!("Account {} is a member of pool {}", account_id, member_of))
+		}
+		return Ok(())
+	}
+
+	let join_tx =
+		subxt::dynamic::tx("FarmingPools", "join_pool", vec![Value::u128(pool_id.into())]);
+	client
+		.api
+		.tx()
+		.sign_and_submit_then_watch_default(&join_tx, &client.signer)
+		.await?
+		.wait_for_finalized_success()
+		.await?;
+
+	info!("🏊 {} joined pool {}", account_id, pool_id);
+	Ok(())
+}
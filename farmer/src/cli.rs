@@ -12,25 +12,40 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use anyhow::{Context, Result};
 use clap::{ArgAction, Parser};
-use melo_das_network::DasNetworkConfig;
-use std::net::SocketAddr;
+use melo_das_network::{DasNetworkConfig, NodeRole};
+use meloxt::{AccountId, SignerSource};
+use serde::Deserialize;
+use std::{net::SocketAddr, path::PathBuf};
 
 pub const DEFAULT_RPC_LISTEN_ADDR: &str = "127.0.0.1:4177";
 
+pub const DEFAULT_PROMETHEUS_ADDR: &str = "127.0.0.1:9616";
+
 const DEV_RPC_URL: &str = "ws://127.0.0.1:9944";
 const TEST_RPC_URL: &str = "wss://dev.melodot.io:9944";
 
 const DEFAULT_RPC_URL: &str = "ws://127.0.0.1:9944";
 
-/// Command line interface configuration
+const DEFAULT_PLOTS_DIR: &str = "./plots";
+const DEV_SURI: &str = "//Alice";
+
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 pub struct Cli {
+	/// Path of the TOML configuration file
+	#[clap(short = 'c', long, env = "FARMER_CONFIG")]
+	config: Option<PathBuf>,
+
 	/// Listening address for the RPC service
 	#[clap(short = 'a', long, env)]
 	rpc_listen_addr: Option<SocketAddr>,
 
+	/// Listening address for the Prometheus endpoint
+	#[clap(long, env = "PROMETHEUS_ADDR")]
+	prometheus_addr: Option<SocketAddr>,
+
 	/// Remote RPC URL for receiving messages
 	#[clap(short = 'r', long, env = "RPC_REMOTE_URL")]
 	rpc_remote_url: Option<String>,
@@ -39,6 +54,10 @@ pub struct Cli {
 	#[clap(short = 'd', long, env = "DAS_NET_LISTEN_PORT")]
 	das_net_listen_port: Option<u16>,
 
+	/// Directory where plotted pieces are stored
+	#[clap(short = 'p', long, env = "FARMER_PLOTS_DIR")]
+	plots_dir: Option<PathBuf>,
+
 	/// Activate development configuration
 	#[clap(long, action = ArgAction::SetTrue)]
 	dev_mode: bool,
@@ -46,23 +65,95 @@ pub struct Cli {
 	/// Activate test configuration
 	#[clap(long, action = ArgAction::SetTrue)]
 	test_mode: bool,
+
+	/// Hand the farmer ID of the signing account over to this account and exit. The plots stay
+	/// valid for the new account, whose key then signs the claims.
+	#[clap(long, value_name = "ACCOUNT")]
+	rotate_to: Option<String>,
+
+	/// Websocket endpoint of the pool server to send solutions to, instead of claiming them
+	#[clap(long, env = "FARMER_POOL_URL")]
+	pool_url: Option<String>,
+
+	/// Publish the solutions on the DAS network for the relaying nodes to claim them, instead of
+	/// claiming them
+	#[clap(long, action = ArgAction::SetTrue)]
+	relay_solutions: bool,
+
+	/// Claim rewards for the segments served to the peers, with the receipts they sign
+	#[clap(long, action = ArgAction::SetTrue)]
+	claim_serving_rewards: bool,
+}
+
+/// The farmer configuration file. Command line arguments take precedence over its values.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+	/// Websocket endpoint of the Melodot node.
+	pub node_url: Option<String>,
+	/// Directory where plotted pieces are stored.
+	pub plots_dir: Option<PathBuf>,
+	/// The account receiving the rewards, it must be the account of the signing key.
+	pub reward_address: Option<String>,
+	/// Keystore file holding the secret URI of the signing key.
+	pub keystore: Option<PathBuf>,
+	/// Secret URI of the signing key, used if no keystore is given.
+	pub suri: Option<String>,
+	/// Listening address for the RPC service.
+	pub rpc_listen_addr: Option<SocketAddr>,
+	/// Listening address for the Prometheus endpoint.
+	pub prometheus_addr: Option<SocketAddr>,
+	/// Listening port of the DAS network.
+	pub das_listen_port: Option<u16>,
+	/// Websocket endpoint of the pool server to send solutions to.
+	pub pool_url: Option<String>,
+	/// Publish the solutions on the DAS network instead of claiming them.
+	pub relay_solutions: Option<bool>,
+	/// Claim rewards for the segments served to the peers.
+	pub claim_serving_rewards: Option<bool>,
+}
+
+impl FileConfig {
+	/// Reads the configuration from a TOML file.
+	pub fn load(path: &PathBuf) -> Result<Self> {
+		let content = std::fs::read_to_string(path)
+			.with_context(|| format!("Failed to read config file {:?}", path))?;
+		toml::from_str(&content).with_context(|| format!("Invalid config file {:?}", path))
+	}
 }
 
-/// Application configuration
 pub struct Config {
 	pub rpc_listen_addr: SocketAddr,
+	pub prometheus_addr: SocketAddr,
 	pub rpc_url: String,
 	pub network_config: DasNetworkConfig,
+	pub plots_dir: PathBuf,
+	pub signer: SignerSource,
+	pub reward_address: Option<AccountId>,
+	pub rotate_to: Option<AccountId>,
+	pub pool_url: Option<String>,
+	pub relay_solutions: bool,
+	pub claim_serving_rewards: bool,
 }
 
 impl Config {
-	pub fn from_cli_args(cli: Cli) -> Self {
-		let rpc_listen_addr = cli.rpc_listen_addr.unwrap_or_else(|| {
+	pub fn from_cli_args(cli: Cli) -> Result<Self> {
+		let file = match &cli.config {
+			Some(path) => FileConfig::load(path)?,
+			None => FileConfig::default(),
+		};
+
+		let rpc_listen_addr = cli.rpc_listen_addr.or(file.rpc_listen_addr).unwrap_or_else(|| {
 			DEFAULT_RPC_LISTEN_ADDR.parse().expect("Invalid DEFAULT SocketAddr")
 		});
 
-		let mut das_network_config = DasNetworkConfig::default();
-		let mut rpc_url = DEFAULT_RPC_URL.to_string();
+		let prometheus_addr = cli.prometheus_addr.or(file.prometheus_addr).unwrap_or_else(|| {
+			DEFAULT_PROMETHEUS_ADDR.parse().expect("Invalid DEFAULT SocketAddr")
+		});
+
+		let mut das_network_config =
+			DasNetworkConfig { role: NodeRole::Farmer, ..Default::default() };
+		let mut rpc_url = file.node_url.unwrap_or_else(|| DEFAULT_RPC_URL.to_string());
 
 		if cli.dev_mode {
 			rpc_url = DEV_RPC_URL.to_string();
@@ -75,13 +166,55 @@ impl Config {
 			rpc_url = rpc_remote_url.to_string();
 		}
 
-        print!("poot: {}", das_network_config.listen_port);
+		if let Some(port) = cli.das_net_listen_port.or(file.das_listen_port) {
+			das_network_config.listen_port = port;
+		}
 
-		Config { rpc_listen_addr, rpc_url, network_config: das_network_config }
+		let plots_dir =
+			cli.plots_dir.or(file.plots_dir).unwrap_or_else(|| PathBuf::from(DEFAULT_PLOTS_DIR));
+
+		let signer = match (file.keystore, file.suri) {
+			(Some(path), _) => SignerSource::Keystore(path),
+			(None, Some(suri)) => SignerSource::Uri(suri),
+			(None, None) => SignerSource::Uri(DEV_SURI.to_string()),
+		};
+
+		let reward_address = file
+			.reward_address
+			.map(|address| {
+				address
+					.parse::<AccountId>()
+					.map_err(|e| anyhow::anyhow!("Invalid reward address {}: {:?}", address, e))
+			})
+			.transpose()?;
+
+		let rotate_to = cli
+			.rotate_to
+			.map(|address| {
+				address
+					.parse::<AccountId>()
+					.map_err(|e| anyhow::anyhow!("Invalid rotation address {}: {:?}", address, e))
+			})
+			.transpose()?;
+
+		Ok(Config {
+			rpc_listen_addr,
+			prometheus_addr,
+			rpc_url,
+			network_config: das_network_config,
+			plots_dir,
+			signer,
+			reward_address,
+			rotate_to,
+			pool_url: cli.pool_url.or(file.pool_url),
+			relay_solutions: cli.relay_solutions || file.relay_solutions.unwrap_or_default(),
+			claim_serving_rewards: cli.claim_serving_rewards ||
+				file.claim_serving_rewards.unwrap_or_default(),
+		})
 	}
 }
 
-pub fn parse_args() -> Config {
+pub fn parse_args() -> Result<Config> {
 	let cli = Cli::parse();
 	Config::from_cli_args(cli)
 }
@@ -0,0 +1,253 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Farm monitoring.
+//!
+//! The [`Dashboard`] collects the farmer statistics. They are exposed as JSON through the
+//! `farmer_stats` RPC method and, together with the DAS network metrics, in the Prometheus format.
+
+use jsonrpsee::{
+	core::{async_trait, RpcResult},
+	proc_macros::rpc,
+	server::ServerBuilder,
+};
+use log::debug;
+use prometheus_endpoint::{register, Counter, Gauge, PrometheusError, Registry, U64};
+use serde::Serialize;
+use std::{
+	collections::BTreeMap,
+	net::SocketAddr,
+	path::PathBuf,
+	sync::{Arc, Mutex},
+};
+
+/// Number of most recent blocks whose stored pieces are reported.
+const TRACKED_BLOCKS: usize = 1024;
+
+/// Name of the DAS network inbound requests metric.
+const INBOUND_REQUESTS_METRIC: &str = "das_network_inbound_requests_total";
+
+/// A snapshot of the farmer statistics.
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FarmerStats {
+	/// Size of the plots database in bytes.
+	pub plot_size_bytes: u64,
+	/// Pieces stored per block, for the most recent blocks.
+	pub pieces_per_block: BTreeMap<u32, u32>,
+	/// Total number of pieces stored.
+	pub pieces_stored: u64,
+	/// Number of blocks whose pieces wait to be plotted.
+	pub plot_queue: u64,
+	/// Number of solutions found.
+	pub solutions_found: u64,
+	/// Number of solutions accepted on chain.
+	pub solutions_accepted: u64,
+	/// Sum of the rewards of accepted solutions.
+	pub estimated_rewards: u128,
+	/// Inbound DHT requests served, by request type.
+	pub dht_requests_served: BTreeMap<String, u64>,
+}
+
+struct Metrics {
+	pieces_stored: Counter<U64>,
+	solutions_found: Counter<U64>,
+	solutions_accepted: Counter<U64>,
+	rewards: Counter<U64>,
+	plot_size: Gauge<U64>,
+	plot_queue: Gauge<U64>,
+}
+
+impl Metrics {
+	fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			pieces_stored: register(
+				Counter::new("farmer_pieces_stored_total", "Total number of pieces stored")?,
+				registry,
+			)?,
+			solutions_found: register(
+				Counter::new("farmer_solutions_found_total", "Total number of solutions found")?,
+				registry,
+			)?,
+			solutions_accepted: register(
+				Counter::new(
+					"farmer_solutions_accepted_total",
+					"Total number of solutions accepted on chain",
+				)?,
+				registry,
+			)?,
+			rewards: register(
+				Counter::new("farmer_rewards_total", "Sum of the rewards of accepted solutions")?,
+				registry,
+			)?,
+			plot_size: register(
+				Gauge::new("farmer_plot_size_bytes", "Size of the plots database in bytes")?,
+				registry,
+			)?,
+			plot_queue: register(
+				Gauge::new(
+					"farmer_plot_queue_blocks",
+					"Number of blocks whose pieces wait to be plotted",
+				)?,
+				registry,
+			)?,
+		})
+	}
+}
+
+/// Collects the farmer statistics.
+pub struct Dashboard {
+	registry: Registry,
+	metrics: Option<Metrics>,
+	stats: Mutex<FarmerStats>,
+	plots_path: PathBuf,
+}
+
+impl Dashboard {
+	/// Creates a new dashboard, registering the farmer metrics in `registry`.
+	pub fn new(registry: Registry, plots_path: PathBuf) -> Self {
+		let metrics = match Metrics::register(&registry) {
+			Ok(metrics) => Some(metrics),
+			Err(e) => {
+				debug!("Failed to register farmer metrics: {:?}", e);
+				None
+			},
+		};
+		Self { registry, metrics, stats: Default::default(), plots_path }
+	}
+
+	/// The registry holding the farmer and DAS network metrics.
+	pub fn registry(&self) -> &Registry {
+		&self.registry
+	}
+
+	/// Records the pieces of a block queued for plotting.
+	pub fn record_plot_queued(&self) {
+		let mut stats = self.stats.lock().expect("Dashboard lock poisoned");
+		stats.plot_queue += 1;
+
+		if let Some(metrics) = &self.metrics {
+			metrics.plot_queue.set(stats.plot_queue);
+		}
+	}
+
+	/// Records `count` pieces of `block_number` plotted, removing the block from the queue.
+	pub fn record_plotted(&self, block_number: u32, count: u32) {
+		let mut stats = self.stats.lock().expect("Dashboard lock poisoned");
+		stats.plot_queue = stats.plot_queue.saturating_sub(1);
+		*stats.pieces_per_block.entry(block_number).or_default() += count;
+		stats.pieces_stored += count as u64;
+		while stats.pieces_per_block.len() > TRACKED_BLOCKS {
+			stats.pieces_per_block.pop_first();
+		}
+
+		if let Some(metrics) = &self.metrics {
+			metrics.pieces_stored.inc_by(count as u64);
+			metrics.plot_queue.set(stats.plot_queue);
+		}
+	}
+
+	/// Records a solution found.
+	pub fn record_solution_found(&self) {
+		self.stats.lock().expect("Dashboard lock poisoned").solutions_found += 1;
+		if let Some(metrics) = &self.metrics {
+			metrics.solutions_found.inc();
+		}
+	}
+
+	/// Records a solution accepted on chain with the given reward.
+	pub fn record_solution_accepted(&self, reward: u128) {
+		let mut stats = self.stats.lock().expect("Dashboard lock poisoned");
+		stats.solutions_accepted += 1;
+		stats.estimated_rewards = stats.estimated_rewards.saturating_add(reward);
+
+		if let Some(metrics) = &self.metrics {
+			metrics.solutions_accepted.inc();
+			metrics.rewards.inc_by(reward.try_into().unwrap_or(u64::MAX));
+		}
+	}
+
+	/// Returns the current statistics.
+	pub fn stats(&self) -> FarmerStats {
+		let plot_size = std::fs::metadata(&self.plots_path).map(|m| m.len()).unwrap_or_default();
+		if let Some(metrics) = &self.metrics {
+			metrics.plot_size.set(plot_size);
+		}
+
+		let dht_requests_served = self
+			.registry
+			.gather()
+			.iter()
+			.filter(|family| family.get_name() == INBOUND_REQUESTS_METRIC)
+			.flat_map(|family| family.get_metric())
+			.map(|metric| {
+				let label = metric
+					.get_label()
+					.iter()
+					.map(|label| label.get_value().to_string())
+					.collect::<Vec<_>>()
+					.join(",");
+				(label, metric.get_counter().get_value() as u64)
+			})
+			.collect();
+
+		let mut stats = self.stats.lock().expect("Dashboard lock poisoned").clone();
+		stats.plot_size_bytes = plot_size;
+		stats.dht_requests_served = dht_requests_served;
+		stats
+	}
+}
+
+/// The farmer dashboard RPC API.
+#[rpc(server, namespace = "farmer")]
+pub trait FarmerApi {
+	/// Returns the current farmer statistics.
+	#[method(name = "stats")]
+	async fn stats(&self) -> RpcResult<FarmerStats>;
+}
+
+struct FarmerRpc {
+	dashboard: Arc<Dashboard>,
+}
+
+#[async_trait]
+impl FarmerApiServer for FarmerRpc {
+	async fn stats(&self) -> RpcResult<FarmerStats> {
+		Ok(self.dashboard.stats())
+	}
+}
+
+/// Starts the JSON-RPC server exposing the dashboard on `addr`.
+pub async fn run_rpc_server(
+	dashboard: Arc<Dashboard>,
+	addr: &SocketAddr,
+) -> anyhow::Result<SocketAddr> {
+	let server = ServerBuilder::default().build(addr).await?;
+	let addr = server.local_addr()?;
+	let handle = server.start(FarmerRpc { dashboard }.into_rpc())?;
+
+	tokio::spawn(handle.stopped());
+
+	Ok(addr)
+}
+
+/// Starts the Prometheus endpoint exposing the dashboard metrics on `addr`.
+pub fn run_prometheus(dashboard: &Dashboard, addr: SocketAddr) {
+	let registry = dashboard.registry().clone();
+	tokio::spawn(async move {
+		if let Err(e) = prometheus_endpoint::init_prometheus(addr, registry).await {
+			log::error!("❌ Prometheus endpoint failed: {:?}", e);
+		}
+	});
+}
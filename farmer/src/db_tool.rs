@@ -0,0 +1,199 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `melo-db-tool`, a utility to back up, restore and move the database of a farmer.
+//!
+//! Snapshots are consistent copies of the database, taken while the farmer runs. Before a
+//! snapshot is imported, its file is checked and the segments of its pieces are verified against
+//! the commitments of their blocks on-chain, so that a damaged snapshot is never plotted.
+
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand};
+use melo_core_primitives::{config::SEGMENTS_PER_BLOB, traits::HeaderWithCommitment};
+use melo_das_db::{sqlite::SqliteDasDb, traits::DasKv};
+use melo_das_primitives::{KZGCommitment, KZG};
+use melo_erasure_coding::erasure_coding::extend_and_reorder_elements;
+use melo_proof_of_space::{plot_hashing, Piece};
+use meloxt::{Client, ClientBuilder};
+use std::{
+	collections::BTreeMap,
+	path::{Path, PathBuf},
+};
+use subxt::ext::codec::DecodeAll;
+
+#[derive(Parser, Debug)]
+#[command(name = "melo-db-tool", about = "Backs up and restores the database of a Melodot farmer.")]
+struct Cli {
+	/// Path of the database.
+	#[arg(long)]
+	db: PathBuf,
+
+	#[command(subcommand)]
+	command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+	/// Shows the size of the database and the pieces it holds.
+	Inspect,
+	/// Rebuilds the database file, reclaiming the space of the removed values.
+	Compact,
+	/// Writes a consistent snapshot of the database to a new file.
+	Export {
+		/// Path of the snapshot, which must not exist.
+		#[arg(long)]
+		output: PathBuf,
+	},
+	/// Imports a snapshot into the database once its pieces are verified against the chain.
+	Import {
+		/// Path of the snapshot.
+		#[arg(long)]
+		input: PathBuf,
+		/// The RPC URL of a node of the chain the pieces were plotted from.
+		#[arg(long, default_value = "ws://127.0.0.1:9944")]
+		rpc_url: String,
+	},
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+	let cli = Cli::parse();
+	let mut db = open(&cli.db)?;
+
+	match cli.command {
+		Command::Inspect => {
+			let (entries, bytes) = db.size()?;
+			let pieces = load_pieces(&db)?;
+			let hashing = plot_hashing(&mut db)?;
+
+			println!("entries: {}", entries);
+			println!("size:    {} bytes", bytes);
+			println!("pieces:  {}", pieces.len());
+			let blocks = pieces.iter().map(|piece| piece.metadata.block_num);
+			if let (Some(first), Some(last)) = (blocks.clone().min(), blocks.max()) {
+				println!("blocks:  #{} to #{}", first, last);
+			}
+			println!("hashing: {}", hashing.map_or("unrecorded", |h| h.as_str()));
+		},
+		Command::Compact => {
+			let before = std::fs::metadata(&cli.db)?.len();
+			db.compact()?;
+			let after = std::fs::metadata(&cli.db)?.len();
+			println!("Compacted {:?} from {} to {} bytes", cli.db, before, after);
+		},
+		Command::Export { output } => {
+			db.snapshot(&output).map_err(|e| anyhow!(e))?;
+			println!("Exported {:?} to {:?}", cli.db, output);
+		},
+		Command::Import { input, rpc_url } => {
+			let mut snapshot = open(&input)?;
+			let problems = snapshot.integrity_check()?;
+			if !problems.is_empty() {
+				return Err(anyhow!("Snapshot {:?} is damaged: {}", input, problems.join("; ")))
+			}
+			if let (Some(ours), Some(theirs)) =
+				(plot_hashing(&mut db)?, plot_hashing(&mut snapshot)?)
+			{
+				if ours != theirs {
+					return Err(anyhow!(
+						"Snapshot plots are hashed with {}, the database with {}",
+						theirs.as_str(),
+						ours.as_str()
+					))
+				}
+			}
+
+			let pieces = load_pieces(&snapshot)?;
+			let client = ClientBuilder::default().set_url(&rpc_url).build().await?;
+			verify_pieces(&client, &pieces).await?;
+			drop(snapshot);
+
+			let imported = db.import(&input)?;
+			println!("Imported {} entries, {} pieces verified", imported, pieces.len());
+		},
+	}
+
+	Ok(())
+}
+
+fn open(path: &Path) -> Result<SqliteDasDb> {
+	let path = path.to_str().ok_or_else(|| anyhow!("Invalid database path {:?}", path))?;
+	Ok(SqliteDasDb::new(path)?)
+}
+
+/// Loads the pieces stored in the database.
+///
+/// An entry is a piece if its value decodes to a piece whose key is the key of the entry.
+fn load_pieces(db: &SqliteDasDb) -> Result<Vec<Piece<u32>>> {
+	let mut pieces = Vec::new();
+	db.for_each(|key, value| {
+		if let Ok(piece) = Piece::<u32>::decode_all(&mut &value[..]) {
+			if piece.key() == key {
+				pieces.push(piece);
+			}
+		}
+	})?;
+	Ok(pieces)
+}
+
+/// Verifies the segments of `pieces` against the commitments of the rows of the extended matrix
+/// of their blocks, on the chain of `client`.
+async fn verify_pieces(client: &Client, pieces: &[Piece<u32>]) -> Result<()> {
+	let kzg = KZG::default_embedded();
+	let mut commitments = BTreeMap::<u32, Vec<KZGCommitment>>::new();
+
+	for piece in pieces {
+		let number = piece.metadata.block_num;
+		if !commitments.contains_key(&number) {
+			let hash = client
+				.api
+				.rpc()
+				.block_hash(Some(number.into()))
+				.await?
+				.ok_or_else(|| anyhow!("Block #{} is not on the chain", number))?;
+			let header = client
+				.api
+				.rpc()
+				.header(Some(hash))
+				.await?
+				.ok_or_else(|| anyhow!("Header of block #{} not found", number))?;
+			let extended = extend_and_reorder_elements(
+				kzg.get_fs(),
+				&header.commitments().unwrap_or_default(),
+			)
+			.map_err(|e| anyhow!(e))?;
+			commitments.insert(number, extended);
+		}
+
+		let block_commitments = &commitments[&number];
+		for segment in &piece.segments {
+			let valid =
+				block_commitments.get(segment.position.y as usize).map_or(false, |commitment| {
+					segment
+						.checked()
+						.and_then(|segment| segment.verify(&kzg, commitment, SEGMENTS_PER_BLOB))
+						.unwrap_or(false)
+				});
+			if !valid {
+				return Err(anyhow!(
+					"Segment {:?} of block #{} does not match the commitments on-chain",
+					segment.position,
+					number
+				))
+			}
+		}
+	}
+
+	Ok(())
+}
@@ -15,11 +15,13 @@ use std::time::Instant;
 
 use anyhow::{anyhow, Context};
 use futures::lock::Mutex;
+use jsonrpsee::ws_client::WsClient;
 use log::{error, info};
-use melo_das_network::Arc;
+use melo_das_network::{Arc, Service as DasNetworkService, SolutionAnnouncement};
 use melo_das_primitives::{Position, Segment};
+use melodot_pool_server::{PoolApiClient, PoolServerInfo};
 use meloxt::{cell_to_runtime, melodot, pre_cell_to_runtime, Client, MelodotHeader as Header};
-use subxt::utils::H256;
+use subxt::{ext::codec::Encode, rpc::types::Bytes, utils::H256};
 use tokio::sync::mpsc::Sender;
 use tokio_stream::StreamExt;
 
@@ -27,25 +29,47 @@ use melo_core_primitives::{
 	config::{EXTENDED_SEGMENTS_PER_BLOB, PRE_CELL_LEADING_ZEROS},
 	traits::HeaderWithCommitment,
 };
+use crate::dashboard::Dashboard;
 use melo_das_db::sqlite::SqliteDasDb;
-use melo_daser::{DasKv, DasNetworkServiceWrapper, FetchData, SamplingClient};
-use melo_proof_of_space::{find_solutions, FarmerId, Piece, PiecePosition, PreCell, Solution};
+use melo_daser::{DasNetworkServiceWrapper, FetchData, SamplingClient};
+use melo_proof_of_space::{find_solutions, FarmerId, PiecePosition, PreCell, Solution};
+
+/// Where the solutions found are sent.
+pub enum SolutionSink {
+	/// The solutions are claimed on-chain by the farmer.
+	Chain,
+	/// The solutions are sent to a pool server as partial solutions.
+	Pool { client: WsClient, info: PoolServerInfo },
+	/// The solutions are published on the DAS network, for the relaying nodes to claim them.
+	Relay { network: DasNetworkService },
+}
 
+impl SolutionSink {
+	/// Returns the number of leading zeros the pre-cell of a solution requires.
+	fn pre_cell_leading_zeros(&self) -> u8 {
+		match self {
+			SolutionSink::Chain | SolutionSink::Relay { .. } => PRE_CELL_LEADING_ZEROS,
+			SolutionSink::Pool { info, .. } => info.partial_leading_zeros,
+		}
+	}
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn run<H: HeaderWithCommitment + Sync>(
 	rpc_client: Client,
 	message_tx: Sender<(Header, Instant)>,
 	error_sender: Sender<anyhow::Error>,
 	network: DasNetworkServiceWrapper,
 	database: Arc<Mutex<SqliteDasDb>>,
+	dashboard: Arc<Dashboard>,
+	farmer_id: FarmerId,
+	sink: SolutionSink,
 ) {
+	let leading_zeros = sink.pre_cell_leading_zeros();
+
 	let client: SamplingClient<H, SqliteDasDb, DasNetworkServiceWrapper> =
 		SamplingClient::new(network, database.clone());
 
-	let account_id = rpc_client.signer.public_key().to_account_id();
-
-	// let signer =
-	let farmer_id = FarmerId::new(account_id);
-
 	let mut new_heads_sub = match rpc_client.api.blocks().subscribe_best().await {
 		Ok(subscription) => {
 			info!("🌐 Subscribed to best block headers");
@@ -81,16 +105,30 @@ pub async fn run<H: HeaderWithCommitment + Sync>(
 				error!("❌ Fail to process best block header: {error}");
 			}
 
+			// The challenge is derived by the runtime from the epoch randomness and the slot of
+			// the block, so that the producer of the block can not grind it.
+			let challenge = match rpc_client.challenge(block_number, block_hash).await {
+				Ok(Some(challenge)) => challenge,
+				Ok(None) => {
+					error!("❌ No challenge for block #{}", block_number);
+					continue
+				},
+				Err(e) => {
+					error!("❌ Error fetching challenge of block #{}: {:?}", block_number, e);
+					continue
+				},
+			};
+
 			let row_inds = Solution::<H256, u32>::select_indices(
 				&farmer_id,
-				&block_hash,
+				&challenge,
 				(rows_count * 2) as usize,
 				1,
 			);
 
 			let col_inds = Solution::<H256, u32>::select_indices(
 				&farmer_id,
-				&block_hash,
+				&challenge,
 				EXTENDED_SEGMENTS_PER_BLOB,
 				1,
 			);
@@ -111,36 +149,32 @@ pub async fn run<H: HeaderWithCommitment + Sync>(
 			let mut pre_cells: Vec<PreCell> = Vec::new();
 
 			rows.iter().for_each(|row| {
-				process_segment_data(row, &farmer_id, PiecePosition::from_row, &mut pre_cells);
+				process_segment_data(
+					row,
+					&farmer_id,
+					leading_zeros,
+					PiecePosition::from_row,
+					&mut pre_cells,
+				);
 			});
 			cols.iter().for_each(|col| {
-				process_segment_data(col, &farmer_id, PiecePosition::from_column, &mut pre_cells);
+				process_segment_data(
+					col,
+					&farmer_id,
+					leading_zeros,
+					PiecePosition::from_column,
+					&mut pre_cells,
+				);
 			});
 
+			// Pieces are acquired from finalized blocks by the `PieceFetcher`, only the challenge
+			// is looked up here.
 			let mut database_guard = database.lock().await;
 
-			process_segments(
-				&rows,
-				block_number,
-				&mut *database_guard,
-				&farmer_id,
-				PiecePosition::from_row,
-			);
-
-			process_segments(
-				&cols,
-				block_number,
-				&mut *database_guard,
-				&farmer_id,
-				PiecePosition::from_column,
-			);
-
-			info!("💾 Data saved successfully");
-
 			let mut solutions: Vec<Solution<H256, u32>> = Vec::new();
 
 			pre_cells.iter().for_each(|pre_cell| {
-				match find_solutions(&mut *database_guard, &farmer_id, pre_cell, &block_hash) {
+				match find_solutions(&mut *database_guard, &farmer_id, pre_cell, &challenge) {
 					Ok(mut ss) => {
 						solutions.append(&mut ss);
 					},
@@ -152,25 +186,14 @@ pub async fn run<H: HeaderWithCommitment + Sync>(
 
 			for solution in &solutions {
 				info!("✨ Found solution: {:?}", solution);
-
-				let solution_tx = melodot::tx().farmers_fortune().claim(
-					pre_cell_to_runtime(&solution.pre_cell),
-					cell_to_runtime(&solution.win_cell_left),
-					cell_to_runtime(&solution.win_cell_right),
-				);
-
-				let res = rpc_client
-					.api
-					.tx()
-					.sign_and_submit_then_watch_default(&solution_tx, &rpc_client.signer)
-					.await;
-
-				match res {
-					Ok(tx_status) => match tx_status.wait_for_finalized_success().await {
-						Ok(_) => info!("❤️‍ Solution submitted successfully"),
-						Err(e) => error!("❌ Error submitted solution: {:?}", e),
-					},
-					Err(e) => error!("❌ Error submitting solution: {:?}", e),
+				dashboard.record_solution_found();
+
+				match &sink {
+					SolutionSink::Chain => claim(&rpc_client, solution, &dashboard).await,
+					SolutionSink::Pool { client, .. } =>
+						submit_partial(client, &rpc_client, &block_hash, solution).await,
+					SolutionSink::Relay { network } =>
+						publish(network, &rpc_client, &farmer_id, solution, &dashboard).await,
 				}
 			}
 			
@@ -184,38 +207,98 @@ pub async fn run<H: HeaderWithCommitment + Sync>(
 	}
 }
 
-fn process_segments<F>(
-	segments: &[Option<Segment>],
-	block_number: u32,
-	db: &mut impl DasKv,
+/// Claims the reward of `solution` on-chain.
+async fn claim(rpc_client: &Client, solution: &Solution<H256, u32>, dashboard: &Dashboard) {
+	let solution_tx = melodot::tx().farmers_fortune().claim(
+		pre_cell_to_runtime(&solution.pre_cell),
+		cell_to_runtime(&solution.win_cell_left),
+		cell_to_runtime(&solution.win_cell_right),
+	);
+
+	let res = rpc_client
+		.api
+		.tx()
+		.sign_and_submit_then_watch_default(&solution_tx, &rpc_client.signer)
+		.await;
+
+	match res {
+		Ok(tx_status) => match tx_status.wait_for_finalized_success().await {
+			Ok(events) => {
+				info!("❤️‍ Solution submitted successfully");
+				let reward = events
+					.find_first::<melodot::farmers_fortune::events::RewardClaimed>()
+					.ok()
+					.flatten()
+					.map(|claimed| claimed.1)
+					.unwrap_or_default();
+				dashboard.record_solution_accepted(reward);
+			},
+			Err(e) => error!("❌ Error submitted solution: {:?}", e),
+		},
+		Err(e) => error!("❌ Error submitting solution: {:?}", e),
+	}
+}
+
+/// Publishes `solution` on the DAS network, claiming it on-chain if no relaying node can be
+/// reached.
+async fn publish(
+	network: &DasNetworkService,
+	rpc_client: &Client,
 	farmer_id: &FarmerId,
-	piece_position_fn: F,
-) where
-	F: Fn(&Position) -> PiecePosition,
-{
-	segments.chunks(EXTENDED_SEGMENTS_PER_BLOB).for_each(|chunk| {
-		let segment_vec = chunk.iter().filter_map(|seg| seg.clone()).collect::<Vec<_>>();
-		if let Some(first_segment) = segment_vec.first() {
-			let piece_position = piece_position_fn(&first_segment.position);
-			let piece = Piece::new(block_number, piece_position, &segment_vec);
-
-			if let Err(e) = piece.save(db, farmer_id) {
-				error!("❌ Error to save piece : {:?}", e);
-			}
-		}
-	});
+	solution: &Solution<H256, u32>,
+	dashboard: &Dashboard,
+) {
+	let compact = match solution.compact() {
+		Some(compact) => compact,
+		None => return,
+	};
+	let announcement = SolutionAnnouncement {
+		farmer: rpc_client.signer.public_key().to_account_id().0,
+		farmer_id: farmer_id.0.into(),
+		solution: compact.encode(),
+	};
+
+	match network.publish_solution(&announcement).await {
+		Ok(()) => info!("📡 Solution published to the relaying nodes"),
+		Err(e) => {
+			error!("❌ Failed to publish solution, claiming it instead: {:?}", e);
+			claim(rpc_client, solution, dashboard).await
+		},
+	}
+}
+
+/// Sends `solution`, found with the challenge of `block_hash`, to the pool server of `client`.
+async fn submit_partial(
+	client: &WsClient,
+	rpc_client: &Client,
+	block_hash: &H256,
+	solution: &Solution<H256, u32>,
+) {
+	let compact = match solution.compact() {
+		Some(compact) => compact,
+		None => return,
+	};
+	let account_id = rpc_client.signer.public_key().to_account_id();
+
+	match client.submit_partial(account_id, *block_hash, Bytes(compact.encode())).await {
+		Ok(result) if result.claimed =>
+			info!("🏊 Partial solution claimed by the pool, {} points", result.points),
+		Ok(result) => info!("🏊 Partial solution accepted, {} points", result.points),
+		Err(e) => error!("❌ Pool rejected partial solution: {:?}", e),
+	}
 }
 
 fn process_segment_data<F>(
 	segment: &Option<Segment>,
 	farmer_id: &FarmerId,
+	leading_zeros: u8,
 	position_fn: F,
 	pre_cells: &mut Vec<PreCell>,
 ) where
 	F: Fn(&Position) -> PiecePosition,
 {
 	if let Some(seg) = segment {
-		if Solution::<H256, u32>::check_pre_cell(seg, farmer_id, PRE_CELL_LEADING_ZEROS) {
+		if Solution::<H256, u32>::check_pre_cell(seg, farmer_id, leading_zeros) {
 			let piece_position = position_fn(&seg.position);
 			pre_cells.push(PreCell::new(piece_position, seg.clone()));
 		}
@@ -12,22 +12,38 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use anyhow::anyhow;
 use cli::parse_args;
+use dashboard::Dashboard;
+use event_handler::SolutionSink;
 use futures::lock::Mutex;
+use jsonrpsee::ws_client::WsClientBuilder;
 use log::{error, info};
 use melo_das_db::sqlite::SqliteDasDb;
+use melo_das_network::AccountId;
 use melo_das_primitives::KZG;
 use melo_daser::DasNetworkServiceWrapper;
-use meloxt::{ClientBuilder, MelodotHeader};
+use melodot_pool_server::PoolApiClient;
+use meloxt::{Client, ClientBuilder, MelodotHeader};
+use piece_fetcher::PieceFetcher;
+use plotter::{CompactionHorizon, Plotter};
+use prometheus_endpoint::Registry;
 use std::sync::Arc;
 use tokio::sync::mpsc;
 
+mod binding;
 mod cli;
+mod dashboard;
 mod event_handler;
 mod logger;
+mod piece_fetcher;
+mod plotter;
+mod serving;
 
 use event_handler::run as event_handler_run;
 
+const PLOTS_DB_FILE: &str = "plots.sqlite3";
+
 /// Runs the Melodot Farmer Client with the given configuration.
 ///
 /// # Arguments
@@ -42,16 +58,47 @@ pub async fn run(config: &cli::Config) -> anyhow::Result<()> {
 
 	info!("🚀 Melodot Farmer Client starting up");
 
-	let (network_service, network_worker) =
-		melo_das_network::default(Some(config.network_config.clone()), None)?;
-	let network_service_wrapper =
-		DasNetworkServiceWrapper::new(network_service.into(), KZG::default_embedded().into());
+	let registry = Registry::default();
+
+	let (network_service, network_worker) = melo_das_network::default_with_registry(
+		Some(config.network_config.clone()),
+		None,
+		registry.clone(),
+	)?;
+	let network_service_wrapper = DasNetworkServiceWrapper::new(
+		network_service.clone().into(),
+		KZG::default_embedded().into(),
+	);
 
 	let rpc_url = config.rpc_url.clone();
 
-	let database = Arc::new(Mutex::new(SqliteDasDb::default()));
+	std::fs::create_dir_all(&config.plots_dir)?;
+	let plots_path = config.plots_dir.join(PLOTS_DB_FILE);
+	let database = Arc::new(Mutex::new(SqliteDasDb::new(
+		plots_path.to_str().ok_or_else(|| anyhow!("Invalid plots path {:?}", plots_path))?,
+	)?));
+	info!("💾 Plots stored in {:?}", config.plots_dir);
+
+	let dashboard = Arc::new(Dashboard::new(registry, plots_path));
+	let rpc_addr = dashboard::run_rpc_server(dashboard.clone(), &config.rpc_listen_addr).await?;
+	info!("📊 Dashboard RPC listening on {}", rpc_addr);
+	dashboard::run_prometheus(&dashboard, config.prometheus_addr);
+	info!("📈 Prometheus metrics exposed on {}", config.prometheus_addr);
 
-	let rpc_client = match ClientBuilder::default().set_url(&rpc_url).build().await {
+	let signer = config.signer.keypair()?;
+	let account_id = signer.public_key().to_account_id();
+	if let Some(reward_address) = &config.reward_address {
+		if *reward_address != account_id {
+			return Err(anyhow!(
+				"Reward address {} does not match the signing account {}",
+				reward_address,
+				account_id
+			))
+		}
+	}
+	info!("💰 Rewards are claimed for {}", account_id);
+
+	let rpc_client = match ClientBuilder::new(&rpc_url, signer).build().await {
 		Ok(client) => client,
 		Err(e) => {
 			error!("❌ Failed to build RPC client: {:?}", e);
@@ -59,16 +106,81 @@ pub async fn run(config: &cli::Config) -> anyhow::Result<()> {
 		},
 	};
 
+	if let Some(new_account) = &config.rotate_to {
+		return binding::rotate_key(&rpc_client, new_account).await
+	}
+
+	let hashing = rpc_client.plot_hashing(None).await?;
+	if let Some(recorded) = melo_proof_of_space::plot_hashing(&mut *database.lock().await)? {
+		if recorded != hashing {
+			return Err(anyhow!(
+				"Plots were made with {} hashing but the chain requires {}, rebuild them with \
+				 `melo-plot-inspect rebuild --hashing {}`",
+				recorded.as_str(),
+				hashing.as_str(),
+				hashing.as_str()
+			))
+		}
+	}
+	info!("🧮 Plots are hashed with {}", hashing.as_str());
+
+	let farmer_id = binding::ensure_registered(&rpc_client, hashing).await?;
+
+	let sink = match &config.pool_url {
+		Some(pool_url) => {
+			let client = WsClientBuilder::default().build(pool_url).await?;
+			let info = client.info().await?;
+			binding::ensure_pool_member(&rpc_client, info.pool_id).await?;
+			info!("🏊 Solutions are sent to pool {} at {}", info.pool_id, pool_url);
+			SolutionSink::Pool { client, info }
+		},
+		None if config.relay_solutions => {
+			info!("📡 Solutions are published to the relaying nodes");
+			SolutionSink::Relay { network: network_service }
+		},
+		None => SolutionSink::Chain,
+	};
+
+	let network_worker = if config.claim_serving_rewards {
+		let (receipt_tx, receipt_rx) =
+			futures::channel::mpsc::channel(serving::MAX_RECEIPTS_PER_CLAIM);
+		let client = Client { api: rpc_client.api.clone(), signer: config.signer.keypair()? };
+		tokio::spawn(serving::run(client, receipt_rx));
+		info!("📤 Serving rewards are claimed for {}", account_id);
+		network_worker.with_serving_rewards(AccountId::new(account_id.0), receipt_tx)
+	} else {
+		network_worker
+	};
 	tokio::spawn(network_worker.run());
 
 	let (message_tx, _message_rx) = mpsc::channel(100);
 	let (error_tx, mut error_rx) = mpsc::channel(10);
+
+	// Both the fetcher and the plotter drop the blocks whose commitments are compacted.
+	let compaction = CompactionHorizon::default();
+	let (plot_queue, plotter) = Plotter::new(database.clone(), dashboard.clone());
+	tokio::spawn(plotter.with_compaction_horizon(compaction.clone()).run());
+
+	let piece_fetcher = PieceFetcher::new(
+		network_service_wrapper.clone(),
+		database.clone(),
+		plot_queue,
+		farmer_id,
+		hashing,
+		dashboard.clone(),
+	)
+	.with_compaction_horizon(compaction);
+	tokio::spawn(piece_fetcher.run(rpc_client.api.clone(), error_tx.clone()));
+
 	tokio::spawn(event_handler_run::<MelodotHeader>(
 		rpc_client,
 		message_tx,
 		error_tx,
 		network_service_wrapper,
 		database,
+		dashboard,
+		farmer_id,
+		sink,
 	));
 
 	while let Some(error) = error_rx.recv().await {
@@ -79,7 +191,13 @@ pub async fn run(config: &cli::Config) -> anyhow::Result<()> {
 }
 
 pub fn main() {
-	let config = parse_args();
+	let config = match parse_args() {
+		Ok(config) => config,
+		Err(e) => {
+			eprintln!("Invalid configuration: {:?}", e);
+			std::process::exit(1);
+		},
+	};
 
 	tokio::runtime::Builder::new_multi_thread()
 		.worker_threads(4)
@@ -0,0 +1,190 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Piece acquisition.
+//!
+//! The [`PieceFetcher`] follows the finalized blocks and queues, for each block with data, the rows
+//! and columns assigned to the local farmer for plotting, see [`assignment`]. The blocks whose
+//! commitments are compacted on chain are rejected.
+
+use crate::{
+	dashboard::Dashboard,
+	plotter::{CompactionHorizon, PlotQueue},
+};
+use anyhow::{anyhow, Result};
+use futures::lock::Mutex;
+use log::{error, info, warn};
+use melo_core_primitives::{config::EXTENDED_SEGMENTS_PER_BLOB, traits::HeaderWithCommitment};
+use melo_das_db::sqlite::SqliteDasDb;
+use melo_das_primitives::Segment;
+use melo_daser::{DasNetworkServiceWrapper, FetchData, SamplingClient};
+use melo_proof_of_space::{assignment, FarmerId, Piece, PiecePosition, PlotHashing, PlotUpdate};
+use meloxt::{MeloConfig, MelodotHeader as Header};
+use std::sync::{atomic::Ordering, Arc};
+use subxt::{ext::codec::Decode, utils::H256, OnlineClient};
+use tokio::sync::mpsc::Sender;
+use tokio_stream::StreamExt;
+
+/// Fetches the pieces assigned to the local farmer and queues them for plotting.
+pub struct PieceFetcher {
+	client: SamplingClient<Header, SqliteDasDb, DasNetworkServiceWrapper>,
+	queue: PlotQueue,
+	farmer_id: FarmerId,
+	hashing: PlotHashing,
+	dashboard: Arc<Dashboard>,
+	compaction: CompactionHorizon,
+}
+
+/// Returns the first block whose commitments are not compacted, read from the state of the block
+/// `at`.
+async fn next_compaction(api: &OnlineClient<MeloConfig>, at: H256) -> Result<u32> {
+	let address =
+		subxt::dynamic::storage("MeloStore", "NextCompaction", Vec::<subxt::dynamic::Value>::new());
+	let key = api.storage().address_bytes(&address)?;
+	match api.rpc().storage(&key, Some(at)).await? {
+		Some(data) => Ok(u32::decode(&mut &data.0[..])?),
+		None => Ok(0),
+	}
+}
+
+impl PieceFetcher {
+	/// Creates a new fetcher queueing the pieces of `farmer_id`, hashed with `hashing`, to `queue`.
+	pub fn new(
+		network: DasNetworkServiceWrapper,
+		database: Arc<Mutex<SqliteDasDb>>,
+		queue: PlotQueue,
+		farmer_id: FarmerId,
+		hashing: PlotHashing,
+		dashboard: Arc<Dashboard>,
+	) -> Self {
+		let client = SamplingClient::new(network, database);
+		Self { client, queue, farmer_id, hashing, dashboard, compaction: Default::default() }
+	}
+
+	/// Keeps `compaction` up to date with the finalized blocks, and rejects the blocks before it.
+	pub fn with_compaction_horizon(mut self, compaction: CompactionHorizon) -> Self {
+		self.compaction = compaction;
+		self
+	}
+
+	/// Fetches, verifies and queues the pieces of `header` assigned to the farmer.
+	///
+	/// Segments are verified against the commitments of the header while fetched, unverifiable
+	/// segments are dropped. The blocks whose commitments are compacted on chain are rejected,
+	/// their pieces could not be claimed.
+	///
+	/// # Returns
+	///
+	/// Returns the number of pieces queued.
+	pub async fn acquire(&self, header: &Header) -> Result<u32> {
+		if header.number < self.compaction.load(Ordering::Relaxed) {
+			return Err(anyhow!("The commitments of block #{} are compacted", header.number))
+		}
+
+		let rows_count = header.col_num().unwrap_or_default() as usize * 2;
+		if rows_count == 0 {
+			return Ok(0)
+		}
+
+		let block_hash = header.hash();
+		let row_inds = assignment::assigned_rows(&self.farmer_id, &block_hash, rows_count as u32);
+		let col_inds = assignment::assigned_columns(&self.farmer_id, &block_hash);
+
+		let ((rows, _), (cols, _, _)) = tokio::try_join!(
+			self.client.fetch_rows(header, &row_inds),
+			self.client.fetch_cols(header, &col_inds)
+		)?;
+
+		let mut update = PlotUpdate::new(self.farmer_id.clone()).with_hashing(self.hashing);
+
+		for (position, segments) in row_inds
+			.iter()
+			.map(|x| PiecePosition::Row(*x))
+			.zip(rows.chunks(EXTENDED_SEGMENTS_PER_BLOB))
+			.chain(col_inds.iter().map(|y| PiecePosition::Column(*y)).zip(cols.chunks(rows_count)))
+		{
+			let segments = segments.iter().flatten().cloned().collect::<Vec<Segment>>();
+			if segments.is_empty() {
+				error!("❌ No segment available for {:?} of block #{}", position, header.number);
+				continue
+			}
+
+			update.push(Piece::new(header.number, position, &segments));
+		}
+
+		if update.is_empty() {
+			return Ok(0)
+		}
+
+		let queued = update.pieces().len() as u32;
+		self.queue
+			.send((header.number, update))
+			.await
+			.map_err(|_| anyhow!("Plot queue closed"))?;
+		self.dashboard.record_plot_queued();
+
+		Ok(queued)
+	}
+
+	/// Follows the finalized blocks and acquires their pieces.
+	///
+	/// Sends an error to `error_sender` once the subscription ends.
+	pub async fn run(self, api: OnlineClient<MeloConfig>, error_sender: Sender<anyhow::Error>) {
+		let mut finalized_sub = match api.blocks().subscribe_finalized().await {
+			Ok(subscription) => {
+				info!("🌐 Subscribed to finalized blocks for piece acquisition");
+				subscription
+			},
+			Err(e) => {
+				error!("⚠️ Failed to subscribe to finalized blocks: {:?}", e);
+				return
+			},
+		};
+
+		while let Some(message) = finalized_sub.next().await {
+			match message {
+				Ok(block) => {
+					match next_compaction(&api, block.hash()).await {
+						Ok(next) => self.compaction.store(next, Ordering::Relaxed),
+						Err(e) => warn!(
+							"⚠️ Failed to read the compacted blocks at #{}: {:?}",
+							block.number(),
+							e
+						),
+					}
+
+					let header = block.header();
+					match self.acquire(header).await {
+						Ok(0) => (),
+						Ok(queued) => info!(
+							"📥 Queued {} pieces of finalized block #{} for plotting",
+							queued, header.number
+						),
+						Err(e) => error!(
+							"❌ Failed to acquire pieces of block #{}: {:?}",
+							header.number, e
+						),
+					}
+				},
+				Err(e) => error!("❌ Error receiving finalized block: {:?}", e),
+			}
+		}
+
+		if let Err(error) =
+			error_sender.send(anyhow!("Finalized blocks subscription disconnected")).await
+		{
+			error!("🚫 Cannot send error to error channel: {error}");
+		}
+	}
+}
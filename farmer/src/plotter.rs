@@ -0,0 +1,100 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Plotting.
+//!
+//! The [`Plotter`] applies the plot updates queued by the piece fetcher one block at a time, so
+//! that acquiring the pieces of new blocks does not wait for the plot to be indexed.
+//!
+//! The blocks whose commitments are compacted on chain by the time they are plotted are skipped,
+//! their pieces can no longer be verified nor claimed, see [`CompactionHorizon`].
+
+use crate::dashboard::Dashboard;
+use futures::lock::Mutex;
+use log::{debug, error, info, warn};
+use melo_das_db::sqlite::SqliteDasDb;
+use melo_proof_of_space::PlotUpdate;
+use std::sync::{
+	atomic::{AtomicU32, Ordering},
+	Arc,
+};
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+
+/// Number of blocks that can wait to be plotted before the piece fetcher is slowed down.
+pub const PLOT_QUEUE_SIZE: usize = 64;
+
+/// The sending side of the plot queue, carrying the updates of each block.
+pub type PlotQueue = Sender<(u32, PlotUpdate<u32>)>;
+
+/// The first block whose commitments are not compacted by `pallet-melo-store`, its
+/// `NextCompaction`, as of the last finalized block.
+pub type CompactionHorizon = Arc<AtomicU32>;
+
+/// Applies the queued plot updates to the plots database.
+pub struct Plotter {
+	receiver: Receiver<(u32, PlotUpdate<u32>)>,
+	database: Arc<Mutex<SqliteDasDb>>,
+	dashboard: Arc<Dashboard>,
+	compaction: CompactionHorizon,
+}
+
+impl Plotter {
+	/// Creates a new plotter and the queue feeding it.
+	pub fn new(database: Arc<Mutex<SqliteDasDb>>, dashboard: Arc<Dashboard>) -> (PlotQueue, Self) {
+		let (sender, receiver) = channel(PLOT_QUEUE_SIZE);
+		(sender, Self { receiver, database, dashboard, compaction: Default::default() })
+	}
+
+	/// Skips the blocks before `compaction`, whose commitments are compacted.
+	pub fn with_compaction_horizon(mut self, compaction: CompactionHorizon) -> Self {
+		self.compaction = compaction;
+		self
+	}
+
+	/// Applies the queued updates until the queue is closed.
+	pub async fn run(mut self) {
+		while let Some((block_number, update)) = self.receiver.recv().await {
+			if block_number < self.compaction.load(Ordering::Relaxed) {
+				warn!("⏭️ Skipping block #{}, its commitments are compacted on chain", block_number);
+				self.dashboard.record_plotted(block_number, 0);
+				continue
+			}
+
+			let mut database = self.database.lock().await;
+			let result = tokio::task::block_in_place(|| {
+				update.apply(&mut *database, |progress| {
+					debug!(
+						"🌱 Plotting block #{}: {}/{} pieces, {} pairs",
+						block_number, progress.pieces_done, progress.pieces_total, progress.pairs
+					)
+				})
+			});
+			drop(database);
+
+			match result {
+				Ok(progress) => {
+					info!(
+						"💾 Plotted {} pieces of block #{} with {} new pairs",
+						progress.pieces_total, block_number, progress.pairs
+					);
+					self.dashboard.record_plotted(block_number, progress.pieces_total);
+				},
+				Err(e) => {
+					error!("❌ Failed to plot block #{}: {:?}", block_number, e);
+					self.dashboard.record_plotted(block_number, 0);
+				},
+			}
+		}
+	}
+}
@@ -0,0 +1,100 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Serving rewards.
+//!
+//! The DAS network worker hands over the [`ServingReceipt`]s signed by the peers the farmer
+//! served segments to. [`run`] collects them and claims them with `BandwidthIncentive.claim`,
+//! once [`MAX_RECEIPTS_PER_CLAIM`] receipts are collected or every [`CLAIM_INTERVAL`].
+
+use anyhow::Result;
+use futures::{channel::mpsc, StreamExt};
+use log::{info, warn};
+use melo_core_primitives::serving::ServingReceipt;
+use melo_das_network::AccountId;
+use meloxt::Client;
+use std::time::Duration;
+use subxt::dynamic::Value;
+
+/// The maximum number of receipts claimed at once, as the runtime allows.
+pub const MAX_RECEIPTS_PER_CLAIM: usize = 64;
+
+/// How often the collected receipts are claimed.
+pub const CLAIM_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Returns the dynamic value of `receipt`.
+fn receipt_value(receipt: &ServingReceipt<AccountId>) -> Value {
+	let server: &[u8; 32] = receipt.server.as_ref();
+	Value::named_composite([
+		("server", Value::unnamed_composite([Value::from_bytes(server)])),
+		("requester", Value::unnamed_composite([Value::from_bytes(receipt.requester.0)])),
+		("period", Value::u128(receipt.period.into())),
+		("segments", Value::u128(receipt.segments.into())),
+		("bytes", Value::u128(receipt.bytes.into())),
+		("signature", Value::unnamed_composite([Value::from_bytes(receipt.signature.0)])),
+	])
+}
+
+/// Claims `receipts` with the signing account of `client`.
+async fn claim(client: &Client, receipts: &mut Vec<ServingReceipt<AccountId>>) -> Result<()> {
+	// The receipts of a peer are claimed in the order of their periods.
+	receipts.sort_by_key(|receipt| receipt.period);
+	let segments: u64 = receipts.iter().map(|receipt| receipt.segments as u64).sum();
+	let claim_tx = subxt::dynamic::tx(
+		"BandwidthIncentive",
+		"claim",
+		vec![Value::unnamed_composite(receipts.iter().map(receipt_value))],
+	);
+
+	client
+		.api
+		.tx()
+		.sign_and_submit_then_watch_default(&claim_tx, &client.signer)
+		.await?
+		.wait_for_finalized_success()
+		.await?;
+
+	info!("📤 Claimed the serving rewards of {} receipts, {} segments", receipts.len(), segments);
+	Ok(())
+}
+
+/// Claims the serving receipts received from `receipts` until the channel is closed.
+///
+/// A claim failing drops its receipts, a single invalid receipt failing the whole claim.
+pub async fn run(client: Client, mut receipts: mpsc::Receiver<ServingReceipt<AccountId>>) {
+	let mut pending = Vec::new();
+	let mut interval = tokio::time::interval(CLAIM_INTERVAL);
+	loop {
+		let full = tokio::select! {
+			receipt = receipts.next() => match receipt {
+				Some(receipt) => {
+					pending.push(receipt);
+					pending.len() >= MAX_RECEIPTS_PER_CLAIM
+				},
+				None => break,
+			},
+			_ = interval.tick() => true,
+		};
+		if full && !pending.is_empty() {
+			if let Err(e) = claim(&client, &mut pending).await {
+				warn!(
+					"⚠️ Failed to claim the serving rewards of {} receipts: {:?}",
+					pending.len(),
+					e
+				);
+			}
+			pending.clear();
+		}
+	}
+}
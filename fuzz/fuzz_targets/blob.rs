@@ -0,0 +1,30 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parses blobs from arbitrary bytes, exactly or padded to the length of a blob.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use melo_das_primitives::{config::DATA_BYTES_PER_BLOB, Blob};
+
+fuzz_target!(|data: &[u8]| {
+	if let Ok(blob) = Blob::try_from_bytes(data, DATA_BYTES_PER_BLOB) {
+		assert_eq!(blob.to_bytes().len(), DATA_BYTES_PER_BLOB);
+	}
+
+	if let Ok(blob) = Blob::try_from_bytes_pad(data, DATA_BYTES_PER_BLOB) {
+		assert!(data.len() <= DATA_BYTES_PER_BLOB);
+		assert_eq!(Blob::try_from_bytes_pad(&blob.to_bytes(), DATA_BYTES_PER_BLOB), Ok(blob));
+	}
+});
@@ -0,0 +1,32 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parses the records of the DHT: the local key a record is stored under and its value, compressed
+//! or not, as a segment.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use melo_core_primitives::decode_segment_value;
+use melo_das_network::{LegacyKeys, SegmentKey};
+
+fuzz_target!(|data: &[u8]| {
+	if let Some(key) = SegmentKey::from_local_key(data) {
+		assert_eq!(key.local_key(), data);
+		assert_eq!(LegacyKeys::Accept.dht_keys(data).len(), 2);
+	}
+
+	if let Some(segment) = decode_segment_value(data) {
+		let _ = segment.size();
+	}
+});
@@ -0,0 +1,50 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Decodes a segment received from a peer, checks it and verifies its proof.
+//!
+//! The segment is verified against the commitment of a known blob, as the light clients and the
+//! nodes do with the commitments of the header.
+#![no_main]
+
+use codec::Decode;
+use libfuzzer_sys::fuzz_target;
+use melo_core_primitives::config::SEGMENTS_PER_BLOB;
+use melo_das_primitives::{config::DATA_BYTES_PER_BLOB, Blob, KZGCommitment, Segment, KZG};
+use std::sync::OnceLock;
+
+fn settings() -> &'static (KZG, KZGCommitment) {
+	static SETTINGS: OnceLock<(KZG, KZGCommitment)> = OnceLock::new();
+	SETTINGS.get_or_init(|| {
+		let kzg = KZG::default_embedded();
+		let blob = Blob::try_from_bytes_pad(&[7u8; 1024], DATA_BYTES_PER_BLOB).expect("valid blob");
+		let commitment = blob.commit(&kzg).expect("valid commitment");
+		(kzg, commitment)
+	})
+}
+
+fuzz_target!(|data: &[u8]| {
+	let segment = match Segment::decode(&mut &data[..]) {
+		Ok(segment) => segment,
+		Err(_) => return,
+	};
+	let checked = match segment.checked() {
+		Ok(checked) => checked,
+		Err(_) => return,
+	};
+	assert_eq!(checked, segment);
+
+	let (kzg, commitment) = settings();
+	let _ = checked.verify(kzg, commitment, SEGMENTS_PER_BLOB);
+});
@@ -0,0 +1,39 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Decodes a sidecar and the payload following it, as submitted to the node or exchanged with the
+//! archive nodes.
+#![no_main]
+
+use codec::{Decode, Encode};
+use libfuzzer_sys::fuzz_target;
+use melo_core_primitives::Sidecar;
+
+fuzz_target!(|data: &[u8]| {
+	let mut input = data;
+	let sidecar = match Sidecar::decode(&mut input) {
+		Ok(sidecar) => sidecar,
+		Err(_) => return,
+	};
+	assert_eq!(sidecar.encode(), data[..data.len() - input.len()]);
+
+	let metadata = &sidecar.metadata;
+	let _ = sidecar.id();
+	if metadata.check() {
+		let _ = metadata.blob_count();
+	}
+	if let Ok(payload) = metadata.decode_payload(input) {
+		assert_eq!(payload.len(), metadata.bytes_len as usize);
+	}
+});
@@ -13,7 +13,9 @@
 // limitations under the License.
 
 use clap::{ArgAction, Parser};
-use melo_das_network::DasNetworkConfig;
+use melo_core_primitives::reliability::Permill;
+use melo_das_network::{DasNetworkConfig, NodeRole};
+use melo_daser::{parse_app_weight, SamplingWeights, DEFAULT_UNIFORM_SHARE};
 use std::net::SocketAddr;
 
 pub const DEFAULT_RPC_LISTEN_ADDR: &str = "127.0.0.1:4177";
@@ -46,6 +48,18 @@ pub struct Cli {
 	/// Activate test configuration
 	#[clap(long, action = ArgAction::SetTrue)]
 	test_mode: bool,
+
+	/// Sample the rows of an application more often, given as `APP_ID=WEIGHT`
+	#[clap(long, value_parser = parse_app_weight)]
+	app_weight: Vec<(u32, u32)>,
+
+	/// Weight added to a row for each of its samples that failed in the last 16 blocks
+	#[clap(long, default_value_t = 0)]
+	failure_weight: u32,
+
+	/// Share of the samples, in parts per million, drawn uniformly when sampling is weighted
+	#[clap(long, default_value_t = DEFAULT_UNIFORM_SHARE.deconstruct())]
+	uniform_share: u32,
 }
 
 /// Application configuration
@@ -53,6 +67,7 @@ pub struct Config {
 	pub rpc_listen_addr: SocketAddr,
 	pub rpc_url: String,
 	pub network_config: DasNetworkConfig,
+	pub sampling_weights: SamplingWeights,
 }
 
 impl Config {
@@ -61,7 +76,8 @@ impl Config {
 			DEFAULT_RPC_LISTEN_ADDR.parse().expect("Invalid DEFAULT SocketAddr")
 		});
 
-		let mut das_network_config = DasNetworkConfig::default();
+		let mut das_network_config =
+			DasNetworkConfig { role: NodeRole::Light, ..Default::default() };
 		let mut rpc_url = DEFAULT_RPC_URL.to_string();
 
 		if cli.dev_mode {
@@ -77,11 +93,22 @@ impl Config {
 
         print!("poot: {}", das_network_config.listen_port);
 
-		Config { rpc_listen_addr, rpc_url, network_config: das_network_config }
+		let sampling_weights = SamplingWeights {
+			uniform_share: Permill::from_parts(cli.uniform_share),
+			app_weights: cli.app_weight.into_iter().collect(),
+			failure_weight: cli.failure_weight,
+		};
+
+		Config { rpc_listen_addr, rpc_url, network_config: das_network_config, sampling_weights }
 	}
 }
 
 pub fn parse_args() -> Config {
 	let cli = Cli::parse();
-	Config::from_cli_args(cli)
+	let config = Config::from_cli_args(cli);
+	if let Err(e) = config.sampling_weights.validate() {
+		eprintln!("Invalid sampling weights: {}", e);
+		std::process::exit(1);
+	}
+	config
 }
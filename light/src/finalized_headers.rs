@@ -25,7 +25,7 @@ use tokio_stream::StreamExt;
 
 use melo_core_primitives::traits::HeaderWithCommitment;
 use melo_das_db::sqlite::SqliteDasDb;
-use melo_daser::{DasNetworkServiceWrapper, Sampling, SamplingClient};
+use melo_daser::{DasNetworkServiceWrapper, Sampling, SamplingClient, SamplingWeights};
 
 /// Subscribes to finalized block headers and processes them by sending them to a message channel and sampling them.
 ///
@@ -36,15 +36,17 @@ use melo_daser::{DasNetworkServiceWrapper, Sampling, SamplingClient};
 /// * `error_sender` - A Sender that is used to send errors to an error channel.
 /// * `network` - A DasNetworkServiceWrapper that is used to sample the received block headers.
 /// * `database` - An Arc<Mutex<SqliteDasDb>> that is used to store the received block headers.
+/// * `weights` - The weights the samples of the blocks are drawn with.
 pub async fn finalized_headers<H: HeaderWithCommitment + Sync>(
 	rpc_client: OnlineClient<MeloConfig>,
 	message_tx: Sender<(Header, Instant)>,
 	error_sender: Sender<anyhow::Error>,
 	network: DasNetworkServiceWrapper,
 	database: Arc<Mutex<SqliteDasDb>>,
+	weights: SamplingWeights,
 ) {
 	let client: SamplingClient<H, SqliteDasDb, DasNetworkServiceWrapper> =
-		SamplingClient::new(network, database);
+		SamplingClient::new(network, database).with_weights(weights);
 	let mut new_heads_sub = match rpc_client.blocks().subscribe_finalized().await {
 		Ok(subscription) => {
 			info!("🌐 Subscribed to finalized block headers");
@@ -74,6 +74,7 @@ pub async fn run(config: &cli::Config) -> anyhow::Result<()> {
 		error_tx,
 		network_service_wrapper,
 		database,
+		config.sampling_weights.clone(),
 	));
 
 	while let Some(error) = error_rx.recv().await {
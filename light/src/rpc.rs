@@ -18,7 +18,7 @@ use jsonrpsee::{server::ServerBuilder, RpcModule};
 
 use futures::lock::Mutex;
 use melo_das_db::traits::DasKv;
-use melo_daser::DasNetworkOperations;
+use melo_daser::{BackfillProgress, DasNetworkOperations};
 use meloxt::H256;
 use std::{net::SocketAddr, sync::Arc};
 
@@ -38,7 +38,10 @@ where
 	let mut module = RpcModule::new(());
 	let FullDeps { db, das_network } = deps;
 
-	module.merge(Confidence::<DB, H256, DN>::new(&db.clone(), das_network).into_rpc())?;
+	// Light clients do not backfill, their backfill progress is never started.
+	let backfill = Arc::new(BackfillProgress::default());
+	module
+		.merge(Confidence::<DB, H256, DN>::new(&db.clone(), das_network, &backfill).into_rpc())?;
 
 	// Extend this RPC with a custom API by using the following syntax.
 	// `YourRpcStruct` should have a reference to a client, which is needed
@@ -0,0 +1,46 @@
+//! Attesters of the withheld blocks.
+//!
+//! The withholding attestations added through the RPC are only kept if they are signed by one of
+//! the attesters of `pallet-withholding`, which are read again through the `WithholdingApi` at
+//! each finalized block.
+use crate::service::FullClient;
+use futures::StreamExt;
+use log::{info, warn};
+use melo_core_primitives::traits::WithholdingApi;
+use melo_daser::WithholdingCollector;
+use melodot_runtime::Hash;
+use sc_client_api::BlockchainEvents;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::sr25519;
+use std::sync::Arc;
+
+/// Returns the attesters set on chain at the block `hash`, with their maximum number.
+fn attesters(client: &FullClient, hash: Hash) -> Option<(Vec<sr25519::Public>, u32)> {
+	let api = client.runtime_api();
+	let attesters = api
+		.attesters(hash)
+		.and_then(|attesters| Ok((attesters, api.max_attesters(hash)?)));
+	match attesters {
+		Ok(attesters) => Some(attesters),
+		Err(e) => {
+			warn!("⚠️ Failed to read the attesters of block {:?}: {:?}", hash, e);
+			None
+		},
+	}
+}
+
+/// Keeps the attesters of `withholding` those of the last block finalized by `client`.
+pub async fn track_attesters(client: Arc<FullClient>, withholding: Arc<WithholdingCollector>) {
+	if let Some((attesters, max_attesters)) = attesters(&client, client.info().finalized_hash) {
+		info!("🧾 Accepting the withholding attestations of {} attesters", attesters.len());
+		withholding.set_attesters(attesters, max_attesters);
+	}
+
+	let mut finality_notifications = client.finality_notification_stream();
+	while let Some(notification) = finality_notifications.next().await {
+		if let Some((attesters, max_attesters)) = attesters(&client, notification.hash) {
+			withholding.set_attesters(attesters, max_attesters);
+		}
+	}
+}
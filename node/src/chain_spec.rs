@@ -146,6 +146,8 @@ pub fn testnet_genesis(
 			min_join_bond: DOLLARS,
 			..Default::default()
 		},
+		das_config: Default::default(),
+		withholding: Default::default(),
 	}
 }
 
@@ -1,4 +1,18 @@
+use melo_core_primitives::{
+	config::{BLOCK_AVAILABILITY_THRESHOLD, SAMPLES_PER_BLOCK},
+	reliability::{Permill, APP_AVAILABILITY_THRESHOLD_PERMILL, BLOCK_FAILURE_PROBABILITY},
+};
+use melo_das_network::DasNetworkConfig;
+use melo_das_rpc::{CommitteeMirror, DEFAULT_COMMITTEE_TIMEOUT};
+use melo_daser::{
+	parse_app_weight, SampleQueueConfig, SamplingParams, SamplingWeights, DEFAULT_COMMITTEE_QUOTA,
+	DEFAULT_PREPUBLISH_TIMEOUT, DEFAULT_SAMPLE_CONCURRENCY, DEFAULT_SAMPLE_QUEUE_CAPACITY,
+	DEFAULT_SAMPLE_TIMEOUT, DEFAULT_UNIFORM_SHARE,
+};
 use sc_cli::RunCmd;
+use sp_core::{crypto::Ss58Codec, sr25519};
+use sp_keystore::KeystorePtr;
+use std::{net::SocketAddr, path::PathBuf, time::Duration};
 
 #[derive(Debug, clap::Parser)]
 pub struct Cli {
@@ -7,6 +21,296 @@ pub struct Cli {
 
 	#[clap(flatten)]
 	pub run: RunCmd,
+
+	#[clap(flatten)]
+	pub das: DasParams,
+
+	/// How the node seals its blocks. `instant` and `manual` only suit development chains with a
+	/// single authority.
+	#[arg(long, value_enum, default_value_t = Sealing::Babe)]
+	pub sealing: Sealing,
+}
+
+/// How the node seals its blocks.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+pub enum Sealing {
+	/// Author a block at each BABE slot the node wins.
+	Babe,
+	/// Seal and finalize a block as soon as a transaction enters the pool.
+	Instant,
+	/// Seal blocks only when the `engine_createBlock` RPC is called.
+	Manual,
+}
+
+/// Parameters of the DAS services of the node.
+#[derive(Debug, Clone, clap::Args)]
+pub struct DasParams {
+	/// Run the node as a DAS archive node, keeping every segment in its database and serving the
+	/// segments of old blocks to the network.
+	#[arg(long)]
+	pub archive_das: bool,
+
+	/// Once synced, fetch the data of the given number of last finalized blocks from the DAS
+	/// network, so that the node can serve it right away.
+	#[arg(long, value_name = "BLOCKS")]
+	pub das_backfill: Option<u32>,
+
+	/// Serve the gRPC gateway of the DAS services, submitting and retrieving blobs, on the given
+	/// address.
+	#[arg(long, value_name = "ADDR")]
+	pub das_grpc: Option<SocketAddr>,
+
+	/// With `--archive-das`, move the archived segments older than `--das-cold-after-days` to
+	/// this bucket of an S3-compatible object storage, fetching them back when requested. The
+	/// credentials are read from `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY`.
+	#[arg(long, value_name = "BUCKET", requires = "archive_das")]
+	pub das_cold_bucket: Option<String>,
+
+	/// The region of the bucket of `--das-cold-bucket`.
+	#[arg(long, value_name = "REGION", default_value = "us-east-1")]
+	pub das_cold_region: String,
+
+	/// The endpoint of the S3-compatible service of `--das-cold-bucket`, AWS if not set.
+	#[arg(long, value_name = "URL")]
+	pub das_cold_endpoint: Option<String>,
+
+	/// The age in days after which archived segments are moved to `--das-cold-bucket`.
+	#[arg(long, value_name = "DAYS", default_value_t = 7)]
+	pub das_cold_after_days: u64,
+
+	/// Bound the size of the sidecars and segments the node keeps locally, evicting the least
+	/// recently used ones beyond it. Unbounded if not set.
+	#[arg(long, value_name = "MiB", conflicts_with = "archive_das")]
+	pub das_cache_size: Option<u64>,
+
+	/// Let the sidecars submitted through the node be evicted by `--das-cache-size` like any other
+	/// data. They are kept otherwise, so that the node can republish them.
+	#[arg(long)]
+	pub das_cache_evict_authored: bool,
+
+	/// Relay the solutions the farmers publish on the DAS network, submitting the valid ones to
+	/// the transaction pool and gossiping them to the other relaying nodes.
+	#[arg(long)]
+	pub relay_solutions: bool,
+
+	/// With `--relay-solutions`, the minimum quality of the solutions relayed.
+	#[arg(long, value_name = "QUALITY", default_value_t = 0)]
+	pub solution_min_quality: u32,
+
+	/// With `--relay-solutions`, the maximum number of solutions accepted from a peer every six
+	/// seconds.
+	#[arg(long, value_name = "COUNT", default_value_t = 32)]
+	pub solution_peer_quota: u32,
+
+	/// The number of segments sampled in each block.
+	#[arg(long, value_name = "COUNT", default_value_t = SAMPLES_PER_BLOCK as u32)]
+	pub das_samples_per_block: u32,
+
+	/// The number of consecutive samples of a block that have to be fetched for the block to be
+	/// available.
+	#[arg(long, value_name = "COUNT", default_value_t = BLOCK_AVAILABILITY_THRESHOLD)]
+	pub das_block_threshold: u32,
+
+	/// The share of the samples of an application that have to be fetched for its data to be
+	/// available, in parts per million.
+	#[arg(
+		long,
+		value_name = "PPM",
+		default_value_t = APP_AVAILABILITY_THRESHOLD_PERMILL.deconstruct()
+	)]
+	pub das_app_threshold: u32,
+
+	/// The probability, in parts per million, of a sample to be fetched although the data is
+	/// unavailable, the base of the confidence of a block.
+	#[arg(long, value_name = "PPM", default_value_t = BLOCK_FAILURE_PROBABILITY.deconstruct())]
+	pub das_base_factor: u32,
+
+	/// The time after which the samples of a block not fetched yet count as failed.
+	#[arg(long, value_name = "SECS", default_value_t = DEFAULT_SAMPLE_TIMEOUT.as_secs())]
+	pub das_sample_timeout: u64,
+
+	/// A TOML file overriding the sampling parameters above, with the keys `samples_per_block`,
+	/// `block_threshold`, `app_threshold`, `base_factor` and `timeout_secs`. The file is read
+	/// again when the node receives SIGHUP.
+	#[arg(long, value_name = "PATH")]
+	pub das_sampling_config: Option<PathBuf>,
+
+	/// Sample the rows of an application more often, given as `APP_ID=WEIGHT`, the other rows
+	/// weighing 1. May be repeated.
+	#[arg(long, value_name = "APP_ID=WEIGHT", value_parser = parse_app_weight)]
+	pub das_app_weight: Vec<(u32, u32)>,
+
+	/// The weight added to a row for each of its samples that failed in the last 16 blocks, so
+	/// that failing rows are sampled more often.
+	#[arg(long, value_name = "WEIGHT", default_value_t = 0)]
+	pub das_failure_weight: u32,
+
+	/// With `--das-app-weight` or `--das-failure-weight`, the share of the samples of a block, in
+	/// parts per million, still drawn uniformly from the whole block.
+	#[arg(long, value_name = "PPM", default_value_t = DEFAULT_UNIFORM_SHARE.deconstruct())]
+	pub das_uniform_share: u32,
+
+	/// The number of samplings of the data of the transactions imported to the pool waiting to
+	/// run. Past it, the samplings of the transactions of lowest priority are dropped.
+	#[arg(long, value_name = "COUNT", default_value_t = DEFAULT_SAMPLE_QUEUE_CAPACITY)]
+	pub das_sample_queue: usize,
+
+	/// The number of samplings of the data of the transactions imported to the pool running at
+	/// once.
+	#[arg(long, value_name = "COUNT", default_value_t = DEFAULT_SAMPLE_CONCURRENCY)]
+	pub das_sample_concurrency: usize,
+
+	/// The time an authoring node waits for the sidecars of the blob transactions of a block to
+	/// be published, or fetched if it does not hold them, before leaving the transactions out of
+	/// the block. `0` disables the publication before sealing.
+	#[arg(long, value_name = "MILLIS", default_value_t = DEFAULT_PREPUBLISH_TIMEOUT.as_millis() as u64)]
+	pub das_prepublish_timeout: u64,
+
+	/// The number of threads computing and verifying the KZG commitments and proofs of the node,
+	/// and extending the rows of the data it publishes, half of the available cores if not set.
+	#[arg(long, value_name = "COUNT")]
+	pub das_kzg_workers: Option<usize>,
+
+	/// Keep running in verify-only mode, without authoring blocks, if the embedded trusted setup
+	/// differs from the one registered on chain, instead of refusing to start.
+	#[arg(long)]
+	pub das_allow_setup_mismatch: bool,
+
+	/// Only put and fetch the segments under the current layout of the DHT keys, ending the
+	/// transition from the legacy keys.
+	#[arg(long)]
+	pub das_reject_legacy_keys: bool,
+
+	/// The number of distinct peers that have to acknowledge storing a sidecar of a block authored
+	/// by the node before it stops republishing it.
+	#[arg(long, value_name = "COUNT", default_value_t = 3)]
+	pub das_outbox_acks: u32,
+
+	/// The TCP port of the DAS network, to run several nodes on the same host.
+	#[arg(long, value_name = "PORT", default_value_t = 4417)]
+	pub das_listen_port: u16,
+
+	/// Index the blobs, confidences, farmer claims and app statistics of the chain for explorers
+	/// in the SQLite database at the given path.
+	#[arg(long, value_name = "PATH")]
+	pub das_indexer: Option<PathBuf>,
+
+	/// Mirror the sidecars submitted through the node to the data availability committee member
+	/// at the given HTTP JSON-RPC endpoint, alongside their publication to the DHT, reporting the
+	/// storage receipts of the members in the status of the submissions. The requests are signed
+	/// with the `dacm` key of the keystore. May be repeated.
+	#[arg(long, value_name = "URL")]
+	pub das_committee: Vec<String>,
+
+	/// The time a member of `--das-committee` has to acknowledge a sidecar.
+	#[arg(long, value_name = "SECS", default_value_t = DEFAULT_COMMITTEE_TIMEOUT.as_secs())]
+	pub das_committee_timeout: u64,
+
+	/// Serve as a member of a data availability committee, storing the sidecars mirrored to the
+	/// node through `dac_store` and signing storage receipts with the `dacr` key of the keystore.
+	#[arg(long, requires = "das_committee_node")]
+	pub das_committee_member: bool,
+
+	/// The SS58 `dacm` key of a node allowed to mirror sidecars to the node as a member of a data
+	/// availability committee. May be repeated.
+	#[arg(long, value_name = "KEY")]
+	pub das_committee_node: Vec<String>,
+
+	/// The MiB of sidecars the node stores as a member of a data availability committee for each
+	/// node mirroring them, until they are pruned.
+	#[arg(long, value_name = "MIB", default_value_t = DEFAULT_COMMITTEE_QUOTA >> 20)]
+	pub das_committee_quota: u64,
+}
+
+impl DasParams {
+	/// Returns the configuration of the DAS network of the node.
+	pub fn das_network_config(&self) -> DasNetworkConfig {
+		DasNetworkConfig { listen_port: self.das_listen_port, ..Default::default() }
+	}
+
+	/// Returns the sampling parameters of the command line, without the sampling config file.
+	pub fn cli_sampling_params(&self) -> SamplingParams {
+		SamplingParams {
+			samples_per_block: self.das_samples_per_block,
+			block_threshold: self.das_block_threshold,
+			app_threshold: Permill::from_parts(self.das_app_threshold),
+			base_factor: Permill::from_parts(self.das_base_factor),
+			timeout: Duration::from_secs(self.das_sample_timeout),
+		}
+	}
+
+	/// Returns the weights the samples of the blocks are drawn with.
+	pub fn sampling_weights(&self) -> Result<SamplingWeights, String> {
+		let weights = SamplingWeights {
+			uniform_share: Permill::from_parts(self.das_uniform_share),
+			app_weights: self.das_app_weight.iter().copied().collect(),
+			failure_weight: self.das_failure_weight,
+		};
+		weights.validate().map(|_| weights)
+	}
+
+	/// Returns the mirror of the sidecars to `--das-committee`, signing with the keys of
+	/// `keystore`, `None` if no member is given.
+	pub fn committee(&self, keystore: KeystorePtr) -> Result<Option<CommitteeMirror>, String> {
+		if self.das_committee.is_empty() {
+			return Ok(None)
+		}
+		CommitteeMirror::new(
+			&self.das_committee,
+			Duration::from_secs(self.das_committee_timeout),
+			keystore,
+		)
+		.map(Some)
+	}
+
+	/// Returns the keys of the nodes allowed to mirror sidecars to the node and the bytes stored
+	/// for each, `None` unless the node serves as a committee member.
+	pub fn committee_member(&self) -> Result<Option<(Vec<sr25519::Public>, u64)>, String> {
+		if !self.das_committee_member {
+			return Ok(None)
+		}
+		let nodes = self
+			.das_committee_node
+			.iter()
+			.map(|key| {
+				sr25519::Public::from_ss58check(key)
+					.map_err(|e| format!("Invalid committee node key {}: {:?}", key, e))
+			})
+			.collect::<Result<_, _>>()?;
+		Ok(Some((nodes, self.das_committee_quota.saturating_mul(1 << 20))))
+	}
+
+	/// Returns the configuration of the queue of the samplings of the transaction pool listener.
+	pub fn sample_queue(&self) -> SampleQueueConfig {
+		SampleQueueConfig {
+			capacity: self.das_sample_queue,
+			concurrency: self.das_sample_concurrency.max(1),
+		}
+	}
+
+	/// Returns the time given to the sidecars of a block to be published before sealing, if
+	/// enabled.
+	pub fn prepublish_timeout(&self) -> Option<Duration> {
+		(self.das_prepublish_timeout > 0)
+			.then(|| Duration::from_millis(self.das_prepublish_timeout))
+	}
+
+	/// Returns the number of threads of the KZG worker pool.
+	pub fn kzg_workers(&self) -> usize {
+		self.das_kzg_workers
+			.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()) / 2)
+			.max(1)
+	}
+
+	/// Returns the sampling parameters of the command line, overridden by the sampling config
+	/// file if any.
+	pub fn sampling_params(&self) -> Result<SamplingParams, String> {
+		let params = self.cli_sampling_params();
+		match &self.das_sampling_config {
+			Some(path) => crate::sampling::load(path, params),
+			None => params.validate().map(|_| params),
+		}
+	}
 }
 
 #[derive(Debug, clap::Subcommand)]
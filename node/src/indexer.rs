@@ -0,0 +1,166 @@
+//! Explorer index of the chain.
+//!
+//! With `--das-indexer`, the node writes the blocks of the best chain and their blobs to a
+//! `melo_indexer` database as they are imported. As the blocks are finalized, it writes the
+//! rewards claimed by the farmers and the statistics of the apps, and records the confidence of
+//! the recently finalized blocks once they are sampled.
+use crate::service::FullClient;
+use codec::{Decode, Encode};
+use futures::{lock::Mutex, StreamExt};
+use log::{info, warn};
+use melo_core_primitives::{inclusion::commitments_root, reliability::ReliabilityId};
+use melo_das_db::traits::DasKv;
+use melo_daser::block_placements;
+use melo_indexer::{BlobRecord, ClaimRecord, Indexer};
+use melodot_runtime::{Hash, Header, NodeBlock as Block, RuntimeEvent};
+use sc_client_api::{
+	BlockImportNotification, BlockchainEvents, FinalityNotification, StorageProvider,
+};
+use sp_blockchain::HeaderBackend;
+use sp_core::storage::StorageKey;
+use std::sync::Arc;
+
+/// The number of blocks before the last finalized one whose confidence is looked up again on each
+/// finality notification, until their samples show the data available.
+const CONFIDENCE_WINDOW: u32 = 64;
+
+enum Notification {
+	Import(BlockImportNotification<Block>),
+	Finality(FinalityNotification<Block>),
+}
+
+/// Returns the blobs of the block `hash` of `header`.
+fn blob_records(client: &FullClient, hash: Hash, header: &Header) -> Vec<BlobRecord> {
+	match block_placements(client, hash, header) {
+		Ok(placements) => placements
+			.into_iter()
+			.map(|placement| BlobRecord {
+				hash: placement.metadata.id(),
+				app_id: placement.metadata.app_id,
+				nonce: placement.metadata.nonce,
+				size: placement.metadata.bytes_len,
+				start_row: placement.start_row,
+				rows: placement.metadata.commitments.len() as u32,
+				commitments_root: commitments_root(&placement.metadata.commitments).0,
+			})
+			.collect(),
+		Err(e) => {
+			warn!("⚠️ Failed to read the blobs of block {}: {:?}", header.number, e);
+			vec![]
+		},
+	}
+}
+
+/// Returns the rewards claimed in the block `hash`, from its events.
+fn claim_records(client: &FullClient, hash: Hash) -> Vec<ClaimRecord> {
+	let key = StorageKey([sp_core::twox_128(b"System"), sp_core::twox_128(b"Events")].concat());
+	let events = match client.storage(hash, &key) {
+		Ok(Some(events)) => events.0,
+		Ok(None) => return vec![],
+		Err(e) => {
+			warn!("⚠️ Failed to read the events of block {:?}: {:?}", hash, e);
+			return vec![]
+		},
+	};
+	let records =
+		match Vec::<frame_system::EventRecord<RuntimeEvent, Hash>>::decode(&mut &events[..]) {
+			Ok(records) => records,
+			Err(e) => {
+				warn!("⚠️ Failed to decode the events of block {:?}: {:?}", hash, e);
+				return vec![]
+			},
+		};
+
+	records
+		.into_iter()
+		.enumerate()
+		.filter_map(|(index, record)| match record.event {
+			RuntimeEvent::FarmersFortune(pallet_farmers_fortune::Event::RewardClaimed(
+				account,
+				reward,
+			)) => Some(ClaimRecord {
+				event_index: index as u32,
+				account: account.encode(),
+				reward: reward.into(),
+			}),
+			_ => None,
+		})
+		.collect()
+}
+
+/// Indexes the finalized block `hash`.
+fn finalize(client: &FullClient, indexer: &mut Indexer, hash: Hash) {
+	let header = match client.header(hash) {
+		Ok(Some(header)) => header,
+		_ => {
+			warn!("⚠️ Header of finalized block {:?} not found, not indexing it", hash);
+			return
+		},
+	};
+	let blobs = blob_records(client, hash, &header);
+	let claims = claim_records(client, hash);
+	if let Err(e) = indexer.finalize_block(header.number, &hash.encode(), &blobs, &claims) {
+		warn!("⚠️ Failed to index finalized block {}: {:?}", header.number, e);
+	}
+}
+
+/// Records the confidence of the finalized blocks from `from` on whose samples did not show the
+/// data available yet.
+async fn refresh_confidences<DB: DasKv>(indexer: &mut Indexer, das_db: &Mutex<DB>, from: u32) {
+	let blocks = match indexer.unsampled_blocks(from) {
+		Ok(blocks) => blocks,
+		Err(e) => {
+			warn!("⚠️ Failed to read the unsampled blocks of the index: {:?}", e);
+			return
+		},
+	};
+	let mut db = das_db.lock().await;
+	for (number, hash) in blocks {
+		if let Some(confidence) = ReliabilityId::block_confidence(&hash).get_confidence(&mut *db) {
+			if let Err(e) =
+				indexer.set_confidence(number, confidence.value(), confidence.is_availability())
+			{
+				warn!("⚠️ Failed to index the confidence of block {}: {:?}", number, e);
+			}
+		}
+	}
+}
+
+/// Keeps `indexer` up to date with the blocks imported and finalized by `client`, reading the
+/// confidences from `das_db`.
+pub async fn run_indexer<DB: DasKv + Send>(
+	client: Arc<FullClient>,
+	das_db: Arc<Mutex<DB>>,
+	mut indexer: Indexer,
+) {
+	info!("🗂️ Indexing the chain for explorers");
+
+	let mut notifications = futures::stream::select(
+		client.import_notification_stream().map(Notification::Import),
+		client.finality_notification_stream().map(Notification::Finality),
+	);
+
+	while let Some(notification) = notifications.next().await {
+		match notification {
+			Notification::Import(notification) => {
+				if !notification.is_new_best {
+					continue
+				}
+				let number = notification.header.number;
+				let blobs = blob_records(&client, notification.hash, &notification.header);
+				if let Err(e) = indexer.import_block(number, &notification.hash.encode(), &blobs) {
+					warn!("⚠️ Failed to index imported block {}: {:?}", number, e);
+				}
+			},
+			Notification::Finality(notification) => {
+				for hash in
+					notification.tree_route.iter().chain(std::iter::once(&notification.hash))
+				{
+					finalize(&client, &mut indexer, *hash);
+				}
+				let from = notification.header.number.saturating_sub(CONFIDENCE_WINDOW);
+				refresh_confidences(&mut indexer, &das_db, from).await;
+			},
+		}
+	}
+}
@@ -1,8 +1,12 @@
+mod attesters;
 pub mod chain_spec;
+mod indexer;
 pub mod rpc;
 pub mod service;
 
 #[cfg(feature = "cli")]
 mod cli;
 #[cfg(feature = "cli")]
+mod sampling;
+#[cfg(feature = "cli")]
 pub use cli::*;
@@ -7,7 +7,10 @@ mod service;
 mod benchmarking;
 mod cli;
 mod command;
+mod attesters;
+mod indexer;
 mod rpc;
+mod sampling;
 
 fn main() -> sc_cli::Result<()> {
 	command::run()
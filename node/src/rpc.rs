@@ -8,8 +8,15 @@
 use std::sync::Arc;
 
 use jsonrpsee::RpcModule;
-use melo_core_primitives::traits::AppDataApi;
-use melo_daser::DasNetworkOperations;
+use melo_core_primitives::{
+	traits::{AppDataApi, DasParamsApi, Extractor},
+	KzgWorkerPool,
+};
+use melo_das_rpc::{CommitteeMirror, NodeCapabilities, SubmitMetrics};
+use melo_daser::{
+	BackfillProgress, DasNetworkOperations, Pruner, SharedSamplingParams, SidecarVerifier,
+	WithholdingCollector,
+};
 pub use node_primitives::Signature;
 use futures::lock::Mutex;
 
@@ -27,6 +34,7 @@ use sp_block_builder::BlockBuilder;
 use sp_blockchain::{Error as BlockChainError, HeaderBackend, HeaderMetadata};
 use sp_consensus::SelectChain;
 use sp_consensus_babe::BabeApi;
+use sp_core::sr25519;
 use sp_keystore::KeystorePtr;
 
 use melodot_runtime::RuntimeCall;
@@ -76,6 +84,25 @@ pub struct FullDeps<C, P, SC, B, D, DB> {
 	pub das_network: Arc<D>,
 	/// DAS database.
 	pub das_db: Arc<Mutex<DB>>,
+	/// Progress of the backfill of the recent data.
+	pub das_backfill: Arc<BackfillProgress>,
+	/// Sampling parameters of the node.
+	pub das_sampling: SharedSamplingParams,
+	/// DAS services the node runs.
+	pub das_capabilities: NodeCapabilities,
+	/// Attestations that the recent blocks are withheld.
+	pub das_withholding: Arc<WithholdingCollector>,
+	/// Threads computing and verifying KZG commitments and proofs.
+	pub das_kzg_pool: KzgWorkerPool,
+	/// Pruner of the local data of old blocks.
+	pub das_pruner: Arc<Pruner<C, Block, DB>>,
+	/// Latency of the stages of the blob submissions, if metrics are enabled.
+	pub das_submit_metrics: Option<SubmitMetrics>,
+	/// The data availability committee the submitted sidecars are mirrored to, if any.
+	pub das_committee: Option<Arc<CommitteeMirror>>,
+	/// The keys of the nodes allowed to mirror sidecars to the node and the bytes stored for each,
+	/// if it serves as a member of a data availability committee.
+	pub das_committee_member: Option<(Vec<sr25519::Public>, u64)>,
 }
 
 /// Instantiate all full RPC extensions.
@@ -85,6 +112,7 @@ pub fn create_full<C, P, SC, B, D, DB>(
 where
 	C: ProvideRuntimeApi<Block>
 		+ sc_client_api::BlockBackend<Block>
+		+ sc_client_api::BlockchainEvents<Block>
 		+ HeaderBackend<Block>
 		+ AuxStore
 		+ HeaderMetadata<Block, Error = BlockChainError>
@@ -95,7 +123,10 @@ where
 	C::Api: pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance>,
 	C::Api: BabeApi<Block>,
 	C::Api: BlockBuilder<Block>,
+	C::Api: sp_api::Core<Block>,
 	C::Api: AppDataApi<Block, RuntimeCall>,
+	C::Api: DasParamsApi<Block>,
+	C::Api: Extractor<Block>,
 	P: TransactionPool + 'static,
 	SC: SelectChain<Block> + 'static,
 	B: sc_client_api::Backend<Block> + Send + Sync + 'static,
@@ -106,6 +137,13 @@ where
 {
 	use melo_das_rpc::{SubmitBlob, SubmitBlobApiServer};
 	use melo_das_rpc::{Confidence, ConfidenceApiServer};
+	use melo_das_rpc::{BlobList, BlobListApiServer};
+	use melo_das_rpc::{CommitteeApiServer, CommitteeMember};
+	use melo_das_rpc::{GetBlob, GetBlobApiServer};
+	use melo_das_rpc::{HeaderExtensions, HeaderExtensionsApiServer};
+	use melo_das_rpc::{Light, LightApiServer};
+	use melo_das_rpc::{DasNode, NodeInfoApiServer};
+	use melo_das_rpc::{PruneApiServer, PruneLocalData};
 	use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApiServer};
 	use sc_consensus_babe_rpc::{Babe, BabeApiServer};
 	use sc_consensus_grandpa_rpc::{Grandpa, GrandpaApiServer};
@@ -123,6 +161,15 @@ where
 		grandpa,
 		das_network,
 		das_db,
+		das_backfill,
+		das_sampling,
+		das_capabilities,
+		das_withholding,
+		das_kzg_pool,
+		das_pruner,
+		das_submit_metrics,
+		das_committee,
+		das_committee_member,
 	} = deps;
 
 	let BabeDeps { babe_worker_handle, keystore } = babe;
@@ -137,12 +184,18 @@ where
 	module.merge(System::new(client.clone(), pool.clone(), deny_unsafe).into_rpc())?;
 	module.merge(TransactionPayment::new(client.clone()).into_rpc())?;
 	module.merge(
-		Babe::new(client.clone(), babe_worker_handle.clone(), keystore, select_chain, deny_unsafe)
-			.into_rpc(),
+		Babe::new(
+			client.clone(),
+			babe_worker_handle.clone(),
+			keystore.clone(),
+			select_chain,
+			deny_unsafe,
+		)
+		.into_rpc(),
 	)?;
 	module.merge(
 		Grandpa::new(
-			subscription_executor,
+			subscription_executor.clone(),
 			shared_authority_set.clone(),
 			shared_voter_state,
 			justification_stream,
@@ -155,9 +208,60 @@ where
 			.into_rpc(),
 	)?;
 
-	module.merge(SubmitBlob::new(client.clone(), pool, das_network.clone()).into_rpc())?;
+	let verifier = Arc::new(SidecarVerifier::default().with_pool(das_kzg_pool.clone()));
+	let submit_blob = SubmitBlob::new(client.clone(), pool, das_network.clone(), das_db.clone())
+		.with_verifier(verifier.clone())
+		.with_kzg_pool(das_kzg_pool)
+		.with_deny_unsafe(deny_unsafe);
+	let submit_blob = match das_submit_metrics {
+		Some(metrics) => submit_blob.with_metrics(metrics),
+		None => submit_blob,
+	};
+	let submit_blob = match das_committee {
+		Some(committee) => submit_blob.with_committee(committee),
+		None => submit_blob,
+	};
+	module.merge(submit_blob.into_rpc())?;
+
+	if let Some((nodes, quota)) = das_committee_member {
+		let member = CommitteeMember::<_, Block, _>::new(
+			client.clone(),
+			das_db.clone(),
+			verifier,
+			keystore.clone(),
+			nodes,
+		)
+		.with_quota(quota);
+		module.merge(member.into_rpc())?;
+	}
+
+	module.merge(
+		DasNode::new(client.clone(), das_sampling.clone())
+			.with_capabilities(das_capabilities)
+			.into_rpc(),
+	)?;
+
+	module.merge(
+		Confidence::<DB, Hash, D>::new(&das_db, &das_network, &das_backfill)
+			.with_keystore(keystore.clone())
+			.with_sampling_params(das_sampling, deny_unsafe)
+			.with_withholding(das_withholding)
+			.into_rpc(),
+	)?;
+
+	module.merge(HeaderExtensions::new(client.clone(), subscription_executor).into_rpc())?;
+
+	module.merge(
+		Light::<C, Block, DB, D>::new(client.clone(), das_db.clone(), das_network.clone())
+			.with_keystore(keystore)
+			.into_rpc(),
+	)?;
+
+	module.merge(BlobList::new(das_db.clone()).into_rpc())?;
+
+	module.merge(GetBlob::new(das_network, das_db).into_rpc())?;
 
-	module.merge(Confidence::<DB, Hash, D>::new(&das_db, &das_network).into_rpc())?;
+	module.merge(PruneLocalData::new(das_pruner, deny_unsafe).into_rpc())?;
 
 	// Extend this RPC with a custom API by using the following syntax.
 	// `YourRpcStruct` should have a reference to a client, which is needed
@@ -0,0 +1,66 @@
+//! Sampling parameters of the node.
+//!
+//! The parameters are set on the command line, and overridden by the `--das-sampling-config`
+//! file if any. The file is read again on SIGHUP, replacing the parameters in use.
+use log::{info, warn};
+use melo_core_primitives::reliability::Permill;
+use melo_daser::{SamplingParams, SharedSamplingParams};
+use serde::Deserialize;
+use std::{
+	path::{Path, PathBuf},
+	time::Duration,
+};
+use tokio::signal::unix::{signal, SignalKind};
+
+/// The parameters of a `--das-sampling-config` file, each overriding the one of the command line.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SamplingFile {
+	samples_per_block: Option<u32>,
+	block_threshold: Option<u32>,
+	app_threshold: Option<u32>,
+	base_factor: Option<u32>,
+	timeout_secs: Option<u64>,
+}
+
+impl SamplingFile {
+	fn apply(self, params: SamplingParams) -> SamplingParams {
+		SamplingParams {
+			samples_per_block: self.samples_per_block.unwrap_or(params.samples_per_block),
+			block_threshold: self.block_threshold.unwrap_or(params.block_threshold),
+			app_threshold: self.app_threshold.map_or(params.app_threshold, Permill::from_parts),
+			base_factor: self.base_factor.map_or(params.base_factor, Permill::from_parts),
+			timeout: self.timeout_secs.map_or(params.timeout, Duration::from_secs),
+		}
+	}
+}
+
+/// Returns `params` overridden by the file at `path`, if they are valid.
+pub fn load(path: &Path, params: SamplingParams) -> Result<SamplingParams, String> {
+	let content = std::fs::read_to_string(path)
+		.map_err(|e| format!("Failed to read sampling config {:?}: {}", path, e))?;
+	let file: SamplingFile = toml::from_str(&content)
+		.map_err(|e| format!("Invalid sampling config {:?}: {}", path, e))?;
+	let params = file.apply(params);
+	params.validate()?;
+	Ok(params)
+}
+
+/// Reads the file at `path` into `shared` on each SIGHUP, on top of the parameters `base` of the
+/// command line. Invalid files leave the parameters in use unchanged.
+pub async fn reload_on_sighup(path: PathBuf, base: SamplingParams, shared: SharedSamplingParams) {
+	let mut hangup = match signal(SignalKind::hangup()) {
+		Ok(hangup) => hangup,
+		Err(e) => {
+			warn!("⚠️ Sampling parameters will not be reloaded, failed to listen to SIGHUP: {}", e);
+			return
+		},
+	};
+
+	while hangup.recv().await.is_some() {
+		match load(&path, base).and_then(|params| shared.set(params).map(|_| params)) {
+			Ok(params) => info!("🔧 Reloaded sampling parameters: {:?}", params),
+			Err(e) => warn!("⚠️ Sampling parameters not reloaded: {}", e),
+		}
+	}
+}
@@ -1,25 +1,57 @@
 //! Service and ServiceFactory implementation. Specialized wrapper over substrate service.
 
 #![warn(unused_extern_crates)]
+use codec::Decode;
 use futures::{lock::Mutex, prelude::*};
 use grandpa::SharedVoterState;
-use melo_das_db::offchain_outside::OffchainKvOutside;
-use melo_das_network::{default as create_das_network, DasNetwork};
+use melo_core_primitives::{
+	config::PRE_CELL_LEADING_ZEROS, embedded_trusted_setup_hash, traits::DasParamsApi,
+	KzgWorkerPool, DEFAULT_KZG_QUEUE_SIZE, SIDECAR_PREFIX,
+};
+use melo_das_db::{
+	budget::{BudgetConfig, BudgetedDasDb},
+	offchain_outside::OffchainKvOutside,
+	s3_store::S3ColdStore,
+	tiered::{TieredConfig, TieredDasDb},
+};
+use melo_das_network::{
+	default as create_das_network, DasNetwork, LegacyKeys, SolutionAnnouncement,
+};
 use melo_das_primitives::KZG;
 use melo_daser::{
-	start_tx_pool_listener, DasNetworkServiceWrapper, SamplingClient, TPListenerParams,
+	max_prepublish_timeout, start_backfill, start_outbox_task, start_prune_task, start_repair_task,
+	start_resample_task, start_solution_relay, start_tx_pool_listener, AvailabilityBlockImport,
+	BackfillProgress, Candidate, CellCache, CircuitBreaker, DasNetworkServiceWrapper,
+	DataAwarePool, Outbox, OutboxConfig, PrePublisher, PruneConfig, Pruner, RepairConfig,
+	ResampleConfig, SampleQueueMetrics, SamplingClient, SharedSamplingParams, SolutionRelayConfig,
+	TPListenerParams, WithholdingCollector, OUTBOX_PREFIX,
+};
+use melo_proof_of_space::{CompactSolution, FarmerId, Solution};
+use melodot_runtime::{
+	self, AccountId, BlockNumber, Hash, Header, NodeBlock as Block, RuntimeApi, RuntimeCall,
+	UncheckedExtrinsic,
 };
-use melodot_runtime::{self, Header, NodeBlock as Block, RuntimeApi};
 use sc_client_api::{Backend, BlockBackend};
 use sc_consensus_babe::{self, SlotProportion};
+use sc_consensus_manual_seal::{
+	consensus::{babe::BabeConsensusDataProvider, timestamp::SlotTimestampProvider},
+	rpc::{ManualSeal, ManualSealApiServer},
+	EngineCommand, ManualSealParams,
+};
 pub use sc_executor::NativeElseWasmExecutor;
 use sc_network::{event::Event, NetworkEventStream};
 use sc_offchain::OffchainDb;
 use sc_service::{error::Error as ServiceError, Configuration, TaskManager, WarpSyncParams};
 use sc_telemetry::{Telemetry, TelemetryWorker};
+use sp_api::{ApiExt, ProvideRuntimeApi};
+use sp_blockchain::HeaderBackend;
+use sp_runtime::OpaqueExtrinsic;
 use std::{sync::Arc, time::Duration};
 
-use crate::rpc as melo_rpc;
+use crate::{
+	cli::{DasParams, Sealing},
+	rpc as melo_rpc,
+};
 
 // Our native executor instance.
 pub struct ExecutorDispatch;
@@ -27,10 +59,13 @@ pub struct ExecutorDispatch;
 impl sc_executor::NativeExecutionDispatch for ExecutorDispatch {
 	/// Only enable the benchmarking host functions when we actually want to benchmark.
 	#[cfg(feature = "runtime-benchmarks")]
-	type ExtendHostFunctions = frame_benchmarking::benchmarking::HostFunctions;
-	/// Otherwise we only use the default Substrate host functions.
+	type ExtendHostFunctions = (
+		melo_core_primitives::kzg_verifier::HostFunctions,
+		frame_benchmarking::benchmarking::HostFunctions,
+	);
+	/// Otherwise we only add the KZG host functions to the default Substrate ones.
 	#[cfg(not(feature = "runtime-benchmarks"))]
-	type ExtendHostFunctions = ();
+	type ExtendHostFunctions = melo_core_primitives::kzg_verifier::HostFunctions;
 
 	fn dispatch(method: &str, data: &[u8]) -> Option<Vec<u8>> {
 		melodot_runtime::api::dispatch(method, data)
@@ -49,11 +84,73 @@ type FullSelectChain = sc_consensus::LongestChain<FullBackend, Block>;
 type FullGrandpaBlockImport =
 	grandpa::GrandpaBlockImport<FullBackend, Block, FullClient, FullSelectChain>;
 
-type DbType = OffchainKvOutside<Block, FullBackend>;
+type DbType = BudgetedDasDb<OffchainKvOutside<Block, FullBackend>>;
+
+type FullAvailabilityBlockImport =
+	AvailabilityBlockImport<FullGrandpaBlockImport, Header, DbType, DasNetworkServiceWrapper>;
+
+/// The number of solutions waiting for their verdict above which new ones are ignored.
+const SOLUTION_RELAY_QUEUE: usize = 256;
+
+/// Builds the unsigned claim of a solution published by a farmer.
+fn claim_candidate(announcement: &SolutionAnnouncement) -> Option<Candidate<OpaqueExtrinsic>> {
+	let solution = CompactSolution::<BlockNumber>::decode(&mut &announcement.solution[..]).ok()?;
+	let quality = Solution::<Hash, BlockNumber>::pre_cell_quality(
+		&solution.pre_cell.seg,
+		&FarmerId(announcement.farmer_id.into()),
+		PRE_CELL_LEADING_ZEROS,
+	);
+	let call = RuntimeCall::FarmersFortune(pallet_farmers_fortune::Call::claim_unsigned {
+		farmer: AccountId::from(announcement.farmer),
+		solution: Box::new(solution),
+	});
+	Some(Candidate { quality, extrinsic: UncheckedExtrinsic::new_unsigned(call).into() })
+}
+
+/// Compares the hash of the embedded trusted setup with the one registered on chain at the best
+/// block.
+///
+/// Returns whether the node may author blocks. On a mismatch the node refuses to start, unless
+/// `--das-allow-setup-mismatch` is set, in which case it only follows and verifies the chain.
+fn check_trusted_setup(client: &FullClient, das: &DasParams) -> Result<bool, ServiceError> {
+	let best_hash = client.info().best_hash;
+	let api = client.runtime_api();
+	// Runtimes before version 2 of the API can not register a trusted setup.
+	let has_trusted_setup = api
+		.has_api_with::<dyn DasParamsApi<Block>, _>(best_hash, |version| version >= 2)
+		.map_err(|e| ServiceError::from(e.to_string()))?;
+	let registered = if has_trusted_setup {
+		api.trusted_setup_hash(best_hash)
+			.map_err(|e| ServiceError::from(e.to_string()))?
+	} else {
+		None
+	};
+	let local = embedded_trusted_setup_hash();
+
+	match registered {
+		Some(registered) if registered != local => {
+			let msg = format!(
+				"The trusted setup of the node ({local:?}) differs from the one registered on \
+				 chain ({registered:?})"
+			);
+			if !das.das_allow_setup_mismatch {
+				return Err(ServiceError::from(msg))
+			}
+			log::warn!("{msg}, running in verify-only mode");
+			Ok(false)
+		},
+		Some(_) => Ok(true),
+		None => {
+			log::warn!("No trusted setup is registered on chain, skipping the check");
+			Ok(true)
+		},
+	}
+}
 
 #[allow(clippy::type_complexity)]
 pub fn new_partial(
 	config: &Configuration,
+	das: &DasParams,
 ) -> Result<
 	sc_service::PartialComponents<
 		FullClient,
@@ -67,14 +164,17 @@ pub fn new_partial(
 				melo_rpc::SubscriptionTaskExecutor,
 			) -> Result<jsonrpsee::RpcModule<()>, sc_service::Error>,
 			(
-				sc_consensus_babe::BabeBlockImport<Block, FullClient, FullGrandpaBlockImport>,
+				sc_consensus_babe::BabeBlockImport<Block, FullClient, FullAvailabilityBlockImport>,
 				grandpa::LinkHalf<Block, FullClient, FullSelectChain>,
 				sc_consensus_babe::BabeLink<Block>,
 			),
 			grandpa::SharedVoterState,
 			Option<Telemetry>,
-			SamplingClient<Header, OffchainKvOutside<Block, FullBackend>, DasNetworkServiceWrapper>,
+			Arc<SamplingClient<Header, DbType, DasNetworkServiceWrapper>>,
 			DasNetwork,
+			Arc<BackfillProgress>,
+			Arc<WithholdingCollector>,
+			Arc<Pruner<FullClient, Block, DbType>>,
 		),
 	>,
 	ServiceError,
@@ -122,11 +222,92 @@ pub fn new_partial(
 		telemetry.as_ref().map(|x| x.handle()),
 	)?;
 
+	let (das_network_service, das_networker) =
+		create_das_network(Some(das.das_network_config()), None)
+			.map_err(|e| sc_service::Error::from(e.to_string()))?;
+
+	// Initialize the off-chain database using the backend's off-chain storage.
+	// If unavailable, log a warning and return without starting the listener.
+	let offchain_db = backend
+		.offchain_storage()
+		.map(OffchainDb::new)
+		.ok_or_else(|| sc_service::Error::from("No offchain storage available"))?;
+
+	let cache = BudgetConfig {
+		max_bytes: das.das_cache_size.map(|mib| mib.saturating_mul(1024 * 1024)),
+		// The outbox is never evicted, it would lose track of the sidecars not durable yet.
+		pinned_prefixes: if das.das_cache_evict_authored {
+			vec![OUTBOX_PREFIX.to_vec()]
+		} else {
+			vec![SIDECAR_PREFIX.to_vec(), OUTBOX_PREFIX.to_vec()]
+		},
+	};
+	let index_path = config.data_path.join("das_cache_index.sqlite3");
+	let db: DbType = BudgetedDasDb::new(
+		OffchainKvOutside::new(offchain_db, None),
+		&index_path.to_string_lossy(),
+		cache,
+	)
+	.map_err(|e| ServiceError::from(e.to_string()))?;
+	let kzg = Arc::new(KZG::default_embedded());
+	let kzg_pool = KzgWorkerPool::with_kzg(kzg.clone(), das.kzg_workers(), DEFAULT_KZG_QUEUE_SIZE)
+		.map_err(ServiceError::from)?;
+
+	let legacy_keys =
+		if das.das_reject_legacy_keys { LegacyKeys::Reject } else { LegacyKeys::Accept };
+	let das_breaker = match config
+		.prometheus_registry()
+		.map(|registry| CircuitBreaker::default().with_registry(registry))
+	{
+		Some(Ok(breaker)) => breaker,
+		Some(Err(e)) => {
+			log::warn!("Failed to register the metrics of the DHT breaker: {:?}", e);
+			CircuitBreaker::default()
+		},
+		None => CircuitBreaker::default(),
+	};
+	let das_cells = match config
+		.prometheus_registry()
+		.map(|registry| CellCache::default().with_registry(registry))
+	{
+		Some(Ok(cells)) => cells,
+		Some(Err(e)) => {
+			log::warn!("Failed to register the metrics of the cell cache: {:?}", e);
+			CellCache::default()
+		},
+		None => CellCache::default(),
+	};
+	let das_network_warpper = DasNetworkServiceWrapper::new(das_network_service.into(), kzg)
+		.with_legacy_keys(legacy_keys)
+		.with_breaker(Arc::new(das_breaker))
+		.with_cell_cache(Arc::new(das_cells))
+		.with_extend_threads(das.kzg_workers());
+
+	let db = Arc::new(Mutex::new(db));
+
+	let sampling = SharedSamplingParams::new(das.sampling_params().map_err(ServiceError::from)?)
+		.map_err(ServiceError::from)?;
+
+	let das_client: Arc<SamplingClient<Header, DbType, DasNetworkServiceWrapper>> = Arc::new(
+		SamplingClient::new(das_network_warpper.clone(), db.clone())
+			.with_params(sampling.clone())
+			.with_weights(das.sampling_weights().map_err(ServiceError::from)?),
+	);
+
+	let das_backfill = Arc::new(BackfillProgress::default());
+	let das_withholding =
+		Arc::new(WithholdingCollector::default().with_keystore(keystore_container.keystore()));
+	let das_pruner = Arc::new(Pruner::new(client.clone(), db.clone()));
+
 	let justification_import = grandpa_block_import.clone();
 
+	// Blocks of the network are only imported once their data is shown available.
+	let availability_block_import =
+		AvailabilityBlockImport::new(grandpa_block_import, das_client.clone());
+
 	let (babe_block_import, babe_link) = sc_consensus_babe::block_import(
 		sc_consensus_babe::configuration(&*client)?,
-		grandpa_block_import,
+		availability_block_import,
 		client.clone(),
 	)?;
 
@@ -156,26 +337,6 @@ pub fn new_partial(
 
 	let import_setup = (babe_block_import, grandpa_link, babe_link);
 
-	let (das_network_service, das_networker) =
-		create_das_network(None, None).map_err(|e| sc_service::Error::from(e.to_string()))?;
-
-	// Initialize the off-chain database using the backend's off-chain storage.
-	// If unavailable, log a warning and return without starting the listener.
-	let offchain_db = backend
-		.offchain_storage()
-		.map(OffchainDb::new)
-		.ok_or_else(|| sc_service::Error::from("No offchain storage available"))?;
-
-	let db: DbType = OffchainKvOutside::new(offchain_db, None);
-	let kzg = KZG::default_embedded();
-
-	let das_network_warpper = DasNetworkServiceWrapper::new(das_network_service.into(), kzg.into());
-
-	let db = Arc::new(Mutex::new(db));
-
-	let das_client: SamplingClient<Header, DbType, DasNetworkServiceWrapper> =
-		SamplingClient::new(das_network_warpper.clone(), db.clone());
-
 	let (rpc_extensions_builder, rpc_setup) = {
 		let (_, grandpa_link, _) = &import_setup;
 
@@ -194,6 +355,28 @@ pub fn new_partial(
 		let select_chain = select_chain.clone();
 		let keystore = keystore_container.keystore();
 		let chain_spec = config.chain_spec.cloned_box();
+		let das_backfill = das_backfill.clone();
+		let das_withholding = das_withholding.clone();
+		let das_pruner = das_pruner.clone();
+		let das_submit_metrics =
+			match config.prometheus_registry().map(melo_das_rpc::SubmitMetrics::register) {
+				Some(Ok(metrics)) => Some(metrics),
+				Some(Err(e)) => {
+					log::warn!("Failed to register the metrics of blob submissions: {:?}", e);
+					None
+				},
+				None => None,
+			};
+		let kzg_pool = kzg_pool.clone();
+		let sampling = sampling.clone();
+		let das_committee =
+			das.committee(keystore.clone()).map_err(ServiceError::from)?.map(Arc::new);
+		let das_committee_member = das.committee_member().map_err(ServiceError::from)?;
+		let das_capabilities = melo_das_rpc::NodeCapabilities {
+			archive: das.archive_das,
+			relay_solutions: das.relay_solutions,
+			backfill: das.das_backfill.is_some(),
+		};
 
 		let rpc_extensions_builder = move |deny_unsafe, subscription_executor| {
 			let deps = melo_rpc::FullDeps {
@@ -215,6 +398,15 @@ pub fn new_partial(
 				},
 				das_network: das_network_warpper.clone().into(),
 				das_db: db.clone(),
+				das_backfill: das_backfill.clone(),
+				das_sampling: sampling.clone(),
+				das_capabilities,
+				das_withholding: das_withholding.clone(),
+				das_kzg_pool: kzg_pool.clone(),
+				das_pruner: das_pruner.clone(),
+				das_submit_metrics: das_submit_metrics.clone(),
+				das_committee: das_committee.clone(),
+				das_committee_member,
 			};
 
 			melo_rpc::create_full(deps).map_err(Into::into)
@@ -238,12 +430,19 @@ pub fn new_partial(
 			telemetry,
 			das_client,
 			das_networker,
+			das_backfill,
+			das_withholding,
+			das_pruner,
 		),
 	})
 }
 
 /// Builds a new service for a full client.
-pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError> {
+pub fn new_full(
+	mut config: Configuration,
+	das: DasParams,
+	sealing: Sealing,
+) -> Result<TaskManager, ServiceError> {
 	let sc_service::PartialComponents {
 		client,
 		backend,
@@ -252,8 +451,21 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 		import_queue,
 		keystore_container,
 		transaction_pool,
-		other: (rpc_extensions_builder, import_setup, _, mut telemetry, das_client, das_networker),
-	} = new_partial(&config)?;
+		other:
+			(
+				rpc_extensions_builder,
+				import_setup,
+				_,
+				mut telemetry,
+				das_client,
+				das_networker,
+				das_backfill,
+				das_withholding,
+				das_pruner,
+			),
+	} = new_partial(&config, &das)?;
+
+	let can_author = check_trusted_setup(&client, &das)?;
 
 	let grandpa_protocol_name = grandpa::protocol_standard_name(
 		&client.block_hash(0).ok().flatten().expect("Genesis block exists; qed"),
@@ -303,15 +515,166 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 	let enable_grandpa = !config.disable_grandpa;
 	let prometheus_registry = config.prometheus_registry().cloned();
 
+	let listener_params =
+		TPListenerParams::new(client.clone(), das_client.clone(), transaction_pool.clone())
+			.with_withholding(das_withholding.clone())
+			.with_sample_queue(das.sample_queue());
+	let listener_params = match prometheus_registry.as_ref().map(SampleQueueMetrics::register) {
+		Some(Ok(metrics)) => listener_params.with_queue_metrics(metrics),
+		Some(Err(e)) => {
+			log::warn!("Failed to register the metrics of the sampling queue: {:?}", e);
+			listener_params
+		},
+		None => listener_params,
+	};
 	task_manager.spawn_essential_handle().spawn_blocking(
 		"tx_pool_listener",
 		None,
-		start_tx_pool_listener(TPListenerParams::new(
+		start_tx_pool_listener(listener_params),
+	);
+
+	task_manager.spawn_handle().spawn(
+		"das_attesters",
+		None,
+		crate::attesters::track_attesters(client.clone(), das_withholding),
+	);
+
+	task_manager.spawn_handle().spawn(
+		"das_repair",
+		None,
+		start_repair_task(client.clone(), das_client.clone(), RepairConfig::default()),
+	);
+
+	// Authors keep republishing the sidecars of their blocks until enough peers store them.
+	if role.is_authority() {
+		let config = OutboxConfig { required_acks: das.das_outbox_acks, ..OutboxConfig::default() };
+		task_manager.spawn_handle().spawn(
+			"das_outbox",
+			None,
+			start_outbox_task(client.clone(), Outbox::new(das_client.clone(), config)),
+		);
+	}
+
+	task_manager.spawn_handle().spawn(
+		"das_resample",
+		None,
+		start_resample_task(client.clone(), das_client.clone(), ResampleConfig::default()),
+	);
+
+	// Archive nodes keep the data of every block.
+	if !das.archive_das {
+		task_manager.spawn_handle().spawn(
+			"das_prune",
+			None,
+			start_prune_task(das_pruner, PruneConfig::default()),
+		);
+	}
+
+	if let Some(addr) = das.das_grpc {
+		let gateway = melo_das_grpc::DasGateway::new(
 			client.clone(),
-			das_client.into(),
 			transaction_pool.clone(),
-		)),
-	);
+			Arc::new(das_client.network.clone()),
+			das_client.database(),
+			das_backfill.clone(),
+		);
+		task_manager
+			.spawn_handle()
+			.spawn("das_grpc", None, melo_das_grpc::serve(addr, gateway));
+	}
+
+	if let Some(path) = das.das_sampling_config.clone() {
+		task_manager.spawn_handle().spawn(
+			"das_sampling_reload",
+			None,
+			crate::sampling::reload_on_sighup(
+				path,
+				das.cli_sampling_params(),
+				das_client.params().clone(),
+			),
+		);
+	}
+
+	let das_network_service = das_client
+		.network
+		.service()
+		.cloned()
+		.ok_or_else(|| ServiceError::from("The DAS client does not run on the DAS network"))?;
+	let das_db = das_client.database();
+
+	if let Some(path) = das.das_indexer.clone() {
+		let indexer = melo_indexer::Indexer::open(&path).map_err(|e| {
+			ServiceError::from(format!("Failed to open the index at {:?}: {:?}", path, e))
+		})?;
+		task_manager.spawn_handle().spawn(
+			"das_indexer",
+			None,
+			crate::indexer::run_indexer(client.clone(), das_db.clone(), indexer),
+		);
+	}
+
+	if let Some(depth) = das.das_backfill {
+		task_manager.spawn_handle().spawn(
+			"das_backfill",
+			None,
+			start_backfill(client.clone(), das_client, sync_service.clone(), depth, das_backfill),
+		);
+	}
+
+	let das_networker = if das.archive_das {
+		let offchain_db = backend
+			.offchain_storage()
+			.map(OffchainDb::new)
+			.ok_or_else(|| ServiceError::from("No offchain storage available"))?;
+		let archive = OffchainKvOutside::<Block, FullBackend>::new(offchain_db, None);
+		match das.das_cold_bucket {
+			Some(bucket) => {
+				let cold = S3ColdStore::new(
+					&bucket,
+					&das.das_cold_region,
+					das.das_cold_endpoint.as_deref(),
+					"segments",
+				)
+				.map_err(ServiceError::from)?;
+				let index_path = config.data_path.join("das_cold_index.sqlite3");
+				let tiered_config = TieredConfig {
+					max_age: Duration::from_secs(das.das_cold_after_days * 24 * 60 * 60),
+					..Default::default()
+				};
+				let archive =
+					TieredDasDb::new(archive, cold, &index_path.to_string_lossy(), tiered_config)
+						.map_err(|e| ServiceError::from(e.to_string()))?;
+				das_networker.with_archive(Box::new(archive))
+			},
+			None => das_networker.with_archive(Box::new(archive)),
+		}
+	} else {
+		das_networker
+	};
+
+	let das_networker = if das.relay_solutions {
+		let (relay_tx, relay_rx) = futures::channel::mpsc::channel(SOLUTION_RELAY_QUEUE);
+		let relay_config = SolutionRelayConfig {
+			min_quality: das.solution_min_quality,
+			peer_quota: das.solution_peer_quota,
+			..Default::default()
+		};
+		task_manager.spawn_handle().spawn(
+			"solution_relay",
+			None,
+			start_solution_relay(
+				client.clone(),
+				transaction_pool.clone(),
+				das_network_service,
+				relay_rx,
+				relay_config,
+				claim_candidate,
+			),
+		);
+		das_networker.with_solution_relay(relay_tx)
+	} else {
+		das_networker
+	};
 
 	task_manager.spawn_essential_handle().spawn_blocking(
 		"das_networker",
@@ -319,13 +682,24 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 		das_networker.run(),
 	);
 
+	// Manually sealed blocks are requested through the `engine_createBlock` RPC.
+	let (command_sink, manual_commands) = futures::channel::mpsc::channel(1024);
+	let rpc_builder = move |deny_unsafe, subscription_executor| {
+		let mut io = rpc_extensions_builder(deny_unsafe, subscription_executor)?;
+		if sealing == Sealing::Manual {
+			io.merge(ManualSeal::new(command_sink.clone()).into_rpc())
+				.map_err(|e| ServiceError::Other(e.to_string()))?;
+		}
+		Ok(io)
+	};
+
 	let _rpc_handlers = sc_service::spawn_tasks(sc_service::SpawnTasksParams {
 		network: network.clone(),
 		client: client.clone(),
 		keystore: keystore_container.keystore(),
 		task_manager: &mut task_manager,
 		transaction_pool: transaction_pool.clone(),
-		rpc_builder: Box::new(rpc_extensions_builder),
+		rpc_builder: Box::new(rpc_builder),
 		backend,
 		system_rpc_tx,
 		tx_handler_controller,
@@ -334,17 +708,106 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 		telemetry: telemetry.as_mut(),
 	})?;
 
-	if let sc_service::config::Role::Authority { .. } = &role {
+	if role.is_authority() && can_author && sealing != Sealing::Babe {
+		let pool = DataAwarePool::new(client.clone(), transaction_pool.clone(), das_db);
 		let proposer = sc_basic_authorship::ProposerFactory::new(
 			task_manager.spawn_handle(),
 			client.clone(),
-			transaction_pool.clone(),
+			Arc::new(pool),
 			prometheus_registry.as_ref(),
 			telemetry.as_ref().map(|x| x.handle()),
 		);
 
+		let commands_stream: Box<dyn Stream<Item = EngineCommand<Hash>> + Send + Sync + Unpin> =
+			match sealing {
+				Sealing::Instant => Box::new(
+					transaction_pool.pool().validated_pool().import_notification_stream().map(
+						|_| EngineCommand::SealNewBlock {
+							create_empty: false,
+							finalize: true,
+							parent_hash: None,
+							sender: None,
+						},
+					),
+				),
+				_ => Box::new(manual_commands),
+			};
+
+		let consensus_data_provider = BabeConsensusDataProvider::new(
+			client.clone(),
+			keystore_container.keystore(),
+			babe_link.epoch_changes().clone(),
+			babe_link.config().authorities.clone(),
+		)
+		.map_err(|e| ServiceError::Other(format!("{:?}", e)))?;
+
 		let client_clone = client.clone();
+		let manual_seal = sc_consensus_manual_seal::run_manual_seal(ManualSealParams {
+			block_import,
+			env: proposer,
+			client: client.clone(),
+			pool: transaction_pool.clone(),
+			commands_stream,
+			select_chain,
+			consensus_data_provider: Some(Box::new(consensus_data_provider)),
+			create_inherent_data_providers: move |parent, ()| {
+				let client_clone = client_clone.clone();
+				async move {
+					// Each sealed block moves the time forward by one slot.
+					let timestamp = SlotTimestampProvider::new_babe(client_clone.clone())
+						.map_err(|e| format!("{:?}", e))?;
+
+					let slot =
+						sp_consensus_babe::inherents::InherentDataProvider::new(timestamp.slot());
+
+					let storage_proof =
+						sp_transaction_storage_proof::registration::new_data_provider(
+							&*client_clone,
+							&parent,
+						)?;
+
+					Ok((slot, timestamp, storage_proof))
+				}
+			},
+		});
+		task_manager.spawn_essential_handle().spawn_blocking(
+			"manual-seal",
+			Some("block-authoring"),
+			manual_seal,
+		);
+	} else if role.is_authority() && can_author {
+		// Blob transactions whose data the node holds are proposed first, and their sidecars are
+		// published before the block is sealed.
 		let slot_duration = babe_link.config().slot_duration();
+		let proposal_portion = 0.5;
+		let pool = DataAwarePool::new(client.clone(), transaction_pool.clone(), das_db);
+		let pool = match das.prepublish_timeout() {
+			Some(timeout) => {
+				let max =
+					max_prepublish_timeout(slot_duration.as_duration().mul_f32(proposal_portion));
+				if timeout > max {
+					log::warn!(
+						"The prepublish timeout of {:?} is lowered to {:?} to fit in the slots",
+						timeout,
+						max
+					);
+				}
+				pool.with_prepublish(PrePublisher::new(
+					Arc::new(das_client.network.clone()),
+					timeout.min(max),
+				))
+			},
+			None => pool,
+		};
+		let proposer = sc_basic_authorship::ProposerFactory::new(
+			task_manager.spawn_handle(),
+			client.clone(),
+			Arc::new(pool),
+			prometheus_registry.as_ref(),
+			telemetry.as_ref().map(|x| x.handle()),
+		);
+
+		let client_clone = client.clone();
 		let babe_config = sc_consensus_babe::BabeParams {
 			keystore: keystore_container.keystore(),
 			client: client.clone(),
@@ -376,7 +839,7 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 			force_authoring,
 			backoff_authoring_blocks,
 			babe_link,
-			block_proposal_slot_portion: SlotProportion::new(0.5),
+			block_proposal_slot_portion: SlotProportion::new(proposal_portion),
 			max_block_proposal_slot_portion: None,
 			telemetry: telemetry.as_ref().map(|x| x.handle()),
 		};
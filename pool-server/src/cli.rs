@@ -0,0 +1,82 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::pool::PoolConfig;
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use melo_core_primitives::config::PRE_CELL_LEADING_ZEROS;
+use std::{net::SocketAddr, path::PathBuf};
+
+const DEFAULT_RPC_URL: &str = "ws://127.0.0.1:9944";
+const DEFAULT_LISTEN_ADDR: &str = "127.0.0.1:4188";
+const DEFAULT_POINTS_FILE: &str = "./pool-points.json";
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+pub struct Cli {
+	/// The pool the server collects partial solutions for
+	#[clap(long, env = "POOL_ID")]
+	pool_id: u32,
+
+	/// Websocket endpoint of the Melodot node
+	#[clap(short = 'r', long, env = "RPC_REMOTE_URL", default_value = DEFAULT_RPC_URL)]
+	rpc_remote_url: String,
+
+	/// Listening address for the pool RPC service
+	#[clap(short = 'a', long, env = "POOL_LISTEN_ADDR", default_value = DEFAULT_LISTEN_ADDR)]
+	listen_addr: SocketAddr,
+
+	/// Leading zeros required in the pre-cell of a partial solution, at most the number the
+	/// chain requires
+	#[clap(long, default_value_t = PRE_CELL_LEADING_ZEROS)]
+	partial_leading_zeros: u8,
+
+	/// Number of blocks a partial solution can be submitted after its challenge block
+	#[clap(long, default_value_t = 10)]
+	window: u32,
+
+	/// File the points of the members are saved to
+	#[clap(long, env = "POOL_POINTS_FILE", default_value = DEFAULT_POINTS_FILE)]
+	points_file: PathBuf,
+}
+
+pub struct Config {
+	pub rpc_url: String,
+	pub listen_addr: SocketAddr,
+	pub pool: PoolConfig,
+}
+
+pub fn parse_args() -> Result<Config> {
+	let cli = Cli::parse();
+
+	// A partial solution meeting more than the difficulty of the chain would hide wins.
+	if cli.partial_leading_zeros > PRE_CELL_LEADING_ZEROS {
+		return Err(anyhow!(
+			"Partial solutions can require at most {} leading zeros, got {}",
+			PRE_CELL_LEADING_ZEROS,
+			cli.partial_leading_zeros
+		))
+	}
+
+	Ok(Config {
+		rpc_url: cli.rpc_remote_url,
+		listen_addr: cli.listen_addr,
+		pool: PoolConfig {
+			pool_id: cli.pool_id,
+			partial_leading_zeros: cli.partial_leading_zeros,
+			window: cli.window,
+			points_path: cli.points_file,
+		},
+	})
+}
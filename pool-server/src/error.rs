@@ -0,0 +1,85 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pool server errors.
+
+use jsonrpsee::{
+	core::Error as JsonRpseeError,
+	types::error::{CallError, ErrorObject},
+};
+
+/// The reasons a partial solution is rejected.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	/// The solution can not be decoded
+	#[error("Invalid solution encoding")]
+	InvalidEncoding,
+	/// The farmer is not a member of the pool
+	#[error("Not a member of the pool")]
+	NotMember,
+	/// The farmer has not bound a farmer ID
+	#[error("No farmer ID is bound to the account")]
+	NotRegistered,
+	/// The challenge block is unknown or out of the window of the pool
+	#[error("Stale challenge block")]
+	Stale,
+	/// The solution was already submitted
+	#[error("Duplicate solution")]
+	Duplicate,
+	/// The solution does not verify
+	#[error("Invalid solution")]
+	InvalidSolution,
+	/// The node could not be queried
+	#[error("Node request failed: {}", .0)]
+	Node(String),
+}
+
+impl Error {
+	/// Returns the JSON-RPC error code of the error.
+	///
+	/// | Code  | Error                                 |
+	/// |-------|---------------------------------------|
+	/// | 20001 | The solution can not be decoded       |
+	/// | 20002 | The farmer is not a member            |
+	/// | 20003 | No farmer ID is bound to the farmer   |
+	/// | 20004 | The challenge block is stale          |
+	/// | 20005 | The solution was already submitted    |
+	/// | 20006 | The solution does not verify          |
+	/// | 20007 | The node could not be queried         |
+	///
+	/// Only `20007` is transient.
+	pub fn code(&self) -> i32 {
+		match self {
+			Error::InvalidEncoding => 20001,
+			Error::NotMember => 20002,
+			Error::NotRegistered => 20003,
+			Error::Stale => 20004,
+			Error::Duplicate => 20005,
+			Error::InvalidSolution => 20006,
+			Error::Node(_) => 20007,
+		}
+	}
+}
+
+impl From<subxt::Error> for Error {
+	fn from(e: subxt::Error) -> Self {
+		Error::Node(e.to_string())
+	}
+}
+
+impl From<Error> for JsonRpseeError {
+	fn from(e: Error) -> Self {
+		CallError::Custom(ErrorObject::owned(e.code(), e.to_string(), None::<()>)).into()
+	}
+}
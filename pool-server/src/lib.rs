@@ -0,0 +1,76 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The farming pool protocol.
+//!
+//! The members of a pool farm with the farmer ID bound to their own reward account, as solo
+//! farmers do, but send their solutions to the pool server instead of claiming them. The pool
+//! accepts partial solutions, whose pre-cell meets the lower difficulty of the pool, and credits
+//! a point to the member for each of them, so that the operator can pay the members according to
+//! the storage they prove. A partial solution that also meets the difficulty of the chain, while
+//! its challenge block is the best one, is claimed by the pool for the member.
+//!
+//! On-chain, `pallet-farming-pools` records the members of each pool and pays the pool share of
+//! their rewards to the operator.
+
+use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+use serde::{Deserialize, Serialize};
+use subxt::{
+	rpc::types::Bytes,
+	utils::{AccountId32, H256},
+};
+
+/// The parameters of a pool server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolServerInfo {
+	/// The pool the server collects partial solutions for.
+	pub pool_id: u32,
+	/// The number of leading zeros the pre-cell of a partial solution requires.
+	pub partial_leading_zeros: u8,
+	/// The number of blocks a partial solution can be submitted after its challenge block.
+	pub window: u32,
+}
+
+/// The result of the submission of a partial solution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartialResult {
+	/// The points of the member, including this partial solution.
+	pub points: u64,
+	/// Whether the solution was claimed on-chain for the member.
+	pub claimed: bool,
+}
+
+/// The pool server RPC API.
+#[rpc(client, server, namespace = "pool")]
+pub trait PoolApi {
+	/// Returns the parameters of the pool server.
+	#[method(name = "info")]
+	async fn info(&self) -> RpcResult<PoolServerInfo>;
+
+	/// Submits a partial solution of `farmer`, found with the challenge of the block
+	/// `block_hash`. `solution` is the SCALE encoding of its `CompactSolution`.
+	#[method(name = "submitPartial")]
+	async fn submit_partial(
+		&self,
+		farmer: AccountId32,
+		block_hash: H256,
+		solution: Bytes,
+	) -> RpcResult<PartialResult>;
+
+	/// Returns the points of `farmer`.
+	#[method(name = "points")]
+	async fn points(&self, farmer: AccountId32) -> RpcResult<u64>;
+}
@@ -0,0 +1,59 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use cli::parse_args;
+use jsonrpsee::server::ServerBuilder;
+use log::{error, info};
+use melodot_pool_server::PoolApiServer;
+use meloxt::ClientBuilder;
+use pool::Pool;
+
+mod cli;
+mod error;
+mod pool;
+
+/// Runs the pool server with the given configuration, until its RPC server stops.
+pub async fn run(config: cli::Config) -> anyhow::Result<()> {
+	meloxt::init_logger().map_err(|e| anyhow::anyhow!("{}", e))?;
+
+	info!("🚀 Melodot pool server starting up for pool {}", config.pool.pool_id);
+
+	let client = ClientBuilder::default().set_url(&config.rpc_url).build().await?;
+	let pool = Pool::new(client, config.pool)?;
+
+	let server = ServerBuilder::default().build(config.listen_addr).await?;
+	let addr = server.local_addr()?;
+	let handle = server.start(pool.into_rpc())?;
+	info!("🏊 Pool RPC listening on {}", addr);
+
+	handle.stopped().await;
+	Ok(())
+}
+
+pub fn main() {
+	let config = match parse_args() {
+		Ok(config) => config,
+		Err(e) => {
+			eprintln!("Invalid configuration: {:?}", e);
+			std::process::exit(1);
+		},
+	};
+
+	tokio::runtime::Builder::new_multi_thread()
+		.enable_all()
+		.build()
+		.expect("Failed to build runtime")
+		.block_on(run(config))
+		.unwrap_or_else(|e| error!("Fatal error: {}", e));
+}
@@ -0,0 +1,332 @@
+// Copyright 2023 ZeroDAO
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verification and accounting of partial solutions.
+
+use crate::error::Error;
+use codec::{Decode, Encode};
+use jsonrpsee::core::{async_trait, RpcResult};
+use log::{error, info};
+use melo_core_primitives::{config::PRE_CELL_LEADING_ZEROS, traits::HeaderWithCommitment};
+use melo_das_primitives::{KZGCommitment, Position, KZG};
+use melo_erasure_coding::extend_fs_g1;
+use melo_proof_of_space::{CompactSolution, FarmerId, Solution, ZValueManager};
+use melodot_pool_server::{PartialResult, PoolApiServer, PoolServerInfo};
+use meloxt::{Client, MelodotHeader as Header};
+use std::{
+	collections::{BTreeMap, HashMap, HashSet},
+	path::PathBuf,
+	sync::Mutex,
+};
+use subxt::{
+	config::{substrate::BlakeTwo256, Hasher},
+	error::MetadataError,
+	rpc::types::Bytes,
+	tx::TxPayload,
+	utils::{AccountId32, H256},
+	Metadata,
+};
+
+/// The `claim_unsigned` call of `pallet-farmers-fortune`, with the solution already encoded.
+struct ClaimUnsigned<'a> {
+	farmer: &'a AccountId32,
+	solution: &'a [u8],
+}
+
+impl TxPayload for ClaimUnsigned<'_> {
+	fn encode_call_data_to(
+		&self,
+		metadata: &Metadata,
+		out: &mut Vec<u8>,
+	) -> Result<(), subxt::Error> {
+		let pallet = metadata.pallet_by_name_err("FarmersFortune")?;
+		let call = pallet
+			.call_variant_by_name("claim_unsigned")
+			.ok_or_else(|| MetadataError::CallNameNotFound("claim_unsigned".to_string()))?;
+		pallet.index().encode_to(out);
+		call.index.encode_to(out);
+		self.farmer.encode_to(out);
+		out.extend_from_slice(self.solution);
+		Ok(())
+	}
+}
+
+/// The settings of a [`Pool`].
+#[derive(Debug, Clone)]
+pub struct PoolConfig {
+	/// The pool the server collects partial solutions for.
+	pub pool_id: u32,
+	/// The number of leading zeros the pre-cell of a partial solution requires.
+	pub partial_leading_zeros: u8,
+	/// The number of blocks a partial solution can be submitted after its challenge block.
+	pub window: u32,
+	/// The file the points of the members are saved to.
+	pub points_path: PathBuf,
+}
+
+/// The partial solutions accepted by the pool.
+struct Shares {
+	/// The points of each member.
+	points: HashMap<AccountId32, u64>,
+	/// The hashes of the solutions accepted, by the number of their challenge block.
+	seen: BTreeMap<u32, HashSet<H256>>,
+}
+
+/// A pool server, verifying the partial solutions of the members and claiming the winning ones.
+pub struct Pool {
+	client: Client,
+	config: PoolConfig,
+	kzg: KZG,
+	shares: Mutex<Shares>,
+}
+
+impl Pool {
+	/// Creates a pool server querying the node of `client`, with the points saved at
+	/// `config.points_path` by a previous run.
+	pub fn new(client: Client, config: PoolConfig) -> anyhow::Result<Self> {
+		let points = match std::fs::read(&config.points_path) {
+			Ok(bytes) => serde_json::from_slice(&bytes)?,
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+			Err(e) => return Err(e.into()),
+		};
+		let shares = Mutex::new(Shares { points, seen: BTreeMap::new() });
+		Ok(Self { client, config, kzg: KZG::default_embedded(), shares })
+	}
+
+	fn shares(&self) -> std::sync::MutexGuard<'_, Shares> {
+		self.shares.lock().expect("Lock is not poisoned; qed")
+	}
+
+	/// Reads the storage entry `entry` of `pallet` under `key` at the best block.
+	async fn storage<T: Decode>(
+		&self,
+		pallet: &str,
+		entry: &str,
+		key: &AccountId32,
+	) -> Result<Option<T>, Error> {
+		let key = self
+			.client
+			.storage_key(pallet, entry, key)
+			.map_err(|e| Error::Node(e.to_string()))?;
+		match self.client.api.rpc().storage(&key, None).await? {
+			Some(data) =>
+				Ok(Some(T::decode(&mut &data.0[..]).map_err(|e| Error::Node(e.to_string()))?)),
+			None => Ok(None),
+		}
+	}
+
+	async fn header(&self, hash: Option<H256>) -> Result<Header, Error> {
+		self.client.api.rpc().header(hash).await?.ok_or(Error::Stale)
+	}
+
+	async fn block_hash(&self, number: u32) -> Result<H256, Error> {
+		self.client
+			.api
+			.rpc()
+			.block_hash(Some(number.into()))
+			.await?
+			.ok_or(Error::InvalidSolution)
+	}
+
+	/// Returns the commitment of the row of `position` in the block of `header`, extending the
+	/// commitments of the block for the rows of the extended matrix, as the runtime does.
+	fn commitment(&self, header: &Header, position: &Position) -> Result<KZGCommitment, Error> {
+		let commitments = header.commitments().unwrap_or_default();
+		let y = position.y as usize;
+		if y < commitments.len() {
+			return Ok(commitments[y].clone())
+		}
+		extend_fs_g1(self.kzg.get_fs(), &commitments)
+			.ok()
+			.and_then(|extended| extended.get(y).cloned())
+			.ok_or(Error::InvalidSolution)
+	}
+
+	/// Verifies the partial solution of `farmer` found with the challenge of `block_hash`,
+	/// credits a point to `farmer` and claims the solution if it wins.
+	pub async fn submit_partial(
+		&self,
+		farmer: AccountId32,
+		block_hash: H256,
+		encoded: &[u8],
+	) -> Result<PartialResult, Error> {
+		let compact = CompactSolution::<u32>::decode(&mut &encoded[..])
+			.map_err(|_| Error::InvalidEncoding)?;
+
+		let pool_id: Option<u32> = self.storage("FarmingPools", "Memberships", &farmer).await?;
+		if pool_id != Some(self.config.pool_id) {
+			return Err(Error::NotMember)
+		}
+		let farmer_id: FarmerId = self
+			.storage("FarmersFortune", "FarmerBindings", &farmer)
+			.await?
+			.ok_or(Error::NotRegistered)?;
+
+		let best = self.header(None).await?;
+		let header = self.header(Some(block_hash)).await?;
+		if best.number.saturating_sub(header.number) > self.config.window {
+			return Err(Error::Stale)
+		}
+
+		let challenge = self
+			.client
+			.challenge(header.number, block_hash)
+			.await
+			.map_err(|e| Error::Node(e.to_string()))?
+			.ok_or(Error::Stale)?;
+		let hashing = self
+			.client
+			.plot_hashing(Some(block_hash))
+			.await
+			.map_err(|e| Error::Node(e.to_string()))?;
+
+		let id = BlakeTwo256::hash_of(&(&farmer, block_hash, &compact));
+		if self.shares().seen.get(&header.number).map_or(false, |seen| seen.contains(&id)) {
+			return Err(Error::Duplicate)
+		}
+
+		let (pre_cell, win_cell_left, win_cell_right) = compact.clone().into_cells();
+		let left_hash = self.block_hash(win_cell_left.metadata.block_number()).await?;
+		let right_hash = self.block_hash(win_cell_right.metadata.block_number()).await?;
+		let left_commit =
+			self.commitment(&self.header(Some(left_hash)).await?, &win_cell_left.seg.position)?;
+		let right_commit =
+			self.commitment(&self.header(Some(right_hash)).await?, &win_cell_right.seg.position)?;
+		let pre_commit = self.commitment(&header, &pre_cell.seg.position)?;
+
+		let solution = Solution::<H256, u32>::new(
+			&challenge,
+			&farmer_id,
+			&pre_cell,
+			&win_cell_left,
+			&win_cell_right,
+		)
+		.with_hashing(hashing);
+		let z = ZValueManager::<u32>::get_challenge(challenge.as_ref());
+		let verified = Solution::<H256, u32>::check_pre_cell(
+			&pre_cell.seg,
+			&farmer_id,
+			self.config.partial_leading_zeros,
+		) && Solution::<H256, u32>::is_index_valid(
+			&farmer_id,
+			&challenge,
+			pre_cell.piece_index() as usize,
+			32,
+			1,
+		) && pre_cell.verify_kzg_proof(&self.kzg, &pre_commit) &&
+			solution.validate_win_cell_with_kzg(
+				&self.kzg,
+				&left_commit,
+				&right_commit,
+				&left_hash,
+				&right_hash,
+				1,
+				z,
+			);
+		if !verified {
+			return Err(Error::InvalidSolution)
+		}
+
+		let points = self.record(&farmer, header.number, id)?;
+
+		// The runtime takes the challenge of the parent of the block the claim is included in.
+		let claimed = header.number == best.number &&
+			Solution::<H256, u32>::check_pre_cell(
+				&pre_cell.seg,
+				&farmer_id,
+				PRE_CELL_LEADING_ZEROS,
+			) && self.claim(&farmer, &compact.encode()).await;
+
+		Ok(PartialResult { points, claimed })
+	}
+
+	/// Credits a point to `farmer` for the solution `id` of the block `number`, returning the
+	/// points of `farmer`.
+	fn record(&self, farmer: &AccountId32, number: u32, id: H256) -> Result<u64, Error> {
+		let mut shares = self.shares();
+		if !shares.seen.entry(number).or_default().insert(id) {
+			return Err(Error::Duplicate)
+		}
+		// The solutions of blocks out of the window are rejected as stale anyway.
+		let oldest = number.saturating_sub(self.config.window);
+		shares.seen = shares.seen.split_off(&oldest);
+
+		let points = shares.points.entry(farmer.clone()).or_default();
+		*points += 1;
+		let points = *points;
+
+		let saved =
+			serde_json::to_vec(&shares.points).map_err(|e| e.to_string()).and_then(|bytes| {
+				std::fs::write(&self.config.points_path, bytes).map_err(|e| e.to_string())
+			});
+		if let Err(e) = saved {
+			error!("❌ Failed to save points: {}", e);
+		}
+		Ok(points)
+	}
+
+	/// Submits the claim of `solution` for `farmer`, returning whether it was accepted by the
+	/// transaction pool of the node.
+	async fn claim(&self, farmer: &AccountId32, solution: &[u8]) -> bool {
+		let payload = ClaimUnsigned { farmer, solution };
+		let submitted = match self.client.api.tx().create_unsigned(&payload) {
+			Ok(tx) => tx.submit().await,
+			Err(e) => Err(e),
+		};
+		match submitted {
+			Ok(hash) => {
+				info!("🏆 Claimed solution of {} in {:?}", farmer, hash);
+				true
+			},
+			Err(e) => {
+				error!("❌ Failed to claim solution of {}: {:?}", farmer, e);
+				false
+			},
+		}
+	}
+
+	/// Returns the points of `farmer`.
+	pub fn points(&self, farmer: &AccountId32) -> u64 {
+		self.shares().points.get(farmer).copied().unwrap_or_default()
+	}
+
+	/// Returns the parameters of the pool.
+	pub fn info(&self) -> PoolServerInfo {
+		PoolServerInfo {
+			pool_id: self.config.pool_id,
+			partial_leading_zeros: self.config.partial_leading_zeros,
+			window: self.config.window,
+		}
+	}
+}
+
+#[async_trait]
+impl PoolApiServer for Pool {
+	async fn info(&self) -> RpcResult<PoolServerInfo> {
+		Ok(Pool::info(self))
+	}
+
+	async fn submit_partial(
+		&self,
+		farmer: AccountId32,
+		block_hash: H256,
+		solution: Bytes,
+	) -> RpcResult<PartialResult> {
+		Ok(Pool::submit_partial(self, farmer, block_hash, &solution.0).await?)
+	}
+
+	async fn points(&self, farmer: AccountId32) -> RpcResult<u64> {
+		Ok(Pool::points(self, &farmer))
+	}
+}
@@ -39,16 +39,17 @@ use config::{consensus, currency::*, gov, system, time::DAYS};
 
 pub mod voter_bags;
 
-use codec::{Decode, Encode};
+use codec::{Decode, Encode, MaxEncodedLen};
 use melo_auto_config::auto_config;
 pub use node_primitives::{
 	AccountId, AccountIndex, Balance, BlockNumber, Hash, Index, Moment, Signature,
 };
-use sp_core::{crypto::KeyTypeId, OpaqueMetadata};
+use sp_core::{crypto::KeyTypeId, OpaqueMetadata, H256};
 
 use sp_runtime::{
 	generic, impl_opaque_keys,
 	traits::{BlakeTwo256, Block as BlockT, One, OpaqueKeys},
+	RuntimeDebug,
 };
 use sp_std::prelude::*;
 #[cfg(feature = "std")]
@@ -72,10 +73,10 @@ pub use frame_support::{
 	pallet_prelude::Get,
 	parameter_types,
 	traits::{
-		AsEnsureOriginWithArg, ConstU128, ConstU32, ConstU64, ConstU8, Currency, EitherOfDiverse,
-		EqualPrivilegeOnly, Everything, Imbalance, InstanceFilter, KeyOwnerProofSystem,
-		LockIdentifier, OnUnbalanced, Randomness, SortedMembers, StorageInfo, U128CurrencyToVote,
-		WithdrawReasons,
+		AsEnsureOriginWithArg, ConstBool, ConstU128, ConstU32, ConstU64, ConstU8, Currency,
+		EitherOfDiverse, EqualPrivilegeOnly, Everything, Imbalance, InstanceFilter,
+		KeyOwnerProofSystem, LockIdentifier, OnUnbalanced, Randomness, SortedMembers, StorageInfo,
+		U128CurrencyToVote, WithdrawReasons,
 	},
 	weights::{
 		constants::{
@@ -97,7 +98,10 @@ use pallet_transaction_payment::{ConstFeeMultiplier, CurrencyAdapter, Multiplier
 pub use sp_runtime::BuildStorage;
 pub use sp_runtime::{FixedU128, Perbill, Permill};
 
-use melo_core_primitives::{Header as ExtendedHeader, SidecarMetadata};
+use melo_core_primitives::{
+	inclusion::BlockCommitments, BlobLimits, BlobPlacement, DasParams, Header as ExtendedHeader,
+	SidecarMetadata,
+};
 
 pub use consensus::GENESIS_EPOCH_CONFIG;
 use static_assertions::const_assert;
@@ -109,7 +113,7 @@ use sp_runtime::{
 	create_runtime_str,
 	generic::Era,
 	traits::{self, NumberFor},
-	transaction_validity::{TransactionSource, TransactionValidity},
+	transaction_validity::{TransactionPriority, TransactionSource, TransactionValidity},
 	ApplyExtrinsicResult,
 };
 use sp_std::vec;
@@ -201,7 +205,7 @@ impl pallet_timestamp::Config for Runtime {
 
 impl pallet_authorship::Config for Runtime {
 	type FindAuthor = pallet_session::FindAccountFromAuthorIndex<Self, Babe>;
-	type EventHandler = (Staking, ImOnline);
+	type EventHandler = (Staking, ImOnline, Withholding);
 }
 
 #[auto_config]
@@ -210,6 +214,101 @@ impl pallet_utility::Config for Runtime {
 	type PalletsOrigin = OriginCaller;
 }
 
+parameter_types! {
+	// One storage item; key size is 32; value is size 4+4+16+32 bytes = 56 bytes.
+	pub const MultisigDepositBase: Balance = deposit(1, 88);
+	// Additional storage item size of 32 bytes.
+	pub const MultisigDepositFactor: Balance = deposit(0, 32);
+	// One storage item; key size 32, value size 8.
+	pub const ProxyDepositBase: Balance = deposit(1, 8);
+	// Additional storage item size of 33 bytes.
+	pub const ProxyDepositFactor: Balance = deposit(0, 33);
+	pub const AnnouncementDepositBase: Balance = deposit(1, 8);
+	pub const AnnouncementDepositFactor: Balance = deposit(0, 66);
+}
+
+#[auto_config(include_currency)]
+impl pallet_multisig::Config for Runtime {
+	type RuntimeCall = RuntimeCall;
+	type DepositBase = MultisigDepositBase;
+	type DepositFactor = MultisigDepositFactor;
+	type MaxSignatories = ConstU32<100>;
+}
+
+/// The type used to represent the kinds of proxying allowed.
+#[derive(
+	Copy,
+	Clone,
+	Eq,
+	PartialEq,
+	Ord,
+	PartialOrd,
+	Encode,
+	Decode,
+	RuntimeDebug,
+	MaxEncodedLen,
+	scale_info::TypeInfo,
+)]
+pub enum ProxyType {
+	Any,
+	NonTransfer,
+	Governance,
+	Staking,
+}
+
+impl Default for ProxyType {
+	fn default() -> Self {
+		Self::Any
+	}
+}
+
+impl InstanceFilter<RuntimeCall> for ProxyType {
+	fn filter(&self, c: &RuntimeCall) -> bool {
+		match self {
+			ProxyType::Any => true,
+			ProxyType::NonTransfer => !matches!(
+				c,
+				RuntimeCall::Balances(..) |
+					RuntimeCall::Assets(..) |
+					RuntimeCall::Indices(pallet_indices::Call::transfer { .. })
+			),
+			ProxyType::Governance => matches!(
+				c,
+				RuntimeCall::Democracy(..) |
+					RuntimeCall::Council(..) |
+					RuntimeCall::TechnicalCommittee(..) |
+					RuntimeCall::Elections(..) |
+					RuntimeCall::Treasury(..) |
+					RuntimeCall::Bounties(..)
+			),
+			ProxyType::Staking => matches!(c, RuntimeCall::Staking(..)),
+		}
+	}
+
+	fn is_superset(&self, o: &Self) -> bool {
+		match (self, o) {
+			(x, y) if x == y => true,
+			(ProxyType::Any, _) => true,
+			(_, ProxyType::Any) => false,
+			(ProxyType::NonTransfer, _) => true,
+			_ => false,
+		}
+	}
+}
+
+#[auto_config(include_currency)]
+impl pallet_proxy::Config for Runtime {
+	type RuntimeCall = RuntimeCall;
+	type ProxyType = ProxyType;
+	type ProxyDepositBase = ProxyDepositBase;
+	type ProxyDepositFactor = ProxyDepositFactor;
+	type MaxProxies = ConstU32<32>;
+	type MaxPending = ConstU32<32>;
+	type CallHasher = BlakeTwo256;
+	type AnnouncementDepositBase = AnnouncementDepositBase;
+	type AnnouncementDepositFactor = AnnouncementDepositFactor;
+}
+
 use sp_runtime::traits::Convert;
 pub struct BalanceToU256;
 impl Convert<Balance, sp_core::U256> for BalanceToU256 {
@@ -829,24 +928,123 @@ impl pallet_melo_store::Config for Runtime {
 	type AuthorityId = ImOnlineId;
 	type MaxBlobNum = system::MaxBlobNumber;
 	type MaxExtedLen = system::MaxExtedLen;
+	type DasParams = DasConfig;
 	type WeightInfo = ();
 	type MeloUnsignedPriority = ();
 	type MaxKeys = consensus::MaxKeys;
+	type NamespacedRows = ConstBool<true>;
+	type OnDataAvailable = ();
+}
+
+parameter_types! {
+	pub const MinDasParamsActivationDelay: BlockNumber = 1 * time::HOURS;
+}
+
+impl pallet_das_config::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type WeightInfo = pallet_das_config::weights::SubstrateWeight<Runtime>;
+	type UpdateOrigin = EnsureRootOrHalfCouncil;
+	type MaxBlockRows = system::MaxBlobNumber;
+	type MinActivationDelay = MinDasParamsActivationDelay;
 }
 
 parameter_types! {
 	pub const RewardAmount: Balance = 100 * DOLLARS;
 	pub const MaxClaimantsPerBlock: u32 = 100;
+	pub const SolutionUnsignedPriority: TransactionPriority = TransactionPriority::max_value() / 4;
+	pub const FarmerRotationGracePeriod: BlockNumber = DAYS;
+	pub const FarmerBond: Balance = 1_000 * DOLLARS;
+}
+
+/// Derives the proof-of-space challenges from the BABE randomness of the epoch and the slot.
+pub struct BabeChallengeSource;
+
+impl melo_core_primitives::traits::ChallengeSource for BabeChallengeSource {
+	fn epoch_randomness() -> [u8; 32] {
+		Babe::randomness()
+	}
+
+	fn current_slot() -> u64 {
+		Babe::current_slot().into()
+	}
 }
 
 // #[auto_config(skip_weight, include_currency)]
 impl pallet_farmers_fortune::Config for Runtime {
 	type Currency = Balances;
 	type RuntimeEvent = RuntimeEvent;
-	type WeightInfo = ();
+	type WeightInfo = pallet_farmers_fortune::weights::SubstrateWeight<Runtime>;
 	type CommitmentFromPosition = MeloStore;
 	type RewardAmount = RewardAmount;
+	type RewardPayout = FarmingPools;
 	type MaxClaimantsPerBlock = MaxClaimantsPerBlock;
+	type SolutionUnsignedPriority = SolutionUnsignedPriority;
+	type BindingSignature = Signature;
+	type BindingSigner = <Signature as traits::Verify>::Signer;
+	type RotationGracePeriod = FarmerRotationGracePeriod;
+	type FarmerBond = FarmerBond;
+	type ChallengeSource = BabeChallengeSource;
+	type PlotHashing = pallet_das_config::ActivePlotHashingOf<Runtime>;
+}
+
+parameter_types! {
+	pub const MaxPoolMembers: u32 = 1_000;
+	pub const MaxPoolShare: Perbill = Perbill::from_percent(20);
+	pub const PoolShareNotice: BlockNumber = 7 * DAYS;
+	pub const PoolDeposit: Balance = 100 * DOLLARS;
+}
+
+impl pallet_farming_pools::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type WeightInfo = pallet_farming_pools::weights::SubstrateWeight<Runtime>;
+	type Currency = Balances;
+	type MaxPoolMembers = MaxPoolMembers;
+	type MaxPoolShare = MaxPoolShare;
+	type PoolShareNotice = PoolShareNotice;
+	type PoolDeposit = PoolDeposit;
+}
+
+parameter_types! {
+	pub const MaxAttesters: u32 = 100;
+	pub const AttestationThreshold: Permill = Permill::from_percent(66);
+	pub const WithholdingReportWindow: BlockNumber = 1 * time::HOURS;
+	pub const WithholdingSlashFraction: Perbill = Perbill::from_percent(1);
+}
+
+impl pallet_withholding::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type WeightInfo = pallet_withholding::weights::SubstrateWeight<Runtime>;
+	type ValidatorSet = Historical;
+	type ReportWithholding = Offences;
+	type UpdateOrigin = EnsureRootOrHalfCouncil;
+	type MaxAttesters = MaxAttesters;
+	type AttestationThreshold = AttestationThreshold;
+	type ReportWindow = WithholdingReportWindow;
+	type SlashFraction = WithholdingSlashFraction;
+}
+
+parameter_types! {
+	pub const RewardPerServedSegment: Balance = MILLICENTS;
+	pub const BandwidthPotId: PalletId = PalletId(*b"melo/bwi");
+	pub const MaxSegmentsPerReceipt: u32 = 4_096;
+	pub const MaxServedSegmentsPerPeriod: u64 = 65_536;
+	pub const RequesterDeposit: Balance = 10 * DOLLARS;
+	pub const MaxReceiptsPerClaim: u32 = 64;
+	pub const ServingClaimPeriods: u32 = 24;
+}
+
+impl pallet_bandwidth_incentive::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type WeightInfo = pallet_bandwidth_incentive::weights::SubstrateWeight<Runtime>;
+	type Currency = Balances;
+	type PotId = BandwidthPotId;
+	type UnixTime = Timestamp;
+	type RewardPerSegment = RewardPerServedSegment;
+	type MaxSegmentsPerReceipt = MaxSegmentsPerReceipt;
+	type MaxSegmentsPerPeriod = MaxServedSegmentsPerPeriod;
+	type RequesterDeposit = RequesterDeposit;
+	type MaxReceiptsPerClaim = MaxReceiptsPerClaim;
+	type ClaimPeriods = ServingClaimPeriods;
 }
 
 use sp_runtime::OpaqueExtrinsic;
@@ -892,10 +1090,16 @@ construct_runtime!(
 		Assets: pallet_assets = 38,
 		VoterList: pallet_bags_list::<Instance1> = 39,
 		NominationPools: pallet_nomination_pools = 40,
+		Proxy: pallet_proxy = 41,
+		Multisig: pallet_multisig = 42,
 
 		// Melodot.
 		MeloStore: pallet_melo_store = 80,
 		FarmersFortune: pallet_farmers_fortune = 81,
+		DasConfig: pallet_das_config = 82,
+		FarmingPools: pallet_farming_pools = 83,
+		Withholding: pallet_withholding = 84,
+		BandwidthIncentive: pallet_bandwidth_incentive = 85,
 	}
 );
 
@@ -918,6 +1122,13 @@ pub type UncheckedExtrinsic =
 	generic::UncheckedExtrinsic<Address, RuntimeCall, Signature, SignedExtra>;
 /// The payload being signed in transactions.
 pub type SignedPayload = generic::SignedPayload<RuntimeCall, SignedExtra>;
+/// The storage migrations run on the first block after a runtime upgrade, before the hooks of
+/// the pallets. Each one only applies to the storage version it migrates from.
+pub type Migrations = (
+	pallet_melo_store::migrations::v1::MigrateToV1<Runtime>,
+	pallet_farmers_fortune::migrations::v1::MigrateToV1<Runtime>,
+);
+
 /// Executive: handles dispatch to the various modules.
 pub type Executive = frame_executive_ext::Executive<
 	Runtime,
@@ -925,6 +1136,7 @@ pub type Executive = frame_executive_ext::Executive<
 	frame_system::ChainContext<Runtime>,
 	Runtime,
 	AllPalletsWithSystem,
+	Migrations,
 >;
 
 #[cfg(feature = "runtime-benchmarks")]
@@ -951,21 +1163,83 @@ mod benches {
 		[pallet_im_online, ImOnline]
 		[pallet_scheduler, Scheduler]
 		[pallet_bounties, Bounties]
+		[pallet_proxy, Proxy]
+		[pallet_multisig, Multisig]
 		[pallet_melo_store, MeloStore]
 		[pallet_farmers_fortune, FarmersFortune]
+		[pallet_das_config, DasConfig]
+		[pallet_farming_pools, FarmingPools]
+		[pallet_withholding, Withholding]
+		[pallet_bandwidth_incentive, BandwidthIncentive]
 		[pallet_elections_phragmen, Elections]
 	);
 }
 
+/// Returns the blob parameters submitted by `call`, looking into batches and the calls
+/// dispatched on behalf of other origins, such as proxies and multisigs.
+///
+/// Only the wrappers dispatching their call when they are included are looked into:
+/// `Multisig::as_multi` dispatches it on the final approval only, and `Proxy::proxy_announced`
+/// after the announcement delay, so the blobs of their calls are not extracted.
+fn blob_params(call: RuntimeCall) -> Vec<SidecarMetadata> {
+	match call {
+		RuntimeCall::MeloStore(pallet_melo_store::Call::submit_data { params }) => vec![params],
+		RuntimeCall::Utility(pallet_utility::Call::batch { calls }) |
+		RuntimeCall::Utility(pallet_utility::Call::batch_all { calls }) |
+		RuntimeCall::Utility(pallet_utility::Call::force_batch { calls }) =>
+			calls.into_iter().flat_map(blob_params).collect(),
+		RuntimeCall::Utility(pallet_utility::Call::as_derivative { call, .. }) |
+		RuntimeCall::Utility(pallet_utility::Call::dispatch_as { call, .. }) |
+		RuntimeCall::Utility(pallet_utility::Call::with_weight { call, .. }) |
+		RuntimeCall::Proxy(pallet_proxy::Call::proxy { call, .. }) |
+		RuntimeCall::Multisig(pallet_multisig::Call::as_multi_threshold_1 { call, .. }) =>
+			blob_params(*call),
+		_ => vec![],
+	}
+}
+
+/// Returns the blob submitted by `call` if it is a `submit_data` call.
+fn blob_tx_param(call: &RuntimeCall) -> Option<SidecarMetadata> {
+	match call {
+		RuntimeCall::MeloStore(pallet_melo_store::Call::submit_data { params }) =>
+			Some(params.clone()),
+		_ => None,
+	}
+}
+
+/// Places the blobs of `calls` as if the calls were included in the next block in that order,
+/// the blobs of each call after those of the calls before it. Calls that can not be decoded are
+/// `None` and take no rows.
+///
+/// Runtime APIs run on the state of an imported block, where nothing is submitted for the next
+/// block yet, so the placements are a best-effort estimate: they are only exact if `calls` are
+/// all the blob submissions of the block, in order, and all their blobs become available.
+fn place_blobs(calls: Vec<Option<RuntimeCall>>) -> Vec<Option<Vec<BlobPlacement>>> {
+	let mut next_row = 0u32;
+	calls
+		.into_iter()
+		.map(|call| {
+			let placements = BlobPlacement::place(blob_params(call?), next_row);
+			if let Some(last) = placements.last() {
+				next_row = last.rows().end;
+			}
+			Some(placements)
+		})
+		.collect()
+}
+
 #[sp_version::runtime_version]
 pub const VERSION: RuntimeVersion = RuntimeVersion {
 	spec_name: create_runtime_str!("melodot"),
 	impl_name: create_runtime_str!("melodot"),
 	authoring_version: 1,
-	spec_version: 1,
+	// 3: migrates the storage of `MeloStore` and `FarmersFortune` to version 1.
+	spec_version: 3,
 	impl_version: 1,
 	apis: RUNTIME_API_VERSIONS,
-	transaction_version: 1,
+	// 2: `SidecarMetadata` of `MeloStore::submit_data` encodes its `compression`.
+	// 3: `FarmersFortune::claim` only pays the solutions of the farmer ID bound to the caller.
+	transaction_version: 3,
 	state_version: 1,
 };
 
@@ -977,37 +1251,77 @@ impl_runtime_apis! {
 			// Decode the unchecked extrinsic
 			let extrinsic = UncheckedExtrinsic::decode(&mut &extrinsic[..]).ok()?;
 
-			fn filter(call: RuntimeCall) -> Vec<SidecarMetadata> {
-				match call {
-					RuntimeCall::MeloStore(pallet_melo_store::Call::submit_data {
-						params
-					}) => vec![params],
-					RuntimeCall::Utility(pallet_utility::Call::batch { calls })
-					| RuntimeCall::Utility(pallet_utility::Call::batch_all { calls })
-					| RuntimeCall::Utility(pallet_utility::Call::force_batch { calls }) => process_calls(calls),
-					_ => vec![],
-				}
-			}
+			Some(blob_params(extrinsic.function))
+		}
 
-			fn process_calls(
-				calls: Vec<RuntimeCall>,
-			) -> Vec<SidecarMetadata> {
-				calls.into_iter().flat_map(filter).collect()
-			}
+		fn extract_placements(
+			extrinsic: &Vec<u8>,
+		) -> Option<Vec<BlobPlacement>> {
+			let extrinsic = UncheckedExtrinsic::decode(&mut &extrinsic[..]).ok()?;
 
-			Some(process_calls(vec![extrinsic.function]))
+			place_blobs(vec![Some(extrinsic.function)]).pop().flatten()
+		}
+
+		fn extract_placements_batch(
+			extrinsics: Vec<Vec<u8>>,
+		) -> Vec<Option<Vec<BlobPlacement>>> {
+			place_blobs(
+				extrinsics
+					.iter()
+					.map(|extrinsic| {
+						UncheckedExtrinsic::decode(&mut &extrinsic[..])
+							.ok()
+							.map(|extrinsic| extrinsic.function)
+					})
+					.collect(),
+			)
 		}
 	}
 
 	impl melo_core_primitives::traits::AppDataApi<Block, RuntimeCall> for Runtime {
 
 		fn get_blob_tx_param(function: &RuntimeCall) -> Option<SidecarMetadata> {
-			match function {
-				RuntimeCall::MeloStore(pallet_melo_store::Call::submit_data {
-					params,
-				}) => Some(params.clone()),
-				_ => None,
-			}
+			blob_tx_param(function)
+		}
+
+		fn get_blob_tx_params(calls: Vec<RuntimeCall>) -> Vec<Option<SidecarMetadata>> {
+			calls.iter().map(blob_tx_param).collect()
+		}
+
+		fn get_blob_placements(function: &RuntimeCall) -> Vec<BlobPlacement> {
+			place_blobs(vec![Some(function.clone())]).pop().flatten().unwrap_or_default()
+		}
+
+		fn blob_limits(app_id: u32) -> BlobLimits {
+			MeloStore::blob_limits(System::block_number() + 1, app_id)
+		}
+
+		fn block_commitments(at_block: u32) -> BlockCommitments {
+			MeloStore::block_commitments(at_block)
+		}
+	}
+
+	impl melo_core_primitives::traits::DasParamsApi<Block> for Runtime {
+		fn das_params() -> DasParams {
+			DasConfig::active_params()
+		}
+
+		fn pending_das_params() -> Option<(u32, DasParams)> {
+			DasConfig::pending_params()
+		}
+
+		fn trusted_setup_hash() -> Option<H256> {
+			DasConfig::trusted_setup_hash()
+		}
+	}
+
+	impl melo_core_primitives::traits::WithholdingApi<Block> for Runtime {
+		fn attesters() -> Vec<sp_core::sr25519::Public> {
+			Withholding::attesters().into_inner()
+		}
+
+		fn max_attesters() -> u32 {
+			MaxAttesters::get()
 		}
 	}
 
@@ -1292,9 +1606,95 @@ impl_runtime_apis! {
 mod tests {
 	use super::*;
 	use frame_support::traits::WhitelistedStorageKeys;
+	use melo_core_primitives::config::FIELD_ELEMENTS_PER_SEGMENT;
+	use melo_das_primitives::{KZGCommitment, KZGProof};
 	use sp_core::hexdisplay::HexDisplay;
 	use std::collections::HashSet;
 
+	fn submit_data(app_id: u32, rows: usize) -> RuntimeCall {
+		RuntimeCall::MeloStore(pallet_melo_store::Call::submit_data {
+			params: SidecarMetadata::new(
+				app_id,
+				1,
+				1,
+				vec![KZGCommitment::default(); rows],
+				vec![KZGProof::default(); rows],
+			),
+		})
+	}
+
+	#[test]
+	fn claims_fit_in_a_block() {
+		let claim = pallet_farmers_fortune::Pallet::<Runtime>::claim_weight(
+			&[FIELD_ELEMENTS_PER_SEGMENT; 3],
+		);
+		let max_claims = claim.saturating_mul(MaxClaimantsPerBlock::get() as u64);
+		let max_normal = system::RuntimeBlockWeights::get()
+			.get(DispatchClass::Normal)
+			.max_total
+			.expect("Normal extrinsics are limited");
+
+		assert!(max_claims.all_lte(max_normal));
+	}
+
+	#[test]
+	fn blobs_are_placed_after_those_of_the_calls_before() {
+		let batch = RuntimeCall::Utility(pallet_utility::Call::batch {
+			calls: vec![submit_data(2, 1), submit_data(3, 3)],
+		});
+		let placements = place_blobs(vec![
+			Some(submit_data(1, 2)),
+			None,
+			Some(RuntimeCall::System(frame_system::Call::remark { remark: vec![] })),
+			Some(batch),
+		]);
+
+		let rows = placements
+			.iter()
+			.map(|placements| {
+				placements.as_ref().map(|placements| {
+					placements.iter().map(|p| (p.metadata.app_id, p.rows())).collect::<Vec<_>>()
+				})
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(
+			rows,
+			vec![Some(vec![(1, 0..2)]), None, Some(vec![]), Some(vec![(2, 2..3), (3, 3..6)])]
+		);
+	}
+
+	#[test]
+	fn blobs_of_calls_dispatched_later_are_not_extracted() {
+		let real = sp_runtime::MultiAddress::Id(AccountId::new([1; 32]));
+		let proxy = RuntimeCall::Proxy(pallet_proxy::Call::proxy {
+			real: real.clone(),
+			force_proxy_type: None,
+			call: Box::new(submit_data(1, 1)),
+		});
+		let multisig_1 = RuntimeCall::Multisig(pallet_multisig::Call::as_multi_threshold_1 {
+			other_signatories: vec![],
+			call: Box::new(submit_data(2, 1)),
+		});
+		assert_eq!(blob_params(proxy).len(), 1);
+		assert_eq!(blob_params(multisig_1).len(), 1);
+
+		let announced = RuntimeCall::Proxy(pallet_proxy::Call::proxy_announced {
+			delegate: real.clone(),
+			real,
+			force_proxy_type: None,
+			call: Box::new(submit_data(3, 1)),
+		});
+		let multisig = RuntimeCall::Multisig(pallet_multisig::Call::as_multi {
+			threshold: 2,
+			other_signatories: vec![AccountId::new([2; 32])],
+			maybe_timepoint: None,
+			call: Box::new(submit_data(4, 1)),
+			max_weight: Weight::zero(),
+		});
+		assert!(blob_params(announced).is_empty());
+		assert!(blob_params(multisig).is_empty());
+	}
+
 	#[test]
 	fn check_whitelist() {
 		let whitelist: HashSet<String> = AllPalletsWithSystem::whitelisted_storage_keys()